@@ -0,0 +1,22 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Example reading FIRRTL code from stdin and printing it formatted
+//!
+//! The maximum line width may be given as the first argument.
+
+fn main() {
+    use std::io::Read;
+
+    use firrtl_ast::format::Style;
+
+    let mut style = Style::default().with_aligned_connections(true);
+    if let Some(width) = std::env::args().nth(1) {
+        style = style.with_max_width(width.parse().expect("Invalid line width"))
+    }
+
+    let mut buf = Default::default();
+    std::io::stdin().read_to_string(&mut buf).expect("Failed to read from stdin");
+
+    let circuit = firrtl_ast::circuit::parse(buf.as_ref()).expect("Failed to parse circuit");
+    print!("{}", style.circuit(&circuit));
+}
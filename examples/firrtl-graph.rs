@@ -0,0 +1,18 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Example reading FIRRTL code from stdin and printing its module graph
+//!
+//! The graph is emitted in the Graphviz DOT format, e.g. for rendering via
+//! `dot -Tsvg`.
+
+fn main() {
+    use std::io::Read;
+
+    use firrtl_ast::graph::ModuleGraph;
+
+    let mut buf = Default::default();
+    std::io::stdin().read_to_string(&mut buf).expect("Failed to read from stdin");
+
+    let circuit = firrtl_ast::circuit::parse(buf.as_ref()).expect("Failed to parse circuit");
+    print!("{}", ModuleGraph::of(&circuit).dot());
+}
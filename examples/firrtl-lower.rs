@@ -0,0 +1,35 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Example reading FIRRTL code from stdin, validating, lowering and emitting it
+//!
+//! The circuit is checked using the built-in lint rules. Findings are printed
+//! to stderr. If any of them is an error, the example exits with a non-zero
+//! status. Otherwise, partial connects are expanded and conditional
+//! statements with constant conditions are pruned before the circuit is
+//! printed to stdout.
+
+fn main() {
+    use std::io::Read;
+
+    use firrtl_ast::{format::Style, lint::Registry, passes, report::Report};
+
+    let mut buf = Default::default();
+    std::io::stdin().read_to_string(&mut buf).expect("Failed to read from stdin");
+
+    let circuit = firrtl_ast::circuit::parse(buf.as_ref()).expect("Failed to parse circuit");
+
+    let mut report: Report = Registry::builtin().run(&circuit).into();
+    if report.has_errors() {
+        eprint!("{}", report);
+        std::process::exit(1)
+    }
+
+    let (circuit, expanded) = passes::expand_partial_connects(&circuit);
+    let circuit = passes::prune_constant_whens(&circuit);
+    report.extend(expanded.findings().iter().cloned());
+    if !report.is_empty() {
+        eprint!("{}", report);
+    }
+
+    print!("{}", Style::default().circuit(&circuit));
+}
@@ -0,0 +1,16 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Example reading FIRRTL code from files and printing statistics for each
+
+fn main() {
+    use firrtl_ast::stats::CircuitStats;
+
+    for path in std::env::args_os().skip(1) {
+        let file = std::fs::File::open(&path).expect("Could not open file");
+        let circuit = firrtl_ast::Circuit::from_buf_read(std::io::BufReader::new(file))
+            .expect("Failed to parse circuit");
+
+        println!("{}:", path.to_string_lossy());
+        print!("{}", CircuitStats::of(&circuit));
+    }
+}
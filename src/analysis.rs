@@ -0,0 +1,765 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Structural analyses
+//!
+//! This module provides cheap analyses operating on the AST of a single
+//! module, such as an estimate of the [logic_depth] useful as a timing proxy
+//! or the statements which may drive a given signal via [who_drives]. Signals
+//! which may carry undefined values can be found via [undefined_values].
+//!
+//! Some analyses may look past the boundaries formed by instances, as
+//! selected via a [HierarchyMode].
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::expr;
+use crate::module::{Instance, Module};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+use crate::target::{Subscript, Target};
+
+
+/// Expression type used in statements
+type Expression = expr::Expression<Arc<Entity>>;
+
+
+/// A path through combinational logic
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogicPath {
+    depth: usize,
+    chain: Vec<Expression>,
+}
+
+impl LogicPath {
+    /// Retrieve the depth of the path
+    ///
+    /// The depth is the number of primitive operations along the path.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Retrieve the primitive operations along the path
+    ///
+    /// The operations are ordered from the start of the path, i.e. the
+    /// operation closest to a register or port, to its end.
+    pub fn chain(&self) -> &[Expression] {
+        self.chain.as_ref()
+    }
+
+    /// Extend the path by the given operation
+    fn extended(mut self, op: &Expression) -> Self {
+        self.depth += 1;
+        self.chain.push(op.clone());
+        self
+    }
+
+    /// Extend the path by another path starting where this one ends
+    fn joined(mut self, other: &Self) -> Self {
+        self.depth += other.depth;
+        self.chain.extend(other.chain.iter().cloned());
+        self
+    }
+}
+
+
+/// Mode for handling the instance hierarchy in analyses
+///
+/// Analyses operate on a single module. The modules instantiated within it
+/// are usually known, which allows analyses to look past instances rather
+/// than treating them as opaque boundaries.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HierarchyMode {
+    /// Analyse the module as if its hierarchy was flattened
+    ///
+    /// In addition to following signals through instances, results also
+    /// cover the internals of instantiated modules.
+    Flat,
+    /// Analyse the module in isolation, treating instances as boundaries
+    #[default]
+    PerModule,
+    /// Follow signals through instances
+    ///
+    /// Results only cover the module itself, but signals passing through an
+    /// instance are traced through the instantiated module.
+    CrossInstance,
+}
+
+
+/// Compute the logic depth of a module
+///
+/// This function determines the longest path through combinational logic
+/// between any pair of boundaries, i.e. registers, ports, memories and
+/// instances, using a unit-delay model: every primitive operation contributes
+/// a delay of one while references, sub-fields and the like don't contribute
+/// any delay. Nodes and wires are traced through to their drivers. For
+/// wires, every connection to the wire or any of its fields is considered a
+/// driver. Conditions of conditional statements are not considered.
+///
+/// With [HierarchyMode::CrossInstance], paths through instances are traced
+/// through the instantiated modules, from the instance's outputs back to its
+/// inputs. With [HierarchyMode::Flat], paths ending at an instance's input
+/// are additionally extended into the instantiated module and paths entirely
+/// within instantiated modules are considered. External modules are always
+/// treated as boundaries. Ports are traced as a whole, i.e. a path is assumed
+/// to exist from every field of an input port to every field of an output
+/// port if it exists for any pair of fields.
+///
+/// The critical path is returned as a [LogicPath]. Combinational loops are
+/// broken arbitrarily.
+pub fn logic_depth(module: &Module, mode: HierarchyMode) -> LogicPath {
+    let mut summaries = Summaries::default();
+    let summaries = if mode == HierarchyMode::PerModule {
+        None
+    } else {
+        Some(&mut summaries)
+    };
+    Tracer::new(module, Start::Any, summaries).longest(mode == HierarchyMode::Flat).unwrap_or_default()
+}
+
+
+/// Collect all drivers of wires, ports and instances as well as all expressions ending a path
+///
+/// Drivers are recorded per root entity alongside the expression they are
+/// connected to. Connections to entities other than wires also end a path.
+fn collect_drivers<'a>(
+    stmts: &'a [Statement],
+    drivers: &mut HashMap<Arc<str>, Vec<(&'a Expression, &'a Expression)>>,
+    sinks: &mut Vec<&'a Expression>,
+) {
+    stmts.iter().for_each(|s| match s.kind() {
+        Kind::Connection{from, to} | Kind::PartialConnection{from, to} => match root(to) {
+            Some(Entity::Wire{name, ..}) => drivers.entry(name.clone()).or_default().push((to, from)),
+            Some(e @ Entity::Port(..)) | Some(e @ Entity::Instance(..)) | Some(e @ Entity::InstanceChoice(..)) => {
+                drivers.entry(e.name().clone()).or_default().push((to, from));
+                sinks.push(from)
+            },
+            _ => sinks.push(from),
+        },
+        Kind::Conditional{when, r#else, ..} => {
+            collect_drivers(when, drivers, sinks);
+            collect_drivers(r#else, drivers, sinks);
+        },
+        Kind::LayerBlock{stmts, ..} => collect_drivers(stmts, drivers, sinks),
+        Kind::Declaration(e) if matches!(e.as_ref(), Entity::Node{..}) => (),
+        _ => sinks.extend(s.expressions()),
+    })
+}
+
+
+/// A statement possibly driving a signal
+#[derive(Clone, Debug, PartialEq)]
+pub struct Driver<'a> {
+    stmt: &'a Statement,
+    conditions: Vec<(&'a Expression, bool)>,
+}
+
+impl<'a> Driver<'a> {
+    /// Retrieve the driving statement
+    ///
+    /// The statement is either a connection, a partial connection, an
+    /// invalidation or the declaration of a node.
+    pub fn statement(&self) -> &'a Statement {
+        self.stmt
+    }
+
+    /// Retrieve the conditions under which the statement is in effect
+    ///
+    /// Each item consists of the condition of an enclosing conditional
+    /// statement and the value the condition needs to have for the statement
+    /// to be in effect, i.e. `false` if the statement is part of the `else`
+    /// branch. Conditions are ordered from the outermost to the innermost.
+    pub fn conditions(&self) -> &[(&'a Expression, bool)] {
+        self.conditions.as_ref()
+    }
+
+    /// Check whether the statement is in effect unconditionally
+    pub fn is_unconditional(&self) -> bool {
+        self.conditions.is_empty()
+    }
+}
+
+
+/// Determine the statements which may drive a signal
+///
+/// This function retrieves all statements in the given module which may
+/// drive the signal with the given name or the part of it selected by the
+/// given subscripts. A statement is considered a driver if it connects to or
+/// invalidates the signal, a part of it or an aggregate containing it. Dynamic
+/// indices, i.e. sub-accesses, are assumed to select any element. For nodes,
+/// the declaration is the only driver.
+///
+/// Drivers are returned in the order of the statements. As per FIRRTL's last
+/// connect semantics, a later driver overrides earlier ones if its conditions
+/// are met. Note that orientations are not considered, i.e. a connection to a
+/// bundle is reported as a driver of all of its fields, including flipped
+/// ones.
+pub fn drivers_in<'a>(module: &'a Module, name: &str, subscripts: &[Subscript]) -> Vec<Driver<'a>> {
+    fn collect<'a>(
+        stmts: &'a [Statement],
+        name: &str,
+        subscripts: &[Subscript],
+        conditions: &mut Vec<(&'a Expression, bool)>,
+        res: &mut Vec<Driver<'a>>,
+    ) {
+        stmts.iter().for_each(|s| match s.kind() {
+            Kind::Connection{to, ..} | Kind::PartialConnection{to, ..} | Kind::Invalidate(to) | Kind::Define{to, ..}
+                if overlaps(to, name, subscripts) => res.push(Driver {stmt: s, conditions: conditions.clone()}),
+            Kind::Declaration(e) if e.name_ref() == name && matches!(e.as_ref(), Entity::Node{..}) =>
+                res.push(Driver {stmt: s, conditions: conditions.clone()}),
+            Kind::Conditional{cond, when, r#else} => {
+                conditions.push((cond, true));
+                collect(when, name, subscripts, conditions, res);
+                conditions.pop();
+                conditions.push((cond, false));
+                collect(r#else, name, subscripts, conditions, res);
+                conditions.pop();
+            },
+            Kind::LayerBlock{stmts, ..} => collect(stmts, name, subscripts, conditions, res),
+            _ => (),
+        })
+    }
+
+    let mut res = Default::default();
+    collect(module.statements(), name, subscripts, &mut Default::default(), &mut res);
+    res
+}
+
+
+/// Determine the statements which may drive the signal a target refers to
+///
+/// This function resolves the given target within the circuit and retrieves
+/// the [Driver]s of the signal or the part of it it refers to, as defined for
+/// [drivers_in]. If the target cannot be resolved or does not refer to a
+/// signal, this function returns `None`.
+pub fn who_drives<'a>(circuit: &'a Circuit, target: &Target) -> Option<Vec<Driver<'a>>> {
+    let name = target.reference_name()?;
+    circuit.resolve_target(target)?;
+    let module = circuit.module_by_name(target.path().root()).and_then(|m| target.path().resolve_from(m))?;
+    Some(drivers_in(module, name, target.subscripts()))
+}
+
+
+/// Origin of a possibly undefined value
+#[derive(Clone, Debug, PartialEq)]
+pub enum UndefinedOrigin<'a> {
+    /// The signal is invalidated by the given statement
+    Invalidated(&'a Statement),
+    /// The signal is driven by the given `validif` expression
+    ValidIf(&'a Expression),
+    /// The signal is a register without reset, undefined until written
+    UninitializedRegister,
+    /// The signal is the given instance of a module which may drive an
+    /// undefined value to one of its ports
+    Instance(&'a Instance),
+}
+
+
+/// A signal which may carry an undefined value
+#[derive(Clone, Debug, PartialEq)]
+pub struct UndefinedValue<'a> {
+    path: Vec<Arc<str>>,
+    origin: UndefinedOrigin<'a>,
+}
+
+impl<'a> UndefinedValue<'a> {
+    /// Retrieve the name of the signal
+    pub fn signal(&self) -> &Arc<str> {
+        self.path.last().expect("Path of undefined value is empty")
+    }
+
+    /// Retrieve the origin of the undefined value
+    pub fn origin(&self) -> &UndefinedOrigin<'a> {
+        &self.origin
+    }
+
+    /// Retrieve the path along which the undefined value reaches the signal
+    ///
+    /// The path consists of the names of the signals the value passes,
+    /// starting at the signal it originates from and ending at the signal
+    /// itself.
+    pub fn path(&self) -> &[Arc<str>] {
+        self.path.as_ref()
+    }
+}
+
+
+/// Determine the signals in a module which may carry undefined values
+///
+/// Undefined values originate from signals which are invalidated, driven by
+/// `validif` expressions with non-constant conditions and registers without
+/// reset, whose value is undefined until they are written to. An
+/// invalidation is only considered overridden by a subsequent unconditional
+/// connection to the invalidated expression or an aggregate containing it.
+///
+/// From these origins, undefined values propagate to every signal whose
+/// drivers refer to an affected signal. The analysis is performed per
+/// entity, i.e. a signal is affected if any part of it is. Conditions of
+/// conditional statements are not considered. Values read from instances and
+/// memories are considered defined, but instances and memories are reported
+/// if any of their inputs may receive an undefined value.
+///
+/// With [HierarchyMode::CrossInstance], instances are treated like other
+/// signals: an instance is affected if any of its inputs may receive an
+/// undefined value or if the instantiated module may drive an undefined value
+/// to any of its ports, as determined recursively. Values read from such an
+/// instance are considered undefined. With [HierarchyMode::Flat], the
+/// affected signals within instantiated modules are reported in addition.
+/// Their names, including those in their paths, are qualified by the name of
+/// the instance, e.g. `inst.signal`.
+///
+/// Every affected signal is reported once, alongside one of the shortest
+/// paths from an origin. Signals are ordered by the length of that path.
+pub fn undefined_values(module: &Module, mode: HierarchyMode) -> Vec<UndefinedValue<'_>> {
+    let cross = mode != HierarchyMode::PerModule;
+    let mut flow = ValueFlow {cross, ..Default::default()};
+    flow.stmts(module.statements(), true);
+    let ValueFlow {mut origins, invalidations, users, ..} = flow;
+    origins.extend(invalidations.into_iter().filter_map(|(e, s)| Some((root(e)?.name().clone(), s))));
+
+    let mut nested = Vec::new();
+    if cross {
+        module.statements().iter().flat_map(Statement::instantiations).for_each(|inst| {
+            let values = undefined_values(inst.module(), mode);
+            if values.iter().any(|v| inst.module().port_by_name(v.signal()).is_some()) {
+                origins.push((inst.name().clone(), UndefinedOrigin::Instance(inst)))
+            }
+            if mode == HierarchyMode::Flat {
+                nested.extend(values.into_iter().map(|v| UndefinedValue {
+                    path: v.path.iter().map(|s| format!("{}.{}", inst.name(), s).into()).collect(),
+                    origin: v.origin,
+                }))
+            }
+        })
+    }
+
+    let mut res: Vec<UndefinedValue> = Default::default();
+    let mut reached: HashMap<Arc<str>, usize> = Default::default();
+    let mut queue: VecDeque<usize> = Default::default();
+    for (name, origin) in origins {
+        if !reached.contains_key(&name) {
+            reached.insert(name.clone(), res.len());
+            queue.push_back(res.len());
+            res.push(UndefinedValue {path: vec![name], origin});
+        }
+    }
+
+    while let Some(current) = queue.pop_front() {
+        let users = users.get(res[current].signal()).map(Vec::as_slice).unwrap_or_default();
+        for user in users {
+            if !reached.contains_key(user) {
+                let mut path = res[current].path.clone();
+                path.push(user.clone());
+                reached.insert(user.clone(), res.len());
+                queue.push_back(res.len());
+                res.push(UndefinedValue {path, origin: res[current].origin.clone()});
+            }
+        }
+    }
+
+    res.extend(nested);
+    res.sort_by_key(|v| v.path.len());
+    res
+}
+
+
+/// Helper for collecting the flow of values within a module
+#[derive(Default)]
+struct ValueFlow<'a> {
+    origins: Vec<(Arc<str>, UndefinedOrigin<'a>)>,
+    invalidations: Vec<(&'a Expression, UndefinedOrigin<'a>)>,
+    users: HashMap<Arc<str>, Vec<Arc<str>>>,
+    // Whether values read from instances may be undefined
+    cross: bool,
+}
+
+impl<'a> ValueFlow<'a> {
+    /// Collect the flow of values in the given statements
+    fn stmts(&mut self, stmts: &'a [Statement], unconditional: bool) {
+        stmts.iter().for_each(|s| match s.kind() {
+            Kind::Connection{from, to} | Kind::PartialConnection{from, to} => {
+                if unconditional && matches!(s.kind(), Kind::Connection{..}) {
+                    self.invalidations.retain(|(e, _)| !covers(to, e))
+                }
+                if let Some(sink) = root(to) {
+                    self.driven(sink.name(), from)
+                }
+            },
+            Kind::Invalidate(e) => self.invalidations.push((e, UndefinedOrigin::Invalidated(s))),
+            Kind::Declaration(e) => match e.as_ref() {
+                Entity::Node{name, value} => self.driven(name, value),
+                Entity::Register(reg) if reg.reset_signal().is_none() =>
+                    self.origins.push((reg.name().clone(), UndefinedOrigin::UninitializedRegister)),
+                _ => (),
+            },
+            Kind::Conditional{when, r#else, ..} => {
+                self.stmts(when, false);
+                self.stmts(r#else, false);
+            },
+            Kind::LayerBlock{stmts, ..} => self.stmts(stmts, false),
+            _ => (),
+        })
+    }
+
+    /// Record that a signal is driven by the given expression
+    fn driven(&mut self, sink: &Arc<str>, value: &'a Expression) {
+        use transiter::AutoTransIter;
+
+        value.trans_iter().for_each(|e| match e {
+            expr::Expression::Reference(r) if !matches!(
+                r.as_ref(),
+                Entity::Memory(..) | Entity::SimpleMemPort(..)
+            ) && (self.cross || !matches!(r.as_ref(), Entity::Instance(..) | Entity::InstanceChoice(..))) => {
+                let users = self.users.entry(r.name().clone()).or_default();
+                if !users.contains(sink) {
+                    users.push(sink.clone())
+                }
+            },
+            expr::Expression::ValidIf{sel, ..} if !matches!(
+                sel.as_ref(),
+                expr::Expression::UIntLiteral{value, ..} if *value != 0u8.into()
+            ) => self.origins.push((sink.clone(), UndefinedOrigin::ValidIf(e))),
+            _ => (),
+        })
+    }
+}
+
+
+/// Check whether a connection to `sink` overrides the entire expression `part`
+///
+/// This function returns true if both expressions refer to the same entity
+/// and `sink` selects `part` or an aggregate containing it. Dynamic indices
+/// are never considered to cover anything.
+fn covers(sink: &Expression, part: &Expression) -> bool {
+    // Static subscripts applied to the root
+    fn path(expr: &Expression, res: &mut Vec<Subscript>) -> Option<Arc<str>> {
+        let (base, subscript) = match expr {
+            expr::Expression::Reference(r)          => return Some(r.name().clone()),
+            expr::Expression::SubField{base, index} => (base, Subscript::Field(index.clone())),
+            expr::Expression::SubIndex{base, index} => (base, Subscript::Index(*index)),
+            _ => return None,
+        };
+        let root = path(base, res)?;
+        res.push(subscript);
+        Some(root)
+    }
+
+    let mut sink_path = Vec::new();
+    let mut part_path = Vec::new();
+    match (path(sink, &mut sink_path), path(part, &mut part_path)) {
+        (Some(a), Some(b)) => a == b && part_path.starts_with(&sink_path),
+        _ => false,
+    }
+}
+
+
+/// Check whether an expression refers to a signal or a part of it
+///
+/// This function returns true if the expression refers to the signal with the
+/// given name, a part of the signal overlapping with the part selected by the
+/// given subscripts or an aggregate containing it.
+fn overlaps(expr: &Expression, name: &str, subscripts: &[Subscript]) -> bool {
+    // Subscripts applied to the root, with `None` denoting dynamic indices
+    fn path<'e>(expr: &'e Expression, res: &mut Vec<Option<Subscript>>) -> Option<&'e Entity> {
+        let (base, subscript) = match expr {
+            expr::Expression::Reference(r)          => return Some(r.as_ref()),
+            expr::Expression::SubField{base, index} => (base, Some(Subscript::Field(index.clone()))),
+            expr::Expression::SubIndex{base, index} => (base, Some(Subscript::Index(*index))),
+            expr::Expression::SubAccess{base, ..}   => (base, None),
+            _ => return None,
+        };
+        let root = path(base, res)?;
+        res.push(subscript);
+        Some(root)
+    }
+
+    let mut res = Vec::new();
+    if path(expr, &mut res).map(|e| e.name_ref() != name).unwrap_or(true) {
+        return false
+    }
+    res.iter().zip(subscripts).all(|(a, b)| a.as_ref().map(|a| a == b).unwrap_or(true))
+}
+
+
+/// Retrieve the entity at the root of an expression
+fn root(expr: &Expression) -> Option<&Entity> {
+    match expr {
+        expr::Expression::Reference(r)          => Some(r.as_ref()),
+        expr::Expression::SubField{base, ..}    => root(base),
+        expr::Expression::SubIndex{base, ..}    => root(base),
+        expr::Expression::SubAccess{base, ..}   => root(base),
+        _ => None,
+    }
+}
+
+
+/// Retrieve the name of the instance port an expression refers to
+///
+/// This function returns `None` if the expression refers to an instance as a
+/// whole or if it doesn't refer to an instance.
+fn instance_port(expr: &Expression) -> Option<&Arc<str>> {
+    match expr {
+        expr::Expression::SubField{base, index} => match base.as_ref() {
+            expr::Expression::Reference(_) => Some(index),
+            base => instance_port(base),
+        },
+        expr::Expression::SubIndex{base, ..}    => instance_port(base),
+        expr::Expression::SubAccess{base, ..}   => instance_port(base),
+        _ => None,
+    }
+}
+
+
+/// Start points of the paths considered by a [Tracer]
+#[derive(Clone, Debug)]
+enum Start {
+    /// Any boundary, including ports, as well as constants
+    Any,
+    /// Only the port with the given name
+    Port(Arc<str>),
+    /// Any boundary except ports, as well as constants
+    Internal,
+}
+
+
+/// Longest paths through a module relevant for tracing through its instances
+#[derive(Debug, Default)]
+struct PortPaths {
+    /// Longest paths from input ports to each output port
+    through: HashMap<Arc<str>, Vec<(Arc<str>, LogicPath)>>,
+    /// Longest paths from any start point other than a port to each port
+    from_internal: HashMap<Arc<str>, LogicPath>,
+    /// Longest paths from each port to any end point in the flattened module
+    into: HashMap<Arc<str>, LogicPath>,
+    /// Longest path in the flattened module not starting at a port
+    internal: LogicPath,
+}
+
+impl PortPaths {
+    /// Compute the paths for the given module
+    fn of(module: &Module, summaries: &mut Summaries) -> Arc<Self> {
+        let key = module as *const Module;
+        if let Some(res) = summaries.get(&key) {
+            return res.clone()
+        }
+
+        let mut res = Self::default();
+        for input in module.ports() {
+            let mut tracer = Tracer::new(module, Start::Port(input.name().clone()), Some(&mut *summaries));
+            for output in module.ports() {
+                if let Some(path) = tracer.port(output.name()) {
+                    res.through.entry(output.name().clone()).or_default().push((input.name().clone(), path))
+                }
+            }
+            if let Some(path) = tracer.longest(true) {
+                res.into.insert(input.name().clone(), path);
+            }
+        }
+
+        let mut tracer = Tracer::new(module, Start::Internal, Some(&mut *summaries));
+        for output in module.ports() {
+            if let Some(path) = tracer.port(output.name()) {
+                res.from_internal.insert(output.name().clone(), path);
+            }
+        }
+        res.internal = tracer.longest(true).unwrap_or_default();
+
+        let res = Arc::new(res);
+        summaries.insert(key, res.clone());
+        res
+    }
+}
+
+
+/// [PortPaths] of already visited modules
+type Summaries = HashMap<*const Module, Arc<PortPaths>>;
+
+
+/// Paths ending in entities or instance ports, `None` while being traced
+type Paths = HashMap<(Arc<str>, Option<Arc<str>>), Option<Option<LogicPath>>>;
+
+
+/// Helper for tracing paths backwards through nodes and wires
+///
+/// If the tracer is equipped with [Summaries], paths are also traced through
+/// instances.
+struct Tracer<'a, 's> {
+    module: &'a Module,
+    drivers: HashMap<Arc<str>, Vec<(&'a Expression, &'a Expression)>>,
+    sinks: Vec<&'a Expression>,
+    paths: Paths,
+    start: Start,
+    summaries: Option<&'s mut Summaries>,
+}
+
+impl<'a, 's> Tracer<'a, 's> {
+    /// Create a new tracer for the given module
+    fn new(module: &'a Module, start: Start, summaries: Option<&'s mut Summaries>) -> Self {
+        let mut drivers: HashMap<_, Vec<_>> = Default::default();
+        let mut sinks = Vec::new();
+        collect_drivers(module.statements(), &mut drivers, &mut sinks);
+        Self {module, drivers, sinks, paths: Default::default(), start, summaries}
+    }
+
+    /// Determine the longest path ending in any end point of the module
+    ///
+    /// If `flat` is set, paths ending at the inputs of instances are extended
+    /// into the instantiated modules and paths within those are considered.
+    fn longest(&mut self, flat: bool) -> Option<LogicPath> {
+        let mut paths: Vec<_> = self.sinks.clone().into_iter().filter_map(|e| self.expr(e)).collect();
+
+        if flat && self.summaries.is_some() {
+            let inputs: Vec<_> = self.drivers.values().flatten().filter_map(|&(to, from)| match root(to) {
+                Some(Entity::Instance(inst)) => Some((inst, instance_port(to), from)),
+                _ => None,
+            }).collect();
+            for (inst, port, from) in inputs {
+                let summary = self.port_paths(inst.module())?;
+                if let Some(path) = self.expr(from) {
+                    let into = match port {
+                        Some(port)  => summary.into.get(port),
+                        None        => summary.into.values().max_by_key(|p| p.depth()),
+                    };
+                    paths.push(into.map(|p| path.clone().joined(p)).unwrap_or(path))
+                }
+            }
+
+            if self.start(None).is_some() {
+                let instances: Vec<_> = self.module.statements().iter().flat_map(Statement::instantiations).collect();
+                for inst in instances {
+                    paths.push(self.port_paths(inst.module())?.internal.clone())
+                }
+            }
+        }
+
+        paths.into_iter().max_by_key(LogicPath::depth)
+    }
+
+    /// Determine the longest path ending in the given port of the module
+    fn port(&mut self, name: &str) -> Option<LogicPath> {
+        let sources: Vec<_> = self.drivers.get(name).into_iter().flatten().map(|(_, e)| *e).collect();
+        sources.into_iter().filter_map(|e| self.expr(e)).max_by_key(LogicPath::depth)
+    }
+
+    /// Determine the longest path ending in the given expression
+    fn expr(&mut self, expr: &Expression) -> Option<LogicPath> {
+        use transiter::AutoTransIter;
+
+        match expr {
+            expr::Expression::Reference(r) => return self.entity(r),
+            expr::Expression::SubField{base, index} if self.summaries.is_some() => {
+                if let expr::Expression::Reference(r) = base.as_ref() {
+                    if let Entity::Instance(inst) = r.as_ref() {
+                        return self.instance_output(inst, index)
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        let subs = <&Expression as AutoTransIter<_>>::recurse(&expr);
+        if subs.is_empty() {
+            return self.start(None)
+        }
+
+        let longest = subs.into_iter().filter_map(|e| self.expr(e)).max_by_key(LogicPath::depth);
+        match expr {
+            expr::Expression::PrimitiveOp(_)    => longest.map(|p| p.extended(expr)),
+            _                                   => longest,
+        }
+    }
+
+    /// Determine the longest path ending in the given entity
+    fn entity(&mut self, entity: &Entity) -> Option<LogicPath> {
+        let name = match entity {
+            Entity::Node{name, ..} | Entity::Wire{name, ..} => name,
+            Entity::Port(port) => return self.start(Some(port.name())),
+            _ => return self.start(None),
+        };
+
+        let sources: Vec<&Expression> = match entity {
+            Entity::Node{value, ..} => vec![value],
+            _ => self.drivers.get(name).into_iter().flatten().map(|(_, e)| *e).collect(),
+        };
+        if sources.is_empty() {
+            return self.start(None)
+        }
+        self.traced((name.clone(), None), sources)
+    }
+
+    /// Determine the longest path ending in an output of the given instance
+    fn instance_output(&mut self, inst: &Instance, port: &Arc<str>) -> Option<LogicPath> {
+        let summary = self.port_paths(inst.module())?;
+        let internal = self.start(None).and_then(|_| summary.from_internal.get(port).cloned());
+
+        let mut paths = Vec::new();
+        for (input, path) in summary.through.get(port).map(Vec::as_slice).unwrap_or_default() {
+            if let Some(start) = self.instance_input(inst.name(), input) {
+                paths.push(start.joined(path))
+            }
+        }
+        paths.into_iter().chain(internal).max_by_key(LogicPath::depth).or_else(|| self.start(None))
+    }
+
+    /// Determine the longest path ending in an input of the given instance
+    fn instance_input(&mut self, inst: &Arc<str>, port: &Arc<str>) -> Option<LogicPath> {
+        let sources: Vec<_> = self
+            .drivers
+            .get(inst)
+            .into_iter()
+            .flatten()
+            .filter(|(to, _)| instance_port(to).map(|p| p == port).unwrap_or(true))
+            .map(|(_, e)| *e)
+            .collect();
+        self.traced((inst.clone(), Some(port.clone())), sources)
+    }
+
+    /// Determine the longest path through any of the given sources
+    ///
+    /// The result is memoized under the given key. Loops are broken by
+    /// considering the item with the given key a start point while it is
+    /// being traced.
+    fn traced(&mut self, key: (Arc<str>, Option<Arc<str>>), sources: Vec<&Expression>) -> Option<LogicPath> {
+        match self.paths.get(&key) {
+            Some(Some(path))    => return path.clone(),
+            Some(None)          => return self.start(None),
+            None                => (),
+        }
+        self.paths.insert(key.clone(), None);
+
+        let path = sources.into_iter().filter_map(|e| self.expr(e)).max_by_key(LogicPath::depth);
+
+        self.paths.insert(key, Some(path.clone()));
+        path
+    }
+
+    /// Retrieve the [PortPaths] of the given module
+    ///
+    /// This function returns `None` if the tracer does not trace through
+    /// instances.
+    fn port_paths(&mut self, module: &Module) -> Option<Arc<PortPaths>> {
+        self.summaries.as_deref_mut().map(|s| PortPaths::of(module, s))
+    }
+
+    /// Retrieve an empty path if the given boundary is a start point
+    ///
+    /// `port` denotes the name of the port if the boundary is a port of the
+    /// module.
+    fn start(&self, port: Option<&Arc<str>>) -> Option<LogicPath> {
+        let start = match (&self.start, port) {
+            (Start::Any, _)                 => true,
+            (Start::Port(p), Some(port))    => p == port,
+            (Start::Port(_), None)          => false,
+            (Start::Internal, port)         => port.is_none(),
+        };
+        if start {
+            Some(Default::default())
+        } else {
+            None
+        }
+    }
+}
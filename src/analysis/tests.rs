@@ -0,0 +1,79 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to structural analyses
+
+use crate::module::Module;
+
+use super::*;
+
+
+#[quickcheck]
+fn logic_depth_chain(module: Module) -> bool {
+    let path = logic_depth(&module, HierarchyMode::PerModule);
+    path.depth() == path.chain().len() &&
+        path.chain().iter().all(|e| matches!(e, expr::Expression::PrimitiveOp(..)))
+}
+
+
+#[quickcheck]
+fn logic_depth_bounds_exprs(module: Module) -> bool {
+    use transiter::AutoTransIter;
+
+    fn op_depth(expr: &Expression) -> usize {
+        let sub = <&Expression as AutoTransIter<_>>::recurse(&expr).into_iter().map(op_depth).max().unwrap_or(0);
+        if let expr::Expression::PrimitiveOp(..) = expr { sub + 1 } else { sub }
+    }
+
+    let depth = logic_depth(&module, HierarchyMode::PerModule).depth();
+    module.statements().iter().flat_map(AutoTransIter::trans_iter).all(|s| match s.kind() {
+        Kind::Connection{from, to} if !matches!(root(to), Some(Entity::Wire{..})) => op_depth(from) <= depth,
+        _ => true,
+    })
+}
+
+
+#[quickcheck]
+fn drivers_include_connections(module: Module) -> bool {
+    use transiter::AutoTransIter;
+
+    module.statements().iter().flat_map(AutoTransIter::trans_iter).all(|s| match s.kind() {
+        Kind::Connection{to, ..} => root(to).map(|e| drivers_in(&module, e.name_ref(), &[])
+            .iter()
+            .any(|d| std::ptr::eq(d.statement(), s))).unwrap_or(true),
+        _ => true,
+    })
+}
+
+
+#[quickcheck]
+fn undefined_values_paths(module: Module) -> bool {
+    let values = undefined_values(&module, HierarchyMode::PerModule);
+    let signals: std::collections::HashSet<_> = values.iter().map(UndefinedValue::signal).collect();
+    signals.len() == values.len() && values.windows(2).all(|w| w[0].path().len() <= w[1].path().len()) &&
+        values.iter().all(|v| v.path().len() == 1 || values.iter().any(|o| o.path() == &v.path()[..v.path().len() - 1]))
+}
+
+
+#[quickcheck]
+fn logic_depth_modes(module: Module) -> bool {
+    let paths: Vec<_> = [HierarchyMode::PerModule, HierarchyMode::CrossInstance, HierarchyMode::Flat]
+        .iter()
+        .map(|m| logic_depth(&module, *m))
+        .collect();
+    paths.iter().all(|p| p.depth() == p.chain().len()) && paths[1].depth() <= paths[2].depth()
+}
+
+
+#[quickcheck]
+fn undefined_values_modes(module: Module) -> bool {
+    use std::collections::HashSet;
+
+    let signals = |mode| undefined_values(&module, mode)
+        .iter()
+        .map(|v| v.signal().clone())
+        .collect::<HashSet<_>>();
+    let per_module = signals(HierarchyMode::PerModule);
+    let cross = signals(HierarchyMode::CrossInstance);
+    let flat = signals(HierarchyMode::Flat);
+    per_module.is_subset(&cross) && cross.is_subset(&flat)
+}
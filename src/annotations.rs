@@ -0,0 +1,429 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! FIRRTL annotations
+//!
+//! Information not expressible in FIRRTL's textual representation, e.g.
+//! sources of black boxes or hints for downstream tools, is conveyed via
+//! annotations. These are usually stored alongside a FIRRTL file in a JSON
+//! file with the extension `.anno.json`, containing an array of objects. Each
+//! object has a `class` identifying the kind of annotation and usually a
+//! `target` designating the item it refers to. All other members form the
+//! annotation's payload.
+//!
+//! This module provides [Annotation] as well as [Value], a minimal JSON value
+//! used for payloads. A list of annotations may be parsed via [parse] or
+//! [from_read] and serialized via [json]. Annotations may be attached to a
+//! [Circuit](crate::circuit::Circuit) and queried by [Target].
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::annotations;
+//!
+//! let annos = annotations::parse(r#"[
+//!     {"class": "firrtl.transforms.DontTouchAnnotation", "target": "~Top|Top>a"},
+//!     {"class": "sifive.enterprise.firrtl.Foo", "target": "~Top|Top", "depth": 4}
+//! ]"#).unwrap();
+//!
+//! let target = "~Top|Top>a".parse().unwrap();
+//! assert!(annos[0].refers_to(&target));
+//! assert_eq!(annos[1].field("depth").and_then(|v| v.as_f64()), Some(4.0));
+//! assert_eq!(annos[1].to_string(), r#"{"class":"sifive.enterprise.firrtl.Foo","target":"~Top|Top","depth":4}"#);
+//! ```
+
+pub(crate) mod parsers;
+
+#[cfg(test)]
+mod tests;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(any(test, feature = "test-util"))]
+use quickcheck::{Arbitrary, Gen};
+
+use crate::display::JsonString;
+use crate::error::ParseError;
+use crate::target::Target;
+
+
+/// Parse a list of annotations
+///
+/// The input is expected to be a JSON array of annotation objects, as found
+/// in `.anno.json` files.
+pub fn parse(input: &str) -> Result<Vec<Annotation>, ParseError> {
+    match input.parse()? {
+        Value::Array(items) => items.into_iter().map(Annotation::try_from).collect(),
+        _                   => Err("Expected an array of annotations".to_string().into()),
+    }
+}
+
+
+/// Read a list of annotations
+///
+/// See [parse] for details.
+pub fn from_read(mut read: impl std::io::Read) -> Result<Vec<Annotation>, ParseError> {
+    let mut buf = String::new();
+    read.read_to_string(&mut buf)?;
+    parse(&buf)
+}
+
+
+/// Retrieve a value serializing the given annotations as JSON
+pub fn json(annotations: &[Annotation]) -> Json<'_> {
+    Json(annotations)
+}
+
+
+/// A single annotation
+///
+/// An annotation consists of a class, an optional target and a payload in the
+/// form of additional fields. The target is retained verbatim, since not all
+/// targets used in practice are expressible as a [Target], e.g. targets
+/// referring to a circuit as a whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    class: Arc<str>,
+    target: Option<Arc<str>>,
+    fields: Vec<(Arc<str>, Value)>,
+}
+
+impl Annotation {
+    /// Create a new annotation of the given class without target or payload
+    pub fn new(class: impl Into<Arc<str>>) -> Self {
+        Self {class: class.into(), target: Default::default(), fields: Default::default()}
+    }
+
+    /// Create a copy of this annotation with the given target
+    pub fn with_target(self, target: &Target) -> Self {
+        self.with_raw_target(target.to_string())
+    }
+
+    /// Create a copy of this annotation with the given target string
+    pub fn with_raw_target(self, target: impl Into<Arc<str>>) -> Self {
+        Self {target: Some(target.into()), ..self}
+    }
+
+    /// Create a copy of this annotation with an additional field
+    ///
+    /// The field will be added after all existing fields. The names `class`
+    /// and `target` are reserved and should not be used.
+    pub fn with_field(mut self, name: impl Into<Arc<str>>, value: impl Into<Value>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Retrieve the class of this annotation
+    pub fn class(&self) -> &Arc<str> {
+        &self.class
+    }
+
+    /// Retrieve the target of this annotation as given
+    pub fn raw_target(&self) -> Option<&str> {
+        self.target.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Retrieve the target of this annotation
+    ///
+    /// This function returns `None` if the annotation has no target or if the
+    /// target is not expressible as a [Target].
+    pub fn target(&self) -> Option<Target> {
+        self.target.as_ref().and_then(|t| t.parse().ok())
+    }
+
+    /// Check whether this annotation refers to the given target
+    ///
+    /// The circuits named by both targets are only compared if both of them
+    /// name one.
+    pub fn refers_to(&self, target: &Target) -> bool {
+        self.target().map(|t| {
+            let circuit = t.circuit().zip(target.circuit()).map(|(l, r)| l == r).unwrap_or(true);
+            circuit && t.with_circuit(None) == target.clone().with_circuit(None)
+        }).unwrap_or(false)
+    }
+
+    /// Retrieve the payload of this annotation
+    pub fn fields(&self) -> impl Iterator<Item = (&Arc<str>, &Value)> {
+        self.fields.iter().map(|(n, v)| (n, v))
+    }
+
+    /// Retrieve the first payload field with the given name
+    pub fn field(&self, name: impl AsRef<str>) -> Option<&Value> {
+        self.fields.iter().find(|(n, _)| n.as_ref() == name.as_ref()).map(|(_, v)| v)
+    }
+}
+
+impl TryFrom<Value> for Annotation {
+    type Error = ParseError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let members = match value {
+            Value::Object(members)  => members,
+            _                       => return Err("Expected an annotation object".to_string().into()),
+        };
+
+        let mut class = None;
+        let mut target = None;
+        let mut fields = Vec::new();
+        for (name, value) in members {
+            match (name.as_ref(), value) {
+                ("class", Value::String(s)) if class.is_none()  => class = Some(s),
+                ("target", Value::String(s)) if target.is_none() => target = Some(s),
+                ("class", _) | ("target", _)                    =>
+                    return Err(format!("Invalid or duplicate member \"{}\" in annotation", name).into()),
+                (_, value)                                      => fields.push((name, value)),
+            }
+        }
+
+        class
+            .map(|class| Self {class, target, fields})
+            .ok_or_else(|| "Annotation without class".to_string().into())
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{\"class\":{}", JsonString(&self.class))?;
+        if let Some(target) = self.target.as_ref() {
+            write!(f, ",\"target\":{}", JsonString(target))?;
+        }
+        self.fields.iter().try_for_each(|(n, v)| write!(f, ",{}:{}", JsonString(n), v))?;
+        write!(f, "}}")
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Annotation {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        let res = Self::new(Identifier::arbitrary(g));
+        let res = if bool::arbitrary(g) {
+            res.with_target(&Arbitrary::arbitrary(g))
+        } else {
+            res
+        };
+        let names: Vec<_> = (0..u8::arbitrary(g) % 4)
+            .map(|_| Identifier::arbitrary(g))
+            .filter(|n| !["class", "target"].contains(&n.as_ref()))
+            .collect();
+        names.into_iter().fold(res, |a, n| a.with_field(n, Value::arbitrary(g)))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let res = (0..self.fields.len()).map({
+            let a = self.clone();
+            move |i| {
+                let mut res = a.clone();
+                res.fields.remove(i);
+                res
+            }
+        });
+        Box::new(res)
+    }
+}
+
+
+/// JSON serialization of a list of [Annotation]s
+///
+/// The `Display` impl of this type emits a single line of JSON.
+#[derive(Copy, Clone, Debug)]
+pub struct Json<'a>(&'a [Annotation]);
+
+impl fmt::Display for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        self.0.iter().enumerate().try_for_each(|(i, a)| {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            fmt::Display::fmt(a, f)
+        })?;
+        write!(f, "]")
+    }
+}
+
+
+/// JSON value
+///
+/// Numbers are retained in their textual form, which allows emitting them
+/// exactly as they were parsed. Members of objects are kept in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Arc<str>),
+    String(Arc<str>),
+    Array(Vec<Value>),
+    Object(Vec<(Arc<str>, Value)>),
+}
+
+impl Value {
+    /// Retrieve the boolean value, if this is a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(v)   => Some(*v),
+            _               => None,
+        }
+    }
+
+    /// Retrieve the numeric value, if this is a number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(v) => v.parse().ok(),
+            _               => None,
+        }
+    }
+
+    /// Retrieve the integer value, if this is an integral number
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Number(v) => v.parse().ok(),
+            _               => None,
+        }
+    }
+
+    /// Retrieve the string value, if this is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v.as_ref()),
+            _               => None,
+        }
+    }
+
+    /// Retrieve the elements, if this is an array
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(v)  => Some(v.as_ref()),
+            _               => None,
+        }
+    }
+
+    /// Retrieve the first member with the given name, if this is an object
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&Self> {
+        match self {
+            Self::Object(v) => v.iter().find(|(n, _)| n.as_ref() == name.as_ref()).map(|(_, v)| v),
+            _               => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Number(value.to_string().into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<Arc<str>> for Value {
+    fn from(value: Arc<str>) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::Array(value)
+    }
+}
+
+impl From<Annotation> for Value {
+    fn from(anno: Annotation) -> Self {
+        let members = std::iter::once(("class".into(), anno.class.into()))
+            .chain(anno.target.map(|t| ("target".into(), t.into())))
+            .chain(anno.fields)
+            .collect();
+        Self::Object(members)
+    }
+}
+
+impl std::str::FromStr for Value {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use nom::Finish;
+        use nom::character::complete::multispace0;
+        use nom::sequence::terminated;
+
+        nom::combinator::all_consuming(terminated(parsers::value, multispace0))(s)
+            .finish()
+            .map(|(_, v)| v)
+            .map_err(|e| crate::error::convert_error(s, nom::Err::Error(e)))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, items: &mut dyn Iterator<Item = (Option<&str>, &Self)>| {
+            items.enumerate().try_for_each(|(i, (n, v))| {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                if let Some(n) = n {
+                    write!(f, "{}:", JsonString(n))?;
+                }
+                fmt::Display::fmt(v, f)
+            })
+        };
+
+        match self {
+            Self::Null      => write!(f, "null"),
+            Self::Bool(v)   => fmt::Display::fmt(v, f),
+            Self::Number(v) => fmt::Display::fmt(v, f),
+            Self::String(v) => fmt::Display::fmt(&JsonString(v), f),
+            Self::Array(v)  => {
+                write!(f, "[")?;
+                list(f, &mut v.iter().map(|v| (None, v)))?;
+                write!(f, "]")
+            },
+            Self::Object(v) => {
+                write!(f, "{{")?;
+                list(f, &mut v.iter().map(|(n, v)| (Some(n.as_ref()), v)))?;
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Value {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        let mut g = Gen::new(std::cmp::max(g.size() / 2, 1));
+        let opts: [&dyn Fn(&mut Gen) -> Self; 6] = [
+            &|_| Self::Null,
+            &|g| Self::Bool(Arbitrary::arbitrary(g)),
+            &|g| i64::arbitrary(g).into(),
+            &|g| Self::String(String::arbitrary(g).into()),
+            &|g| Self::Array((0..u8::arbitrary(g) % 4).map(|_| Self::arbitrary(g)).collect()),
+            &|g| Self::Object((0..u8::arbitrary(g) % 4)
+                .map(|_| (Identifier::arbitrary(g).into(), Self::arbitrary(g)))
+                .collect()),
+        ];
+        // Avoid deeply nested values by restricting ourselves to scalars once
+        // the size is exhausted.
+        let num = if g.size() > 1 { opts.len() } else { opts.len() - 2 };
+        g.choose(&opts[..num]).unwrap()(&mut g)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Self::Array(v)  => Box::new(v.shrink().map(Self::Array)),
+            Self::Object(v) => Box::new(v.clone().into_iter().map(|(_, v)| v)),
+            _               => Box::new(std::iter::empty()),
+        }
+    }
+}
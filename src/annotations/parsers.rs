@@ -0,0 +1,93 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Parsers for annotations
+
+use nom::Parser;
+use nom::branch::alt;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::{anychar, char as chr, digit0, digit1, one_of};
+use nom::combinator::{map, map_opt, map_res, opt, recognize, value as val, verify};
+use nom::error::context;
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, preceded, separated_pair, tuple};
+
+use crate::parsers::{IResult, kw, op, wrapped};
+
+use super::Value;
+
+
+/// Parse a JSON value, skipping preceding whitespace
+pub fn value(input: &str) -> IResult<'_, Value> {
+    let array = delimited(op("["), separated_list0(wrapped(op(",")), value), wrapped(op("]")));
+    let member = separated_pair(wrapped(string), wrapped(op(":")), value);
+    let object = delimited(op("{"), separated_list0(wrapped(op(",")), member), wrapped(op("}")));
+
+    wrapped(context("expected JSON value", alt((
+        val(Value::Null, kw("null")),
+        val(Value::Bool(true), kw("true")),
+        val(Value::Bool(false), kw("false")),
+        map(number, |n| Value::Number(n.into())),
+        map(string, |s| Value::String(s.into())),
+        map(array, Value::Array),
+        map(object, |m| Value::Object(m.into_iter().map(|(n, v)| (n.into(), v)).collect())),
+    )))).parse(input)
+}
+
+
+/// Parse a JSON number, returning its textual representation
+pub fn number(input: &str) -> IResult<'_, &str> {
+    let int = alt((recognize(chr('0')), recognize(tuple((one_of("123456789"), digit0)))));
+    let frac = tuple((chr('.'), digit1));
+    let exp = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+
+    recognize(tuple((opt(chr('-')), int, opt(frac), opt(exp))))(input)
+}
+
+
+/// Parse a JSON string literal
+pub fn string(input: &str) -> IResult<'_, String> {
+    use nom::combinator::iterator;
+
+    let (input, _) = op("\"").parse(input)?;
+    let mut chars = iterator(
+        input,
+        alt((
+            preceded(chr('\\'), alt((
+                val('"', chr('"')),
+                val('\\', chr('\\')),
+                val('/', chr('/')),
+                val('\u{8}', chr('b')),
+                val('\u{c}', chr('f')),
+                val('\n', chr('n')),
+                val('\r', chr('r')),
+                val('\t', chr('t')),
+                preceded(chr('u'), unicode_escape),
+            ))),
+            verify(anychar, |c| *c != '"' && *c != '\\' && !c.is_ascii_control()),
+        ))
+    );
+    let res = (&mut chars).collect();
+    let (input, _) = chars.finish()?;
+    let (input, _) = context("expected end of string", op("\"")).parse(input)?;
+    Ok((input, res))
+}
+
+
+/// Parse the hexadecimal part of a `\u` escape sequence
+///
+/// Characters outside the basic multilingual plane are encoded as surrogate
+/// pairs, the second of which will be consumed by this parser as well.
+fn unicode_escape(input: &str) -> IResult<'_, char> {
+    let hex = || map_res(take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()), |h| u32::from_str_radix(h, 16));
+
+    alt((
+        map_opt(
+            tuple((
+                verify(hex(), |h| (0xD800..0xDC00).contains(h)),
+                preceded(op("\\u"), verify(hex(), |l| (0xDC00..0xE000).contains(l))),
+            )),
+            |(h, l)| std::char::from_u32(0x10000 + ((h - 0xD800) << 10) + (l - 0xDC00)),
+        ),
+        map_opt(hex(), std::char::from_u32),
+    ))(input)
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to annotations
+
+use crate::circuit::Circuit;
+use crate::target::Target;
+use crate::tests::Equivalence;
+
+use super::{Annotation, Value, json, parse};
+
+
+#[quickcheck]
+fn parse_value(original: Value) -> Result<Equivalence<Value>, String> {
+    let s = original.to_string();
+    s.parse().map(|parsed| Equivalence::of(original, parsed)).map_err(|e: crate::error::ParseError| e.to_string())
+}
+
+
+#[quickcheck]
+fn parse_annotations(original: Vec<Annotation>) -> Result<Equivalence<Vec<Annotation>>, String> {
+    let s = json(&original).to_string();
+    parse(&s).map(|parsed| Equivalence::of(original, parsed)).map_err(|e| e.to_string())
+}
+
+
+#[quickcheck]
+fn annotations_for_target(circuit: Circuit, annotations: Vec<Annotation>, target: Target) -> bool {
+    let anno = Annotation::new("test").with_target(&target);
+    let circuit = circuit.with_annotations(annotations.into_iter().chain(std::iter::once(anno.clone())));
+    circuit.annotations_for(&target).any(|a| a == &anno) &&
+        circuit.annotations_for(&target).all(|a| a.target().map(|t| t.path() == target.path()).unwrap_or(false))
+}
+
+
+#[test]
+fn parse_escapes() {
+    let value: Value = r#" { "a" : [ "ä😀\n\/" , -1.5e3, null, true ] } "#.parse().unwrap();
+    assert_eq!(value.get("a").and_then(|v| v.as_array()).map(|a| a.len()), Some(4));
+    assert_eq!(value.get("a").unwrap().as_array().unwrap()[0].as_str(), Some("\u{e4}\u{1f600}\n/"));
+    assert_eq!(value.get("a").unwrap().as_array().unwrap()[1].as_f64(), Some(-1500.0));
+    assert_eq!(value.to_string(), "{\"a\":[\"\u{e4}\u{1f600}\\n/\",-1.5e3,null,true]}");
+}
+
+
+#[test]
+fn parse_invalid() {
+    assert!(parse("{\"class\": \"a\"}").is_err());
+    assert!(parse("[{\"target\": \"~Top|Top\"}]").is_err());
+    assert!(parse("[{\"class\": 1}]").is_err());
+    assert!(parse("[{\"class\": \"a\"},]").is_err());
+    assert!(parse("[\"unterminated]").is_err());
+}
@@ -0,0 +1,1373 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Binary cache format for circuits
+//!
+//! Parsing the textual representation of a big circuit takes considerable
+//! time. For tools invoked repeatedly on the same design, this module provides
+//! a compact binary encoding of a [Circuit] which is a lot faster to load.
+//! Caches are usually written via [Circuit::save_cache] and loaded via
+//! [Circuit::load_cache].
+//!
+//! A cache starts with the bytes [MAGIC], followed by the [VERSION] of the
+//! format. Caches of a different version are rejected, i.e. caches need to be
+//! regenerated after the format changed. Hence, the format is not suitable for
+//! long-term storage or for exchanging designs. Circuits containing custom
+//! statements, i.e. [stmt::Kind::Extension], cannot be cached.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::Circuit;
+//!
+//! let source = "circuit Top:\n  module Top:\n    input a: UInt<1>\n    output b: UInt<1>\n    b <= a\n";
+//! let circuit = firrtl_ast::circuit::parse(source).unwrap();
+//!
+//! let mut cache = Vec::new();
+//! circuit.save_cache(&mut cache).unwrap();
+//! assert_eq!(Circuit::load_cache(cache.as_slice()).unwrap(), circuit);
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::sync::Arc;
+
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::annotations::{Annotation, Value};
+use crate::circuit::{Circuit, Version, layer::{Convention, Layer}, option::OptionGroup};
+use crate::expr::{Expression, primitive::Operation};
+use crate::info::WithInfo;
+use crate::memory::{Memory, PortDir, ReadUnderWrite, Register, mem, simple};
+use crate::module::{self, Direction, Instance, InstanceChoice, Module, ParamValue, Port, external::Resource};
+use crate::named::Named;
+use crate::provenance::Id;
+use crate::stmt::{self, Entity, Statement, print::{Format, PrintElement}, verification};
+use crate::types::{
+    BitWidth,
+    BundleField,
+    EnumVariant,
+    GroundType,
+    Orientation,
+    ProbeKind,
+    ResetKind,
+    Type,
+    TypeAlias,
+    Typed,
+};
+use crate::types::interval::{Bound, Decimal};
+
+
+/// Bytes identifying a cache
+pub const MAGIC: &[u8; 8] = b"FIRRTLAC";
+
+/// Version of the cache format
+pub const VERSION: u32 = 7;
+
+
+/// Write a binary cache of the given circuit
+pub fn write(circuit: &Circuit, mut write: impl io::Write) -> io::Result<()> {
+    let mut encoder: Encoder = Default::default();
+    encoder.buf.extend_from_slice(MAGIC);
+    encoder.buf.extend_from_slice(&VERSION.to_le_bytes());
+    encoder.circuit(circuit);
+    if let Some(msg) = encoder.unsupported {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+    }
+    write.write_all(&encoder.buf)
+}
+
+
+/// Read a circuit from a binary cache
+///
+/// This function fails if the cache is of a different version or malformed.
+pub fn read(mut read: impl io::Read) -> io::Result<Circuit> {
+    let mut buf = Vec::new();
+    read.read_to_end(&mut buf)?;
+
+    let data = buf.strip_prefix(MAGIC.as_ref()).ok_or_else(|| invalid("not a circuit cache"))?;
+    if data.len() < 4 {
+        return Err(invalid("not a circuit cache"))
+    }
+    let (version, data) = data.split_at(4);
+    let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+    if version != VERSION {
+        return Err(invalid(format!("unsupported cache version {}, expected {}", version, VERSION)))
+    }
+
+    let mut decoder = Decoder {data, modules: Default::default(), ports: Default::default(), entities: Default::default(), memories: Default::default()};
+    let res = decoder.circuit()?;
+    if decoder.data.is_empty() {
+        Ok(res)
+    } else {
+        Err(invalid("trailing data in cache"))
+    }
+}
+
+
+/// Create an error signalling a malformed cache
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+
+/// Encoder for circuits
+///
+/// Modules, ports, entities and simple memories are shared via `Arc`s in the
+/// AST. The encoder assigns an index to each of them when it is encoded for the
+/// first time. Subsequent occurrences are encoded as that index, preserving
+/// the sharing. Except for modules, indices are local to a module.
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+    modules: HashMap<*const Module, usize>,
+    module_names: HashMap<Arc<str>, usize>,
+    ports: HashMap<*const Port, usize>,
+    entities: HashMap<*const Entity, usize>,
+    memories: HashMap<*const simple::Memory, usize>,
+    unsupported: Option<String>,
+}
+
+impl Encoder {
+    fn uint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8)
+    }
+
+    fn int(&mut self, value: i64) {
+        self.uint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.buf.push(value as u8)
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.uint(value.len() as u64);
+        self.buf.extend_from_slice(value)
+    }
+
+    fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes())
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        self.bool(value.is_some());
+        value.into_iter().for_each(|v| self.str(v))
+    }
+
+    fn width(&mut self, width: BitWidth) {
+        self.uint(width.map(|w| w as u64 + 1).unwrap_or(0))
+    }
+
+    fn circuit(&mut self, circuit: &Circuit) {
+        self.opt_str(circuit.info());
+        if let Some(version) = circuit.version() {
+            self.uint(1);
+            [version.major(), version.minor(), version.patch()].iter().for_each(|v| self.uint((*v).into()));
+        } else {
+            self.uint(0);
+        }
+        self.uint(circuit.layers().len() as u64);
+        circuit.layers().iter().for_each(|l| self.layer(l));
+        self.uint(circuit.type_aliases().len() as u64);
+        circuit.type_aliases().iter().for_each(|a| {
+            self.str(a.name());
+            self.opt_str(a.info());
+            self.r#type(a.r#type())
+        });
+        self.uint(circuit.option_groups().len() as u64);
+        circuit.option_groups().iter().for_each(|o| {
+            self.str(o.name());
+            self.opt_str(o.info());
+            self.uint(o.cases().len() as u64);
+            o.cases().iter().for_each(|c| self.str(c))
+        });
+        self.uint(circuit.annotations().len() as u64);
+        circuit.annotations().iter().for_each(|a| self.annotation(a));
+        self.uint(circuit.defined_modules().len() as u64);
+        circuit.defined_modules().iter().for_each(|m| self.module(m));
+        self.module_ref(circuit.top_module())
+    }
+
+    fn annotation(&mut self, annotation: &Annotation) {
+        self.str(annotation.class());
+        self.opt_str(annotation.raw_target());
+        self.uint(annotation.fields().count() as u64);
+        annotation.fields().for_each(|(n, v)| {
+            self.str(n);
+            self.value(v)
+        })
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null         => self.buf.push(0),
+            Value::Bool(b)      => {
+                self.buf.push(1);
+                self.bool(*b)
+            },
+            Value::Number(n)    => {
+                self.buf.push(2);
+                self.str(n)
+            },
+            Value::String(v)    => {
+                self.buf.push(3);
+                self.str(v)
+            },
+            Value::Array(v)     => {
+                self.buf.push(4);
+                self.uint(v.len() as u64);
+                v.iter().for_each(|v| self.value(v))
+            },
+            Value::Object(m)    => {
+                self.buf.push(5);
+                self.uint(m.len() as u64);
+                m.iter().for_each(|(n, v)| {
+                    self.str(n);
+                    self.value(v)
+                })
+            },
+        }
+    }
+
+    fn layer(&mut self, layer: &Layer) {
+        self.str(layer.name());
+        self.opt_str(layer.info());
+        self.bool(layer.convention() == Convention::Inline);
+        self.opt_str(layer.output_dir());
+        self.uint(layer.layers().len() as u64);
+        layer.layers().iter().for_each(|l| self.layer(l))
+    }
+
+    fn module(&mut self, module: &Arc<Module>) {
+        self.ports.clear();
+        self.entities.clear();
+        self.memories.clear();
+
+        self.str(module.name());
+        self.opt_str(module.info());
+        self.uint(module.enabled_layers().len() as u64);
+        module.enabled_layers().iter().for_each(|l| self.str(l));
+        self.uint(module.ports().count() as u64);
+        module.ports().for_each(|p| {
+            self.ports.insert(Arc::as_ptr(p), self.ports.len());
+            self.port(p)
+        });
+        match module.kind() {
+            module::Kind::Regular{stmts} => {
+                self.buf.push(0);
+                self.stmts(stmts)
+            },
+            module::Kind::External{defname, params, resources} => {
+                self.buf.push(1);
+                self.opt_str(defname.as_ref().map(AsRef::as_ref));
+                self.params(params);
+                self.uint(resources.len() as u64);
+                resources.iter().for_each(|r| match r {
+                    Resource::Path(path) => {
+                        self.buf.push(0);
+                        self.str(path)
+                    },
+                    Resource::Inline{name, text} => {
+                        self.buf.push(1);
+                        self.str(name);
+                        self.str(text)
+                    },
+                })
+            },
+        }
+
+        let index = self.modules.len();
+        self.modules.insert(Arc::as_ptr(module), index);
+        self.module_names.entry(module.name().clone()).or_insert(index);
+    }
+
+    fn module_ref(&mut self, module: &Arc<Module>) {
+        let index = self
+            .modules
+            .get(&Arc::as_ptr(module))
+            .or_else(|| self.module_names.get(module.name()))
+            .cloned()
+            .unwrap_or(usize::MAX);
+        self.uint(index as u64)
+    }
+
+    fn port(&mut self, port: &Port) {
+        self.str(port.name());
+        self.opt_str(port.info());
+        self.r#type(port.r#type());
+        self.bool(port.direction() == Direction::Output)
+    }
+
+    fn params(&mut self, params: &HashMap<Arc<str>, ParamValue>) {
+        let mut params: Vec<_> = params.iter().collect();
+        params.sort_unstable_by_key(|(n, _)| *n);
+        self.uint(params.len() as u64);
+        params.into_iter().for_each(|(name, value)| {
+            self.str(name);
+            match value {
+                ParamValue::Int(v)          => {
+                    self.buf.push(0);
+                    self.int(*v)
+                },
+                ParamValue::Double(v)       => {
+                    self.buf.push(1);
+                    self.buf.extend_from_slice(&v.to_bits().to_le_bytes())
+                },
+                ParamValue::String(v)       => {
+                    self.buf.push(2);
+                    self.str(v)
+                },
+                ParamValue::RawVerilog(v)   => {
+                    self.buf.push(3);
+                    self.str(v)
+                },
+            }
+        })
+    }
+
+    fn r#type(&mut self, r#type: &Type) {
+        match r#type {
+            Type::GroundType(g) => {
+                self.buf.push(0);
+                self.ground_type(g)
+            },
+            Type::Vector(t, w)  => {
+                self.buf.push(1);
+                self.uint(*w as u64);
+                self.r#type(t)
+            },
+            Type::Bundle(fields) => {
+                self.buf.push(2);
+                self.uint(fields.len() as u64);
+                fields.iter().for_each(|f| {
+                    self.str(f.name());
+                    self.bool(f.orientation() == Orientation::Flipped);
+                    self.r#type(f.r#type())
+                })
+            },
+            Type::Probe(t, k)   => {
+                self.buf.push(3);
+                self.bool(*k == ProbeKind::ReadWrite);
+                self.r#type(t)
+            },
+            Type::Enum(variants) => {
+                self.buf.push(4);
+                self.uint(variants.len() as u64);
+                variants.iter().for_each(|v| {
+                    self.str(v.name());
+                    self.bool(v.data().is_some());
+                    v.data().into_iter().for_each(|t| self.r#type(t))
+                })
+            },
+            Type::Alias(n, t)   => {
+                self.buf.push(5);
+                self.str(n);
+                self.r#type(t)
+            },
+        }
+    }
+
+    fn ground_type(&mut self, r#type: &GroundType) {
+        match r#type {
+            GroundType::UInt(w)     => {
+                self.buf.push(0);
+                self.width(*w)
+            },
+            GroundType::SInt(w)     => {
+                self.buf.push(1);
+                self.width(*w)
+            },
+            GroundType::Fixed(w, p) => {
+                self.buf.push(2);
+                self.width(*w);
+                self.bool(p.is_some());
+                p.iter().for_each(|p| self.int(*p as i64))
+            },
+            GroundType::Clock       => self.buf.push(3),
+            GroundType::Reset(k)    => self.buf.push(if *k == ResetKind::Async { 5 } else { 4 }),
+            GroundType::Analog(w)   => {
+                self.buf.push(6);
+                self.width(*w)
+            },
+            GroundType::Interval(l, u, p) => {
+                self.buf.push(7);
+                self.bound(l);
+                self.bound(u);
+                self.bool(p.is_some());
+                p.iter().for_each(|p| self.int(*p as i64))
+            },
+        }
+    }
+
+    fn bound(&mut self, bound: &Option<Bound>) {
+        let (tag, value) = match bound {
+            Some(Bound::Closed(v))  => (1, v),
+            Some(Bound::Open(v))    => (2, v),
+            None                    => return self.buf.push(0),
+        };
+        self.buf.push(tag);
+        self.int(value.mantissa());
+        self.uint(value.scale().into())
+    }
+
+    fn stmts(&mut self, stmts: &[Statement]) {
+        self.uint(stmts.len() as u64);
+        stmts.iter().for_each(|s| self.stmt(s))
+    }
+
+    fn stmt(&mut self, stmt: &Statement) {
+        self.opt_str(stmt.info());
+        self.bool(stmt.id().is_some());
+        stmt.id().into_iter().for_each(|id| self.uint(id.raw()));
+
+        match stmt.kind() {
+            stmt::Kind::Connection{from, to} => {
+                self.buf.push(0);
+                self.expr(from);
+                self.expr(to)
+            },
+            stmt::Kind::PartialConnection{from, to} => {
+                self.buf.push(1);
+                self.expr(from);
+                self.expr(to)
+            },
+            stmt::Kind::Empty => self.buf.push(2),
+            stmt::Kind::Declaration(entity) => {
+                self.buf.push(3);
+                self.entity(entity)
+            },
+            stmt::Kind::SimpleMemDecl(mem) => {
+                self.buf.push(4);
+                self.simple_mem(mem)
+            },
+            stmt::Kind::Invalidate(expr) => {
+                self.buf.push(5);
+                self.expr(expr)
+            },
+            stmt::Kind::Attach(exprs) => {
+                self.buf.push(6);
+                self.uint(exprs.len() as u64);
+                exprs.iter().for_each(|e| self.expr(e))
+            },
+            stmt::Kind::Conditional{cond, when, r#else} => {
+                self.buf.push(7);
+                self.expr(cond);
+                self.stmts(when);
+                self.stmts(r#else)
+            },
+            stmt::Kind::Stop{name, clock, cond, code} => {
+                self.buf.push(8);
+                self.opt_str(name.as_ref().map(AsRef::as_ref));
+                self.expr(clock);
+                self.expr(cond);
+                self.int(*code)
+            },
+            stmt::Kind::Print{name, clock, cond, msg} => {
+                self.buf.push(9);
+                self.opt_str(name.as_ref().map(AsRef::as_ref));
+                self.expr(clock);
+                self.expr(cond);
+                self.uint(msg.len() as u64);
+                msg.iter().for_each(|e| match e {
+                    PrintElement::Literal(s) => {
+                        self.buf.push(0);
+                        self.str(s)
+                    },
+                    PrintElement::Value(e, f) => {
+                        self.buf.push(1);
+                        self.expr(e);
+                        self.buf.push(match f {
+                            Format::Binary      => 0,
+                            Format::Decimal     => 1,
+                            Format::Hexadecimal => 2,
+                            Format::Character   => 3,
+                        })
+                    },
+                })
+            },
+            stmt::Kind::Verification{name, op, clock, pred, en, msg} => {
+                self.buf.push(10);
+                self.opt_str(name.as_ref().map(AsRef::as_ref));
+                self.buf.push(match op {
+                    verification::Op::Assert    => 0,
+                    verification::Op::Assume    => 1,
+                    verification::Op::Cover     => 2,
+                });
+                self.expr(clock);
+                self.expr(pred);
+                self.expr(en);
+                self.str(msg)
+            },
+            stmt::Kind::Define{from, to} => {
+                self.buf.push(11);
+                self.expr(from);
+                self.expr(to)
+            },
+            stmt::Kind::Match{value, arms} => {
+                self.buf.push(12);
+                self.expr(value);
+                self.uint(arms.len() as u64);
+                arms.iter().for_each(|a| {
+                    self.str(a.variant());
+                    self.bool(a.binder().is_some());
+                    a.binder().into_iter().for_each(|b| self.entity(b));
+                    self.stmts(a.stmts())
+                })
+            },
+            stmt::Kind::LayerBlock{layer, stmts} => {
+                self.buf.push(13);
+                self.str(layer);
+                self.stmts(stmts)
+            },
+            stmt::Kind::Extension(custom) => if self.unsupported.is_none() {
+                self.unsupported = Some(format!("custom statement '{}' cannot be cached", custom.keyword()))
+            },
+        }
+    }
+
+    fn entity(&mut self, entity: &Arc<Entity>) {
+        if let Some(index) = self.entities.get(&Arc::as_ptr(entity)).cloned() {
+            self.buf.push(0);
+            self.uint(index as u64);
+            return
+        }
+
+        self.buf.push(1);
+        match entity.as_ref() {
+            Entity::Port(port) => {
+                self.buf.push(0);
+                match self.ports.get(&Arc::as_ptr(port)).cloned() {
+                    Some(index) => {
+                        self.buf.push(0);
+                        self.uint(index as u64)
+                    },
+                    None => {
+                        self.buf.push(1);
+                        self.port(port)
+                    },
+                }
+            },
+            Entity::Wire{name, r#type} => {
+                self.buf.push(1);
+                self.str(name);
+                self.r#type(r#type)
+            },
+            Entity::Register(reg) => {
+                self.buf.push(2);
+                self.str(reg.name());
+                self.r#type(&reg.r#type().map_err(|_| ()).expect("Registers are always typed"));
+                self.expr(reg.clock());
+                let reset = reg.reset_signal().zip(reg.reset_value());
+                self.bool(reset.is_some());
+                if let Some((signal, value)) = reset {
+                    self.expr(signal);
+                    self.expr(value)
+                }
+            },
+            Entity::Node{name, value} => {
+                self.buf.push(3);
+                self.str(name);
+                self.expr(value)
+            },
+            Entity::Memory(mem) => {
+                self.buf.push(4);
+                self.str(mem.name());
+                self.r#type(mem.data_type());
+                self.uint(mem.depth());
+                self.uint(mem.ports().count() as u64);
+                mem.ports().for_each(|p| {
+                    self.str(&p.name);
+                    self.buf.push(port_dir(p.dir))
+                });
+                self.uint(mem.read_latency() as u64);
+                self.uint(mem.write_latency() as u64);
+                self.buf.push(read_under_write(mem.read_under_write()))
+            },
+            Entity::SimpleMemPort(port) => {
+                self.buf.push(5);
+                self.str(port.name());
+                self.simple_mem(port.memory());
+                self.buf.push(port.direction().map(|d| port_dir(d) + 1).unwrap_or(0));
+                self.expr(port.address());
+                self.expr(port.clock())
+            },
+            Entity::Instance(inst) => {
+                self.buf.push(6);
+                self.str(inst.name());
+                self.module_ref(inst.module());
+                self.params(inst.parameters())
+            },
+            Entity::Binder{name, r#type} => {
+                self.buf.push(7);
+                self.str(name);
+                self.r#type(r#type)
+            },
+            Entity::InstanceChoice(inst) => {
+                self.buf.push(8);
+                self.str(inst.name());
+                self.module_ref(inst.default_module());
+                self.str(inst.option());
+                self.uint(inst.cases().len() as u64);
+                inst.cases().iter().for_each(|(c, m)| {
+                    self.str(c);
+                    self.module_ref(m)
+                })
+            },
+        }
+        self.entities.insert(Arc::as_ptr(entity), self.entities.len());
+    }
+
+    fn simple_mem(&mut self, mem: &Arc<simple::Memory>) {
+        if let Some(index) = self.memories.get(&Arc::as_ptr(mem)).cloned() {
+            self.buf.push(0);
+            self.uint(index as u64);
+            return
+        }
+
+        self.buf.push(1);
+        self.str(mem.name());
+        self.r#type(&mem.r#type().map_err(|_| ()).expect("Memories are always typed"));
+        match mem.kind() {
+            simple::Kind::Combinatory   => self.buf.push(0),
+            simple::Kind::Sequential(r) => self.buf.push(r.map(|r| read_under_write(r) + 2).unwrap_or(1)),
+        }
+        self.memories.insert(Arc::as_ptr(mem), self.memories.len());
+    }
+
+    fn expr(&mut self, expr: &Expression<Arc<Entity>>) {
+        match expr {
+            Expression::UIntLiteral{value, width} => {
+                self.buf.push(0);
+                self.bytes(&value.to_bytes_le());
+                self.uint(*width as u64)
+            },
+            Expression::SIntLiteral{value, width} => {
+                self.buf.push(1);
+                let (sign, bytes) = value.to_bytes_le();
+                self.bool(sign == Sign::Minus);
+                self.bytes(&bytes);
+                self.uint(*width as u64)
+            },
+            Expression::Reference(entity) => {
+                self.buf.push(2);
+                self.entity(entity)
+            },
+            Expression::SubField{base, index} => {
+                self.buf.push(3);
+                self.expr(base);
+                self.str(index)
+            },
+            Expression::SubIndex{base, index} => {
+                self.buf.push(4);
+                self.expr(base);
+                self.uint(*index as u64)
+            },
+            Expression::SubAccess{base, index} => {
+                self.buf.push(5);
+                self.expr(base);
+                self.expr(index)
+            },
+            Expression::Mux{sel, a, b} => {
+                self.buf.push(6);
+                self.expr(sel);
+                self.expr(a);
+                self.expr(b)
+            },
+            Expression::ValidIf{sel, value} => {
+                self.buf.push(7);
+                self.expr(sel);
+                self.expr(value)
+            },
+            Expression::PrimitiveOp(op) => {
+                self.buf.push(8);
+                self.buf.push(op_tag(op));
+                op.sub_exprs().into_iter().for_each(|e| self.expr(e));
+                match op {
+                    Operation::Pad(_, bits) |
+                    Operation::Shl(_, bits) |
+                    Operation::Shr(_, bits) |
+                    Operation::IncPrecision(_, bits) |
+                    Operation::DecPrecision(_, bits)    => self.uint(*bits as u64),
+                    Operation::SetPrecision(_, bits)    => self.int(*bits as i64),
+                    Operation::AsInterval(_, l, u, p)   => {
+                        self.int(*l);
+                        self.int(*u);
+                        self.int(*p as i64)
+                    },
+                    Operation::Cast(_, t)               => self.ground_type(t),
+                    Operation::Bits(_, hi, lo)          => {
+                        self.width(*hi);
+                        self.width(*lo)
+                    },
+                    _                                   => (),
+                }
+            },
+            Expression::Probe{target, kind} => {
+                self.buf.push(9);
+                self.bool(*kind == ProbeKind::ReadWrite);
+                self.expr(target)
+            },
+            Expression::Read(probe) => {
+                self.buf.push(10);
+                self.expr(probe)
+            },
+        }
+    }
+}
+
+
+/// Decoder for circuits
+///
+/// The decoder mirrors the tables of the [Encoder].
+struct Decoder<'a> {
+    data: &'a [u8],
+    modules: Vec<Arc<Module>>,
+    ports: Vec<Arc<Port>>,
+    entities: Vec<Arc<Entity>>,
+    memories: Vec<Arc<simple::Memory>>,
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let (byte, rest) = self.data.split_first().ok_or_else(|| invalid("unexpected end of cache"))?;
+        self.data = rest;
+        Ok(*byte)
+    }
+
+    fn uint(&mut self) -> io::Result<u64> {
+        let mut res = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            res |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(res)
+            }
+        }
+        Err(invalid("malformed integer"))
+    }
+
+    fn int(&mut self) -> io::Result<i64> {
+        let value = self.uint()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    fn small<T: TryFrom<u64>>(&mut self) -> io::Result<T> {
+        T::try_from(self.uint()?).map_err(|_| invalid("integer out of range"))
+    }
+
+    fn small_int<T: TryFrom<i64>>(&mut self) -> io::Result<T> {
+        T::try_from(self.int()?).map_err(|_| invalid("integer out of range"))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        let len = self.small()?;
+        if len > self.data.len() {
+            Err(invalid("unexpected end of cache"))
+        } else {
+            Ok(len)
+        }
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("malformed boolean")),
+        }
+    }
+
+    fn bytes(&mut self) -> io::Result<&[u8]> {
+        let len = self.len()?;
+        let (res, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(res)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        std::str::from_utf8(self.bytes()?).map(ToString::to_string).map_err(|_| invalid("malformed string"))
+    }
+
+    fn str(&mut self) -> io::Result<Arc<str>> {
+        self.string().map(Into::into)
+    }
+
+    fn opt_string(&mut self) -> io::Result<Option<String>> {
+        if self.bool()? {
+            self.string().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn opt_str(&mut self) -> io::Result<Option<Arc<str>>> {
+        self.opt_string().map(|s| s.map(Into::into))
+    }
+
+    fn width(&mut self) -> io::Result<BitWidth> {
+        match self.uint()? {
+            0 => Ok(None),
+            w => u16::try_from(w - 1).map(Some).map_err(|_| invalid("width out of range")),
+        }
+    }
+
+    fn circuit(&mut self) -> io::Result<Circuit> {
+        let info = self.opt_string()?;
+        let version = match self.uint()? {
+            0 => None,
+            1 => {
+                let mut part = || self.uint().and_then(|v| u32::try_from(v).map_err(|_| invalid("version out of range")));
+                Some(Version::new(part()?, part()?, part()?))
+            },
+            _ => return Err(invalid("invalid version tag")),
+        };
+        let count = self.len()?;
+        let layers = (0..count).map(|_| self.layer()).collect::<io::Result<Vec<_>>>()?;
+        let count = self.len()?;
+        let aliases = (0..count).map(|_| {
+            let name = self.str()?;
+            let info = self.opt_string()?;
+            Ok(TypeAlias::new(name, self.r#type()?).with_info(info))
+        }).collect::<io::Result<Vec<_>>>()?;
+        let count = self.len()?;
+        let options = (0..count).map(|_| {
+            let name = self.str()?;
+            let info = self.opt_string()?;
+            let count = self.len()?;
+            let cases = (0..count).map(|_| self.str()).collect::<io::Result<Vec<_>>>()?;
+            Ok(OptionGroup::new(name, cases).with_info(info))
+        }).collect::<io::Result<Vec<_>>>()?;
+        let count = self.len()?;
+        let annotations = (0..count).map(|_| self.annotation()).collect::<io::Result<Vec<_>>>()?;
+        let count = self.len()?;
+        for _ in 0..count {
+            let module = self.module()?;
+            self.modules.push(Arc::new(module));
+        }
+        let top = self.module_ref()?;
+        Ok(Circuit::new(top)
+            .with_modules(self.modules.iter().cloned())
+            .with_info(info)
+            .with_version(version)
+            .with_layers(layers)
+            .with_type_aliases(aliases)
+            .with_option_groups(options)
+            .with_annotations(annotations))
+    }
+
+    fn annotation(&mut self) -> io::Result<Annotation> {
+        let class = self.str()?;
+        let target = self.opt_str()?;
+        let count = self.len()?;
+        (0..count).try_fold(
+            target.into_iter().fold(Annotation::new(class), Annotation::with_raw_target),
+            |a, _| Ok(a.with_field(self.str()?, self.value()?)),
+        )
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        match self.byte()? {
+            0 => Ok(Value::Null),
+            1 => self.bool().map(Value::Bool),
+            2 => self.str().map(Value::Number),
+            3 => self.str().map(Value::String),
+            4 => {
+                let count = self.len()?;
+                (0..count).map(|_| self.value()).collect::<io::Result<_>>().map(Value::Array)
+            },
+            5 => {
+                let count = self.len()?;
+                (0..count).map(|_| Ok((self.str()?, self.value()?))).collect::<io::Result<_>>().map(Value::Object)
+            },
+            _ => Err(invalid("malformed annotation value")),
+        }
+    }
+
+    fn layer(&mut self) -> io::Result<Layer> {
+        let name = self.str()?;
+        let info = self.opt_string()?;
+        let convention = if self.bool()? { Convention::Inline } else { Convention::Bind };
+        let output_dir = self.opt_string()?;
+        let count = self.len()?;
+        let layers = (0..count).map(|_| self.layer()).collect::<io::Result<Vec<_>>>()?;
+        Ok(Layer::new(name, convention).with_output_dir(output_dir).with_layers(layers).with_info(info))
+    }
+
+    fn module(&mut self) -> io::Result<Module> {
+        self.ports.clear();
+        self.entities.clear();
+        self.memories.clear();
+
+        let name = self.str()?;
+        let info = self.opt_string()?;
+        let count = self.len()?;
+        let layers = (0..count).map(|_| self.str()).collect::<io::Result<Vec<_>>>()?;
+        let count = self.len()?;
+        for _ in 0..count {
+            let port = Arc::new(self.port()?);
+            self.ports.push(port);
+        }
+        let kind = match self.byte()? {
+            0 => module::Kind::Regular{stmts: self.stmts()?},
+            1 => {
+                let defname = self.opt_str()?;
+                let params = self.params()?;
+                let count = self.len()?;
+                let resources = (0..count).map(|_| match self.byte()? {
+                    0 => Ok(Resource::Path(self.str()?)),
+                    1 => Ok(Resource::Inline{name: self.str()?, text: self.str()?}),
+                    _ => Err(invalid("malformed resource")),
+                }).collect::<io::Result<_>>()?;
+                module::Kind::External{defname, params, resources}
+            },
+            _ => return Err(invalid("malformed module kind")),
+        };
+        Ok(Module::new(name, self.ports.iter().cloned(), kind).with_enabled_layers(layers).with_info(info))
+    }
+
+    fn module_ref(&mut self) -> io::Result<Arc<Module>> {
+        let index: usize = self.small()?;
+        self.modules.get(index).cloned().ok_or_else(|| invalid("unknown module"))
+    }
+
+    fn port(&mut self) -> io::Result<Port> {
+        let name = self.str()?;
+        let info = self.opt_string()?;
+        let r#type = self.r#type()?;
+        let direction = if self.bool()? { Direction::Output } else { Direction::Input };
+        Ok(Port::new(name, r#type, direction).with_info(info))
+    }
+
+    fn params(&mut self) -> io::Result<HashMap<Arc<str>, ParamValue>> {
+        let count = self.len()?;
+        (0..count).map(|_| {
+            let name = self.str()?;
+            let value = match self.byte()? {
+                0 => ParamValue::Int(self.int()?),
+                1 => {
+                    let mut bits = [0; 8];
+                    bits.iter_mut().try_for_each(|b| self.byte().map(|v| *b = v))?;
+                    ParamValue::Double(f64::from_bits(u64::from_le_bytes(bits)))
+                },
+                2 => ParamValue::String(self.str()?),
+                3 => ParamValue::RawVerilog(self.str()?),
+                _ => return Err(invalid("malformed parameter")),
+            };
+            Ok((name, value))
+        }).collect()
+    }
+
+    fn r#type(&mut self) -> io::Result<Type> {
+        match self.byte()? {
+            0 => self.ground_type().map(Type::GroundType),
+            1 => {
+                let width = self.small()?;
+                Ok(Type::Vector(Arc::new(self.r#type()?), width))
+            },
+            2 => {
+                let count = self.len()?;
+                let fields = (0..count).map(|_| {
+                    let name = self.str()?;
+                    let orientation = if self.bool()? { Orientation::Flipped } else { Orientation::Normal };
+                    Ok(BundleField::new(name, self.r#type()?).with_orientation(orientation))
+                }).collect::<io::Result<Vec<_>>>()?;
+                Ok(fields.into())
+            },
+            3 => {
+                let kind = if self.bool()? { ProbeKind::ReadWrite } else { ProbeKind::Read };
+                Ok(Type::Probe(Arc::new(self.r#type()?), kind))
+            },
+            4 => {
+                let count = self.len()?;
+                let variants = (0..count).map(|_| {
+                    let variant = EnumVariant::new(self.str()?);
+                    if self.bool()? {
+                        Ok(variant.with_data(self.r#type()?))
+                    } else {
+                        Ok(variant)
+                    }
+                }).collect::<io::Result<Vec<_>>>()?;
+                Ok(Type::Enum(variants.into()))
+            },
+            5 => {
+                let name = self.str()?;
+                Ok(Type::Alias(name, Arc::new(self.r#type()?)))
+            },
+            _ => Err(invalid("malformed type")),
+        }
+    }
+
+    fn ground_type(&mut self) -> io::Result<GroundType> {
+        match self.byte()? {
+            0 => self.width().map(GroundType::UInt),
+            1 => self.width().map(GroundType::SInt),
+            2 => {
+                let width = self.width()?;
+                let point = if self.bool()? { Some(self.small_int()?) } else { None };
+                Ok(GroundType::Fixed(width, point))
+            },
+            3 => Ok(GroundType::Clock),
+            4 => Ok(GroundType::Reset(ResetKind::Regular)),
+            5 => Ok(GroundType::Reset(ResetKind::Async)),
+            6 => self.width().map(GroundType::Analog),
+            7 => {
+                let lower = self.bound()?;
+                let upper = self.bound()?;
+                let point = if self.bool()? { Some(self.small_int()?) } else { None };
+                Ok(GroundType::Interval(lower, upper, point))
+            },
+            _ => Err(invalid("malformed ground type")),
+        }
+    }
+
+    fn bound(&mut self) -> io::Result<Option<Bound>> {
+        let tag = self.byte()?;
+        if tag == 0 {
+            return Ok(None)
+        }
+
+        let value = Decimal::new(self.int()?, self.small()?);
+        match tag {
+            1 => Ok(Some(Bound::Closed(value))),
+            2 => Ok(Some(Bound::Open(value))),
+            _ => Err(invalid("malformed interval bound")),
+        }
+    }
+
+    fn stmts(&mut self) -> io::Result<Vec<Statement>> {
+        let count = self.len()?;
+        (0..count).map(|_| self.stmt()).collect()
+    }
+
+    fn stmt(&mut self) -> io::Result<Statement> {
+        let info = self.opt_string()?;
+        let id = if self.bool()? { Some(Id::from_raw(self.uint()?)) } else { None };
+
+        let kind = match self.byte()? {
+            0 => stmt::Kind::Connection{from: self.expr()?, to: self.expr()?},
+            1 => stmt::Kind::PartialConnection{from: self.expr()?, to: self.expr()?},
+            2 => stmt::Kind::Empty,
+            3 => stmt::Kind::Declaration(self.entity()?),
+            4 => stmt::Kind::SimpleMemDecl(self.simple_mem()?),
+            5 => stmt::Kind::Invalidate(self.expr()?),
+            6 => {
+                let count = self.len()?;
+                stmt::Kind::Attach((0..count).map(|_| self.expr()).collect::<io::Result<_>>()?)
+            },
+            7 => stmt::Kind::Conditional{
+                cond: self.expr()?,
+                when: self.stmts()?.into(),
+                r#else: self.stmts()?.into(),
+            },
+            8 => stmt::Kind::Stop{
+                name: self.opt_str()?,
+                clock: self.expr()?,
+                cond: self.expr()?,
+                code: self.int()?,
+            },
+            9 => {
+                let name = self.opt_str()?;
+                let clock = self.expr()?;
+                let cond = self.expr()?;
+                let count = self.len()?;
+                let msg = (0..count).map(|_| match self.byte()? {
+                    0 => self.string().map(PrintElement::Literal),
+                    1 => {
+                        let expr = self.expr()?;
+                        let format = match self.byte()? {
+                            0 => Format::Binary,
+                            1 => Format::Decimal,
+                            2 => Format::Hexadecimal,
+                            3 => Format::Character,
+                            _ => return Err(invalid("malformed format")),
+                        };
+                        Ok(PrintElement::Value(expr, format))
+                    },
+                    _ => Err(invalid("malformed print element")),
+                }).collect::<io::Result<_>>()?;
+                stmt::Kind::Print{name, clock, cond, msg}
+            },
+            10 => stmt::Kind::Verification{
+                name: self.opt_str()?,
+                op: match self.byte()? {
+                    0 => verification::Op::Assert,
+                    1 => verification::Op::Assume,
+                    2 => verification::Op::Cover,
+                    _ => return Err(invalid("malformed verification statement")),
+                },
+                clock: self.expr()?,
+                pred: self.expr()?,
+                en: self.expr()?,
+                msg: self.string()?,
+            },
+            11 => stmt::Kind::Define{from: self.expr()?, to: self.expr()?},
+            12 => {
+                let value = self.expr()?;
+                let count = self.len()?;
+                let arms = (0..count).map(|_| {
+                    let variant = self.str()?;
+                    let binder = if self.bool()? { Some(self.entity()?) } else { None };
+                    Ok(stmt::r#match::Arm::new(variant, binder, self.stmts()?))
+                }).collect::<io::Result<_>>()?;
+                stmt::Kind::Match{value, arms}
+            },
+            13 => stmt::Kind::LayerBlock{layer: self.str()?, stmts: self.stmts()?.into()},
+            _ => return Err(invalid("malformed statement")),
+        };
+        Ok(Statement::from(kind).with_info(info).with_id(id))
+    }
+
+    fn entity(&mut self) -> io::Result<Arc<Entity>> {
+        if !self.bool()? {
+            let index: usize = self.small()?;
+            return self.entities.get(index).cloned().ok_or_else(|| invalid("unknown entity"))
+        }
+
+        let entity = match self.byte()? {
+            0 => if self.bool()? {
+                Entity::Port(Arc::new(self.port()?))
+            } else {
+                let index: usize = self.small()?;
+                Entity::Port(self.ports.get(index).cloned().ok_or_else(|| invalid("unknown port"))?)
+            },
+            1 => Entity::Wire{name: self.str()?, r#type: self.r#type()?},
+            2 => {
+                let name = self.str()?;
+                let r#type = self.r#type()?;
+                let clock = self.expr()?;
+                let reset = if self.bool()? { Some((self.expr()?, self.expr()?)) } else { None };
+                Entity::Register(Register::new(name, r#type, clock).with_optional_reset(reset))
+            },
+            3 => Entity::Node{name: self.str()?, value: self.expr()?},
+            4 => {
+                let name = self.str()?;
+                let r#type = self.r#type()?;
+                let depth = self.uint()?;
+                let count = self.len()?;
+                let ports = (0..count).map(|_| {
+                    let name = self.str()?;
+                    let dir = self.byte().and_then(decode_port_dir)?;
+                    Ok(mem::Port{name, dir})
+                }).collect::<io::Result<Vec<_>>>()?;
+                let read_latency = self.small()?;
+                let write_latency = self.small()?;
+                let ruw = self.byte().and_then(decode_read_under_write)?;
+
+                let mut mem = Memory::new(name, r#type, depth)
+                    .with_read_latency(read_latency)
+                    .with_write_latency(write_latency)
+                    .with_read_under_write(ruw);
+                mem.add_ports(ports);
+                Entity::Memory(mem)
+            },
+            5 => {
+                let name = self.str()?;
+                let mem = self.simple_mem()?;
+                let dir = match self.byte()? {
+                    0 => None,
+                    d => Some(decode_port_dir(d - 1)?),
+                };
+                let addr = self.expr()?;
+                let clock = self.expr()?;
+                Entity::SimpleMemPort(simple::Port::new(name, mem, dir, addr, clock))
+            },
+            6 => {
+                let name = self.str()?;
+                let module = self.module_ref()?;
+                Entity::Instance(Instance::new(name, module).with_parameters(self.params()?))
+            },
+            7 => Entity::Binder{name: self.str()?, r#type: self.r#type()?},
+            8 => {
+                let name = self.str()?;
+                let default = self.module_ref()?;
+                let option = self.str()?;
+                let count = self.len()?;
+                let cases = (0..count)
+                    .map(|_| Ok((self.str()?, self.module_ref()?)))
+                    .collect::<io::Result<Vec<_>>>()?;
+                Entity::InstanceChoice(InstanceChoice::new(name, default, option).with_cases(cases))
+            },
+            _ => return Err(invalid("malformed entity")),
+        };
+
+        let res = Arc::new(entity);
+        self.entities.push(res.clone());
+        Ok(res)
+    }
+
+    fn simple_mem(&mut self) -> io::Result<Arc<simple::Memory>> {
+        if !self.bool()? {
+            let index: usize = self.small()?;
+            return self.memories.get(index).cloned().ok_or_else(|| invalid("unknown memory"))
+        }
+
+        let name = self.str()?;
+        let r#type = self.r#type()?;
+        let kind = match self.byte()? {
+            0 => simple::Kind::Combinatory,
+            1 => simple::Kind::Sequential(None),
+            r => simple::Kind::Sequential(Some(decode_read_under_write(r - 2)?)),
+        };
+        let res = Arc::new(simple::Memory::new(name, r#type, kind));
+        self.memories.push(res.clone());
+        Ok(res)
+    }
+
+    fn expr(&mut self) -> io::Result<Expression<Arc<Entity>>> {
+        let sub = |d: &mut Self| d.expr().map(Arc::new);
+
+        match self.byte()? {
+            0 => {
+                let value = BigUint::from_bytes_le(self.bytes()?);
+                Ok(Expression::UIntLiteral{value, width: self.small()?})
+            },
+            1 => {
+                let sign = if self.bool()? { Sign::Minus } else { Sign::Plus };
+                let value = BigInt::from_bytes_le(sign, self.bytes()?);
+                Ok(Expression::SIntLiteral{value, width: self.small()?})
+            },
+            2 => self.entity().map(Expression::Reference),
+            3 => Ok(Expression::SubField{base: sub(self)?, index: self.str()?}),
+            4 => Ok(Expression::SubIndex{base: sub(self)?, index: self.small()?}),
+            5 => Ok(Expression::SubAccess{base: sub(self)?, index: sub(self)?}),
+            6 => Ok(Expression::Mux{sel: sub(self)?, a: sub(self)?, b: sub(self)?}),
+            7 => Ok(Expression::ValidIf{sel: sub(self)?, value: sub(self)?}),
+            8 => {
+                use Operation as O;
+
+                let op = match self.byte()? {
+                    0   => O::Add(sub(self)?, sub(self)?),
+                    1   => O::Sub(sub(self)?, sub(self)?),
+                    2   => O::Mul(sub(self)?, sub(self)?),
+                    3   => O::Div(sub(self)?, sub(self)?),
+                    4   => O::Rem(sub(self)?, sub(self)?),
+                    5   => O::Lt(sub(self)?, sub(self)?),
+                    6   => O::LEq(sub(self)?, sub(self)?),
+                    7   => O::Gt(sub(self)?, sub(self)?),
+                    8   => O::GEq(sub(self)?, sub(self)?),
+                    9   => O::Eq(sub(self)?, sub(self)?),
+                    10  => O::NEq(sub(self)?, sub(self)?),
+                    11  => O::Pad(sub(self)?, self.small()?),
+                    12  => O::Cast(sub(self)?, self.ground_type()?),
+                    13  => O::Shl(sub(self)?, self.small()?),
+                    14  => O::Shr(sub(self)?, self.small()?),
+                    15  => O::DShl(sub(self)?, sub(self)?),
+                    16  => O::DShr(sub(self)?, sub(self)?),
+                    17  => O::Cvt(sub(self)?),
+                    18  => O::Neg(sub(self)?),
+                    19  => O::Not(sub(self)?),
+                    20  => O::And(sub(self)?, sub(self)?),
+                    21  => O::Or(sub(self)?, sub(self)?),
+                    22  => O::Xor(sub(self)?, sub(self)?),
+                    23  => O::AndReduce(sub(self)?),
+                    24  => O::OrReduce(sub(self)?),
+                    25  => O::XorReduce(sub(self)?),
+                    26  => O::Cat(sub(self)?, sub(self)?),
+                    27  => O::Bits(sub(self)?, self.width()?, self.width()?),
+                    28  => O::IncPrecision(sub(self)?, self.small()?),
+                    29  => O::DecPrecision(sub(self)?, self.small()?),
+                    30  => O::SetPrecision(sub(self)?, self.small_int()?),
+                    31  => O::Wrap(sub(self)?, sub(self)?),
+                    32  => O::Clip(sub(self)?, sub(self)?),
+                    33  => O::Squeeze(sub(self)?, sub(self)?),
+                    34  => O::AsInterval(sub(self)?, self.int()?, self.int()?, self.small_int()?),
+                    _   => return Err(invalid("malformed operation")),
+                };
+                Ok(Expression::PrimitiveOp(op))
+            },
+            9 => {
+                let kind = if self.bool()? { ProbeKind::ReadWrite } else { ProbeKind::Read };
+                Ok(Expression::Probe{target: sub(self)?, kind})
+            },
+            10 => Ok(Expression::Read(sub(self)?)),
+            _ => Err(invalid("malformed expression")),
+        }
+    }
+}
+
+
+/// Retrieve the tag identifying the kind of an operation
+fn op_tag<R: crate::expr::Reference>(op: &Operation<R>) -> u8 {
+    match op {
+        Operation::Add(..)          => 0,
+        Operation::Sub(..)          => 1,
+        Operation::Mul(..)          => 2,
+        Operation::Div(..)          => 3,
+        Operation::Rem(..)          => 4,
+        Operation::Lt(..)           => 5,
+        Operation::LEq(..)          => 6,
+        Operation::Gt(..)           => 7,
+        Operation::GEq(..)          => 8,
+        Operation::Eq(..)           => 9,
+        Operation::NEq(..)          => 10,
+        Operation::Pad(..)          => 11,
+        Operation::Cast(..)         => 12,
+        Operation::Shl(..)          => 13,
+        Operation::Shr(..)          => 14,
+        Operation::DShl(..)         => 15,
+        Operation::DShr(..)         => 16,
+        Operation::Cvt(..)          => 17,
+        Operation::Neg(..)          => 18,
+        Operation::Not(..)          => 19,
+        Operation::And(..)          => 20,
+        Operation::Or(..)           => 21,
+        Operation::Xor(..)          => 22,
+        Operation::AndReduce(..)    => 23,
+        Operation::OrReduce(..)     => 24,
+        Operation::XorReduce(..)    => 25,
+        Operation::Cat(..)          => 26,
+        Operation::Bits(..)         => 27,
+        Operation::IncPrecision(..) => 28,
+        Operation::DecPrecision(..) => 29,
+        Operation::SetPrecision(..) => 30,
+        Operation::Wrap(..)         => 31,
+        Operation::Clip(..)         => 32,
+        Operation::Squeeze(..)      => 33,
+        Operation::AsInterval(..)   => 34,
+    }
+}
+
+
+fn port_dir(dir: PortDir) -> u8 {
+    match dir {
+        PortDir::Read       => 0,
+        PortDir::Write      => 1,
+        PortDir::ReadWrite  => 2,
+    }
+}
+
+
+fn decode_port_dir(tag: u8) -> io::Result<PortDir> {
+    match tag {
+        0 => Ok(PortDir::Read),
+        1 => Ok(PortDir::Write),
+        2 => Ok(PortDir::ReadWrite),
+        _ => Err(invalid("malformed port direction")),
+    }
+}
+
+
+fn read_under_write(ruw: ReadUnderWrite) -> u8 {
+    match ruw {
+        ReadUnderWrite::Old         => 0,
+        ReadUnderWrite::New         => 1,
+        ReadUnderWrite::Undefined   => 2,
+    }
+}
+
+
+fn decode_read_under_write(tag: u8) -> io::Result<ReadUnderWrite> {
+    match tag {
+        0 => Ok(ReadUnderWrite::Old),
+        1 => Ok(ReadUnderWrite::New),
+        2 => Ok(ReadUnderWrite::Undefined),
+        _ => Err(invalid("malformed read-under-write behaviour")),
+    }
+}
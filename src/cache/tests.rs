@@ -0,0 +1,50 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to the binary cache format
+
+use quickcheck::TestResult;
+
+use crate::annotations::Annotation;
+use crate::circuit::Circuit;
+
+
+#[quickcheck]
+fn cache_roundtrip(original: Circuit) -> std::io::Result<TestResult> {
+    let mut cache = Vec::new();
+    original.save_cache(&mut cache)?;
+    let loaded = Circuit::load_cache(cache.as_slice())?;
+    Ok(TestResult::from_bool(loaded == original))
+}
+
+
+#[quickcheck]
+fn cache_roundtrip_annotations(original: Circuit, annotations: Vec<Annotation>) -> std::io::Result<bool> {
+    let original = original.with_annotations(annotations);
+    let mut cache = Vec::new();
+    original.save_cache(&mut cache)?;
+    Ok(Circuit::load_cache(cache.as_slice())? == original)
+}
+
+
+#[quickcheck]
+fn cache_truncated(original: Circuit, cut: usize) -> bool {
+    let mut cache = Vec::new();
+    original.save_cache(&mut cache).expect("Could not write cache");
+    cache.truncate(cut % cache.len());
+    Circuit::load_cache(cache.as_slice()).is_err()
+}
+
+
+#[test]
+fn cache_version_mismatch() {
+    let circuit = crate::circuit::parse("circuit Top:\n  module Top:\n    input a: UInt<1>\n").expect("Parsing failed");
+    let mut cache = Vec::new();
+    circuit.save_cache(&mut cache).expect("Could not write cache");
+
+    let offset = super::MAGIC.len();
+    cache[offset..offset + 4].copy_from_slice(&(super::VERSION + 1).to_le_bytes());
+    let err = Circuit::load_cache(cache.as_slice()).expect_err("Loaded cache of wrong version");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    assert!(Circuit::load_cache(b"FIRRTL".as_ref()).is_err());
+}
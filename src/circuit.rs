@@ -6,29 +6,58 @@
 //! implements [std::fmt::Display], making formatting straght-forward. There are
 //! multiple APIs for parsing a `Circuit` and therefore an entire AST:
 //!  * [Circuit::from_read] allows reading an AST, e.g. from a file.
+//!  * [Circuit::from_buf_read] also allows reading an AST from a file, but
+//!    reads the input incrementally rather than buffering it as a whole.
 //!  * The [parse] function allows reading an AST from a buffer.
 //!  * A [ModuleConsumer] built via the [consumer] function also allows reading
 //!    an AST from a buffer, but allows accessing every single parsed module
-//!    while parsing.
+//!    while parsing. The [consumer_from_read] function creates one reading
+//!    from a [std::io::BufRead] incrementally.
+//!
+//! For tools only requiring the hierarchy and port lists of a circuit, the
+//! [interfaces] function allows extracting the interface of every module
+//! without parsing any statements. [ParseOptions] allow configuring the
+//! parser, e.g. imposing [Limits] on the AST when parsing untrusted input.
+//! Tools which need to tolerate incomplete or malformed input, e.g. editors,
+//! may use [ParseOptions::parse_recovering], which skips malformed modules and
+//! statements, and [Circuit::with_module_reparsed] for reparsing a single
+//! modified module.
 
 pub(crate) mod parsers;
 
+pub mod layer;
+pub mod option;
+
 #[cfg(test)]
-mod tests;
+pub mod tests;
 
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
+use crate::annotations::Annotation;
 use crate::error::ParseError;
+use crate::hierarchy::InstanceNode;
 use crate::indentation;
-use crate::info::{self, WithInfo};
-use crate::module::Module;
+use crate::info::WithInfo;
+use crate::manifest::Manifest;
+use crate::module::{self, Module};
+use crate::module::external::{DefnameConflict, Interface};
 use crate::named::Named;
+use crate::stmt::extension::Dialect;
+use crate::target::{Referenced, Target};
+use crate::types::TypeAlias;
 
-pub use parsers::{circuit as parse, consumer};
+pub use parsers::{
+    circuit as parse,
+    circuits as parse_all,
+    consumer,
+    consumer_from_read,
+    interfaces,
+};
 
 
 /// FIRRTL circuit
@@ -36,16 +65,138 @@ pub use parsers::{circuit as parse, consumer};
 /// A `Circuit` is the top level construct in FIRRTL. A circuit is defined by
 /// its "top module", which may contain instantiations any number of modules
 /// which need to be part of the same circuit.
+///
+/// In addition, a circuit retains all modules defined in it in the order of
+/// their definition, including modules not instantiated by the top module.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Circuit {
     top: Arc<Module>,
+    modules: Vec<Arc<Module>>,
     info: Option<String>,
+    version: Option<Version>,
+    annotations: Vec<Annotation>,
+    layers: Vec<layer::Layer>,
+    aliases: Vec<TypeAlias>,
+    options: Vec<option::OptionGroup>,
 }
 
 impl Circuit {
     /// Create a new circuit
+    ///
+    /// The circuit will define the top module and all modules instantiated
+    /// directly or indirectly by it, each preceded by the modules it
+    /// instantiates.
     pub fn new(top_module: Arc<Module>) -> Self {
-        Self {top: top_module, info: Default::default()}
+        Self {
+            modules: definition_order(&top_module, None),
+            top: top_module,
+            info: Default::default(),
+            version: Default::default(),
+            annotations: Default::default(),
+            layers: Default::default(),
+            aliases: Default::default(),
+            options: Default::default(),
+        }
+    }
+
+    /// Create a copy of this circuit defining the given modules
+    ///
+    /// The circuit will define the given `modules` in the given order, in
+    /// addition to the modules instantiated by the top module. Since a module
+    /// needs to be defined before it is instantiated, modules are moved in
+    /// front of the first module instantiating them if necessary. If multiple
+    /// modules share a name, only the first one is retained.
+    pub fn with_modules(self, modules: impl IntoIterator<Item = Arc<Module>>) -> Self {
+        Self {modules: definition_order(&self.top, modules), ..self}
+    }
+
+    /// Create a copy of this circuit with the given FIRRTL version
+    pub fn with_version(self, version: impl Into<Option<Version>>) -> Self {
+        Self {version: version.into(), ..self}
+    }
+
+    /// Create a copy of this circuit with the given annotations attached
+    ///
+    /// The annotations replace any annotations previously attached.
+    pub fn with_annotations(self, annotations: impl IntoIterator<Item = Annotation>) -> Self {
+        Self {annotations: annotations.into_iter().collect(), ..self}
+    }
+
+    /// Attach an annotation to this circuit
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        self.annotations.push(annotation)
+    }
+
+    /// Retrieve the annotations attached to this circuit
+    ///
+    /// Annotations are not part of FIRRTL's textual representation. Hence,
+    /// they are neither parsed nor formatted alongside the circuit.
+    pub fn annotations(&self) -> &[Annotation] {
+        self.annotations.as_ref()
+    }
+
+    /// Retrieve the annotations attached to this circuit referring to a target
+    ///
+    /// See [Annotation::refers_to] for details.
+    pub fn annotations_for<'a>(&'a self, target: &'a Target) -> impl Iterator<Item = &'a Annotation> {
+        self.annotations.iter().filter(move |a| a.refers_to(target))
+    }
+
+    /// Retrieve the FIRRTL version declared for this circuit, if any
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Create a copy of this circuit with the given layers declared
+    ///
+    /// The layers replace any layers previously declared.
+    pub fn with_layers(self, layers: impl IntoIterator<Item = layer::Layer>) -> Self {
+        Self {layers: layers.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the layers declared at the top level of this circuit
+    pub fn layers(&self) -> &[layer::Layer] {
+        self.layers.as_ref()
+    }
+
+    /// Retrieve the layer with the given path, e.g. `A.B`
+    pub fn layer(&self, path: &str) -> Option<&layer::Layer> {
+        layer::find(self.layers(), path)
+    }
+
+    /// Create a copy of this circuit with the given type aliases declared
+    ///
+    /// The aliases replace any aliases previously declared. An alias may only
+    /// refer to aliases preceding it.
+    pub fn with_type_aliases(self, aliases: impl IntoIterator<Item = TypeAlias>) -> Self {
+        Self {aliases: aliases.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the type aliases declared in this circuit
+    pub fn type_aliases(&self) -> &[TypeAlias] {
+        self.aliases.as_ref()
+    }
+
+    /// Retrieve the type alias with the given name
+    pub fn type_alias(&self, name: &str) -> Option<&TypeAlias> {
+        self.aliases.iter().find(|a| a.name().as_ref() == name)
+    }
+
+    /// Create a copy of this circuit with the given option groups declared
+    ///
+    /// The option groups replace any option groups previously declared.
+    pub fn with_option_groups(self, options: impl IntoIterator<Item = option::OptionGroup>) -> Self {
+        Self {options: options.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the option groups declared in this circuit
+    pub fn option_groups(&self) -> &[option::OptionGroup] {
+        self.options.as_ref()
+    }
+
+    /// Retrieve the option group with the given name
+    pub fn option_group(&self, name: &str) -> Option<&option::OptionGroup> {
+        self.options.iter().find(|o| o.name().as_ref() == name)
     }
 
     /// Get the top level module
@@ -53,6 +204,397 @@ impl Circuit {
         &self.top
     }
 
+    /// Create a copy of this circuit with the given top module
+    ///
+    /// The new top module and all modules it instantiates are defined in
+    /// addition to the modules already defined, including the previous top
+    /// module. A module already defined with the name of the new top module is
+    /// replaced by it in place.
+    pub fn with_top_module(self, top: Arc<Module>) -> Self {
+        let defined: Vec<_> = self.modules
+            .iter()
+            .map(|m| if m.name() == top.name() { top.clone() } else { m.clone() })
+            .collect();
+        Self {modules: definition_order(&top, defined), top, ..self}
+    }
+
+    /// Create a copy of this circuit with the defined module of the given name as top
+    ///
+    /// This function returns `None` if no module of the given name is
+    /// [defined](Self::defined_modules) in this circuit. The previous top
+    /// module remains defined.
+    pub fn with_top(self, name: impl AsRef<str>) -> Option<Self> {
+        let top = self.modules.iter().find(|m| m.name_ref() == name.as_ref())?.clone();
+        Some(self.with_top_module(top))
+    }
+
+    /// Define an additional module in this circuit
+    ///
+    /// The module is defined after the modules already defined, preceded by
+    /// all modules it instantiates which are not defined yet. If a module of
+    /// the same name is already defined, the circuit is left unchanged.
+    pub fn add_module(&mut self, module: Arc<Module>) {
+        let defined = std::mem::take(&mut self.modules);
+        self.modules = definition_order(&self.top, defined.into_iter().chain(std::iter::once(module)));
+    }
+
+    /// Retrieve all modules in this circuit
+    ///
+    /// This function yields the top module and all modules instantiated
+    /// directly or indirectly by it. Each module is yielded only once, before
+    /// any of the modules it instantiates.
+    pub fn modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        // A module is pushed to `res` only after all modules it instantiates,
+        // i.e. in post-order. Reversing that order yields a topological one.
+        let mut seen = HashSet::new();
+        let mut stack = vec![(self.top_module(), false)];
+        let mut res = Vec::new();
+        while let Some((module, finished)) = stack.pop() {
+            if finished {
+                res.push(module);
+            } else if seen.insert(module.name_ref()) {
+                stack.push((module, true));
+                stack.extend(module.referenced_modules().filter(|r| !seen.contains(r.name_ref())).map(|r| (r, false)));
+            }
+        }
+        res.into_iter().rev()
+    }
+
+    /// Retrieve all modules defined in this circuit
+    ///
+    /// This function yields the modules in the order of their definition,
+    /// e.g. as parsed. Contrary to [Circuit::modules], the modules yielded
+    /// include modules not instantiated by the top module.
+    pub fn defined_modules(&self) -> &[Arc<Module>] {
+        self.modules.as_ref()
+    }
+
+    /// Retrieve all modules not instantiated by the top module
+    ///
+    /// This function yields all [defined](Circuit::defined_modules) modules
+    /// which are neither the top module nor instantiated directly or
+    /// indirectly by it, in the order of their definition.
+    pub fn unreferenced_modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        let referenced: HashSet<_> = self.modules().map(|m| m.name_ref()).collect();
+        self.modules.iter().filter(move |m| !referenced.contains(m.name_ref()))
+    }
+
+    /// Create a copy of this circuit without unreferenced modules
+    ///
+    /// The resulting circuit only defines the top module and the modules
+    /// instantiated directly or indirectly by it, in the original order.
+    pub fn without_unreferenced_modules(&self) -> Self {
+        let unreferenced: HashSet<_> = self.unreferenced_modules().map(|m| m.name_ref()).collect();
+        let modules: Vec<_> = self.modules.iter().filter(|m| !unreferenced.contains(m.name_ref())).cloned().collect();
+        Self {modules, top: self.top.clone(), ..self.clone()}
+    }
+
+    /// Retrieve all modules in this circuit in a canonical order
+    ///
+    /// The modules are ordered topologically by instantiation, i.e. every
+    /// module is preceded by all modules it instantiates. Among modules not
+    /// depending on each other, modules are ordered alphabetically by name.
+    /// Hence, the order depends only on the structure of the circuit.
+    pub fn modules_sorted(&self) -> Vec<&Arc<Module>> {
+        use std::collections::BTreeMap;
+
+        let modules: BTreeMap<_, _> = self.modules().map(|m| (m.name_ref(), m)).collect();
+        let mut pending: HashMap<_, HashSet<_>> = modules
+            .iter()
+            .map(|(n, m)| (*n, m.referenced_modules().map(|r| r.name_ref()).collect()))
+            .collect();
+
+        let mut res = Vec::with_capacity(modules.len());
+        while let Some(name) = modules.keys().find(|n| pending.get(*n).map(HashSet::is_empty).unwrap_or(false)) {
+            pending.remove(name);
+            pending.values_mut().for_each(|d| {
+                d.remove(name);
+            });
+            res.push(modules[name]);
+        }
+        res
+    }
+
+    /// Create a copy of this circuit with modules substituted
+    ///
+    /// This function applies `f` to every module in the circuit. If `f`
+    /// yields a module, that module replaces the original one throughout the
+    /// circuit. Modules are visited bottom-up, i.e. `f` will see a module only
+    /// after all modules it instantiates were substituted. Each module is
+    /// visited only once. Modules not instantiated by the top module are
+    /// visited as well.
+    pub fn substitute_modules(&self, mut f: impl FnMut(&Arc<Module>) -> Option<Arc<Module>>) -> Self {
+        fn subst(
+            module: &Arc<Module>,
+            done: &mut HashMap<Arc<str>, Arc<Module>>,
+            f: &mut dyn FnMut(&Arc<Module>) -> Option<Arc<Module>>,
+        ) -> Arc<Module> {
+            if let Some(res) = done.get(module.name()) {
+                return res.clone()
+            }
+
+            let subs: Vec<_> = module.referenced_modules().map(|m| subst(m, done, f)).collect();
+            let changed = module.referenced_modules().zip(subs.iter()).any(|(m, s)| !Arc::ptr_eq(m, s));
+            let rebuilt = if changed {
+                Arc::new(module.with_substituted_instances(|m| done.get(m.name()).cloned()))
+            } else {
+                module.clone()
+            };
+
+            let res = f(&rebuilt).unwrap_or(rebuilt);
+            done.insert(module.name().clone(), res.clone());
+            res
+        }
+
+        let mut done = Default::default();
+        let modules: Vec<_> = self.modules.iter().map(|m| subst(m, &mut done, &mut f)).collect();
+        let top = subst(self.top_module(), &mut done, &mut f);
+        Self {modules: definition_order(&top, modules), top, ..self.clone()}
+    }
+
+    /// Create a copy of this circuit with a module modified
+    ///
+    /// This function applies `f` to a copy of the module with the given name
+    /// and substitutes the module throughout the circuit. Thus, instances of
+    /// the module reflect changes such as added or removed ports. If `f` fails,
+    /// this function returns the error.
+    pub fn with_module_modified<E>(
+        &self,
+        name: impl AsRef<str>,
+        f: impl FnOnce(&mut Module) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        let mut f = Some(f);
+        let mut res = Ok(());
+        let circuit = self.substitute_modules(|m| if m.name_ref() == name.as_ref() {
+            let mut m = m.as_ref().clone();
+            res = f.take().map(|f| f(&mut m)).unwrap_or(Ok(()));
+            Some(Arc::new(m))
+        } else {
+            None
+        });
+        res.map(|_| circuit)
+    }
+
+    /// Create a copy of this circuit with a module reparsed from the given text
+    ///
+    /// The `text` is expected to hold exactly one module definition, indented
+    /// as within a circuit. The module is parsed with the modules of this
+    /// circuit available for instantiation and replaces the module of the same
+    /// name throughout the circuit. Thus, instances in dependent modules will
+    /// refer to the reparsed module. Other modules are not reparsed. The text
+    /// is parsed in the grammar of this circuit's [version](Self::version).
+    ///
+    /// This function fails if the text does not contain exactly one module,
+    /// if the circuit does not contain a module of the same name or if the
+    /// reparsed module would instantiate itself, directly or indirectly.
+    pub fn with_module_reparsed(&self, text: &str) -> Result<Self, ParseError> {
+        use transiter::IntoTransIter;
+
+        let mut modules = module::Modules::new(text)
+            .with_version(self.version)
+            .with_type_aliases(self.type_aliases());
+        self.defined_modules().iter().for_each(|m| modules.add_module(m.clone()));
+        let reparsed = modules.next().ok_or_else(|| "No module found".to_owned())??;
+        if modules.next().is_some() {
+            return Err("Expected exactly one module".to_owned().into())
+        }
+
+        let name = reparsed.name().clone();
+        if !self.defined_modules().iter().any(|m| m.name() == &name) {
+            return Err(format!("Unknown module {}", name).into())
+        }
+        let recursive = reparsed
+            .referenced_modules()
+            .flat_map(|m| m.trans_iter_with(|m| m.referenced_modules()))
+            .any(|m| m.name() == &name);
+        if recursive {
+            return Err(format!("Module {} instantiates itself", name).into())
+        }
+
+        Ok(self.substitute_modules(|m| if m.name() == &name { Some(reparsed.clone()) } else { None }))
+    }
+
+    /// Create a copy of this circuit with a module replaced by a stub
+    ///
+    /// The module with the given name is replaced by its
+    /// [stub](Module::stub) throughout the circuit, preserving its interface.
+    /// Modules instantiated by the original module remain defined in the
+    /// circuit. If the circuit does not define a module with the given name,
+    /// this function returns `None`.
+    pub fn stub_module(&self, name: impl AsRef<str>, policy: module::StubPolicy) -> Option<Self> {
+        if !self.defined_modules().iter().any(|m| m.name_ref() == name.as_ref()) {
+            return None
+        }
+        Some(self.substitute_modules(|m| if m.name_ref() == name.as_ref() {
+            Some(Arc::new(m.stub(policy)))
+        } else {
+            None
+        }))
+    }
+
+    /// Retrieve all external modules in this circuit
+    pub fn external_modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        self.modules().filter(|m| m.defname().is_some())
+    }
+
+    /// Retrieve all external modules refering to the given definition
+    pub fn modules_with_defname<'a>(&'a self, defname: &'a str) -> impl Iterator<Item = &'a Arc<Module>> + 'a {
+        self.modules().filter(move |m| m.defname().map(|n| n.as_ref() == defname).unwrap_or(false))
+    }
+
+    /// Create a copy of this circuit with a `defname` renamed
+    ///
+    /// All external modules refering to the definition `from` will refer to the
+    /// definition `to` in the resulting circuit.
+    pub fn with_defname_renamed(&self, from: impl AsRef<str>, to: impl Into<Arc<str>>) -> Self {
+        let to = to.into();
+        self.substitute_modules(|m| if m.defname().map(|n| n.as_ref() == from.as_ref()).unwrap_or(false) {
+            let mut res = m.as_ref().clone();
+            if let module::Kind::External{defname, ..} = res.kind_mut() {
+                *defname = Some(to.clone())
+            }
+            Some(Arc::new(res))
+        } else {
+            None
+        })
+    }
+
+    /// Retrieve all conflicts between external modules
+    ///
+    /// This function reports groups of external modules which refer to the
+    /// same definition but don't agree on the ports.
+    pub fn defname_conflicts(&self) -> Vec<DefnameConflict> {
+        DefnameConflict::find(self.modules().map(AsRef::as_ref))
+    }
+
+    /// Retrieve a summary of all external interfaces of this circuit
+    pub fn external_interfaces(&self) -> Vec<Interface> {
+        self.modules().filter_map(|m| Interface::of(m)).collect()
+    }
+
+    /// Create a machine-readable description of the top module's ports
+    ///
+    /// See [Manifest] for details.
+    pub fn interface_manifest(&self) -> Manifest {
+        Manifest::of(self.top_module())
+    }
+
+    /// Create annotations for all external module resources
+    ///
+    /// This function returns a JSON array containing an annotation for every
+    /// [Resource](module::external::Resource) associated with any external
+    /// module in this circuit.
+    pub fn resource_annotations(&self) -> String {
+        use crate::display::CommaSeparated;
+
+        let top = self.top_module().name_ref();
+        let annos: Vec<_> = self
+            .external_modules()
+            .flat_map(|m| m.resources().iter().map(move |r| r.annotation(top, m.name_ref())))
+            .collect();
+        format!("[{}]", CommaSeparated::from(annos.iter()))
+    }
+
+    /// Retrieve a module in this circuit by its name
+    ///
+    /// This function searches the top module and all modules instantiated
+    /// directly or indirectly by it.
+    pub fn module_by_name(&self, name: impl AsRef<str>) -> Option<&Arc<Module>> {
+        use transiter::IntoTransIter;
+
+        self.top_module()
+            .trans_iter_with(|m| m.referenced_modules())
+            .find(|m| m.name_ref() == name.as_ref())
+    }
+
+    /// Resolve a [Target] within this circuit
+    ///
+    /// This function returns the item the given `target` refers to or `None`
+    /// if there is no such item in this circuit. If the target names a
+    /// circuit, it has to match the name of this circuit's top module.
+    pub fn resolve_target(&self, target: &Target) -> Option<Referenced<'_>> {
+        if target.circuit().map(|c| c != self.top_module().name()).unwrap_or(false) {
+            return None
+        }
+
+        self.module_by_name(target.path().root()).and_then(|m| target.resolve_from(m))
+    }
+
+    /// Format this circuit in its canonical textual form
+    ///
+    /// The canonical form consists of the circuit's header, layers and option
+    /// groups followed by the [canonical form](Module::to_canonical_string) of
+    /// the top module and every module instantiated by it, in the [canonical
+    /// order](Self::modules_sorted). Unreferenced modules and info attributes
+    /// are omitted. Like the canonical form of modules, the canonical form of
+    /// a circuit will not change between versions of this library.
+    pub fn to_canonical_string(&self) -> String {
+        use fmt::Write;
+
+        use indentation::DisplayIndented;
+
+        let mut res = String::new();
+        if let Some(version) = self.version() {
+            writeln!(res, "FIRRTL version {}", version).expect("Formatting into a String cannot fail");
+        }
+        writeln!(res, "circuit {}:", self.top_module().name()).expect("Formatting into a String cannot fail");
+        let mut indent = indentation::Indentation::root().sub();
+        self.layers
+            .iter()
+            .try_for_each(|l| l.with_info_mapped(&mut |_| None).fmt(&mut indent, &mut res))
+            .expect("Formatting into a String cannot fail");
+        self.options
+            .iter()
+            .try_for_each(|o| o.clone().with_info(None).fmt(&mut indent, &mut res))
+            .expect("Formatting into a String cannot fail");
+        self.modules_sorted()
+            .into_iter()
+            .try_for_each(|m| m.fmt_canonical(&mut indent, self.version(), &mut res))
+            .expect("Formatting into a String cannot fail");
+        res
+    }
+
+    /// Create a build manifest for this circuit
+    ///
+    /// The manifest lists the [content hash](Module::content_hash) of every
+    /// module defined in this circuit, in the order of definition.
+    pub fn manifest(&self) -> BuildManifest {
+        BuildManifest {
+            entries: self.defined_modules().iter().map(|m| (m.name().clone(), m.content_hash())).collect(),
+        }
+    }
+
+    /// Create a copy of this circuit with all info attributes mapped
+    ///
+    /// This function applies `f` to the info attributes of the circuit and
+    /// of all layers, type aliases, option groups, modules, ports and statements
+    /// within it
+    /// and replaces the attributes with the result.
+    pub fn with_info_mapped(&self, mut f: impl FnMut(Option<&str>) -> Option<String>) -> Self {
+        let info = f(self.info());
+        let layers: Vec<_> = self.layers.iter().map(|l| l.with_info_mapped(&mut f)).collect();
+        let aliases: Vec<_> = self.aliases.iter().map(|a| a.clone().with_info(f(a.info()))).collect();
+        let options: Vec<_> = self.options.iter().map(|o| o.clone().with_info(f(o.info()))).collect();
+        self.substitute_modules(|m| Some(Arc::new(m.with_info_mapped(&mut f))))
+            .with_info(info)
+            .with_layers(layers)
+            .with_type_aliases(aliases)
+            .with_option_groups(options)
+    }
+
+    /// Create a copy of this circuit with all info attributes removed
+    pub fn strip_info(&self) -> Self {
+        self.with_info_mapped(|_| None)
+    }
+
+    /// Elaborate the instance hierarchy of this circuit
+    ///
+    /// This function returns a tree of all distinct module instances, rooted
+    /// at the top module.
+    pub fn elaborate(&self) -> InstanceNode {
+        InstanceNode::elaborate(self.top_module().clone())
+    }
+
     /// Parse a circuit from an object implementing Read
     ///
     /// This function parses a circuit from the given `Read`, e.g. a `File`.
@@ -60,12 +602,38 @@ impl Circuit {
     /// # Note
     ///
     /// This function reads the entire source into a separate buffer in memory.
-    /// Consider using [parse] if the source is in memory already.
+    /// Consider using [parse] if the source is in memory already or
+    /// [from_buf_read](Self::from_buf_read) for large sources.
     pub fn from_read(mut read: impl std::io::Read) -> Result<Self, ParseError> {
         let mut buf = Default::default();
         read.read_to_string(&mut buf)?;
         parse(buf.as_ref())
     }
+
+    /// Parse a circuit from an object implementing BufRead
+    ///
+    /// This function parses a circuit from the given `BufRead`, e.g. a
+    /// `BufReader` wrapping a `File`. Contrary to
+    /// [from_read](Self::from_read), the source is read incrementally. Only
+    /// the lines of a single module are held in memory at any time.
+    pub fn from_buf_read(read: impl std::io::BufRead) -> Result<Self, ParseError> {
+        consumer_from_read(read)?.into_circuit()
+    }
+
+    /// Write a binary cache of this circuit
+    ///
+    /// See the [cache](crate::cache) module for details.
+    pub fn save_cache(&self, write: impl std::io::Write) -> std::io::Result<()> {
+        crate::cache::write(self, write)
+    }
+
+    /// Load a circuit from a binary cache
+    ///
+    /// See the [cache](crate::cache) module for details. This function fails
+    /// if the cache was written by a different version of the format.
+    pub fn load_cache(read: impl std::io::Read) -> std::io::Result<Self> {
+        crate::cache::read(read)
+    }
 }
 
 impl WithInfo for Circuit {
@@ -80,40 +648,358 @@ impl WithInfo for Circuit {
 
 impl fmt::Display for Circuit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use std::collections::HashSet;
-        use indentation::{DisplayIndented, Indentation};
-
-        // Format a module and all its dependencies, if it wasn't yet formatted
-        fn fmt_module<'a>(
-            done: &mut HashSet<&'a str>,
-            indent: &mut Indentation,
-            module: &'a Module,
-            f: &mut fmt::Formatter<'_>,
-        ) -> fmt::Result {
-            if done.insert(module.name()) {
-                module.referenced_modules().try_for_each(|m| fmt_module(done, indent, m, f))?;
-                module.fmt(indent, f)
-            } else {
-                Ok(())
-            }
-        }
+        use crate::emit::{Emitter, FirrtlEmitter};
 
-        let mut done = Default::default();
+        FirrtlEmitter::new(f).circuit(self)
+    }
+}
 
-        writeln!(f, "circuit {}:{}", self.top_module().name(), info::Info::of(self))?;
-        let mut indent = indentation::Indentation::root().sub();
-        fmt_module(&mut done, &mut indent, self.top_module(), f)
+/// Order modules for their definition
+///
+/// The modules are ordered as given by `defined`, followed by the `top`
+/// module. Every module is preceded by the modules it instantiates, which are
+/// included even if they are not part of `defined`. Each name occurs only once.
+fn definition_order(top: &Arc<Module>, defined: impl IntoIterator<Item = Arc<Module>>) -> Vec<Arc<Module>> {
+    fn add(module: &Arc<Module>, done: &mut HashSet<Arc<str>>, res: &mut Vec<Arc<Module>>) {
+        if done.insert(module.name().clone()) {
+            module.referenced_modules().for_each(|m| add(m, done, res));
+            res.push(module.clone());
+        }
     }
+
+    let mut done = Default::default();
+    let mut res = Vec::new();
+    defined.into_iter().chain(std::iter::once(top.clone())).for_each(|m| add(&m, &mut done, &mut res));
+    res
 }
 
-#[cfg(test)]
+
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Circuit {
     fn arbitrary(g: &mut Gen) -> Self {
-        Self::new(Arbitrary::arbitrary(g))
+        // Every instantiation gets a module of its own and module names need
+        // to be unique within a circuit. Hence, we rename modules bottom-up.
+        fn uniquely_named(module: &Arc<Module>, names: &mut HashSet<Arc<str>>) -> Arc<Module> {
+            let module = module.with_substituted_instances(|m| Some(uniquely_named(m, names)));
+            let name = std::iter::once(module.name().clone())
+                .chain((0..).map(|n| format!("{}_{}", module.name(), n).into()))
+                .find(|n| names.insert(Arc::clone(n)))
+                .expect("Could not find unique name");
+            Arc::new(Module::new(name, module.ports().cloned(), module.kind().clone()))
+        }
+
+        let top = uniquely_named(&Arbitrary::arbitrary(g), &mut Default::default());
+        Self::new(top).with_version(Option::<Version>::arbitrary(g))
     }
 
     fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
-        Box::new(self.top.shrink().map(Self::new))
+        use transiter::IntoTransIter;
+
+        let unique = |c: &Self| {
+            let mut names = HashSet::new();
+            c.top_module().trans_iter_with(|m| m.referenced_modules()).all(|m| names.insert(m.name().clone()))
+        };
+        Box::new(self.top.shrink().map(Self::new).filter(unique))
+    }
+}
+
+
+/// Options for parsing a [Circuit]
+///
+/// By default, no [Limits] are imposed, bundles with duplicate field names are
+/// accepted, no custom statements or parameter assignments of instances are
+/// recognized and a `;` always starts a comment. [parse] is equivalent to
+/// parsing with the default options.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    limits: Limits,
+    unique_fields: bool,
+    separated_statements: bool,
+    instance_parameters: bool,
+    dialects: Vec<Arc<dyn Dialect>>,
+}
+
+impl ParseOptions {
+    /// Create new default options
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Impose the given [Limits] on the parsed circuit
+    ///
+    /// Parsing fails if the input exceeds any of the `limits`.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self {limits, ..self}
+    }
+
+    /// Set whether to reject bundles with duplicate field names
+    ///
+    /// See [Modules::with_unique_fields](module::parsers::Modules::with_unique_fields)
+    /// for details.
+    pub fn with_unique_fields(self, unique: bool) -> Self {
+        Self {unique_fields: unique, ..self}
+    }
+
+    /// Set whether to recognize multiple statements on a single line
+    ///
+    /// See [Modules::with_separated_statements](module::parsers::Modules::with_separated_statements)
+    /// for details.
+    pub fn with_separated_statements(self, separated: bool) -> Self {
+        Self {separated_statements: separated, ..self}
+    }
+
+    /// Set whether to recognize parameter assignments of instances
+    ///
+    /// See [Modules::with_instance_parameters](module::parsers::Modules::with_instance_parameters)
+    /// for details.
+    pub fn with_instance_parameters(self, parameters: bool) -> Self {
+        Self {instance_parameters: parameters, ..self}
+    }
+
+    /// Set the [Dialect]s providing custom statements
+    ///
+    /// Statements not recognized otherwise are parsed by the first of the
+    /// `dialects` providing the statement's keyword.
+    pub fn with_dialects(self, dialects: impl IntoIterator<Item = Arc<dyn Dialect>>) -> Self {
+        Self {dialects: dialects.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the [Limits] imposed on the parsed circuit
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Check whether bundles with duplicate field names are rejected
+    pub fn unique_fields(&self) -> bool {
+        self.unique_fields
+    }
+
+    /// Check whether multiple statements on a single line are recognized
+    pub fn separated_statements(&self) -> bool {
+        self.separated_statements
+    }
+
+    /// Check whether parameter assignments of instances are recognized
+    pub fn instance_parameters(&self) -> bool {
+        self.instance_parameters
+    }
+
+    /// Retrieve the [Dialect]s providing custom statements
+    pub fn dialects(&self) -> &[Arc<dyn Dialect>] {
+        self.dialects.as_ref()
+    }
+
+    /// Parse a [Circuit] with these options
+    pub fn parse(&self, input: &str) -> Result<Circuit, ParseError> {
+        parsers::circuit_with_options(input, self)
+    }
+
+    /// Parse a [Circuit] with these options, recovering from malformed input
+    ///
+    /// Malformed modules and statements are skipped. The function returns the
+    /// [Circuit] consisting of the remaining modules along with the errors
+    /// which were recovered from. It fails if the circuit's header is
+    /// malformed or if the top module could not be recovered.
+    pub fn parse_recovering(&self, input: &str) -> Result<(Circuit, Vec<ParseError>), ParseError> {
+        parsers::circuit_recovering(input, self)
+    }
+}
+
+
+/// Resource limits for parsing
+///
+/// Limits allow rejecting pathological inputs, e.g. from untrusted sources,
+/// with an error rather than exhausting memory or the stack while parsing. By
+/// default, no limits are imposed.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    modules: Option<usize>,
+    statements: Option<usize>,
+    depth: Option<usize>,
+}
+
+impl Limits {
+    /// Create new limits, not limiting anything
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the maximum number of modules in a circuit
+    pub fn with_max_modules(self, max: usize) -> Self {
+        Self {modules: Some(max), ..self}
+    }
+
+    /// Set the maximum number of statements in a single module
+    ///
+    /// Statements nested in conditional statements count towards the limit.
+    /// Parsing stops as soon as the limit is exceeded.
+    pub fn with_max_statements(self, max: usize) -> Self {
+        Self {statements: Some(max), ..self}
+    }
+
+    /// Set the maximum nesting depth of expressions and types
+    ///
+    /// The depth is determined from the nesting of parentheses, brackets and
+    /// braces before a module is parsed. Parentheses left open at the end of a
+    /// line count towards the depth of the following lines.
+    pub fn with_max_depth(self, max: usize) -> Self {
+        Self {depth: Some(max), ..self}
+    }
+
+    /// Retrieve the maximum number of modules in a circuit
+    pub fn max_modules(&self) -> Option<usize> {
+        self.modules
+    }
+
+    /// Retrieve the maximum number of statements in a single module
+    pub fn max_statements(&self) -> Option<usize> {
+        self.statements
+    }
+
+    /// Retrieve the maximum nesting depth of expressions and types
+    pub fn max_depth(&self) -> Option<usize> {
+        self.depth
+    }
+}
+
+
+/// FIRRTL specification version
+///
+/// Circuits may be preceded by a line `FIRRTL version <major>.<minor>.<patch>`
+/// declaring the version of the specification they adhere to. The version
+/// governs the grammar both parsed and emitted. For example, connections and
+/// invalidations are expressed via `connect` and `invalidate` statements for
+/// versions `3.0.0` and later and via `<=` and `is invalid` otherwise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl Version {
+    /// First version featuring `connect` and `invalidate` statements
+    pub const CONNECT_KEYWORDS: Self = Self::new(3, 0, 0);
+
+    /// Create a new version
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {major, minor, patch}
+    }
+
+    /// Retrieve the major version
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Retrieve the minor version
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Retrieve the patch version
+    pub fn patch(&self) -> u32 {
+        self.patch
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use nom::Finish;
+
+        nom::combinator::all_consuming(parsers::version)(s)
+            .finish()
+            .map(|(_, v)| v)
+            .map_err(|e| crate::error::convert_error(s, nom::Err::Error(e)))
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Version {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Self::new(u8::arbitrary(g).into(), u8::arbitrary(g).into(), u8::arbitrary(g).into())
+    }
+}
+
+
+/// Content hashes of the modules of a [Circuit]
+///
+/// Instances of this type are created via [Circuit::manifest]. Build systems
+/// may store a manifest alongside generated artifacts and compare it against
+/// the manifest of a later version of the circuit via [BuildManifest::changed]
+/// in order to determine which modules need to be regenerated.
+///
+/// A manifest is displayed as one line per module, consisting of the hash and
+/// the module's name separated by a space. This format is also accepted by the
+/// manifest's `FromStr` implementation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildManifest {
+    entries: Vec<(Arc<str>, module::ContentHash)>,
+}
+
+impl BuildManifest {
+    /// Retrieve the names and hashes of all modules
+    pub fn entries(&self) -> &[(Arc<str>, module::ContentHash)] {
+        self.entries.as_ref()
+    }
+
+    /// Retrieve the hash of the module with the given name
+    pub fn hash(&self, module: impl AsRef<str>) -> Option<module::ContentHash> {
+        let module = module.as_ref();
+        self.entries.iter().find(|(n, _)| n.as_ref() == module).map(|(_, h)| *h)
+    }
+
+    /// Retrieve the names of modules which changed relative to `previous`
+    ///
+    /// A module is considered changed if it is not listed in `previous` or if
+    /// its hash differs.
+    pub fn changed<'a>(&'a self, previous: &Self) -> impl Iterator<Item = &'a Arc<str>> + 'a {
+        let previous: HashMap<_, _> = previous.entries.iter().map(|(n, h)| (n.clone(), *h)).collect();
+        self.entries.iter().filter(move |(n, h)| previous.get(n) != Some(h)).map(|(n, _)| n)
+    }
+
+    /// Serialize the manifest as JSON
+    ///
+    /// The manifest is serialized as an object mapping module names to their
+    /// hashes.
+    pub fn json(&self) -> String {
+        use crate::display::JsonString;
+
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(n, h)| format!("{}:{}", JsonString(n), JsonString(&h.to_string())))
+            .collect();
+        format!("{{{}}}", entries.join(","))
+    }
+}
+
+impl fmt::Display for BuildManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.entries.iter().try_for_each(|(n, h)| writeln!(f, "{} {}", h, n))
+    }
+}
+
+impl std::str::FromStr for BuildManifest {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = s.lines().filter(|l| !l.trim().is_empty()).map(|l| {
+            let (hash, name) = l
+                .trim()
+                .split_once(' ')
+                .ok_or_else(|| format!("Malformed manifest entry: {}", l))?;
+            let hash = hash.parse().map_err(|e| format!("Malformed hash {}: {}", hash, e))?;
+            Ok((name.trim().into(), hash))
+        }).collect::<Result<_, ParseError>>()?;
+        Ok(Self {entries})
     }
 }
 
@@ -127,6 +1013,12 @@ impl Arbitrary for Circuit {
 pub struct ModuleConsumer<I: Iterator<Item = Result<Arc<Module>, E>>, E> {
     top_module: TopState,
     info: Option<String>,
+    version: Option<Version>,
+    layers: Vec<layer::Layer>,
+    aliases: Vec<TypeAlias>,
+    options: Vec<option::OptionGroup>,
+    defined: Vec<Arc<Module>>,
+    retain: bool,
     modules: I,
 }
 
@@ -141,32 +1033,75 @@ where I: Iterator<Item = Result<Arc<Module>, E>>,
     /// The constructed [Circuit] with the given `info`. Note that `None` is a
     /// valid choice, e.g. if the `info` is to be set later.
     pub fn new(top_name: impl Into<String>, info: impl Into<Option<String>>, modules: I) -> Self {
-        Self {top_module: TopState::Name(top_name.into()), info: info.into(), modules}
+        Self {
+            top_module: TopState::Name(top_name.into()),
+            info: info.into(),
+            version: Default::default(),
+            layers: Default::default(),
+            aliases: Default::default(),
+            options: Default::default(),
+            defined: Default::default(),
+            retain: true,
+            modules,
+        }
+    }
+
+    /// Set the FIRRTL version of the [Circuit] constructed
+    pub fn with_version(self, version: impl Into<Option<Version>>) -> Self {
+        Self {version: version.into(), ..self}
+    }
+
+    /// Set the layers declared in the [Circuit] constructed
+    pub fn with_layers(self, layers: impl IntoIterator<Item = layer::Layer>) -> Self {
+        Self {layers: layers.into_iter().collect(), ..self}
+    }
+
+    /// Set the type aliases declared in the [Circuit] constructed
+    pub fn with_type_aliases(self, aliases: impl IntoIterator<Item = TypeAlias>) -> Self {
+        Self {aliases: aliases.into_iter().collect(), ..self}
+    }
+
+    /// Set the option groups declared in the [Circuit] constructed
+    pub fn with_option_groups(self, options: impl IntoIterator<Item = option::OptionGroup>) -> Self {
+        Self {options: options.into_iter().collect(), ..self}
+    }
+
+    /// Set whether to retain modules not instantiated by the top module
+    ///
+    /// By default, the [Circuit] constructed defines all modules collected,
+    /// including those not instantiated directly or indirectly by the top
+    /// module. If `retain` is `false`, those modules are discarded.
+    pub fn with_unreferenced_modules(self, retain: bool) -> Self {
+        Self {retain, ..self}
     }
 
     /// Retrieve the circuit
     ///
     /// If the top module was collected, this function returns the circuit,
-    /// otherwise `None` will be returned.
+    /// otherwise `None` will be returned. The circuit defines all modules
+    /// collected so far, unless configured otherwise.
     pub fn circuit(&self) -> Option<Circuit> {
         if let TopState::Module(m) = &self.top_module {
-            Some(Circuit::new(m.clone()).with_info(self.info.clone()))
+            let res = Circuit::new(m.clone())
+                .with_modules(self.defined.iter().cloned())
+                .with_info(self.info.clone())
+                .with_version(self.version)
+                .with_layers(self.layers.iter().cloned())
+                .with_type_aliases(self.aliases.iter().cloned())
+                .with_option_groups(self.options.iter().cloned());
+            Some(if self.retain { res } else { res.without_unreferenced_modules() })
         } else {
             None
         }
     }
 
     /// Try to create the requested circuit, consuming the iterator
+    ///
+    /// Unless configured otherwise, the circuit defines all modules yielded by
+    /// the inner iterator.
     pub fn into_circuit(mut self) -> Result<Circuit, ParseError> {
-        let info = self.info;
-        match self.top_module {
-            TopState::Name(n)   => self
-                .modules
-                .find(|m| m.as_ref().ok().map(|m| m.name_ref() == n).unwrap_or(true))
-                .map(|r| r.map_err(Into::into))
-                .unwrap_or_else(|| Err("top module not found".to_owned().into())),
-            TopState::Module(m) => Ok(m),
-        }.map(|m| Circuit::new(m).with_info(info))
+        self.by_ref().try_for_each(|m| m.map(|_| ()).map_err(Into::into))?;
+        self.circuit().ok_or_else(|| "top module not found".to_owned().into())
     }
 }
 
@@ -175,9 +1110,12 @@ impl<I: Iterator<Item = Result<Arc<Module>, E>>, E> Iterator for ModuleConsumer<
 
     fn next(&mut self) -> Option<Self::Item> {
         let res = self.modules.next();
-        if let (Some(Ok(m)), TopState::Name(n)) = (res.as_ref(), &self.top_module) {
-            if n == m.name_ref() {
-                self.top_module = TopState::Module(m.clone())
+        if let Some(Ok(m)) = res.as_ref() {
+            self.defined.push(m.clone());
+            if let TopState::Name(n) = &self.top_module {
+                if n == m.name_ref() {
+                    self.top_module = TopState::Module(m.clone())
+                }
             }
         }
         res
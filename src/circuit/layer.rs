@@ -0,0 +1,153 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Optional layers
+//!
+//! A [Layer] declares optional functionality of a circuit, e.g. verification
+//! code, which may be enabled or disabled when the circuit is lowered.
+//! Layers are declared at the circuit level and may be nested. Statements
+//! belonging to a layer are placed in
+//! [layerblock](crate::stmt::Kind::LayerBlock) statements. Layers are referred
+//! to by their path, i.e. the names of the layer and its parents separated by
+//! `.`, e.g. `Verification.Assert`.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::indentation::{DisplayIndented, Indentation};
+use crate::info::{self, WithInfo};
+use crate::named::Named;
+
+
+/// Declaration of an optional layer
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    name: Arc<str>,
+    convention: Convention,
+    output_dir: Option<String>,
+    layers: Vec<Layer>,
+    info: Option<String>,
+}
+
+impl Layer {
+    /// Create a new layer with the given lowering convention
+    pub fn new(name: impl Into<Arc<str>>, convention: Convention) -> Self {
+        Self {
+            name: name.into(),
+            convention,
+            output_dir: Default::default(),
+            layers: Default::default(),
+            info: Default::default(),
+        }
+    }
+
+    /// Retrieve the lowering convention of this layer
+    pub fn convention(&self) -> Convention {
+        self.convention
+    }
+
+    /// Set the output directory for the artifacts of this layer
+    pub fn with_output_dir(self, output_dir: impl Into<Option<String>>) -> Self {
+        Self {output_dir: output_dir.into(), ..self}
+    }
+
+    /// Retrieve the output directory for the artifacts of this layer, if any
+    pub fn output_dir(&self) -> Option<&str> {
+        self.output_dir.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Set the layers nested in this layer
+    pub fn with_layers(self, layers: impl IntoIterator<Item = Layer>) -> Self {
+        Self {layers: layers.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the layers nested directly in this layer
+    pub fn layers(&self) -> &[Layer] {
+        self.layers.as_ref()
+    }
+
+    /// Create a copy of this layer with all info attributes mapped
+    ///
+    /// This function applies `f` to the info attributes of this layer and all
+    /// nested layers and replaces the attributes with the result.
+    pub fn with_info_mapped(&self, f: &mut impl FnMut(Option<&str>) -> Option<String>) -> Self {
+        let layers = self.layers.iter().map(|l| l.with_info_mapped(f)).collect();
+        Self {layers, info: f(self.info()), ..self.clone()}
+    }
+
+    /// Retrieve the layer with the given path, relative to this layer
+    ///
+    /// An empty path refers to this layer.
+    pub fn layer(&self, path: &str) -> Option<&Self> {
+        if path.is_empty() {
+            Some(self)
+        } else {
+            find(&self.layers, path)
+        }
+    }
+}
+
+impl Named for Layer {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl WithInfo for Layer {
+    fn info(&self) -> Option<&str> {
+        self.info.as_ref().map(AsRef::as_ref)
+    }
+
+    fn set_info(&mut self, info: Option<String>) {
+        self.info = info
+    }
+}
+
+impl DisplayIndented for Layer {
+    fn fmt<W: fmt::Write>(&self, indentation: &mut Indentation, f: &mut W) -> fmt::Result {
+        use crate::stmt::display::StringLiteral;
+
+        write!(f, "{}layer {}, {}", indentation.lock(), self.name, self.convention)?;
+        if let Some(dir) = self.output_dir() {
+            write!(f, ", {}", StringLiteral(dir))?;
+        }
+        writeln!(f, ":{}", info::Info::of(self))?;
+
+        let mut sub = indentation.sub();
+        self.layers.iter().try_for_each(|l| l.fmt(&mut sub, f))
+    }
+}
+
+
+/// Convention for lowering a [Layer]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Convention {
+    /// The layer is lowered to modules instantiated via `bind`
+    Bind,
+    /// The layer is lowered to code guarded by preprocessor macros
+    Inline,
+}
+
+impl Convention {
+    /// Retrieve the keyword of this convention
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Bind      => "bind",
+            Self::Inline    => "inline",
+        }
+    }
+}
+
+impl fmt::Display for Convention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+
+/// Find the layer with the given path among the given layers
+pub(crate) fn find<'a>(layers: &'a [Layer], path: &str) -> Option<&'a Layer> {
+    let (head, tail) = path.split_once('.').unwrap_or((path, ""));
+    layers.iter().find(|l| l.name_ref() == head).and_then(|l| l.layer(tail))
+}
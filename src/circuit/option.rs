@@ -0,0 +1,69 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Option groups
+//!
+//! An [OptionGroup] declares a named set of cases, e.g. the platforms a
+//! circuit may be lowered for. Option groups are declared at the circuit level.
+//! A [module::InstanceChoice](crate::module::InstanceChoice) depending on an
+//! option group instantiates a different module for some of its cases. The case
+//! to select is specified when the circuit is lowered.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::indentation::{DisplayIndented, Indentation};
+use crate::info::{self, WithInfo};
+use crate::named::Named;
+
+
+/// Declaration of an option group
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionGroup {
+    name: Arc<str>,
+    cases: Vec<Arc<str>>,
+    info: Option<String>,
+}
+
+impl OptionGroup {
+    /// Create a new option group with the given cases
+    pub fn new(name: impl Into<Arc<str>>, cases: impl IntoIterator<Item = Arc<str>>) -> Self {
+        Self {name: name.into(), cases: cases.into_iter().collect(), info: Default::default()}
+    }
+
+    /// Retrieve the cases of this option group
+    pub fn cases(&self) -> &[Arc<str>] {
+        self.cases.as_ref()
+    }
+
+    /// Check whether this option group has a case with the given name
+    pub fn has_case(&self, case: &str) -> bool {
+        self.cases.iter().any(|c| c.as_ref() == case)
+    }
+}
+
+impl Named for OptionGroup {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl WithInfo for OptionGroup {
+    fn info(&self) -> Option<&str> {
+        self.info.as_ref().map(AsRef::as_ref)
+    }
+
+    fn set_info(&mut self, info: Option<String>) {
+        self.info = info
+    }
+}
+
+impl DisplayIndented for OptionGroup {
+    fn fmt<W: fmt::Write>(&self, indentation: &mut Indentation, f: &mut W) -> fmt::Result {
+        writeln!(f, "{}option {}:{}", indentation.lock(), self.name, info::Info::of(self))?;
+
+        let mut sub = indentation.sub();
+        self.cases.iter().try_for_each(|c| writeln!(f, "{}{}", sub.lock(), c))
+    }
+}
@@ -2,16 +2,24 @@
 // SPDX-License-Identifier: Apache-2.0
 //! Parsers for Circuits
 
-use nom::combinator::map;
-use nom::multi::fold_many0;
-use nom::sequence::tuple;
+use std::io::BufRead;
+use std::sync::Arc;
+
+use nom::character::complete::char as chr;
+use nom::combinator::{map, opt, recognize, value};
+use nom::multi::{fold_many0, separated_list1};
+use nom::sequence::{preceded, tuple};
 
 use crate::error::{ParseError, convert_error};
+use crate::indentation::Indentation;
 use crate::info::parse as parse_info;
-use crate::module::parsers::Modules;
-use crate::parsers::{identifier, kw, le, op, spaced};
+use crate::module::parsers::{Interfaces, Modules, ReadModules};
+use crate::parsers::{IResult, comma, decimal, identifier, kw, le, op, spaced};
+use crate::stmt::parsers::string_literal;
+use crate::types::TypeAlias;
+use crate::types::parsers::r#type;
 
-use super::{Circuit, ModuleConsumer};
+use super::{Circuit, ModuleConsumer, ParseOptions, Version, layer, option::OptionGroup};
 
 
 /// Parse a [Circuit]
@@ -20,24 +28,341 @@ pub fn circuit(input: &str) -> Result<Circuit, ParseError> {
 }
 
 
+/// Parse a [Circuit] according to the given [ParseOptions]
+pub fn circuit_with_options(input: &str, options: &ParseOptions) -> Result<Circuit, ParseError> {
+    let (modules, consumer) = prelude(input, input)?;
+    consumer(configured(modules, options)).into_circuit()
+}
+
+
+/// Parse a [Circuit], recovering from malformed modules and statements
+///
+/// Malformed modules and statements are skipped. The function returns the
+/// [Circuit] consisting of the remaining modules along with the errors which
+/// were recovered from. It fails if the circuit's header is malformed or if
+/// the top module could not be recovered.
+pub fn circuit_recovering(input: &str, options: &ParseOptions) -> Result<(Circuit, Vec<ParseError>), ParseError> {
+    let (modules, consumer) = prelude(input, input)?;
+    let mut modules = configured(modules, options).with_recovery(true);
+    let circuit = consumer(modules.by_ref()).into_circuit()?;
+    Ok((circuit, modules.take_diagnostics()))
+}
+
+
+/// Configure [Modules] according to the given [ParseOptions]
+fn configured<'i>(modules: Modules<'i>, options: &ParseOptions) -> Modules<'i> {
+    modules
+        .with_limits(options.limits())
+        .with_unique_fields(options.unique_fields())
+        .with_separated_statements(options.separated_statements())
+        .with_instance_parameters(options.instance_parameters())
+        .with_dialects(options.dialects().iter().cloned())
+}
+
+
+/// Parse all circuits in the given input
+///
+/// The input may consist of multiple circuits concatenated, each starting
+/// with its header. This function parses all of them in the order they appear
+/// in. Locations reported in errors are relative to the entire input.
+pub fn circuits(input: &str) -> Result<Vec<Circuit>, ParseError> {
+    use nom::Parser;
+
+    // Headers are the only lines which are not indented. A `circuit` following
+    // a version belongs to the same header.
+    let mut starts = Vec::new();
+    let mut versioned = false;
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if kw("FIRRTL").parse(line).is_ok() {
+            starts.push(offset);
+            versioned = true;
+        } else if kw("circuit").parse(line).is_ok() {
+            if !versioned {
+                starts.push(offset);
+            }
+            versioned = false;
+        }
+        offset += line.len();
+    }
+
+    // Any blank lines or comments preceding the first header are included
+    match starts.first_mut() {
+        Some(start) => *start = 0,
+        None        => starts.push(0),
+    }
+
+    let ends = starts.iter().skip(1).copied().chain(std::iter::once(input.len()));
+    starts.iter().zip(ends).map(|(start, end)| {
+        let (modules, consumer) = prelude(&input[*start..end], input)?;
+        consumer(modules).into_circuit()
+    }).collect()
+}
+
+
 /// Create a [ModuleConsumer] for the given input
 ///
-/// The input is expected to contain a full circuit definition. The function
-/// will return a [ModuleConsumer] which will construct a [Circuit] from that
-/// input.
+/// The input is expected to contain a full circuit definition, optionally
+/// preceded by a version header. The function will return a [ModuleConsumer]
+/// which will construct a [Circuit] from that input.
 pub fn consumer(input: &str) -> Result<ModuleConsumer<Modules, ParseError>, ParseError> {
-    let (mod_input, (top_name, info)) = map(
+    let (modules, consumer) = prelude(input, input)?;
+    Ok(consumer(modules))
+}
+
+
+/// Create a [ModuleConsumer] reading from the given [BufRead]
+///
+/// The header of the circuit and the declarations of layers, type aliases and
+/// option groups following it are read and parsed immediately. The returned
+/// [ModuleConsumer] pulls the input from `read` incrementally as modules are
+/// parsed, holding only the lines of a single module in memory at any time.
+pub fn consumer_from_read<R: BufRead>(
+    mut read: R,
+) -> Result<ModuleConsumer<ReadModules<R>, ParseError>, ParseError> {
+    use nom::Parser;
+
+    let mut input = String::new();
+    loop {
+        let start = input.len();
+        if read.read_line(&mut input)? == 0 || input[start..].trim_start().starts_with("circuit") {
+            break
+        }
+    }
+
+    // Declarations consist of lines starting with `layer`, `type` or `option`
+    // and the cases of option groups, which are indented further
+    let mut lookahead = String::new();
+    let mut option_indentation = None;
+    loop {
+        if read.read_line(&mut lookahead)? == 0 {
+            break
+        }
+        let content = lookahead.trim_start();
+        let indentation = lookahead.len() - content.len();
+        let option = kw("option").parse(content).is_ok();
+        let declaration = option || kw("layer").parse(content).is_ok() || kw("type").parse(content).is_ok();
+        let case = option_indentation.map(|i| indentation > i).unwrap_or(false);
+        if !(declaration || case || content.is_empty() || content.starts_with(';')) {
+            break
+        }
+        if declaration {
+            option_indentation = Some(indentation).filter(|_| option);
+        }
+        input.push_str(&lookahead);
+        lookahead.clear();
+    }
+
+    let (rest, (top_name, info, version)) = header(&input).map_err(|e| convert_error(&input, e))?;
+    let mut indentation = Indentation::root().sub();
+    let (_, (layers, aliases, options)) = declarations(rest, &mut indentation)
+        .map_err(|e| convert_error(&input, e))?;
+
+    let modules = ReadModules::new(read)
+        .with_version(version)
+        .with_indentation(indentation)
+        .with_type_aliases(aliases.clone())
+        .with_lookahead(lookahead)
+        .with_line_offset(input.matches('\n').count());
+    Ok(ModuleConsumer::new(top_name, info, modules)
+        .with_version(version)
+        .with_layers(layers)
+        .with_type_aliases(aliases)
+        .with_option_groups(options))
+}
+
+
+/// Create an [Interfaces] iterator for the given input
+///
+/// The input is expected to contain a full circuit definition. This function
+/// returns the name of the top module along with an iterator yielding the
+/// interfaces of all modules without parsing any statements.
+pub fn interfaces(input: &str) -> Result<(&str, Interfaces<'_>), ParseError> {
+    let (mod_input, (top_name, ..)) = header(input).map_err(|e| convert_error(input, e))?;
+    let (mod_input, (_, aliases, _)) = declarations(mod_input, &mut Indentation::root().sub())
+        .map_err(|e| convert_error(input, e))?;
+
+    Ok((top_name, Interfaces::new_with_origin(mod_input, input).with_type_aliases(aliases)))
+}
+
+
+/// Parse the header and the declarations of a circuit
+///
+/// This function returns [Modules] for the remaining input along with a
+/// function creating a [ModuleConsumer] for the circuit from a module
+/// iterator. The `origin`, which `input` is part of, is used for computing
+/// locations reported in errors.
+fn prelude<'i, I>(
+    input: &'i str,
+    origin: &'i str,
+) -> Result<(Modules<'i>, impl FnOnce(I) -> Consumer<I> + 'i), ParseError>
+where I: Iterator<Item = Result<Arc<crate::module::Module>, ParseError>>,
+{
+    let (mod_input, (top_name, info, version)) = header(input).map_err(|e| convert_error(origin, e))?;
+    let mut indentation = Indentation::root().sub();
+    let (mod_input, (layers, aliases, options)) = declarations(mod_input, &mut indentation)
+        .map_err(|e| convert_error(origin, e))?;
+
+    let modules = Modules::new_with_origin(mod_input, origin)
+        .with_version(version)
+        .with_indentation(indentation)
+        .with_type_aliases(aliases.clone());
+    let consumer = move |modules: I| ModuleConsumer::new(top_name, info, modules)
+        .with_version(version)
+        .with_layers(layers)
+        .with_type_aliases(aliases)
+        .with_option_groups(options);
+    Ok((modules, consumer))
+}
+
+
+/// [ModuleConsumer] created by [prelude]
+type Consumer<I> = ModuleConsumer<I, ParseError>;
+
+
+/// Parse the header of a circuit, i.e. the top module name, info and version
+pub fn header(input: &str) -> IResult<'_, (&str, Option<String>, Option<Version>)> {
+    map(
         tuple((
             fold_many0(le, Default::default, |_, _| ()),
+            opt(map(tuple((kw("FIRRTL"), spaced(kw("version")), spaced(version), le)), |(.., v, _)| v)),
             kw("circuit"),
             spaced(identifier),
             spaced(op(":")),
             parse_info,
             le,
         )),
-        |(_, _, n, _, i, ..)| (n, i)
-    )(input).map_err(|e| convert_error(input, e))?;
+        |(_, v, _, n, _, i, ..)| (n, i, v)
+    )(input)
+}
+
+
+/// Parse a FIRRTL version, e.g. `3.3.0`
+pub fn version(input: &str) -> IResult<'_, Version> {
+    map(
+        tuple((decimal, chr('.'), decimal, chr('.'), decimal)),
+        |(major, _, minor, _, patch)| Version::new(major, minor, patch)
+    )(input)
+}
 
-    Ok(ModuleConsumer::new(top_name, info, Modules::new_with_origin(mod_input, input)))
+
+/// Parse a layer declaration, including all nested layers
+pub fn layer<'i>(input: &'i str, indentation: &'_ mut Indentation) -> IResult<'i, layer::Layer> {
+    use crate::info::WithInfo;
+
+    let (mut input, (_, _, n, _, convention, output_dir, _, info, _)) = tuple((
+        indentation.parser(),
+        kw("layer"),
+        spaced(identifier),
+        comma,
+        spaced(layer_convention),
+        opt(preceded(comma, spaced(string_literal))),
+        spaced(op(":")),
+        parse_info,
+        le,
+    ))(input)?;
+
+    let mut sub = indentation.sub();
+    let mut layers = Vec::new();
+    while let Ok((i, l)) = layer(input, &mut sub) {
+        layers.push(l);
+        input = i;
+    }
+
+    let res = layer::Layer::new(n, convention).with_output_dir(output_dir).with_layers(layers);
+    Ok((input, res.with_info(info)))
 }
 
+
+/// Parse the lowering convention of a layer
+pub fn layer_convention(input: &str) -> IResult<'_, layer::Convention> {
+    nom::branch::alt((
+        value(layer::Convention::Bind, kw("bind")),
+        value(layer::Convention::Inline, kw("inline")),
+    ))(input)
+}
+
+
+/// Parse the path of a layer, e.g. `A.B`
+pub fn layer_path(input: &str) -> IResult<'_, Arc<str>> {
+    map(recognize(separated_list1(chr('.'), identifier)), Arc::from)(input)
+}
+
+
+/// Parse the declarations of layers, type aliases and option groups
+///
+/// Layers, type aliases and option groups need to be declared before any
+/// module. Hence, this parser is applied to the input following the header of
+/// a circuit, at the given indentation. Type aliases may refer to aliases
+/// declared before them.
+pub fn declarations<'i>(
+    mut input: &'i str,
+    indentation: &'_ mut Indentation,
+) -> IResult<'i, (Vec<layer::Layer>, Vec<TypeAlias>, Vec<OptionGroup>)> {
+    let mut layers = Vec::new();
+    let mut aliases: Vec<TypeAlias> = Vec::new();
+    let mut options = Vec::new();
+    loop {
+        match layer(input, indentation) {
+            Ok((i, l))                  => {
+                layers.push(l);
+                input = i;
+                continue
+            },
+            Err(nom::Err::Error(_))     => (),
+            Err(e)                      => return Err(e),
+        }
+
+        match option_group(input, indentation) {
+            Ok((i, o))                  => {
+                options.push(o);
+                input = i;
+                continue
+            },
+            Err(nom::Err::Error(_))     => (),
+            Err(e)                      => return Err(e),
+        }
+
+        match type_alias(input, indentation, &aliases) {
+            Ok((i, a))                  => {
+                aliases.push(a);
+                input = i;
+            },
+            Err(nom::Err::Error(_))     => return Ok((input, (layers, aliases, options))),
+            Err(e)                      => return Err(e),
+        }
+    }
+}
+
+
+/// Parse a type alias declaration, e.g. `type Pair = {a: UInt<1>, b: UInt<1>}`
+///
+/// The aliased type may refer to any of the given `aliases`.
+pub fn type_alias<'i>(
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    aliases: &[TypeAlias],
+) -> IResult<'i, TypeAlias> {
+    use crate::info::WithInfo;
+
+    map(
+        tuple((indentation.parser(), kw("type"), spaced(identifier), spaced(op("=")), spaced(|i| r#type(i, aliases)), parse_info, le)),
+        |(_, _, n, _, t, info, _)| TypeAlias::new(n, t).with_info(info),
+    )(input)
+}
+
+
+/// Parse an option group declaration, including its cases
+pub fn option_group<'i>(input: &'i str, indentation: &'_ mut Indentation) -> IResult<'i, OptionGroup> {
+    use nom::combinator::iterator;
+
+    use crate::info::WithInfo;
+
+    let (input, (_, _, n, _, info, _)) =
+        tuple((indentation.parser(), kw("option"), spaced(identifier), spaced(op(":")), parse_info, le))(input)?;
+
+    let mut sub = indentation.sub();
+    let mut cases = iterator(input, map(tuple((sub.parser(), identifier, parse_info, le)), |(_, c, ..)| c.into()));
+    let res = OptionGroup::new(n, &mut cases).with_info(info);
+    cases.finish().map(|(i, _)| (i, res))
+}
@@ -2,13 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0
 //! Tests related to circuits
 
+use std::sync::Arc;
+
 use quickcheck::{Gen, TestResult, Testable};
 
 use crate::error::ParseError;
+use crate::module::Module;
 use crate::named::Named;
 use crate::tests::Equivalence;
 
-use super::{Circuit, parsers};
+use super::{Circuit, Limits, ParseOptions, parsers};
 
 
 #[quickcheck]
@@ -32,3 +35,889 @@ fn parse_circuit(original: Circuit) -> Result<TestResult, ParseError> {
     parsers::circuit(&s)
         .map(|parsed| Equivalence::of(original, parsed).result(&mut Gen::new(0)))
 }
+
+
+#[quickcheck]
+fn parse_interfaces(original: Circuit) -> Result<TestResult, ParseError> {
+    let s = original.to_string();
+    let (top, interfaces) = parsers::interfaces(&s)?;
+    let interfaces = interfaces.collect::<Result<Vec<_>, _>>()?;
+
+    let expected: std::collections::HashMap<_, _> = original
+        .modules()
+        .map(|m| (m.name().clone(), m.interface()))
+        .collect();
+    let res = top == original.top_module().name_ref() &&
+        interfaces.len() == expected.len() &&
+        interfaces.iter().all(|i| expected.get(i.name()) == Some(i));
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn parse_defined_modules(original: Circuit, extra: Module) -> Result<TestResult, ParseError> {
+    let original = original.with_modules(std::iter::once(Arc::new(extra)));
+
+    // Every name must refer to a single module
+    let defined: std::collections::HashMap<_, _> = original.defined_modules().iter().map(|m| (m.name(), m)).collect();
+    let consistent = original
+        .defined_modules()
+        .iter()
+        .flat_map(|m| m.referenced_modules())
+        .chain(std::iter::once(original.top_module()))
+        .all(|m| defined.get(m.name()).map(|d| *d == m).unwrap_or(false));
+    if !consistent {
+        return Ok(TestResult::discard())
+    }
+
+    let s = original.to_string();
+    let parsed = parsers::circuit(&s)?;
+    let res = parsed.defined_modules().iter().map(|m| m.name()).eq(original.defined_modules().iter().map(|m| m.name())) &&
+        parsed.to_string() == s;
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn retain_unreferenced_modules(original: Circuit, extra: Module) -> Result<TestResult, ParseError> {
+    let extra = Arc::new(extra);
+    if original.defined_modules().iter().any(|m| m.name() == extra.name()) ||
+        extra.referenced_modules().next().is_some() {
+        return Ok(TestResult::discard())
+    }
+    let with_extra = original.clone().with_modules(std::iter::once(extra.clone()));
+
+    let s = with_extra.to_string();
+    let retained = parsers::consumer(&s)?.into_circuit()?;
+    let discarded = parsers::consumer(&s)?.with_unreferenced_modules(false).into_circuit()?;
+    let res = retained.unreferenced_modules().map(|m| m.name()).eq(std::iter::once(extra.name())) &&
+        discarded.unreferenced_modules().next().is_none() &&
+        discarded == original &&
+        retained.without_unreferenced_modules() == original;
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn parse_with_limits(original: Circuit) -> Result<TestResult, ParseError> {
+    use transiter::AutoTransIter;
+
+    let s = original.to_string();
+    let modules = original.defined_modules().len();
+    let statements = original
+        .defined_modules()
+        .iter()
+        .map(|m| m.statements().iter().flat_map(AutoTransIter::trans_iter).count())
+        .max()
+        .unwrap_or(0);
+    let limits = Limits::new().with_max_modules(modules).with_max_statements(statements);
+
+    let res = ParseOptions::new().with_limits(limits).parse(&s)? == parsers::circuit(&s)? &&
+        ParseOptions::new().with_limits(limits.with_max_modules(modules - 1)).parse(&s).is_err() &&
+        (statements == 0 || ParseOptions::new().with_limits(limits.with_max_statements(statements - 1)).parse(&s).is_err());
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn parse_depth_limit(depth: u8) -> bool {
+    let depth = depth as usize % 64;
+    let expr = format!("{}a{}", "not(".repeat(depth), ")".repeat(depth));
+    let source = format!("circuit Top:\n  module Top:\n    input a: UInt<1>\n    node n = {}\n", expr);
+
+    let res = ParseOptions::new().with_limits(Limits::new().with_max_depth(32)).parse(&source);
+    res.is_ok() == (depth <= 32)
+}
+
+
+#[test]
+fn parse_depth_limit_multiline() {
+    let expr = format!("{}a{}", "not(\n".repeat(20), ")".repeat(20));
+    let source = format!("circuit Top:\n  module Top:\n    input a: UInt<1>\n    node n = {}\n", expr);
+
+    assert!(ParseOptions::new().with_limits(Limits::new().with_max_depth(8)).parse(&source).is_err());
+}
+
+
+#[test]
+fn separated_statements() {
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    input c: UInt<1>\n",
+        "    output b: UInt<1>\n",
+        "    b <= a ; b <= c\n",
+    );
+    let statements = |options: ParseOptions| options
+        .parse(source)
+        .map(|c| c.top_module().statements().len())
+        .expect("Could not parse circuit");
+    assert_eq!(parsers::circuit(source).expect("Could not parse circuit").top_module().statements().len(), 1);
+    assert_eq!(statements(ParseOptions::new()), 1);
+    assert_eq!(statements(ParseOptions::new().with_separated_statements(true)), 2);
+}
+
+
+#[test]
+fn instance_parameters() {
+    use crate::emit::{Emitter, FirrtlEmitter};
+    use crate::module::ParamValue;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  extmodule Sub:\n",
+        "    input a: UInt<1>\n",
+        "    parameter WIDTH = 1\n",
+        "  module Top:\n",
+        "    inst sub of Sub with: (WIDTH = 8)\n",
+    );
+    assert!(parsers::circuit(source).is_err());
+
+    let circuit = ParseOptions::new().with_instance_parameters(true).parse(source).expect("Could not parse circuit");
+    let inst = circuit.top_module().statements().iter().flat_map(crate::stmt::Statement::instantiations).next();
+    assert_eq!(inst.and_then(|i| i.parameters().get("WIDTH")), Some(&ParamValue::Int(8)));
+
+    let plain = circuit.to_string();
+    assert!(plain.contains("    inst sub of Sub\n"), "{}", plain);
+    assert!(parsers::circuit(&plain).is_ok());
+
+    let mut emitter = FirrtlEmitter::new(String::new()).with_instance_parameters(true);
+    emitter.circuit(&circuit).expect("Could not emit circuit");
+    let extended = emitter.into_inner();
+    assert!(extended.contains("    inst sub of Sub with: (WIDTH = 8)\n"), "{}", extended);
+    assert_eq!(ParseOptions::new().with_instance_parameters(true).parse(&extended).expect("Could not reparse"), circuit);
+
+    let mut cache = Vec::new();
+    circuit.save_cache(&mut cache).expect("Could not save cache");
+    assert_eq!(Circuit::load_cache(cache.as_slice()).expect("Could not load cache"), circuit);
+}
+
+
+#[quickcheck]
+fn modules_topological(original: Circuit) -> bool {
+    let modules: Vec<_> = original.modules().collect();
+    modules.iter().enumerate().all(|(i, m)| m
+        .referenced_modules()
+        .all(|r| modules[i + 1..].iter().any(|s| s.name() == r.name())))
+}
+
+
+#[test]
+fn modules_topological_diamond() {
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Leaf:\n",
+        "    input a: UInt<1>\n",
+        "  module Left:\n",
+        "    inst leaf of Leaf\n",
+        "  module Right:\n",
+        "    inst leaf of Leaf\n",
+        "  module Top:\n",
+        "    inst left of Left\n",
+        "    inst right of Right\n",
+    );
+    let circuit = parsers::circuit(source).expect("Could not parse circuit");
+    let names: Vec<_> = circuit.modules().map(|m| m.name().to_string()).collect();
+    assert_eq!(names, ["Top", "Left", "Right", "Leaf"]);
+}
+
+
+#[quickcheck]
+fn modules_sorted(original: Circuit) -> TestResult {
+    let sorted = original.modules_sorted();
+    let topological = sorted.iter().enumerate().all(|(i, m)| m
+        .referenced_modules()
+        .all(|r| sorted[..i].iter().any(|p| p.name() == r.name())));
+    TestResult::from_bool(sorted.len() == original.modules().count() && topological)
+}
+
+
+#[quickcheck]
+fn display_deterministic(original: Circuit) -> Result<TestResult, ParseError> {
+    let s = original.to_string();
+    parsers::circuit(&s).map(|parsed| TestResult::from_bool(parsed.to_string() == s))
+}
+
+
+#[quickcheck]
+fn substitute_modules_identity(original: Circuit) -> TestResult {
+    TestResult::from_bool(original.substitute_modules(|_| None) == original)
+}
+
+
+#[quickcheck]
+fn reparse_module(original: Circuit) -> Result<TestResult, ParseError> {
+    use crate::emit::{Emitter, FirrtlEmitter};
+    use crate::indentation::Indentation;
+
+    let original = parsers::circuit(&original.to_string())?;
+    for module in original.defined_modules() {
+        let mut emitter = FirrtlEmitter::new(String::new())
+            .with_indentation(Indentation::root().sub())
+            .with_version(original.version());
+        emitter.module(module).map_err(|_| "Could not format module".to_owned())?;
+        let text = emitter.into_inner();
+        if original.with_module_reparsed(&text)? != original {
+            return Ok(TestResult::error(format!("Reparsing {} changed the circuit", module.name())))
+        }
+    }
+    Ok(TestResult::passed())
+}
+
+
+#[test]
+fn reparse_module_updates_instances() {
+    let original = parsers::circuit(concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input a: UInt<1>\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    inst s of Sub\n",
+        "    s.a <= a\n",
+    )).expect("Could not parse circuit");
+
+    let modified = "  module Sub:\n    input a: UInt<1>\n    output b: UInt<1>\n    b <= a\n";
+    let res = original.with_module_reparsed(modified).expect("Could not reparse module");
+    let sub = res.top_module().referenced_modules().next().expect("Instance missing");
+    assert_eq!(sub.ports().count(), 2);
+    assert_eq!(res.module_by_name("Sub"), Some(sub));
+
+    assert!(original.with_module_reparsed("  module Foo:\n    input a: UInt<1>\n").is_err());
+    assert!(original.with_module_reparsed("  module Sub:\n    inst t of Top\n").is_err());
+    assert!(original.with_module_reparsed(&format!("{}{}", modified, modified)).is_err());
+}
+
+
+#[test]
+fn retarget_top() {
+    let original = parsers::circuit(concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input a: UInt<1>\n",
+        "  module Other:\n",
+        "    input a: UInt<1>\n",
+        "    inst s of Sub\n",
+        "    s.a <= a\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+    )).expect("Could not parse circuit");
+    let names = |c: &Circuit| c.defined_modules().iter().map(|m| m.name().to_string()).collect::<Vec<_>>();
+
+    let other = original.clone().with_top("Other").expect("Could not retarget top");
+    assert_eq!(other.top_module().name().as_ref(), "Other");
+    assert_eq!(names(&other), ["Sub", "Other", "Top"]);
+    assert_eq!(other.modules().map(|m| m.name().to_string()).collect::<Vec<_>>(), ["Other", "Sub"]);
+    assert_eq!(parsers::circuit(&other.to_string()).expect("Could not reparse circuit"), other);
+    assert!(original.clone().with_top("Missing").is_none());
+
+    let extra = parsers::circuit(concat!(
+        "circuit Extra:\n",
+        "  module Leaf:\n",
+        "    input a: UInt<1>\n",
+        "  module Extra:\n",
+        "    input a: UInt<1>\n",
+        "    inst l of Leaf\n",
+        "    l.a <= a\n",
+    )).expect("Could not parse circuit");
+    let mut assembled = original.clone();
+    assembled.add_module(extra.top_module().clone());
+    assembled.add_module(other.module_by_name("Sub").expect("Missing module").clone());
+    assert_eq!(names(&assembled), ["Sub", "Other", "Top", "Leaf", "Extra"]);
+    assert_eq!(assembled.top_module(), original.top_module());
+
+    let retargeted = assembled.with_top_module(extra.top_module().clone());
+    assert_eq!(retargeted.top_module().name().as_ref(), "Extra");
+    assert_eq!(names(&retargeted).len(), 5);
+}
+
+
+#[quickcheck]
+fn stub_module(original: Circuit) -> TestResult {
+    use crate::manifest::tests::leaf_count;
+
+    // Vectors with flipped fields are driven element-wise
+    let leaves = original.top_module().ports().map(|p| leaf_count(p.r#type())).fold(0, u64::saturating_add);
+    if leaves > 256 {
+        return TestResult::discard()
+    }
+
+    let name = original.top_module().name().clone();
+    let stubbed = match original.stub_module(&name, crate::module::StubPolicy::Zero) {
+        Some(c) => c,
+        None => return TestResult::error("Top module not found"),
+    };
+    let top = stubbed.top_module();
+    TestResult::from_bool(
+        top.name() == &name &&
+        top.ports().eq(original.top_module().ports()) &&
+        top.referenced_modules().next().is_none() &&
+        stubbed.defined_modules().len() == original.defined_modules().len() &&
+        original.stub_module("no_such_module", crate::module::StubPolicy::Invalidate).is_none()
+    )
+}
+
+
+#[quickcheck]
+fn rename_defname(original: Circuit) -> TestResult {
+    const NEW_NAME: &str = "renamed_defname";
+
+    let defname = if let Some(n) = original.external_modules().next().and_then(|m| m.defname()) {
+        n.clone()
+    } else {
+        return TestResult::discard()
+    };
+
+    let renamed = original.with_defname_renamed(defname.as_ref(), NEW_NAME);
+    let count = |c: &Circuit, n: &str| c.modules_with_defname(n).count();
+    let res = renamed.modules().count() == original.modules().count() &&
+        count(&renamed, NEW_NAME) == count(&original, NEW_NAME) + count(&original, &defname) &&
+        (defname.as_ref() == NEW_NAME || count(&renamed, &defname) == 0);
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn build_manifest(original: Circuit) -> Result<TestResult, ParseError> {
+    let manifest = original.manifest();
+    let parsed = parsers::circuit(&original.to_string())?;
+    let relabeled = original.with_info_mapped(|_| Some("relabeled".into()));
+    let res = manifest.entries().len() == original.defined_modules().len() &&
+        manifest.to_string().parse::<super::BuildManifest>()? == manifest &&
+        parsed.manifest() == manifest &&
+        relabeled.manifest() == manifest &&
+        manifest.changed(&manifest).next().is_none() &&
+        manifest.changed(&Default::default()).count() == manifest.entries().len();
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn canonical_form(original: Circuit) -> Result<TestResult, ParseError> {
+    let canonical = original.to_canonical_string();
+    let parsed = parsers::circuit(&original.to_string())?;
+    let relabeled = original.with_info_mapped(|_| Some("relabeled".into()));
+    let res = parsed.to_canonical_string() == canonical &&
+        relabeled.to_canonical_string() == canonical &&
+        original.modules_sorted().iter().all(|m| canonical.contains(&format!("module {}:\n", m.name())));
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[test]
+fn syntax_error_location() {
+    use crate::error::SyntaxErrorKind as K;
+
+    let cases = [
+        ("circuit Top:\n  module Top:\n    input a: UInt<1>\n      node n = a\n", K::Indentation, 4, 1),
+        ("circuit Top:\n  module Top:\n    input a: UInt<1>\n    node n = foo(a)\n", K::UnknownKeyword, 4, 14),
+        ("circuit Top:\n  module Top:\n    input a: UInt<", K::UnexpectedToken, 3, 5),
+        ("circuit Top:\n  module Top:\n    input a: UInt<1>\n    wire w: UInt<1>\n    w <= ", K::UnexpectedToken, 5, 5),
+        ("circuit Top", K::UnexpectedEnd, 1, 12),
+    ];
+
+    for (source, kind, line, column) in cases.iter() {
+        let err = parsers::circuit(source).expect_err(source);
+        let err = err.syntax().expect("Expected a syntax error");
+        assert_eq!((err.kind(), err.line(), err.column()), (*kind, *line, *column), "{}", source);
+        assert_eq!(Some(err.snippet()), source.lines().nth(line - 1));
+    }
+}
+
+
+#[test]
+fn unknown_reference_location() {
+    use crate::error::SyntaxErrorKind as K;
+
+    let header = "circuit Top:\n  module Top:\n    input a: UInt<1>\n    output b: UInt<1>\n";
+    let cases = [
+        ("    b <= c\n", K::UnknownReference, "c", 10),
+        ("    node n = and(a, x)\n", K::UnknownReference, "x", 21),
+        ("    c.x <= a\n", K::UnknownReference, "c", 5),
+        ("    c is invalid\n", K::UnknownReference, "c", 5),
+        ("    when a : b <= y\n", K::UnknownReference, "y", 19),
+        ("    inst i of Foo\n", K::UnknownReference, "Foo", 15),
+        ("    b <= frob(a)\n", K::UnknownKeyword, "frob", 10),
+    ];
+
+    for (stmt, kind, identifier, column) in cases.iter() {
+        let source = format!("{}{}", header, stmt);
+        let err = parsers::circuit(&source).expect_err(&source);
+        let err = err.syntax().expect("Expected a syntax error");
+        assert_eq!(
+            (err.kind(), err.line(), err.column(), err.identifier()),
+            (*kind, 5, *column, Some(*identifier)),
+            "{}",
+            source,
+        );
+    }
+}
+
+
+#[test]
+fn port_placement() {
+    use crate::error::SyntaxErrorKind as K;
+
+    let spaced = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "\n",
+        "    input a: UInt<1>\n",
+        "    ; comment\n",
+        "  \n",
+        "    output b: UInt<1> ; trailing comment\n",
+        "\n",
+        "    b <= a\n",
+    );
+    let circuit = parsers::circuit(spaced).expect("Could not parse circuit");
+    assert_eq!(circuit.top_module().ports().count(), 2);
+
+    let misplaced = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    node n = a\n",
+        "    output b: UInt<1>\n",
+    );
+    let err = parsers::circuit(misplaced).expect_err("Parsed misplaced port");
+    let err = err.syntax().expect("Expected a syntax error");
+    assert_eq!((err.kind(), err.line(), err.column()), (K::MisplacedPort, 5, 5));
+}
+
+
+#[quickcheck]
+fn parse_recovering_valid(original: Circuit) -> Result<TestResult, ParseError> {
+    let s = original.to_string();
+    let (parsed, diagnostics) = ParseOptions::new().parse_recovering(&s)?;
+    Ok(TestResult::from_bool(diagnostics.is_empty() && parsed == parsers::circuit(&s)?))
+}
+
+
+#[test]
+fn parse_recovering_malformed() {
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input a: UInt<1>\n",
+        "    output b: UInt<\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    output b: UInt<1>\n",
+        "    node n = frob(a)\n",
+        "    when a:\n",
+        "      b <= n\n",
+        "    else:\n",
+        "      b <= a\n",
+        "    b <= a\n",
+        "  modle Broken:\n",
+        "    input a: UInt<1>\n",
+    );
+
+    assert!(parsers::circuit(source).is_err());
+
+    let (circuit, diagnostics) = ParseOptions::new().parse_recovering(source).expect("Could not recover");
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.syntax().expect("Expected a syntax error").line()).collect();
+    assert_eq!(lines, vec![4, 8, 10, 14]);
+    assert_eq!(circuit.defined_modules().len(), 2);
+
+    let stmts = circuit.top_module().statements();
+    assert_eq!(stmts.len(), 2);
+    match stmts[0].kind() {
+        crate::stmt::Kind::Conditional{when, r#else, ..} => assert_eq!((when.len(), r#else.len()), (0, 1)),
+        kind => panic!("Expected conditional, found {:?}", kind),
+    }
+}
+
+
+#[test]
+fn parse_recovering_nested_when() {
+    use crate::stmt::Kind;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    input c: UInt<1>\n",
+        "    output b: UInt<1>\n",
+        "    when a:\n",
+        "      when c:\n",
+        "        b <= frob(a)\n",
+        "        b <= c\n",
+        "      else:\n",
+        "        b <= a\n",
+        "        node n = frob(c)\n",
+        "        b <= c\n",
+        "    b <= a\n",
+    );
+
+    let (circuit, diagnostics) = ParseOptions::new().parse_recovering(source).expect("Could not recover");
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.syntax().expect("Expected a syntax error").line()).collect();
+    assert_eq!(lines, vec![8, 12]);
+
+    let stmts = circuit.top_module().statements();
+    assert_eq!(stmts.len(), 2);
+    let inner = match stmts[0].kind() {
+        Kind::Conditional{when, r#else, ..} if r#else.is_empty() && when.len() == 1 => &when[0],
+        kind => panic!("Expected conditional, found {:?}", kind),
+    };
+    match inner.kind() {
+        Kind::Conditional{when, r#else, ..} => assert_eq!((when.len(), r#else.len()), (1, 2)),
+        kind => panic!("Expected conditional, found {:?}", kind),
+    }
+}
+
+
+#[test]
+fn parse_recovering_match() {
+    use crate::stmt::Kind;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input e: {|A, B: UInt<1>|}\n",
+        "    output b: UInt<1>\n",
+        "    b <= UInt<1>(0)\n",
+        "    match e:\n",
+        "      A:\n",
+        "        b <= frob(e)\n",
+        "        b <= UInt<1>(1)\n",
+        "      B(x):\n",
+        "        b <= x\n",
+        "    b <= UInt<1>(0)\n",
+    );
+
+    let (circuit, diagnostics) = ParseOptions::new().parse_recovering(source).expect("Could not recover");
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.syntax().expect("Expected a syntax error").line()).collect();
+    assert_eq!(lines, vec![8]);
+
+    let stmts = circuit.top_module().statements();
+    assert_eq!(stmts.len(), 3);
+    match stmts[1].kind() {
+        Kind::Match{arms, ..} => {
+            let lens: Vec<_> = arms.iter().map(|a| a.stmts().len()).collect();
+            assert_eq!(lens, vec![1, 1]);
+        },
+        kind => panic!("Expected match, found {:?}", kind),
+    }
+}
+
+
+#[test]
+fn parse_recovering_layerblock() {
+    use crate::stmt::Kind;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  layer Debug, inline:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    layerblock Debug:\n",
+        "      node n = a\n",
+        "      node m = frob(a)\n",
+        "      node o = n\n",
+        "    node p = a\n",
+    );
+
+    let (circuit, diagnostics) = ParseOptions::new().parse_recovering(source).expect("Could not recover");
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.syntax().expect("Expected a syntax error").line()).collect();
+    assert_eq!(lines, vec![7]);
+
+    let stmts = circuit.top_module().statements();
+    assert_eq!(stmts.len(), 2);
+    match stmts[0].kind() {
+        Kind::LayerBlock{stmts, ..} => assert_eq!(stmts.len(), 2),
+        kind => panic!("Expected layer block, found {:?}", kind),
+    }
+}
+
+
+#[quickcheck]
+fn parse_version(version: super::Version) -> Result<bool, ParseError> {
+    Ok(version.to_string().parse::<super::Version>()? == version)
+}
+
+
+#[test]
+fn version_gated_grammar() {
+    let body = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    output b: UInt<1>\n",
+        "    invalidate b\n",
+        "    connect b, a\n",
+    );
+
+    assert!(parsers::circuit(body).is_err());
+    assert!(parsers::circuit(&format!("FIRRTL version 2.0.0\n{}", body)).is_err());
+
+    let circuit = parsers::circuit(&format!("FIRRTL version 3.3.0\n{}", body)).expect("Could not parse circuit");
+    assert_eq!(circuit.version(), Some(super::Version::new(3, 3, 0)));
+    assert_eq!(circuit.top_module().statements().len(), 2);
+    assert!(circuit.to_string().starts_with("FIRRTL version 3.3.0\ncircuit Top:\n"));
+    assert!(circuit.to_string().ends_with("    invalidate b\n    connect b, a\n"));
+
+    let legacy = body.replace("invalidate b", "b is invalid").replace("connect b, a", "b <= a");
+    assert!(parsers::circuit(&format!("FIRRTL version 3.3.0\n{}", legacy)).is_err());
+    let circuit = parsers::circuit(&format!("FIRRTL version 2.0.0\n{}", legacy)).expect("Could not parse circuit");
+    assert!(circuit.to_string().ends_with("    b is invalid\n    b <= a\n"));
+}
+
+
+#[quickcheck]
+fn parse_buf_read(original: Circuit) -> Result<TestResult, ParseError> {
+    let s = original.to_string();
+    Ok(TestResult::from_bool(Circuit::from_buf_read(s.as_bytes())? == parsers::circuit(&s)?))
+}
+
+
+#[test]
+fn buf_read_error_location() {
+    let cases = [
+        "circuit Top:\n  module Top:\n    input a: UInt<1>\n      node n = a\n",
+        "circuit Top:\n  module Top:\n    input a: UInt<1>\n    node n = foo(a)\n",
+        "circuit Top:\n  module Sub:\n    input a: UInt<1>\n\n  module Top:\n    input a: UInt<",
+        "circuit Top:\n  module Sub:\n    input a: UInt<1>\n  ; comment\n  module Top:\n    output b: UInt<1>\n    b <= c\n",
+        "circuit Top",
+    ];
+
+    for source in cases.iter() {
+        let expected = parsers::circuit(source).expect_err(source);
+        let err = Circuit::from_buf_read(source.as_bytes()).expect_err(source);
+        assert_eq!(err.syntax(), expected.syntax(), "{}", source);
+    }
+}
+
+
+#[quickcheck]
+fn parse_crlf(original: Circuit, final_newline: bool) -> Result<TestResult, ParseError> {
+    let mut s = original.to_string().replace('\n', "\r\n");
+    if !final_newline {
+        s.truncate(s.trim_end().len());
+    }
+    Ok(TestResult::from_bool(parsers::circuit(&s)? == parsers::circuit(&original.to_string())?))
+}
+
+
+#[quickcheck]
+fn parse_concatenated(a: Circuit, b: Circuit) -> Result<TestResult, ParseError> {
+    let parsed = parsers::circuits(&format!("{}\n{}", a, b))?;
+    Ok(TestResult::from_bool(parsed == vec![parsers::circuit(&a.to_string())?, parsers::circuit(&b.to_string())?]))
+}
+
+
+#[test]
+fn concatenated_error_location() {
+    let source = concat!(
+        "; leading comment\n",
+        "circuit A:\n",
+        "  module A:\n",
+        "    input a: UInt<1>\n",
+        "FIRRTL version 3.3.0\n",
+        "circuit B:\n",
+        "  module B:\n",
+        "    input a: UInt<\n",
+    );
+
+    let err = parsers::circuits(source).expect_err("Parsed malformed circuit");
+    assert_eq!(err.syntax().map(|e| e.line()), Some(8));
+
+    let circuits = parsers::circuits(&source.replace("UInt<\n", "UInt<1>\n")).expect("Could not parse circuits");
+    let names: Vec<_> = circuits.iter().map(|c| c.top_module().name().to_string()).collect();
+    assert_eq!(names, ["A", "B"]);
+    assert_eq!(circuits[1].version(), Some(super::Version::new(3, 3, 0)));
+}
+
+
+#[test]
+fn duplicate_field_names() {
+    let wire = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    wire w: {b: UInt<1>, c: {d: UInt<1>, d: SInt<1>}[2]}\n",
+        "    w is invalid\n",
+    );
+    let port = "circuit Top:\n  module Top:\n    input a: {b: UInt<1>, b: UInt<2>}\n";
+    let unique = "circuit Top:\n  module Top:\n    input a: {b: UInt<1>, c: {b: UInt<2>}}\n";
+
+    assert!(parsers::circuit(wire).is_ok());
+    assert!(parsers::circuit(port).is_ok());
+    assert!(ParseOptions::new().with_unique_fields(true).parse(wire).is_err());
+    assert!(ParseOptions::new().with_unique_fields(true).parse(port).is_err());
+    assert!(ParseOptions::new().with_unique_fields(true).parse(unique).is_ok());
+}
+
+
+#[test]
+fn layers() {
+    use crate::stmt::Kind;
+    use super::layer::Convention;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  layer Verification, bind, \"verification\":\n",
+        "    layer Assert, inline:\n",
+        "  layer Debug, inline:\n",
+        "\n",
+        "  module Top enablelayer Debug enablelayer Verification.Assert:\n",
+        "    input a: UInt<1>\n",
+        "    layerblock Verification:\n",
+        "      node n = a\n",
+        "      layerblock Assert:\n",
+        "        skip\n",
+    );
+    let circuit = parsers::circuit(source).expect("Could not parse circuit");
+
+    let layer = circuit.layer("Verification").expect("Missing layer");
+    assert_eq!(layer.convention(), Convention::Bind);
+    assert_eq!(layer.output_dir(), Some("verification"));
+    assert_eq!(circuit.layer("Verification.Assert").map(|l| l.convention()), Some(Convention::Inline));
+    assert!(circuit.layer("Debug.Assert").is_none());
+
+    let top = circuit.top_module();
+    let enabled: Vec<_> = top.enabled_layers().iter().map(AsRef::as_ref).collect();
+    assert_eq!(enabled, ["Debug", "Verification.Assert"]);
+    let stmts = top.statements();
+    assert!(matches!(stmts[0].kind(), Kind::LayerBlock{layer, ..} if layer.as_ref() == "Verification"));
+
+    assert_eq!(parsers::circuit(&circuit.to_string()).expect("Could not reparse circuit"), circuit);
+    assert_eq!(Circuit::from_buf_read(source.as_bytes()).expect("Could not read circuit"), circuit);
+
+    let mut cache = Vec::new();
+    circuit.save_cache(&mut cache).expect("Could not save cache");
+    assert_eq!(Circuit::load_cache(cache.as_slice()).expect("Could not load cache"), circuit);
+}
+
+
+#[test]
+fn type_aliases() {
+    use crate::emit::{Emitter, FirrtlEmitter};
+    use crate::types::{BundleField, GroundType, Type};
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  type Pair = {a: UInt<1>, b: UInt<1>}\n",
+        "  type Pairs = Pair[2]\n",
+        "  module Top:\n",
+        "    input p: Pairs\n",
+        "    output q: Pair\n",
+        "    output r: {a: UInt<1>, b: UInt<1>}\n",
+        "    q <= p[0]\n",
+        "    r <= p[1]\n",
+    );
+    let circuit = parsers::circuit(source).expect("Could not parse circuit");
+
+    let bit = Type::GroundType(GroundType::UInt(Some(1)));
+    let pair = Type::Bundle(vec![BundleField::new("a", bit.clone()), BundleField::new("b", bit)].into());
+    let pair_alias = Type::Alias("Pair".into(), Arc::new(pair.clone()));
+    let pairs = Type::Vector(Arc::new(pair_alias.clone()), 2);
+    assert_eq!(circuit.type_alias("Pair").map(|a| a.r#type()), Some(&pair));
+    assert_eq!(circuit.type_alias("Pairs").map(|a| a.r#type()), Some(&pairs));
+    let ports: Vec<_> = circuit.top_module().ports().map(|p| p.r#type().clone()).collect();
+    assert_eq!(ports, [Type::Alias("Pairs".into(), Arc::new(pairs.clone())), pair_alias, pair.clone()]);
+    assert_eq!(ports[0].expanded(), Type::Vector(Arc::new(pair.clone()), 2));
+
+    let preserved = circuit.to_string();
+    assert!(preserved.contains("  type Pairs = Pair[2]\n"), "{}", preserved);
+    assert!(preserved.contains("    input p: Pairs\n"), "{}", preserved);
+    assert!(preserved.contains("    output r: {a: UInt<1>, b: UInt<1>}\n"), "{}", preserved);
+    assert_eq!(parsers::circuit(&preserved).expect("Could not reparse circuit"), circuit);
+
+    let mut emitter = FirrtlEmitter::new(String::new()).with_expanded_aliases(true);
+    emitter.circuit(&circuit).expect("Could not emit circuit");
+    let expanded = emitter.into_inner();
+    assert!(!expanded.contains("type"), "{}", expanded);
+    assert!(expanded.contains("    input p: {a: UInt<1>, b: UInt<1>}[2]\n"), "{}", expanded);
+    let reparsed = parsers::circuit(&expanded).expect("Could not reparse circuit");
+    assert!(reparsed.type_aliases().is_empty());
+    assert_eq!(reparsed.top_module().as_ref(), &circuit.top_module().with_expanded_aliases());
+
+    assert_eq!(Circuit::from_buf_read(source.as_bytes()).expect("Could not read circuit"), circuit);
+
+    let mut cache = Vec::new();
+    circuit.save_cache(&mut cache).expect("Could not save cache");
+    assert_eq!(Circuit::load_cache(cache.as_slice()).expect("Could not load cache"), circuit);
+}
+
+
+#[test]
+fn type_alias_canonical_form() {
+    let source = |width| format!(
+        "circuit Top:\n  type Word = UInt<{}>\n  module Top:\n    input a: Word\n    output b: Word\n    b <= a\n",
+        width,
+    );
+    let narrow = parsers::circuit(&source(8)).expect("Could not parse circuit");
+    let wide = parsers::circuit(&source(16)).expect("Could not parse circuit");
+
+    let canonical = narrow.to_canonical_string();
+    assert!(!canonical.contains("Word"), "{}", canonical);
+    assert!(canonical.contains("    input a: UInt<8>\n"), "{}", canonical);
+    assert_eq!(parsers::circuit(&canonical).expect("Could not reparse circuit").to_canonical_string(), canonical);
+    assert_ne!(narrow.top_module().content_hash(), wide.top_module().content_hash());
+}
+
+
+#[test]
+fn option_groups() {
+    use crate::info::WithInfo;
+    use crate::stmt::Entity;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  option Platform:\n",
+        "    FPGA\n",
+        "    ASIC\n",
+        "  module Generic:\n",
+        "    input i: UInt<1>\n",
+        "    output o: UInt<1>\n",
+        "    o <= i\n",
+        "  module FPGATarget:\n",
+        "    input i: UInt<1>\n",
+        "    output o: UInt<1>\n",
+        "    o <= i\n",
+        "  module Top:\n",
+        "    input i: UInt<1>\n",
+        "    output o: UInt<1>\n",
+        "    instchoice x of Generic, Platform :\n",
+        "      FPGA => FPGATarget\n",
+        "    x.i <= i\n",
+        "    o <= x.o\n",
+    );
+    let circuit = parsers::circuit(source).expect("Could not parse circuit");
+
+    let platform = circuit.option_group("Platform").expect("Option group not found");
+    assert_eq!(platform.cases(), [Arc::from("FPGA"), Arc::from("ASIC")]);
+    let choice = circuit
+        .top_module()
+        .statements()
+        .iter()
+        .flat_map(crate::stmt::Statement::instance_choices)
+        .next()
+        .expect("Instance choice not found");
+    assert_eq!(choice.option().as_ref(), "Platform");
+    assert_eq!(choice.module("FPGA").name().as_ref(), "FPGATarget");
+    assert_eq!(choice.module("ASIC").name().as_ref(), "Generic");
+    assert_eq!(circuit.unreferenced_modules().count(), 0);
+
+    let mut renamed = circuit.top_module().with_entities_renamed(|e| match e {
+        Entity::InstanceChoice(..) => Some("y".into()),
+        _ => None,
+    });
+    renamed = renamed.with_substituted_instances(|m| Some(Arc::new(m.as_ref().clone().with_info(Some("sub".into())))));
+    let choice = renamed.statements().iter().flat_map(crate::stmt::Statement::instance_choices).next().unwrap();
+    assert_eq!(choice.name().as_ref(), "y");
+    assert!(choice.modules().all(|m| m.info() == Some("sub")));
+
+    let formatted = circuit.to_string();
+    assert!(formatted.contains("  option Platform:\n    FPGA\n    ASIC\n"), "{}", formatted);
+    assert!(formatted.contains("    instchoice x of Generic, Platform:\n      FPGA => FPGATarget\n"), "{}", formatted);
+    assert_eq!(parsers::circuit(&formatted).expect("Could not reparse circuit"), circuit);
+
+    assert_eq!(Circuit::from_buf_read(source.as_bytes()).expect("Could not read circuit"), circuit);
+
+    let mut cache = Vec::new();
+    circuit.save_cache(&mut cache).expect("Could not save cache");
+    assert_eq!(Circuit::load_cache(cache.as_slice()).expect("Could not load cache"), circuit);
+}
@@ -53,3 +53,49 @@ where I: IntoIterator<Item = E> + Clone,
     }
 }
 
+
+
+/// Utility for formatting a string as a JSON string literal
+///
+/// The string will be quoted and escaped as required by JSON.
+pub struct JsonString<'a>(pub &'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&'"', f)?;
+        self.0.chars().try_for_each(|c| match c {
+            '"'     => write!(f, "\\\""),
+            '\\'    => write!(f, "\\\\"),
+            '\n'    => write!(f, "\\n"),
+            '\r'    => write!(f, "\\r"),
+            '\t'    => write!(f, "\\t"),
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32),
+            c       => fmt::Display::fmt(&c, f),
+        })?;
+        fmt::Display::fmt(&'"', f)
+    }
+}
+
+
+/// Utility for formatting a string as XML character data
+///
+/// Characters with special meaning in XML are replaced by entity references.
+/// The result may be used both as element content and as attribute value.
+/// Control characters other than tabs and line breaks can not be represented
+/// in XML 1.0 and are replaced by the replacement character.
+pub struct XmlString<'a>(pub &'a str);
+
+impl fmt::Display for XmlString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.chars().try_for_each(|c| match c {
+            '&'                 => write!(f, "&amp;"),
+            '<'                 => write!(f, "&lt;"),
+            '>'                 => write!(f, "&gt;"),
+            '"'                 => write!(f, "&quot;"),
+            '\''                => write!(f, "&apos;"),
+            '\n' | '\r' | '\t'  => fmt::Display::fmt(&c, f),
+            c if c.is_control() => fmt::Display::fmt(&char::REPLACEMENT_CHARACTER, f),
+            c                   => fmt::Display::fmt(&c, f),
+        })
+    }
+}
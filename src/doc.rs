@@ -0,0 +1,409 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Documentation generation
+//!
+//! This module provides [generate], which renders human-readable
+//! documentation for a circuit, e.g. as an artifact for design reviews. The
+//! documentation contains the instance hierarchy and, for every module, its
+//! ports, parameters, instances and memories. Info attributes are rendered as
+//! source locations which may be turned into links by specifying a base URL
+//! via [Options::with_source_url].
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::doc::{self, Format, Options};
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo: @[Foo.scala 3:7]\n",
+//!     "    input a: UInt<8>\n",
+//!     "    output b: UInt<8>\n",
+//!     "    b <= a\n",
+//! )).unwrap();
+//! let options = Options::default().with_source_url("https://example.com/src/");
+//! let markdown = doc::generate(&circuit, &options);
+//! assert!(markdown.contains("| `a` | input | `UInt<8>` |"));
+//! assert!(markdown.contains("(https://example.com/src/Foo.scala#L3)"));
+//!
+//! let html = doc::generate(&circuit, &options.with_format(Format::Html));
+//! assert!(html.contains("<td><code>UInt&lt;8&gt;</code></td>"));
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Write;
+
+use crate::circuit::Circuit;
+use crate::display::XmlString;
+use crate::hierarchy::InstanceNode;
+use crate::info::WithInfo;
+use crate::module::{Kind as ModuleKind, Module};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+use crate::types::Typed;
+
+
+/// Generate documentation for the given circuit
+pub fn generate(circuit: &Circuit, options: &Options) -> String {
+    let mut doc = Document {options, out: Default::default()};
+
+    doc.begin(circuit.top_module().name());
+    doc.heading(1, &[Inline::Text("Circuit ".into()), Inline::Code(circuit.top_module().name().to_string())]);
+    if let Some(info) = circuit.info() {
+        doc.paragraph(&[Inline::Text("Defined at ".into()), options.source(info)]);
+    }
+
+    doc.heading(2, &[Inline::Text("Instance hierarchy".into())]);
+    doc.tree(&circuit.elaborate());
+
+    doc.heading(2, &[Inline::Text("Modules".into())]);
+    circuit.modules().for_each(|m| doc.module(m));
+
+    doc.end();
+    doc.out
+}
+
+
+/// Options for documentation generation
+///
+/// Options are created with sensible defaults via [Default] and may be
+/// adjusted via their `with_*` functions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    format: Format,
+    source_url: Option<String>,
+}
+
+impl Options {
+    /// Set the output format
+    pub fn with_format(self, format: Format) -> Self {
+        Self {format, ..self}
+    }
+
+    /// Set the base URL for links to sources
+    ///
+    /// If set, source locations extracted from info attributes are rendered
+    /// as links. The link is formed by appending the file name and a fragment
+    /// selecting the line to the base URL, e.g. `Foo.scala#L12`.
+    pub fn with_source_url(self, url: impl Into<String>) -> Self {
+        Self {source_url: Some(url.into()), ..self}
+    }
+
+    /// Retrieve the output format
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Render the given info attribute
+    fn source(&self, info: &str) -> Inline {
+        let url = self.source_url.as_ref().and_then(|base| {
+            let (file, line) = location(info)?;
+            Some(format!("{}{}#L{}", base, file, line))
+        });
+        match url {
+            Some(url)   => Inline::Link(info.to_string(), url),
+            None        => Inline::Code(info.to_string()),
+        }
+    }
+}
+
+
+/// Output format of the documentation
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Markdown, with tables as supported by CommonMark extensions
+    #[default]
+    Markdown,
+    /// A self-contained HTML document
+    Html,
+}
+
+
+/// Extract the file and line number from an info attribute
+///
+/// Both the `file line:col` form emitted by Chisel and the `file:line:col`
+/// form are supported. Only the first location is considered.
+fn location(info: &str) -> Option<(&str, u32)> {
+    let mut words = info.split_whitespace();
+    let first = words.next()?;
+    let line = |s: &str| s.split(':').next().and_then(|l| l.parse().ok());
+
+    match first.split_once(':') {
+        Some((file, rest))  => line(rest).map(|l| (file, l)),
+        None                => words.next().and_then(line).map(|l| (first, l)),
+    }
+}
+
+
+/// Inline content
+#[derive(Clone, Debug)]
+enum Inline {
+    Text(String),
+    Code(String),
+    Link(String, String),
+}
+
+
+/// A document under construction
+struct Document<'a> {
+    options: &'a Options,
+    out: String,
+}
+
+impl Document<'_> {
+    /// Document a single module
+    fn module(&mut self, module: &Module) {
+        let keyword = match module.kind() {
+            ModuleKind::Regular{..}     => "Module ",
+            ModuleKind::External{..}    => "External module ",
+        };
+        self.heading(3, &[Inline::Text(keyword.into()), Inline::Code(module.name().to_string())]);
+        if let Some(info) = module.info() {
+            self.paragraph(&[Inline::Text("Defined at ".into()), self.options.source(info)]);
+        }
+
+        if let ModuleKind::External{defname, params, ..} = module.kind() {
+            if let Some(defname) = defname {
+                self.paragraph(&[Inline::Text("Refers to ".into()), Inline::Code(defname.to_string())]);
+            }
+            if !params.is_empty() {
+                let mut params: Vec<_> = params.iter().collect();
+                params.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                self.heading(4, &[Inline::Text("Parameters".into())]);
+                self.table(&["Name", "Value"], params
+                    .into_iter()
+                    .map(|(n, v)| vec![Inline::Code(n.to_string()), Inline::Code(v.to_string())])
+                    .collect());
+            }
+        }
+
+        if module.ports().next().is_some() {
+            self.heading(4, &[Inline::Text("Ports".into())]);
+            let rows = module.ports().map(|p| vec![
+                Inline::Code(p.name().to_string()),
+                Inline::Text(p.direction().keyword().into()),
+                Inline::Code(p.r#type().to_string()),
+                self.source_cell(p.info()),
+            ]).collect();
+            self.table(&["Name", "Direction", "Type", "Source"], rows);
+        }
+
+        let stmts = || module.statements().iter().flat_map(transiter::AutoTransIter::trans_iter);
+
+        let instances: Vec<_> = stmts().filter_map(|s| match s.kind() {
+            Kind::Declaration(e) => match e.as_ref() {
+                Entity::Instance(i) => Some(vec![
+                    Inline::Code(i.name().to_string()),
+                    Inline::Code(i.module().name().to_string()),
+                    self.source_cell(s.info()),
+                ]),
+                Entity::InstanceChoice(i) => Some(vec![
+                    Inline::Code(i.name().to_string()),
+                    Inline::Code(i.default_module().name().to_string()),
+                    self.source_cell(s.info()),
+                ]),
+                _ => None,
+            },
+            _ => None,
+        }).collect();
+        if !instances.is_empty() {
+            self.heading(4, &[Inline::Text("Instances".into())]);
+            self.table(&["Name", "Module", "Source"], instances);
+        }
+
+        let memories: Vec<_> = stmts().filter_map(|s| self.memory_row(s)).collect();
+        if !memories.is_empty() {
+            self.heading(4, &[Inline::Text("Memories".into())]);
+            self.table(&["Name", "Kind", "Type", "Depth", "Bits", "Source"], memories);
+        }
+    }
+
+    /// Create a row in the memory inventory for the given statement
+    ///
+    /// If the statement does not declare a memory, this function returns
+    /// `None`.
+    fn memory_row(&self, stmt: &Statement) -> Option<Vec<Inline>> {
+        let optional = |v: Option<u64>| Inline::Text(v.map(|v| v.to_string()).unwrap_or_else(|| "?".into()));
+
+        let (name, kind, r#type, depth, bits) = match stmt.kind() {
+            Kind::Declaration(e) => match e.as_ref() {
+                Entity::Memory(m)   => (m.name(), "mem", m.data_type().clone(), Some(m.depth()), m.bits()),
+                _                   => return None,
+            },
+            Kind::SimpleMemDecl(m) => {
+                let r#type = m.r#type().ok()?;
+                let depth = r#type.vector().map(|(_, w)| w.into());
+                (m.name(), m.kind().keyword(), r#type, depth, m.bits())
+            },
+            _ => return None,
+        };
+
+        Some(vec![
+            Inline::Code(name.to_string()),
+            Inline::Text(kind.into()),
+            Inline::Code(r#type.to_string()),
+            optional(depth),
+            optional(bits),
+            self.source_cell(stmt.info()),
+        ])
+    }
+
+    /// Create a table cell holding the given info attribute
+    fn source_cell(&self, info: Option<&str>) -> Inline {
+        info.map(|i| self.options.source(i)).unwrap_or_else(|| Inline::Text(Default::default()))
+    }
+
+    /// Emit the beginning of the document
+    fn begin(&mut self, title: &str) {
+        if self.options.format == Format::Html {
+            self.out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+            let _ = writeln!(self.out, "<title>{}</title>", XmlString(title));
+            self.out.push_str("</head>\n<body>\n");
+        }
+    }
+
+    /// Emit the end of the document
+    fn end(&mut self) {
+        if self.options.format == Format::Html {
+            self.out.push_str("</body>\n</html>\n");
+        }
+    }
+
+    /// Emit a heading of the given level
+    fn heading(&mut self, level: usize, content: &[Inline]) {
+        match self.options.format {
+            Format::Markdown    => {
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+                self.inlines(content);
+                self.out.push_str("\n\n");
+            },
+            Format::Html        => {
+                let _ = write!(self.out, "<h{}>", level);
+                self.inlines(content);
+                let _ = writeln!(self.out, "</h{}>", level);
+            },
+        }
+    }
+
+    /// Emit a paragraph
+    fn paragraph(&mut self, content: &[Inline]) {
+        match self.options.format {
+            Format::Markdown    => {
+                self.inlines(content);
+                self.out.push_str("\n\n");
+            },
+            Format::Html        => {
+                self.out.push_str("<p>");
+                self.inlines(content);
+                self.out.push_str("</p>\n");
+            },
+        }
+    }
+
+    /// Emit a table with the given header and rows
+    fn table(&mut self, header: &[&str], rows: Vec<Vec<Inline>>) {
+        match self.options.format {
+            Format::Markdown    => {
+                let _ = writeln!(self.out, "| {} |", header.join(" | "));
+                let _ = writeln!(self.out, "|{}", " --- |".repeat(header.len()));
+                rows.into_iter().for_each(|r| {
+                    self.out.push('|');
+                    r.iter().for_each(|c| {
+                        self.out.push(' ');
+                        self.inlines(std::slice::from_ref(c));
+                        self.out.push_str(" |");
+                    });
+                    self.out.push('\n');
+                });
+                self.out.push('\n');
+            },
+            Format::Html        => {
+                self.out.push_str("<table>\n<tr>");
+                header.iter().for_each(|h| { let _ = write!(self.out, "<th>{}</th>", XmlString(h)); });
+                self.out.push_str("</tr>\n");
+                rows.into_iter().for_each(|r| {
+                    self.out.push_str("<tr>");
+                    r.iter().for_each(|c| {
+                        self.out.push_str("<td>");
+                        self.inlines(std::slice::from_ref(c));
+                        self.out.push_str("</td>");
+                    });
+                    self.out.push_str("</tr>\n");
+                });
+                self.out.push_str("</table>\n");
+            },
+        }
+    }
+
+    /// Emit the instance tree rooted at the given node as a nested list
+    fn tree(&mut self, node: &InstanceNode) {
+        let item = |n: &InstanceNode| match n.instance_name() {
+            Some(i) => vec![
+                Inline::Code(i.to_string()),
+                Inline::Text(": ".into()),
+                Inline::Code(n.module().name().to_string()),
+            ],
+            None    => vec![Inline::Code(n.module().name().to_string())],
+        };
+
+        match self.options.format {
+            Format::Markdown    => {
+                node.iter().for_each(|n| {
+                    self.out.push_str(&"  ".repeat(n.depth()));
+                    self.out.push_str("- ");
+                    self.inlines(&item(n));
+                    self.out.push('\n');
+                });
+                self.out.push('\n');
+            },
+            Format::Html        => {
+                self.out.push_str("<ul>\n");
+                self.tree_item(node, &item);
+                self.out.push_str("</ul>\n");
+            },
+        }
+    }
+
+    /// Emit a list item for the given node and its children in HTML
+    fn tree_item(&mut self, node: &InstanceNode, item: &impl Fn(&InstanceNode) -> Vec<Inline>) {
+        self.out.push_str("<li>");
+        self.inlines(&item(node));
+        if !node.children().is_empty() {
+            self.out.push_str("\n<ul>\n");
+            node.children().iter().for_each(|c| self.tree_item(c, item));
+            self.out.push_str("</ul>\n");
+        }
+        self.out.push_str("</li>\n");
+    }
+
+    /// Emit inline content
+    fn inlines(&mut self, content: &[Inline]) {
+        content.iter().for_each(|i| {
+            let _ = match (self.options.format, i) {
+                (Format::Markdown, Inline::Text(t))     => write!(self.out, "{}", markdown_escaped(t)),
+                (Format::Markdown, Inline::Code(c))     => write!(self.out, "`{}`", c.replace('|', "\\|").replace('\n', " ")),
+                (Format::Markdown, Inline::Link(t, u))  =>
+                    write!(self.out, "[{}]({})", markdown_escaped(t), u.replace(' ', "%20")),
+                (Format::Html, Inline::Text(t))         => write!(self.out, "{}", XmlString(t)),
+                (Format::Html, Inline::Code(c))         => write!(self.out, "<code>{}</code>", XmlString(c)),
+                (Format::Html, Inline::Link(t, u))      =>
+                    write!(self.out, "<a href=\"{}\">{}</a>", XmlString(u), XmlString(t)),
+            };
+        })
+    }
+}
+
+
+/// Escape characters with special meaning in Markdown
+fn markdown_escaped(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut res, c| {
+        if "\\`*_[]<>|#".contains(c) {
+            res.push('\\');
+        }
+        res.push(c);
+        res
+    })
+}
@@ -0,0 +1,31 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to documentation generation
+
+use crate::circuit::Circuit;
+use crate::named::Named;
+
+use super::*;
+
+
+#[quickcheck]
+fn doc_lists_modules(circuit: Circuit) -> bool {
+    let markdown = generate(&circuit, &Default::default());
+    let html = generate(&circuit, &Options::default().with_format(Format::Html));
+    circuit.modules().all(|m| markdown.contains(&format!(" `{}`\n", m.name()))) &&
+        html.matches("<table>").count() == html.matches("</table>").count() &&
+        html.matches("<ul>").count() == html.matches("</ul>").count() &&
+        html.matches("<li>").count() == circuit.elaborate().iter().count()
+}
+
+
+#[quickcheck]
+fn doc_source_location(file: crate::tests::Identifier, line: u32, col: u16, colon: bool) -> bool {
+    let info = if colon {
+        format!("{}.scala:{}:{}", file, line, col)
+    } else {
+        format!("{}.scala {}:{}", file, line, col)
+    };
+    let file = format!("{}.scala", file);
+    location(&info) == Some((file.as_str(), line))
+}
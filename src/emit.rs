@@ -0,0 +1,337 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Pluggable emitters
+//!
+//! Backends such as alternative printers, graph exporters or metrics
+//! collectors all need to walk the AST. This module provides the [Emitter]
+//! trait featuring a hook for every kind of node. By default, each hook visits
+//! the node's children via the corresponding `walk_*` function. Hence,
+//! implementors only need to override the hooks they are interested in.
+//!
+//! The FIRRTL text printer behind the [Display](fmt::Display) implementation
+//! of [Circuit] is implemented on top of this trait as [FirrtlEmitter]. For
+//! comparisons against golden files generated by legacy tooling, the
+//! [LegacyEmitter] reproduces the output of the Scala FIRRTL compiler.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::emit::{Emitter, walk_statement};
+//! use firrtl_ast::stmt::Statement;
+//!
+//! #[derive(Default)]
+//! struct StatementCounter(usize);
+//!
+//! impl Emitter for StatementCounter {
+//!     type Error = std::convert::Infallible;
+//!
+//!     fn statement(&mut self, stmt: &Statement) -> Result<(), Self::Error> {
+//!         self.0 += 1;
+//!         walk_statement(self, stmt)
+//!     }
+//! }
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    input a: UInt<1>\n",
+//!     "    output b: UInt<1>\n",
+//!     "    when a:\n",
+//!     "      b <= a\n",
+//! )).unwrap();
+//! let mut counter = StatementCounter::default();
+//! counter.circuit(&circuit).unwrap();
+//! assert_eq!(counter.0, 2);
+//! ```
+
+mod legacy;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::circuit::{Circuit, Version};
+use crate::expr;
+use crate::indentation::{DisplayIndented, Indentation};
+use crate::info::Info;
+use crate::module::{self, Module, Port};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+
+pub use legacy::LegacyEmitter;
+
+
+/// Expression type visited by an [Emitter]
+pub type Expression = expr::Expression<Arc<Entity>>;
+
+
+/// Visitor-based emitter for the AST
+///
+/// The hooks are called for the respective nodes while walking the AST. The
+/// default implementation of each hook visits the node's children. Overriding
+/// implementations may call the corresponding `walk_*` function for visiting
+/// the children before or after processing the node itself, or not at all.
+pub trait Emitter {
+    /// Error type emitted by the hooks
+    type Error;
+
+    /// Emit a circuit
+    ///
+    /// By default, this hook visits all modules defined in the circuit.
+    fn circuit(&mut self, circuit: &Circuit) -> Result<(), Self::Error> {
+        walk_circuit(self, circuit)
+    }
+
+    /// Emit a module
+    ///
+    /// By default, this hook visits the module's ports and statements.
+    fn module(&mut self, module: &Module) -> Result<(), Self::Error> {
+        walk_module(self, module)
+    }
+
+    /// Emit a port
+    fn port(&mut self, _port: &Port) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Emit a statement
+    ///
+    /// By default, this hook visits the entity declared, the expressions
+    /// used directly and the statements nested in the statement.
+    fn statement(&mut self, stmt: &Statement) -> Result<(), Self::Error> {
+        walk_statement(self, stmt)
+    }
+
+    /// Emit an entity declared by a statement
+    fn entity(&mut self, _entity: &Entity) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Emit an expression
+    ///
+    /// By default, this hook visits all direct sub-expressions.
+    fn expression(&mut self, expr: &Expression) -> Result<(), Self::Error> {
+        walk_expression(self, expr)
+    }
+}
+
+
+/// Visit all modules defined in the given circuit
+pub fn walk_circuit<E: Emitter + ?Sized>(emitter: &mut E, circuit: &Circuit) -> Result<(), E::Error> {
+    circuit.defined_modules().iter().try_for_each(|m| emitter.module(m))
+}
+
+
+/// Visit the ports and statements of the given module
+pub fn walk_module<E: Emitter + ?Sized>(emitter: &mut E, module: &Module) -> Result<(), E::Error> {
+    module.ports().try_for_each(|p| emitter.port(p))?;
+    module.statements().iter().try_for_each(|s| emitter.statement(s))
+}
+
+
+/// Visit the children of the given statement
+///
+/// This function visits the entity declared by the statement, if any, all
+/// expressions used directly in the statement and all statements nested in
+/// it, in this order.
+pub fn walk_statement<E: Emitter + ?Sized>(emitter: &mut E, stmt: &Statement) -> Result<(), E::Error> {
+    if let Kind::Declaration(entity) = stmt.kind() {
+        emitter.entity(entity)?;
+    }
+    stmt.expressions().into_iter().try_for_each(|e| emitter.expression(e))?;
+    match stmt.kind() {
+        Kind::Conditional{when, r#else, ..} => when.iter().chain(r#else.iter()).try_for_each(|s| emitter.statement(s))?,
+        Kind::LayerBlock{stmts, ..}         => stmts.iter().try_for_each(|s| emitter.statement(s))?,
+        _                                   => (),
+    }
+    Ok(())
+}
+
+
+/// Visit the direct sub-expressions of the given expression
+pub fn walk_expression<E: Emitter + ?Sized>(emitter: &mut E, expr: &Expression) -> Result<(), E::Error> {
+    use transiter::AutoTransIter;
+
+    <&Expression as AutoTransIter<&Expression>>::recurse(&expr)
+        .into_iter()
+        .try_for_each(|e| emitter.expression(e))
+}
+
+
+/// [Emitter] producing FIRRTL text
+///
+/// This emitter writes the FIRRTL representation of the nodes visited to some
+/// [fmt::Write]. Its output is identical to that of the `Display`
+/// implementation of [Circuit].
+#[derive(Debug)]
+pub struct FirrtlEmitter<W: fmt::Write> {
+    out: W,
+    indentation: Indentation,
+    expand_aliases: bool,
+    instance_parameters: bool,
+    version: Option<Version>,
+}
+
+impl<W: fmt::Write> FirrtlEmitter<W> {
+    /// Create a new emitter writing to the given output
+    pub fn new(out: W) -> Self {
+        Self {out, indentation: Indentation::root(), expand_aliases: false, instance_parameters: false, version: None}
+    }
+
+    /// Set whether to expand type aliases
+    ///
+    /// By default, the [type aliases](crate::types::alias) declared in a
+    /// circuit are emitted and uses of an alias are emitted as the alias'
+    /// name. If `expand` is `true`, no aliases are declared and all types are
+    /// emitted in full.
+    pub fn with_expanded_aliases(self, expand: bool) -> Self {
+        Self {expand_aliases: expand, ..self}
+    }
+
+    /// Set whether to emit parameter assignments of instances
+    ///
+    /// By default, only the instantiated module is emitted for an
+    /// [Instance](module::Instance). If `emit` is `true`, its parameter
+    /// assignments are emitted as well, using a syntax which is an extension
+    /// not covered by the FIRRTL specification. See
+    /// [Instance](module::Instance) for details.
+    pub fn with_instance_parameters(self, emit: bool) -> Self {
+        Self {instance_parameters: emit, ..self}
+    }
+
+    /// Set the FIRRTL version governing the grammar of the statements emitted
+    ///
+    /// For versions `3.0.0` and later, connections and invalidations are
+    /// emitted using the `connect` and `invalidate` keywords. When emitting a
+    /// [Circuit], its own [version](Circuit::version) is used instead.
+    pub fn with_version(self, version: impl Into<Option<Version>>) -> Self {
+        Self {version: version.into(), ..self}
+    }
+
+    /// Set the indentation of the nodes emitted
+    pub(crate) fn with_indentation(self, indentation: Indentation) -> Self {
+        Self {indentation, ..self}
+    }
+
+    /// Retrieve the indentation of the nodes emitted
+    pub(crate) fn indentation(&self) -> &Indentation {
+        &self.indentation
+    }
+
+    /// Retrieve the output
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Switch to a nested indentation level, returning the current one
+    fn enter(&mut self) -> Indentation {
+        let sub = self.indentation.sub();
+        std::mem::replace(&mut self.indentation, sub)
+    }
+
+    /// Emit a list of statements on a nested indentation level
+    ///
+    /// An empty list is emitted as a `skip` statement.
+    fn block(&mut self, stmts: &[Statement]) -> fmt::Result {
+        let parent = self.enter();
+        if stmts.is_empty() {
+            self.statement(&Kind::Empty.into())?;
+        } else {
+            stmts.iter().try_for_each(|s| self.statement(s))?;
+        }
+        self.indentation = parent;
+        Ok(())
+    }
+
+    /// Emit the remainder of a conditional statement
+    ///
+    /// The indentation is expected to be emitted already.
+    fn conditional(
+        &mut self,
+        cond: &Expression,
+        when: &[Statement],
+        r#else: &[Statement],
+        info: Info<'_>,
+    ) -> fmt::Result {
+        writeln!(self.out, "when {}:{}", cond, info)?;
+        self.block(when)?;
+
+        if let [stmt] = r#else {
+            if let Kind::Conditional{cond, when, r#else} = stmt.kind() {
+                write!(self.out, "{}else ", self.indentation.lock())?;
+                return self.conditional(cond, when, r#else, Info::of(stmt))
+            }
+        }
+
+        if r#else.is_empty() {
+            Ok(())
+        } else {
+            writeln!(self.out, "{}else:", self.indentation.lock())?;
+            self.block(r#else)
+        }
+    }
+}
+
+impl<W: fmt::Write> Emitter for FirrtlEmitter<W> {
+    type Error = fmt::Error;
+
+    fn circuit(&mut self, circuit: &Circuit) -> fmt::Result {
+        if let Some(version) = circuit.version() {
+            writeln!(self.out, "FIRRTL version {}", version)?;
+        }
+        writeln!(self.out, "circuit {}:{}", circuit.top_module().name(), Info::of(circuit))?;
+
+        self.version = circuit.version();
+        let parent = self.enter();
+        circuit.layers().iter().try_for_each(|l| l.fmt(&mut self.indentation, &mut self.out))?;
+        circuit.option_groups().iter().try_for_each(|o| o.fmt(&mut self.indentation, &mut self.out))?;
+        if self.expand_aliases {
+            circuit.defined_modules().iter().try_for_each(|m| self.module(&m.with_expanded_aliases()))?;
+        } else {
+            circuit.type_aliases()
+                .iter()
+                .try_for_each(|a| DisplayIndented::fmt(a, &mut self.indentation, &mut self.out))?;
+            walk_circuit(self, circuit)?;
+        }
+        self.indentation = parent;
+        Ok(())
+    }
+
+    fn module(&mut self, module: &Module) -> fmt::Result {
+        write!(self.out, "{}{} {}", self.indentation.lock(), module.kind().keyword(), module.name())?;
+        module.enabled_layers().iter().try_for_each(|l| write!(self.out, " enablelayer {}", l))?;
+        writeln!(self.out, ":{}", Info::of(module))?;
+
+        let parent = self.enter();
+        module.ports().try_for_each(|p| self.port(p))?;
+        match module.kind() {
+            module::Kind::Regular{stmts} => stmts.iter().try_for_each(|s| self.statement(s))?,
+            module::Kind::External{defname, params, ..} => {
+                if let Some(defname) = defname {
+                    writeln!(self.out, "{}defname = {}", self.indentation.lock(), defname)?;
+                }
+                module::sorted_params(params)
+                    .into_iter()
+                    .try_for_each(|(k, v)| writeln!(self.out, "{}parameter {} = {}", self.indentation.lock(), k, v))?
+            },
+        }
+        self.indentation = parent;
+        Ok(())
+    }
+
+    fn port(&mut self, port: &Port) -> fmt::Result {
+        port.fmt(&mut self.indentation, &mut self.out)
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> fmt::Result {
+        if let Kind::Conditional{cond, when, r#else} = stmt.kind() {
+            write!(self.out, "{}", self.indentation.lock())?;
+            self.conditional(cond, when, r#else, Info::of(stmt))
+        } else {
+            let syntax = crate::stmt::display::Syntax {version: self.version, instance_parameters: self.instance_parameters};
+            stmt.fmt_versioned(syntax, &mut self.indentation, &mut self.out)
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Emitter reproducing the output of legacy FIRRTL tooling
+
+use std::fmt;
+
+use crate::circuit::Circuit;
+use crate::indentation::{DisplayIndented, Indentation};
+use crate::info::Info;
+use crate::module::{self, Module, Port};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+use crate::types::{Orientation, Type, Typed};
+
+use super::{Emitter, Expression};
+
+
+/// [Emitter] producing FIRRTL text in the style of the Scala FIRRTL compiler
+///
+/// Golden files generated by the Scala FIRRTL compiler's printer (version 1.x)
+/// differ from the output of [FirrtlEmitter](super::FirrtlEmitter) in a number
+/// of details. This emitter reproduces these quirks, allowing byte-identical
+/// comparisons against such files:
+///
+/// * colons in declarations and headers are surrounded by spaces, e.g.
+///   `input a : UInt<1>` and `module Foo :`,
+/// * bundle types are emitted as `{ flip a : UInt<1>, b : UInt<1>}`,
+/// * literals are emitted with hexadecimal values, e.g. `UInt<4>("ha")`,
+/// * the ports of a regular module are separated from its body by a blank
+///   line and modules are separated from each other by a blank line,
+/// * the reset of a register is emitted on a separate line following a
+///   `with :`, with the info attribute at the end of that line,
+/// * info attributes are also emitted for `is invalid` statements,
+/// * `else` branches are always emitted as a block, i.e. `else when` chains
+///   are emitted as nested conditional statements, and
+/// * latencies of memories precede their ports.
+///
+/// Note that the register declarations emitted can not be parsed by this
+/// crate. Since legacy FIRRTL has no option groups, instance choices are
+/// emitted as instances of their default modules.
+#[derive(Debug)]
+pub struct LegacyEmitter<W: fmt::Write> {
+    out: W,
+    indentation: Indentation,
+}
+
+impl<W: fmt::Write> LegacyEmitter<W> {
+    /// Create a new emitter writing to the given output
+    pub fn new(out: W) -> Self {
+        Self {out, indentation: Indentation::root()}
+    }
+
+    /// Retrieve the output
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Switch to a nested indentation level, returning the current one
+    fn enter(&mut self) -> Indentation {
+        let sub = self.indentation.sub();
+        std::mem::replace(&mut self.indentation, sub)
+    }
+
+    /// Emit a list of statements on a nested indentation level
+    ///
+    /// An empty list is emitted as a `skip` statement.
+    fn block(&mut self, stmts: &[Statement]) -> fmt::Result {
+        let parent = self.enter();
+        if stmts.is_empty() {
+            self.statement(&Kind::Empty.into())?;
+        } else {
+            stmts.iter().try_for_each(|s| self.statement(s))?;
+        }
+        self.indentation = parent;
+        Ok(())
+    }
+
+    /// Emit the declaration of an entity
+    fn declaration(&mut self, entity: &Entity, info: Info<'_>) -> fmt::Result {
+        let indent = self.indentation.lock();
+        match entity {
+            Entity::Port(_)             => Err(Default::default()),
+            Entity::Wire{name, r#type}  =>
+                writeln!(self.out, "{}wire {} : {}{}", indent, name, LegacyType(r#type), info),
+            Entity::Register(reg)       => {
+                let r#type = reg.r#type().map_err(|_| fmt::Error)?;
+                write!(self.out, "{}reg {} : {}, {}", indent, reg.name(), LegacyType(&r#type), Legacy(reg.clock()))?;
+                if let (Some(sig), Some(val)) = (reg.reset_signal(), reg.reset_value()) {
+                    writeln!(self.out, " with :")?;
+                    write!(self.out, "{}reset => ({}, {})", self.indentation.sub().lock(), Legacy(sig), Legacy(val))?;
+                }
+                writeln!(self.out, "{}", info)
+            },
+            Entity::Node{name, value}   => writeln!(self.out, "{}node {} = {}{}", indent, name, Legacy(value), info),
+            Entity::Memory(mem)         => {
+                writeln!(self.out, "{}mem {} :{}", indent, mem.name(), info)?;
+                let indent = self.indentation.sub().lock();
+                writeln!(self.out, "{}data-type => {}", indent, LegacyType(mem.data_type()))?;
+                writeln!(self.out, "{}depth => {}", indent, mem.depth())?;
+                writeln!(self.out, "{}read-latency => {}", indent, mem.read_latency())?;
+                writeln!(self.out, "{}write-latency => {}", indent, mem.write_latency())?;
+                mem.ports().try_for_each(|p| writeln!(self.out, "{}{}", indent, p))?;
+                writeln!(self.out, "{}read-under-write => {}", indent, mem.read_under_write())
+            },
+            Entity::SimpleMemPort(port) => writeln!(self.out, "{}{}{}", indent, hex_literals(&port.to_string()), info),
+            Entity::Instance(inst)      => writeln!(self.out, "{}{}{}", indent, inst, info),
+            Entity::InstanceChoice(i)   =>
+                writeln!(self.out, "{}inst {} of {}{}", indent, i.name(), i.default_module().name(), info),
+            Entity::Binder{..}          => Err(Default::default()),
+        }
+    }
+}
+
+impl<W: fmt::Write> Emitter for LegacyEmitter<W> {
+    type Error = fmt::Error;
+
+    fn circuit(&mut self, circuit: &Circuit) -> fmt::Result {
+        if let Some(version) = circuit.version() {
+            writeln!(self.out, "FIRRTL version {}", version)?;
+        }
+        writeln!(self.out, "circuit {} :{}", circuit.top_module().name(), Info::of(circuit))?;
+
+        let parent = self.enter();
+        circuit.defined_modules().iter().enumerate().try_for_each(|(i, m)| {
+            if i > 0 {
+                writeln!(self.out)?;
+            }
+            self.module(m)
+        })?;
+        self.indentation = parent;
+        Ok(())
+    }
+
+    fn module(&mut self, module: &Module) -> fmt::Result {
+        writeln!(
+            self.out,
+            "{}{} {} :{}",
+            self.indentation.lock(),
+            module.kind().keyword(),
+            module.name(),
+            Info::of(module),
+        )?;
+
+        let parent = self.enter();
+        module.ports().try_for_each(|p| self.port(p))?;
+        match module.kind() {
+            module::Kind::Regular{stmts} => {
+                writeln!(self.out)?;
+                if stmts.is_empty() {
+                    self.statement(&Kind::Empty.into())?;
+                } else {
+                    stmts.iter().try_for_each(|s| self.statement(s))?;
+                }
+            },
+            module::Kind::External{defname, params, ..} => {
+                if let Some(defname) = defname {
+                    writeln!(self.out, "{}defname = {}", self.indentation.lock(), defname)?;
+                }
+                module::sorted_params(params)
+                    .into_iter()
+                    .try_for_each(|(k, v)| writeln!(self.out, "{}parameter {} = {}", self.indentation.lock(), k, v))?
+            },
+        }
+        self.indentation = parent;
+        Ok(())
+    }
+
+    fn port(&mut self, port: &Port) -> fmt::Result {
+        writeln!(
+            self.out,
+            "{}{} {} : {}{}",
+            self.indentation.lock(),
+            port.direction(),
+            port.name(),
+            LegacyType(port.r#type()),
+            Info::of(port),
+        )
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> fmt::Result {
+        use crate::display::CommaSeparated;
+
+        let info = Info::of(stmt);
+        let indent = self.indentation.lock();
+        match stmt.kind() {
+            Kind::Connection{from, to}              =>
+                writeln!(self.out, "{}{} <= {}{}", indent, Legacy(to), Legacy(from), info),
+            Kind::PartialConnection{from, to}       =>
+                writeln!(self.out, "{}{} <- {}{}", indent, Legacy(to), Legacy(from), info),
+            Kind::Declaration(entity)               => self.declaration(entity, info),
+            Kind::SimpleMemDecl(mem)                => {
+                let r#type = mem.r#type().map_err(|_| fmt::Error)?;
+                write!(self.out, "{}{} {} : {}", indent, mem.kind().keyword(), mem.name(), LegacyType(&r#type))?;
+                if let Some(ruw) = mem.kind().read_under_write() {
+                    write!(self.out, ", {}", ruw)?;
+                }
+                writeln!(self.out, "{}", info)
+            },
+            Kind::Invalidate(expr)                  =>
+                writeln!(self.out, "{}{} is invalid{}", indent, Legacy(expr), info),
+            Kind::Attach(exprs)                     => {
+                let exprs: Vec<_> = exprs.iter().map(Legacy).collect();
+                writeln!(self.out, "{}attach ({}){}", indent, CommaSeparated::from(&exprs), info)
+            },
+            Kind::Conditional{cond, when, r#else}   => {
+                writeln!(self.out, "{}when {} :{}", indent, Legacy(cond), info)?;
+                self.block(when)?;
+                if r#else.is_empty() {
+                    Ok(())
+                } else {
+                    writeln!(self.out, "{}else :", indent)?;
+                    self.block(r#else)
+                }
+            },
+            _ => {
+                let mut line = String::new();
+                stmt.fmt(&mut self.indentation, &mut line)?;
+                self.out.write_str(&hex_literals(&line))
+            },
+        }
+    }
+}
+
+
+/// Utility for displaying an expression in the legacy format
+struct Legacy<'a>(&'a Expression);
+
+impl fmt::Display for Legacy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex_literals(&self.0.to_string()))
+    }
+}
+
+
+/// Utility for displaying a type in the legacy format
+struct LegacyType<'a>(&'a Type);
+
+impl fmt::Display for LegacyType<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Type::GroundType(g) => fmt::Display::fmt(g, f),
+            Type::Vector(t, w)  => write!(f, "{}[{}]", LegacyType(t), w),
+            Type::Bundle(v)     => {
+                write!(f, "{{ ")?;
+                v.iter().enumerate().try_for_each(|(i, field)| write!(
+                    f,
+                    "{}{}{} : {}",
+                    if i > 0 { ", " } else { "" },
+                    if field.orientation() == Orientation::Flipped { "flip " } else { "" },
+                    field.name(),
+                    LegacyType(field.r#type()),
+                ))?;
+                write!(f, "}}")
+            },
+            Type::Probe(t, k)   => write!(f, "{}<{}>", k.keyword(), LegacyType(t)),
+            Type::Enum(v)       => {
+                write!(f, "{{|")?;
+                v.iter().enumerate().try_for_each(|(i, variant)| {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, variant.name())?;
+                    variant.data().map_or(Ok(()), |t| write!(f, ": {}", LegacyType(t)))
+                })?;
+                write!(f, "|}}")
+            },
+            Type::Alias(_, t)   => fmt::Display::fmt(&LegacyType(t), f),
+        }
+    }
+}
+
+
+/// Replace the literals in some FIRRTL text with their legacy format
+///
+/// Literals emitted in the default format, i.e. with a decimal value such as
+/// `UInt<4>(10)`, are replaced with literals with a quoted hexadecimal value
+/// such as `UInt<4>("ha")`. Negative values are emitted with a sign, e.g.
+/// `SInt<4>("h-5")`. Text within string literals is left untouched.
+fn hex_literals(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if !quoted {
+            if let Some((literal, len)) = hex_literal(rest) {
+                res.push_str(&literal);
+                rest = &rest[len..];
+                continue
+            }
+        }
+
+        if c == '"' && !escaped {
+            quoted = !quoted;
+        }
+        escaped = quoted && c == '\\' && !escaped;
+        res.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    res
+}
+
+
+/// Convert a literal at the start of the given text to the legacy format
+///
+/// This function returns the literal in the legacy format along with the
+/// length of the original literal.
+fn hex_literal(text: &str) -> Option<(String, usize)> {
+    let kind = ["UInt<", "SInt<"].iter().find(|k| text.starts_with(*k))?;
+    let rest = &text[kind.len()..];
+    let width_len = rest.find(|c: char| !c.is_ascii_digit()).filter(|l| *l > 0)?;
+    let rest = rest[width_len..].strip_prefix(">(")?;
+    let value_len = rest.find(')')?;
+    let value: num_bigint::BigInt = rest[..value_len].parse().ok()?;
+
+    let literal = format!("{}{}>(\"h{}\")", kind, &text[kind.len()..kind.len() + width_len], value.to_str_radix(16));
+    Some((literal, kind.len() + width_len + 2 + value_len + 1))
+}
+
@@ -0,0 +1,112 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to emitters
+
+use transiter::AutoTransIter;
+
+use crate::circuit::Circuit;
+use crate::indentation::{DisplayIndented, Indentation};
+use crate::stmt::Statement;
+
+use super::{Emitter, Expression, FirrtlEmitter, LegacyEmitter, walk_expression, walk_statement};
+
+
+#[quickcheck]
+fn firrtl_emitter_statement(stmt: Statement) -> Result<bool, String> {
+    let mut expected = String::new();
+    stmt.fmt(&mut Indentation::root(), &mut expected).map_err(|e| e.to_string())?;
+
+    let mut emitter = FirrtlEmitter::new(String::new());
+    emitter.statement(&stmt).map_err(|e| e.to_string())?;
+    Ok(emitter.into_inner() == expected)
+}
+
+
+#[quickcheck]
+fn walk_visits_all(circuit: Circuit) -> bool {
+    #[derive(Default)]
+    struct Counter {
+        statements: usize,
+        expressions: usize,
+    }
+
+    impl Emitter for Counter {
+        type Error = ();
+
+        fn statement(&mut self, stmt: &Statement) -> Result<(), ()> {
+            self.statements += 1;
+            walk_statement(self, stmt)
+        }
+
+        fn expression(&mut self, expr: &Expression) -> Result<(), ()> {
+            self.expressions += 1;
+            walk_expression(self, expr)
+        }
+    }
+
+    let stmts: Vec<_> = circuit
+        .defined_modules()
+        .iter()
+        .flat_map(|m| m.statements())
+        .flat_map(AutoTransIter::trans_iter)
+        .collect();
+    let expressions = stmts
+        .iter()
+        .flat_map(|s| s.expressions())
+        .map(|e| e.trans_iter().count())
+        .sum::<usize>();
+
+    let mut counter = Counter::default();
+    counter.circuit(&circuit).is_ok() && counter.statements == stmts.len() && counter.expressions == expressions
+}
+
+
+#[test]
+fn legacy_emitter_quirks() {
+    let circuit = crate::circuit::parse(concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input io: {flip a: UInt<4>, b: SInt<4>[2]}\n",
+        "    io.a <= UInt<4>(10) @[Sub.scala 3:8]\n",
+        "  module Top:\n",
+        "    input clock: Clock\n",
+        "    input reset: UInt<1>\n",
+        "    output b: SInt<4> @[Top.scala 1:2]\n",
+        "    inst s of Sub\n",
+        "    reg r: SInt<4>, clock with: (reset => (reset, SInt<4>(-5))) @[Top.scala 2:3]\n",
+        "    s.io.b[0] is invalid @[Top.scala 3:4]\n",
+        "    when reset:\n",
+        "      b <= r\n",
+        "    else when eq(s.io.a, UInt(3)):\n",
+        "      printf(clock, reset, \"UInt<1>(1) %d\", UInt<4>(12))\n",
+        "    else:\n",
+        "      b <= s.io.b[1]\n",
+    )).expect("Could not parse circuit");
+
+    let mut emitter = LegacyEmitter::new(String::new());
+    emitter.circuit(&circuit).expect("Could not emit circuit");
+    assert_eq!(emitter.into_inner(), concat!(
+        "circuit Top :\n",
+        "  module Sub :\n",
+        "    input io : { flip a : UInt<4>, b : SInt<4>[2]}\n",
+        "\n",
+        "    io.a <= UInt<4>(\"ha\") @[Sub.scala 3:8]\n",
+        "\n",
+        "  module Top :\n",
+        "    input clock : Clock\n",
+        "    input reset : UInt<1>\n",
+        "    output b : SInt<4> @[Top.scala 1:2]\n",
+        "\n",
+        "    inst s of Sub\n",
+        "    reg r : SInt<4>, clock with :\n",
+        "      reset => (reset, SInt<4>(\"h-5\")) @[Top.scala 2:3]\n",
+        "    s.io.b[0] is invalid @[Top.scala 3:4]\n",
+        "    when reset :\n",
+        "      b <= r\n",
+        "    else :\n",
+        "      when eq(s.io.a, UInt<2>(\"h3\")) :\n",
+        "        printf(clock, reset, \"UInt<1>(1) %d\", UInt<4>(\"hc\"))\n",
+        "      else :\n",
+        "        b <= s.io.b[1]\n",
+    ));
+}
@@ -5,17 +5,42 @@
 use std::error::Error as Error;
 use std::fmt;
 use std::io;
+use std::sync::Arc;
 
+use crate::indentation::IndentationConfig;
+use crate::module::ParamValue;
 use crate::parsers;
+use crate::types::UBits;
 
 
 /// Parsing error type
 #[derive(Debug)]
 pub enum ParseError {
     IO(io::Error),
+    Syntax(SyntaxError),
     Other(String),
 }
 
+impl ParseError {
+    /// Retrieve the syntax error, if this is one
+    pub fn syntax(&self) -> Option<&SyntaxError> {
+        match self {
+            Self::Syntax(err)   => Some(err),
+            _                   => None,
+        }
+    }
+
+    /// Shift the location of a syntax error by the given number of lines
+    ///
+    /// This is used for errors reported for some fragment of a larger input.
+    pub(crate) fn with_line_offset(self, offset: usize) -> Self {
+        match self {
+            Self::Syntax(err)   => Self::Syntax(err.with_line_offset(offset)),
+            err                 => err,
+        }
+    }
+}
+
 impl From<io::ErrorKind> for ParseError {
     fn from(err: io::ErrorKind) -> Self {
         Self::IO(err.into())
@@ -28,6 +53,12 @@ impl From<io::Error> for ParseError {
     }
 }
 
+impl From<SyntaxError> for ParseError {
+    fn from(err: SyntaxError) -> Self {
+        Self::Syntax(err)
+    }
+}
+
 impl From<String> for ParseError {
     fn from(err: String) -> Self {
         Self::Other(err)
@@ -37,8 +68,9 @@ impl From<String> for ParseError {
 impl Error for ParseError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            Self::IO(err) => Some(err),
-            _ => None,
+            Self::IO(err)       => Some(err),
+            Self::Syntax(err)   => Some(err),
+            _                   => None,
         }
     }
 }
@@ -47,19 +79,379 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::IO(_)         => fmt::Display::fmt("IO error", f),
+            Self::Syntax(err)   => fmt::Display::fmt(err, f),
             Self::Other(err)    => fmt::Display::fmt(err, f),
         }
     }
 }
 
 
+/// Syntax error encountered while parsing
+///
+/// In addition to a human readable message, a syntax error carries the
+/// location at which parsing failed and a machine readable [SyntaxErrorKind].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    kind: SyntaxErrorKind,
+    line: usize,
+    column: usize,
+    snippet: String,
+    identifier: Option<String>,
+    indentation: Option<Box<IndentationMismatch>>,
+    message: String,
+}
+
+impl SyntaxError {
+    /// Retrieve the kind of error
+    pub fn kind(&self) -> SyntaxErrorKind {
+        self.kind
+    }
+
+    /// Retrieve the line at which parsing failed, starting at `1`
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Retrieve the column at which parsing failed, starting at `1`
+    ///
+    /// The column is given in characters rather than bytes.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Retrieve the offending line, without the line break
+    pub fn snippet(&self) -> &str {
+        self.snippet.as_ref()
+    }
+
+    /// Retrieve the offending identifier, if known
+    ///
+    /// The identifier is available for errors of kind
+    /// [UnknownReference](SyntaxErrorKind::UnknownReference) and
+    /// [UnknownKeyword](SyntaxErrorKind::UnknownKeyword).
+    pub fn identifier(&self) -> Option<&str> {
+        self.identifier.as_deref()
+    }
+
+    /// Retrieve the expected and found indentation, if known
+    ///
+    /// The indentation is available for errors of kind
+    /// [Indentation](SyntaxErrorKind::Indentation).
+    pub fn indentation(&self) -> Option<&IndentationMismatch> {
+        self.indentation.as_deref()
+    }
+
+    /// Retrieve a detailed message, including the parsers' contexts
+    pub fn message(&self) -> &str {
+        self.message.as_ref()
+    }
+
+    /// Shift the location by the given number of lines
+    ///
+    /// Line numbers mentioned in the message are shifted as well.
+    fn with_line_offset(self, offset: usize) -> Self {
+        const MARKER: &str = "at line ";
+
+        let mut parts = self.message.split(MARKER);
+        let mut message = parts.next().unwrap_or_default().to_string();
+        parts.for_each(|part| {
+            let digits = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+            message.push_str(MARKER);
+            match part[..digits].parse::<usize>() {
+                Ok(line)    => message.push_str(&(line + offset).to_string()),
+                Err(_)      => message.push_str(&part[..digits]),
+            }
+            message.push_str(&part[digits..]);
+        });
+
+        Self {line: self.line + offset, message, ..self}
+    }
+}
+
+impl Error for SyntaxError {}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)?;
+        if let Some(identifier) = self.identifier.as_ref() {
+            write!(f, " `{}`", identifier)?;
+        }
+        writeln!(f)?;
+        fmt::Display::fmt(&self.message, f)
+    }
+}
+
+
+/// Kind of a [SyntaxError]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxErrorKind {
+    /// The input ended prematurely
+    UnexpectedEnd,
+    /// A token not permitted at that location was encountered
+    UnexpectedToken,
+    /// A line is not indented as expected
+    Indentation,
+    /// An unknown keyword or primitive operation was encountered
+    UnknownKeyword,
+    /// A name which does not refer to any known entity, memory or module
+    UnknownReference,
+    /// A port was declared after the first statement of a module
+    MisplacedPort,
+}
+
+impl fmt::Display for SyntaxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd     => fmt::Display::fmt("unexpected end of input", f),
+            Self::UnexpectedToken   => fmt::Display::fmt("unexpected token", f),
+            Self::Indentation       => fmt::Display::fmt("unexpected indentation", f),
+            Self::UnknownKeyword    => fmt::Display::fmt("unknown keyword", f),
+            Self::UnknownReference  => fmt::Display::fmt("unknown reference", f),
+            Self::MisplacedPort     => fmt::Display::fmt("port declaration after first statement", f),
+        }
+    }
+}
+
+
+/// Error type for port manipulation
+#[derive(Clone, Debug, PartialEq)]
+pub enum PortError {
+    /// A port with the given name already exists
+    Duplicate(Arc<str>),
+    /// There is no port with the given name
+    NotFound(Arc<str>),
+    /// The port with the given name is still referenced
+    Referenced(Arc<str>),
+}
+
+impl Error for PortError {}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate(n)  => write!(f, "port {} already exists", n),
+            Self::NotFound(n)   => write!(f, "no port named {}", n),
+            Self::Referenced(n) => write!(f, "port {} is still referenced", n),
+        }
+    }
+}
+
+
+/// Error type for accessing parameter values
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamError {
+    /// The value is not of the kind expected
+    Mismatch{expected: &'static str, value: ParamValue},
+}
+
+impl Error for ParamError {}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mismatch{expected, value} => write!(f, "expected {} parameter, found {}", expected, value),
+        }
+    }
+}
+
+
+/// Error type for elaborating generic modules
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElaborationError {
+    /// No value was supplied for the parameter and it has no default
+    Missing(Arc<str>),
+    /// The parameter with the given name is not declared
+    Undeclared(Arc<str>),
+    /// The value of the given parameter is not suitable
+    Param(Arc<str>, ParamError),
+    /// A width or depth evaluated to the given, unrepresentable value
+    Range(i64),
+    /// There is no port or declared entity with the given name
+    NotFound(Arc<str>),
+    /// The entity with the given name has no type or depth to parameterize
+    Unsupported(Arc<str>),
+    /// The given width expression could not be evaluated
+    Arithmetic(String),
+}
+
+impl Error for ElaborationError {}
+
+impl fmt::Display for ElaborationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(n)        => write!(f, "no value for parameter {}", n),
+            Self::Undeclared(n)     => write!(f, "parameter {} is not declared", n),
+            Self::Param(n, e)       => write!(f, "parameter {}: {}", n, e),
+            Self::Range(v)          => write!(f, "value {} is out of range", v),
+            Self::NotFound(n)       => write!(f, "no port or entity named {}", n),
+            Self::Unsupported(n)    => write!(f, "{} cannot be parameterized", n),
+            Self::Arithmetic(w)     => write!(f, "could not evaluate {}", w),
+        }
+    }
+}
+
+
+/// Error type for creating literals
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LiteralError {
+    /// The value requires more bits than any supported width
+    Overflow{required: u64},
+    /// The value does not fit into the given width
+    Width{width: UBits, required: u64},
+}
+
+impl Error for LiteralError {}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow{required}        => write!(f, "literal requires {} bits, exceeding the maximum width", required),
+            Self::Width{width, required}    => write!(f, "literal requires {} bits but has width {}", required, width),
+        }
+    }
+}
+
+
+/// Mismatch between the expected and the found indentation of a line
+///
+/// Indentation is measured in characters. Both are reported in the unit of
+/// the [IndentationConfig] in effect, i.e. in tabs or spaces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IndentationMismatch {
+    expected: usize,
+    minimum: bool,
+    found: usize,
+    config: IndentationConfig,
+}
+
+impl IndentationMismatch {
+    /// Create a new mismatch
+    ///
+    /// If `minimum` is `true`, `expected` denotes the minimum indentation
+    /// rather than the exact one.
+    pub(crate) fn new(expected: usize, minimum: bool, found: usize, config: IndentationConfig) -> Self {
+        Self {expected, minimum, found, config}
+    }
+
+    /// Retrieve the expected indentation
+    pub fn expected(&self) -> usize {
+        self.expected
+    }
+
+    /// Retrieve whether the expected indentation is only a lower bound
+    pub fn is_minimum(&self) -> bool {
+        self.minimum
+    }
+
+    /// Retrieve the indentation found
+    pub fn found(&self) -> usize {
+        self.found
+    }
+
+    /// Retrieve the indentation configuration in effect
+    pub fn config(&self) -> IndentationConfig {
+        self.config
+    }
+}
+
+impl fmt::Display for IndentationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = |n: usize| match (self.config.tabs(), n) {
+            (true, 1)   => "tab",
+            (true, _)   => "tabs",
+            (false, 1)  => "space",
+            (false, _)  => "spaces",
+        };
+
+        let bound = if self.minimum { "at least " } else { "" };
+        write!(f, "expected {}{} {}, found {}", bound, self.expected, unit(self.expected), self.found)
+    }
+}
+
+
 /// Convert a `nom::Err` into a `ParseError`
+///
+/// Errors reported by parsers are converted into [SyntaxError]s. The location
+/// is derived from the innermost error, which needs to refer to a part of
+/// `input`.
 pub(crate) fn convert_error(input: &str, err: nom::Err<parsers::Error>) -> ParseError {
-    use nom::error::convert_error;
+    use nom::error::{ErrorKind, VerboseErrorKind};
 
-    match err {
-        nom::Err::Incomplete(_) => io::ErrorKind::UnexpectedEof.into(),
-        nom::Err::Error(e) | nom::Err::Failure(e) => convert_error(input, e).into(),
-    }
+    let err = match err {
+        nom::Err::Incomplete(_) => return io::ErrorKind::UnexpectedEof.into(),
+        nom::Err::Error(e) | nom::Err::Failure(e) => e,
+    };
+
+    let rest = err.trace().first().map(|(i, _)| *i).unwrap_or_default();
+    let offset = (rest.as_ptr() as usize)
+        .checked_sub(input.as_ptr() as usize)
+        .filter(|o| *o <= input.len() && input.is_char_boundary(*o))
+        .unwrap_or(input.len());
+    let before = &input[..offset];
+    let start = before.rfind('\n').map(|p| p + 1).unwrap_or(0);
+
+    let contexts = || err.trace().iter().filter_map(|(_, k)| match k {
+        VerboseErrorKind::Context(c) => Some(*c),
+        _ => None,
+    });
+    let kind = if contexts().any(|c| c == UNKNOWN_REFERENCE_CONTEXT) {
+        SyntaxErrorKind::UnknownReference
+    } else if contexts().any(|c| c == UNKNOWN_OPERATION_CONTEXT) {
+        SyntaxErrorKind::UnknownKeyword
+    } else if contexts().any(|c| c == MISPLACED_PORT_CONTEXT) {
+        SyntaxErrorKind::MisplacedPort
+    } else if contexts().any(|c| c == INDENTATION_CONTEXT) {
+        SyntaxErrorKind::Indentation
+    } else if rest.trim().is_empty() || matches!(err.trace().first(), Some((_, VerboseErrorKind::Nom(ErrorKind::Eof)))) {
+        SyntaxErrorKind::UnexpectedEnd
+    } else {
+        SyntaxErrorKind::UnexpectedToken
+    };
+
+    let line = before.matches('\n').count() + 1;
+    SyntaxError {
+        kind,
+        line,
+        column: before[start..].chars().count() + 1,
+        snippet: input[start..].lines().next().unwrap_or_default().to_string(),
+        identifier: match kind {
+            SyntaxErrorKind::UnknownReference | SyntaxErrorKind::UnknownKeyword => parsers::identifier(rest)
+                .ok()
+                .map(|(_, i)| i.to_string()),
+            _ => None,
+        },
+        indentation: err.indentation_mismatch().cloned().map(Box::new).filter(|_| kind == SyntaxErrorKind::Indentation),
+        message: match (kind, err.indentation_mismatch()) {
+            (SyntaxErrorKind::Indentation, Some(mismatch)) => indentation_message(mismatch, rest, line),
+            _ => err.to_message(input),
+        },
+    }.into()
 }
 
+
+/// Create a message for an indentation error
+///
+/// The message states the expected and the found indentation of the line
+/// starting at `rest`, followed by that line.
+fn indentation_message(mismatch: &IndentationMismatch, rest: &str, line: usize) -> String {
+    let snippet = rest.lines().next().unwrap_or_default();
+    let indentation = snippet.len() - snippet.trim_start_matches(&[' ', '\t'][..]).len();
+    format!("at line {}, {}:\n{}\n{}^\n", line, mismatch, snippet, &snippet[..indentation])
+}
+
+
+/// Context attached to errors caused by unexpected indentation
+pub(crate) const INDENTATION_CONTEXT: &str = "unexpected indentation";
+
+/// Context attached to errors caused by ports declared after statements
+pub(crate) const MISPLACED_PORT_CONTEXT: &str = "port declaration after first statement";
+
+/// Context attached to errors caused by unknown primitive operations
+pub(crate) const UNKNOWN_OPERATION_CONTEXT: &str = "unknown primitive operation";
+
+/// Context attached to errors caused by exceeding the limit of statements
+pub(crate) const STATEMENT_LIMIT_CONTEXT: &str = "statement limit exceeded";
+
+/// Context attached to errors caused by names which could not be resolved
+pub(crate) const UNKNOWN_REFERENCE_CONTEXT: &str = "unknown reference";
+
@@ -3,24 +3,32 @@
 //! FIRRTL expressions and associated utilities
 
 pub(crate) mod parsers;
+pub mod dsl;
+pub mod intern;
 pub mod primitive;
 
-#[cfg(test)]
-pub mod tests;
+#[cfg(any(test, feature = "test-util"))]
+#[cfg_attr(not(test), allow(dead_code, unused_imports))]
+pub(crate) mod tests;
 
+use std::convert::TryInto;
 use std::fmt;
 use std::sync::Arc;
 
+use crate::error::LiteralError;
 use crate::named::Named;
 use crate::types;
 use types::{Typed, UBits, VecWidth};
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use crate::tests::Identifier;
 
+pub use dsl::Expr;
+pub use intern::Interner;
+
 
 /// A FIRRTL expression
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Expression<R: Reference> {
     /// An UInt literal
     UIntLiteral{value: num_bigint::BigUint, width: UBits},
@@ -40,6 +48,58 @@ pub enum Expression<R: Reference> {
     ValidIf{sel: Arc<Expression<R>>, value: Arc<Expression<R>>},
     /// A primitive operation
     PrimitiveOp(primitive::Operation<R>),
+    /// A probe expression, i.e. `probe(...)` or `rwprobe(...)`
+    Probe{target: Arc<Expression<R>>, kind: types::ProbeKind},
+    /// A read of a probe, i.e. `read(...)`
+    Read(Arc<Expression<R>>),
+}
+
+impl<R: Reference> Expression<R> {
+    /// Create an `UInt` literal of minimal width
+    ///
+    /// The width of the literal is determined via [uint_width]. This function
+    /// fails if that width exceeds the maximum width supported.
+    pub fn uint(value: impl Into<num_bigint::BigUint>) -> Result<Self, LiteralError> {
+        let value = value.into();
+        let required = uint_width(&value);
+        let width = required.try_into().map_err(|_| LiteralError::Overflow{required})?;
+        Ok(Self::UIntLiteral{value, width})
+    }
+
+    /// Create an `UInt` literal of the given width
+    ///
+    /// This function fails if the value does not fit into the given width.
+    pub fn uint_w(value: impl Into<num_bigint::BigUint>, width: UBits) -> Result<Self, LiteralError> {
+        let value = value.into();
+        let required = uint_width(&value);
+        if required > width.into() {
+            return Err(LiteralError::Width{width, required})
+        }
+        Ok(Self::UIntLiteral{value, width})
+    }
+
+    /// Create an `SInt` literal of minimal width
+    ///
+    /// The width of the literal is determined via [sint_width]. This function
+    /// fails if that width exceeds the maximum width supported.
+    pub fn sint(value: impl Into<num_bigint::BigInt>) -> Result<Self, LiteralError> {
+        let value = value.into();
+        let required = sint_width(&value);
+        let width = required.try_into().map_err(|_| LiteralError::Overflow{required})?;
+        Ok(Self::SIntLiteral{value, width})
+    }
+
+    /// Create an `SInt` literal of the given width
+    ///
+    /// This function fails if the value does not fit into the given width.
+    pub fn sint_w(value: impl Into<num_bigint::BigInt>, width: UBits) -> Result<Self, LiteralError> {
+        let value = value.into();
+        let required = sint_width(&value);
+        if required > width.into() {
+            return Err(LiteralError::Width{width, required})
+        }
+        Ok(Self::SIntLiteral{value, width})
+    }
 }
 
 impl<R> Expression<R>
@@ -60,6 +120,51 @@ where Self: Typed<Type = types::Type, Err = Expression<R>> + Clone,
         }
     }
 
+    /// Create a sub-field expression for the field with the given name
+    ///
+    /// If the type of this expression can be determined, this function checks
+    /// that it is a bundle with a field of the given name. If it is not, the
+    /// invalid sub-field expression is returned as the error. Thus, invalid
+    /// paths are detected when constructing an expression rather than when
+    /// typing or emitting it.
+    pub fn field(self, name: impl Into<Arc<str>>) -> Result<Self, Self> {
+        let index = name.into();
+        let valid = self.r#type().map(|t| t.field(index.as_ref()).is_some()).unwrap_or(true);
+        let res = Self::SubField{base: Arc::new(self), index};
+        if valid { Ok(res) } else { Err(res) }
+    }
+
+    /// Create a sub-index expression for the element with the given index
+    ///
+    /// If the type of this expression can be determined, this function checks
+    /// that it is a vector with an element of the given index. If it is not,
+    /// the invalid sub-index expression is returned as the error.
+    pub fn index(self, index: VecWidth) -> Result<Self, Self> {
+        let valid = match self.r#type().as_ref().map(types::Type::resolved) {
+            Ok(types::Type::Vector(_, width))   => index < *width,
+            Ok(_)                               => false,
+            Err(_)                              => true,
+        };
+        let res = Self::SubIndex{base: Arc::new(self), index};
+        if valid { Ok(res) } else { Err(res) }
+    }
+
+    /// Create a sub-access expression with the given index expression
+    ///
+    /// If the types of this expression and the index can be determined, this
+    /// function checks that this expression is a vector and the index an
+    /// unsigned integer. If they are not, the invalid sub-access expression is
+    /// returned as the error.
+    pub fn access(self, index: impl Into<Self>) -> Result<Self, Self> {
+        let index = index.into();
+        let valid = self.r#type().map(|t| t.vector_base().is_some()).unwrap_or(true) && match index.r#type() {
+            Ok(t)   => matches!(t.resolved(), types::Type::GroundType(types::GroundType::UInt(_))),
+            Err(_)  => true,
+        };
+        let res = Self::SubAccess{base: Arc::new(self), index: Arc::new(index)};
+        if valid { Ok(res) } else { Err(res) }
+    }
+
     /// Retrieve all references in this expression
     pub fn references(&self) -> impl Iterator<Item = &R> {
         use transiter::AutoTransIter;
@@ -73,6 +178,74 @@ where Self: Typed<Type = types::Type, Err = Expression<R>> + Clone,
     }
 }
 
+impl<R: Reference> Expression<R> {
+    /// Create an expression with mapped references
+    ///
+    /// This function creates an expression with the same structure as this
+    /// one, but with every reference replaced by the result of the given
+    /// function applied to it.
+    pub fn map_references<S: Reference>(&self, f: &mut impl FnMut(&R) -> S) -> Expression<S> {
+        fn map<R: Reference, S: Reference>(
+            expr: &Expression<R>,
+            f: &mut dyn FnMut(&R) -> S,
+        ) -> Expression<S> {
+            let mut sub = |e: &Arc<Expression<R>>| Arc::new(map(e.as_ref(), f));
+            match expr {
+                Expression::UIntLiteral{value, width}   => Expression::UIntLiteral{value: value.clone(), width: *width},
+                Expression::SIntLiteral{value, width}   => Expression::SIntLiteral{value: value.clone(), width: *width},
+                Expression::Reference(r)                => Expression::Reference(f(r)),
+                Expression::SubField{base, index}       => Expression::SubField{base: sub(base), index: index.clone()},
+                Expression::SubIndex{base, index}       => Expression::SubIndex{base: sub(base), index: *index},
+                Expression::SubAccess{base, index}      => Expression::SubAccess{base: sub(base), index: sub(index)},
+                Expression::Mux{sel, a, b}              => Expression::Mux{sel: sub(sel), a: sub(a), b: sub(b)},
+                Expression::ValidIf{sel, value}         => Expression::ValidIf{sel: sub(sel), value: sub(value)},
+                Expression::PrimitiveOp(op)             => Expression::PrimitiveOp(op.map_exprs(sub)),
+                Expression::Probe{target, kind}         => Expression::Probe{target: sub(target), kind: *kind},
+                Expression::Read(probe)                 => Expression::Read(sub(probe)),
+            }
+        }
+
+        map(self, f)
+    }
+
+    /// Decompose this expression into a root expression and an access path
+    ///
+    /// This function strips all sub-field, sub-index and sub-access
+    /// expressions from this expression. It returns the remaining innermost
+    /// expression along with the [Access](types::Access)es leading from it to
+    /// this expression, in the order in which they apply. The type of this expression is thus
+    /// the type of the root at that path, as computed by
+    /// [Type::at_access_path](types::Type::at_access_path).
+    pub fn access_path(&self) -> (&Self, Vec<types::Access>) {
+        let mut path = Vec::new();
+        let mut expr = self;
+        loop {
+            let (base, access) = match expr {
+                Self::SubField{base, index} => (base, types::Access::Field(index.clone())),
+                Self::SubIndex{base, index} => (base, types::Access::Index(*index)),
+                Self::SubAccess{base, ..}   => (base, types::Access::Dynamic),
+                _                           => break,
+            };
+            path.push(access);
+            expr = base.as_ref();
+        }
+        path.reverse();
+        (expr, path)
+    }
+
+    /// Format this expression with line breaks
+    ///
+    /// This function returns a value which formats the expression just like
+    /// its [Display](fmt::Display) impl. However, if the expression would
+    /// exceed `max_width` characters, the arguments of primitive operations,
+    /// muxes and the like are put on separate lines. Continuation lines are
+    /// indented relative to the first line. The result may still exceed the
+    /// width, e.g. for long references.
+    pub fn fmt_wrapped(&self, max_width: usize) -> Wrapped<'_, R> {
+        Wrapped {expr: self, max_width, max_depth: usize::MAX}
+    }
+}
+
 impl<R: Reference> From<R> for Expression<R> {
     fn from(reference: R) -> Self {
         Self::Reference(reference)
@@ -94,7 +267,7 @@ impl<R> Typed for Expression<R>
     type Type = types::Type;
 
     fn r#type(&self) -> Result<Self::Type, Self::Err> {
-        use types::{Combinator, GroundType as GT, MaxWidth};
+        use types::{Combinator, GroundType as GT, MaxWidth, TypeExt};
 
         match self {
             Self::UIntLiteral{width, ..}    => Ok(GT::UInt(Some(*width)).into()),
@@ -114,6 +287,16 @@ impl<R> Typed for Expression<R>
                 .map_err(|_| self.clone()),
             Self::ValidIf{value, ..}        => value.r#type(),
             Self::PrimitiveOp(op)           => op.r#type().map(Into::into).map_err(|_| self.clone()),
+            Self::Probe{target, kind}       => target
+                .r#type()
+                .and_then(|t| if t.is_passive() && t.probe().is_none() {
+                    Ok(types::Type::Probe(Arc::new(t), *kind))
+                } else {
+                    Err(self.clone())
+                }),
+            Self::Read(probe)               => probe
+                .r#type()
+                .and_then(|t| t.probe().map(|(t, _)| t.as_ref().clone()).ok_or(self.clone())),
         }
     }
 }
@@ -129,6 +312,8 @@ impl<'a, R: Reference> transiter::AutoTransIter<&'a Expression<R>> for &'a Expre
             Expression::Mux{sel, a, b}            => vec![sel.as_ref(), a.as_ref(), b.as_ref()],
             Expression::ValidIf{sel, value}       => vec![sel.as_ref(), value.as_ref()],
             Expression::PrimitiveOp(op)           => op.sub_exprs().into_iter().map(AsRef::as_ref).collect(),
+            Expression::Probe{target, ..}         => vec![target.as_ref()],
+            Expression::Read(probe)               => vec![probe.as_ref()],
             _ => Default::default(),
         }
     }
@@ -146,11 +331,66 @@ impl<R: Reference> fmt::Display for Expression<R> {
             Self::Mux{sel, a, b}            => write!(f, "mux({}, {}, {})", sel, a, b),
             Self::ValidIf{sel, value}       => write!(f, "validif({}, {})", sel, value),
             Self::PrimitiveOp(op)           => fmt::Display::fmt(op, f),
+            Self::Probe{target, kind}       => write!(f, "{}({})", kind.operator(), target),
+            Self::Read(probe)               => write!(f, "read({})", probe),
         }
     }
 }
 
 
+/// Compute the width of an `UInt` literal with the given value
+///
+/// This is the width the parser assigns to literals without an explicit width,
+/// i.e. the number of bits required for representing the value.
+pub fn uint_width(value: &num_bigint::BigUint) -> u64 {
+    value.bits()
+}
+
+
+/// Compute the width of an `SInt` literal with the given value
+///
+/// This is the width the parser assigns to literals without an explicit width,
+/// i.e. the number of bits required for representing the value's magnitude
+/// plus a sign bit.
+pub fn sint_width(value: &num_bigint::BigInt) -> u64 {
+    value.bits() + 1
+}
+
+
+/// Expression formatted with line breaks
+///
+/// Instances of this type are created via [Expression::fmt_wrapped].
+#[derive(Copy, Clone, Debug)]
+pub struct Wrapped<'a, R: Reference> {
+    expr: &'a Expression<R>,
+    max_width: usize,
+    max_depth: usize,
+}
+
+impl<R: Reference> Wrapped<'_, R> {
+    /// Limit the nesting depth of operations on a single line
+    ///
+    /// Arguments of operations nesting deeper than `max_depth` are put on
+    /// separate lines, even if the expression would fit the maximum width.
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self {max_depth, ..self}
+    }
+}
+
+impl<R: Reference> fmt::Display for Wrapped<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        crate::format::wrap(
+            &self.expr.to_string(),
+            0,
+            crate::indentation::Indentation::root().lock(),
+            self.max_width,
+            self.max_depth,
+            f,
+        )
+    }
+}
+
+
 /// A reference to a named entity
 pub trait Reference: Named {
     /// Retrieve the flow associated with the referenced entity
@@ -160,7 +400,7 @@ pub trait Reference: Named {
     fn flow(&self) -> Option<Flow>;
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Reference for Identifier {
     fn flow(&self) -> Option<Flow> {
         Some(Flow::Duplex)
@@ -217,7 +457,7 @@ impl std::ops::Add<types::Orientation> for Flow {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl quickcheck::Arbitrary for Flow {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
         g.choose(&[Self::Source, Self::Sink, Self::Duplex]).unwrap().clone()
@@ -0,0 +1,328 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Operator-based construction of expressions
+//!
+//! Constructing nested [Expression]s by hand requires wrapping every operand
+//! in an `Arc` and spelling out the [Operation] for every operator. This
+//! module provides [Expr], a thin wrapper around an [Expression] implementing
+//! the arithmetic and bitwise operators of `std::ops` as well as methods for
+//! the remaining primitive operations. Operands may be [Expr]s or plain
+//! [Expression]s.
+//!
+//! | Rust              | FIRRTL            |
+//! |-------------------|-------------------|
+//! | `a + b`           | `add(a, b)`       |
+//! | `a - b`           | `sub(a, b)`       |
+//! | `a * b`           | `mul(a, b)`       |
+//! | `a / b`           | `div(a, b)`       |
+//! | `a % b`           | `rem(a, b)`       |
+//! | `a & b`           | `and(a, b)`       |
+//! | `a \| b`          | `or(a, b)`        |
+//! | `a ^ b`           | `xor(a, b)`       |
+//! | `!a`              | `not(a)`          |
+//! | `-a`              | `neg(a)`          |
+//! | `a << 2`          | `shl(a, 2)`       |
+//! | `a >> 2`          | `shr(a, 2)`       |
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use firrtl_ast::expr::dsl::Expr;
+//! use firrtl_ast::stmt::Entity;
+//! use firrtl_ast::types::GroundType;
+//!
+//! let wire = |n: &str| Expr::reference(Arc::new(Entity::Wire{
+//!     name: n.into(),
+//!     r#type: GroundType::UInt(Some(8)).into(),
+//! }));
+//! let (a, b, c) = (wire("a"), wire("b"), wire("c"));
+//!
+//! let expr = ((a.clone() + b) & c.bits(3, 0)).mux(a.bits(0, 0), Expr::uint(0u8, 9));
+//! assert_eq!(expr.to_string(), "mux(bits(a, 0, 0), and(add(a, b), bits(c, 3, 0)), UInt<9>(0))");
+//! ```
+
+use std::fmt;
+use std::ops;
+use std::sync::Arc;
+
+use crate::types::{GroundType, SBits, UBits, VecWidth};
+
+use super::{Expression, Reference, primitive::Operation};
+
+
+/// Wrapper around an [Expression] for operator-based construction
+///
+/// See the [module level documentation](self) for an overview.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Expr<R: Reference>(pub Expression<R>);
+
+impl<R: Reference> Expr<R> {
+    /// Create an expression referring to the given entity
+    pub fn reference(reference: R) -> Self {
+        Self(Expression::Reference(reference))
+    }
+
+    /// Create an `UInt` literal with the given value and width
+    pub fn uint(value: impl Into<num_bigint::BigUint>, width: UBits) -> Self {
+        Self(Expression::UIntLiteral{value: value.into(), width})
+    }
+
+    /// Create an `SInt` literal with the given value and width
+    pub fn sint(value: impl Into<num_bigint::BigInt>, width: UBits) -> Self {
+        Self(Expression::SIntLiteral{value: value.into(), width})
+    }
+
+    /// Retrieve the wrapped expression
+    pub fn into_inner(self) -> Expression<R> {
+        self.0
+    }
+
+    /// Create a sub-field expression
+    pub fn field(self, name: impl Into<Arc<str>>) -> Self {
+        Self(Expression::SubField{base: self.into(), index: name.into()})
+    }
+
+    /// Create a sub-index expression
+    pub fn index(self, index: VecWidth) -> Self {
+        Self(Expression::SubIndex{base: self.into(), index})
+    }
+
+    /// Create a sub-access expression
+    pub fn access(self, index: impl Into<Self>) -> Self {
+        Self(Expression::SubAccess{base: self.into(), index: index.into().into()})
+    }
+
+    /// Create a multiplexer selecting this expression if `sel` is set
+    ///
+    /// The multiplexer yields this expression if `sel` is `1` and `other`
+    /// otherwise.
+    pub fn mux(self, sel: impl Into<Self>, other: impl Into<Self>) -> Self {
+        Self(Expression::Mux{sel: sel.into().into(), a: self.into(), b: other.into().into()})
+    }
+
+    /// Create a valid-if expression for this expression
+    pub fn valid_if(self, sel: impl Into<Self>) -> Self {
+        Self(Expression::ValidIf{sel: sel.into().into(), value: self.into()})
+    }
+
+    /// Compare this expression to another via `lt`
+    pub fn lt(self, rhs: impl Into<Self>) -> Self {
+        Operation::Lt(self.into(), rhs.into().into()).into()
+    }
+
+    /// Compare this expression to another via `leq`
+    pub fn leq(self, rhs: impl Into<Self>) -> Self {
+        Operation::LEq(self.into(), rhs.into().into()).into()
+    }
+
+    /// Compare this expression to another via `gt`
+    pub fn gt(self, rhs: impl Into<Self>) -> Self {
+        Operation::Gt(self.into(), rhs.into().into()).into()
+    }
+
+    /// Compare this expression to another via `geq`
+    pub fn geq(self, rhs: impl Into<Self>) -> Self {
+        Operation::GEq(self.into(), rhs.into().into()).into()
+    }
+
+    /// Compare this expression to another via `eq`
+    pub fn equal(self, rhs: impl Into<Self>) -> Self {
+        Operation::Eq(self.into(), rhs.into().into()).into()
+    }
+
+    /// Compare this expression to another via `neq`
+    pub fn not_equal(self, rhs: impl Into<Self>) -> Self {
+        Operation::NEq(self.into(), rhs.into().into()).into()
+    }
+
+    /// Pad this expression to the given number of bits
+    pub fn pad(self, bits: UBits) -> Self {
+        Operation::Pad(self.into(), bits).into()
+    }
+
+    /// Cast this expression to the given ground type
+    pub fn cast(self, r#type: GroundType) -> Self {
+        Operation::Cast(self.into(), r#type).into()
+    }
+
+    /// Shift this expression left by a dynamic amount
+    pub fn dshl(self, amount: impl Into<Self>) -> Self {
+        Operation::DShl(self.into(), amount.into().into()).into()
+    }
+
+    /// Shift this expression right by a dynamic amount
+    pub fn dshr(self, amount: impl Into<Self>) -> Self {
+        Operation::DShr(self.into(), amount.into().into()).into()
+    }
+
+    /// Convert this expression to a signed integer
+    pub fn cvt(self) -> Self {
+        Operation::Cvt(self.into()).into()
+    }
+
+    /// Reduce this expression to a single bit via AND
+    pub fn and_reduce(self) -> Self {
+        Operation::AndReduce(self.into()).into()
+    }
+
+    /// Reduce this expression to a single bit via OR
+    pub fn or_reduce(self) -> Self {
+        Operation::OrReduce(self.into()).into()
+    }
+
+    /// Reduce this expression to a single bit via XOR
+    pub fn xor_reduce(self) -> Self {
+        Operation::XorReduce(self.into()).into()
+    }
+
+    /// Concatenate this expression with another
+    ///
+    /// The bits of this expression will form the most significant bits of
+    /// the result.
+    pub fn cat(self, rhs: impl Into<Self>) -> Self {
+        Operation::Cat(self.into(), rhs.into().into()).into()
+    }
+
+    /// Extract the bits `hi` down to `lo` of this expression
+    pub fn bits(self, hi: UBits, lo: UBits) -> Self {
+        Operation::Bits(self.into(), Some(hi), Some(lo)).into()
+    }
+
+    /// Extract the `n` most significant bits of this expression
+    pub fn head(self, n: UBits) -> Self {
+        Operation::Bits(self.into(), None, Some(n)).into()
+    }
+
+    /// Remove the `n` most significant bits of this expression
+    pub fn tail(self, n: UBits) -> Self {
+        Operation::Bits(self.into(), Some(n), None).into()
+    }
+
+    /// Increase the precision of this expression
+    pub fn inc_precision(self, bits: UBits) -> Self {
+        Operation::IncPrecision(self.into(), bits).into()
+    }
+
+    /// Decrease the precision of this expression
+    pub fn dec_precision(self, bits: UBits) -> Self {
+        Operation::DecPrecision(self.into(), bits).into()
+    }
+
+    /// Set the precision of this expression
+    pub fn set_precision(self, bits: SBits) -> Self {
+        Operation::SetPrecision(self.into(), bits).into()
+    }
+
+    /// Wrap this interval into the bounds of the type of another expression
+    pub fn wrap(self, r#ref: impl Into<Self>) -> Self {
+        Operation::Wrap(self.into(), r#ref.into().into()).into()
+    }
+
+    /// Clip this interval to the bounds of the type of another expression
+    pub fn clip(self, r#ref: impl Into<Self>) -> Self {
+        Operation::Clip(self.into(), r#ref.into().into()).into()
+    }
+
+    /// Squeeze this interval into the bounds of another interval
+    pub fn squeeze(self, r#ref: impl Into<Self>) -> Self {
+        Operation::Squeeze(self.into(), r#ref.into().into()).into()
+    }
+
+    /// Interpret this expression as an interval
+    ///
+    /// The bounds are given as multiples of `2^-point`.
+    pub fn as_interval(self, lower: i64, upper: i64, point: SBits) -> Self {
+        Operation::AsInterval(self.into(), lower, upper, point).into()
+    }
+}
+
+impl<R: Reference> From<Expression<R>> for Expr<R> {
+    fn from(expr: Expression<R>) -> Self {
+        Self(expr)
+    }
+}
+
+impl<R: Reference> From<Operation<R>> for Expr<R> {
+    fn from(op: Operation<R>) -> Self {
+        Self(Expression::PrimitiveOp(op))
+    }
+}
+
+impl<R: Reference> From<Expr<R>> for Expression<R> {
+    fn from(expr: Expr<R>) -> Self {
+        expr.0
+    }
+}
+
+impl<R: Reference> From<Expr<R>> for Arc<Expression<R>> {
+    fn from(expr: Expr<R>) -> Self {
+        Arc::new(expr.0)
+    }
+}
+
+impl<R: Reference> AsRef<Expression<R>> for Expr<R> {
+    fn as_ref(&self) -> &Expression<R> {
+        &self.0
+    }
+}
+
+impl<R: Reference> fmt::Display for Expr<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Implement a binary operator trait via the given [Operation]
+macro_rules! binary_op {
+    ($trait:ident, $fn:ident, $op:ident) => {
+        impl<R: Reference, T: Into<Expr<R>>> ops::$trait<T> for Expr<R> {
+            type Output = Self;
+
+            fn $fn(self, rhs: T) -> Self {
+                Operation::$op(self.into(), rhs.into().into()).into()
+            }
+        }
+    };
+}
+
+binary_op!(Add,     add,    Add);
+binary_op!(Sub,     sub,    Sub);
+binary_op!(Mul,     mul,    Mul);
+binary_op!(Div,     div,    Div);
+binary_op!(Rem,     rem,    Rem);
+binary_op!(BitAnd,  bitand, And);
+binary_op!(BitOr,   bitor,  Or);
+binary_op!(BitXor,  bitxor, Xor);
+
+impl<R: Reference> ops::Not for Expr<R> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Operation::Not(self.into()).into()
+    }
+}
+
+impl<R: Reference> ops::Neg for Expr<R> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Operation::Neg(self.into()).into()
+    }
+}
+
+impl<R: Reference> ops::Shl<UBits> for Expr<R> {
+    type Output = Self;
+
+    fn shl(self, bits: UBits) -> Self {
+        Operation::Shl(self.into(), bits).into()
+    }
+}
+
+impl<R: Reference> ops::Shr<UBits> for Expr<R> {
+    type Output = Self;
+
+    fn shr(self, bits: UBits) -> Self {
+        Operation::Shr(self.into(), bits).into()
+    }
+}
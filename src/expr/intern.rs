@@ -0,0 +1,130 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Hash-consing of expressions
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use super::{Expression, Reference};
+
+
+/// Factory for shared expressions
+///
+/// An interner yields the same `Arc` for structurally identical expressions.
+/// Sub-expressions are interned, too. Thus, interning all expressions within
+/// a module reduces the memory required for common sub-expressions. In
+/// addition, two expressions interned by the same interner are equal if and
+/// only if they are the same allocation, i.e. comparison via [Arc::ptr_eq]
+/// is sufficient.
+///
+/// Since sub-expressions are shared, interning is a shallow operation once
+/// sub-expressions have been interned. Hence, interning an expression takes
+/// time linear in the number of its (non-interned) sub-expressions.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use firrtl_ast::expr::{Expression, Interner, primitive::Operation};
+/// use firrtl_ast::stmt::Entity;
+/// use firrtl_ast::types::GroundType;
+///
+/// let wire = Arc::new(Entity::Wire{name: "a".into(), r#type: GroundType::UInt(Some(4)).into()});
+///
+/// let mut interner = Interner::new();
+/// let a = interner.intern(wire.clone());
+/// let x = interner.intern(Operation::Not(a));
+/// let y = interner.intern(Operation::Not(Arc::new(Expression::Reference(wire))));
+/// assert!(Arc::ptr_eq(&x, &y));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Interner<R: Reference + Clone + Eq + Hash> {
+    exprs: HashMap<Key<R>, Arc<Expression<R>>>,
+    shared: HashSet<usize>,
+    hole: Arc<Expression<R>>,
+}
+
+impl<R: Reference + Clone + Eq + Hash> Interner<R> {
+    /// Create a new interner
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Retrieve the shared instance of the given expression
+    pub fn intern(&mut self, expr: impl Into<Expression<R>>) -> Arc<Expression<R>> {
+        let expr = map_sub_exprs(&expr.into(), |e| self.intern_arc(e));
+        let subs = <&Expression<R> as transiter::AutoTransIter<_>>::recurse(&&expr)
+            .into_iter()
+            .map(address)
+            .collect();
+        let skeleton = map_sub_exprs(&expr, |_| self.hole.clone());
+        let res = self.exprs.entry((skeleton, subs)).or_insert_with(|| Arc::new(expr)).clone();
+        self.shared.insert(address(res.as_ref()));
+        res
+    }
+
+    /// Retrieve the shared instance of the given expression
+    ///
+    /// If the given expression is already the shared instance, it is returned
+    /// without traversing its sub-expressions.
+    pub fn intern_arc(&mut self, expr: &Arc<Expression<R>>) -> Arc<Expression<R>> {
+        if self.shared.contains(&address(expr)) {
+            expr.clone()
+        } else {
+            self.intern(expr.as_ref().clone())
+        }
+    }
+
+    /// Retrieve the number of distinct expressions interned
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Check whether no expressions were interned yet
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+}
+
+impl<R: Reference + Clone + Eq + Hash> Default for Interner<R> {
+    fn default() -> Self {
+        Self {
+            exprs: Default::default(),
+            shared: Default::default(),
+            hole: Arc::new(Expression::UIntLiteral{value: Default::default(), width: 0}),
+        }
+    }
+}
+
+
+/// Key identifying an expression with interned sub-expressions
+///
+/// The key consists of the expression with all sub-expressions replaced by a
+/// placeholder and the addresses of the (interned) sub-expressions.
+type Key<R> = (Expression<R>, Vec<usize>);
+
+
+/// Create an expression with its direct sub-expressions mapped
+fn map_sub_exprs<R: Reference + Clone>(
+    expr: &Expression<R>,
+    mut f: impl FnMut(&Arc<Expression<R>>) -> Arc<Expression<R>>,
+) -> Expression<R> {
+    match expr {
+        Expression::SubField{base, index}   => Expression::SubField{base: f(base), index: index.clone()},
+        Expression::SubIndex{base, index}   => Expression::SubIndex{base: f(base), index: *index},
+        Expression::SubAccess{base, index}  => Expression::SubAccess{base: f(base), index: f(index)},
+        Expression::Mux{sel, a, b}          => Expression::Mux{sel: f(sel), a: f(a), b: f(b)},
+        Expression::ValidIf{sel, value}     => Expression::ValidIf{sel: f(sel), value: f(value)},
+        Expression::PrimitiveOp(op)         => Expression::PrimitiveOp(op.map_exprs(f)),
+        Expression::Probe{target, kind}     => Expression::Probe{target: f(target), kind: *kind},
+        Expression::Read(probe)             => Expression::Read(f(probe)),
+        expr                                => expr.clone(),
+    }
+}
+
+
+/// Retrieve the address of an expression
+fn address<R: Reference>(expr: &Expression<R>) -> usize {
+    expr as *const _ as usize
+}
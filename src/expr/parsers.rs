@@ -9,7 +9,7 @@ use nom::combinator::{map, map_opt, value};
 use nom::sequence::{preceded, terminated, tuple};
 use nom::multi::fold_many0;
 
-use crate::parsers::{IResult, comma, decimal, identifier, kw, lp, op, rp, spaced};
+use crate::parsers::{IResult, decimal, identifier, kw, lp, op, spaced, wrapped};
 use crate::types;
 
 
@@ -23,14 +23,14 @@ pub fn expr<'i, R: super::Reference + Clone>(
 
     use super::Expression as E;
 
-    let sub = |i| map(spaced(|i| expr(reference, i)), Arc::new)(i);
+    let sub = |i| map(wrapped(|i| expr(reference, i)), Arc::new)(i);
 
     let (input, res) = alt((
         map(
             tuple((kw("UInt"), spaced(bitwidth), lp, spaced(num_lit), rp)),
             |(_, width, _, value, _): (_, _, _, num_bigint::BigUint, _)| {
                 let width = width
-                    .or_else(|| value.bits().try_into().ok())
+                    .or_else(|| super::uint_width(&value).try_into().ok())
                     .expect("Could not determine appropriate width");
                 E::UIntLiteral{value, width}
             }
@@ -39,7 +39,7 @@ pub fn expr<'i, R: super::Reference + Clone>(
             tuple((kw("SInt"), spaced(bitwidth), lp, spaced(num_lit), rp)),
             |(_, width, _, value, _): (_, _, _, num_bigint::BigInt, _)| {
                 let width = width
-                    .or_else(|| value.bits().checked_add(1).and_then(|b| b.try_into().ok()))
+                    .or_else(|| super::sint_width(&value).try_into().ok())
                     .expect("Could not determine appropriate width");
                 E::SIntLiteral{value, width}
             }
@@ -52,6 +52,16 @@ pub fn expr<'i, R: super::Reference + Clone>(
             tuple((kw("validif"), lp, &sub, comma, &sub, rp)),
             |(_, _, sel, _, value, _)| E::ValidIf{sel, value}
         ),
+        map(
+            tuple((
+                alt((value(types::ProbeKind::Read, kw("probe")), value(types::ProbeKind::ReadWrite, kw("rwprobe")))),
+                lp,
+                &sub,
+                rp,
+            )),
+            |(kind, _, target, _)| E::Probe{target, kind}
+        ),
+        map(tuple((kw("read"), lp, &sub, rp)), |(_, _, probe, _)| E::Read(probe)),
         map(|i| primitive_op(reference, i), E::PrimitiveOp),
         map_opt(identifier, |name| reference(name).map(E::Reference)),
     ))(input)?;
@@ -67,7 +77,7 @@ pub fn expr<'i, R: super::Reference + Clone>(
         spaced(alt((
             map(preceded(op("."), spaced(field_name)), |i| Subscript::Field(Arc::from(i))),
             map(tuple((op("["), spaced(decimal), spaced(op("]")))), |(_, i, _)| Subscript::Index(i)),
-            map(tuple((op("["), sub, spaced(op("]")))), |(_, i, _)| Subscript::Access(i)),
+            map(tuple((op("["), sub, wrapped(op("]")))), |(_, i, _)| Subscript::Access(i)),
         ))),
         move || res.clone(),
         |e, s| match s {
@@ -90,8 +100,9 @@ pub fn primitive_op<'i, R: super::Reference + Clone>(
 
     use super::primitive::Operation as PO;
 
-    let sub = |i| map(spaced(|i| expr(reference, i)), Arc::new)(i);
+    let sub = |i| map(wrapped(|i| expr(reference, i)), Arc::new)(i);
 
+    let start = input;
     let (input, op) = terminated(identifier, lp)(input)?;
     let (input, op) = match op {
         "add"           => map(tuple((&sub, comma, &sub)), |(l, _, r)| PO::Add(l, r))(input)?,
@@ -105,17 +116,17 @@ pub fn primitive_op<'i, R: super::Reference + Clone>(
         "geq"           => map(tuple((&sub, comma, &sub)), |(l, _, r)| PO::GEq(l, r))(input)?,
         "eq"            => map(tuple((&sub, comma, &sub)), |(l, _, r)| PO::Eq(l, r))(input)?,
         "neq"           => map(tuple((&sub, comma, &sub)), |(l, _, r)| PO::NEq(l, r))(input)?,
-        "pad"           => map(tuple((&sub, comma, spaced(decimal))), |(e, _, b)| PO::Pad(e, b))(input)?,
+        "pad"           => map(tuple((&sub, comma, wrapped(decimal))), |(e, _, b)| PO::Pad(e, b))(input)?,
         "asUInt"        => map(&sub, |e| PO::Cast(e, GT::UInt(None)))(input)?,
         "asSInt"        => map(&sub, |e| PO::Cast(e, GT::SInt(None)))(input)?,
         "asFixed"       => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, p)| PO::Cast(e, GT::Fixed(None, Some(p)))
         )(input)?,
         "asClock"       => map(&sub, |e| PO::Cast(e, GT::Clock))(input)?,
         "asAsyncReset"  => map(&sub, |e| PO::Cast(e, GT::Reset(RK::Async)))(input)?,
-        "shl"           => map(tuple((&sub, comma, spaced(decimal))), |(e, _, b)| PO::Shl(e, b))(input)?,
-        "shr"           => map(tuple((&sub, comma, spaced(decimal))), |(e, _, b)| PO::Shr(e, b))(input)?,
+        "shl"           => map(tuple((&sub, comma, wrapped(decimal))), |(e, _, b)| PO::Shl(e, b))(input)?,
+        "shr"           => map(tuple((&sub, comma, wrapped(decimal))), |(e, _, b)| PO::Shr(e, b))(input)?,
         "dshl"          => map(tuple((&sub, comma, &sub)), |(e, _, b)| PO::DShl(e, b))(input)?,
         "dshr"          => map(tuple((&sub, comma, &sub)), |(e, _, b)| PO::DShr(e, b))(input)?,
         "cvt"           => map(&sub, PO::Cvt)(input)?,
@@ -129,32 +140,41 @@ pub fn primitive_op<'i, R: super::Reference + Clone>(
         "xorr"          => map(&sub, PO::XorReduce)(input)?,
         "cat"           => map(tuple((&sub, comma, &sub)), |(l, _, r)| PO::Cat(l, r))(input)?,
         "bits"          => map(
-            tuple((&sub, comma, spaced(decimal), comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal), comma, wrapped(decimal))),
             |(e, _, l, _, h)| PO::Bits(e, Some(l), Some(h))
         )(input)?,
         "head"          => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, h)| PO::Bits(e, None, Some(h))
         )(input)?,
         "tail"          => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, l)| PO::Bits(e, Some(l), None)
         )(input)?,
         "incp"          => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, b)| PO::IncPrecision(e, b)
         )(input)?,
         "decp"          => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, b)| PO::DecPrecision(e, b)
         )(input)?,
         "setp"          => map(
-            tuple((&sub, comma, spaced(decimal))),
+            tuple((&sub, comma, wrapped(decimal))),
             |(e, _, b)| PO::SetPrecision(e, b)
         )(input)?,
-        _               => return Err(
-            nom::Err::Error(crate::parsers::Error::from_error_kind(input, nom::error::ErrorKind::Tag))
-        ),
+        "wrap"          => map(tuple((&sub, comma, &sub)), |(e, _, r)| PO::Wrap(e, r))(input)?,
+        "clip"          => map(tuple((&sub, comma, &sub)), |(e, _, r)| PO::Clip(e, r))(input)?,
+        "squz"          => map(tuple((&sub, comma, &sub)), |(e, _, r)| PO::Squeeze(e, r))(input)?,
+        "asInterval"    => map(
+            tuple((&sub, comma, wrapped(decimal), comma, wrapped(decimal), comma, wrapped(decimal))),
+            |(e, _, l, _, u, _, p)| PO::AsInterval(e, l, u, p)
+        )(input)?,
+        _               => return Err(nom::Err::Error(nom::error::ContextError::add_context(
+            start,
+            crate::error::UNKNOWN_OPERATION_CONTEXT,
+            crate::parsers::Error::from_error_kind(start, nom::error::ErrorKind::Tag),
+        ))),
     };
 
     value(op, rp)(input)
@@ -182,3 +202,24 @@ fn num_lit<T: num_traits::Num + std::str::FromStr>(input: &str) -> IResult<T> {
     ))(input)
 }
 
+
+/// Parse a comma within an argument list
+///
+/// Arguments may be spread over multiple lines. Hence, this parser skips any
+/// preceding whitespace including line breaks.
+fn comma(input: &str) -> IResult<'_, ()> {
+    use nom::Parser;
+
+    wrapped(op(",")).parse(input)
+}
+
+
+/// Parse the right parenthesis terminating an argument list
+///
+/// Arguments may be spread over multiple lines. Hence, this parser skips any
+/// preceding whitespace including line breaks.
+fn rp(input: &str) -> IResult<'_, ()> {
+    use nom::Parser;
+
+    wrapped(op(")")).parse(input)
+}
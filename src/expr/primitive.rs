@@ -11,7 +11,7 @@ use super::{Expression, Reference};
 
 
 /// A single ("primitive") operation
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Operation<R: Reference> {
     /// Arithmetic addition
     Add(Arc<Expression<R>>, Arc<Expression<R>>),
@@ -75,6 +75,14 @@ pub enum Operation<R: Reference> {
     DecPrecision(Arc<Expression<R>>, UBits),
     /// Set precision (of "fixed")
     SetPrecision(Arc<Expression<R>>, SBits),
+    /// Wrap an interval into the bounds of another expression's type
+    Wrap(Arc<Expression<R>>, Arc<Expression<R>>),
+    /// Clip an interval to the bounds of another expression's type
+    Clip(Arc<Expression<R>>, Arc<Expression<R>>),
+    /// Squeeze an interval into the bounds of another interval
+    Squeeze(Arc<Expression<R>>, Arc<Expression<R>>),
+    /// Interpret as an interval, with raw lower and upper bound and point
+    AsInterval(Arc<Expression<R>>, i64, i64, SBits),
 }
 
 impl<R: Reference> Operation<R> {
@@ -113,6 +121,113 @@ impl<R: Reference> Operation<R> {
             Self::IncPrecision(sub, ..) => vec![sub],
             Self::DecPrecision(sub, ..) => vec![sub],
             Self::SetPrecision(sub, ..) => vec![sub],
+            Self::Wrap(sub, r#ref)      => vec![sub, r#ref],
+            Self::Clip(sub, r#ref)      => vec![sub, r#ref],
+            Self::Squeeze(sub, r#ref)   => vec![sub, r#ref],
+            Self::AsInterval(sub, ..)   => vec![sub],
+        }
+    }
+
+    /// Create an operation of the same kind with mapped subexpressions
+    ///
+    /// This function creates an operation with every subexpression replaced
+    /// by the result of the given function applied to it.
+    pub fn map_exprs<S: Reference>(
+        &self,
+        mut f: impl FnMut(&Arc<Expression<R>>) -> Arc<Expression<S>>,
+    ) -> Operation<S> {
+        use Operation as O;
+
+        match self {
+            Self::Add(lhs, rhs)             => O::Add(f(lhs), f(rhs)),
+            Self::Sub(lhs, rhs)             => O::Sub(f(lhs), f(rhs)),
+            Self::Mul(lhs, rhs)             => O::Mul(f(lhs), f(rhs)),
+            Self::Div(lhs, rhs)             => O::Div(f(lhs), f(rhs)),
+            Self::Rem(lhs, rhs)             => O::Rem(f(lhs), f(rhs)),
+            Self::Lt(lhs, rhs)              => O::Lt(f(lhs), f(rhs)),
+            Self::LEq(lhs, rhs)             => O::LEq(f(lhs), f(rhs)),
+            Self::Gt(lhs, rhs)              => O::Gt(f(lhs), f(rhs)),
+            Self::GEq(lhs, rhs)             => O::GEq(f(lhs), f(rhs)),
+            Self::Eq(lhs, rhs)              => O::Eq(f(lhs), f(rhs)),
+            Self::NEq(lhs, rhs)             => O::NEq(f(lhs), f(rhs)),
+            Self::Pad(sub, bits)            => O::Pad(f(sub), *bits),
+            Self::Cast(sub, t)              => O::Cast(f(sub), *t),
+            Self::Shl(sub, bits)            => O::Shl(f(sub), *bits),
+            Self::Shr(sub, bits)            => O::Shr(f(sub), *bits),
+            Self::DShl(sub, index)          => O::DShl(f(sub), f(index)),
+            Self::DShr(sub, index)          => O::DShr(f(sub), f(index)),
+            Self::Cvt(sub)                  => O::Cvt(f(sub)),
+            Self::Neg(sub)                  => O::Neg(f(sub)),
+            Self::Not(sub)                  => O::Not(f(sub)),
+            Self::And(lhs, rhs)             => O::And(f(lhs), f(rhs)),
+            Self::Or(lhs, rhs)              => O::Or(f(lhs), f(rhs)),
+            Self::Xor(lhs, rhs)             => O::Xor(f(lhs), f(rhs)),
+            Self::AndReduce(sub)            => O::AndReduce(f(sub)),
+            Self::OrReduce(sub)             => O::OrReduce(f(sub)),
+            Self::XorReduce(sub)            => O::XorReduce(f(sub)),
+            Self::Cat(lhs, rhs)             => O::Cat(f(lhs), f(rhs)),
+            Self::Bits(sub, hi, lo)         => O::Bits(f(sub), *hi, *lo),
+            Self::IncPrecision(sub, bits)   => O::IncPrecision(f(sub), *bits),
+            Self::DecPrecision(sub, bits)   => O::DecPrecision(f(sub), *bits),
+            Self::SetPrecision(sub, bits)   => O::SetPrecision(f(sub), *bits),
+            Self::Wrap(sub, r#ref)          => O::Wrap(f(sub), f(r#ref)),
+            Self::Clip(sub, r#ref)          => O::Clip(f(sub), f(r#ref)),
+            Self::Squeeze(sub, r#ref)       => O::Squeeze(f(sub), f(r#ref)),
+            Self::AsInterval(sub, l, u, p)  => O::AsInterval(f(sub), *l, *u, *p),
+        }
+    }
+
+    /// Retrieve the keyword identifying the kind of operation
+    ///
+    /// The keyword is the name of the operation as it appears in FIRRTL
+    /// source. For casts, the keyword depends on the target type and for bit
+    /// extractions on the bounds present.
+    pub fn keyword(&self) -> &'static str {
+        use types::{GroundType as GT, ResetKind as RK};
+
+        match self {
+            Self::Add(..)                       => "add",
+            Self::Sub(..)                       => "sub",
+            Self::Mul(..)                       => "mul",
+            Self::Div(..)                       => "div",
+            Self::Rem(..)                       => "rem",
+            Self::Lt(..)                        => "lt",
+            Self::LEq(..)                       => "leq",
+            Self::Gt(..)                        => "gt",
+            Self::GEq(..)                       => "geq",
+            Self::Eq(..)                        => "eq",
+            Self::NEq(..)                       => "neq",
+            Self::Pad(..)                       => "pad",
+            Self::Cast(_, GT::UInt(..))         => "asUInt",
+            Self::Cast(_, GT::SInt(..))         => "asSInt",
+            Self::Cast(_, GT::Fixed(..))        => "asFixed",
+            Self::Cast(_, GT::Clock)            => "asClock",
+            Self::Cast(_, GT::Reset(RK::Async)) => "asAsyncReset",
+            Self::Cast(..)                      => "cast",
+            Self::Shl(..)                       => "shl",
+            Self::Shr(..)                       => "shr",
+            Self::DShl(..)                      => "dshl",
+            Self::DShr(..)                      => "dshr",
+            Self::Cvt(..)                       => "cvt",
+            Self::Neg(..)                       => "neg",
+            Self::Not(..)                       => "not",
+            Self::And(..)                       => "and",
+            Self::Or(..)                        => "or",
+            Self::Xor(..)                       => "xor",
+            Self::AndReduce(..)                 => "andr",
+            Self::OrReduce(..)                  => "orr",
+            Self::XorReduce(..)                 => "xorr",
+            Self::Cat(..)                       => "cat",
+            Self::Bits(_, None, Some(_))        => "head",
+            Self::Bits(_, Some(_), None)        => "tail",
+            Self::Bits(..)                      => "bits",
+            Self::IncPrecision(..)              => "incp",
+            Self::DecPrecision(..)              => "decp",
+            Self::SetPrecision(..)              => "setp",
+            Self::Wrap(..)                      => "wrap",
+            Self::Clip(..)                      => "clip",
+            Self::Squeeze(..)                   => "squz",
+            Self::AsInterval(..)                => "asInterval",
         }
     }
 }
@@ -144,6 +259,19 @@ impl<R> types::Typed for Operation<R>
         } else {
             Err(self.clone().into())
         });
+        let interval = |e: &Arc<Expression<R>>| ground(e).and_then(|t| if let GT::Interval(l, u, p) = t {
+            Ok((l, u, p))
+        } else {
+            Err(self.clone().into())
+        });
+
+        // Common logic for wrap and clip, which take the bounds of the reference
+        let rebound = |e: &Arc<Expression<R>>, r: &Arc<Expression<R>>| -> Result<GT, Expression<R>> {
+            let (_, _, point) = interval(e)?;
+            let (lower, upper) = types::interval::bounds(&ground(r)?)
+                .ok_or_else(|| Expression::from(self.clone()))?;
+            Ok(GT::Interval(lower, upper, point))
+        };
 
         // Common logic for "sums", i.e. add and sub
         let sum = |l: &Arc<Expression<R>>, r: &Arc<Expression<R>>| match (ground(l)?, ground(r)?) {
@@ -247,6 +375,21 @@ impl<R> types::Typed for Operation<R>
                     .and_then(|w| w.try_into().ok()),
                 p
             )),
+            Self::Wrap(sub, r#ref)          => rebound(sub, r#ref),
+            Self::Clip(sub, r#ref)          => rebound(sub, r#ref),
+            Self::Squeeze(sub, r#ref)       => {
+                use types::interval::{lower_intersection, upper_intersection};
+
+                let (sl, su, p) = interval(sub)?;
+                let (rl, ru, _) = interval(r#ref)?;
+                Ok(GT::Interval(lower_intersection(sl, rl), upper_intersection(su, ru), p))
+            },
+            Self::AsInterval(sub, l, u, p)  => ground(sub).map(|_| {
+                use types::interval::{Bound, Decimal};
+
+                let bound = |v| Decimal::from_fixed(v, *p).map(Bound::Closed);
+                GT::Interval(bound(*l), bound(*u), Some(*p))
+            }),
         }
     }
 }
@@ -295,6 +438,10 @@ impl<R: Reference> fmt::Display for Operation<R> {
             Self::IncPrecision(sub, bits)           => write!(f, "incp({}, {})", sub, bits),
             Self::DecPrecision(sub, bits)           => write!(f, "decp({}, {})", sub, bits),
             Self::SetPrecision(sub, bits)           => write!(f, "setp({}, {})", sub, bits),
+            Self::Wrap(sub, r#ref)                  => write!(f, "wrap({}, {})", sub, r#ref),
+            Self::Clip(sub, r#ref)                  => write!(f, "clip({}, {})", sub, r#ref),
+            Self::Squeeze(sub, r#ref)               => write!(f, "squz({}, {})", sub, r#ref),
+            Self::AsInterval(sub, l, u, p)          => write!(f, "asInterval({}, {}, {}, {})", sub, l, u, p),
         }
     }
 }
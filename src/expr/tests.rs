@@ -12,7 +12,7 @@ use crate::named::Named;
 use crate::tests::{Equivalence, Identifier};
 use crate::types;
 
-use super::{Expression, Flow, parsers, primitive};
+use super::{Expr, Expression, Flow, Interner, parsers, primitive};
 
 
 #[quickcheck]
@@ -26,6 +26,40 @@ fn parse_expr(original: TypedExpr<Identifier>) -> Result<Equivalence<Expression<
 }
 
 
+#[quickcheck]
+fn parse_wrapped_expr(
+    original: TypedExpr<Identifier>,
+    width: u8,
+    depth: u8,
+) -> Result<Equivalence<Expression<Identifier>>, String> {
+    let s = original.expr.fmt_wrapped(width.into()).with_max_depth(depth.into()).to_string();
+    let res = all_consuming(|i| parsers::expr(|s| Some(s.into()), i))(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original.expr, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn wrapped_expr_depth(original: TypedExpr<Identifier>, depth: u8) -> bool {
+    // Determine the nesting depth of argument lists closed within a line
+    fn line_depth(line: &str) -> usize {
+        line.chars().fold((0usize, 0), |(depth, max), c| match c {
+            '(' => (depth + 1, max),
+            ')' => (depth.saturating_sub(1), max.max(depth)),
+            _   => (depth, max),
+        }).1
+    }
+
+    // Argument lists consisting of a single atom, e.g. those of literals, are
+    // never broken up.
+    let depth = (depth % 4).into();
+    let limit = std::cmp::max(depth, 1);
+    original.expr.fmt_wrapped(usize::MAX).with_max_depth(depth).to_string().lines().all(|l| line_depth(l) <= limit)
+}
+
+
 #[quickcheck]
 fn expr_typing(expr: TypedExpr<Entity>) -> Result<bool, String> {
     use types::Typed;
@@ -40,6 +74,205 @@ fn expr_typing(expr: TypedExpr<Entity>) -> Result<bool, String> {
 }
 
 
+#[quickcheck]
+fn checked_sub_exprs(expr: TypedExpr<Entity>, index: u16, name: Identifier) -> bool {
+    use types::Type;
+
+    let base = expr.expr;
+    let uint = Expression::UIntLiteral{value: 0u8.into(), width: 1};
+    let sint = Expression::SIntLiteral{value: 0u8.into(), width: 1};
+    match &expr.r#type {
+        Type::Bundle(fields) => fields.iter().all(|f| base.clone().field(f.name().clone()).is_ok()) &&
+            base.clone().field(name.clone()).is_ok() == fields.iter().any(|f| f.name().as_ref() == name.as_ref()) &&
+            base.clone().index(0).is_err() &&
+            base.clone().access(uint).is_err(),
+        Type::Vector(_, width) => base.clone().index(index).is_ok() == (index < *width) &&
+            base.clone().access(uint).is_ok() &&
+            base.clone().access(sint).is_err() &&
+            base.clone().field(name).is_err(),
+        Type::GroundType(_) | Type::Probe(..) | Type::Enum(_) | Type::Alias(..) =>
+            base.clone().field(name).is_err() && base.index(index).is_err(),
+    }
+}
+
+
+#[quickcheck]
+fn interned_exprs_shared(original: TypedExpr<Identifier>) -> bool {
+    let mut interner = Interner::new();
+    let a = interner.intern(original.expr.clone());
+    let len = interner.len();
+
+    // Create a deep copy not sharing any sub-expressions with the original
+    let b = interner.intern(original.expr.map_references(&mut Clone::clone));
+    a.as_ref() == &original.expr && Arc::ptr_eq(&a, &b) && interner.len() == len
+}
+
+
+#[quickcheck]
+fn literal_widths(value: i64, width: u8) -> Result<bool, String> {
+    let width = width as u16;
+    let parse = |s: &str| all_consuming(|i| parsers::expr(|_| None::<Identifier>, i))(s)
+        .finish()
+        .map(|(_, e)| e)
+        .map_err(|e| e.to_string());
+
+    let unsigned = value.unsigned_abs();
+    let uint = Expression::<Identifier>::uint(unsigned).map_err(|e| e.to_string())?;
+    let sint = Expression::<Identifier>::sint(value).map_err(|e| e.to_string())?;
+    let fits_unsigned = width >= 64 || unsigned < 1 << width;
+    let fits_signed = width > 0 && (width > 64 || unsigned < 1 << (width - 1));
+    Ok(uint == parse(&format!("UInt({})", unsigned))? &&
+        sint == parse(&format!("SInt({})", value))? &&
+        Expression::<Identifier>::uint_w(unsigned, width).is_ok() == fits_unsigned &&
+        Expression::<Identifier>::sint_w(value, width).is_ok() == fits_signed)
+}
+
+
+#[quickcheck]
+fn dsl_operations(a: TypedExpr<Identifier>, b: TypedExpr<Identifier>, bits: u16) -> bool {
+    use primitive::Operation as O;
+
+    let (a, b) = (Arc::new(a.expr), Arc::new(b.expr));
+    let x = || Expr::from(a.as_ref().clone());
+    let y = || b.as_ref().clone();
+    let op = |op: O<Identifier>| Expression::PrimitiveOp(op);
+
+    (x() + y()).into_inner() == op(O::Add(a.clone(), b.clone())) &&
+        (x() - y()).into_inner() == op(O::Sub(a.clone(), b.clone())) &&
+        (x() * y()).into_inner() == op(O::Mul(a.clone(), b.clone())) &&
+        (x() / y()).into_inner() == op(O::Div(a.clone(), b.clone())) &&
+        (x() % y()).into_inner() == op(O::Rem(a.clone(), b.clone())) &&
+        (x() & y()).into_inner() == op(O::And(a.clone(), b.clone())) &&
+        (x() | y()).into_inner() == op(O::Or(a.clone(), b.clone())) &&
+        (x() ^ y()).into_inner() == op(O::Xor(a.clone(), b.clone())) &&
+        (!x()).into_inner() == op(O::Not(a.clone())) &&
+        (-x()).into_inner() == op(O::Neg(a.clone())) &&
+        (x() << bits).into_inner() == op(O::Shl(a.clone(), bits)) &&
+        (x() >> bits).into_inner() == op(O::Shr(a.clone(), bits)) &&
+        x().bits(bits, 0).into_inner() == op(O::Bits(a.clone(), Some(bits), Some(0))) &&
+        x().mux(y(), y()).into_inner() == Expression::Mux{sel: b.clone(), a: a.clone(), b: b.clone()}
+}
+
+
+#[test]
+fn dsl_display() {
+    let r = |n: &str| Expr::reference(Identifier::from(n));
+    let expr = (r("a") + r("b")).tail(1).cat(!r("c")).mux(r("s").field("en").index(2), Expr::uint(3u8, 4) << 1);
+    assert_eq!(expr.to_string(), "mux(s.en[2], cat(tail(add(a, b), 1), not(c)), shl(UInt<4>(3), 1))");
+}
+
+
+#[test]
+fn probe_exprs() {
+    use crate::stmt::Entity;
+    use types::{GroundType as GT, ProbeKind, Type, Typed};
+
+    let uint: Type = GT::UInt(Some(4)).into();
+    let wire = |name: &str, r#type: Type| Arc::new(Entity::Wire{name: name.into(), r#type});
+    let entities = [
+        wire("x", uint.clone()),
+        wire("p", Type::Probe(Arc::new(uint.clone()), ProbeKind::ReadWrite)),
+        wire("read", uint.clone()),
+    ];
+    let entities = &entities;
+    let lookup = move |n: &str| entities.iter().find(|e| e.name_ref() == n).cloned();
+    let parse = |s: &str| all_consuming(|i| parsers::expr(lookup, i))(s)
+        .finish()
+        .map(|(_, e)| e)
+        .map_err(|e| e.to_string());
+    let reference = |n: &str| Arc::new(Expression::Reference(lookup(n).expect("Unknown entity")));
+
+    let probe = parse("rwprobe(x)").expect("Could not parse probe");
+    assert_eq!(probe, Expression::Probe{target: reference("x"), kind: ProbeKind::ReadWrite});
+    assert_eq!(probe.to_string(), "rwprobe(x)");
+    assert_eq!(probe.r#type(), Ok(Type::Probe(Arc::new(uint.clone()), ProbeKind::ReadWrite)));
+
+    let read = parse("read(p)").expect("Could not parse read");
+    assert_eq!(read, Expression::Read(reference("p")));
+    assert_eq!(read.to_string(), "read(p)");
+    assert_eq!(read.r#type(), Ok(uint.clone()));
+    assert_eq!(read.flow(), Ok(Flow::Source));
+
+    assert_eq!(parse("read"), Ok(Expression::Reference(lookup("read").expect("Unknown entity"))));
+    assert!(parse("read(x)").expect("Could not parse read").r#type().is_err());
+    assert!(parse("probe(p)").expect("Could not parse probe").r#type().is_err());
+}
+
+
+#[test]
+fn access_chains() {
+    use crate::stmt::Entity;
+    use types::{Access as A, GroundType as GT, Type, Typed};
+
+    let (_, r#type) = all_consuming(|i| types::parsers::r#type(i, &[]))("{a: UInt<1>[2]}[3][4]")
+        .finish()
+        .expect("Could not parse type");
+    let entities = [
+        Arc::new(Entity::Wire{name: "w".into(), r#type}),
+        Arc::new(Entity::Wire{name: "i".into(), r#type: GT::UInt(Some(2)).into()}),
+    ];
+    let entities = &entities;
+    let lookup = move |n: &str| entities.iter().find(|e| e.name_ref() == n).cloned();
+    let parse = |s: &str| all_consuming(|i| parsers::expr(lookup, i))(s)
+        .finish()
+        .map(|(_, e)| e)
+        .map_err(|e| e.to_string());
+
+    let expr = parse("w[i][ 2 ].a[w[0][1].a[i]]").expect("Could not parse expression");
+    assert_eq!(expr.to_string(), "w[i][2].a[w[0][1].a[i]]");
+    assert_eq!(parse(&expr.to_string()), Ok(expr.clone()));
+
+    let (root, path) = expr.access_path();
+    assert_eq!(root.to_string(), "w");
+    assert_eq!(path, vec![A::Dynamic, A::Index(2), A::Field("a".into()), A::Dynamic]);
+
+    let root_type = root.r#type().expect("Could not type root");
+    assert_eq!(root_type.at_access_path(&path), Some(&Type::from(GT::UInt(Some(1)))));
+    assert_eq!(expr.r#type().ok().as_ref(), root_type.at_access_path(&path));
+
+    let expr = parse("i").expect("Could not parse expression");
+    let (root, path) = expr.access_path();
+    assert_eq!(root.to_string(), "i");
+    assert!(path.is_empty());
+}
+
+
+#[test]
+fn interval_ops() {
+    use crate::stmt::Entity;
+    use types::Typed;
+
+    let wire = |name: &str, r#type: &str| {
+        let (_, r#type) = all_consuming(|i| types::parsers::r#type(i, &[]))(r#type).finish().expect("Could not parse type");
+        Arc::new(Entity::Wire{name: name.into(), r#type})
+    };
+    let entities = [
+        wire("a", "Interval[0, 4].2"),
+        wire("b", "Interval(-1.5, 2.25].1"),
+        wire("s", "SInt<3>"),
+        wire("u", "UInt<4>"),
+    ];
+    let entities = &entities;
+    let lookup = move |n: &str| entities.iter().find(|e| e.name_ref() == n).cloned();
+    let parse = |s: &str| all_consuming(|i| parsers::expr(lookup, i))(s)
+        .finish()
+        .map(|(_, e)| e)
+        .map_err(|e| e.to_string());
+    let type_of = |s: &str| parse(s).expect("Could not parse expression").r#type().map(|t| t.to_string()).ok();
+
+    assert_eq!(type_of("wrap(a, s)"), Some("Interval[-4, 3].2".to_string()));
+    assert_eq!(type_of("clip(b, u)"), Some("Interval[0, 15].1".to_string()));
+    assert_eq!(type_of("squz(a, b)"), Some("Interval[0, 2.25].2".to_string()));
+    assert_eq!(type_of("asInterval(u, -3, 5, 1)"), Some("Interval[-1.5, 2.5].1".to_string()));
+    assert_eq!(type_of("wrap(u, s)"), None);
+    assert_eq!(type_of("squz(a, s)"), None);
+
+    let expr = parse("asInterval(clip(b, a), -3, 5, 1)").expect("Could not parse expression");
+    assert_eq!(expr.to_string(), "asInterval(clip(b, a), -3, 5, 1)");
+    assert_eq!(parse(&expr.to_string()), Ok(expr));
+}
+
+
 /// Helper for expressions preserving the type used for generation
 ///
 /// Expressions are generated from a type, but the `Arbitrary` impl discards the
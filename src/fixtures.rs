@@ -0,0 +1,211 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Hand-curated example circuits
+//!
+//! This module is only available with the `fixtures` feature enabled. It
+//! provides a small set of realistic, deterministic circuits intended for
+//! benchmarks, examples and tests, both in downstream crates and in this
+//! library. Each fixture is available as FIRRTL source as well as via a
+//! function constructing the corresponding [Circuit]:
+//!  * [ALU]/[alu]: a combinational ALU with a registered flag output,
+//!  * [FIFO]/[fifo]: a FIFO queue backed by a memory and
+//!  * [HIERARCHY]/[hierarchy]: a hierarchical design instantiating several
+//!    modules, including an external module, multiple times.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::{Named, fixtures};
+//!
+//! let circuit = fixtures::fifo();
+//! assert_eq!(circuit.top_module().name().as_ref(), "Fifo");
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::circuit::Circuit;
+
+
+/// Source of the [alu] fixture
+pub const ALU: &str = concat!(
+    "circuit ALU :\n",
+    "  module ALU :\n",
+    "    input clock : Clock\n",
+    "    input reset : UInt<1>\n",
+    "    input op : UInt<3>\n",
+    "    input a : UInt<32>\n",
+    "    input b : UInt<32>\n",
+    "    output result : UInt<32>\n",
+    "    output zero : UInt<1>\n",
+    "\n",
+    "    wire res : UInt<32>\n",
+    "    res <= UInt<32>(0)\n",
+    "    when eq(op, UInt<3>(0)) :\n",
+    "      res <= tail(add(a, b), 1)\n",
+    "    else when eq(op, UInt<3>(1)) :\n",
+    "      res <= tail(sub(a, b), 1)\n",
+    "    else when eq(op, UInt<3>(2)) :\n",
+    "      res <= and(a, b)\n",
+    "    else when eq(op, UInt<3>(3)) :\n",
+    "      res <= or(a, b)\n",
+    "    else when eq(op, UInt<3>(4)) :\n",
+    "      res <= xor(a, b)\n",
+    "    else when eq(op, UInt<3>(5)) :\n",
+    "      res <= bits(dshl(a, bits(b, 4, 0)), 31, 0)\n",
+    "    else when eq(op, UInt<3>(6)) :\n",
+    "      res <= dshr(a, bits(b, 4, 0))\n",
+    "    else :\n",
+    "      res <= pad(lt(a, b), 32)\n",
+    "    result <= res\n",
+    "\n",
+    "    node is_zero = eq(res, UInt<32>(0))\n",
+    "    reg zero_r : UInt<1>, clock with: (reset => (reset, UInt<1>(0)))\n",
+    "    zero_r <= is_zero\n",
+    "    zero <= zero_r\n",
+);
+
+/// Source of the [fifo] fixture
+pub const FIFO: &str = concat!(
+    "circuit Fifo :\n",
+    "  module Fifo :\n",
+    "    input clock : Clock\n",
+    "    input reset : UInt<1>\n",
+    "    input enq : { flip ready : UInt<1>, valid : UInt<1>, bits : UInt<8> }\n",
+    "    output deq : { flip ready : UInt<1>, valid : UInt<1>, bits : UInt<8> }\n",
+    "\n",
+    "    mem ram :\n",
+    "      data-type => UInt<8>\n",
+    "      depth => 16\n",
+    "      read-latency => 0\n",
+    "      write-latency => 1\n",
+    "      reader => r\n",
+    "      writer => w\n",
+    "      read-under-write => undefined\n",
+    "\n",
+    "    reg enq_ptr : UInt<4>, clock with: (reset => (reset, UInt<4>(0)))\n",
+    "    reg deq_ptr : UInt<4>, clock with: (reset => (reset, UInt<4>(0)))\n",
+    "    reg maybe_full : UInt<1>, clock with: (reset => (reset, UInt<1>(0)))\n",
+    "\n",
+    "    node ptr_match = eq(enq_ptr, deq_ptr)\n",
+    "    node empty = and(ptr_match, not(maybe_full))\n",
+    "    node full = and(ptr_match, maybe_full)\n",
+    "    node do_enq = and(enq.ready, enq.valid)\n",
+    "    node do_deq = and(deq.ready, deq.valid)\n",
+    "\n",
+    "    ram.w.addr <= enq_ptr\n",
+    "    ram.w.en <= do_enq\n",
+    "    ram.w.clk <= clock\n",
+    "    ram.w.data <= enq.bits\n",
+    "    ram.w.mask <= UInt<1>(1)\n",
+    "    ram.r.addr <= deq_ptr\n",
+    "    ram.r.en <= UInt<1>(1)\n",
+    "    ram.r.clk <= clock\n",
+    "\n",
+    "    when do_enq :\n",
+    "      enq_ptr <= tail(add(enq_ptr, UInt<4>(1)), 1)\n",
+    "    when do_deq :\n",
+    "      deq_ptr <= tail(add(deq_ptr, UInt<4>(1)), 1)\n",
+    "    when neq(do_enq, do_deq) :\n",
+    "      maybe_full <= do_enq\n",
+    "\n",
+    "    enq.ready <= not(full)\n",
+    "    deq.valid <= not(empty)\n",
+    "    deq.bits <= ram.r.data\n",
+);
+
+/// Source of the [hierarchy] fixture
+pub const HIERARCHY: &str = concat!(
+    "circuit SoC :\n",
+    "  extmodule ClockGen :\n",
+    "    input clk_in : Clock\n",
+    "    output clk_out : Clock\n",
+    "    defname = PLL\n",
+    "    parameter MULTIPLIER = 4\n",
+    "\n",
+    "  module Counter :\n",
+    "    input clock : Clock\n",
+    "    input reset : UInt<1>\n",
+    "    input en : UInt<1>\n",
+    "    output count : UInt<8>\n",
+    "\n",
+    "    reg value : UInt<8>, clock with: (reset => (reset, UInt<8>(0)))\n",
+    "    when en :\n",
+    "      value <= tail(add(value, UInt<8>(1)), 1)\n",
+    "    count <= value\n",
+    "\n",
+    "  module Compare :\n",
+    "    input a : UInt<8>\n",
+    "    input b : UInt<8>\n",
+    "    output eq : UInt<1>\n",
+    "    output lt : UInt<1>\n",
+    "\n",
+    "    eq <= eq(a, b)\n",
+    "    lt <= lt(a, b)\n",
+    "\n",
+    "  module Timer :\n",
+    "    input clock : Clock\n",
+    "    input reset : UInt<1>\n",
+    "    input threshold : UInt<8>\n",
+    "    output fire : UInt<1>\n",
+    "\n",
+    "    inst counter of Counter\n",
+    "    counter.clock <= clock\n",
+    "    counter.reset <= reset\n",
+    "    counter.en <= UInt<1>(1)\n",
+    "    inst compare of Compare\n",
+    "    compare.a <= counter.count\n",
+    "    compare.b <= threshold\n",
+    "    fire <= compare.eq\n",
+    "\n",
+    "  module SoC :\n",
+    "    input clock : Clock\n",
+    "    input reset : UInt<1>\n",
+    "    input thresholds : UInt<8>[2]\n",
+    "    output irq : UInt<2>\n",
+    "\n",
+    "    inst clkgen of ClockGen\n",
+    "    clkgen.clk_in <= clock\n",
+    "    inst timer0 of Timer\n",
+    "    timer0.clock <= clkgen.clk_out\n",
+    "    timer0.reset <= reset\n",
+    "    timer0.threshold <= thresholds[0]\n",
+    "    inst timer1 of Timer\n",
+    "    timer1.clock <= clkgen.clk_out\n",
+    "    timer1.reset <= reset\n",
+    "    timer1.threshold <= thresholds[1]\n",
+    "    irq <= cat(timer1.fire, timer0.fire)\n",
+);
+
+
+/// Construct a combinational ALU with a registered zero flag
+///
+/// The circuit consists of a single module `ALU` selecting one of eight
+/// operations on two 32 bit operands.
+pub fn alu() -> Circuit {
+    parse(ALU)
+}
+
+
+/// Construct a FIFO queue
+///
+/// The circuit consists of a single module `Fifo` with ready/valid interfaces
+/// buffering up to 16 bytes in a memory.
+pub fn fifo() -> Circuit {
+    parse(FIFO)
+}
+
+
+/// Construct a hierarchical design
+///
+/// The top module `SoC` instantiates an external module and two instances of
+/// a `Timer`, which in turn instantiates two more modules.
+pub fn hierarchy() -> Circuit {
+    parse(HIERARCHY)
+}
+
+
+/// Parse one of the fixtures' sources
+fn parse(source: &str) -> Circuit {
+    crate::circuit::parse(source).expect("Malformed fixture")
+}
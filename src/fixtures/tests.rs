@@ -0,0 +1,25 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to fixtures
+
+use crate::circuit::{Circuit, parse};
+
+
+#[test]
+fn fixtures_round_trip() {
+    let fixtures: [fn() -> Circuit; 3] = [super::alu, super::fifo, super::hierarchy];
+    for fixture in fixtures.iter() {
+        let circuit = fixture();
+        assert_eq!(circuit, fixture());
+        assert_eq!(parse(&circuit.to_string()).expect("Could not reparse fixture"), circuit);
+    }
+}
+
+
+#[test]
+fn hierarchy_modules() {
+    let circuit = super::hierarchy();
+    assert_eq!(circuit.modules().count(), 5);
+    assert_eq!(circuit.external_modules().count(), 1);
+    assert_eq!(circuit.elaborate().iter().count(), 8);
+}
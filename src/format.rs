@@ -0,0 +1,487 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Canonical formatting of FIRRTL
+//!
+//! The [Display](fmt::Display) implementations of the AST nodes emit valid
+//! FIRRTL, but they put every statement on a single line regardless of its
+//! length. This module provides a formatter which emits FIRRTL according to a
+//! configurable [Style]. Most notably, expressions exceeding the maximum line
+//! width are broken up by putting the arguments of primitive operations, muxes
+//! and the like on separate lines.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::format::Style;
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    input a: UInt<8>\n",
+//!     "    output b: UInt<8>\n",
+//!     "    b <= add(a, a)\n",
+//! )).unwrap();
+//! let style = Style::default().with_max_width(16);
+//! assert!(style.circuit(&circuit).to_string().contains("add(\n"));
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::{self, Write};
+
+use crate::circuit::{Circuit, Version};
+use crate::indentation::{DisplayIndented, Indentation, LockedIndentation};
+use crate::info::{Info, InfoMode, WithInfo};
+use crate::memory::simple::Kind as SimpleKind;
+use crate::module::{Kind as ModuleKind, Module};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+
+pub use crate::indentation::IndentationConfig;
+
+
+/// Formatting style
+///
+/// A `Style` is created with sensible defaults via [Default] and may be
+/// adjusted via its `with_*` functions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    max_width: usize,
+    sort_ports: bool,
+    sort_params: bool,
+    align_connections: bool,
+    info: InfoMode,
+    explicit_ruw: bool,
+    inline_branches: bool,
+    indentation: IndentationConfig,
+    version: Option<Version>,
+}
+
+impl Style {
+    /// Set the maximum line width
+    ///
+    /// The formatter will break up expressions which would exceed this width.
+    /// Note that lines may still exceed the width, e.g. if they contain long
+    /// identifiers.
+    pub fn with_max_width(self, max_width: usize) -> Self {
+        Self {max_width, ..self}
+    }
+
+    /// Set whether ports are to be emitted in alphabetical order
+    ///
+    /// Note that the order of ports is significant, e.g. for the type of an
+    /// instance. Hence, sorting them may alter the semantics of a circuit.
+    pub fn with_sorted_ports(self, sort_ports: bool) -> Self {
+        Self {sort_ports, ..self}
+    }
+
+    /// Set whether parameters of external modules are to be sorted by name
+    pub fn with_sorted_params(self, sort_params: bool) -> Self {
+        Self {sort_params, ..self}
+    }
+
+    /// Set whether `<=` and `<-` in consecutive connections are to be aligned
+    pub fn with_aligned_connections(self, align_connections: bool) -> Self {
+        Self {align_connections, ..self}
+    }
+
+    /// Set the treatment of info attributes
+    ///
+    /// By default, info attributes are emitted as they are.
+    pub fn with_info(self, info: InfoMode) -> Self {
+        Self {info, ..self}
+    }
+
+    /// Set whether the read-under-write behaviour of `smem`s is always emitted
+    ///
+    /// If set, `smem`s without an explicit read-under-write behaviour are
+    /// emitted with `undefined`, which some tools require.
+    pub fn with_explicit_ruw(self, explicit_ruw: bool) -> Self {
+        Self {explicit_ruw, ..self}
+    }
+
+    /// Set whether branches consisting of a single statement are put inline
+    ///
+    /// If set, a branch of a conditional statement consisting of a single
+    /// statement is emitted on the same line as its `when` or `else`, e.g.
+    /// `when c : x <= y`, if the line does not exceed the maximum width.
+    /// Otherwise, every branch is emitted as an indented block.
+    pub fn with_inline_branches(self, inline_branches: bool) -> Self {
+        Self {inline_branches, ..self}
+    }
+
+    /// Set the indentation of nested levels
+    ///
+    /// By default, every level is indented by two spaces.
+    pub fn with_indentation(self, indentation: IndentationConfig) -> Self {
+        Self {indentation, ..self}
+    }
+
+    /// Set the FIRRTL version governing the grammar of modules formatted
+    ///
+    /// For versions `3.0.0` and later, connections and invalidations are
+    /// emitted using the `connect` and `invalidate` keywords. Circuits are
+    /// always formatted according to their own [version](Circuit::version).
+    pub fn with_version(self, version: impl Into<Option<Version>>) -> Self {
+        Self {version: version.into(), ..self}
+    }
+
+    /// Retrieve the maximum line width
+    pub fn max_width(&self) -> usize {
+        self.max_width
+    }
+
+    /// Format a circuit according to this style
+    pub fn circuit<'a>(&'a self, circuit: &'a Circuit) -> Formatted<'a, Circuit> {
+        Formatted {item: circuit, style: self}
+    }
+
+    /// Format a module according to this style
+    pub fn module<'a>(&'a self, module: &'a Module) -> Formatted<'a, Module> {
+        Formatted {item: module, style: self}
+    }
+
+    /// Format a module with the given indentation
+    fn fmt_module(&self, module: &Module, indent: &mut Indentation, f: &mut impl Write) -> fmt::Result {
+        writeln!(f, "{}{} {}:{}", indent.lock(), module.kind().keyword(), module.name(), Info::of(module))?;
+
+        let mut indent = indent.sub();
+        let ports = if self.sort_ports {
+            module.ports_sorted()
+        } else {
+            module.ports().collect()
+        };
+        ports.into_iter().try_for_each(|p| DisplayIndented::fmt(p, &mut indent, f))?;
+
+        match module.kind() {
+            ModuleKind::Regular{stmts}          => self.fmt_stmts(stmts, &mut indent, f),
+            ModuleKind::External{defname, params, ..} => {
+                defname.as_ref().map(|n| writeln!(f, "{}defname = {}", indent.lock(), n)).transpose()?;
+                let mut params: Vec<_> = params.iter().collect();
+                if self.sort_params {
+                    params.sort_by_key(|(k, _)| *k)
+                }
+                params.into_iter().try_for_each(|(k, v)| writeln!(f, "{}parameter {} = {}", indent.lock(), k, v))
+            },
+        }
+    }
+
+    /// Format a list of statements with the given indentation
+    fn fmt_stmts(&self, stmts: &[Statement], indent: &mut Indentation, f: &mut impl Write) -> fmt::Result {
+        // For alignment, we need to know the width of the widest sink in each
+        // run of consecutive connections.
+        let sinks: Vec<_> = stmts.iter().map(|s| match s.kind() {
+            Kind::Connection{to, ..}        => Some(to.to_string().len()),
+            Kind::PartialConnection{to, ..} => Some(to.to_string().len()),
+            _ => None,
+        }).collect();
+        let mut widths = vec![0; stmts.len()];
+        if self.align_connections {
+            sinks.split(Option::is_none).fold(0, |start, run| {
+                let width = run.iter().flatten().max().cloned().unwrap_or(0);
+                widths[start..start + run.len()].iter_mut().for_each(|w| *w = width);
+                start + run.len() + 1
+            });
+        }
+
+        stmts.iter().zip(widths).try_for_each(|(s, w)| self.fmt_stmt(s, w, indent, f))
+    }
+
+    /// Format a single statement with the given indentation
+    ///
+    /// If the statement is a connection, its sink will be padded to `width`.
+    fn fmt_stmt(
+        &self,
+        stmt: &Statement,
+        width: usize,
+        indent: &mut Indentation,
+        f: &mut impl Write,
+    ) -> fmt::Result {
+        let base = indent.lock();
+        let info = Info::of(stmt);
+        match stmt.kind() {
+            Kind::Connection{from, to} if self.connect_keywords() => {
+                let prefix = format!("{}connect {}, ", indent.lock(), to);
+                self.fmt_line(&prefix, &from.to_string(), "", info, base, f)
+            },
+            Kind::Connection{from, to}          => {
+                let prefix = format!("{}{:width$} <= ", indent.lock(), to.to_string(), width = width);
+                self.fmt_line(&prefix, &from.to_string(), "", info, base, f)
+            },
+            Kind::PartialConnection{from, to}   => {
+                let prefix = format!("{}{:width$} <- ", indent.lock(), to.to_string(), width = width);
+                self.fmt_line(&prefix, &from.to_string(), "", info, base, f)
+            },
+            Kind::Declaration(entity)           => if let Entity::Node{name, value} = entity.as_ref() {
+                let prefix = format!("{}node {} = ", indent.lock(), name);
+                self.fmt_line(&prefix, &value.to_string(), "", info, base, f)
+            } else {
+                stmt.fmt_versioned(self.version.into(), indent, f)
+            },
+            Kind::SimpleMemDecl(mem) if self.explicit_ruw => {
+                let kind = mem.kind().read_under_write().map_or(mem.kind(), |r| SimpleKind::Sequential(Some(r)));
+                writeln!(f, "{}{}{}", indent.lock(), mem.as_ref().clone().with_kind(kind), info)
+            },
+            Kind::Conditional{..}               => {
+                let lead = indent.lock().to_string();
+                self.fmt_cond(stmt, &lead, indent, f)
+            },
+            _ => stmt.fmt_versioned(self.version.into(), indent, f),
+        }
+    }
+
+    /// Format a conditional statement
+    ///
+    /// The `lead` is emitted in front of the `when` keyword.
+    fn fmt_cond(
+        &self,
+        stmt: &Statement,
+        lead: &str,
+        indent: &mut Indentation,
+        f: &mut impl Write,
+    ) -> fmt::Result {
+        let (cond, when, r#else) = if let Kind::Conditional{cond, when, r#else} = stmt.kind() {
+            (cond, when, r#else)
+        } else {
+            return Err(Default::default())
+        };
+
+        let base = indent.lock();
+        let head = format!("{}when {} : ", lead, cond);
+        match self.inline(when, head.len()).filter(|_| stmt.info().is_none()) {
+            Some(line)  => writeln!(f, "{}{}", head, line)?,
+            None        => {
+                self.fmt_line(&format!("{}when ", lead), &cond.to_string(), ":", Info::of(stmt), base, f)?;
+                self.fmt_branch(when, &mut indent.sub(), f)?;
+            },
+        }
+
+        if let [stmt] = r#else.as_ref() {
+            if let Kind::Conditional{..} = stmt.kind() {
+                let lead = format!("{}else ", indent.lock());
+                return self.fmt_cond(stmt, &lead, indent, f)
+            }
+        }
+
+        if r#else.is_empty() {
+            return Ok(())
+        }
+
+        let head = format!("{}else : ", indent.lock());
+        if let Some(line) = self.inline(r#else, head.len()) {
+            writeln!(f, "{}{}", head, line)
+        } else {
+            writeln!(f, "{}else:", indent.lock())?;
+            self.fmt_branch(r#else, &mut indent.sub(), f)
+        }
+    }
+
+    /// Format a branch for putting it inline, if applicable
+    ///
+    /// If inline branches are enabled and the branch consists of a single
+    /// statement other than a conditional statement which fits on a single
+    /// line following a prefix of the given width, this function returns that
+    /// line, without any line ending.
+    fn inline(&self, stmts: &[Statement], prefix: usize) -> Option<String> {
+        let stmt = match stmts {
+            [stmt] if self.inline_branches && !matches!(stmt.kind(), Kind::Conditional{..}) => stmt,
+            _ => return None,
+        };
+
+        let mut line = String::new();
+        self.fmt_stmt(stmt, 0, &mut self.root(), &mut line).ok()?;
+        line.strip_suffix('\n')
+            .filter(|l| !l.contains('\n') && prefix + l.len() <= self.max_width)
+            .map(ToString::to_string)
+    }
+
+    /// Format the statements of a branch of a conditional statement
+    ///
+    /// Branches may not be empty. Hence, an empty list of statements will be
+    /// formatted as `skip`.
+    fn fmt_branch(&self, stmts: &[Statement], indent: &mut Indentation, f: &mut impl Write) -> fmt::Result {
+        if stmts.is_empty() {
+            writeln!(f, "{}skip", indent.lock())
+        } else {
+            self.fmt_stmts(stmts, indent, f)
+        }
+    }
+
+    /// Format a line containing an expression
+    ///
+    /// The `expr` will be broken up if the line would exceed the maximum
+    /// width. Continuation lines will be indented relative to `base`.
+    fn fmt_line(
+        &self,
+        prefix: &str,
+        expr: &str,
+        suffix: &str,
+        info: Info,
+        base: LockedIndentation,
+        f: &mut impl Write,
+    ) -> fmt::Result {
+        f.write_str(prefix)?;
+        wrap(expr, prefix.len() + suffix.len(), base, self.max_width, usize::MAX, f)?;
+        writeln!(f, "{}{}", suffix, info)
+    }
+
+    /// Create a root [Indentation] with the configured indentation
+    fn root(&self) -> Indentation {
+        Indentation::root().with_config(self.indentation)
+    }
+
+    /// Check whether connections are emitted using the `connect` keyword
+    fn connect_keywords(&self) -> bool {
+        self.version.map(|v| v >= Version::CONNECT_KEYWORDS).unwrap_or(false)
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            sort_ports: false,
+            sort_params: true,
+            align_connections: false,
+            info: Default::default(),
+            explicit_ruw: false,
+            inline_branches: false,
+            indentation: Default::default(),
+            version: Default::default(),
+        }
+    }
+}
+
+
+/// An item formatted according to some [Style]
+///
+/// Instances of this type are created via [Style::circuit] and
+/// [Style::module]. They implement [Display](fmt::Display).
+#[derive(Copy, Clone, Debug)]
+pub struct Formatted<'a, T> {
+    item: &'a T,
+    style: &'a Style,
+}
+
+impl fmt::Display for Formatted<'_, Circuit> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mapped;
+        let circuit = if self.style.info == InfoMode::Keep {
+            self.item
+        } else {
+            mapped = self.item.with_info_mapped(|i| self.style.info.apply(i));
+            &mapped
+        };
+        if let Some(version) = circuit.version() {
+            writeln!(f, "FIRRTL version {}", version)?;
+        }
+        writeln!(f, "circuit {}:{}", circuit.top_module().name(), Info::of(circuit))?;
+        let style = self.style.clone().with_version(circuit.version());
+        let mut indent = style.root().sub();
+        circuit.defined_modules().iter().try_for_each(|m| style.fmt_module(m, &mut indent, f))
+    }
+}
+
+impl fmt::Display for Formatted<'_, Module> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.style.info == InfoMode::Keep {
+            self.style.fmt_module(self.item, &mut self.style.root(), f)
+        } else {
+            let module = self.item.with_info_mapped(&mut |i| self.style.info.apply(i));
+            self.style.fmt_module(&module, &mut self.style.root(), f)
+        }
+    }
+}
+
+
+/// Write the textual representation of an expression, breaking it if necessary
+///
+/// If the `text` doesn't fit in the remaining space after `column` or if its
+/// argument lists are nested deeper than `max_depth`, the arguments of its
+/// outermost argument list will be put on separate lines, each indented one
+/// step deeper than `indent`. Arguments are broken up recursively.
+pub(crate) fn wrap(
+    text: &str,
+    column: usize,
+    indent: LockedIndentation,
+    max_width: usize,
+    max_depth: usize,
+    f: &mut impl Write,
+) -> fmt::Result {
+    let call = Some(text)
+        .filter(|t| column + t.len() > max_width || nesting_depth(t) > max_depth)
+        .and_then(split_call);
+    if let Some((head, args, tail)) = call {
+        let indent = indent.sub().lock();
+        f.write_str(head)?;
+        args.iter().enumerate().try_for_each(|(i, arg)| {
+            write!(f, "\n{}", indent)?;
+            // Account for the comma or closing parenthesis following the arg
+            wrap(arg, usize::from(indent) + 1, indent, max_width, max_depth, f)?;
+            if i + 1 < args.len() {
+                f.write_char(',')
+            } else {
+                Ok(())
+            }
+        })?;
+        // The tail may contain further argument lists, e.g. in sub-accesses
+        wrap(tail, indent.into(), indent, max_width, max_depth, f)
+    } else {
+        f.write_str(text)
+    }
+}
+
+
+/// Determine the maximum nesting depth of argument lists in an expression
+fn nesting_depth(text: &str) -> usize {
+    text.chars().fold((0usize, 0), |(depth, max), c| match c {
+        '(' => (depth + 1, max.max(depth + 1)),
+        ')' => (depth.saturating_sub(1), max),
+        _   => (depth, max),
+    }).1
+}
+
+
+/// Split the textual representation of an expression at its first argument list
+///
+/// This function returns the text up to and including the opening
+/// parenthesis, the arguments and the text starting at the closing
+/// parenthesis. Argument lists consisting of a single, atomic argument, e.g.
+/// those of literals, are not considered.
+fn split_call(text: &str) -> Option<(&str, Vec<&str>, &str)> {
+    let open = text.find('(')?;
+
+    let mut args = Vec::new();
+    let mut start = open + 1;
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut close = None;
+    for (i, c) in text.char_indices().skip_while(|(i, _)| *i <= open) {
+        match c {
+            '"'                         => quoted = !quoted,
+            _ if quoted                 => (),
+            '(' | '[' | '{'             => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ')'                         => {
+                close = Some(i);
+                break
+            },
+            ',' if depth == 0           => {
+                args.push(text[start..i].trim());
+                start = i + 1;
+            },
+            _ => (),
+        }
+    }
+    let close = close?;
+    args.push(text[start..close].trim());
+
+    if args.len() == 1 && !args[0].contains('(') {
+        // The argument list may be followed by further lists, e.g. in a sub-
+        // access, which we may split instead.
+        let (head, args, tail) = split_call(&text[close..])?;
+        return Some((&text[..close + head.len()], args, tail))
+    }
+    Some((&text[..=open], args, &text[close..]))
+}
@@ -0,0 +1,135 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to formatting
+
+use quickcheck::{Gen, TestResult, Testable};
+
+use crate::circuit::{Circuit, parse};
+use crate::error::ParseError;
+use crate::named::Named;
+use crate::tests::Equivalence;
+
+use super::{IndentationConfig, Style};
+use crate::info::{InfoMode, WithInfo};
+
+
+#[quickcheck]
+fn parse_formatted(original: Circuit, width: u8, align: bool) -> Result<TestResult, ParseError> {
+    let style = Style::default().with_max_width(width.into()).with_aligned_connections(align);
+    parse(&style.circuit(&original).to_string())
+        .map(|parsed| Equivalence::of(original, parsed).result(&mut Gen::new(0)))
+}
+
+
+#[quickcheck]
+fn parse_inline_branches(original: Circuit, width: u8) -> Result<TestResult, ParseError> {
+    let style = Style::default().with_max_width(width.into()).with_inline_branches(true);
+    parse(&style.circuit(&original).to_string())
+        .map(|parsed| Equivalence::of(original, parsed).result(&mut Gen::new(0)))
+}
+
+
+#[quickcheck]
+fn parse_indented(original: Circuit, width: u8, indentation: IndentationConfig) -> Result<TestResult, ParseError> {
+    let style = Style::default().with_max_width(width.into()).with_indentation(indentation);
+    parse(&style.circuit(&original).to_string())
+        .map(|parsed| Equivalence::of(original, parsed).result(&mut Gen::new(0)))
+}
+
+
+#[test]
+fn indentation_width() {
+    use std::num::NonZeroUsize;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    output b: UInt<1>\n",
+        "    when a:\n",
+        "      b <= a\n",
+    );
+    let original = parse(source).expect("Could not parse circuit");
+
+    let four = NonZeroUsize::new(4).unwrap();
+    let spaces = Style::default().with_indentation(IndentationConfig::default().with_width(four));
+    let spaced = spaces.circuit(&original).to_string();
+    assert!(spaced.contains("\n        when a:\n            b <= a\n"), "{}", spaced);
+    assert_eq!(parse(&spaced).expect("Could not parse circuit"), original);
+
+    let one = NonZeroUsize::new(1).unwrap();
+    let tabs = Style::default().with_indentation(IndentationConfig::default().with_width(one).with_tabs(true));
+    let tabbed = tabs.circuit(&original).to_string();
+    assert!(tabbed.contains("\n\t\twhen a:\n\t\t\tb <= a\n"), "{}", tabbed);
+    assert_eq!(parse(&tabbed).expect("Could not parse circuit"), original);
+}
+
+
+#[quickcheck]
+fn inline_branches(inline: bool, width: u8) -> Result<bool, ParseError> {
+    let original = parse(concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    input b: UInt<1>\n",
+        "    output c: UInt<1>\n",
+        "    c <= a\n",
+        "    when a : c <= b @[Top.scala 1:2]\n",
+        "    else when b : c is invalid\n",
+        "    else : skip\n",
+        "    when b :\n",
+        "      c <= a\n",
+        "    else : c <= b\n",
+    ))?;
+
+    let style = Style::default().with_max_width(width.into()).with_inline_branches(inline);
+    let formatted = style.circuit(&original).to_string();
+    let lines = formatted.lines().count();
+    let expected = match (inline, width >= 36) {
+        (false, _)      => lines == 16,
+        (true, true)    => lines == 11,
+        (true, false)   => (11..=16).contains(&lines),
+    };
+    Ok(parse(&formatted)? == original && expected)
+}
+
+
+#[quickcheck]
+fn sorted_ports(original: Circuit) -> Result<TestResult, ParseError> {
+    let style = Style::default().with_sorted_ports(true);
+    let parsed = parse(&style.circuit(&original).to_string())?;
+    let res = parsed.modules().all(|m| {
+        let names: Vec<_> = m.ports().map(|p| p.name()).collect();
+        names.windows(2).all(|w| w[0] <= w[1])
+    });
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn stripped_info(original: Circuit) -> Result<TestResult, ParseError> {
+    use transiter::AutoTransIter;
+
+    let style = Style::default().with_info(InfoMode::Strip);
+    let parsed = parse(&style.circuit(&original).to_string())?;
+    let res = parsed.info().is_none() && parsed.modules().all(|m| m.info().is_none() &&
+        m.ports().all(|p| p.info().is_none()) &&
+        m.statements().iter().flat_map(AutoTransIter::trans_iter).all(|s| s.info().is_none()));
+    Ok(TestResult::from_bool(res))
+}
+
+
+#[quickcheck]
+fn explicit_ruw(original: Circuit) -> Result<TestResult, ParseError> {
+    use transiter::AutoTransIter;
+
+    use crate::memory::simple::Kind as SimpleKind;
+    use crate::stmt::Kind;
+
+    let style = Style::default().with_explicit_ruw(true);
+    let parsed = parse(&style.circuit(&original).to_string())?;
+    let res = parsed.modules().all(|m| m.statements().iter().flat_map(AutoTransIter::trans_iter).all(|s|
+        !matches!(s.kind(), Kind::SimpleMemDecl(mem) if mem.kind() == SimpleKind::Sequential(None))
+    ));
+    Ok(TestResult::from_bool(res))
+}
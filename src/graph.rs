@@ -0,0 +1,166 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Module dependency graph
+//!
+//! This module provides [ModuleGraph], the graph of modules of a circuit and
+//! the instantiations between them. Contrary to the elaborated instance tree
+//! provided by the [hierarchy](crate::hierarchy) module, every module appears
+//! only once, regardless of the number of its instances. A graph may be
+//! rendered in the Graphviz DOT format via [ModuleGraph::dot].
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::graph::ModuleGraph;
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Top:\n",
+//!     "  module Sub:\n",
+//!     "    input a: UInt<1>\n",
+//!     "  module Top:\n",
+//!     "    inst x of Sub\n",
+//!     "    inst y of Sub\n",
+//!     "    x.a is invalid\n",
+//!     "    y.a is invalid\n",
+//! )).unwrap();
+//!
+//! let graph = ModuleGraph::of(&circuit);
+//! assert_eq!(graph.edges()[0].instances(), 2);
+//! assert!(graph.dot().to_string().contains("\"Top\" -> \"Sub\""));
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::display::JsonString;
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::Statement;
+
+
+/// Graph of the modules of a circuit and their instantiations
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleGraph {
+    top: Arc<str>,
+    modules: Vec<Arc<Module>>,
+    edges: Vec<Edge>,
+}
+
+impl ModuleGraph {
+    /// Create the graph of the modules instantiated in the given circuit
+    ///
+    /// Modules are included in their canonical order, i.e. every module
+    /// follows the modules it instantiates. Modules not instantiated by the
+    /// top module are not included.
+    pub fn of(circuit: &Circuit) -> Self {
+        let modules: Vec<_> = circuit.modules_sorted().into_iter().cloned().collect();
+        let edges = modules.iter().flat_map(|m| {
+            let mut children: Vec<Edge> = Vec::new();
+            m.statements().iter().flat_map(Statement::instantiations).for_each(|i| {
+                let child = i.module().name();
+                match children.iter_mut().find(|e| &e.child == child) {
+                    Some(edge)  => edge.instances += 1,
+                    None        => children.push(Edge {
+                        parent: m.name().clone(),
+                        child: child.clone(),
+                        instances: 1,
+                    }),
+                }
+            });
+            children
+        }).collect();
+
+        Self {top: circuit.top_module().name().clone(), modules, edges}
+    }
+
+    /// Retrieve the name of the top module
+    pub fn top(&self) -> &Arc<str> {
+        &self.top
+    }
+
+    /// Retrieve the modules, i.e. the nodes of the graph
+    pub fn modules(&self) -> &[Arc<Module>] {
+        self.modules.as_ref()
+    }
+
+    /// Retrieve the instantiations, i.e. the edges of the graph
+    ///
+    /// Every pair of parent and child module is represented by exactly one
+    /// edge. Edges are ordered by parent module.
+    pub fn edges(&self) -> &[Edge] {
+        self.edges.as_ref()
+    }
+
+    /// Retrieve the modules instantiated by the module with the given name
+    pub fn children<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a Edge> + 'a {
+        self.edges.iter().filter(move |e| e.parent.as_ref() == module)
+    }
+
+    /// Retrieve the modules instantiating the module with the given name
+    pub fn parents<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a Edge> + 'a {
+        self.edges.iter().filter(move |e| e.child.as_ref() == module)
+    }
+
+    /// Retrieve a value rendering the graph in the Graphviz DOT format
+    ///
+    /// External modules are rendered as boxes, the top module is highlighted.
+    /// Edges are labeled with the number of instances if it exceeds one.
+    pub fn dot(&self) -> Dot<'_> {
+        Dot(self)
+    }
+}
+
+
+/// Instantiation of one module in another
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    parent: Arc<str>,
+    child: Arc<str>,
+    instances: usize,
+}
+
+impl Edge {
+    /// Retrieve the name of the instantiating module
+    pub fn parent(&self) -> &Arc<str> {
+        &self.parent
+    }
+
+    /// Retrieve the name of the instantiated module
+    pub fn child(&self) -> &Arc<str> {
+        &self.child
+    }
+
+    /// Retrieve the number of instances of the child in the parent module
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+}
+
+
+/// Graphviz DOT rendering of a [ModuleGraph]
+#[derive(Copy, Clone, Debug)]
+pub struct Dot<'a>(&'a ModuleGraph);
+
+impl fmt::Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let graph = self.0;
+        writeln!(f, "digraph {} {{", JsonString(graph.top.as_ref()))?;
+        graph.modules.iter().try_for_each(|m| {
+            let shape = if m.defname().is_some() { "box" } else { "ellipse" };
+            let style = if m.name() == &graph.top { ", style=bold" } else { "" };
+            writeln!(f, "  {} [shape={}{}];", JsonString(m.name().as_ref()), shape, style)
+        })?;
+        graph.edges.iter().try_for_each(|e| {
+            write!(f, "  {} -> {}", JsonString(e.parent.as_ref()), JsonString(e.child.as_ref()))?;
+            if e.instances > 1 {
+                write!(f, " [label=\"{}\"]", e.instances)?;
+            }
+            writeln!(f, ";")
+        })?;
+        writeln!(f, "}}")
+    }
+}
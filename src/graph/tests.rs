@@ -0,0 +1,41 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to module graphs
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+
+use super::*;
+
+
+#[quickcheck]
+fn graph_edges(circuit: Circuit) -> TestResult {
+    let graph = ModuleGraph::of(&circuit);
+    let instances = circuit
+        .modules()
+        .flat_map(|m| m.statements())
+        .flat_map(Statement::instantiations)
+        .count();
+    let res = graph.modules().len() == circuit.modules().count() &&
+        graph.edges().iter().map(Edge::instances).sum::<usize>() == instances &&
+        graph.modules().iter().all(|m| graph.children(m.name()).count() == {
+            let mut children: Vec<_> = m.referenced_modules().map(|c| c.name()).collect();
+            children.sort();
+            children.dedup();
+            children.len()
+        });
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn graph_dot(circuit: Circuit) -> TestResult {
+    let graph = ModuleGraph::of(&circuit);
+    let dot = graph.dot().to_string();
+    let lines = dot.lines().count();
+    TestResult::from_bool(
+        dot.starts_with("digraph ") && dot.ends_with("}\n") &&
+        lines == graph.modules().len() + graph.edges().len() + 2
+    )
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Instance hierarchy of a circuit
+//!
+//! A [Circuit](crate::circuit::Circuit) defines its hardware via a top module
+//! which may instantiate other modules. While modules are shared between all
+//! of their instances, some applications need to distinguish between distinct
+//! instances of the same module. This module provides an elaborated
+//! representation of the instance hierarchy in the form of a tree of
+//! [InstanceNode]s, which is created via
+//! [Circuit::elaborate](crate::circuit::Circuit::elaborate).
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::module::{Kind, Module, ParamValue};
+use crate::named::Named;
+use crate::stmt::Statement;
+use crate::target::{InstancePath, Target};
+
+
+/// Node in an elaborated instance tree
+///
+/// Each node represents one distinct instance of a module, identified by its
+/// [InstancePath]. The root node represents the top module of a circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceNode {
+    path: InstancePath,
+    module: Arc<Module>,
+    parameters: HashMap<Arc<str>, ParamValue>,
+    children: Vec<InstanceNode>,
+}
+
+impl InstanceNode {
+    /// Elaborate the instance hierarchy with the given module as the root
+    pub fn elaborate(module: Arc<Module>) -> Self {
+        Self::elaborate_at(InstancePath::new(module.name().clone()), module)
+    }
+
+    /// Elaborate the instance hierarchy for the module at the given path
+    fn elaborate_at(path: InstancePath, module: Arc<Module>) -> Self {
+        let children = module
+            .statements()
+            .iter()
+            .flat_map(Statement::instantiations)
+            .map(|i| {
+                let mut node = Self::elaborate_at(
+                    path.clone().with_instance(i.name().clone(), i.module().name().clone()),
+                    i.module().clone(),
+                );
+                node.parameters.extend(i.parameters().iter().map(|(k, v)| (k.clone(), v.clone())));
+                node
+            })
+            .collect();
+
+        let parameters = if let Kind::External{params, ..} = module.kind() {
+            params.clone()
+        } else {
+            Default::default()
+        };
+
+        Self {path, module, parameters, children}
+    }
+
+    /// Retrieve the path identifying this instance
+    pub fn path(&self) -> &InstancePath {
+        &self.path
+    }
+
+    /// Retrieve a [Target] referring to this instance
+    pub fn target(&self) -> Target {
+        Target::module(self.path.clone())
+    }
+
+    /// Retrieve the instantiated module
+    pub fn module(&self) -> &Arc<Module> {
+        &self.module
+    }
+
+    /// Retrieve the parameter bindings for this instance
+    ///
+    /// The bindings consist of the parameters defined by the module, if it is
+    /// an external module, overridden by the assignments of the instance.
+    pub fn parameters(&self) -> &HashMap<Arc<str>, ParamValue> {
+        &self.parameters
+    }
+
+    /// Retrieve the instances directly instantiated in this instance's module
+    pub fn children(&self) -> &[Self] {
+        self.children.as_ref()
+    }
+
+    /// Retrieve the name of this instance
+    ///
+    /// For the root of an instance tree, this function returns `None`.
+    pub fn instance_name(&self) -> Option<&Arc<str>> {
+        self.path.elements().last().map(|e| &e.instance)
+    }
+
+    /// Retrieve the depth of this node in the instance tree
+    ///
+    /// The depth of the root node is `0`.
+    pub fn depth(&self) -> usize {
+        self.path.elements().len()
+    }
+
+    /// Estimate the number of flip-flops in the subtree rooted at this node
+    ///
+    /// This function sums up the [Module::estimated_flops] of this node and all
+    /// its (transitive) children, i.e. modules instantiated multiple times are
+    /// accounted for once per instance.
+    pub fn estimated_flops(&self) -> u64 {
+        self.iter().map(|n| n.module.estimated_flops()).fold(0, u64::saturating_add)
+    }
+
+    /// Estimate the memory bits in the subtree rooted at this node
+    ///
+    /// This function sums up the [Module::memory_bits] of this node and all
+    /// its (transitive) children.
+    pub fn memory_bits(&self) -> u64 {
+        self.iter().map(|n| n.module.memory_bits()).fold(0, u64::saturating_add)
+    }
+
+    /// Iterate over this node and all its (transitive) children
+    ///
+    /// The nodes are yielded in depth-first pre-order, i.e. each node is
+    /// yielded before its children and the children of any node are yielded
+    /// in the order of their instantiation.
+    pub fn iter(&self) -> impl Iterator<Item = &Self> {
+        use transiter::AutoTransIter;
+
+        self.trans_iter().depth_first()
+    }
+
+    /// Find the node with the given path
+    ///
+    /// The path is interpreted relative to this node, i.e. its root must name
+    /// this node's module.
+    pub fn find(&self, path: &InstancePath) -> Option<&Self> {
+        if path.root() != self.module.name() {
+            return None
+        }
+        path.elements().iter().try_fold(self, |n, e| n
+            .children
+            .iter()
+            .find(|c| c.path.elements().last() == Some(e)))
+    }
+
+    /// Retrieve all instances of the module with the given name
+    pub fn instances_of<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a Self> + 'a {
+        self.iter().filter(move |n| n.module.name_ref() == module)
+    }
+
+    /// Retrieve all leaf instances
+    ///
+    /// Leaf instances are instances of modules which don't instantiate any
+    /// other module, e.g. external modules.
+    pub fn leaves(&self) -> impl Iterator<Item = &Self> {
+        self.iter().filter(|n| n.children.is_empty())
+    }
+}
+
+impl<'a> transiter::AutoTransIter<&'a InstanceNode> for &'a InstanceNode {
+    type RecIter = std::slice::Iter<'a, InstanceNode>;
+
+    fn recurse(item: &Self) -> Self::RecIter {
+        item.children.iter()
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to the instance hierarchy
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+use crate::module::Module;
+use crate::named::Named;
+use crate::target::Referenced;
+
+
+#[quickcheck]
+fn elaborate_instance_count(circuit: Circuit) -> TestResult {
+    fn count(module: &Module) -> usize {
+        module.referenced_modules().map(|m| count(m)).sum::<usize>() + 1
+    }
+
+    TestResult::from_bool(circuit.elaborate().iter().count() == count(circuit.top_module()))
+}
+
+
+#[quickcheck]
+fn elaborate_paths_resolve(circuit: Circuit) -> TestResult {
+    let tree = circuit.elaborate();
+    let res = tree.iter().all(|n| match circuit.resolve_target(&n.target()) {
+        Some(Referenced::Module(m)) => m.name() == n.module().name(),
+        _ => false,
+    });
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn elaborate_find(circuit: Circuit) -> TestResult {
+    let tree = circuit.elaborate();
+    let res = tree.iter().all(|n| tree.find(n.path()).map(|f| f.path() == n.path()).unwrap_or(false));
+    TestResult::from_bool(res)
+}
+
+
+#[test]
+fn elaborate_instance_parameters() {
+    use crate::circuit::ParseOptions;
+    use crate::module::ParamValue;
+
+    let source = concat!(
+        "circuit Top:\n",
+        "  extmodule Sub:\n",
+        "    input a: UInt<1>\n",
+        "    parameter WIDTH = 1\n",
+        "    parameter DEPTH = 2\n",
+        "  module Top:\n",
+        "    inst sub of Sub with: (WIDTH = 8)\n",
+    );
+    let circuit = ParseOptions::new().with_instance_parameters(true).parse(source).expect("Could not parse circuit");
+    let tree = circuit.elaborate();
+    let params = tree.children()[0].parameters();
+    assert_eq!(params.get("WIDTH"), Some(&ParamValue::Int(8)));
+    assert_eq!(params.get("DEPTH"), Some(&ParamValue::Int(2)));
+}
@@ -0,0 +1,216 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Incremental parsing
+//!
+//! Tools such as editors or language servers need to keep an AST up to date
+//! while the source is being edited. Reparsing an entire circuit after every
+//! edit may be too slow for big sources. This module provides [ParsedSource],
+//! which retains the source text along with the location of each module in it.
+//! When applying an [Edit], only the modules touched by the edit are reparsed.
+//! Modules preceding the edit are reused as is. Modules following the edit are
+//! reused if they don't instantiate any reparsed module, and re-linked against
+//! the reparsed modules otherwise.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::circuit::{Circuit, Version, parsers::header};
+use crate::error::{ParseError, convert_error};
+use crate::indentation::Indentation;
+use crate::info::WithInfo;
+use crate::module::{Module, parsers::Modules};
+use crate::named::Named;
+
+
+/// Modules along with their location in the source
+type Located = Vec<(Range<usize>, Arc<Module>)>;
+
+
+/// A text edit
+///
+/// An edit replaces the text within a byte `range` with some other `text`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl Edit {
+    /// Create a new edit
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {range, text: text.into()}
+    }
+
+    /// Apply the edit to the given source
+    ///
+    /// This function fails if the edit's range is not a valid range within
+    /// the source.
+    pub fn apply(&self, source: &str) -> Result<String, ParseError> {
+        let range = self.range.clone();
+        if range.start > range.end || source.get(range.clone()).is_none() {
+            return Err(format!("Invalid edit range {:?}", range).into())
+        }
+
+        let mut res = String::with_capacity(source.len() + self.text.len() - range.len());
+        res.push_str(&source[..range.start]);
+        res.push_str(&self.text);
+        res.push_str(&source[range.end..]);
+        Ok(res)
+    }
+}
+
+
+/// Source of a circuit along with its parsed modules
+#[derive(Clone, Debug)]
+pub struct ParsedSource {
+    text: String,
+    top: Arc<str>,
+    info: Option<String>,
+    version: Option<Version>,
+    header_end: usize,
+    indentation: Indentation,
+    modules: Located,
+}
+
+impl ParsedSource {
+    /// Parse the given source
+    pub fn parse(text: impl Into<String>) -> Result<Self, ParseError> {
+        let text = text.into();
+        let (rest, (top, info, version)) = header(&text).map_err(|e| convert_error(&text, e))?;
+        let top = top.into();
+        let header_end = text.len() - rest.len();
+
+        let (modules, indentation) = parse_modules(
+            &text,
+            header_end..text.len(),
+            Indentation::root().sub(),
+            version,
+            Default::default(),
+        )?;
+
+        let res = Self {text, top, info, version, header_end, indentation, modules};
+        res.circuit()?;
+        Ok(res)
+    }
+
+    /// Retrieve the source text
+    pub fn text(&self) -> &str {
+        self.text.as_ref()
+    }
+
+    /// Retrieve all modules in the order they are defined in
+    pub fn modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        self.modules.iter().map(|(_, m)| m)
+    }
+
+    /// Retrieve the location of the module with the given name
+    pub fn module_range(&self, name: impl AsRef<str>) -> Option<Range<usize>> {
+        self.modules.iter().find(|(_, m)| m.name_ref() == name.as_ref()).map(|(r, _)| r.clone())
+    }
+
+    /// Retrieve the circuit
+    pub fn circuit(&self) -> Result<Circuit, ParseError> {
+        self.modules()
+            .find(|m| m.name() == &self.top)
+            .map(|m| Circuit::new(m.clone())
+                .with_modules(self.modules().cloned())
+                .with_info(self.info.clone())
+                .with_version(self.version))
+            .ok_or_else(|| "top module not found".to_owned().into())
+    }
+
+    /// Apply an edit to the source
+    ///
+    /// This function applies the edit to the source text and reparses the
+    /// modules affected by the edit. If parsing fails, the source remains
+    /// unaltered and the error is returned.
+    pub fn apply(&mut self, edit: &Edit) -> Result<(), ParseError> {
+        let text = edit.apply(&self.text)?;
+        let range = edit.range.clone();
+        if range.start < self.header_end {
+            *self = Self::parse(text)?;
+            return Ok(())
+        }
+
+        // Modules touching the edit (inclusive) need to be reparsed
+        let first = self.modules.iter().position(|(r, _)| r.end >= range.start).unwrap_or(self.modules.len());
+        let last = self.modules.iter().rposition(|(r, _)| r.start <= range.end);
+        let (old_region, tail) = match last {
+            Some(last) if last >= first => (
+                self.modules[first].0.start.min(range.start)..self.modules[last].0.end.max(range.end),
+                last + 1,
+            ),
+            _ => (range.clone(), first),
+        };
+
+        let shift = |i: usize| (i + edit.text.len()) - range.len();
+        let region = old_region.start..shift(old_region.end);
+
+        let known = self.modules[..first].iter().map(|(_, m)| (m.name().clone(), m.clone())).collect();
+        let (reparsed, _) = parse_modules(&text, region, self.indentation.clone(), self.version, known)?;
+
+        // If the set of module names changed, we may need to resolve instances
+        // differently than before.
+        let old_names: HashSet<_> = self.modules[first..tail].iter().map(|(_, m)| m.name()).collect();
+        let new_names: HashSet<_> = reparsed.iter().map(|(_, m)| m.name()).collect();
+        if old_names != new_names {
+            *self = Self::parse(text)?;
+            return Ok(())
+        }
+
+        let mut replaced: HashMap<_, _> = reparsed.iter().map(|(_, m)| (m.name().clone(), m.clone())).collect();
+        let relinked: Vec<_> = self.modules[tail..].iter().map(|(r, m)| {
+            let module = if m.referenced_modules().any(|i| replaced.contains_key(i.name())) {
+                let module = Arc::new(m.with_substituted_instances(|i| replaced.get(i.name()).cloned()));
+                replaced.insert(module.name().clone(), module.clone());
+                module
+            } else {
+                m.clone()
+            };
+            (shift(r.start)..shift(r.end), module)
+        }).collect();
+
+        let mut modules = self.modules[..first].to_vec();
+        modules.extend(reparsed);
+        modules.extend(relinked);
+
+        let res = Self {text, modules, ..self.clone()};
+        res.circuit()?;
+        *self = res;
+        Ok(())
+    }
+}
+
+
+/// Parse the modules within the given range of the source
+///
+/// The modules `known` will be available for instantiation. This function
+/// returns the modules parsed along with their locations.
+fn parse_modules(
+    text: &str,
+    range: Range<usize>,
+    indentation: Indentation,
+    version: Option<Version>,
+    known: HashMap<Arc<str>, Arc<Module>>,
+) -> Result<(Located, Indentation), ParseError> {
+    let end = range.end;
+    let mut modules = Modules::new_with_origin(&text[range], text)
+        .with_indentation(indentation)
+        .with_version(version);
+    known.into_iter().for_each(|(_, m)| modules.add_module(m));
+
+    let mut res = Vec::new();
+    loop {
+        let start = end - modules.remaining().len();
+        if let Some(module) = modules.next() {
+            res.push((start..end - modules.remaining().len(), module?));
+        } else {
+            break
+        }
+    }
+    Ok((res, modules.indentation().clone()))
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to incremental parsing
+
+use std::sync::Arc;
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+
+use super::{Edit, ParsedSource};
+
+
+#[quickcheck]
+fn reparse_module(original: Circuit, index: usize) -> TestResult {
+    let mut source = match ParsedSource::parse(original.to_string()) {
+        Ok(s) => s,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+    let modules: Vec<_> = source.modules().cloned().collect();
+    let index = index % modules.len();
+    // We only edit the interior of the module, since edits touching a module
+    // boundary affect the adjacent module, too.
+    let range = source.modules[index].0.clone();
+    let range = range.start + 1..range.end - 1;
+    let text = source.text()[range.clone()].to_owned();
+
+    if let Err(e) = source.apply(&Edit::new(range, text)) {
+        return TestResult::error(e.to_string())
+    }
+
+    let reused = modules[..index].iter().zip(source.modules()).all(|(a, b)| Arc::ptr_eq(a, b));
+    let res = source.circuit().map(|c| c == original).unwrap_or(false);
+    TestResult::from_bool(reused && res)
+}
+
+
+#[quickcheck]
+fn reparse_matches_full_parse(original: Circuit, pos: usize, text: crate::tests::ASCII) -> TestResult {
+    let mut source = match ParsedSource::parse(original.to_string()) {
+        Ok(s) => s,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+    let pos = pos % (source.text().len() + 1);
+    let edit = Edit::new(pos..pos, text.as_ref());
+
+    let full = edit.apply(source.text()).and_then(ParsedSource::parse).and_then(|s| s.circuit());
+    let incremental = source.apply(&edit).and_then(|_| source.circuit());
+    match (full, incremental) {
+        (Ok(a), Ok(b))  => TestResult::from_bool(a == b),
+        (Err(_), Err(_)) => TestResult::passed(),
+        (a, b)          => TestResult::error(format!("Mismatch: {:?} vs {:?}", a, b)),
+    }
+}
@@ -5,9 +5,10 @@
 use std::fmt;
 use std::num::NonZeroUsize;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
+use crate::error::IndentationMismatch;
 use crate::parsers;
 
 /// Print with indentation
@@ -28,13 +29,17 @@ impl<T> DisplayIndented for T
 /// Indentation
 ///
 /// Instances of this type represent either a lower bound or an exact length of
-/// a sequences of space characters. Usually, a new `Indentation` will represent
-/// a lower bound. After an instance has been locked, it will always refer to
-/// the same exact length.
+/// a sequences of indentation characters. Usually, a new `Indentation` will
+/// represent a lower bound. After an instance has been locked, it will always
+/// refer to the same exact length.
+///
+/// In addition, an `Indentation` carries an [IndentationConfig] governing the
+/// width of each indentation step and the characters emitted. The
+/// configuration is passed on to nested levels.
 #[derive(Clone, Debug, PartialEq)]
-pub enum Indentation {
-    MoreThan(usize),
-    Exact(usize),
+pub struct Indentation {
+    level: Level,
+    config: IndentationConfig,
 }
 
 impl Indentation {
@@ -48,14 +53,14 @@ impl Indentation {
     /// Lock the indentation to a concrete value
     ///
     /// If the `Indentation` represents only a lower bound, this function will
-    /// set an exact value which will be some fixed value above the parent
-    /// indentation level. If the `Indentation` is already locked, this function
-    /// doesn't have any effect.
+    /// set an exact value which will be one step, as configured, above the
+    /// parent indentation level. If the `Indentation` is already locked, this
+    /// function doesn't have any effect.
     ///
     /// The function returns a `LockedIndentation` reflecting the excact
     /// indentation length.
     pub fn lock(&mut self) -> LockedIndentation {
-        self.lock_with(NonZeroUsize::new(INDENTATION_STEP).expect("Invalid indentation width"))
+        self.lock_with(self.config.width())
     }
 
     /// Lock the indentation to a concrete value
@@ -68,14 +73,15 @@ impl Indentation {
     /// The function returns a `LockedIndentation` reflecting the excact
     /// indentation length.
     pub fn lock_with(&mut self, steps: NonZeroUsize) -> LockedIndentation {
-        match self {
-            Self::MoreThan(i) => {
-                let i = *i + steps.get();
-                *self = Self::Exact(i);
-                LockedIndentation(i)
+        let len = match self.level {
+            Level::MoreThan(i) => {
+                let i = i + steps.get();
+                self.level = Level::Exact(i);
+                i
             },
-            Self::Exact(i) => LockedIndentation(*i),
-        }
+            Level::Exact(i) => i,
+        };
+        LockedIndentation {len, config: self.config}
     }
 
     /// Create a new, locked "root"
@@ -83,7 +89,17 @@ impl Indentation {
     /// The `Indentation` returned will be locked to a length of `0`, i.e. no
     /// indentation at all.
     pub fn root() -> Self {
-        Self::Exact(0)
+        Self::exact(0)
+    }
+
+    /// Create a new `Indentation` locked to the given length
+    pub fn exact(len: usize) -> Self {
+        Self {level: Level::Exact(len), config: Default::default()}
+    }
+
+    /// Set the [IndentationConfig] for this and all nested levels
+    pub fn with_config(self, config: IndentationConfig) -> Self {
+        Self {config, ..self}
     }
 
     /// Create a parser for this Indentation
@@ -103,42 +119,115 @@ impl Default for Indentation {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Indentation {
     fn arbitrary(g: &mut Gen) -> Self {
         // Testing huge widths will (probably) not yield any benefits.
         let i = u8::arbitrary(g) as usize;
-        g.choose(&[Self::MoreThan(i), Self::Exact(i)]).unwrap().clone()
+        let level = *g.choose(&[Level::MoreThan(i), Level::Exact(i)]).unwrap();
+        Self {level, config: Arbitrary::arbitrary(g)}
     }
 
     fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
-        match self {
-            Self::MoreThan(i) => Box::new(i.shrink().map(Self::MoreThan)),
-            Self::Exact(i)    => Box::new(i.shrink().map(Self::Exact)),
+        let config = self.config;
+        match self.level {
+            Level::MoreThan(i) => Box::new(i.shrink().map(move |i| Self {level: Level::MoreThan(i), config})),
+            Level::Exact(i)    => Box::new(i.shrink().map(move |i| Self {level: Level::Exact(i), config})),
         }
     }
 }
 
 
+/// Length requirement represented by an [Indentation]
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Level {
+    MoreThan(usize),
+    Exact(usize),
+}
+
+
+/// Indentation configuration
+///
+/// A configuration determines the number of characters by which each nested
+/// level is indented and whether tabs or spaces are emitted. By default,
+/// every level is indented by two spaces.
+///
+/// Parsers accept both spaces and tabs regardless of the configuration, with
+/// every tab counting as a single character. Hence, a level indented with a
+/// single tab is considered shallower than one indented with two spaces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IndentationConfig {
+    width: NonZeroUsize,
+    tabs: bool,
+}
+
+impl IndentationConfig {
+    /// Set the number of characters by which each level is indented
+    pub fn with_width(self, width: NonZeroUsize) -> Self {
+        Self {width, ..self}
+    }
+
+    /// Set whether tabs are emitted rather than spaces
+    ///
+    /// For indenting each level with a single tab, set the
+    /// [width](Self::with_width) to `1`.
+    pub fn with_tabs(self, tabs: bool) -> Self {
+        Self {tabs, ..self}
+    }
+
+    /// Retrieve the number of characters by which each level is indented
+    pub fn width(&self) -> NonZeroUsize {
+        self.width
+    }
+
+    /// Retrieve whether tabs are emitted rather than spaces
+    pub fn tabs(&self) -> bool {
+        self.tabs
+    }
+
+    /// Retrieve the character emitted for indentation
+    fn char(&self) -> char {
+        if self.tabs { '\t' } else { ' ' }
+    }
+}
+
+impl Default for IndentationConfig {
+    fn default() -> Self {
+        Self {width: NonZeroUsize::new(INDENTATION_STEP).expect("Invalid indentation width"), tabs: false}
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for IndentationConfig {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let width = NonZeroUsize::new((u8::arbitrary(g) % 8 + 1).into()).expect("Invalid indentation width");
+        Self {width, tabs: Arbitrary::arbitrary(g)}
+    }
+}
+
+
 #[derive(Copy, Clone, Debug)]
-pub struct LockedIndentation(usize);
+pub struct LockedIndentation {
+    len: usize,
+    config: IndentationConfig,
+}
 
 impl LockedIndentation {
     /// Create a new indentation with a lower (i.e. nested) level
     pub fn sub(&self) -> Indentation {
-        Indentation::MoreThan(self.into())
+        Indentation {level: Level::MoreThan(self.len), config: self.config}
     }
 }
 
 impl From<&LockedIndentation> for usize {
     fn from(i: &LockedIndentation) -> Self {
-        i.0
+        i.len
     }
 }
 
 impl From<LockedIndentation> for usize {
     fn from(i: LockedIndentation) -> Self {
-        i.0
+        i.len
     }
 }
 
@@ -146,15 +235,16 @@ impl fmt::Display for LockedIndentation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use std::fmt::Write;
 
-        (0..self.0).try_for_each(|_| f.write_char(' '))
+        let c = self.config.char();
+        (0..self.len).try_for_each(|_| f.write_char(c))
     }
 }
 
 
 /// Indentation parser
 ///
-/// This parser consumes sequences of space characters. The sequence is only
-/// accepted if the length-requirement represented by the associated
+/// This parser consumes sequences of space and tab characters. The sequence is
+/// only accepted if the length-requirement represented by the associated
 /// `Indentation` is met.
 pub struct IndentationParser<'a> {
     inner: &'a mut Indentation
@@ -162,23 +252,33 @@ pub struct IndentationParser<'a> {
 
 impl<'i> nom::Parser<&'i str, (), parsers::Error<'i>> for IndentationParser<'_> {
     fn parse(&mut self, input: &'i str) -> parsers::IResult<'i, ()> {
-        use nom::error::ParseError;
-
-        let (rest, len) = nom::multi::many0_count(nom::character::complete::char(' '))(input)?;
-        match self.inner {
-            Indentation::MoreThan(l) if len > *l => *self.inner = Indentation::Exact(len),
-            Indentation::Exact(l) if len == *l => (),
-           _ => return Err(nom::Err::Error(parsers::Error::from_error_kind(input, nom::error::ErrorKind::Many1Count))),
+        let (rest, len) = nom::multi::many0_count(nom::character::complete::one_of(" \t"))(input)?;
+        match self.inner.level {
+            Level::MoreThan(l) if len > l => self.inner.level = Level::Exact(len),
+            Level::Exact(l) if len == l => (),
+            level => return Err(nom::Err::Error(error(input, level, len, self.inner.config))),
         };
         Ok((rest, ()))
     }
 }
 
 
+/// Create an error for a line starting at `input` not meeting `level`
+///
+/// The line is indented by `found` characters.
+fn error(input: &str, level: Level, found: usize, config: IndentationConfig) -> parsers::Error<'_> {
+    let mismatch = match level {
+        Level::MoreThan(l)  => IndentationMismatch::new(l + 1, true, found, config),
+        Level::Exact(l)     => IndentationMismatch::new(l, false, found, config),
+    };
+    parsers::Error::indentation(input, mismatch)
+}
+
+
 /// Owning indentation parser
 ///
 /// This parser wraps an `Indentation`, which it owns. It consumes sequences of
-/// space characters. A sequence is only accepted if the length-requirement
+/// space and tab characters. A sequence is only accepted if the length-requirement
 /// represented by the wrapped `Indentation` is met. The parser yields a copy
 /// of the updated `Indentation`.
 #[derive(Clone)]
@@ -228,5 +328,28 @@ mod tests {
         all_consuming(parsed.parser())(&s).finish().map_err(|e| e.to_string())?;
         Ok(Equivalence::of(original, parsed))
     }
+
+    #[test]
+    fn indentation_error_message() {
+        use nom::Parser;
+        use crate::error::{SyntaxErrorKind, convert_error};
+
+        let tabs = super::IndentationConfig::default().with_tabs(true);
+        let cases = [
+            (Indentation::exact(2), "a:\n   b\n", (2, 3), "at line 2, expected 2 spaces, found 3:\n   b\n   ^\n"),
+            (Indentation::exact(2).sub(), "a:\n  b\n", (3, 2), "at line 2, expected at least 3 spaces, found 2:\n  b\n  ^\n"),
+            (Indentation::exact(1).with_config(tabs), "a:\n\t\tb\n", (1, 2), "at line 2, expected 1 tab, found 2:\n\t\tb\n\t\t^\n"),
+        ];
+
+        for (indentation, input, counts, message) in cases.iter() {
+            let mut indentation = indentation.clone();
+            let err = indentation.parser().parse(&input[3..]).expect_err(input);
+            let err = convert_error(input, err);
+            let err = err.syntax().expect("Expected a syntax error");
+            assert_eq!((err.kind(), err.line(), err.column()), (SyntaxErrorKind::Indentation, 2, 1));
+            assert_eq!(err.indentation().map(|i| (i.expected(), i.found())), Some(*counts));
+            assert_eq!(err.message(), *message);
+        }
+    }
 }
 
@@ -36,6 +36,58 @@ pub trait WithInfo {
 }
 
 
+/// Treatment of info attributes on emission
+///
+/// Info attributes frequently contain absolute paths to generator sources,
+/// which prevents reproducible output across machines. This mode allows
+/// dropping info attributes altogether or rewriting paths embedded in them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum InfoMode {
+    /// Keep info attributes as they are
+    #[default]
+    Keep,
+    /// Drop all info attributes
+    Strip,
+    /// Rewrite paths via a prefix map
+    ///
+    /// Every whitespace separated word in an info attribute starting with one
+    /// of the prefixes is rewritten by replacing the prefix with the
+    /// associated replacement. The first matching prefix is applied.
+    Remap(Vec<(String, String)>),
+}
+
+impl InfoMode {
+    /// Apply this mode to the given info attribute
+    pub fn apply(&self, info: Option<&str>) -> Option<String> {
+        let info = info?;
+        match self {
+            Self::Keep          => Some(info.to_string()),
+            Self::Strip         => None,
+            Self::Remap(map)    => {
+                let mut res = String::with_capacity(info.len());
+                let mut rest = info;
+                while !rest.is_empty() {
+                    let start = rest.len() - rest.trim_start().len();
+                    res.push_str(&rest[..start]);
+                    rest = &rest[start..];
+
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    match map.iter().find(|(p, _)| word.starts_with(p.as_str())) {
+                        Some((p, r))    => {
+                            res.push_str(r);
+                            res.push_str(&word[p.len()..]);
+                        },
+                        None            => res.push_str(word),
+                    }
+                    rest = &rest[end..];
+                }
+                Some(res)
+            },
+        }
+    }
+}
+
 /// Helper for formatting an entities info attribute
 #[derive(Clone, Default)]
 pub(crate) struct Info<'a>(pub Option<&'a str>);
@@ -107,3 +159,18 @@ fn parse_info(original: crate::tests::ASCII) -> Result<crate::tests::Equivalence
     res
 }
 
+
+
+#[cfg(test)]
+#[quickcheck]
+fn remap_info(
+    prefix: crate::tests::Identifier,
+    replacement: crate::tests::Identifier,
+    suffix: crate::tests::Identifier,
+) -> bool {
+    let (prefix, replacement, suffix) = (prefix.as_ref(), replacement.as_ref(), suffix.as_ref());
+    let mode = InfoMode::Remap(vec![(prefix.to_string(), replacement.to_string())]);
+    let last = suffix.strip_prefix(prefix).map(|s| format!("{}{}", replacement, s)).unwrap_or(suffix.to_string());
+    let info = format!("{}{} 12:3 {}", prefix, suffix, suffix);
+    mode.apply(Some(&info)) == Some(format!("{}{} 12:3 {}", replacement, suffix, last))
+}
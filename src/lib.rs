@@ -13,21 +13,44 @@ mod display;
 mod indentation;
 mod parsers;
 
+pub mod analysis;
+pub mod annotations;
+pub mod cache;
 pub mod circuit;
+pub mod doc;
+pub mod emit;
 pub mod error;
 pub mod expr;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod format;
+pub mod graph;
+pub mod hierarchy;
+pub mod incremental;
 pub mod info;
+pub mod lint;
+pub mod lossless;
+pub mod lsp;
+pub mod manifest;
 pub mod memory;
 pub mod module;
 pub mod named;
+pub mod passes;
+pub mod provenance;
+pub mod report;
+pub mod stats;
 pub mod stmt;
+pub mod target;
 pub mod types;
 
-#[cfg(test)]
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(any(test, feature = "test-util"))]
 mod tests;
 
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
@@ -0,0 +1,303 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Lint framework
+//!
+//! This module provides the infrastructure for checking circuits for issues
+//! which are not errors per se but may hint at problems, e.g. naming
+//! convention violations. Checks are implemented as [LintRule]s, which are
+//! collected in a [Registry]. Rules may be enabled or disabled and their
+//! [Severity] may be overridden per registry. Running a registry on a circuit
+//! yields a list of [Finding]s, which may be collected in a [Report].
+//! Findings may carry a [Fix], which may be applied via [apply_fixes].
+//!
+//! Rules checking naming conventions are provided in the [naming] module,
+//! rules checking memory configurations in the [memory] module, rules
+//! checking the use of instances in the [instance] module, rules checking
+//! for undefined values in the [undefined] module, rules checking the
+//! contents of conditional blocks in the [conditional] module and rules
+//! checking subscripts of vectors in the [subscript] module.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::lint::{LintRule, Registry, Sink};
+//! use firrtl_ast::{Module, Named};
+//!
+//! struct NoEmptyModules;
+//!
+//! impl LintRule for NoEmptyModules {
+//!     fn id(&self) -> &'static str {
+//!         "no-empty-modules"
+//!     }
+//!
+//!     fn check_module(&self, module: &Module, sink: &mut Sink) {
+//!         if module.statements().is_empty() && module.defname().is_none() {
+//!             sink.report_module(format!("module {} is empty", module.name()))
+//!         }
+//!     }
+//! }
+//!
+//! let source = "circuit Top:\n  module Top:\n    skip\n";
+//! let circuit = firrtl_ast::circuit::parse(source).unwrap();
+//! let registry = Registry::new().with_rule(NoEmptyModules);
+//! assert!(registry.run(&circuit).is_empty());
+//! ```
+
+pub mod conditional;
+pub mod instance;
+pub mod memory;
+pub mod naming;
+pub mod subscript;
+pub mod undefined;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::Statement;
+use crate::target::{InstancePath, Target};
+
+pub use crate::report::{Finding, Fix, Report, Severity};
+
+
+/// Apply the fixes suggested in the given findings to a module
+///
+/// This function applies all fixes associated with findings targeting
+/// entities within the given module. Renames are applied via
+/// [Module::with_entities_renamed]. Findings targeting other modules are
+/// ignored.
+pub fn apply_fixes(module: &Module, findings: &[Finding]) -> Module {
+    let renames: HashMap<_, _> = findings
+        .iter()
+        .filter_map(|f| f.target.as_ref().zip(f.fix.as_ref()))
+        .filter(|(t, _)| t.path().elements().is_empty() && t.path().root() == module.name())
+        .filter_map(|(t, fix)| match (t.reference_name(), fix) {
+            (Some(name), Fix::Rename(to)) => Some((name.clone(), to.clone())),
+            _ => None,
+        })
+        .collect();
+    module.with_entities_renamed(|e| renames.get(e.name()).cloned())
+}
+
+
+/// A lint rule
+///
+/// A rule is identified by its [id](LintRule::id). Rules may check a circuit
+/// as a whole, each module individually and/or each statement. All checks do
+/// nothing by default, i.e. rules only need to implement those checks they
+/// care about. Issues are reported via a [Sink].
+pub trait LintRule: Send + Sync {
+    /// Retrieve the unique id of this rule
+    fn id(&self) -> &'static str;
+
+    /// Retrieve the default severity of findings reported by this rule
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Check the circuit as a whole
+    fn check_circuit(&self, _circuit: &Circuit, _sink: &mut Sink) {}
+
+    /// Check a single module
+    ///
+    /// This function is called once for every module in the circuit.
+    fn check_module(&self, _module: &Module, _sink: &mut Sink) {}
+
+    /// Check a single statement
+    ///
+    /// This function is called for every statement in a module, including
+    /// statements nested in conditional statements.
+    fn check_statement(&self, _module: &Module, _stmt: &Statement, _sink: &mut Sink) {}
+}
+
+
+/// Receiver of [Finding]s reported by a [LintRule]
+///
+/// The sink fills in the rule id and severity of findings as well as the
+/// circuit and module targets refer to.
+#[derive(Debug)]
+pub struct Sink {
+    rule: &'static str,
+    severity: Severity,
+    circuit: Arc<str>,
+    module: Arc<str>,
+    findings: Vec<Finding>,
+}
+
+impl Sink {
+    /// Report a finding concerning the given target
+    pub fn report(&mut self, target: Target, message: impl Into<String>) {
+        self.report_with_fix(target, message, None)
+    }
+
+    /// Report a finding concerning the given target along with a fix
+    pub fn report_with_fix(&mut self, target: Target, message: impl Into<String>, fix: impl Into<Option<Fix>>) {
+        self.findings.push(Finding::new(self.rule, self.severity, message)
+            .with_target(target.with_circuit(self.circuit.clone()))
+            .with_fix(fix))
+    }
+
+    /// Report a finding concerning the current module
+    ///
+    /// For circuit-wide checks, the current module is the top module.
+    pub fn report_module(&mut self, message: impl Into<String>) {
+        self.report(self.module_target(), message)
+    }
+
+    /// Report a finding concerning an entity in the current module
+    pub fn report_entity(&mut self, name: impl Into<Arc<str>>, message: impl Into<String>) {
+        self.report(self.entity_target(name), message)
+    }
+
+    /// Retrieve a target referring to the current module
+    pub fn module_target(&self) -> Target {
+        Target::module(InstancePath::new(self.module.clone()))
+    }
+
+    /// Retrieve a target referring to an entity in the current module
+    pub fn entity_target(&self, name: impl Into<Arc<str>>) -> Target {
+        Target::reference(InstancePath::new(self.module.clone()), name)
+    }
+}
+
+
+/// Collection of [LintRule]s
+///
+/// A registry holds a set of rules along with their configuration. Rules are
+/// enabled when registered but may be disabled subsequently.
+#[derive(Default)]
+pub struct Registry {
+    rules: Vec<Box<dyn LintRule>>,
+    disabled: HashSet<&'static str>,
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl Registry {
+    /// Create a new registry without any rules
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a new registry holding all built-in rules with a default config
+    ///
+    /// The registry holds the rules checking for undefined values, instances,
+    /// memories and subscripts which don't require any configuration. Naming
+    /// rules are not included.
+    pub fn builtin() -> Self {
+        Self::new()
+            .with_rule(conditional::DeclarationInWhen::new())
+            .with_rule(instance::UndrivenInstanceInput)
+            .with_rule(memory::MemoryLatency::new())
+            .with_rule(memory::PipelinedReadOld)
+            .with_rule(subscript::SubscriptBounds)
+            .with_rule(undefined::MaybeUndefined)
+    }
+
+    /// Add a rule to the registry
+    ///
+    /// If a rule with the same id is already registered, it is replaced.
+    pub fn register(&mut self, rule: impl LintRule + 'static) {
+        self.rules.retain(|r| r.id() != rule.id());
+        self.rules.push(Box::new(rule))
+    }
+
+    /// Add a rule to the registry
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.register(rule);
+        self
+    }
+
+    /// Retrieve the ids of all registered rules
+    pub fn rules(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.rules.iter().map(|r| r.id())
+    }
+
+    /// Enable the rule with the given id
+    pub fn enable(&mut self, id: &str) {
+        self.disabled.retain(|d| *d != id)
+    }
+
+    /// Disable the rule with the given id
+    ///
+    /// Disabling a rule which is not registered has no effect.
+    pub fn disable(&mut self, id: &str) {
+        let id = self.rules().find(|r| *r == id);
+        if let Some(id) = id {
+            self.disabled.insert(id);
+        }
+    }
+
+    /// Disable the rule with the given id
+    pub fn with_disabled(mut self, id: &str) -> Self {
+        self.disable(id);
+        self
+    }
+
+    /// Check whether the rule with the given id is registered and enabled
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.rules().any(|r| r == id) && !self.disabled.contains(id)
+    }
+
+    /// Override the severity of findings of the rule with the given id
+    ///
+    /// Overriding the severity of a rule which is not registered has no
+    /// effect.
+    pub fn set_severity(&mut self, id: &str, severity: Severity) {
+        let id = self.rules().find(|r| *r == id);
+        if let Some(id) = id {
+            self.severities.insert(id, severity);
+        }
+    }
+
+    /// Override the severity of findings of the rule with the given id
+    pub fn with_severity(mut self, id: &str, severity: Severity) -> Self {
+        self.set_severity(id, severity);
+        self
+    }
+
+    /// Run all enabled rules on the given circuit
+    ///
+    /// Findings are ordered by rule, in the order the rules were registered.
+    /// Modules are visited in their canonical order.
+    pub fn run(&self, circuit: &Circuit) -> Vec<Finding> {
+        use transiter::AutoTransIter;
+
+        let modules = circuit.modules_sorted();
+        self.rules.iter().filter(|r| !self.disabled.contains(r.id())).flat_map(|rule| {
+            let mut sink = Sink {
+                rule: rule.id(),
+                severity: self.severities.get(rule.id()).cloned().unwrap_or_else(|| rule.severity()),
+                circuit: circuit.top_module().name().clone(),
+                module: circuit.top_module().name().clone(),
+                findings: Default::default(),
+            };
+
+            rule.check_circuit(circuit, &mut sink);
+            modules.iter().for_each(|m| {
+                sink.module = m.name().clone();
+                rule.check_module(m, &mut sink);
+                m.statements()
+                    .iter()
+                    .flat_map(AutoTransIter::trans_iter)
+                    .for_each(|s| rule.check_statement(m, s, &mut sink));
+            });
+            sink.findings
+        }).collect()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("rules", &self.rules().collect::<Vec<_>>())
+            .field("disabled", &self.disabled)
+            .field("severities", &self.severities)
+            .finish()
+    }
+}
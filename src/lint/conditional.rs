@@ -0,0 +1,98 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Conditional rules
+//!
+//! This module provides [LintRule]s checking the contents of conditional
+//! statements, i.e. `when` and `else` blocks.
+
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+
+use super::{LintRule, Severity, Sink};
+
+
+/// FIRRTL dialect a [DeclarationInWhen] rule checks against
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Declarations in conditional blocks are legal but discouraged
+    #[default]
+    Permissive,
+    /// Declarations in conditional blocks are illegal
+    Strict,
+}
+
+
+/// Rule checking for instances and memories declared in conditional blocks
+///
+/// Depending on the version of the FIRRTL specification, declaring instances
+/// or memories inside `when` or `else` blocks is either discouraged or
+/// forbidden. The declaration does not make the instance or memory itself
+/// conditional, which is frequently not what the author intended. For the
+/// [Permissive](Dialect::Permissive) dialect, findings are reported as
+/// warnings by default. For the [Strict](Dialect::Strict) dialect, they are
+/// reported as errors.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeclarationInWhen {
+    dialect: Dialect,
+}
+
+impl DeclarationInWhen {
+    /// Create a new rule for the [Permissive](Dialect::Permissive) dialect
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the dialect to check against
+    pub fn with_dialect(self, dialect: Dialect) -> Self {
+        Self {dialect}
+    }
+}
+
+impl LintRule for DeclarationInWhen {
+    fn id(&self) -> &'static str {
+        "declaration-in-when"
+    }
+
+    fn severity(&self) -> Severity {
+        match self.dialect {
+            Dialect::Permissive => Severity::Warning,
+            Dialect::Strict     => Severity::Error,
+        }
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        fn check(stmts: &[Statement], nested: bool, sink: &mut Sink) {
+            stmts.iter().for_each(|s| match s.kind() {
+                Kind::Conditional{when, r#else, ..} => {
+                    check(when, true, sink);
+                    check(r#else, true, sink);
+                },
+                _ if !nested => (),
+                Kind::Declaration(e) => match e.as_ref() {
+                    Entity::Instance(inst) => sink.report_entity(inst.name().clone(), format!(
+                        "instance `{}` of module `{}` is declared inside a `when` block",
+                        inst.name(),
+                        inst.module().name(),
+                    )),
+                    Entity::InstanceChoice(inst) => sink.report_entity(inst.name().clone(), format!(
+                        "instance choice `{}` is declared inside a `when` block",
+                        inst.name(),
+                    )),
+                    Entity::Memory(mem) => sink.report_entity(
+                        mem.name().clone(),
+                        format!("memory `{}` is declared inside a `when` block", mem.name()),
+                    ),
+                    _ => (),
+                },
+                Kind::SimpleMemDecl(mem) => sink.report_entity(
+                    mem.name().clone(),
+                    format!("memory `{}` is declared inside a `when` block", mem.name()),
+                ),
+                _ => (),
+            })
+        }
+
+        check(module.statements(), false, sink)
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Instance rules
+//!
+//! This module provides [LintRule]s checking the use of [Instance]s.
+
+use crate::analysis::drivers_in;
+use crate::module::{Direction, Instance, Module};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+use crate::target::{Subscript, Target};
+
+use super::{LintRule, Severity, Sink};
+
+
+/// Rule checking that all inputs of instances are driven
+///
+/// FIRRTL requires every input port of an instance to be driven, either
+/// unconditionally, conditionally or by invalidating it. This rule reports
+/// input ports for which the instantiating module contains no such statement
+/// at all. Connections to a part of a port, e.g. a single field of a bundle,
+/// are considered driving the port. Findings target the port via the
+/// instance, i.e. the target's path includes the instance. Findings are
+/// reported as errors by default.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UndrivenInstanceInput;
+
+impl LintRule for UndrivenInstanceInput {
+    fn id(&self) -> &'static str {
+        "undriven-instance-input"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_statement(&self, module: &Module, stmt: &Statement, sink: &mut Sink) {
+        let inst = if let Some(inst) = instance(stmt) { inst } else { return };
+        inst.module()
+            .ports()
+            .filter(|p| p.direction() == Direction::Input)
+            .filter(|p| drivers_in(module, inst.name(), &[Subscript::Field(p.name().clone())]).is_empty())
+            .for_each(|p| {
+                let path = sink.module_target().path().clone().with_instance(inst.name().clone(), inst.module().name().clone());
+                sink.report(
+                    Target::reference(path, p.name().clone()),
+                    format!("input `{}` of instance `{}` is not driven", p.name(), inst.name()),
+                )
+            })
+    }
+}
+
+
+/// Retrieve the instance declared by a statement
+fn instance(stmt: &Statement) -> Option<&Instance> {
+    match stmt.kind() {
+        Kind::Declaration(e) => if let Entity::Instance(inst) = e.as_ref() { Some(inst) } else { None },
+        _ => None,
+    }
+}
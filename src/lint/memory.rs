@@ -0,0 +1,109 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Memory rules
+//!
+//! This module provides [LintRule]s checking the configuration of
+//! [Memory]s, i.e. `mem` declarations.
+
+use crate::memory::{Memory, ReadUnderWrite, mem::Latency};
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+
+use super::{LintRule, Severity, Sink};
+
+
+/// Rule checking the latencies of memories
+///
+/// FIRRTL requires the write latency of memories to be at least one. In
+/// addition, this rule may enforce maximum latencies, e.g. those supported by
+/// a target technology. By default, no maximum is enforced. Findings are
+/// reported as errors by default.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLatency {
+    max_read: Option<Latency>,
+    max_write: Option<Latency>,
+}
+
+impl MemoryLatency {
+    /// Create a new rule without maximum latencies
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the maximum read latency
+    pub fn with_max_read(self, max: Latency) -> Self {
+        Self {max_read: Some(max), ..self}
+    }
+
+    /// Set the maximum write latency
+    pub fn with_max_write(self, max: Latency) -> Self {
+        Self {max_write: Some(max), ..self}
+    }
+}
+
+impl LintRule for MemoryLatency {
+    fn id(&self) -> &'static str {
+        "memory-latency"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_statement(&self, _module: &Module, stmt: &Statement, sink: &mut Sink) {
+        let mem = if let Some(mem) = memory(stmt) { mem } else { return };
+        let mut report = |message: String| {
+            let target = sink.entity_target(mem.name().clone());
+            sink.report(target, format!("memory `{}` {}", mem.name(), message))
+        };
+
+        if mem.write_latency() < 1 {
+            report("has a write latency of zero".to_string())
+        }
+        if let Some(max) = self.max_read.filter(|m| mem.read_latency() > *m) {
+            report(format!("exceeds the maximum read latency of {}", max))
+        }
+        if let Some(max) = self.max_write.filter(|m| mem.write_latency() > *m) {
+            report(format!("exceeds the maximum write latency of {}", max))
+        }
+    }
+}
+
+
+/// Rule warning about pipelined reads of old values
+///
+/// Memories with a read latency greater than one and read-under-write
+/// behaviour `old` require additional logic for preserving the old value over
+/// multiple cycles and are frequently not supported by synthesis tools.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PipelinedReadOld;
+
+impl LintRule for PipelinedReadOld {
+    fn id(&self) -> &'static str {
+        "pipelined-read-old"
+    }
+
+    fn check_statement(&self, _module: &Module, stmt: &Statement, sink: &mut Sink) {
+        match memory(stmt) {
+            Some(mem) if mem.read_latency() > 1 && mem.read_under_write() == ReadUnderWrite::Old => {
+                let target = sink.entity_target(mem.name().clone());
+                sink.report(target, format!(
+                    "memory `{}` has a read latency of {} with read-under-write behaviour `old`",
+                    mem.name(),
+                    mem.read_latency(),
+                ))
+            },
+            _ => (),
+        }
+    }
+}
+
+
+/// Retrieve the memory declared by a statement
+fn memory(stmt: &Statement) -> Option<&Memory> {
+    match stmt.kind() {
+        Kind::Declaration(e) => if let Entity::Memory(mem) = e.as_ref() { Some(mem) } else { None },
+        _ => None,
+    }
+}
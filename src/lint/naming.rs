@@ -0,0 +1,245 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Naming convention rules
+//!
+//! This module provides [LintRule]s checking the names of modules, ports and
+//! declared entities. Where possible, findings carry a [Fix] renaming the
+//! offending entity.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::{Entity, Statement};
+
+use super::{Fix, LintRule, Sink};
+
+
+/// Kind of a named item
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EntityKind {Module, Port, Wire, Register, Node, Memory, MemoryPort, Instance}
+
+impl EntityKind {
+    /// Determine the kind of the given entity
+    pub fn of(entity: &Entity) -> Self {
+        match entity {
+            Entity::Port(..)            => Self::Port,
+            Entity::Wire{..}            => Self::Wire,
+            Entity::Register(..)        => Self::Register,
+            Entity::Node{..}            => Self::Node,
+            Entity::Memory(..)          => Self::Memory,
+            Entity::SimpleMemPort(..)   => Self::MemoryPort,
+            Entity::Instance(..)        => Self::Instance,
+            Entity::InstanceChoice(..)  => Self::Instance,
+            Entity::Binder{..}          => Self::Node,
+        }
+    }
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Module        => write!(f, "module"),
+            Self::Port          => write!(f, "port"),
+            Self::Wire          => write!(f, "wire"),
+            Self::Register      => write!(f, "register"),
+            Self::Node          => write!(f, "node"),
+            Self::Memory        => write!(f, "memory"),
+            Self::MemoryPort    => write!(f, "memory port"),
+            Self::Instance      => write!(f, "instance"),
+        }
+    }
+}
+
+
+/// Rule checking names against a pattern per [EntityKind]
+///
+/// Names of items of a kind for which a pattern is configured must match that
+/// pattern as a whole. Findings don't carry a fix.
+#[derive(Clone, Debug, Default)]
+pub struct NamingPattern {
+    patterns: HashMap<EntityKind, Regex>,
+}
+
+impl NamingPattern {
+    /// Create a new rule without any patterns
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the pattern for items of the given kind
+    ///
+    /// The pattern is a regular expression which needs to match a name as a
+    /// whole, i.e. it is anchored implicitly.
+    pub fn with_pattern(mut self, kind: EntityKind, pattern: &str) -> Result<Self, regex::Error> {
+        self.patterns.insert(kind, Regex::new(&format!("^(?:{})$", pattern))?);
+        Ok(self)
+    }
+}
+
+impl LintRule for NamingPattern {
+    fn id(&self) -> &'static str {
+        "naming-pattern"
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        items(module).into_iter().for_each(|(kind, name)| if let Some(pattern) = self.patterns.get(&kind) {
+            if !pattern.is_match(name) {
+                report(sink, kind, name, format!("does not match pattern `{}`", pattern), None)
+            }
+        })
+    }
+}
+
+
+/// Rule rejecting names with prefixes reserved for generated code
+///
+/// By default, the prefixes `_T` and `_GEN` are forbidden, which are used by
+/// Chisel for temporaries. A name has a forbidden prefix if it starts with the
+/// prefix followed by the end of the name, an underscore or a digit. For
+/// declared entities, findings suggest renaming the entity to the name with
+/// the prefix replaced by a lower-case variant without leading underscores,
+/// e.g. `_T_5` to `t_5`.
+#[derive(Clone, Debug)]
+pub struct ForbiddenPrefix {
+    prefixes: Vec<Arc<str>>,
+}
+
+impl ForbiddenPrefix {
+    /// Create a new rule with the given prefixes
+    pub fn new(prefixes: impl IntoIterator<Item = impl Into<Arc<str>>>) -> Self {
+        Self {prefixes: prefixes.into_iter().map(Into::into).collect()}
+    }
+
+    /// Add a forbidden prefix
+    pub fn with_prefix(mut self, prefix: impl Into<Arc<str>>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Retrieve the prefix the given name starts with, if any
+    fn prefix_of(&self, name: &str) -> Option<&str> {
+        self.prefixes.iter().map(AsRef::as_ref).find(|p: &&str| name
+            .strip_prefix(*p)
+            .map(|r| r.chars().next().map(|c| c == '_' || c.is_ascii_digit()).unwrap_or(true))
+            .unwrap_or(false))
+    }
+}
+
+impl Default for ForbiddenPrefix {
+    fn default() -> Self {
+        Self::new(["_T", "_GEN"].iter().cloned())
+    }
+}
+
+impl LintRule for ForbiddenPrefix {
+    fn id(&self) -> &'static str {
+        "forbidden-prefix"
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        let items = items(module);
+        let mut taken: HashSet<String> = items.iter().map(|(_, n)| n.to_string()).collect();
+        items.iter().for_each(|(kind, name)| if let Some(prefix) = self.prefix_of(name) {
+            let replacement = prefix.trim_start_matches('_').to_lowercase();
+            let fix = renamable(*kind)
+                .then(|| unique(replacement.clone() + &name[prefix.len()..], &mut taken, usize::MAX))
+                .flatten();
+            report(sink, *kind, name, format!("has forbidden prefix `{}`", prefix), fix)
+        })
+    }
+}
+
+
+/// Rule limiting the length of names
+///
+/// Names may not exceed a maximum number of characters, which is 64 by
+/// default. For declared entities, findings suggest renaming the entity to a
+/// truncated name.
+#[derive(Clone, Debug)]
+pub struct MaxLength {
+    max: usize,
+}
+
+impl MaxLength {
+    /// Create a new rule with the given maximum length
+    pub fn new(max: usize) -> Self {
+        Self {max}
+    }
+}
+
+impl Default for MaxLength {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
+
+impl LintRule for MaxLength {
+    fn id(&self) -> &'static str {
+        "max-identifier-length"
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        let items = items(module);
+        let mut taken: HashSet<String> = items.iter().map(|(_, n)| n.to_string()).collect();
+        items.iter().filter(|(_, n)| n.chars().count() > self.max).for_each(|(kind, name)| {
+            let fix = renamable(*kind)
+                .then(|| unique(name.chars().take(self.max).collect(), &mut taken, self.max))
+                .flatten();
+            report(sink, *kind, name, format!("exceeds the maximum length of {}", self.max), fix)
+        })
+    }
+}
+
+
+/// Retrieve all named items in a module, including the module itself
+fn items(module: &Module) -> Vec<(EntityKind, &Arc<str>)> {
+    std::iter::once((EntityKind::Module, module.name()))
+        .chain(module.ports().map(|p| (EntityKind::Port, p.name())))
+        .chain(module
+            .statements()
+            .iter()
+            .flat_map(Statement::declarations)
+            .map(|e| (EntityKind::of(e), e.name())))
+        .collect()
+}
+
+
+/// Check whether items of the given kind may be renamed via a [Fix]
+fn renamable(kind: EntityKind) -> bool {
+    !matches!(kind, EntityKind::Module | EntityKind::Port)
+}
+
+
+/// Report a finding for a named item
+fn report(sink: &mut Sink, kind: EntityKind, name: &str, issue: String, fix: Option<String>) {
+    let message = format!("{} name `{}` {}", kind, name, issue);
+    if kind == EntityKind::Module {
+        sink.report_module(message)
+    } else {
+        let target = sink.entity_target(name);
+        sink.report_with_fix(target, message, fix.map(|n| Fix::Rename(n.into())))
+    }
+}
+
+
+/// Derive a name not yet taken from the given base name
+///
+/// The name derived will not exceed `max` characters. It is added to the set
+/// of taken names.
+fn unique(base: String, taken: &mut HashSet<String>, max: usize) -> Option<String> {
+    let res = std::iter::once(base.clone())
+        .chain((0..1000).map(|n| {
+            let suffix = format!("_{}", n);
+            let len = max.saturating_sub(suffix.len()).min(base.len());
+            base.chars().take(len).collect::<String>() + &suffix
+        }))
+        .filter(|n| n.chars().count() <= max)
+        .find(|n| !taken.contains(n))?;
+    taken.insert(res.clone());
+    Some(res)
+}
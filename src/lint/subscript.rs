@@ -0,0 +1,71 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Subscript rules
+//!
+//! This module provides [LintRule]s checking sub-index and sub-access
+//! expressions against the types of the vectors they select from.
+
+use transiter::AutoTransIter;
+
+use crate::expr::Expression;
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::Statement;
+use crate::types::{GroundType, Type, Typed};
+
+use super::{LintRule, Severity, Sink};
+
+
+/// Rule checking subscripts of vectors
+///
+/// FIRRTL requires the index of a sub-index expression to be lower than the
+/// width of the indexed vector and the index of a sub-access expression to be
+/// an unsigned integer. This rule reports every subscript violating these
+/// requirements, naming the type of the vector and the offending index.
+/// Subscripts of expressions whose type cannot be determined are not
+/// reported. Findings target the entity at the root of the expression, if
+/// any, and are reported as errors by default.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SubscriptBounds;
+
+impl LintRule for SubscriptBounds {
+    fn id(&self) -> &'static str {
+        "subscript-bounds"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_statement(&self, _module: &Module, stmt: &Statement, sink: &mut Sink) {
+        stmt.expressions().into_iter().flat_map(AutoTransIter::trans_iter).for_each(|e| {
+            let message = match e {
+                Expression::SubIndex{base, index} => match base.r#type() {
+                    Ok(t) if t.vector().map(|(_, width)| *index >= width).unwrap_or(false) => format!(
+                        "index {} of `{}` is out of bounds for type {}",
+                        index,
+                        e,
+                        t,
+                    ),
+                    _ => return,
+                },
+                Expression::SubAccess{base, index} => match index.r#type().as_ref().map(Type::resolved) {
+                    Ok(Type::GroundType(GroundType::UInt(_))) | Err(_) => return,
+                    Ok(t) => format!(
+                        "index `{}` of `{}` is of type {}, expected UInt, for base type {}",
+                        index,
+                        e,
+                        t,
+                        base.r#type().map(|t| t.to_string()).unwrap_or_else(|_| "unknown".to_string()),
+                    ),
+                },
+                _ => return,
+            };
+
+            match e.access_path().0 {
+                Expression::Reference(r) => sink.report_entity(r.name().clone(), message),
+                _ => sink.report_module(message),
+            }
+        })
+    }
+}
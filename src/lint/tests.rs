@@ -0,0 +1,249 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to the lint framework
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+
+use super::*;
+
+
+/// Rule reporting every module
+struct EveryModule;
+
+impl LintRule for EveryModule {
+    fn id(&self) -> &'static str {
+        "every-module"
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        sink.report_module(module.name().to_string())
+    }
+}
+
+
+/// Rule reporting every statement
+struct EveryStatement;
+
+impl LintRule for EveryStatement {
+    fn id(&self) -> &'static str {
+        "every-statement"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn check_statement(&self, _module: &Module, _stmt: &Statement, sink: &mut Sink) {
+        sink.report_module("statement")
+    }
+}
+
+
+#[quickcheck]
+fn lint_module_findings(circuit: Circuit) -> TestResult {
+    let findings = Registry::new().with_rule(EveryModule).run(&circuit);
+    let res = findings.len() == circuit.modules().count() && findings.iter().all(|f| {
+        f.rule == "every-module" &&
+            f.severity == Severity::Warning &&
+            f.target.as_ref().and_then(|t| t.circuit()) == Some(circuit.top_module().name()) &&
+            f.target.as_ref().map(|t| t.path().root().as_ref() == f.message).unwrap_or(false)
+    });
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn lint_registry_config(circuit: Circuit) -> TestResult {
+    let registry = Registry::new()
+        .with_rule(EveryModule)
+        .with_rule(EveryStatement)
+        .with_disabled("every-module")
+        .with_severity("every-statement", Severity::Error);
+
+    let findings = registry.run(&circuit);
+    let res = !registry.is_enabled("every-module") &&
+        registry.is_enabled("every-statement") &&
+        findings.iter().all(|f| f.rule == "every-statement" && f.severity == Severity::Error);
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn naming_pattern(circuit: Circuit) -> TestResult {
+    use naming::{EntityKind, NamingPattern};
+
+    let any = NamingPattern::new().with_pattern(EntityKind::Module, ".*").unwrap();
+    let none = NamingPattern::new().with_pattern(EntityKind::Module, "").unwrap();
+    let res = Registry::new().with_rule(any).run(&circuit).is_empty() &&
+        Registry::new().with_rule(none).run(&circuit).len() == circuit.modules().count();
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn naming_fixes(circuit: Circuit) -> TestResult {
+    let registry = Registry::new().with_rule(naming::MaxLength::new(4));
+    let findings = registry.run(&circuit);
+    let res = circuit.modules().all(|m| {
+        let fixed = Circuit::new(Arc::new(apply_fixes(m, &findings)));
+        registry.run(&fixed).iter().all(|f| f.fix.is_none() || f.target.as_ref().map(|t| t.path().root()) != Some(m.name()))
+    });
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn memory_latency(circuit: Circuit) -> TestResult {
+    use transiter::AutoTransIter;
+
+    use crate::stmt::{Entity, Kind};
+
+    let zero_latency = circuit
+        .modules()
+        .flat_map(|m| m.statements().iter().flat_map(AutoTransIter::trans_iter).collect::<Vec<_>>())
+        .filter(|s| matches!(s.kind(), Kind::Declaration(e)
+            if matches!(e.as_ref(), Entity::Memory(m) if m.write_latency() == 0)))
+        .count();
+    let findings = Registry::new().with_rule(memory::MemoryLatency::new()).run(&circuit);
+    TestResult::from_bool(findings.len() == zero_latency && findings.iter().all(|f| f.severity == Severity::Error))
+}
+
+
+#[quickcheck]
+fn undriven_instance_input(driver: u8) -> Result<bool, crate::error::ParseError> {
+    let driver = match driver % 4 {
+        0 => "skip",
+        1 => "sub.b <= a",
+        2 => "when a:\n      sub.b <= a",
+        _ => "sub.b is invalid",
+    };
+    let source = format!(concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input a: UInt<1>\n",
+        "    input b: UInt<1>\n",
+        "    output c: UInt<1>\n",
+        "    c <= and(a, b)\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    output c: UInt<1>\n",
+        "    inst sub of Sub\n",
+        "    sub.a <= a\n",
+        "    {}\n",
+        "    c <= sub.c\n",
+    ), driver);
+    let circuit = crate::circuit::parse(&source)?;
+
+    let findings = Registry::new().with_rule(instance::UndrivenInstanceInput).run(&circuit);
+    let expected = InstancePath::new("Top").with_instance("sub", "Sub");
+    Ok(match findings.as_slice() {
+        [] => driver != "skip",
+        [f] => driver == "skip" && f.target.as_ref().map(|t| t.path() == &expected) == Some(true),
+        _ => false,
+    })
+}
+
+
+#[quickcheck]
+fn maybe_undefined(driver: u8) -> Result<bool, crate::error::ParseError> {
+    let driver = match driver % 4 {
+        0 => "w <= a",
+        1 => "w is invalid",
+        2 => "w is invalid\n    w <= a",
+        _ => "w <= validif(a, a)",
+    };
+    let source = format!(concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    output c: UInt<1>\n",
+        "    wire w: UInt<1>\n",
+        "    {}\n",
+        "    node n = not(w)\n",
+        "    c <= n\n",
+    ), driver);
+    let circuit = crate::circuit::parse(&source)?;
+
+    let findings = Registry::new().with_rule(undefined::MaybeUndefined).run(&circuit);
+    let names: Vec<_> = findings.iter().filter_map(|f| f.target.as_ref()?.reference_name().cloned()).collect();
+    Ok(match driver {
+        "w <= a" | "w is invalid\n    w <= a" => names.is_empty(),
+        _ => names == ["w".into(), "n".into(), "c".into()] && findings[2].message.contains("w -> n -> c"),
+    })
+}
+
+
+#[quickcheck]
+fn declaration_in_when(kind: u8, strict: bool) -> Result<bool, crate::error::ParseError> {
+    let (decl, name) = match kind % 4 {
+        0 => ("inst sub of Sub", "sub"),
+        1 => ("smem m: UInt<1>[4]", "m"),
+        2 => ("wire w: UInt<1>", "w"),
+        _ => ("mem m:\n        data-type => UInt<1>\n        depth => 4\n        read-latency => 0\n        write-latency => 1\n        read-under-write => undefined", "m"),
+    };
+    let source = format!(concat!(
+        "circuit Top:\n",
+        "  module Sub:\n",
+        "    input a: UInt<1>\n",
+        "  module Top:\n",
+        "    input a: UInt<1>\n",
+        "    {}\n",
+        "    when a:\n",
+        "      {}\n",
+    ), decl.replace("\n    ", "\n  "), decl);
+    let circuit = crate::circuit::parse(&source)?;
+
+    let dialect = if strict { conditional::Dialect::Strict } else { conditional::Dialect::Permissive };
+    let rule = conditional::DeclarationInWhen::new().with_dialect(dialect);
+    let findings = Registry::new().with_rule(rule).run(&circuit);
+    let severity = if strict { Severity::Error } else { Severity::Warning };
+    Ok(match findings.as_slice() {
+        [] => name == "w",
+        [f] => name != "w" &&
+            f.severity == severity &&
+            f.target.as_ref().and_then(|t| t.reference_name()).map(AsRef::as_ref) == Some(name),
+        _ => false,
+    })
+}
+
+
+#[quickcheck]
+fn subscript_bounds(index: u8, signed: bool) -> Result<bool, crate::error::ParseError> {
+    let index = index % 6;
+    let access = if signed { "s" } else { "u" };
+    let source = format!(concat!(
+        "circuit Top:\n",
+        "  module Top:\n",
+        "    input v: UInt<1>[4]\n",
+        "    input u: UInt<2>\n",
+        "    input s: SInt<2>\n",
+        "    output c: UInt<1>\n",
+        "    output d: UInt<1>\n",
+        "    c <= v[{}]\n",
+        "    d <= v[{}]\n",
+    ), index, access);
+    let circuit = crate::circuit::parse(&source)?;
+
+    let findings = Registry::new().with_rule(subscript::SubscriptBounds).run(&circuit);
+    let expected = usize::from(index >= 4) + usize::from(signed);
+    Ok(findings.len() == expected && findings.iter().all(|f| {
+        f.severity == Severity::Error &&
+            f.target.as_ref().and_then(|t| t.reference_name()).map(AsRef::as_ref) == Some("v") &&
+            f.message.contains("UInt<1>[4]")
+    }))
+}
+
+
+#[test]
+fn builtin_rules() {
+    let registry = Registry::builtin();
+    assert!(registry.rules().all(|r| registry.is_enabled(r)));
+    assert_eq!(registry.rules().count(), 6);
+
+    for circuit in [crate::fixtures::alu(), crate::fixtures::fifo(), crate::fixtures::hierarchy()].iter() {
+        let report = crate::report::Report::from(registry.run(circuit));
+        assert!(report.is_empty(), "{}", report);
+    }
+}
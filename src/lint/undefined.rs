@@ -0,0 +1,50 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Undefined value rules
+//!
+//! This module provides [LintRule]s checking for signals which may carry
+//! undefined values, e.g. as a sanity check prior to simulation.
+
+use crate::analysis::{HierarchyMode, UndefinedOrigin, undefined_values};
+use crate::module::Module;
+use crate::named::Named;
+
+use super::{LintRule, Sink};
+
+
+/// Rule reporting signals which may carry undefined values
+///
+/// This rule reports every signal which may carry an undefined value as
+/// determined by [undefined_values], i.e. signals which are invalidated,
+/// driven by `validif` expressions or registers without reset as well as all
+/// signals those values propagate to. The message of each finding names the
+/// origin of the undefined value and the path along which it reaches the
+/// signal.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MaybeUndefined;
+
+impl LintRule for MaybeUndefined {
+    fn id(&self) -> &'static str {
+        "maybe-undefined"
+    }
+
+    fn check_module(&self, module: &Module, sink: &mut Sink) {
+        undefined_values(module, HierarchyMode::PerModule).into_iter().for_each(|v| {
+            let path = v.path();
+            let origin = match v.origin() {
+                UndefinedOrigin::Invalidated(_)         => format!("`{}` is invalidated", path[0]),
+                UndefinedOrigin::ValidIf(_)             => format!("`{}` is driven by a `validif`", path[0]),
+                UndefinedOrigin::UninitializedRegister  => format!("`{}` is a register without reset", path[0]),
+                UndefinedOrigin::Instance(i)            =>
+                    format!("`{}` is an instance of `{}` with undefined outputs", path[0], i.module().name()),
+            };
+            let message = if path.len() > 1 {
+                let path: Vec<_> = path.iter().map(AsRef::as_ref).collect();
+                format!("`{}` may be undefined: {}, reaching it via {}", v.signal(), origin, path.join(" -> "))
+            } else {
+                format!("`{}` may be undefined: {}", v.signal(), origin)
+            };
+            sink.report_entity(v.signal().clone(), message)
+        })
+    }
+}
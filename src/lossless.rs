@@ -0,0 +1,353 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Lossless parsing and formatting
+//!
+//! The AST does not retain comments, blank lines or the original indentation
+//! of the source it was parsed from. Refactoring tools, however, must not
+//! disturb the parts of a source they did not touch. This module provides
+//! [LosslessSource], which retains the trivia preceding each module and each
+//! top-level statement alongside the original text. Given a modified circuit,
+//! [LosslessSource::format] emits the original text for every module and
+//! statement which was not altered and formats only the altered ones. If
+//! nothing was altered, the original source is reproduced byte-for-byte.
+//!
+//! Statements nested in conditional statements are not tracked individually.
+//! If any of them is altered, the enclosing top-level statement is formatted
+//! as a whole.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::lossless::LosslessSource;
+//!
+//! let text = concat!(
+//!     "circuit Foo:\n",
+//!     "  ; The only module\n",
+//!     "  module Foo:\n",
+//!     "    input a: UInt<8>\n",
+//!     "    output b: UInt<8>\n",
+//!     "\n",
+//!     "    b <= a ; Just a wire\n",
+//! );
+//! let source = LosslessSource::parse(text).unwrap();
+//! assert_eq!(source.to_string(), text);
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use crate::circuit::{Circuit, Version};
+use crate::emit::{Emitter, FirrtlEmitter};
+use crate::error::ParseError;
+use crate::incremental::ParsedSource;
+use crate::indentation::{Indentation, IndentationConfig};
+use crate::info::{Info, WithInfo};
+use crate::module::{Kind, Module};
+use crate::named::Named;
+use crate::stmt::Statement;
+
+
+/// Source of a circuit retaining comments and layout
+#[derive(Clone, Debug)]
+pub struct LosslessSource {
+    circuit: Circuit,
+    header: String,
+    modules: Vec<ModuleSource>,
+    tail: String,
+}
+
+impl LosslessSource {
+    /// Parse the given source
+    pub fn parse(text: impl Into<String>) -> Result<Self, ParseError> {
+        let source = ParsedSource::parse(text)?;
+        let circuit = source.circuit()?;
+        let text = source.text();
+
+        let mut located = Vec::new();
+        for module in source.modules() {
+            let range = source
+                .module_range(module.name())
+                .ok_or_else(|| format!("Location of module {} unknown", module.name()))?;
+            located.push((range, module.clone()));
+        }
+
+        // Trivia following the last line of code of a module are attributed to
+        // the next module rather than the module the parser assigned them to.
+        let header = located.first().map(|(r, _)| chunks(&text[..r.start]).1).unwrap_or(text.len());
+        let mut start = header;
+        let modules = located.into_iter().map(|(range, module)| {
+            let end = (range.start + chunks(&text[range]).1).max(start);
+            let res = ModuleSource::new(&text[start..end], module);
+            start = end;
+            res
+        }).collect();
+
+        Ok(Self {header: text[..header].into(), tail: text[start..].into(), circuit, modules})
+    }
+
+    /// Retrieve the circuit parsed
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// Retrieve the trivia preceding the module with the given name
+    pub fn module_trivia(&self, module: impl AsRef<str>) -> Option<&str> {
+        self.module(module.as_ref()).map(|m| m.leading.as_ref())
+    }
+
+    /// Retrieve the trivia preceding a top-level statement
+    ///
+    /// This function retrieves the trivia preceding the statement with the
+    /// given `index` in the module with the given name. If the statements of
+    /// the module could not be associated with their text, this function
+    /// returns `None`.
+    pub fn statement_trivia(&self, module: impl AsRef<str>, index: usize) -> Option<&str> {
+        self.module(module.as_ref())
+            .and_then(|m| m.stmts.as_ref())
+            .and_then(|s| s.get(index))
+            .map(|s| s.leading.as_ref())
+    }
+
+    /// Format the given circuit, retaining the layout of this source
+    ///
+    /// Modules and top-level statements present in this source are emitted
+    /// as they appear in the source, including the trivia preceding them.
+    /// Altered or added ones are formatted with the indentation of the
+    /// surrounding source. Modules and statements not present in the given
+    /// circuit are omitted, along with their trivia.
+    pub fn format<'a>(&'a self, circuit: &'a Circuit) -> Formatted<'a> {
+        Formatted {source: self, circuit}
+    }
+
+    /// Retrieve the source of the module with the given name
+    fn module(&self, name: &str) -> Option<&ModuleSource> {
+        self.modules.iter().find(|m| m.module.name_ref() == name)
+    }
+}
+
+impl fmt::Display for LosslessSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&self.circuit))
+    }
+}
+
+
+/// A circuit formatted with the layout of a [LosslessSource]
+///
+/// Instances of this type are created via [LosslessSource::format].
+#[derive(Copy, Clone, Debug)]
+pub struct Formatted<'a> {
+    source: &'a LosslessSource,
+    circuit: &'a Circuit,
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let original = &self.source.circuit;
+        if self.circuit.top_module().name() == original.top_module().name() &&
+            self.circuit.info() == original.info() &&
+            self.circuit.version() == original.version() {
+            f.write_str(&self.source.header)?;
+        } else {
+            if let Some(version) = self.circuit.version() {
+                writeln!(f, "FIRRTL version {}", version)?;
+            }
+            writeln!(f, "circuit {}:{}", self.circuit.top_module().name(), Info::of(self.circuit))?;
+        }
+
+        let indentation = self
+            .source
+            .modules
+            .first()
+            .map(|m| m.indentation())
+            .unwrap_or_else(|| Indentation::root().sub());
+        let version = self.circuit.version();
+        self.circuit.defined_modules().iter().try_for_each(|m| match self.source.module(m.name()) {
+            Some(source) => source.fmt(m, version, f),
+            None => FirrtlEmitter::new(&mut *f).with_indentation(indentation.clone()).with_version(version).module(m),
+        })?;
+
+        f.write_str(&self.source.tail)
+    }
+}
+
+
+/// Source of a single module
+#[derive(Clone, Debug)]
+struct ModuleSource {
+    module: Arc<Module>,
+    text: String,
+    leading: String,
+    head: String,
+    stmts: Option<Vec<StatementSource>>,
+    level: usize,
+    body_level: usize,
+    config: IndentationConfig,
+}
+
+impl ModuleSource {
+    /// Create a new module source from the module's text
+    fn new(text: &str, module: Arc<Module>) -> Self {
+        let (chunks, end) = chunks(text);
+        let ports = module.ports().count();
+
+        let stmts = match module.kind() {
+            Kind::Regular{stmts} if chunks.len() == 1 + ports + stmts.len() => Some(chunks[1 + ports..]
+                .iter()
+                .zip(stmts)
+                .map(|(c, s)| StatementSource {
+                    stmt: s.clone(),
+                    leading: text[c.start..c.code].into(),
+                    text: text[c.code..c.end].into(),
+                })
+                .collect()),
+            _ => None,
+        };
+
+        let level = |c: Option<&Chunk>| c.map(|c| indentation_of(&text[c.code..]));
+        let head_start = chunks.first().map(|c| c.code).unwrap_or(0);
+        let head = head_start..chunks.get(ports).map(|c| c.end).unwrap_or(end).max(head_start);
+        let tabs = chunks.first().map(|c| text[c.code..].starts_with('\t')).unwrap_or(false);
+        let module_level = level(chunks.first()).unwrap_or(0);
+        let body_level = level(chunks.get(1)).filter(|l| *l > module_level);
+        let config = body_level
+            .and_then(|l| NonZeroUsize::new(l - module_level))
+            .map(|w| IndentationConfig::default().with_width(w))
+            .unwrap_or_default()
+            .with_tabs(tabs);
+
+        Self {
+            text: text.into(),
+            leading: text[..head.start].into(),
+            head: text[head.clone()].into(),
+            stmts,
+            level: module_level,
+            body_level: body_level.unwrap_or(module_level + config.width().get()),
+            config,
+            module,
+        }
+    }
+
+    /// Retrieve the indentation of the module's header
+    fn indentation(&self) -> Indentation {
+        Indentation::exact(self.level).with_config(self.config)
+    }
+
+    /// Format the given module with the layout of this source
+    ///
+    /// Statements not present in the source are formatted in the grammar of
+    /// the given FIRRTL `version`.
+    fn fmt(&self, module: &Arc<Module>, version: Option<Version>, f: &mut impl fmt::Write) -> fmt::Result {
+        if Arc::ptr_eq(module, &self.module) || module == &self.module {
+            return f.write_str(&self.text)
+        }
+
+        f.write_str(&self.leading)?;
+        match (module.kind(), self.stmts.as_ref()) {
+            (Kind::Regular{stmts}, Some(sources)) if self.same_head(module) => {
+                f.write_str(&self.head)?;
+                let mut indentation = Indentation::exact(self.body_level).with_config(self.config);
+                let mut next = 0;
+                stmts.iter().try_for_each(|s| {
+                    if let Some(i) = sources[next..].iter().position(|o| &o.stmt == s) {
+                        let source = &sources[next + i];
+                        next += i + 1;
+                        f.write_str(&source.leading)?;
+                        f.write_str(&source.text)
+                    } else {
+                        s.fmt_versioned(version.into(), &mut indentation, f)
+                    }
+                })
+            },
+            _ => FirrtlEmitter::new(f).with_indentation(self.indentation()).with_version(version).module(module),
+        }
+    }
+
+    /// Check whether the given module's header and ports equal the original's
+    fn same_head(&self, module: &Module) -> bool {
+        module.name() == self.module.name() &&
+            module.info() == self.module.info() &&
+            module.ports().eq(self.module.ports())
+    }
+}
+
+
+/// Source of a single top-level statement
+#[derive(Clone, Debug)]
+struct StatementSource {
+    stmt: Statement,
+    leading: String,
+    text: String,
+}
+
+
+/// A chunk of source text
+///
+/// A chunk consists of a line of code, the lines continuing it and the trivia
+/// preceding it. Trivia are blank lines and lines containing only a comment.
+#[derive(Copy, Clone, Debug)]
+struct Chunk {
+    /// Start of the chunk, including the trivia
+    start: usize,
+    /// Start of the first line of code
+    code: usize,
+    /// End of the last line of code, including the line break
+    end: usize,
+}
+
+
+/// Split the text of a module into chunks
+///
+/// The first chunk will contain the module's header. Each of the following
+/// chunks will contain a port, a parameter or a top-level statement. This
+/// function returns the chunks along with the end of the last one.
+fn chunks(text: &str) -> (Vec<Chunk>, usize) {
+    let mut res: Vec<Chunk> = Vec::new();
+    let mut body = None;
+    let mut end = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let code = line.trim_start_matches(&[' ', '\t'][..]);
+        if code.trim().is_empty() || code.starts_with(';') {
+            continue
+        }
+
+        let level = line.len() - code.len();
+        let continued = match body {
+            Some(body) => level > body || (level == body && is_else(code)),
+            None => {
+                if !res.is_empty() {
+                    body = Some(level);
+                }
+                false
+            },
+        };
+        match res.last_mut() {
+            Some(last) if continued => last.end = offset,
+            _ => res.push(Chunk {start: end, code: start, end: offset}),
+        }
+        end = offset;
+    }
+    (res, end)
+}
+
+
+/// Check whether a line of code starts with the `else` keyword
+fn is_else(code: &str) -> bool {
+    code.strip_prefix("else")
+        .map(|r| r.trim_start().is_empty() || r.starts_with(&[' ', '\t', ':'][..]))
+        .unwrap_or(false)
+}
+
+
+/// Determine the indentation of a line
+fn indentation_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to lossless parsing and formatting
+
+use quickcheck::TestResult;
+
+use crate::circuit::{Circuit, parse};
+use crate::stmt::{Kind, Statement};
+
+use super::LosslessSource;
+
+
+#[quickcheck]
+fn reproduce_source(original: Circuit) -> TestResult {
+    let text = original.to_string();
+    match LosslessSource::parse(text.as_str()) {
+        Ok(source) => TestResult::from_bool(source.to_string() == text),
+        Err(e) => TestResult::error(e.to_string()),
+    }
+}
+
+
+#[quickcheck]
+fn format_modified(original: Circuit) -> TestResult {
+    let source = match LosslessSource::parse(original.to_string()) {
+        Ok(source) => source,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+    let modified = crate::passes::simplify_whens(source.circuit());
+    match (parse(&source.format(&modified).to_string()), parse(&modified.to_string())) {
+        (Ok(a), Ok(b)) => TestResult::from_bool(a == b),
+        (a, b) => TestResult::error(format!("Mismatch: {:?} vs {:?}", a, b)),
+    }
+}
+
+
+#[test]
+fn retain_trivia() {
+    let text = concat!(
+        "circuit Top :\n",
+        "  ; Leaf module\n",
+        "  module Sub :\n",
+        "    input a : UInt<1>\n",
+        "    output b : UInt<1>\n",
+        "    b <= a\n",
+        "\n",
+        "  module Top :\n",
+        "    input a : UInt<1>\n",
+        "    output b : UInt<1>\n",
+        "\n",
+        "    ; Instance\n",
+        "    inst s of Sub\n",
+        "    s.a <= a ; drive input\n",
+        "    when a :\n",
+        "      ; Only if a\n",
+        "      b <= s.b\n",
+        "    else :\n",
+        "      b <= a\n",
+        "\n",
+        "    ; Trailing comment\n",
+    );
+    let source = LosslessSource::parse(text).expect("Could not parse source");
+    assert_eq!(source.to_string(), text);
+    assert_eq!(source.module_trivia("Sub"), Some("  ; Leaf module\n"));
+    assert_eq!(source.statement_trivia("Top", 0), Some("\n    ; Instance\n"));
+    assert_eq!(source.statement_trivia("Top", 2), Some(""));
+
+    let modified = source
+        .circuit()
+        .with_module_modified("Top", |m| {
+            let stmts: Vec<Statement> = m.statements().to_vec();
+            let invalid = Kind::Invalidate(
+                stmts[1].expressions().into_iter().next().expect("Missing expression").clone()
+            );
+            *m = m.clone().with_statements(vec![stmts[0].clone(), invalid.into(), stmts[2].clone()]);
+            Ok::<_, ()>(())
+        })
+        .expect("Could not modify module");
+    assert_eq!(source.format(&modified).to_string(), concat!(
+        "circuit Top :\n",
+        "  ; Leaf module\n",
+        "  module Sub :\n",
+        "    input a : UInt<1>\n",
+        "    output b : UInt<1>\n",
+        "    b <= a\n",
+        "\n",
+        "  module Top :\n",
+        "    input a : UInt<1>\n",
+        "    output b : UInt<1>\n",
+        "\n",
+        "    ; Instance\n",
+        "    inst s of Sub\n",
+        "    s.a is invalid\n",
+        "    when a :\n",
+        "      ; Only if a\n",
+        "      b <= s.b\n",
+        "    else :\n",
+        "      b <= a\n",
+        "\n",
+        "    ; Trailing comment\n",
+    ));
+}
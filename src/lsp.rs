@@ -0,0 +1,373 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Building blocks for language servers
+//!
+//! This module provides utilities for implementing language server features on
+//! top of a [ParsedSource]. All locations are byte ranges within the source
+//! text. [Position] allows converting between byte offsets and positions as
+//! used by the language server protocol.
+//!
+//! Locations of items within a module are derived from the module's layout,
+//! i.e. the indentation of its lines, in the same way the parser associates
+//! lines with statements.
+
+#[cfg(test)]
+mod tests;
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::error::ParseError;
+use crate::incremental::ParsedSource;
+use crate::module::{Module, Port};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind, Statement};
+
+
+/// Position in a text document
+///
+/// A position consists of a zero-based line and a zero-based character offset
+/// within that line, counted in UTF-16 code units as mandated by the language
+/// server protocol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    /// Compute the position of a byte offset in the given text
+    pub fn of_offset(text: &str, offset: usize) -> Self {
+        let offset = offset.min(text.len());
+        let line_start = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Self {
+            line: text[..line_start].matches('\n').count(),
+            character: text[line_start..offset].encode_utf16().count(),
+        }
+    }
+
+    /// Compute the byte offset of this position in the given text
+    ///
+    /// Positions beyond the end of a line are mapped to the end of that line.
+    /// Positions beyond the end of the text are mapped to the end of the text.
+    pub fn offset(&self, text: &str) -> usize {
+        let line_start = if self.line == 0 {
+            Some(0)
+        } else {
+            text.match_indices('\n').nth(self.line - 1).map(|(i, _)| i + 1)
+        };
+        let line_start = if let Some(start) = line_start {
+            start
+        } else {
+            return text.len()
+        };
+
+        let mut units = 0;
+        text[line_start..]
+            .char_indices()
+            .find(|(_, c)| {
+                units += c.len_utf16();
+                *c == '\n' || units > self.character
+            })
+            .map(|(i, _)| line_start + i)
+            .unwrap_or(text.len())
+    }
+}
+
+
+/// AST node located at some position
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Node<'a> {
+    Module(&'a Arc<Module>),
+    Port(&'a Arc<Port>),
+    Statement(&'a Statement),
+}
+
+
+/// Kind of a [Symbol]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SymbolKind {Module, Port, Wire, Register, Node, Memory, Instance}
+
+
+/// Symbol in a document
+///
+/// Symbols are named items such as modules and declarations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: Arc<str>,
+    pub kind: SymbolKind,
+    pub range: Range<usize>,
+    pub children: Vec<Symbol>,
+}
+
+
+/// Severity of a [Diagnostic]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {Error, Warning, Information, Hint}
+
+
+/// Diagnostic message associated with a location in a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic for a parse error
+    ///
+    /// Since parse errors don't carry a location, the diagnostic will refer to
+    /// the given `range`, e.g. the range of the text which was parsed.
+    pub fn from_parse_error(err: &ParseError, range: Range<usize>) -> Self {
+        Self {range, severity: Severity::Error, message: err.to_string()}
+    }
+}
+
+
+/// Retrieve the innermost AST node at the given offset
+pub fn node_at(source: &ParsedSource, offset: usize) -> Option<Node<'_>> {
+    let (module, range) = module_at(source, offset)?;
+    let layout = Layout::of(source.text(), range, module);
+    if layout.header.contains(&offset) {
+        return Some(Node::Module(module))
+    }
+
+    layout
+        .ports
+        .iter()
+        .find(|(_, r)| r.contains(&offset))
+        .map(|(p, _)| Node::Port(p))
+        .or_else(|| layout
+            .stmts
+            .iter()
+            .filter(|(_, r, _)| r.contains(&offset))
+            .max_by_key(|(_, _, depth)| *depth)
+            .map(|(s, ..)| Node::Statement(s)))
+        .or(Some(Node::Module(module)))
+}
+
+
+/// Retrieve the symbols of all modules in a document
+///
+/// This function returns one symbol per module. The symbols of ports and
+/// declarations are included as children of the module's symbol.
+pub fn document_symbols(source: &ParsedSource) -> Vec<Symbol> {
+    source.modules().filter_map(|m| source.module_range(m.name()).map(|r| (m, r))).map(|(module, range)| {
+        let layout = Layout::of(source.text(), range.clone(), module);
+        let ports = layout.ports.iter().map(|(p, r)| Symbol {
+            name: p.name().clone(),
+            kind: SymbolKind::Port,
+            range: r.clone(),
+            children: Default::default(),
+        });
+        let decls = layout.stmts.iter().filter_map(|(s, r, _)| match s.kind() {
+            Kind::Declaration(e)    => entity_kind(e).map(|k| (e.name().clone(), k)),
+            Kind::SimpleMemDecl(m)  => Some((m.name().clone(), SymbolKind::Memory)),
+            _ => None,
+        }.map(|(name, kind)| Symbol {name, kind, range: r.clone(), children: Default::default()}));
+
+        Symbol {
+            name: module.name().clone(),
+            kind: SymbolKind::Module,
+            range,
+            children: ports.chain(decls).collect(),
+        }
+    }).collect()
+}
+
+
+/// Find the definition of the identifier at the given offset
+///
+/// If the identifier at the given offset refers to a port, a declared entity
+/// or a module, this function returns the location of its definition, i.e.
+/// the port, the declaration statement or the module, respectively.
+pub fn definition(source: &ParsedSource, offset: usize) -> Option<Range<usize>> {
+    let text = source.text();
+    let (ident, start) = identifier_at(text, offset)?;
+    let (module, range) = module_at(source, offset)?;
+
+    // Module names only appear after specific keywords
+    let preceding = text[..start].trim_end_matches([' ', '\t']);
+    if ["module", "extmodule", " of"].iter().any(|k| preceding.ends_with(k)) {
+        return source.module_range(ident)
+    }
+
+    let layout = Layout::of(text, range, module);
+    layout
+        .ports
+        .iter()
+        .find(|(p, _)| p.name_ref() == ident)
+        .map(|(_, r)| r.clone())
+        .or_else(|| layout.stmts.iter().find(|(s, ..)| match s.kind() {
+            Kind::Declaration(e)    => e.name_ref() == ident,
+            Kind::SimpleMemDecl(m)  => m.name_ref() == ident,
+            _ => false,
+        }).map(|(_, r, _)| r.clone()))
+}
+
+
+/// Retrieve the module defined at the given offset along with its range
+fn module_at(source: &ParsedSource, offset: usize) -> Option<(&Arc<Module>, Range<usize>)> {
+    source
+        .modules()
+        .filter_map(|m| source.module_range(m.name()).map(|r| (m, r)))
+        .find(|(_, r)| r.contains(&offset))
+}
+
+
+/// Retrieve the identifier at the given offset along with its start
+fn identifier_at(text: &str, offset: usize) -> Option<(&str, usize)> {
+    use crate::parsers::is_identifier_char;
+
+    let start = text.get(..offset)?
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_identifier_char(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    let end = text[offset..].find(|c| !is_identifier_char(c)).map(|i| offset + i).unwrap_or(text.len());
+    Some((&text[start..end], start)).filter(|(s, _)| !s.is_empty())
+}
+
+
+/// Determine the [SymbolKind] for an entity
+fn entity_kind(entity: &Entity) -> Option<SymbolKind> {
+    match entity {
+        Entity::Port(_)             => None,
+        Entity::Wire{..}            => Some(SymbolKind::Wire),
+        Entity::Register(_)         => Some(SymbolKind::Register),
+        Entity::Node{..}            => Some(SymbolKind::Node),
+        Entity::Memory(_)           => Some(SymbolKind::Memory),
+        Entity::SimpleMemPort(_)    => Some(SymbolKind::Node),
+        Entity::Instance(_)         => Some(SymbolKind::Instance),
+        Entity::InstanceChoice(_)   => Some(SymbolKind::Instance),
+        Entity::Binder{..}          => Some(SymbolKind::Node),
+    }
+}
+
+
+/// Layout of a module in a source text
+struct Layout<'a> {
+    header: Range<usize>,
+    ports: Vec<(&'a Arc<Port>, Range<usize>)>,
+    stmts: Vec<(&'a Statement, Range<usize>, usize)>,
+}
+
+impl<'a> Layout<'a> {
+    /// Determine the layout of a module defined in the given range
+    fn of(text: &str, range: Range<usize>, module: &'a Module) -> Self {
+        let lines = Line::split(text, range.clone());
+        let header = lines.first().map(|l| l.range.clone()).unwrap_or(range);
+
+        let ports: Vec<_> = module.ports().zip(lines.iter().skip(1)).map(|(p, l)| (p, l.range.clone())).collect();
+        let mut stmts = Default::default();
+        Self::assign(module.statements(), &lines[(ports.len() + 1).min(lines.len())..], 0, &mut stmts);
+
+        Self {header, ports, stmts}
+    }
+
+    /// Associate statements with lines
+    ///
+    /// This function returns the number of lines consumed.
+    fn assign(
+        stmts: &'a [Statement],
+        lines: &[Line],
+        depth: usize,
+        res: &mut Vec<(&'a Statement, Range<usize>, usize)>,
+    ) -> usize {
+        let mut pos = 0;
+        for stmt in stmts {
+            let first = if let Some(l) = lines.get(pos) { l } else { break };
+            let indent = first.indent;
+            let block = |p: usize| p + lines[p..].iter().take_while(|l| l.indent > indent).count();
+
+            let index = res.len();
+            res.push((stmt, Default::default(), depth));
+
+            let body = pos + 1;
+            pos = block(body);
+            if let Kind::Conditional{when, r#else, ..} = stmt.kind() {
+                Self::assign(when, &lines[body..pos], depth + 1, res);
+
+                let else_line = lines.get(pos).filter(|l| l.indent == indent && l.text.starts_with("else"));
+                if let (Some(line), false) = (else_line, r#else.is_empty()) {
+                    let chained = line.text["else".len()..].trim_start().starts_with("when");
+                    if chained {
+                        pos += Self::assign(r#else, &lines[pos..], depth + 1, res);
+                    } else {
+                        let end = block(pos + 1);
+                        Self::assign(r#else, &lines[pos + 1..end], depth + 1, res);
+                        pos = end;
+                    }
+                }
+            }
+
+            res[index].1 = first.range.start..lines[pos - 1].range.end;
+        }
+        pos
+    }
+}
+
+
+/// A logical line of a source text
+///
+/// Comments and blank lines are not considered logical lines. A logical line
+/// may span multiple physical lines if a string literal or info attribute
+/// contains a line break or if an argument list is broken up.
+struct Line<'t> {
+    range: Range<usize>,
+    indent: usize,
+    text: &'t str,
+}
+
+impl<'t> Line<'t> {
+    /// Split the given range of a text into logical lines
+    fn split(text: &'t str, range: Range<usize>) -> Vec<Self> {
+        let base = range.start;
+        let mut res = Vec::new();
+        let mut start = 0;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut depth = 0usize;
+        let mut comment = None;
+        let chunk = &text[range];
+        let mut chars = chunk.char_indices().chain(std::iter::once((chunk.len(), '\n'))).peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                _ if escaped                    => escaped = false,
+                '\\' if quote.is_some()         => escaped = true,
+                c if Some(c) == quote           => quote = None,
+                _ if quote.is_some()            => (),
+                _ if comment.is_some() && c != '\n' => (),
+                '"'                             => quote = Some('"'),
+                '@' if chars.peek().map(|(_, c)| *c) == Some('[') => {
+                    chars.next();
+                    quote = Some(']')
+                },
+                ';'                             => comment = Some(i),
+                '(' | '['                       => depth += 1,
+                ')' | ']'                       => depth = depth.saturating_sub(1),
+                '\n' if depth == 0 || i == chunk.len() => {
+                    let line = &chunk[start..comment.unwrap_or(i)];
+                    let content = line.trim_start_matches([' ', '\t']);
+                    if !content.trim().is_empty() {
+                        let indent = line.len() - content.len();
+                        res.push(Self {
+                            range: base + start..base + i,
+                            indent,
+                            text: content.trim_end(),
+                        });
+                    }
+                    start = i + 1;
+                    comment = None;
+                    depth = 0;
+                },
+                '\n'                            => comment = None,
+                _ => (),
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to language server building blocks
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+use crate::incremental::ParsedSource;
+use crate::named::Named;
+
+use super::*;
+
+
+#[quickcheck]
+fn position_offset_roundtrip(text: String, offset: usize) -> TestResult {
+    let offset = offset % (text.len() + 1);
+    if !text.is_char_boundary(offset) {
+        return TestResult::discard()
+    }
+
+    TestResult::from_bool(Position::of_offset(&text, offset).offset(&text) == offset)
+}
+
+
+#[quickcheck]
+fn symbols_cover_modules(original: Circuit) -> TestResult {
+    let source = match source(&original) {
+        Some(s) => s,
+        None => return TestResult::discard(),
+    };
+
+    let res = document_symbols(&source).into_iter().zip(source.modules()).all(|(symbol, module)| {
+        let children_contained = symbol
+            .children
+            .iter()
+            .all(|c| symbol.range.start <= c.range.start && c.range.end <= symbol.range.end);
+        symbol.name == *module.name() && children_contained &&
+            symbol.children.iter().filter(|c| c.kind == SymbolKind::Port).count() == module.ports().count()
+    });
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn definition_of_declaration(original: Circuit) -> TestResult {
+    let source = match source(&original) {
+        Some(s) => s,
+        None => return TestResult::discard(),
+    };
+
+    let text = source.text();
+    let res = document_symbols(&source).into_iter().all(|module| module.children.iter().all(|symbol| {
+        let first = module.children.iter().find(|s| s.name == symbol.name).map(|s| s.range.clone());
+        let offset = symbol.range.start + text[symbol.range.clone()]
+            .match_indices(symbol.name.as_ref())
+            .find(|(i, _)| identifier_at(text, symbol.range.start + i).map(|(n, _)| n) == Some(symbol.name.as_ref()))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        definition(&source, offset) == first &&
+            matches!(node_at(&source, offset), Some(Node::Port(_)) | Some(Node::Statement(_)))
+    }));
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn definition_of_module(original: Circuit) -> TestResult {
+    let source = match source(&original) {
+        Some(s) => s,
+        None => return TestResult::discard(),
+    };
+
+    let text = source.text();
+    let res = source.modules().all(|m| {
+        let range = source.module_range(m.name());
+        let offset = range.as_ref().and_then(|r| text[r.clone()].find(m.name_ref()).map(|i| r.start + i));
+        offset.map(|o| definition(&source, o) == range).unwrap_or(false)
+    });
+    TestResult::from_bool(res)
+}
+
+
+/// Create a [ParsedSource] for a circuit
+fn source(circuit: &Circuit) -> Option<ParsedSource> {
+    ParsedSource::parse(circuit.to_string()).ok()
+}
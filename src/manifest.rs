@@ -0,0 +1,296 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Machine-readable descriptions of module interfaces
+//!
+//! Firmware and board integration frequently need to know the ports of a
+//! design's top module without parsing FIRRTL. This module provides a
+//! [Manifest] describing the ports of a module as they appear after lowering,
+//! i.e. with aggregate ports flattened into ground type ports. A manifest may
+//! be serialized as JSON via [Manifest::json] or as an IP-XACT component
+//! description via [Manifest::ip_xact]. Ports forming bus interfaces may be
+//! identified via [Manifest::bus_interfaces].
+//!
+//! # Example
+//!
+//! ```
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    input clock: Clock\n",
+//!     "    output io: {a: UInt<8>, flip b: UInt<4>}\n",
+//!     "    io.a <= io.b\n",
+//! )).unwrap();
+//! let manifest = circuit.interface_manifest();
+//! let names: Vec<_> = manifest.ports().iter().map(|p| p.name()).collect();
+//! assert_eq!(names, ["clock", "io_a", "io_b"]);
+//! ```
+
+pub mod bus;
+pub mod ipxact;
+
+#[cfg(test)]
+pub mod tests;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::display::JsonString;
+use crate::module::{Direction, Kind, Module, ParamValue};
+use crate::named::Named;
+use crate::types::{BitWidth, GroundType, Orientation, ResetKind, Type};
+
+
+/// Description of the ports of a module
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    module: Arc<str>,
+    ports: Vec<PortEntry>,
+    params: Vec<(Arc<str>, ParamValue)>,
+}
+
+impl Manifest {
+    /// Create a manifest describing the ports of the given module
+    pub fn of(module: &Module) -> Self {
+        let mut ports = Vec::new();
+        module.ports().for_each(|p| flatten(
+            p.name().to_string(),
+            p.r#type(),
+            p.direction(),
+            &mut ports,
+        ));
+        let mut params: Vec<_> = if let Kind::External{params, ..} = module.kind() {
+            params.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        } else {
+            Default::default()
+        };
+        params.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        Self {module: module.name().clone(), ports, params}
+    }
+
+    /// Retrieve the name of the module described
+    pub fn module(&self) -> &Arc<str> {
+        &self.module
+    }
+
+    /// Retrieve the flattened ports of the module
+    ///
+    /// Ports are listed in the order of their declaration. Aggregate ports are
+    /// replaced by their ground type elements, in the order the elements
+    /// appear in the type.
+    pub fn ports(&self) -> &[PortEntry] {
+        self.ports.as_ref()
+    }
+
+    /// Retrieve the module's parameters, ordered by name
+    ///
+    /// Only external modules have parameters. For regular modules, this
+    /// function returns an empty slice.
+    pub fn parameters(&self) -> &[(Arc<str>, ParamValue)] {
+        self.params.as_ref()
+    }
+
+    /// Infer bus interfaces formed by the ports of the module
+    ///
+    /// See [bus::infer] for details.
+    pub fn bus_interfaces(&self) -> Vec<bus::BusInterface> {
+        bus::infer(self)
+    }
+
+    /// Retrieve a value serializing the manifest as JSON
+    ///
+    /// The manifest is serialized as an object holding the name of the module
+    /// under the key `module`, the list of ports under the key `ports`, the
+    /// parameters under the key `parameters` and the inferred bus interfaces
+    /// under the key `interfaces`. Each port is an object with the keys
+    /// `name`, `direction`, `type`, `width` and `class`. Unknown widths are
+    /// serialized as `null`. Parameters are serialized as an object mapping
+    /// their names to their values. Each interface is an object with the keys
+    /// `name`, `kind`, `role` and `signals`, the latter holding a list of
+    /// objects with the keys `signal` and `port`.
+    pub fn json(&self) -> Json<'_> {
+        Json(self)
+    }
+
+    /// Retrieve a value serializing the manifest as IP-XACT component
+    ///
+    /// See [ipxact::IpXact] for details.
+    pub fn ip_xact(&self) -> ipxact::IpXact<'_> {
+        ipxact::IpXact::new(self)
+    }
+}
+
+
+/// A single ground type port in a [Manifest]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortEntry {
+    name: String,
+    direction: Direction,
+    r#type: GroundType,
+    class: PortClass,
+}
+
+impl PortEntry {
+    /// Retrieve the flattened name of the port
+    ///
+    /// The name is formed from the name of the original port and the names
+    /// of fields or indices of vector elements, separated by underscores, as
+    /// done when lowering aggregate types.
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    /// Retrieve the direction of the port
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Retrieve the type of the port
+    pub fn r#type(&self) -> &GroundType {
+        &self.r#type
+    }
+
+    /// Retrieve the width of the port
+    pub fn width(&self) -> BitWidth {
+        self.r#type.width()
+    }
+
+    /// Retrieve the classification of the port
+    pub fn class(&self) -> PortClass {
+        self.class
+    }
+}
+
+
+/// Classification of a port
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PortClass {
+    Clock,
+    Reset(ResetKind),
+    Data,
+}
+
+impl PortClass {
+    /// Classify a port with the given name and type
+    ///
+    /// Ports of type `Clock` are classified as clocks and ports of type
+    /// `Reset` or `AsyncReset` as resets. In addition, `UInt<1>` ports named
+    /// `reset` or `rst`, optionally with a prefix separated by an underscore,
+    /// are classified as synchronous resets, since generators frequently emit
+    /// resets this way.
+    pub fn classify(name: &str, r#type: &GroundType) -> Self {
+        let base = name.rsplit('_').next().unwrap_or(name);
+        match r#type {
+            GroundType::Clock                                   => Self::Clock,
+            GroundType::Reset(k)                                => Self::Reset(*k),
+            GroundType::UInt(Some(1)) if base == "reset" || base == "rst"
+                                                                => Self::Reset(ResetKind::Regular),
+            _                                                   => Self::Data,
+        }
+    }
+
+    /// Retrieve the keyword associated with the class
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Clock                     => "clock",
+            Self::Reset(ResetKind::Regular) => "reset",
+            Self::Reset(ResetKind::Async)   => "async-reset",
+            Self::Data                      => "data",
+        }
+    }
+}
+
+impl fmt::Display for PortClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+
+/// JSON serialization of a [Manifest]
+///
+/// The `Display` impl of this type emits a single line of JSON.
+#[derive(Copy, Clone, Debug)]
+pub struct Json<'a>(&'a Manifest);
+
+impl fmt::Display for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{\"module\":{},\"ports\":[", JsonString(self.0.module()))?;
+        self.0.ports().iter().enumerate().try_for_each(|(i, p)| {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(
+                f,
+                "{{\"name\":{},\"direction\":\"{}\",\"type\":{},\"width\":",
+                JsonString(p.name()),
+                p.direction().keyword(),
+                JsonString(&p.r#type().to_string()),
+            )?;
+            match p.width() {
+                Some(w) => write!(f, "{}", w)?,
+                None    => write!(f, "null")?,
+            }
+            write!(f, ",\"class\":\"{}\"}}", p.class())
+        })?;
+        write!(f, "],\"parameters\":{{")?;
+        self.0.parameters().iter().enumerate().try_for_each(|(i, (n, v))| {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}:", JsonString(n))?;
+            match v {
+                ParamValue::Int(v)                      => write!(f, "{}", v),
+                ParamValue::Double(v) if v.is_finite()  => write!(f, "{:?}", v),
+                ParamValue::Double(_)                   => write!(f, "null"),
+                ParamValue::String(v)                   => write!(f, "{}", JsonString(v)),
+                ParamValue::RawVerilog(v)               => write!(f, "{}", JsonString(v)),
+            }
+        })?;
+        write!(f, "}},\"interfaces\":[")?;
+        self.0.bus_interfaces().iter().enumerate().try_for_each(|(i, b)| {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(
+                f,
+                "{{\"name\":{},\"kind\":\"{}\",\"role\":\"{}\",\"signals\":[",
+                JsonString(b.name()),
+                b.kind(),
+                b.role(),
+            )?;
+            b.signals().iter().enumerate().try_for_each(|(i, (s, p))| {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{{\"signal\":\"{}\",\"port\":{}}}", s, JsonString(p))
+            })?;
+            write!(f, "]}}")
+        })?;
+        write!(f, "]}}")
+    }
+}
+
+
+/// Flatten a port of the given type into ground type ports
+fn flatten(name: String, r#type: &Type, direction: Direction, res: &mut Vec<PortEntry>) {
+    match r#type {
+        Type::GroundType(g) => res.push(PortEntry {
+            class: PortClass::classify(&name, g),
+            name,
+            direction,
+            r#type: *g,
+        }),
+        Type::Vector(t, w)  => (0..*w).for_each(|i| flatten(format!("{}_{}", name, i), t, direction, res)),
+        Type::Bundle(v)     => v.iter().for_each(|f| {
+            let direction = match (direction, f.orientation()) {
+                (d,                 Orientation::Normal)    => d,
+                (Direction::Input,  Orientation::Flipped)   => Direction::Output,
+                (Direction::Output, Orientation::Flipped)   => Direction::Input,
+            };
+            flatten(format!("{}_{}", name, f.name()), f.r#type(), direction, res)
+        }),
+        Type::Probe(..)     => (),
+        Type::Enum(..)      => (),
+        Type::Alias(_, t)   => flatten(name, t, direction, res),
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Inference of bus interfaces
+//!
+//! Ports of a module often form bus interfaces, e.g. a valid/ready handshake
+//! with an associated payload. This module provides a heuristic grouping of
+//! the ports of a [Manifest] into [BusInterface]s based on their names. Since
+//! names in a manifest are derived from the bundle structure of the original
+//! ports, this covers both flat ports following a naming scheme and bundles
+//! such as Chisel's `Decoupled`.
+//!
+//! Currently, the following kinds of interfaces are recognized:
+//!
+//! * AXI4-Lite interfaces, with ports named after the AXI signals (e.g.
+//!   `s_axi_awvalid` or `axi_aw_valid`), optionally with payloads in a `bits`
+//!   bundle (e.g. `axi_aw_bits_addr`).
+//! * Decoupled interfaces consisting of ports `*_valid` and `*_ready` and an
+//!   optional payload `*_bits`.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::manifest::bus::{Kind, Role};
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    output io: {flip in: {flip ready: UInt<1>, valid: UInt<1>, bits: UInt<8>}}\n",
+//!     "    io.in.ready <= UInt<1>(1)\n",
+//! )).unwrap();
+//! let interfaces = circuit.interface_manifest().bus_interfaces();
+//! assert_eq!(interfaces.len(), 1);
+//! assert_eq!(interfaces[0].name(), "io_in");
+//! assert_eq!(interfaces[0].kind(), Kind::Decoupled);
+//! assert_eq!(interfaces[0].role(), Role::Subordinate);
+//! assert_eq!(interfaces[0].signal("bits"), Some("io_in_bits"));
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::module::Direction;
+use crate::types::GroundType;
+
+use super::{Manifest, PortEntry};
+
+
+/// Infer bus interfaces from the ports in the given manifest
+///
+/// Each port is part of at most one interface. AXI4-Lite interfaces take
+/// precedence over decoupled interfaces. They are ordered by name and listed
+/// before any decoupled interface, which are listed in the order of their
+/// `valid` ports.
+pub fn infer(manifest: &Manifest) -> Vec<BusInterface> {
+    let mut res = axi_lite(manifest.ports());
+
+    let used: HashSet<_> = res.iter().flat_map(|i| i.signals.iter().map(|(_, p)| p.as_str())).collect();
+    let ports: Vec<_> = manifest.ports().iter().filter(|p| !used.contains(p.name())).cloned().collect();
+    res.extend(decoupled(&ports));
+    res
+}
+
+
+/// A group of ports forming a bus interface
+#[derive(Clone, Debug, PartialEq)]
+pub struct BusInterface {
+    name: String,
+    kind: Kind,
+    role: Role,
+    signals: Vec<(&'static str, String)>,
+}
+
+impl BusInterface {
+    /// Retrieve the name of the interface
+    ///
+    /// The name is the common prefix of the interface's ports, without any
+    /// trailing underscore.
+    pub fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    /// Retrieve the kind of the interface
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Retrieve the role of the module in the interface
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Retrieve the signals of the interface
+    ///
+    /// This function returns pairs of signal names and the names of the
+    /// ports implementing them. Signal names are lower case, e.g. `valid` or
+    /// `awaddr`. A signal may be implemented by multiple ports, e.g. the
+    /// `bits` of a decoupled interface if the payload is a bundle.
+    pub fn signals(&self) -> &[(&'static str, String)] {
+        self.signals.as_ref()
+    }
+
+    /// Retrieve the name of the first port implementing the given signal
+    pub fn signal(&self, signal: &str) -> Option<&str> {
+        self.signals.iter().find(|(s, _)| *s == signal).map(|(_, p)| p.as_str())
+    }
+}
+
+
+/// Kind of a [BusInterface]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// AXI4-Lite interface
+    AxiLite,
+    /// Valid/ready handshake with an optional payload
+    Decoupled,
+}
+
+impl Kind {
+    /// Retrieve the keyword associated with the kind
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::AxiLite   => "axi-lite",
+            Self::Decoupled => "decoupled",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+
+/// Role of a module in a [BusInterface]
+///
+/// For decoupled interfaces, the manager is the side driving `valid`. For
+/// AXI4-Lite interfaces, it is the side issuing requests, i.e. driving
+/// `awvalid` and `arvalid`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    Manager,
+    Subordinate,
+}
+
+impl Role {
+    /// Determine the role from the direction of a signal driven by the manager
+    fn of(direction: Direction) -> Self {
+        match direction {
+            Direction::Output   => Self::Manager,
+            Direction::Input    => Self::Subordinate,
+        }
+    }
+
+    /// Retrieve the keyword associated with the role
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Manager       => "manager",
+            Self::Subordinate   => "subordinate",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+
+/// Signals of an AXI4-Lite interface
+///
+/// Each entry consists of the channel, the name of the signal within the
+/// channel, the full name of the signal, whether the signal is driven by the
+/// manager and whether the signal is required.
+const AXI_LITE_SIGNALS: [(&str, &str, &str, bool, bool); 19] = [
+    ("aw",  "valid",    "awvalid",  true,   true),
+    ("aw",  "ready",    "awready",  false,  true),
+    ("aw",  "addr",     "awaddr",   true,   true),
+    ("aw",  "prot",     "awprot",   true,   false),
+    ("w",   "valid",    "wvalid",   true,   true),
+    ("w",   "ready",    "wready",   false,  true),
+    ("w",   "data",     "wdata",    true,   true),
+    ("w",   "strb",     "wstrb",    true,   false),
+    ("b",   "valid",    "bvalid",   false,  true),
+    ("b",   "ready",    "bready",   true,   true),
+    ("b",   "resp",     "bresp",    false,  false),
+    ("ar",  "valid",    "arvalid",  true,   true),
+    ("ar",  "ready",    "arready",  false,  true),
+    ("ar",  "addr",     "araddr",   true,   true),
+    ("ar",  "prot",     "arprot",   true,   false),
+    ("r",   "valid",    "rvalid",   false,  true),
+    ("r",   "ready",    "rready",   true,   true),
+    ("r",   "data",     "rdata",    false,  true),
+    ("r",   "resp",     "rresp",    false,  false),
+];
+
+
+/// Infer AXI4-Lite interfaces
+fn axi_lite(ports: &[PortEntry]) -> Vec<BusInterface> {
+    // Group ports by the prefix preceding the name of an AXI signal
+    let mut groups: BTreeMap<&str, Vec<(&'static str, &PortEntry)>> = Default::default();
+    ports.iter().for_each(|p| AXI_LITE_SIGNALS.iter().for_each(|(c, s, full, ..)| {
+        let handshake = *s == "valid" || *s == "ready";
+        let suffixes = [full.to_string(), format!("{}_{}", c, s), format!("{}_bits_{}", c, s)];
+        if let Some(prefix) = suffixes
+            .iter()
+            .take(if handshake { 2 } else { 3 })
+            .find_map(|suffix| prefix_of(p.name(), suffix)) {
+            groups.entry(prefix).or_default().push((full, p))
+        }
+    }));
+
+    groups.into_iter().filter_map(|(prefix, found)| {
+        let lookup = |n: &str| found.iter().find(|(f, _)| *f == n).map(|(_, p)| *p);
+
+        let role = Role::of(lookup("awvalid")?.direction());
+        let manager = match role {
+            Role::Manager       => Direction::Output,
+            Role::Subordinate   => Direction::Input,
+        };
+
+        let mut signals = Vec::new();
+        for (_, s, full, by_manager, required) in AXI_LITE_SIGNALS.iter() {
+            let handshake = *s == "valid" || *s == "ready";
+            match lookup(full).filter(|p| (p.direction() == manager) == *by_manager && (is_bit(p) || !handshake)) {
+                Some(p)             => signals.push((*full, p.name().to_string())),
+                None if *required   => return None,
+                None                => (),
+            }
+        }
+
+        Some(BusInterface {name: prefix.to_string(), kind: Kind::AxiLite, role, signals})
+    }).collect()
+}
+
+
+/// Infer decoupled interfaces
+fn decoupled(ports: &[PortEntry]) -> Vec<BusInterface> {
+    ports.iter().filter_map(|valid| {
+        let prefix = valid.name().strip_suffix("_valid").filter(|_| is_bit(valid))?;
+        let ready_name = format!("{}_ready", prefix);
+        let ready = ports
+            .iter()
+            .find(|p| p.name() == ready_name && is_bit(p) && p.direction() != valid.direction())?;
+
+        let bits = format!("{}_bits", prefix);
+        let mut signals = vec![("valid", valid.name().to_string()), ("ready", ready.name().to_string())];
+        signals.extend(ports
+            .iter()
+            .filter(|p| p.direction() == valid.direction())
+            .filter(|p| p.name() == bits || p.name().strip_prefix(&bits).map(|s| s.starts_with('_')).unwrap_or(false))
+            .map(|p| ("bits", p.name().to_string())));
+
+        Some(BusInterface {
+            name: prefix.to_string(),
+            kind: Kind::Decoupled,
+            role: Role::of(valid.direction()),
+            signals,
+        })
+    }).collect()
+}
+
+
+/// Determine the prefix of a name ending in the given suffix
+///
+/// The suffix is matched case-insensitively and must either make up the
+/// entire name or be preceded by an underscore. The prefix is returned
+/// without the underscore.
+fn prefix_of<'a>(name: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = name.len().checked_sub(suffix.len())?;
+    if !name.is_char_boundary(split) || !name[split..].eq_ignore_ascii_case(suffix) {
+        return None
+    }
+
+    let prefix = &name[..split];
+    if prefix.is_empty() {
+        Some(prefix)
+    } else {
+        prefix.strip_suffix('_')
+    }
+}
+
+
+/// Check whether the port is a single bit port, as required for handshakes
+fn is_bit(port: &PortEntry) -> bool {
+    matches!(port.r#type(), GroundType::UInt(Some(1)) | GroundType::UInt(None))
+}
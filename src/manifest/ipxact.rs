@@ -0,0 +1,148 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! IP-XACT component descriptions
+//!
+//! SoC integration tools commonly import IP blocks via IP-XACT (IEEE 1685)
+//! component descriptions. This module provides [IpXact], which serializes a
+//! [Manifest] as such a description following the 2014 revision of the
+//! standard.
+//!
+//! # Example
+//!
+//! ```
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    input clock: Clock\n",
+//!     "    output data: UInt<8>\n",
+//!     "    data <= UInt<8>(0)\n",
+//! )).unwrap();
+//! let manifest = circuit.interface_manifest();
+//! let xml = manifest.ip_xact().with_vendor("example.com").to_string();
+//! assert!(xml.contains("<ipxact:vendor>example.com</ipxact:vendor>"));
+//! assert!(xml.contains("<ipxact:name>data</ipxact:name>"));
+//! ```
+
+use std::fmt;
+
+use crate::display::XmlString;
+use crate::module::{Direction, ParamValue};
+use crate::types::GroundType;
+
+use super::Manifest;
+
+
+/// Namespace of the IP-XACT 2014 schema
+pub const NAMESPACE: &str = "http://www.accellera.org/XMLSchema/IPXACT/1685-2014";
+
+
+/// IP-XACT serialization of a [Manifest]
+///
+/// The `Display` impl of this type emits an XML document holding a single
+/// `component`. The component's name is the name of the module described by
+/// the manifest. Its vendor, library and version may be set via the builder
+/// functions and default to `unknown`, `unknown` and `1.0` respectively.
+///
+/// Every port in the manifest is described as a wire port. Ports with a known
+/// width other than one are given a vector with the range `[width-1:0]`.
+/// Analog ports are described as bidirectional ports. Parameters are emitted
+/// as user-resolved component parameters.
+#[derive(Copy, Clone, Debug)]
+pub struct IpXact<'a> {
+    manifest: &'a Manifest,
+    vendor: &'a str,
+    library: &'a str,
+    version: &'a str,
+}
+
+impl<'a> IpXact<'a> {
+    /// Create a new IP-XACT serialization of the given manifest
+    pub fn new(manifest: &'a Manifest) -> Self {
+        Self {manifest, vendor: "unknown", library: "unknown", version: "1.0"}
+    }
+
+    /// Set the vendor of the component
+    pub fn with_vendor(self, vendor: &'a str) -> Self {
+        Self {vendor, ..self}
+    }
+
+    /// Set the library of the component
+    pub fn with_library(self, library: &'a str) -> Self {
+        Self {library, ..self}
+    }
+
+    /// Set the version of the component
+    pub fn with_version(self, version: &'a str) -> Self {
+        Self {version, ..self}
+    }
+}
+
+impl fmt::Display for IpXact<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(f, "<ipxact:component xmlns:ipxact=\"{}\">", NAMESPACE)?;
+        writeln!(f, "  <ipxact:vendor>{}</ipxact:vendor>", XmlString(self.vendor))?;
+        writeln!(f, "  <ipxact:library>{}</ipxact:library>", XmlString(self.library))?;
+        writeln!(f, "  <ipxact:name>{}</ipxact:name>", XmlString(self.manifest.module()))?;
+        writeln!(f, "  <ipxact:version>{}</ipxact:version>", XmlString(self.version))?;
+
+        let ports = self.manifest.ports();
+        if !ports.is_empty() {
+            writeln!(f, "  <ipxact:model>")?;
+            writeln!(f, "    <ipxact:ports>")?;
+            ports.iter().try_for_each(|p| {
+                let direction = match (p.r#type(), p.direction()) {
+                    (GroundType::Analog(_), _)  => "inout",
+                    (_, Direction::Input)       => "in",
+                    (_, Direction::Output)      => "out",
+                };
+
+                writeln!(f, "      <ipxact:port>")?;
+                writeln!(f, "        <ipxact:name>{}</ipxact:name>", XmlString(p.name()))?;
+                writeln!(f, "        <ipxact:wire>")?;
+                writeln!(f, "          <ipxact:direction>{}</ipxact:direction>", direction)?;
+                match p.width() {
+                    Some(w) if w > 1 => {
+                        writeln!(f, "          <ipxact:vectors>")?;
+                        writeln!(f, "            <ipxact:vector>")?;
+                        writeln!(f, "              <ipxact:left>{}</ipxact:left>", w - 1)?;
+                        writeln!(f, "              <ipxact:right>0</ipxact:right>")?;
+                        writeln!(f, "            </ipxact:vector>")?;
+                        writeln!(f, "          </ipxact:vectors>")?;
+                    },
+                    _ => (),
+                }
+                writeln!(f, "        </ipxact:wire>")?;
+                writeln!(f, "      </ipxact:port>")
+            })?;
+            writeln!(f, "    </ipxact:ports>")?;
+            writeln!(f, "  </ipxact:model>")?;
+        }
+
+        let params = self.manifest.parameters();
+        if !params.is_empty() {
+            writeln!(f, "  <ipxact:parameters>")?;
+            params.iter().try_for_each(|(n, v)| {
+                let r#type = match v {
+                    ParamValue::Int(_)          => "longint",
+                    ParamValue::Double(_)       => "real",
+                    ParamValue::String(_)       => "string",
+                    ParamValue::RawVerilog(_)   => "string",
+                };
+
+                writeln!(
+                    f,
+                    "    <ipxact:parameter parameterId=\"{}\" resolve=\"user\" type=\"{}\">",
+                    XmlString(n),
+                    r#type,
+                )?;
+                writeln!(f, "      <ipxact:name>{}</ipxact:name>", XmlString(n))?;
+                writeln!(f, "      <ipxact:value>{}</ipxact:value>", XmlString(&v.to_string()))?;
+                writeln!(f, "    </ipxact:parameter>")
+            })?;
+            writeln!(f, "  </ipxact:parameters>")?;
+        }
+
+        writeln!(f, "</ipxact:component>")
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to interface manifests
+
+use quickcheck::TestResult;
+
+use crate::module::Module;
+
+use super::*;
+
+
+#[quickcheck]
+fn manifest_widths(module: Module) -> TestResult {
+    // Vectors may be large and nested, so flattening may yield huge manifests
+    if module.ports().map(|p| leaf_count(p.r#type())).fold(0, u64::saturating_add) > 256 {
+        return TestResult::discard()
+    }
+
+    let manifest = Manifest::of(&module);
+    let total: Option<u64> = manifest.ports().iter().try_fold(0, |acc, p| p.width().map(|w| acc + u64::from(w)));
+    let expected = module.ports().try_fold(0, |acc, p| p.r#type().bit_width().and_then(|w| w.checked_add(acc)));
+    let json = manifest.json().to_string();
+    TestResult::from_bool(
+        (expected.is_none() || total == expected) &&
+        json.starts_with(&format!("{{\"module\":{},\"ports\":[", JsonString(module.name()))) &&
+        json.matches("\"class\":").count() == manifest.ports().len()
+    )
+}
+
+
+#[quickcheck]
+fn manifest_directions(name: crate::tests::Identifier, flipped: bool) -> bool {
+    use crate::module::Port;
+    use crate::types::BundleField;
+
+    let field = BundleField::new("a", GroundType::UInt(Some(4)));
+    let field = if flipped { field.flipped() } else { field };
+    let r#type: Type = vec![field, BundleField::new("reset", GroundType::UInt(Some(1)))].into();
+    let port = Arc::new(Port::new(name.to_string(), r#type, Direction::Input));
+    let module = Module::new(Arc::from("Foo"), vec![port], crate::module::Kind::empty_regular());
+
+    let manifest = Manifest::of(&module);
+    let expected = if flipped { Direction::Output } else { Direction::Input };
+    match manifest.ports() {
+        [a, r] => a.name() == format!("{}_a", name) && a.direction() == expected && a.class() == PortClass::Data &&
+            r.direction() == Direction::Input && r.class() == PortClass::Reset(ResetKind::Regular),
+        _ => false,
+    }
+}
+
+
+/// Compute the number of ground type elements in a type
+///
+/// Elements of vectors are counted as at least one element, since traversing
+/// a vector is costly even if its elements are empty bundles.
+pub fn leaf_count(r#type: &Type) -> u64 {
+    match r#type {
+        Type::GroundType(_) => 1,
+        Type::Enum(_)       => 1,
+        Type::Vector(t, w)  => leaf_count(t).max(1).saturating_mul((*w).into()),
+        Type::Bundle(v)     => v.iter().map(|f| leaf_count(f.r#type())).fold(0, u64::saturating_add),
+        Type::Probe(..)     => 0,
+        Type::Alias(_, t)   => leaf_count(t),
+    }
+}
+
+
+#[quickcheck]
+fn manifest_ip_xact(module: Module) -> TestResult {
+    if module.ports().map(|p| leaf_count(p.r#type())).fold(0, u64::saturating_add) > 256 {
+        return TestResult::discard()
+    }
+
+    let manifest = Manifest::of(&module);
+    let xml = manifest.ip_xact().to_string();
+    TestResult::from_bool(
+        xml.contains(&format!("<ipxact:name>{}</ipxact:name>", module.name())) &&
+        xml.matches("<ipxact:port>").count() == manifest.ports().len() &&
+        xml.matches("<ipxact:parameter ").count() == manifest.parameters().len() &&
+        xml.trim_end().ends_with("</ipxact:component>")
+    )
+}
+
+
+#[quickcheck]
+fn bus_axi_lite(prefix: crate::tests::Identifier, manager: bool, chisel: bool) -> bool {
+    use crate::module::Port;
+
+    let dir = |by_manager: bool| if by_manager == manager { Direction::Output } else { Direction::Input };
+    let signals = [
+        ("aw", "valid", 1, true), ("aw", "ready", 1, false), ("aw", "addr", 32, true),
+        ("w", "valid", 1, true), ("w", "ready", 1, false), ("w", "data", 32, true), ("w", "strb", 4, true),
+        ("b", "valid", 1, false), ("b", "ready", 1, true), ("b", "resp", 2, false),
+        ("ar", "valid", 1, true), ("ar", "ready", 1, false), ("ar", "addr", 32, true),
+        ("r", "valid", 1, false), ("r", "ready", 1, true), ("r", "data", 32, false),
+    ];
+    let ports = signals.iter().map(|(c, s, w, m)| {
+        let name = match (chisel, *s) {
+            (false, _)                  => format!("{}_{}{}", prefix, c, s),
+            (true, "valid" | "ready")   => format!("{}_{}_{}", prefix, c, s),
+            (true, _)                   => format!("{}_{}_bits_{}", prefix, c, s),
+        };
+        Arc::new(Port::new(name, GroundType::UInt(Some(*w)).into(), dir(*m)))
+    }).collect::<Vec<_>>();
+    let module = Module::new(Arc::from("Foo"), ports, crate::module::Kind::empty_regular());
+
+    let role = if manager { bus::Role::Manager } else { bus::Role::Subordinate };
+    match Manifest::of(&module).bus_interfaces().as_slice() {
+        [i] => i.name() == prefix.as_ref() && i.kind() == bus::Kind::AxiLite && i.role() == role &&
+            i.signals().len() == signals.len() && i.signal("wstrb").is_some(),
+        _ => false,
+    }
+}
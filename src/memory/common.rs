@@ -4,7 +4,7 @@
 
 use std::fmt;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 
@@ -42,7 +42,7 @@ impl fmt::Display for ReadUnderWrite {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for ReadUnderWrite {
     fn arbitrary(g: &mut Gen) -> Self {
         g.choose(&[Self::Old, Self::New, Self::Undefined]).unwrap().clone()
@@ -54,7 +54,7 @@ impl Arbitrary for ReadUnderWrite {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PortDir {Read, Write, ReadWrite}
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for PortDir {
     fn arbitrary(g: &mut Gen) -> Self {
         g.choose(&[Self::Read, Self::Write, Self::ReadWrite]).unwrap().clone()
@@ -5,14 +5,14 @@
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use crate::expr;
 use crate::named::Named;
 use crate::types;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use crate::tests::Identifier;
 
 use super::common;
@@ -64,6 +64,15 @@ impl Memory {
         self.depth
     }
 
+    /// Retrieve the number of bits held by the memory
+    ///
+    /// The number of bits is the width of the data type multiplied by the
+    /// depth. If the width of the data type is not known, this function
+    /// returns `None`.
+    pub fn bits(&self) -> Option<u64> {
+        self.data_type.bit_width().and_then(|b| b.checked_mul(self.depth))
+    }
+
     /// Add a port
     ///
     /// This function appends a the given port to the list of ports.
@@ -85,6 +94,42 @@ impl Memory {
         self.ports.iter()
     }
 
+    /// Retrieve the port with the given name
+    pub fn port_by_name(&self, name: &str) -> Option<&Port> {
+        self.ports.iter().find(|p| p.name.as_ref() == name)
+    }
+
+    /// Retrieve accessors for the fields of the port with the given name
+    ///
+    /// The accessors yield expressions referring to the fields of the port
+    /// via the given `reference` to this memory, e.g. `m.r.addr` for a port
+    /// `r` of a memory `m`. If the memory does not have a port with the given
+    /// name, this function returns `None`.
+    pub fn port_access<R: expr::Reference + Clone>(&self, reference: R, port: &str) -> Option<PortAccess<R>> {
+        self.port_by_name(port).map(|p| PortAccess {
+            port: Arc::new(expr::Expression::SubField{
+                base: Arc::new(expr::Expression::Reference(reference)),
+                index: p.name.clone(),
+            }),
+            dir: p.dir,
+        })
+    }
+
+    /// Create a copy of this memory with the given name
+    pub fn with_name(self, name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), ..self}
+    }
+
+    /// Create a copy of this memory with the given element type
+    pub fn with_data_type(self, data_type: impl Into<types::Type>) -> Self {
+        Self {data_type: data_type.into(), ..self}
+    }
+
+    /// Create a copy of this memory with the given depth
+    pub fn with_depth(self, depth: Depth) -> Self {
+        Self {depth, ..self}
+    }
+
     /// Set the read latency
     pub fn with_read_latency(self, latency: Latency) -> Self {
         Self {read_latency: latency, ..self}
@@ -105,6 +150,13 @@ impl Memory {
         self.write_latency
     }
 
+    /// Check whether the memory has synchronous read ports
+    ///
+    /// A memory is read synchronously if its read latency is at least one.
+    pub fn is_sync_read(&self) -> bool {
+        self.read_latency > 0
+    }
+
     /// Set the read-under-write behaviour
     pub fn with_read_under_write(self, behaviour: common::ReadUnderWrite) -> Self {
         Self {read_under_write: behaviour, ..self}
@@ -145,8 +197,11 @@ impl types::Typed for Memory {
         fn mask(t: &Type) -> Type {
             match t {
                 Type::GroundType(_) => GT::UInt(Some(1)).into(),
+                Type::Enum(_)       => GT::UInt(Some(1)).into(),
                 Type::Vector(v, w)  => Type::Vector(Arc::new(mask(v)), *w),
                 Type::Bundle(v)     => v.iter().map(|f| f.clone().with_type(mask(f.r#type()))).collect(),
+                Type::Probe(t, _)   => mask(t),
+                Type::Alias(_, t)   => mask(t),
             }
         }
 
@@ -185,7 +240,7 @@ impl types::Typed for Memory {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Memory {
     fn arbitrary(g: &mut Gen) -> Self {
         let mut res = Self::new(Identifier::arbitrary(g), types::Type::arbitrary(g), Arbitrary::arbitrary(g));
@@ -216,6 +271,80 @@ impl Arbitrary for Memory {
 }
 
 
+/// Accessors for the fields of a memory port
+///
+/// Instances of this type are created via [Memory::port_access]. Accessors
+/// for fields not present in the port, e.g. `mask` for a read port, yield
+/// `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortAccess<R: expr::Reference> {
+    port: Arc<expr::Expression<R>>,
+    dir: common::PortDir,
+}
+
+impl<R: expr::Reference> PortAccess<R> {
+    /// Retrieve the expression referring to the port as a whole
+    pub fn port(&self) -> &expr::Expression<R> {
+        self.port.as_ref()
+    }
+
+    /// Retrieve the `addr` field
+    pub fn addr(&self) -> expr::Expression<R> {
+        self.field("addr")
+    }
+
+    /// Retrieve the `en` field
+    pub fn en(&self) -> expr::Expression<R> {
+        self.field("en")
+    }
+
+    /// Retrieve the `clk` field
+    pub fn clk(&self) -> expr::Expression<R> {
+        self.field("clk")
+    }
+
+    /// Retrieve the `data` field of a read or write port
+    pub fn data(&self) -> Option<expr::Expression<R>> {
+        self.field_if("data", self.dir != common::PortDir::ReadWrite)
+    }
+
+    /// Retrieve the `mask` field of a write port
+    pub fn mask(&self) -> Option<expr::Expression<R>> {
+        self.field_if("mask", self.dir == common::PortDir::Write)
+    }
+
+    /// Retrieve the `wmode` field of a read-write port
+    pub fn wmode(&self) -> Option<expr::Expression<R>> {
+        self.field_if("wmode", self.dir == common::PortDir::ReadWrite)
+    }
+
+    /// Retrieve the `rdata` field of a read-write port
+    pub fn rdata(&self) -> Option<expr::Expression<R>> {
+        self.field_if("rdata", self.dir == common::PortDir::ReadWrite)
+    }
+
+    /// Retrieve the `wdata` field of a read-write port
+    pub fn wdata(&self) -> Option<expr::Expression<R>> {
+        self.field_if("wdata", self.dir == common::PortDir::ReadWrite)
+    }
+
+    /// Retrieve the `wmask` field of a read-write port
+    pub fn wmask(&self) -> Option<expr::Expression<R>> {
+        self.field_if("wmask", self.dir == common::PortDir::ReadWrite)
+    }
+
+    /// Retrieve the given field
+    fn field(&self, name: &str) -> expr::Expression<R> {
+        expr::Expression::SubField{base: self.port.clone(), index: name.into()}
+    }
+
+    /// Retrieve the given field if `present`
+    fn field_if(&self, name: &str, present: bool) -> Option<expr::Expression<R>> {
+        if present { Some(self.field(name)) } else { None }
+    }
+}
+
+
 /// Depth of a memory
 pub type Depth = u64;
 
@@ -242,7 +371,7 @@ impl fmt::Display for Port {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Port {
     fn arbitrary(g: &mut Gen) -> Self {
         Self {name: Identifier::arbitrary(g).into(), dir: Arbitrary::arbitrary(g)}
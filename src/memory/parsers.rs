@@ -9,7 +9,7 @@ use nom::sequence::tuple;
 use crate::expr::{Reference, parsers::expr};
 use crate::indentation::Indentation;
 use crate::parsers::{self, IResult, comma, decimal, identifier, kw, le, lp, op, rp, spaced};
-use crate::types::Type;
+use crate::types::{Type, TypeAlias};
 use crate::types::parsers::r#type;
 use crate::info::parse as info;
 
@@ -17,9 +17,12 @@ use super::{common, mem, simple};
 
 
 /// Parse a Memory
+///
+/// The data type may refer to any of the given `aliases`.
 pub fn memory<'i>(
     input: &'i str,
-    indentation: &'_ mut Indentation
+    indentation: &'_ mut Indentation,
+    aliases: &[TypeAlias],
 ) -> IResult<'i, (super::Memory, Option<String>)> {
     use nom::error::{ErrorKind as EK, ParseError};
 
@@ -29,7 +32,10 @@ pub fn memory<'i>(
     )(input)?;
 
     let mut indentation = indentation.sub();
-    let mut entries = iterator(input, map(tuple((indentation.parser(), entry, le)), |(_, e, _)| e));
+    let mut entries = iterator(
+        input,
+        map(tuple((indentation.parser(), |i| entry(i, aliases), le)), |(_, e, _)| e),
+    );
 
     let mut data_type: Option<Type> = Default::default();
     let mut depth: Option<mem::Depth> = Default::default();
@@ -65,7 +71,9 @@ pub fn memory<'i>(
 
 
 /// Parse a simple memory
-pub fn simple_mem(input: &str) -> IResult<simple::Memory> {
+///
+/// The type may refer to any of the given `aliases`.
+pub fn simple_mem<'i>(input: &'i str, aliases: &[TypeAlias]) -> IResult<'i, simple::Memory> {
     use nom::sequence::preceded;
 
     use simple::Kind;
@@ -77,12 +85,13 @@ pub fn simple_mem(input: &str) -> IResult<simple::Memory> {
         alt((value(K::Cmem, kw("cmem")), value(K::Smem, kw("smem")))),
         spaced(identifier),
         spaced(op(":")),
-        spaced(r#type),
+        spaced(|i| r#type(i, aliases)),
     ))(input)?;
 
     let (input, kind) = match k {
         K::Cmem => (input, Kind::Combinatory),
-        K::Smem => map(opt(preceded(comma, spaced(ruw))), |ruw| Kind::Sequential(ruw))(input)?,
+        // Older emitters omit the comma preceding the read-under-write behaviour
+        K::Smem => map(opt(preceded(opt(comma), spaced(ruw))), Kind::Sequential)(input)?,
     };
     Ok((input, super::simple::Memory::new(name, r#type, kind)))
 }
@@ -120,9 +129,12 @@ pub fn simple_mem_port<'i, R: Reference + Clone>(
 
 
 /// Parse a register definition
+///
+/// The register's type may refer to any of the given `aliases`.
 pub fn register<'i, R: Reference + Clone>(
     reference: impl Fn(&str) -> Option<R> + Copy,
-    input: &'i str
+    input: &'i str,
+    aliases: &[TypeAlias],
 ) -> IResult<'i, super::Register<R>> {
     use nom::Parser;
 
@@ -138,7 +150,7 @@ pub fn register<'i, R: Reference + Clone>(
             kw("reg"),
             spaced(identifier),
             spaced(op(":")),
-            spaced(r#type),
+            spaced(|i| r#type(i, aliases)),
             comma,
             &expr,
             opt(spaced(map(tuple((kw("with"), spaced(op(":")), spaced(reset))), |(.., r)| r)))
@@ -160,9 +172,9 @@ enum Entry {
 }
 
 
-fn entry<'i>(input: &'i str) -> IResult<'i, Entry> {
+fn entry<'i>(input: &'i str, aliases: &[TypeAlias]) -> IResult<'i, Entry> {
     alt((
-        map(tuple((kw("data-type"), arrow, spaced(r#type))), |(.., t)| Entry::DataType(t)),
+        map(tuple((kw("data-type"), arrow, spaced(|i| r#type(i, aliases)))), |(.., t)| Entry::DataType(t)),
         map(tuple((kw("depth"), arrow, spaced(decimal))), |(.., v)| Entry::Depth(v)),
         map(
             tuple((kw("reader"), arrow, spaced(identifier))),
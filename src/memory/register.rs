@@ -5,7 +5,7 @@
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use crate::expr;
@@ -32,6 +32,16 @@ impl<R: expr::Reference> Register<R> {
         Self {name: name.into(), r#type: r#type.into(), clock: clock.into(), reset: Default::default()}
     }
 
+    /// Create a copy of this register with the given name
+    pub fn with_name(self, name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), ..self}
+    }
+
+    /// Create a copy of this register with the given type
+    pub fn with_type(self, r#type: impl Into<types::Type>) -> Self {
+        Self {r#type: r#type.into(), ..self}
+    }
+
     /// Retrieve the clock driving the register
     pub fn clock(&self) -> &expr::Expression<R> {
         &self.clock
@@ -65,6 +75,19 @@ impl<R: expr::Reference> Register<R> {
     pub fn reset_value(&self) -> Option<&expr::Expression<R>> {
         self.reset.as_ref().map(|(_, val)| val)
     }
+
+    /// Create a register with mapped references
+    ///
+    /// This function creates a register with every reference in its clock and
+    /// reset expressions replaced by the result of the given function.
+    pub fn map_references<S: expr::Reference>(&self, f: &mut impl FnMut(&R) -> S) -> Register<S> {
+        Register {
+            name: self.name.clone(),
+            r#type: self.r#type.clone(),
+            clock: self.clock.map_references(f),
+            reset: self.reset.as_ref().map(|(s, v)| (s.map_references(f), v.map_references(f))),
+        }
+    }
 }
 
 impl<R: expr::Reference> expr::Reference for Register<R> {
@@ -101,7 +124,7 @@ impl<R: expr::Reference> fmt::Display for Register<R> {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl<R: expr::tests::TypedRef + Clone + 'static> Arbitrary for Register<R> {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
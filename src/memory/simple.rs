@@ -5,7 +5,7 @@
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use crate::expr;
@@ -31,10 +31,36 @@ impl Memory {
         Self {name: name.into(), data_type: data_type.into(), kind}
     }
 
+    /// Retrieve the memory's data type
+    pub fn data_type(&self) -> &types::Type {
+        &self.data_type
+    }
+
     /// Retrieve the kind of simple memory
     pub fn kind(&self) -> Kind {
         self.kind
     }
+
+    /// Create a copy of this memory with the given kind
+    pub fn with_kind(self, kind: Kind) -> Self {
+        Self {kind, ..self}
+    }
+
+    /// Check whether the memory is read synchronously
+    ///
+    /// This is the case for sequential memories, i.e. `smem`s.
+    pub fn is_sync_read(&self) -> bool {
+        matches!(self.kind, Kind::Sequential(_))
+    }
+
+    /// Retrieve the number of bits held by the memory
+    ///
+    /// A simple memory's type is usually a vector of elements, hence the
+    /// number of bits is the width of its type. If that width is not known,
+    /// this function returns `None`.
+    pub fn bits(&self) -> Option<u64> {
+        self.data_type.bit_width()
+    }
 }
 
 impl Named for Memory {
@@ -66,7 +92,7 @@ impl fmt::Display for Memory {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Memory {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -107,9 +133,21 @@ impl Kind {
             Self::Sequential(_) => "smem",
         }
     }
+
+    /// Retrieve the effective read-under-write behaviour
+    ///
+    /// For sequential memories without an explicit read-under-write
+    /// behaviour, this function returns [ReadUnderWrite::Undefined]. For
+    /// combinatory memories, it returns `None`.
+    pub fn read_under_write(&self) -> Option<ReadUnderWrite> {
+        match self {
+            Self::Combinatory   => None,
+            Self::Sequential(r) => Some(r.unwrap_or(ReadUnderWrite::Undefined)),
+        }
+    }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Kind {
     fn arbitrary(g: &mut Gen) -> Self {
         let opts: [&dyn Fn(&mut Gen) -> Self; 2] = [
@@ -150,6 +188,11 @@ impl<R: expr::Reference> Port<R> {
         Self {name: name.into(), mem, dir, addr, clock}
     }
 
+    /// Create a copy of this port with the given name
+    pub fn with_name(self, name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), ..self}
+    }
+
     /// Retrieve the memory associated with this port
     pub fn memory(&self) -> &Arc<Memory> {
         &self.mem
@@ -169,6 +212,20 @@ impl<R: expr::Reference> Port<R> {
     pub fn clock(&self) -> &expr::Expression<R> {
         &self.clock
     }
+
+    /// Create a port with mapped references
+    ///
+    /// This function creates a port with every reference in its address and
+    /// clock expressions replaced by the result of the given function.
+    pub fn map_references<S: expr::Reference>(&self, f: &mut impl FnMut(&R) -> S) -> Port<S> {
+        Port {
+            name: self.name.clone(),
+            mem: self.mem.clone(),
+            dir: self.dir,
+            addr: self.addr.map_references(f),
+            clock: self.clock.map_references(f),
+        }
+    }
 }
 
 impl<R: expr::Reference> types::Typed for Port<R> {
@@ -222,7 +279,7 @@ impl<R: expr::Reference> fmt::Display for Port<R> {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl<R: expr::tests::TypedRef + Clone + 'static> Arbitrary for Port<R> {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -19,7 +19,7 @@ fn parse_memory(
     let mut s: String = Default::default();
     MemoryDecl(&original, Default::default()).fmt(&mut base, &mut s).map_err(|e| e.to_string())?;
 
-    let res = all_consuming(|i| parsers::memory(i, &mut base))(&s)
+    let res = all_consuming(|i| parsers::memory(i, &mut base, &[]))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of((original, None), parsed))
         .map_err(|e| e.to_string());
@@ -31,7 +31,23 @@ fn parse_memory(
 fn parse_simple_mem(original: simple::Memory) -> Result<Equivalence<simple::Memory>, String> {
     let s = original.to_string();
 
-    let res = all_consuming(parsers::simple_mem)(&s)
+    let res = all_consuming(|i| parsers::simple_mem(i, &[]))(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn parse_simple_mem_without_comma(original: simple::Memory) -> Result<Equivalence<simple::Memory>, String> {
+    let mut s = original.to_string();
+    if let simple::Kind::Sequential(Some(_)) = original.kind() {
+        let pos = s.rfind(", ").expect("no comma before read-under-write");
+        s.replace_range(pos..pos + 2, " ");
+    }
+
+    let res = all_consuming(|i| parsers::simple_mem(i, &[]))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of(original, parsed))
         .map_err(|e| e.to_string());
@@ -64,10 +80,39 @@ fn parse_simple_mem_port(
 #[quickcheck]
 fn parse_register(original: Register<Identifier>) -> Result<Equivalence<Register<Identifier>>, String> {
     let s = original.to_string();
-    let res = all_consuming(|i| parsers::register(|s| Some(s.into()), i))(&s)
+    let res = all_consuming(|i| parsers::register(|s| Some(s.into()), i, &[]))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of(original, parsed))
         .map_err(|e| e.to_string());
     res
 }
 
+
+
+#[quickcheck]
+fn mem_port_access(original: Memory) -> bool {
+    use std::sync::Arc;
+
+    use crate::stmt::Entity;
+    use crate::types::Typed;
+
+    let entity = Arc::new(Entity::Memory(original.clone()));
+    let r#type = original.r#type().expect("Memories are always typed");
+    original.ports().all(|p| {
+        let access = original.port_access(entity.clone(), &p.name).expect("Port not found");
+        let fields = [
+            Some(access.addr()),
+            Some(access.en()),
+            Some(access.clk()),
+            access.data(),
+            access.mask(),
+            access.wmode(),
+            access.rdata(),
+            access.wdata(),
+            access.wmask(),
+        ];
+        let port_type = r#type.field(&p.name).expect("Port missing in type").r#type();
+        fields.iter().flatten().count() == port_type.fields().map(Iterator::count).unwrap_or(0) &&
+            fields.iter().flatten().all(|e| e.r#type().is_ok())
+    })
+}
@@ -4,6 +4,11 @@
 
 pub(crate) mod parsers;
 
+pub mod external;
+#[cfg(any(test, feature = "generics"))]
+pub mod generic;
+pub mod interface;
+
 #[cfg(test)]
 mod tests;
 
@@ -11,9 +16,10 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
+use crate::error::{ParamError, PortError};
 use crate::expr;
 use crate::indentation::{DisplayIndented, Indentation};
 use crate::info;
@@ -21,7 +27,8 @@ use crate::named::Named;
 use crate::stmt::Statement;
 use crate::types::{self, Type};
 
-pub use parsers::Modules;
+pub use interface::{InterfaceDiff, ModuleInterface, PortChange, interface_diff};
+pub use parsers::{Modules, ReadModules};
 
 
 /// FIRRTL `module` or `extmodule`
@@ -34,13 +41,31 @@ pub struct Module {
     name: Arc<str>,
     ports: Vec<Arc<Port>>,
     kind: Kind,
+    layers: Vec<Arc<str>>,
     info: Option<String>,
 }
 
 impl Module {
     /// Create a new module
     pub fn new(name: Arc<str>, ports: impl IntoIterator<Item = Arc<Port>>, kind: Kind) -> Self {
-        Self {name, ports: ports.into_iter().collect(), kind, info: Default::default()}
+        Self {name, ports: ports.into_iter().collect(), kind, layers: Default::default(), info: Default::default()}
+    }
+
+    /// Create a copy of this module with the given layers enabled
+    ///
+    /// Layers are given by their [path](crate::circuit::layer), e.g. `A.B`.
+    /// The layers replace any layers previously enabled.
+    pub fn with_enabled_layers(self, layers: impl IntoIterator<Item = Arc<str>>) -> Self {
+        Self {layers: layers.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the paths of the layers enabled in this module
+    ///
+    /// In a module with enabled layers, statements are allowed to refer to
+    /// probes colored with these layers as if the statements were placed in
+    /// a `layerblock` of the respective layer.
+    pub fn enabled_layers(&self) -> &[Arc<str>] {
+        self.layers.as_ref()
     }
 
     /// Retrieve the module's I/O ports
@@ -48,11 +73,109 @@ impl Module {
         self.ports.iter()
     }
 
+    /// Retrieve the module's I/O ports, ordered by name
+    pub fn ports_sorted(&self) -> Vec<&Arc<Port>> {
+        crate::named::sorted_by_name(self.ports())
+    }
+
     /// Retrieve a specific port by its name
     pub fn port_by_name(&self, name: &impl AsRef<str>) -> Option<&Arc<Port>> {
         self.ports().find(|p| p.name.as_ref() == name.as_ref())
     }
 
+    /// Add a port to this module
+    ///
+    /// This function fails if the module already has a port with the same
+    /// name as `port`. The port will be added after all existing ports.
+    pub fn add_port(&mut self, port: Arc<Port>) -> Result<(), PortError> {
+        if self.port_by_name(port.name()).is_some() {
+            return Err(PortError::Duplicate(port.name().clone()))
+        }
+        self.ports.push(port);
+        Ok(())
+    }
+
+    /// Remove a port from this module
+    ///
+    /// This function removes the port with the given name and returns it. If
+    /// the port is referenced by any statement, the given `policy` decides
+    /// whether the removal fails or references are re-pointed to a stub.
+    pub fn remove_port(&mut self, name: impl AsRef<str>, policy: PortRemoval) -> Result<Arc<Port>, PortError> {
+        use crate::stmt::{Entity, Kind as SKind};
+
+        let index = self
+            .ports
+            .iter()
+            .position(|p| p.name_ref() == name.as_ref())
+            .ok_or_else(|| PortError::NotFound(name.as_ref().into()))?;
+        let port = self.ports[index].clone();
+
+        let stub = Arc::new(Entity::Wire{name: port.name().clone(), r#type: port.r#type().clone()});
+        let mut referenced = false;
+        let stmts = self.rewrite_port_refs(port.name(), |_| {
+            referenced = true;
+            stub.clone()
+        });
+
+        if referenced {
+            match policy {
+                PortRemoval::Strict => return Err(PortError::Referenced(port.name().clone())),
+                PortRemoval::Stub   => {
+                    let stmts = vec![
+                        SKind::Declaration(stub.clone()).into(),
+                        SKind::Invalidate(stub.into()).into(),
+                    ].into_iter().chain(stmts).collect();
+                    self.kind = Kind::Regular{stmts};
+                },
+            }
+        }
+
+        self.ports.remove(index);
+        Ok(port)
+    }
+
+    /// Change the type of a port of this module
+    ///
+    /// This function replaces the type of the port with the given name and
+    /// returns the original port. All references to the port are updated.
+    pub fn retype_port(&mut self, name: impl AsRef<str>, r#type: Type) -> Result<Arc<Port>, PortError> {
+        use crate::stmt::Entity;
+
+        let index = self
+            .ports
+            .iter()
+            .position(|p| p.name_ref() == name.as_ref())
+            .ok_or_else(|| PortError::NotFound(name.as_ref().into()))?;
+        let original = self.ports[index].clone();
+        let port = Arc::new(Port {r#type, ..original.as_ref().clone()});
+
+        let entity = Arc::new(Entity::Port(port.clone()));
+        let stmts = self.rewrite_port_refs(port.name(), |_| entity.clone());
+        if let Kind::Regular{..} = self.kind {
+            self.kind = Kind::Regular{stmts};
+        }
+
+        self.ports[index] = port;
+        Ok(original)
+    }
+
+    /// Rewrite this module's statements, replacing references to a port
+    ///
+    /// The function `f` will be called for references to the port with the
+    /// given name.
+    fn rewrite_port_refs(
+        &self,
+        name: &str,
+        mut f: impl FnMut(&Arc<crate::stmt::Entity>) -> Arc<crate::stmt::Entity>,
+    ) -> Vec<Statement> {
+        use crate::stmt::{Entity, rewrite::EntityRewriter};
+
+        EntityRewriter::new(|e| match e.as_ref() {
+            Entity::Port(p) if p.name_ref() == name => Some(f(e)),
+            _ => None,
+        }).stmts(self.statements())
+    }
+
     /// Retrieve the module kind
     pub fn kind(&self) -> &Kind {
         &self.kind
@@ -68,9 +191,484 @@ impl Module {
         self.kind.statements()
     }
 
+    /// Create a copy of this module with the given statements
+    ///
+    /// The statements replace those of this module. If this module is an
+    /// external module, it will become a regular module.
+    pub fn with_statements(self, stmts: impl IntoIterator<Item = Statement>) -> Self {
+        Self {kind: Kind::Regular{stmts: stmts.into_iter().collect()}, ..self}
+    }
+
+    /// Create a stub of this module
+    ///
+    /// The stub is a regular module with the same name, ports and info as this
+    /// module. Its body ignores all inputs and drives every output as
+    /// determined by the given `policy`. Flipped fields of output ports are
+    /// treated as inputs and flipped fields of input ports as outputs. Analog
+    /// signals are left unconnected.
+    pub fn stub(&self, policy: StubPolicy) -> Self {
+        use crate::stmt::Entity;
+
+        let mut stmts = Vec::new();
+        self.ports().for_each(|p| stub_sinks(
+            Arc::new(Entity::Port(p.clone())).into(),
+            p.r#type(),
+            p.direction(),
+            policy,
+            &mut stmts,
+        ));
+        Self {kind: Kind::Regular{stmts}, ..self.clone()}
+    }
+
+    /// Create a copy of this module with its statements mapped
+    ///
+    /// This function applies `f` to every top-level statement of this module
+    /// and replaces it with the result. Nested statements may be processed
+    /// via [Statement::map_children]. External modules are returned as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use firrtl_ast::module::Module;
+    /// use firrtl_ast::stmt::{Kind, Statement};
+    ///
+    /// fn drop_stops(stmt: &Statement) -> Statement {
+    ///     match stmt.kind() {
+    ///         Kind::Stop{..}  => Kind::Empty.into(),
+    ///         _               => stmt.map_children(drop_stops),
+    ///     }
+    /// }
+    ///
+    /// # let module = Module::new("Top".into(), Vec::new(), Default::default());
+    /// let module = module.map_statements(drop_stops);
+    /// ```
+    pub fn map_statements(&self, f: impl FnMut(&Statement) -> Statement) -> Self {
+        match self.kind() {
+            Kind::Regular{stmts} => Self {kind: Kind::Regular{stmts: stmts.iter().map(f).collect()}, ..self.clone()},
+            Kind::External{..}   => self.clone(),
+        }
+    }
+
+    /// Associate a resource with this module
+    ///
+    /// Resources can only be associated with external modules. If this module
+    /// is a regular module, this function returns the resource as an error.
+    pub fn add_resource(&mut self, resource: external::Resource) -> Result<(), external::Resource> {
+        match &mut self.kind {
+            Kind::External{resources, ..}  => {
+                resources.push(resource);
+                Ok(())
+            },
+            Kind::Regular{..}               => Err(resource),
+        }
+    }
+
+    /// Retrieve the resources associated with this module
+    ///
+    /// See [Kind::resources] for details.
+    pub fn resources(&self) -> &[external::Resource] {
+        self.kind.resources()
+    }
+
+    /// Retrieve the name of the external definition of this module
+    ///
+    /// For external modules, this function returns the explicitly specified
+    /// `defname` or the module's name if none was specified. For regular
+    /// modules, this function returns `None`.
+    pub fn defname(&self) -> Option<&Arc<str>> {
+        match self.kind() {
+            Kind::External{defname, ..} => Some(defname.as_ref().unwrap_or(&self.name)),
+            Kind::Regular{..}           => None,
+        }
+    }
+
+    /// Extract the interface of this module
+    ///
+    /// The interface holds the name, ports, kind and parameters of this module
+    /// as well as the names of instances and the modules they instantiate.
+    pub fn interface(&self) -> ModuleInterface {
+        self.into()
+    }
+
     /// Retrieve all modules referenced from this module via instantiations
+    ///
+    /// For [InstanceChoice]s, all modules which may be instantiated are
+    /// included.
     pub fn referenced_modules(&self) -> impl Iterator<Item = &Arc<Self>> {
-        self.statements().iter().flat_map(Statement::instantiations).map(Instance::module)
+        let stmts = self.statements();
+        stmts.iter()
+            .flat_map(Statement::instantiations)
+            .map(Instance::module)
+            .chain(stmts.iter().flat_map(Statement::instance_choices).flat_map(InstanceChoice::modules))
+    }
+
+    /// Create a copy of this module with entities renamed
+    ///
+    /// This function applies `f` to every entity declared in this module. If
+    /// `f` yields a name, the entity is renamed and all references to it are
+    /// updated accordingly. Ports are never renamed. No check is performed
+    /// regarding the uniqueness of the new names.
+    pub fn with_entities_renamed(&self, mut f: impl FnMut(&crate::stmt::Entity) -> Option<Arc<str>>) -> Self {
+        use crate::stmt::rewrite::EntityRewriter;
+
+        let kind = match self.kind() {
+            Kind::Regular{stmts} => {
+                let mut rewriter = EntityRewriter::new(|e| f(e)
+                    .and_then(|n| e.with_name(n))
+                    .map(Arc::new));
+                Kind::Regular{stmts: rewriter.stmts(stmts)}
+            },
+            kind => kind.clone(),
+        };
+        Self {kind, ..self.clone()}
+    }
+
+    /// Create a copy of this module with all type aliases expanded
+    ///
+    /// Uses of [type aliases](crate::types::alias) in the types of ports and
+    /// declared entities, including nested ones, are replaced by the aliased
+    /// types.
+    pub fn with_expanded_aliases(&self) -> Self {
+        use crate::memory::simple;
+        use crate::stmt::{Entity, Kind as StmtKind, rewrite::EntityRewriter};
+        use types::Typed;
+
+        fn simple_mems(stmt: &Statement) -> Statement {
+            match stmt.kind() {
+                StmtKind::SimpleMemDecl(mem) => stmt.with_kind(StmtKind::SimpleMemDecl(Arc::new(simple::Memory::new(
+                    mem.name().clone(),
+                    mem.data_type().expanded(),
+                    mem.kind(),
+                )))),
+                _ => stmt.map_children(simple_mems),
+            }
+        }
+
+        let ports: Vec<_> = self.ports
+            .iter()
+            .map(|p| Arc::new(Port {r#type: p.r#type.expanded(), ..p.as_ref().clone()}))
+            .collect();
+
+        let kind = match self.kind() {
+            Kind::Regular{stmts} => {
+                let mut rewriter = EntityRewriter::new(|e| match e.as_ref() {
+                    Entity::Port(p) => ports.iter().find(|n| n.name() == p.name()).cloned().map(Entity::Port),
+                    Entity::Wire{name, r#type} => Some(Entity::Wire{name: name.clone(), r#type: r#type.expanded()}),
+                    Entity::Register(reg) => reg.r#type().ok().map(|t| reg.clone().with_type(t.expanded()).into()),
+                    Entity::Memory(mem) => Some(mem.clone().with_data_type(mem.data_type().expanded()).into()),
+                    Entity::Binder{name, r#type} => Some(Entity::Binder{name: name.clone(), r#type: r#type.expanded()}),
+                    _ => None,
+                }.map(Arc::new));
+                Kind::Regular{stmts: rewriter.stmts(stmts).iter().map(simple_mems).collect()}
+            },
+            kind => kind.clone(),
+        };
+        Self {ports, kind, ..self.clone()}
+    }
+
+    /// Create a copy of this module with all info attributes mapped
+    ///
+    /// This function applies `f` to the info attributes of the module itself,
+    /// its ports and all of its statements, including nested ones, and
+    /// replaces the attributes with the result.
+    pub fn with_info_mapped(&self, f: &mut impl FnMut(Option<&str>) -> Option<String>) -> Self {
+        use crate::stmt::{Entity, Kind as StmtKind, rewrite::EntityRewriter};
+        use info::WithInfo;
+
+        fn map_stmts(stmts: &[Statement], f: &mut impl FnMut(Option<&str>) -> Option<String>) -> Vec<Statement> {
+            stmts.iter().map(|s| {
+                let stmt = match s.kind() {
+                    StmtKind::Conditional{cond, when, r#else} => s.with_kind(StmtKind::Conditional{
+                        cond: cond.clone(),
+                        when: map_stmts(when, f).into(),
+                        r#else: map_stmts(r#else, f).into(),
+                    }),
+                    StmtKind::LayerBlock{layer, stmts} => s.with_kind(StmtKind::LayerBlock{
+                        layer: layer.clone(),
+                        stmts: map_stmts(stmts, f).into(),
+                    }),
+                    _ => s.clone(),
+                };
+                let info = f(s.info());
+                stmt.with_info(info)
+            }).collect()
+        }
+
+        let ports: Vec<_> = self.ports.iter().map(|p| {
+            let info = f(p.info());
+            Arc::new(p.as_ref().clone().with_info(info))
+        }).collect();
+
+        let kind = match self.kind() {
+            Kind::Regular{stmts} => {
+                let mut rewriter = EntityRewriter::new(|e| match e.as_ref() {
+                    Entity::Port(p) => self.ports
+                        .iter()
+                        .zip(ports.iter())
+                        .find(|(old, _)| old.name() == p.name())
+                        .map(|(_, new)| Arc::new(Entity::Port(new.clone()))),
+                    _ => None,
+                });
+                Kind::Regular{stmts: map_stmts(&rewriter.stmts(stmts), f)}
+            },
+            kind => kind.clone(),
+        };
+        let info = f(self.info());
+        Self {name: self.name.clone(), ports, kind, layers: self.layers.clone(), info}
+    }
+
+    /// Estimate the number of flip-flops in this module
+    ///
+    /// This function sums up the widths of all registers declared in the
+    /// module, excluding instantiated modules. Registers of unknown width are
+    /// not accounted for. Use [Self::has_unknown_state_widths] for detecting
+    /// whether the estimate is incomplete.
+    pub fn estimated_flops(&self) -> u64 {
+        self.registers()
+            .filter_map(|r| types::Typed::r#type(r).ok().and_then(|t| t.bit_width()))
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Estimate the number of bits held in memories of this module
+    ///
+    /// This function sums up the bits of all memories, including `cmem`s and
+    /// `smem`s, declared in the module, excluding instantiated modules.
+    /// Memories of unknown width are not accounted for.
+    pub fn memory_bits(&self) -> u64 {
+        self.memories().flatten().fold(0, u64::saturating_add)
+    }
+
+    /// Check whether the state estimates of this module are incomplete
+    ///
+    /// This function returns `true` if any register or memory declared in
+    /// the module has a type with an unknown width.
+    pub fn has_unknown_state_widths(&self) -> bool {
+        self.registers().any(|r| types::Typed::r#type(r).ok().and_then(|t| t.bit_width()).is_none()) ||
+            self.memories().any(|b| b.is_none())
+    }
+
+    /// Retrieve all registers declared in this module
+    fn registers(&self) -> impl Iterator<Item = &crate::memory::Register<Arc<crate::stmt::Entity>>> {
+        use crate::stmt::Entity;
+
+        self.statements()
+            .iter()
+            .flat_map(Statement::declarations)
+            .filter_map(|e| if let Entity::Register(r) = e.as_ref() { Some(r) } else { None })
+    }
+
+    /// Retrieve the bits of all memories declared in this module
+    fn memories(&self) -> impl Iterator<Item = Option<u64>> + '_ {
+        use transiter::AutoTransIter;
+
+        use crate::stmt::{Entity, Kind as StmtKind};
+
+        self.statements().iter().flat_map(AutoTransIter::trans_iter).filter_map(|s| match s.kind() {
+            StmtKind::Declaration(e) => if let Entity::Memory(m) = e.as_ref() { Some(m.bits()) } else { None },
+            StmtKind::SimpleMemDecl(m) => Some(m.bits()),
+            _ => None,
+        })
+    }
+
+    /// Create a copy of this module with instantiated modules substituted
+    ///
+    /// This function applies `f` to every module instantiated in this module.
+    /// If `f` yields a module, the instance will refer to that module instead
+    /// of the original one. All references to affected instances are updated
+    /// accordingly.
+    pub fn with_substituted_instances(&self, mut f: impl FnMut(&Arc<Self>) -> Option<Arc<Self>>) -> Self {
+        use crate::stmt::{Entity, rewrite::EntityRewriter};
+
+        let kind = match self.kind() {
+            Kind::Regular{stmts} => {
+                let mut rewriter = EntityRewriter::new(|e| match e.as_ref() {
+                    Entity::Instance(i) => f(i.module())
+                        .map(|m| Arc::new(i.clone().with_module(m).into())),
+                    Entity::InstanceChoice(i) => {
+                        let mut changed = false;
+                        let res = i.with_modules_mapped(|m| f(m).inspect(|_| changed = true));
+                        Some(Arc::new(res.into())).filter(|_| changed)
+                    },
+                    _ => None,
+                });
+                Kind::Regular{stmts: rewriter.stmts(stmts)}
+            },
+            kind => kind.clone(),
+        };
+        Self {kind, ..self.clone()}
+    }
+
+    /// Replace an entity in this module
+    ///
+    /// This function replaces the declaration of `old` with `new` and
+    /// re-points every reference to `old` to `new`. References are considered
+    /// to refer to `old` if they point to the same entity or to an entity of
+    /// the same name. Entities depending on `old`, e.g. nodes, are rebuilt
+    /// accordingly. Ports of this module are not affected, only references to
+    /// them. The function returns the number of references which changed.
+    pub fn replace_entity(&mut self, old: &Arc<crate::stmt::Entity>, new: Arc<crate::stmt::Entity>) -> usize {
+        use transiter::AutoTransIter;
+
+        use crate::stmt::rewrite::EntityRewriter;
+
+        let stmts = match &self.kind {
+            Kind::Regular{stmts} => stmts,
+            Kind::External{..}   => return 0,
+        };
+
+        let matches = |e: &Arc<crate::stmt::Entity>| Arc::ptr_eq(e, old) || e.name() == old.name();
+        let uses = stmts
+            .iter()
+            .flat_map(AutoTransIter::trans_iter)
+            .flat_map(Statement::expressions)
+            .flat_map(expr::Expression::references)
+            .filter(|r| matches(r))
+            .count();
+
+        let stmts = EntityRewriter::new(|e| if matches(e) { Some(new.clone()) } else { None })
+            .with_replacement(old, new.clone())
+            .stmts(stmts);
+        self.kind = Kind::Regular{stmts};
+        uses
+    }
+
+    /// Create a preview of this module
+    ///
+    /// The preview is formatted like the module itself, but includes only the
+    /// first `max_statements` statements. Statements nested in conditional
+    /// statements count towards that limit. Omitted statements are replaced by
+    /// a comment stating their number, which allows showing snippets of huge
+    /// modules without formatting all of their statements.
+    pub fn preview(&self, max_statements: usize) -> Preview<'_> {
+        Preview {module: self, max_statements}
+    }
+
+    /// Compute a hash of this module's content
+    ///
+    /// The hash is computed from the module's [canonical
+    /// form](Self::to_canonical_string) and, for external modules, its
+    /// resources. Hence, info attributes and the formatting of the source are
+    /// not taken into account. Instantiated modules are only considered by
+    /// name, i.e. the hash of a module does not change if a module it
+    /// instantiates changes.
+    ///
+    /// The hash is stable across runs and platforms, which allows build
+    /// systems to detect which modules changed between two versions of a
+    /// circuit.
+    pub fn content_hash(&self) -> ContentHash {
+        use fmt::Write;
+
+        let mut hasher: ContentHasher = Default::default();
+        self.fmt_canonical(&mut Indentation::root(), None, &mut hasher).expect("Hashing cannot fail");
+        if let Kind::External{resources, ..} = self.kind() {
+            resources
+                .iter()
+                .try_for_each(|r| writeln!(hasher, "{}", r.annotation("", "")))
+                .expect("Hashing cannot fail");
+        }
+        ContentHash(hasher.0)
+    }
+
+    /// Format this module in its canonical textual form
+    ///
+    /// The canonical form is the regular FIRRTL representation of the module
+    /// with the following normalizations applied:
+    ///
+    /// * info attributes are omitted,
+    /// * uses of [type aliases](crate::types::alias) are replaced by the
+    ///   aliased types,
+    /// * ports are sorted by name, since they are referred to by name only,
+    /// * `skip` statements are omitted unless they form an otherwise empty
+    ///   block,
+    /// * the operands of `attach` statements are sorted by their textual
+    ///   representation,
+    /// * parameters are sorted by name and `-0.0` is formatted as `0`,
+    /// * parameter assignments of instances are included using the
+    ///   [extension syntax](Instance).
+    ///
+    /// Literals are always formatted with an explicit width and a decimal
+    /// value and indentation consists of two spaces per level. As a contract,
+    /// the canonical form of a given module will not change between versions
+    /// of this library, which makes it suitable for hashing and diffing.
+    pub fn to_canonical_string(&self) -> String {
+        let mut res = String::new();
+        self.fmt_canonical(&mut Indentation::root(), None, &mut res).expect("Formatting into a String cannot fail");
+        res
+    }
+
+    /// Format the canonical form of this module
+    ///
+    /// Statements are formatted in the grammar of the given FIRRTL `version`.
+    pub(crate) fn fmt_canonical(
+        &self,
+        indentation: &mut Indentation,
+        version: Option<crate::circuit::Version>,
+        f: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        use crate::emit::{Emitter, FirrtlEmitter};
+        use crate::stmt::{Entity, Kind as StmtKind, rewrite::EntityRewriter};
+        use info::WithInfo;
+
+        fn params(params: &HashMap<Arc<str>, ParamValue>) -> Option<HashMap<Arc<str>, ParamValue>> {
+            let negative_zero = |v: &ParamValue| matches!(v, ParamValue::Double(d) if *d == 0. && d.is_sign_negative());
+            if params.values().any(negative_zero) {
+                Some(params.iter().map(|(k, v)| if negative_zero(v) {
+                    (k.clone(), ParamValue::Double(0.))
+                } else {
+                    (k.clone(), v.clone())
+                }).collect())
+            } else {
+                None
+            }
+        }
+
+        fn canonical_stmts(stmts: &[Statement]) -> Vec<Statement> {
+            stmts.iter().filter(|s| !matches!(s.kind(), StmtKind::Empty)).map(|s| {
+                let stmt = match s.kind() {
+                    StmtKind::Conditional{cond, when, r#else} => s.with_kind(StmtKind::Conditional{
+                        cond: cond.clone(),
+                        when: canonical_stmts(when).into(),
+                        r#else: canonical_stmts(r#else).into(),
+                    }),
+                    StmtKind::LayerBlock{layer, stmts} => s.with_kind(StmtKind::LayerBlock{
+                        layer: layer.clone(),
+                        stmts: canonical_stmts(stmts).into(),
+                    }),
+                    StmtKind::Attach(exprs) => {
+                        let mut exprs = exprs.clone();
+                        exprs.sort_by_cached_key(ToString::to_string);
+                        s.with_kind(StmtKind::Attach(exprs))
+                    },
+                    _ => s.clone(),
+                };
+                stmt.with_info(None)
+            }).collect()
+        }
+
+        let kind = match self.kind() {
+            Kind::Regular{stmts} => {
+                let mut rewriter = EntityRewriter::new(|e| match e.as_ref() {
+                    Entity::Instance(i) => params(i.parameters())
+                        .map(|p| Arc::new(Entity::Instance(i.clone().with_parameters(p)))),
+                    _ => None,
+                });
+                Kind::Regular{stmts: canonical_stmts(&rewriter.stmts(stmts))}
+            },
+            Kind::External{defname, params: p, resources} => Kind::External{
+                defname: defname.clone(),
+                params: params(p).unwrap_or_else(|| p.clone()),
+                resources: resources.clone(),
+            },
+        };
+        let ports = self.ports_sorted().into_iter().map(|p| Arc::new(p.as_ref().clone().with_info(None)));
+        let module = Self::new(self.name.clone(), ports, kind).with_enabled_layers(self.layers.iter().cloned());
+        let mut emitter = FirrtlEmitter::new(f)
+            .with_indentation(indentation.clone())
+            .with_version(version)
+            .with_instance_parameters(true);
+        emitter.module(&module.with_expanded_aliases())?;
+        *indentation = emitter.indentation().clone();
+        Ok(())
     }
 }
 
@@ -94,31 +692,135 @@ impl info::WithInfo for Module {
 
 impl DisplayIndented for Module {
     fn fmt<W: fmt::Write>(&self, indentation: &mut Indentation, f: &mut W) -> fmt::Result {
-        writeln!(
-            f,
-            "{}{} {}:{}",
-            indentation.lock(),
-            self.kind().keyword(),
-            self.name(),
-            info::Info::of(self),
-        )?;
-        let mut indentation = indentation.sub();
-        self.ports().try_for_each(|p| DisplayIndented::fmt(p, &mut indentation, f))?;
-        match self.kind() {
-            Kind::Regular{stmts} => stmts
-                .iter()
-                .try_for_each(|s| DisplayIndented::fmt(s, &mut indentation, f)),
-            Kind::External{defname, params} => {
-                defname.as_ref().map(|n| writeln!(f, "{}defname = {}", indentation.lock(), n)).transpose()?;
-                params
-                    .iter()
-                    .try_for_each(|(k, v)| writeln!(f, "{}parameter {} = {}", indentation.lock(), k, v))
-            },
+        use crate::emit::{Emitter, FirrtlEmitter};
+
+        let mut emitter = FirrtlEmitter::new(f).with_indentation(indentation.clone());
+        emitter.module(self)?;
+        *indentation = emitter.indentation().clone();
+        Ok(())
+    }
+}
+
+/// Preview of a [Module]
+///
+/// Instances of this type are created via [Module::preview].
+#[derive(Copy, Clone, Debug)]
+pub struct Preview<'a> {
+    module: &'a Module,
+    max_statements: usize,
+}
+
+impl fmt::Display for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use transiter::AutoTransIter;
+
+        use crate::stmt::Kind as StmtKind;
+
+        // Format statements until the budget is exhausted, returning whether
+        // any statement was omitted
+        fn fmt_stmts(
+            stmts: &[Statement],
+            budget: &mut usize,
+            omitted: usize,
+            indent: &mut Indentation,
+            f: &mut fmt::Formatter<'_>,
+        ) -> Result<bool, fmt::Error> {
+            if stmts.is_empty() {
+                writeln!(f, "{}skip", indent.lock())?;
+                return Ok(false)
+            }
+
+            for stmt in stmts {
+                if *budget == 0 {
+                    writeln!(f, "{}; ... {} more statements", indent.lock(), omitted)?;
+                    return Ok(true)
+                }
+                *budget -= 1;
+
+                if let StmtKind::Conditional{cond, when, r#else} = stmt.kind() {
+                    writeln!(f, "{}when {}:{}", indent.lock(), cond, info::Info::of(stmt))?;
+                    if fmt_stmts(when, budget, omitted, &mut indent.sub(), f)? {
+                        return Ok(true)
+                    }
+                    if !r#else.is_empty() {
+                        writeln!(f, "{}else:", indent.lock())?;
+                        if fmt_stmts(r#else, budget, omitted, &mut indent.sub(), f)? {
+                            return Ok(true)
+                        }
+                    }
+                } else {
+                    DisplayIndented::fmt(stmt, indent, f)?;
+                }
+            }
+            Ok(false)
+        }
+
+        let module = self.module;
+        let total = module.statements().iter().flat_map(AutoTransIter::trans_iter).count();
+        if total <= self.max_statements {
+            return DisplayIndented::fmt(module, &mut Indentation::root(), f)
         }
+
+        let mut indent = Indentation::root();
+        writeln!(f, "{}{} {}:{}", indent.lock(), module.kind().keyword(), module.name(), info::Info::of(module))?;
+        let mut indent = indent.sub();
+        module.ports().try_for_each(|p| DisplayIndented::fmt(p, &mut indent, f))?;
+        let mut budget = self.max_statements;
+        fmt_stmts(module.statements(), &mut budget, total - self.max_statements, &mut indent, f).map(|_| ())
     }
 }
 
-#[cfg(test)]
+
+/// Hash of a [Module]'s content
+///
+/// Instances of this type are created via [Module::content_hash]. A hash is
+/// displayed and parsed as 16 hexadecimal digits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(u64);
+
+impl ContentHash {
+    /// Retrieve the numerical value of this hash
+    pub fn value(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for ContentHash {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+
+/// FNV-1a hasher fed with formatted text
+///
+/// Unlike the hashers provided by `std`, the result is guaranteed to be stable
+/// across versions and platforms.
+struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl fmt::Write for ContentHasher {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 = s.bytes().fold(self.0, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        Ok(())
+    }
+}
+
+
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Module {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::stmt::{self, tests::stmt_exprs};
@@ -173,6 +875,129 @@ impl Arbitrary for Module {
 }
 
 
+/// Policy for removing referenced ports
+///
+/// This policy decides what [Module::remove_port] does if the port to remove
+/// is referenced by any statement.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PortRemoval {
+    /// Fail with an error
+    Strict,
+    /// Replace the port with an invalidated wire of the same name and type
+    Stub,
+}
+
+
+/// Policy for driving the outputs of a stub
+///
+/// This policy decides how the body generated by [Module::stub] drives the
+/// module's outputs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StubPolicy {
+    /// Invalidate all outputs
+    Invalidate,
+    /// Connect all outputs of ground type to zero
+    ///
+    /// Outputs of types without a zero literal, i.e. fixed point types with
+    /// an unknown binary point and intervals, are invalidated instead. Passive
+    /// aggregates are invalidated as a whole.
+    Zero,
+}
+
+
+/// Generate statements driving the outputs within a stubbed port
+///
+/// The `signal` refers to (a part of) a port of the given type, flowing in the
+/// given `direction`. Statements driving all outputs in that signal as
+/// determined by the `policy` are appended to `stmts`. Passive aggregates are
+/// invalidated in a single statement, bundles are only split if they contain
+/// flipped fields.
+fn stub_sinks(
+    signal: expr::Expression<Arc<crate::stmt::Entity>>,
+    r#type: &Type,
+    direction: Direction,
+    policy: StubPolicy,
+    stmts: &mut Vec<Statement>,
+) {
+    use crate::stmt::Kind as SKind;
+    use expr::{Expression, primitive::Operation};
+    use types::{GroundType as GT, ResetKind as RK};
+
+    match r#type {
+        Type::GroundType(GT::Analog(_)) => (),
+        Type::Vector(..) | Type::Bundle(..) if invalidatable(r#type) => if direction == Direction::Output {
+            stmts.push(SKind::Invalidate(signal).into())
+        },
+        Type::GroundType(g) => if direction == Direction::Output {
+            let zero = || Expression::UIntLiteral{value: Default::default(), width: 1};
+            let from = match (policy, g) {
+                (StubPolicy::Invalidate, _)             => None,
+                (_, GT::UInt(_) | GT::Reset(RK::Regular)) => Some(zero()),
+                (_, GT::SInt(_))                        => Some(Expression::SIntLiteral{
+                    value: Default::default(),
+                    width: 1,
+                }),
+                (_, GT::Fixed(_, p))                    => p.map(|p| Expression::PrimitiveOp(
+                    Operation::Cast(Arc::new(zero()), GT::Fixed(None, Some(p)))
+                )),
+                (_, GT::Interval(..))                   => None,
+                (_, g)                                  => Some(Expression::PrimitiveOp(
+                    Operation::Cast(Arc::new(zero()), *g)
+                )),
+            };
+            stmts.push(match from {
+                Some(from)  => SKind::Connection{from, to: signal}.into(),
+                None        => SKind::Invalidate(signal).into(),
+            })
+        },
+        Type::Vector(t, w) => (0..*w).for_each(|i| stub_sinks(
+            Expression::SubIndex{base: Arc::new(signal.clone()), index: i},
+            t,
+            direction,
+            policy,
+            stmts,
+        )),
+        Type::Bundle(v) => v.iter().for_each(|f| {
+            let direction = match (direction, f.orientation()) {
+                (d,                 types::Orientation::Normal)     => d,
+                (Direction::Input,  types::Orientation::Flipped)    => Direction::Output,
+                (Direction::Output, types::Orientation::Flipped)    => Direction::Input,
+            };
+            stub_sinks(
+                Expression::SubField{base: Arc::new(signal.clone()), index: f.name().clone()},
+                f.r#type(),
+                direction,
+                policy,
+                stmts,
+            )
+        }),
+        Type::Enum(..) => if direction == Direction::Output {
+            stmts.push(SKind::Invalidate(signal).into())
+        },
+        Type::Probe(..) => (),
+        Type::Alias(_, t) => stub_sinks(signal, t, direction, policy, stmts),
+    }
+}
+
+
+/// Check whether a signal of the given type may be invalidated as a whole
+///
+/// This is the case for passive types not containing analog signals or probes.
+fn invalidatable(r#type: &Type) -> bool {
+    match r#type {
+        Type::GroundType(types::GroundType::Analog(_))  => false,
+        Type::GroundType(_)                             => true,
+        Type::Vector(t, _)                              => invalidatable(t),
+        Type::Bundle(v)                                 => v
+            .iter()
+            .all(|f| f.orientation() == types::Orientation::Normal && invalidatable(f.r#type())),
+        Type::Enum(..)                                  => true,
+        Type::Probe(..)                                 => false,
+        Type::Alias(_, t)                               => invalidatable(t),
+    }
+}
+
+
 /// Module kind
 ///
 /// The FIRRTL spec defines multiple kinds of modules.
@@ -182,7 +1007,11 @@ pub enum Kind {
     Regular{stmts: Vec<Statement>},
     /// An external module, usually an interface to some IP or external
     /// VHDL/Verilog.
-    External{defname: Option<Arc<str>>, params: HashMap<Arc<str>, ParamValue>},
+    External{
+        defname: Option<Arc<str>>,
+        params: HashMap<Arc<str>, ParamValue>,
+        resources: Vec<external::Resource>,
+    },
 }
 
 impl Kind {
@@ -201,7 +1030,18 @@ impl Kind {
 
     /// Create a new, empty module kind for external modules
     pub fn empty_external() -> Self {
-        Self::External{defname: Default::default(), params: Default::default()}
+        Self::External{defname: Default::default(), params: Default::default(), resources: Default::default()}
+    }
+
+    /// Retrieve the resources associated with this module
+    ///
+    /// Only external modules may have resources associated with them. For
+    /// regular modules, this function returns an empty slice.
+    pub fn resources(&self) -> &[external::Resource] {
+        match self {
+            Self::Regular{..}               => &[],
+            Self::External{resources, ..}   => resources.as_ref(),
+        }
     }
 
     /// Retrieve the statements in this module
@@ -219,7 +1059,7 @@ impl Default for Kind {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Kind {
     fn arbitrary(g: &mut Gen) -> Self {
         use std::iter::from_fn as fn_iter;
@@ -246,7 +1086,7 @@ impl Arbitrary for Kind {
                 let params = fn_iter(
                     || Some((Identifier::arbitrary(&mut g).into(), Arbitrary::arbitrary(&mut g)))
                 ).take(n).collect();
-                Kind::External{defname, params}
+                Kind::External{defname, params, resources: Default::default()}
             },
         ];
         g.choose(&opts).unwrap()(g)
@@ -269,26 +1109,38 @@ impl Arbitrary for Kind {
                     .map(|stmts| Self::Regular{stmts});
                 Box::new(res)
             },
-            Kind::External{defname, params} => {
+            Kind::External{defname, params, ..} => {
                 let res = defname
                     .as_ref()
                     .map(|n| Identifier::from(n.as_ref()))
                     .shrink()
                     .map({
                         let p = params.clone();
-                        move |n| Kind::External{defname: n.map(Into::into), params: p.clone()}
+                        move |n| Kind::External{
+                            defname: n.map(Into::into),
+                            params: p.clone(),
+                            resources: Default::default(),
+                        }
                     });
                 if params.len() > 1 {
                     let n = defname.clone();
                     let res = res.chain(params
                         .clone()
                         .into_iter()
-                        .map(move |p| Kind::External{defname: n.clone(), params: once(p).collect()})
+                        .map(move |p| Kind::External{
+                            defname: n.clone(),
+                            params: once(p).collect(),
+                            resources: Default::default(),
+                        })
                     );
                     Box::new(res)
                 } else if params.len() > 1 {
                     let res = res.chain(
-                        once(Kind::External{defname: defname.clone(), params: Default::default()})
+                        once(Kind::External{
+                            defname: defname.clone(),
+                            params: Default::default(),
+                            resources: Default::default(),
+                        })
                     );
                     Box::new(res)
                 } else {
@@ -301,8 +1153,99 @@ impl Arbitrary for Kind {
 
 
 /// Representation of a parameter value
-#[derive(Clone, PartialEq, Debug)]
-pub enum ParamValue {Int(i64), Double(f64), String(Arc<str>)}
+///
+/// A `RawVerilog` value holds the verbatim content of a single-quoted
+/// parameter, e.g. a Verilog literal or type expression, which is meant to be
+/// passed to the Verilog output as is.
+///
+/// Comparison, ordering and hashing of `Double` values are normalized: all
+/// `NaN`s are considered equal and `-0.0` equals `0.0`. Values of different
+/// variants never compare equal and are ordered by variant.
+#[derive(Clone, Debug)]
+pub enum ParamValue {Int(i64), Double(f64), String(Arc<str>), RawVerilog(Arc<str>)}
+
+impl ParamValue {
+    /// Retrieve the value of an `Int` parameter
+    pub fn as_int(&self) -> Result<i64, ParamError> {
+        match self {
+            Self::Int(v)    => Ok(*v),
+            _               => Err(self.mismatch("integer")),
+        }
+    }
+
+    /// Retrieve the value of a numeric parameter
+    ///
+    /// For `Int` parameters, the value is converted to the nearest `f64`.
+    pub fn as_f64(&self) -> Result<f64, ParamError> {
+        match self {
+            Self::Int(v)    => Ok(*v as f64),
+            Self::Double(v) => Ok(*v),
+            _               => Err(self.mismatch("numeric")),
+        }
+    }
+
+    /// Retrieve the value of a `String` or `RawVerilog` parameter
+    pub fn as_str(&self) -> Result<&str, ParamError> {
+        match self {
+            Self::String(v) | Self::RawVerilog(v)   => Ok(v.as_ref()),
+            _                                       => Err(self.mismatch("string")),
+        }
+    }
+
+    /// Create a [ParamError::Mismatch] for this value
+    fn mismatch(&self, expected: &'static str) -> ParamError {
+        ParamError::Mismatch{expected, value: self.clone()}
+    }
+
+    /// Retrieve the index of the variant and a normalized `Double` value
+    fn normalized(&self) -> (u8, f64) {
+        match self {
+            Self::Int(_)        => (0, 0.),
+            Self::Double(v)     => (1, if v.is_nan() { f64::NAN } else if *v == 0. { 0. } else { *v }),
+            Self::String(_)     => (2, 0.),
+            Self::RawVerilog(_) => (3, 0.),
+        }
+    }
+}
+
+impl PartialEq for ParamValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for ParamValue {}
+
+impl PartialOrd for ParamValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ParamValue {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let ((l, ld), (r, rd)) = (self.normalized(), other.normalized());
+        match (self, other) {
+            (Self::Int(l), Self::Int(r))                    => l.cmp(r),
+            (Self::Double(_), Self::Double(_))              => ld.total_cmp(&rd),
+            (Self::String(l), Self::String(r))              => l.cmp(r),
+            (Self::RawVerilog(l), Self::RawVerilog(r))      => l.cmp(r),
+            _                                               => l.cmp(&r),
+        }
+    }
+}
+
+impl std::hash::Hash for ParamValue {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let (variant, double) = self.normalized();
+        variant.hash(state);
+        match self {
+            Self::Int(v)                            => v.hash(state),
+            Self::Double(_)                         => double.to_bits().hash(state),
+            Self::String(v) | Self::RawVerilog(v)   => v.hash(state),
+        }
+    }
+}
 
 impl fmt::Display for ParamValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -312,29 +1255,46 @@ impl fmt::Display for ParamValue {
             Self::String(v) => {
                 fmt::Display::fmt(&'"', f)?;
                 v.chars().try_for_each(|c| match c {
-                    '\n' => write!(f, "\\\n"),
-                    '\t' => write!(f, "\\\t"),
+                    '\n' => write!(f, "\\n"),
+                    '\t' => write!(f, "\\t"),
+                    '\r' => write!(f, "\\r"),
                     '"'  => write!(f, "\\\""),
                     '\'' => write!(f, "\\'"),
                     '\\' => write!(f, "\\\\"),
+                    c if c.is_control() => write!(f, "\\x{:02x}", c as u32),
                     c    => fmt::Display::fmt(&c, f),
                 })?;
                 fmt::Display::fmt(&'"', f)
             },
+            Self::RawVerilog(v) => {
+                // Backslashes only need escaping if they would otherwise form
+                // an escape sequence with the following character.
+                fmt::Display::fmt(&'\'', f)?;
+                let mut chars = v.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match (c, chars.peek()) {
+                        ('\'', _)                                 => write!(f, "\\'")?,
+                        ('\\', None | Some('\'') | Some('\\'))    => write!(f, "\\\\")?,
+                        (c, _)                                    => fmt::Display::fmt(&c, f)?,
+                    }
+                }
+                fmt::Display::fmt(&'\'', f)
+            },
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for ParamValue {
     fn arbitrary(g: &mut Gen) -> Self {
         // We decided against considering Double values in our tests. With parse
         // tests, trying to get back the same double is a matter of luck,
         // especially since our formatting will happily format it as an integer
         // if possible.
-        let opts: [&dyn Fn(&mut Gen) -> Self; 2] = [
+        let opts: [&dyn Fn(&mut Gen) -> Self; 3] = [
             &|g| Self::Int(Arbitrary::arbitrary(g)),
             &|g| Self::String(crate::tests::ASCII::arbitrary(g).into()),
+            &|g| Self::RawVerilog(crate::tests::ASCII::arbitrary(g).as_ref().replace('\n', " ").into()),
         ];
         g.choose(&opts).unwrap()(g)
     }
@@ -346,6 +1306,7 @@ impl Arbitrary for ParamValue {
             Self::Int(v)    => Box::new(v.shrink().map(Self::Int)),
             Self::Double(v) => Box::new(v.shrink().map(Self::Double)),
             Self::String(v) => Box::new(ASCII::from(v.as_ref()).shrink().map(Into::into).map(Self::String)),
+            Self::RawVerilog(v) => Box::new(ASCII::from(v.as_ref()).shrink().map(Into::into).map(Self::RawVerilog)),
         }
     }
 }
@@ -425,7 +1386,7 @@ impl fmt::Display for Port {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Port {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -478,7 +1439,7 @@ impl fmt::Display for Direction {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Direction {
     fn arbitrary(g: &mut Gen) -> Self {
         *g.choose(&[Self::Input, Self::Output]).unwrap()
@@ -487,22 +1448,78 @@ impl Arbitrary for Direction {
 
 
 /// Representation of a [Module] instance
+///
+/// An instance may carry parameter assignments overriding the parameters of
+/// the instantiated module, e.g. for specializing an external module. FIRRTL
+/// does not define a syntax for such assignments. As an extension, they may be
+/// given in the form `inst foo of Bar with: (WIDTH = 8, NAME = "baz")`. This
+/// syntax is only emitted if enabled via
+/// [FirrtlEmitter::with_instance_parameters](crate::emit::FirrtlEmitter::with_instance_parameters)
+/// and only parsed if enabled via
+/// [ParseOptions::with_instance_parameters](crate::circuit::ParseOptions::with_instance_parameters).
+/// Likewise, the alternate form `{:#}` of the `Display` implementation
+/// includes the assignments while the regular form doesn't.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Instance {
     name: Arc<str>,
     module: Arc<Module>,
+    params: HashMap<Arc<str>, ParamValue>,
 }
 
 impl Instance {
     /// Create a new module instance
     pub fn new(name: impl Into<Arc<str>>, module: Arc<Module>) -> Self {
-        Self {name: name.into(), module}
+        Self {name: name.into(), module, params: Default::default()}
+    }
+
+    /// Create a copy of this instance with the given name
+    pub fn with_name(self, name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), ..self}
     }
 
     /// Retrieve the instantiated [Module]
     pub fn module(&self) -> &Arc<Module> {
         &self.module
     }
+
+    /// Replace the instantiated [Module]
+    pub fn with_module(self, module: Arc<Module>) -> Self {
+        Self {module, ..self}
+    }
+
+    /// Set the parameter assignments for this instance
+    pub fn with_parameters(
+        self,
+        params: impl IntoIterator<Item = (Arc<str>, ParamValue)>,
+    ) -> Self {
+        Self {params: params.into_iter().collect(), ..self}
+    }
+
+    /// Assign a value to a parameter for this instance
+    ///
+    /// This function returns the value previously assigned, if any.
+    pub fn set_parameter(&mut self, name: impl Into<Arc<str>>, value: ParamValue) -> Option<ParamValue> {
+        self.params.insert(name.into(), value)
+    }
+
+    /// Retrieve the parameter assignments of this instance
+    ///
+    /// Only parameters explicitly assigned for this instance are included.
+    pub fn parameters(&self) -> &HashMap<Arc<str>, ParamValue> {
+        &self.params
+    }
+
+    /// Retrieve the value of a specific parameter for this instance
+    ///
+    /// If the parameter is not assigned for this instance, this function
+    /// returns the parameter value defined by the instantiated module, if it is
+    /// an external module defining that parameter.
+    pub fn parameter(&self, name: impl AsRef<str>) -> Option<&ParamValue> {
+        self.params.get(name.as_ref()).or_else(|| match self.module.kind() {
+            Kind::External{params, ..}  => params.get(name.as_ref()),
+            Kind::Regular{..}           => None,
+        })
+    }
 }
 
 impl expr::Reference for Instance {
@@ -525,30 +1542,24 @@ impl types::Typed for Instance {
     type Type = Type;
 
     fn r#type(&self) -> Result<Self::Type, Self::Err> {
-        use types::{BundleField, Orientation};
-
-        fn orientation(dir: Direction) -> Orientation {
-            match dir {
-                Direction::Input  => Orientation::Flipped,
-                Direction::Output => Orientation::Normal,
-            }
-        }
-
-        let res = self.module.ports().map(|p| BundleField::new(p.name.clone(), p.r#type().clone())
-            .with_orientation(orientation(p.direction()))
-        ).collect();
-
-        Ok(res)
+        Ok(instance_type(&self.module))
     }
 }
 
 impl fmt::Display for Instance {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "inst {} of {}", self.name(), self.module().name())
+        use crate::display::CommaSeparated;
+
+        write!(f, "inst {} of {}", self.name(), self.module().name())?;
+        if f.alternate() && !self.params.is_empty() {
+            let params = sorted_params(&self.params).into_iter().map(|(k, v)| format!("{} = {}", k, v));
+            write!(f, " with: ({})", CommaSeparated::from(params))?;
+        }
+        Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Instance {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -568,3 +1579,126 @@ impl Arbitrary for Instance {
     }
 }
 
+
+/// Representation of an instance choice
+///
+/// An instance choice instantiates one of several [Module]s depending on the
+/// case of an [option group](crate::circuit::option::OptionGroup) selected
+/// when the circuit is lowered. If no case or a case without a dedicated
+/// module is selected, the default module is instantiated. All modules are
+/// expected to have the same ports, the instance's type is derived from the
+/// default module's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceChoice {
+    name: Arc<str>,
+    default: Arc<Module>,
+    option: Arc<str>,
+    cases: Vec<(Arc<str>, Arc<Module>)>,
+}
+
+impl InstanceChoice {
+    /// Create a new instance choice depending on the given option group
+    pub fn new(name: impl Into<Arc<str>>, default: Arc<Module>, option: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), default, option: option.into(), cases: Default::default()}
+    }
+
+    /// Create a copy of this instance choice with the given name
+    pub fn with_name(self, name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), ..self}
+    }
+
+    /// Retrieve the [Module] instantiated by default
+    pub fn default_module(&self) -> &Arc<Module> {
+        &self.default
+    }
+
+    /// Retrieve the name of the option group the choice depends on
+    pub fn option(&self) -> &Arc<str> {
+        &self.option
+    }
+
+    /// Set the modules instantiated for specific cases of the option group
+    pub fn with_cases(self, cases: impl IntoIterator<Item = (Arc<str>, Arc<Module>)>) -> Self {
+        Self {cases: cases.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the modules instantiated for specific cases
+    pub fn cases(&self) -> &[(Arc<str>, Arc<Module>)] {
+        self.cases.as_ref()
+    }
+
+    /// Retrieve the [Module] instantiated for the given case
+    ///
+    /// If no module is chosen for the case, the default module is returned.
+    pub fn module(&self, case: &str) -> &Arc<Module> {
+        self.cases.iter().find(|(c, _)| c.as_ref() == case).map(|(_, m)| m).unwrap_or(&self.default)
+    }
+
+    /// Retrieve all modules which may be instantiated, starting with the default
+    pub fn modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        std::iter::once(&self.default).chain(self.cases.iter().map(|(_, m)| m))
+    }
+
+    /// Create a copy of this instance choice with modules replaced
+    ///
+    /// This function applies `f` to every module which may be instantiated. If
+    /// `f` yields a module, it replaces the original one.
+    pub fn with_modules_mapped(&self, mut f: impl FnMut(&Arc<Module>) -> Option<Arc<Module>>) -> Self {
+        let mut map = |m: &Arc<Module>| f(m).unwrap_or_else(|| m.clone());
+        let default = map(&self.default);
+        let cases = self.cases.iter().map(|(c, m)| (c.clone(), map(m))).collect();
+        Self {default, cases, ..self.clone()}
+    }
+}
+
+impl expr::Reference for InstanceChoice {
+    fn flow(&self) -> Option<expr::Flow> {
+        Some(expr::Flow::Source)
+    }
+}
+
+impl Named for InstanceChoice {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl types::Typed for InstanceChoice {
+    type Err = Self;
+
+    type Type = Type;
+
+    fn r#type(&self) -> Result<Self::Type, Self::Err> {
+        Ok(instance_type(&self.default))
+    }
+}
+
+
+/// Determine the type of an instance of the given module
+fn instance_type(module: &Module) -> Type {
+    use types::{BundleField, Orientation};
+
+    fn orientation(dir: Direction) -> Orientation {
+        match dir {
+            Direction::Input  => Orientation::Flipped,
+            Direction::Output => Orientation::Normal,
+        }
+    }
+
+    module.ports().map(|p| BundleField::new(p.name.clone(), p.r#type().clone())
+        .with_orientation(orientation(p.direction()))
+    ).collect()
+}
+
+
+/// Retrieve parameters ordered by name
+///
+/// Parameters are kept in a `HashMap`. For reproducible output, they need to
+/// be sorted.
+pub(crate) fn sorted_params(params: &HashMap<Arc<str>, ParamValue>) -> Vec<(&Arc<str>, &ParamValue)> {
+    let mut res: Vec<_> = params.iter().collect();
+    res.sort_unstable_by_key(|(k, _)| *k);
+    res
+}
@@ -0,0 +1,180 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Utilities for external modules
+//!
+//! External modules are black boxes referring to definitions outside of the
+//! FIRRTL circuit, identified by their `defname`. This module provides an
+//! [Interface] summary for such modules, e.g. for integration scripts, and
+//! [DefnameConflict] for reporting external modules which refer to the same
+//! definition but disagree on its interface. In addition, [Resource]s such as
+//! Verilog sources may be associated with external modules.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::named::Named;
+
+use super::{Kind, Module, ParamValue, Port};
+
+
+/// Summary of the interface of an external module
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interface {
+    name: Arc<str>,
+    defname: Arc<str>,
+    params: Vec<(Arc<str>, ParamValue)>,
+    ports: Vec<Arc<Port>>,
+}
+
+impl Interface {
+    /// Extract the interface of the given module
+    ///
+    /// This function returns `None` if the module is not an external module.
+    pub fn of(module: &Module) -> Option<Self> {
+        if let Kind::External{params, ..} = module.kind() {
+            let mut params: Vec<_> = params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            params.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            Some(Self {
+                name: module.name().clone(),
+                defname: module.defname()?.clone(),
+                params,
+                ports: module.ports().cloned().collect(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve the name of the external module
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    /// Retrieve the name of the external definition
+    pub fn defname(&self) -> &Arc<str> {
+        &self.defname
+    }
+
+    /// Retrieve the module's parameters, ordered by name
+    pub fn parameters(&self) -> &[(Arc<str>, ParamValue)] {
+        self.params.as_ref()
+    }
+
+    /// Retrieve the module's ports
+    pub fn ports(&self) -> &[Arc<Port>] {
+        self.ports.as_ref()
+    }
+
+    /// Check whether this interface has the same ports as another one
+    ///
+    /// Ports are compared by name, direction and type, in order. Any `info`
+    /// attached to ports is not considered.
+    pub fn same_ports(&self, other: &Self) -> bool {
+        self.ports.len() == other.ports.len() && self.ports.iter().zip(other.ports.iter()).all(|(a, b)|
+            a.name() == b.name() && a.direction() == b.direction() && a.r#type() == b.r#type()
+        )
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} (defname = {}):", self.name, self.defname)?;
+        self.params.iter().try_for_each(|(k, v)| writeln!(f, "  parameter {} = {}", k, v))?;
+        self.ports.iter().try_for_each(|p| writeln!(f, "  {} {}: {}", p.direction(), p.name(), p.r#type()))
+    }
+}
+
+
+/// Conflict between external modules sharing a `defname`
+///
+/// A conflict exists if multiple external modules refer to the same external
+/// definition but don't have the same ports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefnameConflict {
+    defname: Arc<str>,
+    interfaces: Vec<Interface>,
+}
+
+impl DefnameConflict {
+    /// Find all conflicts among the given modules
+    ///
+    /// Regular modules are ignored.
+    pub fn find<'a>(modules: impl IntoIterator<Item = &'a Module>) -> Vec<Self> {
+        let mut groups: Vec<Self> = Default::default();
+        modules.into_iter().filter_map(Interface::of).for_each(|i| {
+            if let Some(g) = groups.iter_mut().find(|g| g.defname == i.defname) {
+                g.interfaces.push(i)
+            } else {
+                groups.push(Self {defname: i.defname.clone(), interfaces: vec![i]})
+            }
+        });
+
+        groups.retain(|g| g.interfaces.iter().any(|i| !i.same_ports(&g.interfaces[0])));
+        groups
+    }
+
+    /// Retrieve the `defname` in question
+    pub fn defname(&self) -> &Arc<str> {
+        &self.defname
+    }
+
+    /// Retrieve the interfaces of all modules with the `defname`
+    pub fn interfaces(&self) -> &[Interface] {
+        self.interfaces.as_ref()
+    }
+}
+
+impl fmt::Display for DefnameConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use crate::display::CommaSeparated;
+
+        write!(
+            f,
+            "external modules with conflicting interfaces for defname {}: {}",
+            self.defname,
+            CommaSeparated::from(self.interfaces.iter().map(Interface::name)),
+        )
+    }
+}
+
+
+/// Resource associated with an external module
+///
+/// Resources provide the external definition of a module, e.g. in the form of
+/// Verilog sources. They are not part of FIRRTL's textual representation but
+/// are conveyed via annotations. An annotation for a resource can be created
+/// via [Resource::annotation].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resource {
+    /// Path to a file, e.g. a Verilog source or header
+    Path(Arc<str>),
+    /// A file with the given name and inline content
+    Inline{name: Arc<str>, text: Arc<str>},
+}
+
+impl Resource {
+    /// Create an annotation for this resource
+    ///
+    /// This function formats a JSON annotation equivalent to the ones created
+    /// by Chisel's `HasBlackBoxPath` and `HasBlackBoxInline`, respectively.
+    /// The annotation will target the module with the given name in the given
+    /// circuit.
+    pub fn annotation(&self, circuit: &str, module: &str) -> String {
+        use crate::display::JsonString;
+
+        let target = format!("~{}|{}", circuit, module);
+        match self {
+            Self::Path(path) => format!(
+                "{{\"class\":\"firrtl.transforms.BlackBoxPathAnno\",\"target\":{},\"path\":{}}}",
+                JsonString(&target),
+                JsonString(path),
+            ),
+            Self::Inline{name, text} => format!(
+                "{{\"class\":\"firrtl.transforms.BlackBoxInlineAnno\",\"target\":{},\"name\":{},\"text\":{}}}",
+                JsonString(&target),
+                JsonString(name),
+                JsonString(text),
+            ),
+        }
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Generic modules
+//!
+//! Generators usually emit FIRRTL after elaboration, i.e. with all parameters
+//! of a design resolved. This module provides a representation of regular
+//! modules prior to elaboration: a [GenericModule] declares [Param]s which may
+//! be referred to in the widths of port and entity types and in memory depths.
+//! Substituting concrete values via [GenericModule::elaborate] yields a plain
+//! [Module].
+//!
+//! This module is an extension to FIRRTL and only available with the
+//! `generics` feature enabled.
+//!
+//! # Extension syntax
+//!
+//! [Width]s, [GenericType]s and [Param]s are displayed in a syntax extending
+//! the one of FIRRTL types:
+//!
+//!  * A parameter is displayed as its name, followed by ` = ` and its default
+//!    value if it has one, e.g. `N = 2`.
+//!  * A width is an expression over integer literals and parameter names,
+//!    combined via `+`, `-`, `*` and `/` with the usual precedence and
+//!    associativity, `clog2(...)` and parentheses, e.g. `clog2(N) + 1`.
+//!  * In types, widths which are not plain literals are enclosed in braces
+//!    wherever FIRRTL expects a width or vector length, e.g. `UInt<{W + 1}>`,
+//!    `Fixed<{W}><<2>>` or `SInt<8>[{N}]`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::ElaborationError;
+use crate::named::Named;
+use crate::stmt::{Entity, Statement};
+use crate::types::{BundleField, GroundType, Orientation, Type};
+
+use super::{Module, ParamValue};
+
+
+/// Values of parameters during elaboration
+pub type Values = HashMap<Arc<str>, i64>;
+
+
+/// A parameter of a [GenericModule]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Param {
+    name: Arc<str>,
+    default: Option<i64>,
+}
+
+impl Param {
+    /// Create a new parameter without a default value
+    pub fn new(name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), default: None}
+    }
+
+    /// Create a copy of this parameter with the given default value
+    pub fn with_default(self, default: i64) -> Self {
+        Self {default: Some(default), ..self}
+    }
+
+    /// Retrieve the default value of this parameter
+    pub fn default(&self) -> Option<i64> {
+        self.default
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        self.default.map(|d| write!(f, " = {}", d)).unwrap_or(Ok(()))
+    }
+}
+
+impl Named for Param {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+
+/// A width or depth which may depend on parameters
+///
+/// A width is a small expression over integer literals and parameters.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Width {
+    /// A fixed value
+    Literal(i64),
+    /// The value of the parameter with the given name
+    Param(Arc<str>),
+    /// Sum of two widths
+    Add(Arc<Self>, Arc<Self>),
+    /// Difference of two widths
+    Sub(Arc<Self>, Arc<Self>),
+    /// Product of two widths
+    Mul(Arc<Self>, Arc<Self>),
+    /// Quotient of two widths, rounded towards zero
+    Div(Arc<Self>, Arc<Self>),
+    /// Ceiled binary logarithm, i.e. the number of bits required for
+    /// addressing the given number of elements
+    CLog2(Arc<Self>),
+}
+
+impl Width {
+    /// Evaluate the width for the given parameter values
+    ///
+    /// This function fails if the width refers to a parameter for which no
+    /// value is given or if an operation overflows or is undefined for its
+    /// operands, e.g. a division by zero.
+    pub fn eval(&self, values: &Values) -> Result<i64, ElaborationError> {
+        let binary = |l: &Self, r: &Self, f: fn(i64, i64) -> Option<i64>| -> Result<i64, ElaborationError> {
+            f(l.eval(values)?, r.eval(values)?).ok_or_else(|| ElaborationError::Arithmetic(self.to_string()))
+        };
+
+        match self {
+            Self::Literal(v)    => Ok(*v),
+            Self::Param(n)      => values.get(n).cloned().ok_or_else(|| ElaborationError::Undeclared(n.clone())),
+            Self::Add(l, r)     => binary(l, r, i64::checked_add),
+            Self::Sub(l, r)     => binary(l, r, i64::checked_sub),
+            Self::Mul(l, r)     => binary(l, r, i64::checked_mul),
+            Self::Div(l, r)     => binary(l, r, i64::checked_div),
+            Self::CLog2(w)      => match w.eval(values)? {
+                v if v > 0  => Ok((64 - (v - 1).leading_zeros()).into()),
+                _           => Err(ElaborationError::Arithmetic(self.to_string())),
+            },
+        }
+    }
+
+    /// Check whether the width is a plain literal
+    pub fn is_literal(&self) -> bool {
+        matches!(self, Self::Literal(_))
+    }
+
+    /// Format the width, parenthesizing it if it binds less tight than `prec`
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, prec: u8) -> fmt::Result {
+        let (own, op, l, r) = match self {
+            Self::Literal(v)    => return write!(f, "{}", v),
+            Self::Param(n)      => return write!(f, "{}", n),
+            Self::CLog2(w)      => return write!(f, "clog2({})", w),
+            Self::Add(l, r)     => (1, "+", l, r),
+            Self::Sub(l, r)     => (1, "-", l, r),
+            Self::Mul(l, r)     => (2, "*", l, r),
+            Self::Div(l, r)     => (2, "/", l, r),
+        };
+
+        if own < prec {
+            write!(f, "(")?;
+        }
+        l.fmt_prec(f, own)?;
+        write!(f, " {} ", op)?;
+        r.fmt_prec(f, own + 1)?;
+        if own < prec {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate the width, converting it to the target type
+    fn eval_as<T: std::convert::TryFrom<i64>>(&self, values: &Values) -> Result<T, ElaborationError> {
+        let value = self.eval(values)?;
+        value.try_into().map_err(|_| ElaborationError::Range(value))
+    }
+}
+
+impl From<i64> for Width {
+    fn from(value: i64) -> Self {
+        Self::Literal(value)
+    }
+}
+
+impl From<&str> for Width {
+    fn from(param: &str) -> Self {
+        Self::Param(param.into())
+    }
+}
+
+impl std::ops::Add for Width {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::Add(Arc::new(self), Arc::new(rhs))
+    }
+}
+
+impl std::ops::Sub for Width {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::Sub(Arc::new(self), Arc::new(rhs))
+    }
+}
+
+impl std::ops::Mul for Width {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::Mul(Arc::new(self), Arc::new(rhs))
+    }
+}
+
+impl std::ops::Div for Width {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Self::Div(Arc::new(self), Arc::new(rhs))
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+
+/// Utility for displaying a [Width] in place of a FIRRTL width
+///
+/// Widths which are not plain literals are enclosed in braces.
+struct Embedded<'a>(&'a Width);
+
+impl fmt::Display for Embedded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_literal() {
+            fmt::Display::fmt(self.0, f)
+        } else {
+            write!(f, "{{{}}}", self.0)
+        }
+    }
+}
+
+
+/// A type which may depend on parameters
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenericType {
+    /// A type which does not depend on any parameter
+    Concrete(Type),
+    /// A ground type with the given width replacing the type's width
+    Ground(GroundType, Width),
+    /// A vector of the given element type and length
+    Vector(Arc<Self>, Width),
+    /// A bundle with fields of the given names, orientations and types
+    Bundle(Vec<(Arc<str>, Orientation, Self)>),
+}
+
+impl GenericType {
+    /// Compute the concrete type for the given parameter values
+    pub fn elaborate(&self, values: &Values) -> Result<Type, ElaborationError> {
+        match self {
+            Self::Concrete(t)   => Ok(t.clone()),
+            Self::Ground(t, w)  => w.eval_as(values).map(|w| t.with_width(Some(w)).into()),
+            Self::Vector(t, w)  => Ok(Type::Vector(Arc::new(t.elaborate(values)?), w.eval_as(values)?)),
+            Self::Bundle(f)     => f
+                .iter()
+                .map(|(n, o, t)| t.elaborate(values).map(|t| BundleField::new(n.clone(), t).with_orientation(*o)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|f| Type::Bundle(f.into())),
+        }
+    }
+}
+
+impl From<Type> for GenericType {
+    fn from(r#type: Type) -> Self {
+        Self::Concrete(r#type)
+    }
+}
+
+impl fmt::Display for GenericType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GroundType as GT;
+
+        match self {
+            Self::Concrete(t)                   => fmt::Display::fmt(t, f),
+            Self::Ground(GT::Fixed(_, p), w)    => {
+                write!(f, "Fixed<{}>", Embedded(w))?;
+                p.map(|p| write!(f, "<<{}>>", p)).unwrap_or(Ok(()))
+            },
+            Self::Ground(t @ GT::UInt(_), w)    |
+            Self::Ground(t @ GT::SInt(_), w)    |
+            Self::Ground(t @ GT::Analog(_), w)  => write!(f, "{}<{}>", t.with_width(None), Embedded(w)),
+            Self::Ground(t, _)                  => fmt::Display::fmt(t, f),
+            Self::Vector(t, w)                  => write!(f, "{}[{}]", t, Embedded(w)),
+            Self::Bundle(v)                     => {
+                write!(f, "{{")?;
+                v.iter().enumerate().try_for_each(|(i, (n, o, t))| {
+                    let sep = if i > 0 { ", " } else { "" };
+                    let flip = if *o == Orientation::Flipped { "flip " } else { "" };
+                    write!(f, "{}{}{}: {}", sep, flip, n, t)
+                })?;
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+
+/// A regular module prior to elaboration
+///
+/// A generic module consists of a template [Module] and a list of [Param]s.
+/// The types of ports and declared entities, i.e. wires, registers and
+/// memories, as well as the depths of memories may be overridden by
+/// [GenericType]s and [Width]s referring to those parameters. In the template,
+/// these types and depths serve as placeholders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenericModule {
+    template: Module,
+    params: Vec<Param>,
+    types: HashMap<Arc<str>, GenericType>,
+    depths: HashMap<Arc<str>, Width>,
+}
+
+impl GenericModule {
+    /// Create a new generic module from a template without any parameters
+    pub fn new(template: Module) -> Self {
+        Self {template, params: Default::default(), types: Default::default(), depths: Default::default()}
+    }
+
+    /// Add a parameter
+    pub fn with_param(mut self, param: Param) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    /// Override the type of the port or entity with the given name
+    pub fn with_type(mut self, name: impl Into<Arc<str>>, r#type: GenericType) -> Self {
+        self.types.insert(name.into(), r#type);
+        self
+    }
+
+    /// Override the depth of the memory with the given name
+    pub fn with_depth(mut self, name: impl Into<Arc<str>>, depth: Width) -> Self {
+        self.depths.insert(name.into(), depth);
+        self
+    }
+
+    /// Retrieve the template
+    pub fn template(&self) -> &Module {
+        &self.template
+    }
+
+    /// Retrieve the parameters of this module
+    pub fn params(&self) -> &[Param] {
+        self.params.as_ref()
+    }
+
+    /// Retrieve the type override for the port or entity with the given name
+    pub fn generic_type(&self, name: &str) -> Option<&GenericType> {
+        self.types.get(name)
+    }
+
+    /// Retrieve the depth override for the memory with the given name
+    pub fn generic_depth(&self, name: &str) -> Option<&Width> {
+        self.depths.get(name)
+    }
+
+    /// Compute the values of all parameters for the given arguments
+    ///
+    /// Parameters for which no argument is given take their default value.
+    /// This function fails if an argument is not an integer, if it refers to
+    /// an undeclared parameter or if a parameter without a default has no
+    /// argument.
+    pub fn values(&self, args: &HashMap<Arc<str>, ParamValue>) -> Result<Values, ElaborationError> {
+        if let Some(n) = args.keys().find(|n| !self.params.iter().any(|p| p.name() == *n)) {
+            return Err(ElaborationError::Undeclared(n.clone()))
+        }
+
+        self.params.iter().map(|p| {
+            let value = match args.get(p.name()) {
+                Some(v) => v.as_int().map_err(|e| ElaborationError::Param(p.name().clone(), e))?,
+                None    => p.default().ok_or_else(|| ElaborationError::Missing(p.name().clone()))?,
+            };
+            Ok((p.name().clone(), value))
+        }).collect()
+    }
+
+    /// Elaborate this module for the given arguments
+    ///
+    /// This function substitutes the parameter values derived via
+    /// [Self::values] into the overridden types and depths and yields the
+    /// template with the resulting types and depths. References to affected
+    /// ports and entities are updated accordingly. The name of the module is
+    /// retained.
+    pub fn elaborate(&self, args: &HashMap<Arc<str>, ParamValue>) -> Result<Module, ElaborationError> {
+        let values = self.values(args)?;
+        let mut module = self.template.clone();
+
+        let names: BTreeSet<_> = self.types.keys().chain(self.depths.keys()).collect();
+        for name in names {
+            let r#type = self.types.get(name).map(|t| t.elaborate(&values)).transpose()?;
+            let depth = self.depths.get(name).map(|d| d.eval_as(&values)).transpose()?;
+
+            if module.port_by_name(name).is_some() {
+                match (r#type, depth) {
+                    (Some(t), None) => module
+                        .retype_port(name, t)
+                        .map_err(|_| ElaborationError::NotFound(name.clone()))?,
+                    _ => return Err(ElaborationError::Unsupported(name.clone())),
+                };
+                continue
+            }
+
+            let old = module
+                .statements()
+                .iter()
+                .flat_map(Statement::declarations)
+                .find(|e| e.name() == name)
+                .cloned()
+                .ok_or_else(|| ElaborationError::NotFound(name.clone()))?;
+            let new = match (old.as_ref(), r#type, depth) {
+                (Entity::Wire{name, ..}, Some(t), None) => Entity::Wire{name: name.clone(), r#type: t},
+                (Entity::Register(r), Some(t), None) => Entity::Register(r.clone().with_type(t)),
+                (Entity::Memory(m), t, d) => {
+                    let m = t.into_iter().fold(m.clone(), |m, t| m.with_data_type(t));
+                    Entity::Memory(d.into_iter().fold(m, |m, d| m.with_depth(d)))
+                },
+                _ => return Err(ElaborationError::Unsupported(name.clone())),
+            };
+            module.replace_entity(&old, Arc::new(new));
+        }
+
+        Ok(module)
+    }
+}
+
+impl Named for GenericModule {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        self.template.name()
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Lightweight module interfaces
+//!
+//! Some tools only need the hierarchy and the port lists of a design, e.g. for
+//! generating wrappers or for visualization. A [ModuleInterface] summarizes a
+//! module without its statements. Interfaces may be extracted from a parsed
+//! [Module] via [Module::interface] or parsed directly via [Interfaces], which
+//! skips statement bodies entirely.
+//!
+//! For checking whether a regenerated module may replace an earlier version,
+//! [interface_diff] classifies the changes between the two versions' ports.
+
+use std::sync::Arc;
+
+use crate::named::Named;
+use crate::stmt::{Entity, Kind as StmtKind, Statement};
+use crate::types::Type;
+
+use super::{Direction, Kind, Module, ParamValue, Port};
+
+pub use super::parsers::Interfaces;
+
+
+/// Interface of a module
+///
+/// A `ModuleInterface` holds the name, ports, kind and parameters of a module
+/// as well as the instantiations within it. For regular modules, the kind
+/// does not contain any statements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleInterface {
+    name: Arc<str>,
+    ports: Vec<Arc<Port>>,
+    kind: Kind,
+    instances: Vec<(Arc<str>, Arc<str>)>,
+}
+
+impl ModuleInterface {
+    /// Create a new interface
+    pub(crate) fn new(
+        name: Arc<str>,
+        ports: Vec<Arc<Port>>,
+        kind: Kind,
+        instances: Vec<(Arc<str>, Arc<str>)>,
+    ) -> Self {
+        Self {name, ports, kind, instances}
+    }
+
+    /// Retrieve the module's ports
+    pub fn ports(&self) -> &[Arc<Port>] {
+        self.ports.as_ref()
+    }
+
+    /// Retrieve the module's kind
+    ///
+    /// For regular modules, the kind returned won't hold any statements.
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    /// Retrieve the module's parameters, ordered by name
+    ///
+    /// Only external modules have parameters.
+    pub fn parameters(&self) -> Vec<(&Arc<str>, &ParamValue)> {
+        let mut res: Vec<_> = if let Kind::External{params, ..} = &self.kind {
+            params.iter().collect()
+        } else {
+            Default::default()
+        };
+        res.sort_unstable_by_key(|(k, _)| *k);
+        res
+    }
+
+    /// Retrieve the instantiations within the module
+    ///
+    /// This function yields the name of each instance along with the name of
+    /// the module instantiated, in the order they appear in. For instance
+    /// choices, the module instantiated by default is yielded.
+    pub fn instances(&self) -> impl Iterator<Item = (&Arc<str>, &Arc<str>)> {
+        self.instances.iter().map(|(i, m)| (i, m))
+    }
+}
+
+impl Named for ModuleInterface {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl From<&Module> for ModuleInterface {
+    fn from(module: &Module) -> Self {
+        fn instances(stmts: &[Statement], res: &mut Vec<(Arc<str>, Arc<str>)>) {
+            stmts.iter().for_each(|s| match s.kind() {
+                StmtKind::Declaration(e) => match e.as_ref() {
+                    Entity::Instance(i) => res.push((i.name().clone(), i.module().name().clone())),
+                    Entity::InstanceChoice(i) => res.push((i.name().clone(), i.default_module().name().clone())),
+                    _ => (),
+                },
+                StmtKind::Conditional{when, r#else, ..} => {
+                    instances(when, res);
+                    instances(r#else, res);
+                },
+                StmtKind::LayerBlock{stmts, ..} => instances(stmts, res),
+                _ => (),
+            })
+        }
+
+        let kind = match module.kind() {
+            Kind::Regular{..}   => Kind::empty_regular(),
+            kind                => kind.clone(),
+        };
+        let mut res = Self::new(module.name().clone(), module.ports().cloned().collect(), kind, Default::default());
+        instances(module.statements(), &mut res.instances);
+        res
+    }
+}
+
+
+/// Change of a single port between two versions of a module
+#[derive(Clone, Debug, PartialEq)]
+pub enum PortChange {
+    /// The port was added
+    Added(Arc<Port>),
+    /// The port was removed
+    Removed(Arc<Port>),
+    /// The port's direction changed
+    ///
+    /// The port's type may or may not have changed as well.
+    Direction{old: Arc<Port>, new: Arc<Port>},
+    /// Only the widths within the port's type changed
+    ///
+    /// Widths include bit-widths of ground types and binary points of fixed
+    /// point types, but not the number of elements of vectors.
+    Width{old: Arc<Port>, new: Arc<Port>},
+    /// The port's type changed beyond widths
+    Retyped{old: Arc<Port>, new: Arc<Port>},
+}
+
+impl PortChange {
+    /// Retrieve the name of the port affected
+    pub fn port_name(&self) -> &Arc<str> {
+        match self {
+            Self::Added(p)              => p.name(),
+            Self::Removed(p)            => p.name(),
+            Self::Direction{new, ..}    => new.name(),
+            Self::Width{new, ..}        => new.name(),
+            Self::Retyped{new, ..}      => new.name(),
+        }
+    }
+
+    /// Check whether this change retains compatibility with existing users
+    ///
+    /// Only the addition of an output port is considered compatible, since an
+    /// instance of the module need not connect to it.
+    pub fn is_compatible(&self) -> bool {
+        matches!(self, Self::Added(p) if p.direction() == Direction::Output)
+    }
+}
+
+
+/// Differences between the interfaces of two versions of a module
+///
+/// Instances of this type are created via [interface_diff].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InterfaceDiff {
+    changes: Vec<PortChange>,
+}
+
+impl InterfaceDiff {
+    /// Retrieve all port changes
+    ///
+    /// Changes to ports present in the old version are listed in the order of
+    /// the old version's ports, followed by the ports added in the order of
+    /// the new version's ports.
+    pub fn changes(&self) -> &[PortChange] {
+        self.changes.as_ref()
+    }
+
+    /// Check whether the interfaces are identical
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Check whether the new version is a drop-in replacement for the old one
+    ///
+    /// The new version is a drop-in replacement if all changes are
+    /// [compatible](PortChange::is_compatible).
+    pub fn is_compatible(&self) -> bool {
+        self.changes.iter().all(PortChange::is_compatible)
+    }
+}
+
+
+/// Compute the differences between the interfaces of two modules
+///
+/// Ports are matched by name. Neither the order of the ports nor their info
+/// are considered part of the interface.
+pub fn interface_diff(old: &Module, new: &Module) -> InterfaceDiff {
+    let find = |module: &Module, name: &str| module.ports().find(|p| p.name_ref() == name).cloned();
+
+    let mut changes: Vec<_> = old.ports().filter_map(|o| match find(new, o.name_ref()) {
+        None => Some(PortChange::Removed(o.clone())),
+        Some(n) if n.direction() != o.direction() => Some(PortChange::Direction{old: o.clone(), new: n}),
+        Some(n) if n.r#type() == o.r#type() => None,
+        Some(n) if same_shape(o.r#type(), n.r#type()) => Some(PortChange::Width{old: o.clone(), new: n}),
+        Some(n) => Some(PortChange::Retyped{old: o.clone(), new: n}),
+    }).collect();
+    changes.extend(new.ports().filter(|n| find(old, n.name_ref()).is_none()).cloned().map(PortChange::Added));
+
+    InterfaceDiff {changes}
+}
+
+
+/// Check whether two types are equal except for widths
+fn same_shape(lhs: &Type, rhs: &Type) -> bool {
+    use crate::types::GroundType as GT;
+
+    match (lhs.resolved(), rhs.resolved()) {
+        (Type::GroundType(l), Type::GroundType(r)) => match (l, r) {
+            (GT::UInt(_),       GT::UInt(_))        => true,
+            (GT::SInt(_),       GT::SInt(_))        => true,
+            (GT::Fixed(_, _),   GT::Fixed(_, _))    => true,
+            (GT::Analog(_),     GT::Analog(_))      => true,
+            (GT::Interval(..),  GT::Interval(..))   => true,
+            (l, r)                                  => l == r,
+        },
+        (Type::Vector(l, lw), Type::Vector(r, rw)) => lw == rw && same_shape(l, r),
+        (Type::Probe(l, lk), Type::Probe(r, rk)) => lk == rk && same_shape(l, r),
+        (Type::Enum(l), Type::Enum(r)) => l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)|
+            l.name() == r.name() && match (l.data(), r.data()) {
+                (Some(l), Some(r))  => same_shape(l, r),
+                (None, None)        => true,
+                _                   => false,
+            }
+        ),
+        (Type::Bundle(l), Type::Bundle(r)) => l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)|
+            l.name() == r.name() && l.orientation() == r.orientation() && same_shape(l.r#type(), r.r#type())
+        ),
+        _ => false,
+    }
+}
@@ -2,21 +2,26 @@
 // SPDX-License-Identifier: Apache-2.0
 //! Parsers for modules and related items
 
+use std::io::BufRead;
 use std::sync::Arc;
 
 use nom::branch::alt;
 use nom::character::complete::char as chr;
 use nom::combinator::{iterator, map, value};
 use nom::multi::many0;
-use nom::sequence::tuple;
+use nom::sequence::{preceded, tuple};
 
+use crate::circuit::{Limits, Version, parsers::layer_path};
 use crate::error::{ParseError, convert_error};
 use crate::indentation::Indentation;
 use crate::info::{WithInfo, parse as parse_info};
-use crate::parsers::{IResult, decimal, float, identifier, kw, le, op, spaced, unquoted_string};
-use crate::stmt::{self, parsers::stmts as parse_stmts};
+use crate::parsers::{IResult, decimal, float, identifier, kw, le, line_len, op, skip_block, spaced, unquoted_string};
+use crate::stmt::{self, extension::Dialect, parsers::{stmts as parse_stmts, stmts_recovering as parse_stmts_recovering}};
+use crate::types::TypeAlias;
 use crate::types::parsers::r#type;
 
+use super::interface::ModuleInterface;
+
 
 /// Module iterator
 ///
@@ -29,6 +34,17 @@ pub struct Modules<'i> {
     origin: &'i str,
     current: &'i str,
     indentation: Indentation,
+    limits: Limits,
+    parsed: usize,
+    recover: bool,
+    unique_fields: bool,
+    separated: bool,
+    instance_parameters: bool,
+    diagnostics: Vec<ParseError>,
+    version: Option<Version>,
+    line_offset: usize,
+    dialects: Vec<Arc<dyn Dialect>>,
+    aliases: Arc<[TypeAlias]>,
 }
 
 impl<'i> Modules<'i> {
@@ -51,7 +67,95 @@ impl<'i> Modules<'i> {
     /// they are defined in. The `original` parameter will be used for computing
     /// offsets during for error reporting.
     pub fn new_with_origin(input: &'i str, origin: &'i str) -> Self {
-        Self {modules: Default::default(), origin, current: input, indentation: Indentation::root().sub()}
+        Self {
+            modules: Default::default(),
+            origin,
+            current: input,
+            indentation: Indentation::root().sub(),
+            limits: Default::default(),
+            parsed: 0,
+            recover: false,
+            unique_fields: false,
+            separated: false,
+            instance_parameters: false,
+            diagnostics: Default::default(),
+            version: Default::default(),
+            line_offset: 0,
+            dialects: Default::default(),
+            aliases: Arc::new([]),
+        }
+    }
+
+    /// Impose the given [Limits] on the modules parsed
+    ///
+    /// If a limit is exceeded, the iterator yields an error and stops.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self {limits, ..self}
+    }
+
+    /// Set the FIRRTL version governing the grammar
+    ///
+    /// The version is usually declared in the header of a circuit.
+    pub fn with_version(self, version: Option<Version>) -> Self {
+        Self {version, ..self}
+    }
+
+    /// Set whether to recover from malformed modules and statements
+    ///
+    /// By default, the iterator yields an error and stops at the first syntax
+    /// error. If `recover` is `true`, malformed statements and modules are
+    /// skipped instead and the errors are recorded as
+    /// [diagnostics](Self::diagnostics). Modules are yielded without the
+    /// statements skipped. Exceeding any [Limits] is still reported as an error.
+    pub fn with_recovery(self, recover: bool) -> Self {
+        Self {recover, ..self}
+    }
+
+    /// Set whether to reject bundles with duplicate field names
+    ///
+    /// By default, bundles such as `{a: UInt, a: SInt}` are accepted, with
+    /// [Type::field](crate::types::Type::field) yielding the first of the
+    /// fields. If `unique` is `true`, a module with a port or declaration of
+    /// a type containing such a bundle is reported as an error instead.
+    pub fn with_unique_fields(self, unique: bool) -> Self {
+        Self {unique_fields: unique, ..self}
+    }
+
+    /// Set whether to recognize multiple statements on a single line
+    ///
+    /// By default, a `;` starts a comment, as specified. If `separated` is
+    /// `true`, a `;` followed by a statement on the same line is treated as a
+    /// separator instead, e.g. `b <= a ; c <= a` forms two statements. This is
+    /// an extension not covered by the specification.
+    pub fn with_separated_statements(self, separated: bool) -> Self {
+        Self {separated, ..self}
+    }
+
+    /// Set whether to recognize parameter assignments of instances
+    ///
+    /// By default, instances are parsed as specified. If `parameters` is
+    /// `true`, parameter assignments given in the extension syntax described
+    /// for [Instance](super::Instance) are recognized as well.
+    pub fn with_instance_parameters(self, parameters: bool) -> Self {
+        Self {instance_parameters: parameters, ..self}
+    }
+
+    /// Set the [Dialect]s providing custom statements
+    ///
+    /// Statements not recognized otherwise are parsed by the first dialect
+    /// providing the statement's keyword.
+    pub fn with_dialects(self, dialects: impl IntoIterator<Item = Arc<dyn Dialect>>) -> Self {
+        Self {dialects: dialects.into_iter().collect(), ..self}
+    }
+
+    /// Retrieve the errors recovered from so far
+    pub fn diagnostics(&self) -> &[ParseError] {
+        self.diagnostics.as_ref()
+    }
+
+    /// Take the errors recovered from so far
+    pub fn take_diagnostics(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.diagnostics)
     }
 
     /// Retrieve a previously parsed module by name
@@ -65,21 +169,466 @@ impl<'i> Modules<'i> {
     pub fn add_module(&mut self, module: Arc<super::Module>) {
         self.modules.insert(module.name.clone(), module.clone());
     }
+
+    /// Retrieve the input not yet consumed
+    pub(crate) fn remaining(&self) -> &'i str {
+        self.current
+    }
+
+    /// Retrieve the indentation of module declarations
+    pub(crate) fn indentation(&self) -> &Indentation {
+        &self.indentation
+    }
+
+    /// Set the indentation of module declarations
+    pub(crate) fn with_indentation(self, indentation: Indentation) -> Self {
+        Self {indentation, ..self}
+    }
+
+    /// Set the type aliases which may be used in modules
+    pub(crate) fn with_type_aliases(self, aliases: impl Into<Arc<[TypeAlias]>>) -> Self {
+        Self {aliases: aliases.into(), ..self}
+    }
+
+    /// Set the number of lines preceding the origin
+    ///
+    /// Locations reported in errors are shifted by the given number of lines.
+    pub(crate) fn with_line_offset(self, line_offset: usize) -> Self {
+        Self {line_offset, ..self}
+    }
+
+    /// Continue parsing with the given input, retaining all other state
+    fn with_input<'j>(self, input: &'j str) -> Modules<'j> {
+        Modules {
+            modules: self.modules,
+            origin: input,
+            current: input,
+            indentation: self.indentation,
+            limits: self.limits,
+            parsed: self.parsed,
+            recover: self.recover,
+            unique_fields: self.unique_fields,
+            separated: self.separated,
+            instance_parameters: self.instance_parameters,
+            diagnostics: self.diagnostics,
+            version: self.version,
+            line_offset: self.line_offset,
+            dialects: self.dialects,
+            aliases: self.aliases,
+        }
+    }
 }
 
 impl Iterator for Modules<'_> {
     type Item = Result<Arc<super::Module>, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if !self.current.is_empty() {
+        while !self.current.is_empty() {
+            if let Err(e) = self.check_limits() {
+                self.current = self.current.split_at(self.current.len()).1;
+                return Some(Err(e))
+            }
+
             let modules = &self.modules;
+            let mut diagnostics = Vec::new();
+
+            let ctx = stmt::context::TopContext::new(|name: &str| modules.get(name).cloned())
+                .with_version(self.version)
+                .with_dialects(self.dialects.iter().cloned())
+                .with_type_aliases(self.aliases.clone())
+                .with_max_statements(self.limits.max_statements())
+                .with_separated_statements(self.separated)
+                .with_instance_parameters(self.instance_parameters);
+            let res = module(
+                ctx,
+                self.current,
+                &mut self.indentation,
+                if self.recover { Some(&mut diagnostics) } else { None },
+            );
 
-            let res = module(|name| modules.get(name).cloned(), self.current, &mut self.indentation)
-                .map(|(i, m)| {
+            if let (Err(nom::Err::Failure(e)), Some(max)) = (&res, self.limits.max_statements()) {
+                if stmt::parsers::exceeds_statement_limit(e) {
+                    let name = header(self.current, &mut self.indentation.clone())
+                        .map(|(_, (name, ..))| name.to_string())
+                        .unwrap_or_default();
+                    self.current = self.current.split_at(self.current.len()).1;
+                    return Some(Err(format!("Module {} exceeds the limit of {} statements", name, max).into()))
+                }
+            }
+
+            let (origin, offset) = (self.origin, self.line_offset);
+            self.diagnostics.extend(
+                diagnostics.into_iter().map(|e| convert_error(origin, nom::Err::Failure(e)).with_line_offset(offset))
+            );
+            let res = res
+                .map_err(|e| convert_error(origin, e).with_line_offset(offset))
+                .and_then(|(i, m)| self.check_fields(&m).map(|_| (i, m)));
+            match res {
+                Ok((i, m)) => {
                     let module = Arc::new(m);
                     self.add_module(module.clone());
+                    self.parsed += 1;
+                    self.current = i;
+                    return Some(Ok(module))
+                },
+                Err(e) if self.recover && e.syntax().is_some() => {
+                    self.diagnostics.push(e);
+                    self.current = skip_block(self.current);
+                },
+                Err(e) => {
+                    self.current = self.current.split_at(self.current.len()).1;
+                    return Some(Err(e))
+                },
+            }
+        }
+        None
+    }
+}
+
+impl Modules<'_> {
+    /// Check the limits for the module to be parsed next
+    fn check_limits(&self) -> Result<(), ParseError> {
+        if let Some(max) = self.limits.max_modules().filter(|m| self.parsed >= *m) {
+            return Err(format!("Circuit exceeds the limit of {} modules", max).into())
+        }
+
+        let max = if let Some(max) = self.limits.max_depth() { max } else { return Ok(()) };
+        let mut input = self.current;
+        let mut module_indentation = None;
+        let mut depth = 0;
+        while !input.is_empty() {
+            let (line, rest) = input.split_at(line_len(input));
+            let content = line.trim_start_matches([' ', '\t']);
+            let indentation = line.len() - content.len();
+
+            // Lines continuing an unclosed parenthesis may have any indentation
+            if depth == 0 && !(content.trim().is_empty() || content.starts_with(';')) {
+                match module_indentation {
+                    Some(i) if indentation <= i => break,
+                    Some(_) => (),
+                    None => module_indentation = Some(indentation),
+                }
+            }
+
+            let (end, line_max) = nesting_depth(content, depth);
+            if line_max > max {
+                let line = self.origin.len() - input.len();
+                let line = self.origin.get(..line).map(|s| s.lines().count() + 1 + self.line_offset).unwrap_or(0);
+                return Err(format!("Nesting depth exceeds the limit of {} in line {}", max, line).into())
+            }
+            depth = end;
+            input = rest;
+        }
+        Ok(())
+    }
+
+    /// Check the field names of the types in a parsed module, if required
+    fn check_fields(&self, module: &super::Module) -> Result<(), ParseError> {
+        use transiter::AutoTransIter;
+
+        use crate::named::Named;
+        use crate::stmt::Kind;
+        use crate::types::Typed;
+
+        if !self.unique_fields {
+            return Ok(())
+        }
+
+        let ports = module.ports().map(|p| (p.name(), p.r#type().clone()));
+        let decls = module.statements().iter().flat_map(AutoTransIter::trans_iter).filter_map(|s| match s.kind() {
+            Kind::Declaration(e)    => e.r#type().ok().map(|t| (e.name(), t)),
+            Kind::SimpleMemDecl(m)  => m.r#type().ok().map(|t| (m.name(), t)),
+            _                       => None,
+        });
+        match ports.chain(decls).find(|(_, t)| !t.fields_unique()) {
+            Some((name, t)) => Err(format!(
+                "Type {} of {} in module {} contains duplicate field names",
+                t,
+                name,
+                module.name,
+            ).into()),
+            None            => Ok(()),
+        }
+    }
+}
+
+
+/// Determine the nesting depth of parentheses, brackets and braces in a line
+///
+/// Starting from the given `depth`, this function returns the depth at the end
+/// of the line and the maximum depth reached within it. String literals and
+/// comments are not considered.
+fn nesting_depth(line: &str, mut depth: usize) -> (usize, usize) {
+    let mut max = depth;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(_), _) if escaped     => escaped = false,
+            (Some(_), '\\')             => escaped = true,
+            (Some(q), c) if c == q      => quote = None,
+            (Some(_), _)                => (),
+            (None, '"' | '\'')          => quote = Some(c),
+            (None, ';')                 => break,
+            (None, '(' | '[' | '{')     => {
+                depth += 1;
+                max = max.max(depth);
+            },
+            (None, ')' | ']' | '}')     => depth = depth.saturating_sub(1),
+            _                           => (),
+        }
+    }
+    (depth, max)
+}
+
+
+/// Module iterator reading from a [BufRead]
+///
+/// Like [Modules], this `Iterator` will yield `Module`s parsed from a given
+/// input in the order they are defined in. However, the input is pulled from
+/// a [BufRead] incrementally. Only the lines of the module to be parsed next
+/// are held in memory at any time. Parsed modules are retained for
+/// instantiation in subsequent modules.
+#[derive(Debug)]
+pub struct ReadModules<R: BufRead> {
+    read: R,
+    state: Modules<'static>,
+    lookahead: String,
+    done: bool,
+}
+
+impl<R: BufRead> ReadModules<R> {
+    /// Create a new module iterator for a given reader
+    ///
+    /// The iterator will yield all modules read from the given reader in the
+    /// order they are defined in.
+    pub fn new(read: R) -> Self {
+        Self {read, state: Modules::new(""), lookahead: Default::default(), done: false}
+    }
+
+    /// Impose the given [Limits] on the modules parsed
+    ///
+    /// If a limit is exceeded, the iterator yields an error and stops.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self {state: self.state.with_limits(limits), ..self}
+    }
+
+    /// Set the FIRRTL version governing the grammar
+    ///
+    /// The version is usually declared in the header of a circuit.
+    pub fn with_version(self, version: Option<Version>) -> Self {
+        Self {state: self.state.with_version(version), ..self}
+    }
+
+    /// Set whether to recover from malformed modules and statements
+    ///
+    /// See [Modules::with_recovery] for details.
+    pub fn with_recovery(self, recover: bool) -> Self {
+        Self {state: self.state.with_recovery(recover), ..self}
+    }
+
+    /// Set whether to reject bundles with duplicate field names
+    ///
+    /// See [Modules::with_unique_fields] for details.
+    pub fn with_unique_fields(self, unique: bool) -> Self {
+        Self {state: self.state.with_unique_fields(unique), ..self}
+    }
+
+    /// Set the [Dialect]s providing custom statements
+    ///
+    /// See [Modules::with_dialects] for details.
+    pub fn with_dialects(self, dialects: impl IntoIterator<Item = Arc<dyn Dialect>>) -> Self {
+        Self {state: self.state.with_dialects(dialects), ..self}
+    }
+
+    /// Retrieve the errors recovered from so far
+    pub fn diagnostics(&self) -> &[ParseError] {
+        self.state.diagnostics()
+    }
+
+    /// Take the errors recovered from so far
+    pub fn take_diagnostics(&mut self) -> Vec<ParseError> {
+        self.state.take_diagnostics()
+    }
+
+    /// Retrieve a previously parsed module by name
+    pub fn module(&self, name: impl AsRef<str>) -> Option<&Arc<super::Module>> {
+        self.state.module(name)
+    }
+
+    /// Add a module to the list of known modules
+    ///
+    /// Parsed modules will be able to instantiate the added `Module`.
+    pub fn add_module(&mut self, module: Arc<super::Module>) {
+        self.state.add_module(module)
+    }
+
+    /// Set the number of lines preceding the input read
+    ///
+    /// Locations reported in errors are shifted by the given number of lines.
+    pub(crate) fn with_line_offset(self, line_offset: usize) -> Self {
+        Self {state: self.state.with_line_offset(line_offset), ..self}
+    }
+
+    /// Set the indentation of module declarations
+    pub(crate) fn with_indentation(self, indentation: Indentation) -> Self {
+        Self {state: self.state.with_indentation(indentation), ..self}
+    }
+
+    /// Set the type aliases resolved in modules
+    pub(crate) fn with_type_aliases(self, aliases: impl Into<Arc<[TypeAlias]>>) -> Self {
+        Self {state: self.state.with_type_aliases(aliases), ..self}
+    }
+
+    /// Set input already read from the reader, which is parsed first
+    pub(crate) fn with_lookahead(self, lookahead: String) -> Self {
+        Self {lookahead, ..self}
+    }
+
+    /// Read the lines of the next module
+    ///
+    /// This function returns the number of blank lines and comments preceding
+    /// the module along with the module's lines. The first line following the
+    /// module is retained for the next call.
+    fn read_module(&mut self) -> std::io::Result<(usize, String)> {
+        let mut skipped = 0;
+        let mut block = None;
+        let mut res = String::new();
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                break
+            }
+
+            let content = line.trim_start_matches([' ', '\t']);
+            let indentation = line.len() - content.len();
+            if content.trim().is_empty() || content.starts_with(';') {
+                if block.is_none() {
+                    skipped += line.matches('\n').count();
+                    continue
+                }
+            } else {
+                match block {
+                    Some(b) if indentation <= b => {
+                        self.lookahead = line;
+                        break
+                    },
+                    Some(_) => (),
+                    None    => block = Some(indentation),
+                }
+            }
+            res.push_str(&line);
+        }
+        Ok((skipped, res))
+    }
+
+    /// Read a single line, including escaped line breaks
+    fn read_line(&mut self) -> std::io::Result<String> {
+        if !self.lookahead.is_empty() {
+            let rest = self.lookahead.split_off(line_len(&self.lookahead));
+            return Ok(std::mem::replace(&mut self.lookahead, rest))
+        }
+
+        let escaped = |l: &str| l
+            .strip_suffix('\n')
+            .map(|l| (l.len() - l.trim_end_matches('\\').len()) % 2 == 1)
+            .unwrap_or(false);
+
+        let mut line = String::new();
+        while self.read.read_line(&mut line)? > 0 && escaped(&line) {}
+        Ok(line)
+    }
+}
+
+impl<R: BufRead> Iterator for ReadModules<R> {
+    type Item = Result<Arc<super::Module>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let (skipped, input) = match self.read_module() {
+                Ok(r) => r,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()))
+                },
+            };
+            if input.is_empty() {
+                break
+            }
+
+            let offset = self.state.line_offset + skipped;
+            let mut modules = std::mem::replace(&mut self.state, Modules::new(""))
+                .with_input(input.as_ref())
+                .with_line_offset(offset);
+            let res = modules.next();
+
+            // Anything not consumed by the module parser is parsed next
+            let remaining = modules.remaining();
+            let consumed = input[..input.len() - remaining.len()].matches('\n').count();
+            self.lookahead.insert_str(0, remaining);
+            self.state = modules.with_input("").with_line_offset(offset + consumed);
+
+            match res {
+                Some(Ok(m))     => return Some(Ok(m)),
+                Some(Err(e))    => {
+                    self.done = true;
+                    return Some(Err(e))
+                },
+                None            => (),
+            }
+        }
+        None
+    }
+}
+
+
+/// Module interface iterator
+///
+/// This `Iterator` will yield the [ModuleInterface]s of the modules defined in
+/// a given input in the order they are defined in. Contrary to [Modules], the
+/// statements of regular modules are not parsed, which makes this iterator
+/// considerably faster. However, syntax errors within statements will go
+/// unnoticed and instantiations are not resolved.
+#[derive(Debug)]
+pub struct Interfaces<'i> {
+    origin: &'i str,
+    current: &'i str,
+    indentation: Indentation,
+    aliases: Arc<[TypeAlias]>,
+}
+
+impl<'i> Interfaces<'i> {
+    /// Create a new interface iterator for a given input
+    ///
+    /// The iterator will yield the interfaces of all modules from the given
+    /// input in the order they are defined in.
+    pub fn new(input: &'i str) -> Self {
+        Self::new_with_origin(input, input)
+    }
+
+    /// Create a new interface iterator for a given input
+    ///
+    /// The `original` parameter will be used for computing offsets for error
+    /// reporting.
+    pub fn new_with_origin(input: &'i str, origin: &'i str) -> Self {
+        Self {origin, current: input, indentation: Indentation::root().sub(), aliases: Arc::new([])}
+    }
+
+    /// Set the type aliases which may be used in port types
+    pub(crate) fn with_type_aliases(self, aliases: impl Into<Arc<[TypeAlias]>>) -> Self {
+        Self {aliases: aliases.into(), ..self}
+    }
+}
+
+impl Iterator for Interfaces<'_> {
+    type Item = Result<ModuleInterface, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.current.is_empty() {
+            let res = interface(self.current, &mut self.indentation, self.aliases.as_ref())
+                .map(|(i, m)| {
                     self.current = i;
-                    module
+                    m
                 })
                 .map_err(|e| {
                     self.current = self.current.split_at(self.current.len()).1;
@@ -94,59 +643,162 @@ impl Iterator for Modules<'_> {
 
 
 /// Parse a Module
+///
+/// Statements are parsed in the given `ctx`, which provides the modules which
+/// may be instantiated, the FIRRTL version governing the grammar, the dialects
+/// providing custom statements, the type aliases and the limit of statements.
+/// If `diagnostics` are given, malformed statements are skipped, including any
+/// lines indented further, and errors are recorded in `diagnostics`. Errors in
+/// the module's header or in the body of an external module are still
+/// reported as such.
 pub fn module<'i>(
-    module: impl Fn(&str) -> Option<Arc<super::Module>> + Copy,
+    ctx: stmt::context::TopContext<impl Fn(&str) -> Option<Arc<super::Module>>>,
     input: &'i str,
     indentation: &'_ mut Indentation,
+    diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
 ) -> IResult<'i, super::Module> {
-    let (input, (name, mut kind, info)) = map(
-        tuple((indentation.parser(), kind, spaced(identifier), spaced(op(":")), parse_info, le)),
-        |(_, kind, name, _, info, ..)| (name.into(), kind, info)
-    )(input)?;
+    use stmt::context::Context;
+
+    let (input, (name, mut kind, layers, info)) = header(input, indentation)?;
 
     let mut indentation = indentation.sub();
 
-    let (input, ports) = many0(
-        map(tuple((indentation.parser(), port, le)), |(_, p, ..)| Arc::new(p))
-    )(input)?;
+    let (input, ports) = ports(input, &mut indentation, ctx.type_aliases())?;
 
     let input = match &mut kind {
         super::Kind::Regular{stmts} => {
-            let ctx = stmt::context::TopContext::new(module).with_ports(ports.clone());
-            let (input, s) = parse_stmts(ctx, input, &mut indentation)?;
+            let ctx = ctx.with_ports(ports.clone());
+            let (input, s) = if let Some(diagnostics) = diagnostics {
+                parse_stmts_recovering(ctx, input, &mut indentation, diagnostics)?
+            } else {
+                parse_stmts(ctx, input, &mut indentation)?
+            };
 
             *stmts = s;
             input
         },
-        super::Kind::External{defname, params} => {
-            let (input, n) = nom::combinator::opt(
-                map(
-                    tuple((indentation.parser(), kw("defname"), spaced(op("=")), spaced(identifier), le)),
-                    |(.., n, _)| n.into()
-                )
-            )(input)?;
-            *defname = n;
-
-            let mut param_iter = iterator(
-                input,
-                map(
-                    tuple((
-                        indentation.parser(),
-                        kw("parameter"),
-                        spaced(identifier),
-                        spaced(op("=")),
-                        spaced(param_value),
-                        le,
-                    )),
-                    |(.., k, _, v, _)| (k.into(), v)
-                ),
-            );
-            params.extend(&mut param_iter);
-            param_iter.finish()?.0
-        },
+        kind => external_body(input, &mut indentation, kind)?.0,
     };
 
-    Ok((input, super::Module::new(name, ports, kind).with_info(info)))
+    Ok((input, super::Module::new(name, ports, kind).with_enabled_layers(layers).with_info(info)))
+}
+
+
+/// Parse the interface of a module
+///
+/// Contrary to [module], this parser does not parse the statements of regular
+/// modules. They are skipped, only extracting instantiations.
+pub fn interface<'i>(
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    aliases: &[TypeAlias],
+) -> IResult<'i, ModuleInterface> {
+    let (input, (name, mut kind, ..)) = header(input, indentation)?;
+    let module_indentation: usize = indentation.lock().into();
+
+    let mut indentation = indentation.sub();
+
+    let (mut input, ports) = ports(input, &mut indentation, aliases)?;
+
+    let mut instances = Vec::new();
+    if let super::Kind::Regular{..} = kind {
+        let inst = tuple((
+            alt((kw("inst"), kw("instchoice"))),
+            spaced(identifier),
+            spaced(kw("of")),
+            spaced(identifier),
+        ));
+        let mut inst = map(inst, |(_, i, _, m)| (Arc::from(i), Arc::from(m)));
+        while !input.is_empty() {
+            let (line, rest) = input.split_at(line_len(input));
+            let content = line.trim_start_matches([' ', '\t']);
+            let blank = content.trim().is_empty() || content.starts_with(';');
+            if !blank && line.len() - content.len() <= module_indentation {
+                break
+            }
+            if let Ok((_, i)) = nom::Parser::parse(&mut inst, content) {
+                instances.push(i)
+            }
+            input = rest;
+        }
+    } else {
+        input = external_body(input, &mut indentation, &mut kind)?.0;
+    }
+
+    Ok((input, ModuleInterface::new(name, ports, kind, instances)))
+}
+
+
+/// Parse the header of a module, i.e. its kind, name, enabled layers and info
+fn header<'i>(input: &'i str, indentation: &'_ mut Indentation) -> IResult<'i, Header> {
+    map(
+        tuple((
+            indentation.parser(),
+            kind,
+            spaced(identifier),
+            many0(preceded(spaced(kw("enablelayer")), spaced(layer_path))),
+            spaced(op(":")),
+            parse_info,
+            le,
+        )),
+        |(_, kind, n, layers, _, info, ..)| (n.into(), kind, layers, info)
+    )(input)
+}
+
+
+/// Name, kind, enabled layers and info of a module
+type Header = (Arc<str>, super::Kind, Vec<Arc<str>>, Option<String>);
+
+
+/// Parse the ports of a module
+fn ports<'i>(
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    aliases: &[TypeAlias],
+) -> IResult<'i, Vec<Arc<super::Port>>> {
+    many0(map(tuple((indentation.parser(), |i| port(i, aliases), le)), |(_, p, ..)| Arc::new(p)))(input)
+}
+
+
+/// Parse the body of an external module, i.e. its defname and parameters
+///
+/// The defname and parameters parsed will be stored in the given `kind`. If
+/// the `kind` is not an external one, no input will be consumed.
+fn external_body<'i>(
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    kind: &mut super::Kind,
+) -> IResult<'i, ()> {
+    let (defname, params) = if let super::Kind::External{defname, params, ..} = kind {
+        (defname, params)
+    } else {
+        return Ok((input, ()))
+    };
+
+    let (input, n) = nom::combinator::opt(
+        map(
+            tuple((indentation.parser(), kw("defname"), spaced(op("=")), spaced(identifier), le)),
+            |(.., n, _)| n.into()
+        )
+    )(input)?;
+    *defname = n;
+
+    let mut param_iter = iterator(
+        input,
+        map(
+            tuple((
+                indentation.parser(),
+                kw("parameter"),
+                spaced(identifier),
+                spaced(op("=")),
+                spaced(param_value),
+                le,
+            )),
+            |(.., k, _, v, _)| (k.into(), v)
+        ),
+    );
+    params.extend(&mut param_iter);
+    param_iter.finish()
 }
 
 
@@ -160,6 +812,10 @@ pub fn kind<'i>(input: &str) -> IResult<super::Kind> {
 
 
 /// Parse a parameter value
+///
+/// Double-quoted strings may contain the escape sequences recognized by
+/// [escape_sequence](crate::parsers::escape_sequence). Single-quoted strings are
+/// parsed as raw Verilog, in which only `\'` and `\\` are escape sequences.
 pub fn param_value(input: &str) -> IResult<super::ParamValue> {
     use super::ParamValue as PV;
 
@@ -170,31 +826,112 @@ pub fn param_value(input: &str) -> IResult<super::ParamValue> {
             tuple((chr('"'), |i| unquoted_string(i, &['\n', '\t', '"']), chr('"'))),
             |(_, s, _)| PV::String(s.into())
         ),
-        map(
-            tuple((chr('\''), |i| unquoted_string(i, &['\n', '\t', '\'']), chr('\''))),
-            |(_, s, _)| PV::String(s.into())
-        ),
+        map(tuple((chr('\''), raw_string, chr('\''))), |(_, s, _)| PV::RawVerilog(s.into())),
     ))(input)
 }
 
 
+/// Parse the content of a single-quoted string verbatim
+///
+/// Only `\'` and `\\` are treated as escape sequences. Other backslashes are
+/// preserved.
+fn raw_string(input: &str) -> IResult<'_, String> {
+    use nom::character::complete::{anychar, one_of};
+    use nom::combinator::verify;
+
+    let mut chars = iterator(
+        input,
+        alt((
+            preceded(chr('\\'), one_of("'\\")),
+            verify(anychar, |c| *c != '\'' && *c != '\n'),
+        ))
+    );
+    let res = (&mut chars).collect();
+    chars.finish().map(|(i, _)| (i, res))
+}
+
+
 /// Parse a module instance
+///
+/// If `parameters` is `true`, parameter assignments given in the extension
+/// syntax described for [Instance](super::Instance) are recognized.
 pub fn instance<'i>(
     module: impl Fn(&str) -> Option<Arc<super::Module>>,
     input: &'i str,
+    parameters: bool,
 ) -> IResult<'i, super::Instance> {
+    use nom::combinator::{cond, opt};
+    use nom::multi::separated_list0;
+
+    use crate::parsers::{comma, lp, rp};
+
+    let param = map(
+        tuple((spaced(identifier), spaced(op("=")), spaced(param_value))),
+        |(k, _, v)| (Arc::from(k), v),
+    );
+
     nom::combinator::map_opt(
-        tuple((kw("inst"), spaced(identifier), spaced(kw("of")), spaced(identifier))),
-        |(_, inst_name, _, mod_name)| module(mod_name).map(|m| super::Instance::new(inst_name, m)),
+        tuple((
+            kw("inst"),
+            spaced(identifier),
+            spaced(kw("of")),
+            spaced(identifier),
+            cond(parameters, opt(map(
+                tuple((spaced(kw("with")), spaced(op(":")), lp, separated_list0(comma, param), rp)),
+                |(.., p, _)| p,
+            ))),
+        )),
+        |(_, inst_name, _, mod_name, params)| module(mod_name)
+            .map(|m| super::Instance::new(inst_name, m).with_parameters(params.flatten().unwrap_or_default())),
     )(input)
 }
 
 
+/// Parse an instance choice, including the cases following the declaration
+///
+/// The declaration, e.g. `instchoice x of Default, Platform :`, is expected at
+/// the given indentation. It is followed by one case per line, e.g.
+/// `FPGA => FPGATarget`, at a sub-indentation.
+pub fn instance_choice<'i>(
+    module: impl Fn(&str) -> Option<Arc<super::Module>>,
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+) -> IResult<'i, (super::InstanceChoice, Option<String>)> {
+    use nom::combinator::map_opt;
+
+    use crate::parsers::comma;
+
+    let (input, (_, _, inst_name, _, default, _, option, _, info, _)) = tuple((
+        indentation.parser(),
+        kw("instchoice"),
+        spaced(identifier),
+        spaced(kw("of")),
+        spaced(map_opt(identifier, &module)),
+        comma,
+        spaced(identifier),
+        spaced(op(":")),
+        parse_info,
+        le,
+    ))(input)?;
+
+    let mut indentation = indentation.sub();
+    let mut cases = iterator(input, map_opt(
+        tuple((indentation.parser(), identifier, spaced(op("=>")), spaced(identifier), parse_info, le)),
+        |(_, case, _, m, ..)| module(m).map(|m| (case.into(), m)),
+    ));
+
+    let res = super::InstanceChoice::new(inst_name, default, option).with_cases(&mut cases);
+    cases.finish().map(|(i, _)| (i, (res, info)))
+}
+
+
 /// Parse the elements of a port
-pub fn port<'i>(input: &str) -> IResult<super::Port> {
+///
+/// The port's type may refer to any of the given `aliases`.
+pub fn port<'i>(input: &'i str, aliases: &[TypeAlias]) -> IResult<'i, super::Port> {
     map(
-        tuple((direction, spaced(identifier), spaced(op(":")), spaced(r#type), parse_info)),
-        |(direction, name, _, r#type, info)| super::Port::new(name.to_string(), r#type, direction)
+        tuple((direction, spaced(identifier), spaced(op(":")), spaced(|i| r#type(i, aliases)), parse_info)),
+        |(direction, n, _, r#type, info)| super::Port::new(n, r#type, direction)
             .with_info(info)
     )(input)
 }
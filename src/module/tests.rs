@@ -2,13 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 //! Tests related to modules
 
+use std::sync::Arc;
+
 use nom::Finish;
 use nom::combinator::all_consuming;
 
 use quickcheck::{Gen, TestResult, Testable};
 
+use crate::error::PortError;
 use crate::indentation::{DisplayIndented, Indentation};
 use crate::named::Named;
+use crate::stmt::context::TopContext;
 use crate::tests::Equivalence;
 
 use super::{Direction, Instance, Module, ParamValue, Port, parsers};
@@ -28,9 +32,10 @@ fn parse_module(mut base: Indentation, original: Module) -> Result<TestResult, S
 
     let res = all_consuming(
         |i| parsers::module(
-            |n| mods.binary_search_by_key(&n, |r| r.name()).ok().map(|i| mods[i].clone()),
+            TopContext::new(|n: &str| mods.binary_search_by_key(&n, |r| r.name()).ok().map(|i| mods[i].clone())),
             i,
             &mut base,
+            None,
         )
     )(&s)
         .finish()
@@ -53,9 +58,156 @@ fn parse_param_value(original: ParamValue) -> Result<Equivalence<ParamValue>, St
 
 
 #[quickcheck]
-fn parse_instance(original: Instance) -> Result<Equivalence<Instance>, String> {
+fn parse_param_string(original: String) -> Result<Equivalence<ParamValue>, String> {
+    let original = ParamValue::String(original.into());
     let s = original.to_string();
 
+    let res = all_consuming(parsers::param_value)(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn parse_raw_param_verbatim(content: crate::tests::ASCII) -> TestResult {
+    let content = content.as_ref();
+    if content.contains(['\'', '\n']) || content.contains("\\\\") || content.ends_with('\\') {
+        return TestResult::discard()
+    }
+
+    let s = format!("'{}'", content);
+    let res = all_consuming(parsers::param_value)(&s)
+        .finish()
+        .map(|(_, parsed)| parsed == ParamValue::RawVerilog(content.into()))
+        .unwrap_or(false);
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn param_value_accessors(value: ParamValue) -> bool {
+    let numeric = matches!(value, ParamValue::Int(_) | ParamValue::Double(_));
+    value.as_int().is_ok() == matches!(value, ParamValue::Int(_)) &&
+        value.as_f64().is_ok() == numeric &&
+        value.as_str().is_ok() == !numeric
+}
+
+
+#[quickcheck]
+fn param_value_ord(lhs: ParamValue, rhs: ParamValue) -> bool {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |v: &ParamValue| {
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    };
+    lhs.cmp(&rhs) == rhs.cmp(&lhs).reverse() &&
+        (lhs == rhs) == lhs.cmp(&rhs).is_eq() &&
+        (lhs != rhs || hash(&lhs) == hash(&rhs))
+}
+
+
+#[quickcheck]
+fn param_value_double_eq(lhs: f64, rhs: f64) -> bool {
+    let expected = lhs == rhs || (lhs.is_nan() && rhs.is_nan());
+    (ParamValue::Double(lhs) == ParamValue::Double(rhs)) == expected &&
+        ParamValue::Double(lhs) == ParamValue::Double(lhs)
+}
+
+
+#[quickcheck]
+fn module_preview(module: Module, max: u8) -> TestResult {
+    use transiter::AutoTransIter;
+
+    let max = max as usize % 8;
+    let total = module.statements().iter().flat_map(AutoTransIter::trans_iter).count();
+    if total == 0 {
+        return TestResult::discard()
+    }
+
+    let mut full = String::new();
+    if module.fmt(&mut Indentation::root(), &mut full).is_err() {
+        return TestResult::error("Could not format module")
+    }
+    let preview = module.preview(max).to_string();
+    let marker = format!("; ... {} more statements", total.saturating_sub(max));
+    let res = if total <= max {
+        preview == full
+    } else {
+        preview.matches(&marker).count() == 1 && preview.lines().last().map(|l| l.trim() == marker) == Some(true)
+    };
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn module_content_hash(module: Module, name: crate::tests::Identifier) -> bool {
+    let renamed = Module::new(Arc::<str>::from(name), module.ports().cloned(), module.kind().clone());
+    module.with_info_mapped(&mut |_| Some("info".into())).content_hash() == module.content_hash() &&
+        (renamed.name() == module.name() || renamed.content_hash() != module.content_hash())
+}
+
+
+#[quickcheck]
+fn module_canonical_form(module: Module) -> TestResult {
+    let mut ports: Vec<_> = module.ports().cloned().collect();
+    if ports.iter().any(|p| module.port_by_name(p.name()) != Some(p)) {
+        // Ports with the same name can't be ordered by name
+        return TestResult::discard()
+    }
+    ports.reverse();
+    let reordered = Module::new(module.name().clone(), ports, module.kind().clone())
+        .with_info_mapped(&mut |_| Some("info".into()));
+    TestResult::from_bool(reordered.to_canonical_string() == module.to_canonical_string())
+}
+
+
+#[test]
+fn module_canonical_form_stable() {
+    use crate::info::WithInfo;
+    use crate::stmt::Kind as StmtKind;
+    use crate::types::GroundType;
+
+    let ports = [
+        Arc::new(Port::new("b", GroundType::Analog(Some(4)).into(), Direction::Input).with_info(Some("a.fir 1:2".into()))),
+        Arc::new(Port::new("a", GroundType::Analog(Some(4)).into(), Direction::Input)),
+        Arc::new(Port::new("o", GroundType::UInt(Some(8)).into(), Direction::Output)),
+    ];
+    let refer = |p: &Arc<Port>| crate::expr::Expression::Reference(Arc::new(crate::stmt::Entity::Port(p.clone())));
+    let stmts = vec![
+        StmtKind::Attach(vec![refer(&ports[0]), refer(&ports[1])]).into(),
+        StmtKind::Empty.into(),
+        StmtKind::Connection{
+            from: crate::expr::Expression::UIntLiteral{value: 10u8.into(), width: 8},
+            to: refer(&ports[2]),
+        }.into(),
+    ];
+    let module = Module::new(Arc::from("Foo"), ports.iter().cloned(), super::Kind::Regular{stmts});
+
+    assert_eq!(module.to_canonical_string(), concat!(
+        "module Foo:\n",
+        "  input a: Analog<4>\n",
+        "  input b: Analog<4>\n",
+        "  output o: UInt<8>\n",
+        "  attach(a, b)\n",
+        "  o <= UInt<8>(10)\n",
+    ));
+    assert_eq!(module.content_hash().to_string(), "cacf36e494fd60a5");
+}
+
+
+#[quickcheck]
+fn parse_instance(
+    original: Instance,
+    params: Vec<(crate::tests::Identifier, ParamValue)>,
+) -> Result<Equivalence<Instance>, String> {
+    let original = original.with_parameters(params.into_iter().map(|(k, v)| (k.into(), v)));
+    let s = format!("{:#}", original);
+
     let m = original.module().clone();
     let lookup = move |n: &str| if n == m.name_ref() {
         Some(m.clone())
@@ -63,7 +215,7 @@ fn parse_instance(original: Instance) -> Result<Equivalence<Instance>, String> {
         None
     };
 
-    let res = all_consuming(|i| parsers::instance(&lookup, i))(&s)
+    let res = all_consuming(|i| parsers::instance(&lookup, i, true))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of(original, parsed))
         .map_err(|e| e.to_string());
@@ -75,7 +227,7 @@ fn parse_instance(original: Instance) -> Result<Equivalence<Instance>, String> {
 fn parse_port(original: Port) -> Result<Equivalence<Port>, String> {
     let s = original.to_string();
 
-    let res = all_consuming(|i| parsers::port(i))(&s)
+    let res = all_consuming(|i| parsers::port(i, &[]))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of(original, parsed))
         .map_err(|e| e.to_string());
@@ -93,3 +245,344 @@ fn parse_direction(original: Direction) -> Result<Equivalence<Direction>, String
     res
 }
 
+
+#[quickcheck]
+fn defname_conflict(module: Module) -> TestResult {
+    use super::{Kind, external::DefnameConflict};
+
+    let defname = if let Some(n) = module.defname() {
+        n.clone()
+    } else {
+        return TestResult::discard()
+    };
+    if module.ports().next().is_none() {
+        return TestResult::discard()
+    }
+
+    let mut kind = module.kind().clone();
+    if let Kind::External{defname: d, ..} = &mut kind {
+        *d = Some(defname)
+    }
+    let other = Module::new("other".into(), module.ports().skip(1).cloned(), kind);
+
+    let res = DefnameConflict::find(vec![&module, &module]).is_empty() &&
+        DefnameConflict::find(vec![&module, &other]).len() == 1;
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn add_port(mut module: Module, port: Port) -> TestResult {
+    let port = Arc::new(port);
+    let existing = module.port_by_name(port.name()).is_some();
+    match module.add_port(port.clone()) {
+        Ok(()) if !existing => TestResult::from_bool(module.ports().last() == Some(&port)),
+        Err(PortError::Duplicate(_)) if existing => TestResult::passed(),
+        _ => TestResult::failed(),
+    }
+}
+
+
+#[quickcheck]
+fn remove_port(module: Module, policy: bool) -> TestResult {
+    use super::PortRemoval;
+
+    let name = if let Some(p) = module.ports().next() {
+        p.name().clone()
+    } else {
+        return TestResult::discard()
+    };
+    if module.ports().filter(|p| p.name() == &name).count() > 1 {
+        return TestResult::discard()
+    }
+    let policy = if policy { PortRemoval::Stub } else { PortRemoval::Strict };
+
+    let referenced = !port_refs(&module, &name).is_empty();
+    let mut modified = module.clone();
+    match (modified.remove_port(&name, policy), policy) {
+        (Err(PortError::Referenced(_)), PortRemoval::Strict) => TestResult::from_bool(referenced),
+        (Ok(_), _) => TestResult::from_bool(
+            modified.port_by_name(&name).is_none() &&
+            port_refs(&modified, &name).is_empty() &&
+            (policy == PortRemoval::Stub || !referenced)
+        ),
+        _ => TestResult::failed(),
+    }
+}
+
+
+#[quickcheck]
+fn stub_module(module: Module, zero: bool) -> TestResult {
+    use crate::manifest::tests::leaf_count;
+    use crate::stmt::Kind;
+    use super::StubPolicy;
+
+    // Vectors with flipped fields are driven element-wise
+    if module.ports().map(|p| leaf_count(p.r#type())).fold(0, u64::saturating_add) > 256 {
+        return TestResult::discard()
+    }
+
+    let policy = if zero { StubPolicy::Zero } else { StubPolicy::Invalidate };
+    let stub = module.stub(policy);
+    let drives = stub.statements().iter().all(|s| match s.kind() {
+        Kind::Connection{from, ..}  => policy == StubPolicy::Zero && from.references().next().is_none(),
+        Kind::Invalidate(_)         => true,
+        _                           => false,
+    });
+    TestResult::from_bool(
+        stub.name() == module.name() &&
+        stub.ports().eq(module.ports()) &&
+        stub.referenced_modules().next().is_none() &&
+        drives &&
+        stub.stub(policy) == stub
+    )
+}
+
+
+#[quickcheck]
+fn retype_port(mut module: Module, r#type: crate::types::Type) -> TestResult {
+    let name = if let Some(p) = module.ports().next() {
+        p.name().clone()
+    } else {
+        return TestResult::discard()
+    };
+
+    if module.retype_port(&name, r#type.clone()).is_err() {
+        return TestResult::failed()
+    }
+    let res = module.port_by_name(&name).map(|p| p.r#type() == &r#type).unwrap_or(false) &&
+        port_refs(&module, &name).iter().all(|p| p.r#type() == &r#type);
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn interface_diff_added_port(module: Module, port: Port) -> TestResult {
+    use super::{PortChange, interface_diff};
+
+    let mut names: Vec<_> = module.ports().map(|p| p.name()).collect();
+    names.sort_unstable();
+    if names.windows(2).any(|p| p[0] == p[1]) {
+        // Ports are matched by name
+        return TestResult::discard()
+    }
+
+    let port = Arc::new(port);
+    let mut modified = module.clone();
+    if modified.add_port(port.clone()).is_err() {
+        return TestResult::discard()
+    }
+
+    let diff = interface_diff(&module, &modified);
+    TestResult::from_bool(
+        interface_diff(&module, &module).is_empty() &&
+        diff.changes() == [PortChange::Added(port.clone())] &&
+        diff.is_compatible() == (port.direction() == Direction::Output) &&
+        interface_diff(&modified, &module).changes() == [PortChange::Removed(port)]
+    )
+}
+
+
+#[test]
+fn interface_diff_classification() {
+    use crate::types::{BundleField, GroundType as GT, Type};
+    use super::{Kind, PortChange, interface_diff};
+
+    let bundle = |w| Type::Bundle(vec![BundleField::new("x", GT::UInt(Some(w)))].into());
+    let old = Module::new("M".into(), vec![
+        Arc::new(Port::new("a", GT::UInt(Some(4)).into(), Direction::Input)),
+        Arc::new(Port::new("b", GT::UInt(Some(4)).into(), Direction::Input)),
+        Arc::new(Port::new("c", bundle(2), Direction::Output)),
+        Arc::new(Port::new("d", GT::SInt(Some(4)).into(), Direction::Output)),
+        Arc::new(Port::new("e", GT::Clock.into(), Direction::Input)),
+    ], Kind::empty_regular());
+    let new = Module::new("M".into(), vec![
+        Arc::new(Port::new("e", GT::Clock.into(), Direction::Input)),
+        Arc::new(Port::new("d", GT::UInt(Some(4)).into(), Direction::Output)),
+        Arc::new(Port::new("c", bundle(3), Direction::Output)),
+        Arc::new(Port::new("b", GT::UInt(Some(4)).into(), Direction::Output)),
+        Arc::new(Port::new("f", GT::Clock.into(), Direction::Output)),
+    ], Kind::empty_regular());
+
+    let diff = interface_diff(&old, &new);
+    let kinds: Vec<_> = diff.changes().iter().map(|c| (c.port_name().as_ref(), match c {
+        PortChange::Added(_)        => "added",
+        PortChange::Removed(_)      => "removed",
+        PortChange::Direction{..}   => "direction",
+        PortChange::Width{..}       => "width",
+        PortChange::Retyped{..}     => "retyped",
+    })).collect();
+    assert_eq!(kinds, vec![("a", "removed"), ("b", "direction"), ("c", "width"), ("d", "retyped"), ("f", "added")]);
+    assert!(!diff.is_compatible());
+}
+
+
+/// Retrieve all references to the port with the given name in a module
+fn port_refs(module: &Module, name: &str) -> Vec<Arc<Port>> {
+    use transiter::AutoTransIter;
+
+    use crate::stmt::{Entity, tests::stmt_exprs};
+
+    module
+        .statements()
+        .iter()
+        .flat_map(AutoTransIter::trans_iter)
+        .flat_map(stmt_exprs)
+        .flat_map(crate::expr::Expression::references)
+        .filter_map(|e| if let Entity::Port(p) = e.as_ref() { Some(p.clone()) } else { None })
+        .filter(|p| p.name_ref() == name)
+        .collect()
+}
+
+
+#[quickcheck]
+fn add_resource(mut module: Module, path: crate::tests::ASCII) -> bool {
+    use super::external::Resource;
+
+    let resource = Resource::Path(path.into());
+    let external = module.defname().is_some();
+    match module.add_resource(resource.clone()) {
+        Ok(()) => external && module.resources().last() == Some(&resource),
+        Err(r) => !external && r == resource && module.resources().is_empty(),
+    }
+}
+
+
+#[quickcheck]
+fn map_statements_identity(module: Module) -> bool {
+    fn id(stmt: &crate::stmt::Statement) -> crate::stmt::Statement {
+        stmt.map_children(id)
+    }
+
+    module.map_statements(id) == module &&
+        (module.defname().is_some() || module.clone().with_statements(module.statements().to_vec()) == module)
+}
+
+
+#[quickcheck]
+fn replace_entity(mut module: Module) -> TestResult {
+    use transiter::AutoTransIter;
+
+    use crate::stmt::Statement;
+
+    let refs = |m: &Module, name: &str| m
+        .statements()
+        .iter()
+        .flat_map(AutoTransIter::trans_iter)
+        .flat_map(Statement::expressions)
+        .flat_map(crate::expr::Expression::references)
+        .filter(|r| r.name_ref() == name)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let old = match module.statements().iter().flat_map(Statement::declarations).next() {
+        Some(e) => e.clone(),
+        None => return TestResult::discard(),
+    };
+    let new = match old.with_name(format!("{}_new", old.name())) {
+        Some(e) => Arc::new(e),
+        None => return TestResult::discard(),
+    };
+    let expected = refs(&module, old.name_ref()).len();
+
+    let uses = module.replace_entity(&old, new.clone());
+    let decl = module.statements().iter().flat_map(Statement::declarations).any(|e| Arc::ptr_eq(e, &new));
+    let new_refs = refs(&module, new.name_ref());
+    TestResult::from_bool(
+        uses == expected && decl && refs(&module, old.name_ref()).is_empty() &&
+        new_refs.len() == expected && new_refs.iter().all(|r| Arc::ptr_eq(r, &new))
+    )
+}
+
+
+#[test]
+fn elaborate_generic_module() {
+    use std::collections::HashMap;
+
+    use crate::error::ElaborationError;
+    use crate::types::{GroundType as GT, Typed};
+
+    use super::generic::{GenericModule, GenericType, Param, Width};
+
+    let source = concat!(
+        "circuit Foo:\n",
+        "  module Foo:\n",
+        "    input a: UInt\n",
+        "    output b: UInt[1]\n",
+        "    wire w: UInt\n",
+        "    w <= a\n",
+        "    b[0] <= w\n",
+    );
+    let circuit = crate::circuit::parse(source).expect("Could not parse circuit");
+    let template = circuit.top_module().as_ref().clone();
+
+    let width = || GenericType::Ground(GT::UInt(None), Width::Param("W".into()));
+    let generic = GenericModule::new(template)
+        .with_param(Param::new("W"))
+        .with_param(Param::new("N").with_default(2))
+        .with_type("a", width())
+        .with_type("b", GenericType::Vector(Arc::new(width()), Width::Param("N".into())))
+        .with_type("w", width());
+
+    let args: HashMap<Arc<str>, ParamValue> = vec![("W".into(), ParamValue::Int(8))].into_iter().collect();
+    let module = generic.elaborate(&args).expect("Could not elaborate module");
+    assert_eq!(module.name_ref(), "Foo");
+    assert_eq!(module.port_by_name(&"a").map(|p| p.r#type().to_string()), Some("UInt<8>".to_string()));
+    assert_eq!(module.port_by_name(&"b").map(|p| p.r#type().to_string()), Some("UInt<8>[2]".to_string()));
+
+    let wire = module.statements().iter().flat_map(crate::stmt::Statement::declarations).next().cloned();
+    assert_eq!(wire.map(|w| w.r#type().map(|t| t.to_string())), Some(Ok("UInt<8>".to_string())));
+
+    assert_eq!(generic.elaborate(&Default::default()), Err(ElaborationError::Missing("W".into())));
+    let args: HashMap<Arc<str>, ParamValue> = vec![("X".into(), ParamValue::Int(8))].into_iter().collect();
+    assert_eq!(generic.elaborate(&args), Err(ElaborationError::Undeclared("X".into())));
+    let args: HashMap<Arc<str>, ParamValue> = vec![("W".into(), ParamValue::Int(-1))].into_iter().collect();
+    assert_eq!(generic.elaborate(&args), Err(ElaborationError::Range(-1)));
+}
+
+
+#[test]
+fn generic_widths() {
+    use crate::error::ElaborationError;
+    use crate::types::{GroundType as GT, Orientation};
+
+    use super::generic::{GenericType, Param, Values, Width};
+
+    let values: Values = vec![("W".into(), 8), ("N".into(), 5)].into_iter().collect();
+    let lit = Width::Literal;
+
+    let width = Width::from("W") + lit(1);
+    assert_eq!(width.to_string(), "W + 1");
+    assert_eq!(width.eval(&values), Ok(9));
+
+    let width = (Width::from("W") - (Width::from("N") - lit(1))) * lit(2);
+    assert_eq!(width.to_string(), "(W - (N - 1)) * 2");
+    assert_eq!(width.eval(&values), Ok(8));
+
+    let depth = Width::CLog2(Arc::new(Width::from("N"))) + Width::from("W") / lit(3);
+    assert_eq!(depth.to_string(), "clog2(N) + W / 3");
+    assert_eq!(depth.eval(&values), Ok(5));
+
+    assert_eq!(
+        (Width::from("W") / lit(0)).eval(&values),
+        Err(ElaborationError::Arithmetic("W / 0".into())),
+    );
+    assert_eq!(Width::CLog2(Arc::new(lit(0))).eval(&values), Err(ElaborationError::Arithmetic("clog2(0)".into())));
+    assert_eq!(Width::from("X").eval(&values), Err(ElaborationError::Undeclared("X".into())));
+
+    let r#type = GenericType::Bundle(vec![
+        ("a".into(), Orientation::Normal, GenericType::Ground(GT::UInt(None), Width::from("W") + lit(1))),
+        ("b".into(), Orientation::Flipped, GenericType::Vector(
+            Arc::new(GenericType::Ground(GT::Fixed(None, Some(2)), lit(4))),
+            Width::from("N"),
+        )),
+        ("c".into(), Orientation::Normal, GenericType::Concrete(GT::Clock.into())),
+    ]);
+    assert_eq!(r#type.to_string(), "{a: UInt<{W + 1}>, flip b: Fixed<4><<2>>[{N}], c: Clock}");
+    assert_eq!(
+        r#type.elaborate(&values).map(|t| t.to_string()),
+        Ok("{a: UInt<9>, flip b: Fixed<4><<2>>[5], c: Clock}".to_string()),
+    );
+
+    assert_eq!(Param::new("N").with_default(2).to_string(), "N = 2");
+}
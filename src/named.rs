@@ -20,6 +20,14 @@ pub trait Named {
     }
 }
 
+impl<N: Named + ?Sized> Named for &N {
+    type Name = N::Name;
+
+    fn name(&self) -> &Self::Name {
+        (*self).name()
+    }
+}
+
 impl<N: Named> Named for std::sync::Arc<N> {
     type Name = N::Name;
 
@@ -28,7 +36,7 @@ impl<N: Named> Named for std::sync::Arc<N> {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Named for crate::tests::Identifier {
     type Name = Self;
 
@@ -37,3 +45,17 @@ impl Named for crate::tests::Identifier {
     }
 }
 
+
+/// Collect named items, ordered by their name
+///
+/// Items with the same name retain their relative order. This function allows
+/// creating a canonical ordering for items such as ports or modules, which
+/// does not depend on the order they were parsed or created in.
+pub fn sorted_by_name<I>(items: I) -> Vec<I::Item>
+where I: IntoIterator,
+      I::Item: Named,
+{
+    let mut res: Vec<_> = items.into_iter().collect();
+    res.sort_by(|a, b| a.name_ref().cmp(b.name_ref()));
+    res
+}
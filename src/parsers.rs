@@ -6,19 +6,90 @@
 mod tests;
 
 
+use std::fmt;
+
 use nom::Parser;
 use nom::bytes::complete::{tag, take_while};
 use nom::character::complete::{char as chr, satisfy, space0};
 use nom::combinator::{not, peek, value};
-use nom::error::context;
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError, VerboseError, VerboseErrorKind, context};
 use nom::sequence::{preceded, tuple};
 
+use crate::error::IndentationMismatch;
+
 /// Result type for our (sub)parsers
 pub type IResult<'i, O> = nom::IResult<&'i str, O, Error<'i>>;
 
 
 /// Error type for our (sub)parsers
-pub type Error<'i> = nom::error::VerboseError<&'i str>;
+///
+/// Like a [VerboseError], an error records the kinds and contexts of the
+/// parsers which failed, innermost first. In addition, errors caused by lines
+/// not meeting the expected indentation carry an [IndentationMismatch].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error<'i> {
+    trace: VerboseError<&'i str>,
+    indentation: Option<IndentationMismatch>,
+}
+
+impl<'i> Error<'i> {
+    /// Create an error for a line starting at `input` with unexpected indentation
+    pub(crate) fn indentation(input: &'i str, mismatch: IndentationMismatch) -> Self {
+        let trace = VerboseError::from_error_kind(input, ErrorKind::Many1Count);
+        let trace = VerboseError::add_context(input, crate::error::INDENTATION_CONTEXT, trace);
+        Self {trace, indentation: Some(mismatch)}
+    }
+
+    /// Retrieve the kinds and contexts of the parsers which failed
+    ///
+    /// Each entry is associated with the input the respective parser failed
+    /// on. The innermost parser comes first.
+    pub fn trace(&self) -> &[(&'i str, VerboseErrorKind)] {
+        self.trace.errors.as_ref()
+    }
+
+    /// Retrieve the indentation mismatch causing this error, if any
+    pub fn indentation_mismatch(&self) -> Option<&IndentationMismatch> {
+        self.indentation.as_ref()
+    }
+
+    /// Convert this error into a human readable message for the given `input`
+    pub(crate) fn to_message(&self, input: &str) -> String {
+        nom::error::convert_error(input, self.trace.clone())
+    }
+}
+
+impl<'i> ParseError<&'i str> for Error<'i> {
+    fn from_error_kind(input: &'i str, kind: ErrorKind) -> Self {
+        Self {trace: VerboseError::from_error_kind(input, kind), indentation: None}
+    }
+
+    fn append(input: &'i str, kind: ErrorKind, other: Self) -> Self {
+        Self {trace: VerboseError::append(input, kind, other.trace), ..other}
+    }
+
+    fn from_char(input: &'i str, c: char) -> Self {
+        Self {trace: VerboseError::from_char(input, c), indentation: None}
+    }
+}
+
+impl<'i> ContextError<&'i str> for Error<'i> {
+    fn add_context(input: &'i str, ctx: &'static str, other: Self) -> Self {
+        Self {trace: VerboseError::add_context(input, ctx, other.trace), ..other}
+    }
+}
+
+impl<'i, E> FromExternalError<&'i str, E> for Error<'i> {
+    fn from_external_error(input: &'i str, kind: ErrorKind, e: E) -> Self {
+        Self {trace: VerboseError::from_external_error(input, kind, e), indentation: None}
+    }
+}
+
+impl fmt::Display for Error<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.trace, f)
+    }
+}
 
 
 /// Parse an identifier
@@ -37,9 +108,8 @@ pub fn identifier(input: &str) -> IResult<&str> {
 /// Parse an unquoted string
 ///
 /// This function parses the inner of a string literal or info attribute. It
-/// parses unescaped characters if they are not in `special` and characters
-/// escaped with a backslash. `\n` and `\t` are special in this regard as these
-/// are parsed as newline and tab characters respectively.
+/// parses unescaped characters if they are not in `special` and escape
+/// sequences as recognized by [escape_sequence].
 pub fn unquoted_string<'i>(input: &'i str, special: &[char]) -> IResult<'i, String> {
     use nom::combinator::{iterator, verify};
     use nom::branch::alt;
@@ -48,9 +118,7 @@ pub fn unquoted_string<'i>(input: &'i str, special: &[char]) -> IResult<'i, Stri
     let mut chars = iterator(
         input,
         alt((
-            value('\n', tag("\\n")),
-            value('\t', tag("\\t")),
-            preceded(chr('\\'), anychar),
+            escape_sequence,
             verify(anychar, |c| *c != '\\' && !special.contains(c)),
         ))
     );
@@ -59,6 +127,39 @@ pub fn unquoted_string<'i>(input: &'i str, special: &[char]) -> IResult<'i, Stri
 }
 
 
+/// Parse an escape sequence
+///
+/// `\n`, `\t` and `\r` are parsed as newline, tab and carriage return
+/// characters respectively. `\xNN`, `\uNNNN` and `\u{N}` denote the character
+/// with the code point given as exactly two, exactly four or up to six hex
+/// digits. Any other character escaped with a backslash denotes itself.
+pub fn escape_sequence(input: &str) -> IResult<'_, char> {
+    use nom::branch::alt;
+    use nom::bytes::complete::take_while_m_n;
+    use nom::character::complete::anychar;
+    use nom::combinator::map_opt;
+    use nom::sequence::terminated;
+
+    let hex = |min, max| map_opt(
+        take_while_m_n(min, max, |c: char| c.is_ascii_hexdigit()),
+        |s: &str| u32::from_str_radix(s, 16).ok().and_then(std::char::from_u32),
+    );
+
+    preceded(
+        chr('\\'),
+        alt((
+            value('\n', chr('n')),
+            value('\t', chr('t')),
+            value('\r', chr('r')),
+            preceded(chr('x'), hex(2, 2)),
+            preceded(tag("u{"), terminated(hex(1, 6), chr('}'))),
+            preceded(chr('u'), hex(4, 4)),
+            anychar,
+        )),
+    )(input)
+}
+
+
 /// Parse a decimal numeral
 pub fn decimal<O>(input: &str) -> IResult<O>
     where O: std::str::FromStr
@@ -144,16 +245,25 @@ pub fn op<'i>(operator: &'static str) -> impl nom::Parser<&'i str, (), Error<'i>
 /// Parse line endings, skipping preceding whitespace
 ///
 /// This parser consumes line endings, optionally preceded by whitespace and/or
-/// a comment. If no line ending is recognized, this parser will yield an error.
+/// a comment. Both `\n` and `\r\n` are recognized as line endings. The end of
+/// the input, again optionally preceded by whitespace and/or a comment, is
+/// accepted in place of a final line ending. If neither a line ending nor the
+/// end of the input is recognized, this parser will yield an error.
 pub fn le<'i>(input: &'i str) -> IResult<'i, ()> {
+    use nom::branch::alt;
     use nom::character::complete::{line_ending, not_line_ending};
-    use nom::combinator::opt;
-
-    nom::multi::fold_many1(
-        spaced(preceded(opt(tuple((chr(';'), not_line_ending))), line_ending)),
-        Default::default,
-        |_, _| ()
-    )(input)
+    use nom::combinator::{eof, opt};
+
+    let comment = || opt(tuple((chr(';'), not_line_ending)));
+    let end = || value((), spaced(preceded(comment(), eof)));
+
+    alt((
+        value((), tuple((
+            nom::multi::fold_many1(spaced(preceded(comment(), line_ending)), Default::default, |_, _| ()),
+            opt(end()),
+        ))),
+        end(),
+    ))(input)
 }
 
 
@@ -169,8 +279,68 @@ pub fn spaced<'i, O>(
 }
 
 
+/// Create a parser which discards any whitespace before applying another parser
+///
+/// This function wraps the given parser in another parser which will be
+/// returned to the caller. Contrary to [spaced], the returned parser will also
+/// consume line breaks. It is intended for use within delimited lists, e.g.
+/// argument lists, which may span multiple lines.
+pub fn wrapped<'i, O>(
+    inner: impl nom::Parser<&'i str, O, Error<'i>>
+) -> impl nom::Parser<&'i str, O, Error<'i>> {
+    preceded(nom::character::complete::multispace0, inner)
+}
+
+
+/// Determine the length of the first line in the input, including the newline
+///
+/// Line breaks in strings and info attributes are escaped with a backslash.
+/// Such line breaks are not considered the end of a line.
+pub fn line_len(input: &str) -> usize {
+    let mut escaped = false;
+    input
+        .char_indices()
+        .find(|(_, c)| match c {
+            '\n' if !escaped    => true,
+            '\\'                => {
+                escaped = !escaped;
+                false
+            },
+            _                   => {
+                escaped = false;
+                false
+            },
+        })
+        .map(|(i, _)| i + 1)
+        .unwrap_or(input.len())
+}
+
+
+/// Skip an indented block of lines
+///
+/// This function skips the first non-blank line in the input as well as all
+/// following lines which are either blank or indented further than that line.
+/// It is used for skipping malformed items during error recovery.
+pub fn skip_block(mut input: &str) -> &str {
+    let mut block = None;
+    while !input.is_empty() {
+        let (line, rest) = input.split_at(line_len(input));
+        let content = line.trim_start_matches([' ', '\t']);
+        let indentation = line.len() - content.len();
+        if !(content.trim().is_empty() || content.starts_with(';')) {
+            match block {
+                Some(b) if indentation <= b => break,
+                Some(_)                     => (),
+                None                        => block = Some(indentation),
+            }
+        }
+        input = rest;
+    }
+    input
+}
+
+
 /// Check whether the character is allowed in identifiers
 pub fn is_identifier_char(c: char) -> bool {
     c.is_alphanumeric() || c == '_' || c == '$'
 }
-
@@ -29,3 +29,16 @@ fn parse_decimal(original: i128) -> Result<Equivalence<i128>, String> {
     res
 }
 
+
+#[test]
+fn escape_sequences() {
+    let parse = |s: &str| all_consuming(|i| super::unquoted_string(i, &['"']))(s)
+        .finish()
+        .map(|(_, p)| p)
+        .map_err(|e| e.to_string());
+    assert_eq!(parse(r"a\nb\tc\rd"), Ok("a\nb\tc\rd".to_string()));
+    assert_eq!(parse(r"\x41\x7f\xe9"), Ok("A\u{7f}\u{e9}".to_string()));
+    assert_eq!(parse(r"\u00e9\u{1F600}\u{41}"), Ok("\u{e9}\u{1F600}A".to_string()));
+    assert_eq!(parse("\u{e9}\u{1F600}"), Ok("\u{e9}\u{1F600}".to_string()));
+    assert_eq!(parse(r#"\\\"\q\xg"#), Ok("\\\"qxg".to_string()));
+}
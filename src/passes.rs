@@ -0,0 +1,97 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Transformations of circuits
+//!
+//! This module provides passes, i.e. transformations operating on entire
+//! [Circuit](crate::circuit::Circuit)s. Passes don't alter their input but
+//! create modified copies of the affected parts of a circuit.
+//!
+//! Passes creating entities not named by the user derive their names via a
+//! [NamePolicy], which generates reproducible names not colliding with
+//! existing ones.
+
+mod conditions;
+mod gensym;
+mod manager;
+mod miter;
+mod partial;
+mod prune;
+mod specialize;
+mod strip;
+mod tap;
+mod watch;
+mod wiring;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::PortError;
+use crate::target::Target;
+
+pub use conditions::simplify_whens;
+pub use gensym::{NamePolicy, Namespace};
+pub use manager::{AnalysisCache, Pass, PassManager, PipelineState};
+pub use miter::miter;
+pub use partial::{DROPPED_BY_PARTIAL_CONNECT, ILLEGAL_PARTIAL_CONNECT, expand_partial_connects};
+pub use prune::prune_constant_whens;
+pub use specialize::specialize;
+pub use strip::{StripOptions, Stripped, strip_simulation_constructs};
+pub use tap::{Tap, TapMap, insert_debug_tap};
+pub use watch::{Watchpoint, insert_watchpoints};
+pub use wiring::punch_port;
+
+
+/// Error type for passes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The given target could not be resolved
+    Unresolved(Target),
+    /// The given target does not refer to a signal
+    NotASignal(Target),
+    /// The given target refers to a signal or instance declared in a nested
+    /// statement, e.g. a conditional branch
+    Nested(Target),
+    /// The operation would require modifying the given external module
+    ExternalModule(Arc<str>),
+    /// A port could not be created or modified
+    Port(PortError),
+    /// The value of the given parameter is not applicable to its port
+    Parameter(Arc<str>),
+    /// The interfaces of two modules differ in the port with the given name
+    Interface(Arc<str>),
+    /// Different modules with the given name would end up in the same circuit
+    ModuleConflict(Arc<str>),
+}
+
+impl From<PortError> for Error {
+    fn from(err: PortError) -> Self {
+        Self::Port(err)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Port(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unresolved(t)     => write!(f, "could not resolve target {}", t),
+            Self::NotASignal(t)     => write!(f, "target {} does not refer to a signal", t),
+            Self::Nested(t)         => write!(f, "target {} is not declared at the top level of its module", t),
+            Self::ExternalModule(n) => write!(f, "cannot modify external module {}", n),
+            Self::Port(err)         => fmt::Display::fmt(err, f),
+            Self::Parameter(n)      => write!(f, "invalid value for parameter {}", n),
+            Self::Interface(n)      => write!(f, "interfaces differ in port {}", n),
+            Self::ModuleConflict(n) => write!(f, "conflicting modules named {}", n),
+        }
+    }
+}
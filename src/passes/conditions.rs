@@ -0,0 +1,184 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Simplification of conditional statements
+
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::expr::{self, primitive::Operation};
+use crate::module::Kind;
+use crate::stmt::{self, Entity, Statement};
+
+
+type Expression = expr::Expression<Arc<Entity>>;
+
+
+/// Simplify conditional statements
+///
+/// This function reduces the nesting depth of conditional statements in all
+/// modules of the circuit by applying the following transformations:
+///
+/// * Conditional statements whose condition is known due to the conditions of
+///   enclosing statements are replaced by the statements of the branch taken.
+/// * Adjacent conditional statements with identical conditions are merged.
+/// * A conditional statement without an `else` branch containing only another
+///   conditional statement without an `else` branch, i.e. `when c: when d:`,
+///   is flattened into `when and(c, d):`.
+/// * Conditional statements with two empty branches are removed.
+///
+/// Conditions are compared structurally. Inside the `when` branch of a
+/// conditional statement, its condition is known to be true. If the condition
+/// is an `and`, the same holds for both operands. Inside the `else` branch, the
+/// condition is known to be false, which also holds for both operands if the
+/// condition is an `or`. The values of `not`, `and` and `or` conditions are
+/// derived from those of their operands if possible.
+pub fn simplify_whens(circuit: &Circuit) -> Circuit {
+    circuit.substitute_modules(|module| match module.kind() {
+        Kind::Regular{stmts} => {
+            let simplified = simplify(stmts, &mut Default::default());
+            if &simplified == stmts {
+                None
+            } else {
+                let mut module = module.as_ref().clone();
+                *module.kind_mut() = Kind::Regular{stmts: simplified};
+                Some(Arc::new(module))
+            }
+        },
+        _ => None,
+    })
+}
+
+
+/// Simplify conditional statements in a statement list
+fn simplify(stmts: &[Statement], facts: &mut Facts) -> Vec<Statement> {
+    let mut res = Vec::new();
+    stmts.iter().for_each(|s| match s.kind() {
+        stmt::Kind::Conditional{cond, when, r#else} => match facts.lookup(cond) {
+            Some(true)  => simplify(when, facts).into_iter().for_each(|s| push(&mut res, s)),
+            Some(false) => simplify(r#else, facts).into_iter().for_each(|s| push(&mut res, s)),
+            None        => {
+                let when = facts.under(cond, true, |f| simplify(when, f));
+                let r#else = facts.under(cond, false, |f| simplify(r#else, f));
+                if !when.is_empty() || !r#else.is_empty() {
+                    push(&mut res, flatten(s, cond, when, r#else))
+                }
+            },
+        },
+        _ => res.push(s.clone()),
+    });
+    res
+}
+
+
+/// Append a statement to a list, merging it with the last one if possible
+fn push(stmts: &mut Vec<Statement>, stmt: Statement) {
+    if let (stmt::Kind::Conditional{cond, when, r#else}, Some(last)) = (stmt.kind(), stmts.last_mut()) {
+        if let stmt::Kind::Conditional{cond: last_cond, when: last_when, r#else: last_else} = last.kind() {
+            if last_cond == cond {
+                let mut merged_when = last_when.to_vec();
+                when.iter().for_each(|s| push(&mut merged_when, s.clone()));
+                let mut merged_else = last_else.to_vec();
+                r#else.iter().for_each(|s| push(&mut merged_else, s.clone()));
+
+                *last = last.with_kind(stmt::Kind::Conditional{
+                    cond: cond.clone(),
+                    when: merged_when.into(),
+                    r#else: merged_else.into(),
+                });
+                return
+            }
+        }
+    }
+    stmts.push(stmt)
+}
+
+
+/// Create a conditional statement, flattening a nested one if possible
+///
+/// The resulting statement will carry the `info` of the `original` one.
+fn flatten(original: &Statement, cond: &Expression, when: Vec<Statement>, r#else: Vec<Statement>) -> Statement {
+    if let ([inner], true) = (when.as_slice(), r#else.is_empty()) {
+        if let stmt::Kind::Conditional{cond: inner_cond, when: inner_when, r#else: inner_else} = inner.kind() {
+            if inner_else.is_empty() {
+                return original.with_kind(stmt::Kind::Conditional{
+                    cond: Expression::PrimitiveOp(Operation::And(
+                        Arc::new(cond.clone()),
+                        Arc::new(inner_cond.clone()),
+                    )),
+                    when: inner_when.clone(),
+                    r#else: inner_else.clone(),
+                })
+            }
+        }
+    }
+
+    original.with_kind(stmt::Kind::Conditional{cond: cond.clone(), when: when.into(), r#else: r#else.into()})
+}
+
+
+/// Conditions known to be either true or false
+#[derive(Default)]
+struct Facts {
+    truths: Vec<Expression>,
+    falsities: Vec<Expression>,
+}
+
+impl Facts {
+    /// Determine the value of the given condition, if known
+    fn lookup(&self, cond: &Expression) -> Option<bool> {
+        if self.truths.contains(cond) {
+            Some(true)
+        } else if self.falsities.contains(cond) {
+            Some(false)
+        } else if let Expression::PrimitiveOp(op) = cond {
+            match op {
+                Operation::Not(sub)         => self.lookup(sub).map(|v| !v),
+                Operation::And(lhs, rhs)    => match (self.lookup(lhs), self.lookup(rhs)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true))            => Some(true),
+                    _                                   => None,
+                },
+                Operation::Or(lhs, rhs)     => match (self.lookup(lhs), self.lookup(rhs)) {
+                    (Some(true), _) | (_, Some(true))   => Some(true),
+                    (Some(false), Some(false))          => Some(false),
+                    _                                   => None,
+                },
+                _                           => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Perform some operation with an additional assumption
+    ///
+    /// This function calls `f` with the assumption that `cond` has the given
+    /// `value`. The assumption is dropped afterwards.
+    fn under<T>(&mut self, cond: &Expression, value: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let len = (self.truths.len(), self.falsities.len());
+        self.assume(cond, value);
+        let res = f(self);
+        self.truths.truncate(len.0);
+        self.falsities.truncate(len.1);
+        res
+    }
+
+    /// Record that the given condition has the given value
+    fn assume(&mut self, cond: &Expression, value: bool) {
+        match (cond, value) {
+            (Expression::PrimitiveOp(Operation::And(lhs, rhs)), true) |
+            (Expression::PrimitiveOp(Operation::Or(lhs, rhs)), false) => {
+                self.assume(lhs, value);
+                self.assume(rhs, value);
+            },
+            (Expression::PrimitiveOp(Operation::Not(sub)), _) => self.assume(sub, !value),
+            _ => (),
+        }
+
+        if value {
+            self.truths.push(cond.clone())
+        } else {
+            self.falsities.push(cond.clone())
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Generation of names for entities created by passes
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::module::Module;
+use crate::named::Named;
+use crate::stmt::Statement;
+
+
+/// Policy for names of entities created by passes
+///
+/// Names are generated from a prefix, an optional tag identifying the pass
+/// creating the entity and a counter, separated by underscores, e.g.
+/// `_GEN_strip_0`. The default prefix is `_GEN`. Tags may be disabled, in which
+/// case names only consist of the prefix and the counter, e.g. `_GEN_0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamePolicy {
+    prefix: Arc<str>,
+    tagged: bool,
+}
+
+impl NamePolicy {
+    /// Set the prefix of generated names
+    pub fn with_prefix(self, prefix: impl Into<Arc<str>>) -> Self {
+        Self {prefix: prefix.into(), ..self}
+    }
+
+    /// Set whether generated names include the tag of the creating pass
+    pub fn with_tags(self, tagged: bool) -> Self {
+        Self {tagged, ..self}
+    }
+
+    /// Retrieve the prefix of generated names
+    pub fn prefix(&self) -> &Arc<str> {
+        &self.prefix
+    }
+
+    /// Create a [Namespace] for generating names in the given module
+    pub fn namespace(&self, module: &Module) -> Namespace {
+        let taken = module
+            .ports()
+            .map(|p| p.name().clone())
+            .chain(module.statements().iter().flat_map(Statement::declarations).map(|e| e.name().clone()))
+            .collect();
+        Namespace {policy: self.clone(), taken, counters: Default::default()}
+    }
+
+    /// Compute the base of generated names for the given tag
+    fn base(&self, tag: &str) -> String {
+        if self.tagged && !tag.is_empty() {
+            format!("{}_{}", self.prefix, tag)
+        } else {
+            self.prefix.to_string()
+        }
+    }
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self {prefix: "_GEN".into(), tagged: true}
+    }
+}
+
+
+/// Generator for fresh names within a module
+///
+/// A namespace is created via [NamePolicy::namespace] and knows all names
+/// used in the module it was created for. Names are generated with counters
+/// starting at zero, separately for each tag. Names already taken are
+/// skipped. Thus, the names generated only depend on the module and the
+/// sequence of requests, which makes the output of passes reproducible.
+#[derive(Clone, Debug)]
+pub struct Namespace {
+    policy: NamePolicy,
+    taken: HashSet<Arc<str>>,
+    counters: HashMap<String, usize>,
+}
+
+impl Namespace {
+    /// Generate a fresh name for an entity created by the pass with the given tag
+    pub fn fresh(&mut self, tag: &str) -> Arc<str> {
+        let base = self.policy.base(tag);
+        let counter = self.counters.entry(base.clone()).or_default();
+        loop {
+            let name: Arc<str> = format!("{}_{}", base, counter).into();
+            *counter += 1;
+            if self.taken.insert(name.clone()) {
+                return name
+            }
+        }
+    }
+
+    /// Derive a fresh name from the given one
+    ///
+    /// If the given name is not yet taken, it is returned as is. Otherwise, a
+    /// counter is appended.
+    pub fn derive(&mut self, name: &str) -> Arc<str> {
+        if self.taken.insert(name.into()) {
+            name.into()
+        } else {
+            let counter = self.counters.entry(name.to_string()).or_default();
+            loop {
+                let res: Arc<str> = format!("{}_{}", name, counter).into();
+                *counter += 1;
+                if self.taken.insert(res.clone()) {
+                    return res
+                }
+            }
+        }
+    }
+
+    /// Mark the given name as taken
+    ///
+    /// This function returns `false` if the name was already taken.
+    pub fn reserve(&mut self, name: impl Into<Arc<str>>) -> bool {
+        self.taken.insert(name.into())
+    }
+
+    /// Check whether the given name is taken
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.taken.contains(name)
+    }
+}
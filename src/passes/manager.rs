@@ -0,0 +1,228 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Management of pass pipelines
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+
+use super::Error;
+
+
+/// A pass which may be run by a [PassManager]
+///
+/// A pass receives the current circuit and the [AnalysisCache] associated with
+/// it. It may retrieve artifacts published by earlier passes and publish new
+/// ones. If the pass transforms the circuit, it yields the resulting circuit.
+/// If it leaves the circuit unaltered, it yields `None`.
+///
+/// This trait is implemented for functions and closures of the appropriate
+/// signature, allowing existing passes to be wrapped easily:
+///
+/// ```
+/// use firrtl_ast::circuit::Circuit;
+/// use firrtl_ast::passes::{AnalysisCache, Error, PassManager, simplify_whens};
+///
+/// fn simplify(circuit: &Circuit, _: &mut AnalysisCache) -> Result<Option<Circuit>, Error> {
+///     Ok(Some(simplify_whens(circuit)))
+/// }
+///
+/// # let circuit = firrtl_ast::circuit::parse("circuit Top:\n  module Top:\n").unwrap();
+/// let mut manager = PassManager::new(circuit).with_pass(simplify);
+/// let circuit = manager.run().unwrap();
+/// ```
+pub trait Pass {
+    /// Run the pass on the given circuit
+    fn run(&self, circuit: &Circuit, cache: &mut AnalysisCache) -> Result<Option<Circuit>, Error>;
+}
+
+impl<F> Pass for F
+    where F: Fn(&Circuit, &mut AnalysisCache) -> Result<Option<Circuit>, Error>
+{
+    fn run(&self, circuit: &Circuit, cache: &mut AnalysisCache) -> Result<Option<Circuit>, Error> {
+        self(circuit, cache)
+    }
+}
+
+
+/// Manager for a pipeline of passes
+///
+/// A `PassManager` runs a sequence of [Pass]es on a circuit, each pass
+/// operating on the result of the previous one. Along with the current circuit,
+/// the manager owns an [AnalysisCache] through which passes share artifacts
+/// such as the results of expensive analyses. The cache is invalidated whenever
+/// a pass alters the circuit.
+///
+/// The state of the pipeline, i.e. the current circuit, the number of passes
+/// completed and the cache, may be saved as a [PipelineState] and restored
+/// later, e.g. for re-running the remaining passes with different settings.
+pub struct PassManager {
+    passes: Vec<Arc<dyn Pass>>,
+    state: PipelineState,
+}
+
+impl PassManager {
+    /// Create a new pass manager for the given circuit
+    pub fn new(circuit: Circuit) -> Self {
+        Self {passes: Default::default(), state: PipelineState {circuit, completed: 0, cache: Default::default()}}
+    }
+
+    /// Append a pass to the pipeline
+    pub fn with_pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.add_pass(pass);
+        self
+    }
+
+    /// Append a pass to the pipeline
+    pub fn add_pass(&mut self, pass: impl Pass + 'static) {
+        self.passes.push(Arc::new(pass))
+    }
+
+    /// Retrieve the current circuit
+    pub fn circuit(&self) -> &Circuit {
+        &self.state.circuit
+    }
+
+    /// Retrieve the cache associated with the current circuit
+    pub fn cache(&self) -> &AnalysisCache {
+        &self.state.cache
+    }
+
+    /// Retrieve the number of passes completed
+    pub fn completed(&self) -> usize {
+        self.state.completed
+    }
+
+    /// Run the next pass in the pipeline
+    ///
+    /// This function returns `false` if all passes were already completed. If
+    /// the pass fails, the error is returned and the pass will be run again
+    /// on the next call.
+    pub fn step(&mut self) -> Result<bool, Error> {
+        let pass = if let Some(pass) = self.passes.get(self.state.completed) {
+            pass.clone()
+        } else {
+            return Ok(false)
+        };
+
+        if let Some(circuit) = pass.run(&self.state.circuit, &mut self.state.cache)? {
+            self.state.circuit = circuit;
+            self.state.cache.invalidate();
+        }
+        self.state.completed += 1;
+        Ok(true)
+    }
+
+    /// Run all remaining passes in the pipeline
+    ///
+    /// This function returns the resulting circuit. If a pass fails, the
+    /// pipeline stops and the error is returned.
+    pub fn run(&mut self) -> Result<&Circuit, Error> {
+        while self.step()? {}
+        Ok(self.circuit())
+    }
+
+    /// Save the current state of the pipeline
+    pub fn save(&self) -> PipelineState {
+        self.state.clone()
+    }
+
+    /// Restore a previously saved state of the pipeline
+    ///
+    /// Passes are not part of the state. Thus, the pipeline will continue with
+    /// the pass following the ones completed when the state was saved.
+    pub fn restore(&mut self, state: PipelineState) {
+        self.state = state
+    }
+}
+
+
+/// Saved state of a [PassManager]
+#[derive(Clone, Debug)]
+pub struct PipelineState {
+    circuit: Circuit,
+    completed: usize,
+    cache: AnalysisCache,
+}
+
+impl PipelineState {
+    /// Retrieve the circuit
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// Retrieve the number of passes completed
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Retrieve the cache
+    pub fn cache(&self) -> &AnalysisCache {
+        &self.cache
+    }
+}
+
+
+/// Cache for artifacts shared among passes
+///
+/// Artifacts are identified by name and may be of any type. Since artifacts
+/// are shared, saving a [PipelineState] does not copy them.
+#[derive(Clone, Default)]
+pub struct AnalysisCache {
+    artifacts: HashMap<Arc<str>, Arc<dyn Any + Send + Sync>>,
+}
+
+impl AnalysisCache {
+    /// Publish an artifact under the given name
+    ///
+    /// The artifact replaces any artifact previously published under the same
+    /// name.
+    pub fn insert<T: Any + Send + Sync>(&mut self, name: impl Into<Arc<str>>, artifact: T) {
+        self.artifacts.insert(name.into(), Arc::new(artifact));
+    }
+
+    /// Retrieve the artifact with the given name
+    ///
+    /// This function returns `None` if no artifact of type `T` was published
+    /// under the given name.
+    pub fn get<T: Any + Send + Sync>(&self, name: impl AsRef<str>) -> Option<&T> {
+        self.artifacts.get(name.as_ref()).and_then(|a| a.downcast_ref())
+    }
+
+    /// Retrieve the artifact with the given name, computing it if necessary
+    ///
+    /// If no artifact of type `T` was published under the given name, this
+    /// function publishes the result of `f`.
+    pub fn get_or_insert_with<T: Any + Send + Sync>(&mut self, name: impl AsRef<str>, f: impl FnOnce() -> T) -> &T {
+        if self.get::<T>(name.as_ref()).is_none() {
+            self.insert(name.as_ref(), f());
+        }
+        self.get(name).expect("Artifact was just inserted")
+    }
+
+    /// Remove the artifact with the given name
+    ///
+    /// This function returns `true` if an artifact was removed.
+    pub fn remove(&mut self, name: impl AsRef<str>) -> bool {
+        self.artifacts.remove(name.as_ref()).is_some()
+    }
+
+    /// Remove all artifacts
+    pub fn invalidate(&mut self) {
+        self.artifacts.clear()
+    }
+
+    /// Retrieve the names of all artifacts, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.artifacts.keys()
+    }
+}
+
+impl fmt::Debug for AnalysisCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Generation of miters for equivalence checking
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::expr::{Expression, primitive::Operation};
+use crate::module::{Direction, Instance, Kind, Module, Port};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement, verification::Op};
+use crate::types::{GroundType, Orientation, Type};
+
+use super::{Error, NamePolicy};
+
+
+/// Generate a miter for checking the equivalence of two modules
+///
+/// This function generates a circuit with a new top module instantiating both
+/// `left` and `right`. The miter features the same ports as the two modules.
+/// Inputs of the miter drive the corresponding inputs of both instances and
+/// outputs are driven by `left`. For every output signal, an `assert`
+/// statement checks that both instances produce the same value, allowing the
+/// equivalence of the modules to be established via formal verification.
+/// Analog signals are attached to each other instead.
+///
+/// The assertions are triggered by the first input clock of the modules. If
+/// there is none, a dedicated clock input is added to the miter. The function
+/// fails if the ports of the modules differ in name, type or direction or if
+/// different modules within the two hierarchies share a name.
+pub fn miter(left: &Arc<Module>, right: &Arc<Module>) -> Result<Circuit, Error> {
+    let ports = left.ports_sorted();
+    let other = right.ports_sorted();
+    if let Some(p) = ports
+        .iter()
+        .zip(other.iter())
+        .find(|(l, r)| l.name() != r.name() || l.r#type() != r.r#type() || l.direction() != r.direction())
+        .map(|(l, _)| l.name())
+        .or_else(|| ports.get(other.len()).or_else(|| other.get(ports.len())).map(|p| p.name())) {
+        return Err(Error::Interface(p.clone()))
+    }
+
+    // Both hierarchies end up in the same circuit
+    let mut modules: HashMap<Arc<str>, Arc<Module>> = Default::default();
+    for module in Circuit::new(left.clone()).modules().chain(Circuit::new(right.clone()).modules()) {
+        match modules.get(module.name()) {
+            Some(m) if m != module  => return Err(Error::ModuleConflict(module.name().clone())),
+            Some(_)                 => (),
+            None                    => { modules.insert(module.name().clone(), module.clone()); },
+        }
+    }
+    let taken: HashSet<_> = modules.keys().cloned().collect();
+    let name = (0..)
+        .map(|i| if i == 0 { format!("{}_miter", left.name()) } else { format!("{}_miter_{}", left.name(), i) })
+        .map(Arc::<str>::from)
+        .find(|n| !taken.contains(n))
+        .unwrap_or_else(|| "miter".into());
+
+    let ports: Vec<_> = left
+        .ports()
+        .map(|p| Arc::new(Port::new(p.name().clone(), p.r#type().clone(), p.direction())))
+        .collect();
+    let mut module = Module::new(name, ports.iter().cloned(), Kind::empty_regular());
+    let mut namespace = NamePolicy::default().namespace(&module);
+
+    let clock = match ports.iter().find(|p| p.direction() == Direction::Input && p.r#type() == &GroundType::Clock) {
+        Some(p) => p.clone(),
+        None    => {
+            let port = Arc::new(Port::new(namespace.derive("clock"), GroundType::Clock.into(), Direction::Input));
+            module.add_port(port.clone())?;
+            port
+        },
+    };
+    let clock = Expression::Reference(Arc::new(Entity::Port(clock)));
+
+    let left = Arc::new(Entity::Instance(Instance::new(namespace.derive("left"), left.clone())));
+    let right = Arc::new(Entity::Instance(Instance::new(namespace.derive("right"), right.clone())));
+    let sub = |base: &Arc<Entity>, index: &Arc<str>| Expression::SubField{
+        base: Arc::new(base.clone().into()),
+        index: index.clone(),
+    };
+
+    let mut stmts: Vec<Statement> = vec![
+        stmt::Kind::Declaration(left.clone()).into(),
+        stmt::Kind::Declaration(right.clone()).into(),
+    ];
+    for port in ports {
+        let name = port.name().clone();
+        tie(
+            [Arc::new(Entity::Port(port.clone())).into(), sub(&left, &name), sub(&right, &name)],
+            port.r#type(),
+            port.direction(),
+            name.to_string(),
+            &clock,
+            &mut stmts,
+        )
+    }
+    *module.kind_mut() = Kind::Regular{stmts};
+
+    Ok(Circuit::new(Arc::new(module)))
+}
+
+
+/// Tie together the signals of a miter and the two instances
+///
+/// The `signals` are the signal of the miter itself and the corresponding
+/// signals of the left and right instance. Inputs of the miter drive the
+/// inputs of both instances. Outputs are driven by the left instance and
+/// compared against the right one. The `label` identifies the signal in the
+/// messages of the generated assertions.
+fn tie(
+    signals: [Expression<Arc<Entity>>; 3],
+    r#type: &Type,
+    direction: Direction,
+    label: String,
+    clock: &Expression<Arc<Entity>>,
+    stmts: &mut Vec<Statement>,
+) {
+    let [outer, left, right] = signals;
+    match (r#type, direction) {
+        (Type::GroundType(GroundType::Analog(_)), _)    => stmts.push(stmt::Kind::Attach(vec![outer, left, right]).into()),
+        (Type::GroundType(_), Direction::Input)         => {
+            stmts.push(stmt::Kind::Connection{from: outer.clone(), to: left}.into());
+            stmts.push(stmt::Kind::Connection{from: outer, to: right}.into());
+        },
+        (Type::GroundType(g), Direction::Output)        => {
+            let comparable = |e: Expression<Arc<Entity>>| match g {
+                GroundType::UInt(_) | GroundType::SInt(_)   => e,
+                _ => Expression::PrimitiveOp(Operation::Cast(Arc::new(e), GroundType::UInt(None))),
+            };
+            let pred = Operation::Eq(Arc::new(comparable(left.clone())), Arc::new(comparable(right)));
+            stmts.push(stmt::Kind::Connection{from: left, to: outer}.into());
+            stmts.push(stmt::Kind::Verification{
+                name: None,
+                op: Op::Assert,
+                clock: clock.clone(),
+                pred: Expression::PrimitiveOp(pred),
+                en: Expression::UIntLiteral{value: 1u8.into(), width: 1},
+                msg: format!("{} differs", label),
+            }.into());
+        },
+        (Type::Vector(t, w), _)                         => (0..*w).for_each(|i| {
+            let index = |e: &Expression<Arc<Entity>>| Expression::SubIndex{base: Arc::new(e.clone()), index: i};
+            tie(
+                [index(&outer), index(&left), index(&right)],
+                t,
+                direction,
+                format!("{}[{}]", label, i),
+                clock,
+                stmts,
+            )
+        }),
+        (Type::Bundle(v), _)                            => v.iter().for_each(|f| {
+            let field = |e: &Expression<Arc<Entity>>| Expression::SubField{
+                base: Arc::new(e.clone()),
+                index: f.name().clone(),
+            };
+            let direction = match (direction, f.orientation()) {
+                (d,                 Orientation::Normal)    => d,
+                (Direction::Input,  Orientation::Flipped)   => Direction::Output,
+                (Direction::Output, Orientation::Flipped)   => Direction::Input,
+            };
+            tie(
+                [field(&outer), field(&left), field(&right)],
+                f.r#type(),
+                direction,
+                format!("{}.{}", label, f.name()),
+                clock,
+                stmts,
+            )
+        }),
+        (Type::Probe(..), _) | (Type::Enum(..), _)      => (),
+        (Type::Alias(_, t), _)                          => tie([outer, left, right], t, direction, label, clock, stmts),
+    }
+}
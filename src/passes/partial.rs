@@ -0,0 +1,220 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Expansion of partial connections
+
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::expr::{self, primitive::Operation};
+use crate::module::Kind;
+use crate::named::Named;
+use crate::report::{Finding, Report, Severity};
+use crate::stmt::{self, Entity, Statement};
+use crate::target::{InstancePath, Target};
+use crate::types::{GroundType, Orientation, Type, TypeExt, Typed};
+
+
+/// Expression type used in statements
+type Expression = expr::Expression<Arc<Entity>>;
+
+
+/// Id of findings reporting illegal partial connections
+pub const ILLEGAL_PARTIAL_CONNECT: &str = "illegal-partial-connect";
+
+/// Id of findings reporting parts not connected by a partial connection
+pub const DROPPED_BY_PARTIAL_CONNECT: &str = "dropped-by-partial-connect";
+
+
+/// Expand partial connections into full connections
+///
+/// This function replaces every partial connection (`<-`) in the circuit with
+/// full connections (`<=`) of the ground type elements the partial connection
+/// connects according to FIRRTL's partial connection rules:
+///
+///  * Ground types of the same kind are connected. If the source is wider than
+///    the sink and both widths are known, the source is truncated.
+///  * For vectors, elements up to the length of the shorter vector are
+///    connected.
+///  * For bundles, fields with the same name on both sides are connected. Such
+///    fields need to have the same orientation. Flipped elements are
+///    connected in the reverse direction.
+///
+/// Partial connections violating these rules are retained and reported in the
+/// returned [Report] with [Severity::Error]. Vector elements and bundle fields
+/// dropped from a legal partial connection are reported with
+/// [Severity::Info].
+pub fn expand_partial_connects(circuit: &Circuit) -> (Circuit, Report) {
+    let mut report = Report::new();
+    let circuit = circuit.substitute_modules(|module| match module.kind() {
+        Kind::Regular{stmts} => {
+            let path = InstancePath::new(module.name().clone());
+            let mut expander = Expander {path, report: &mut report, changed: false};
+            let stmts = expander.stmts(stmts);
+            if !expander.changed {
+                return None
+            }
+
+            let mut module = module.as_ref().clone();
+            *module.kind_mut() = Kind::Regular{stmts};
+            Some(Arc::new(module))
+        },
+        _ => None,
+    });
+    (circuit, report)
+}
+
+
+/// Helper for expanding partial connections in a single module
+struct Expander<'r> {
+    path: InstancePath,
+    report: &'r mut Report,
+    changed: bool,
+}
+
+impl Expander<'_> {
+    /// Expand partial connections in the given statements
+    fn stmts(&mut self, stmts: &[Statement]) -> Vec<Statement> {
+        stmts.iter().flat_map(|s| match s.kind() {
+            stmt::Kind::PartialConnection{from, to} => self.connection(s, from, to),
+            stmt::Kind::Conditional{cond, when, r#else} => vec![s.with_kind(stmt::Kind::Conditional{
+                cond: cond.clone(),
+                when: self.stmts(when).into(),
+                r#else: self.stmts(r#else).into(),
+            })],
+            _ => vec![s.clone()],
+        }).collect()
+    }
+
+    /// Expand a single partial connection
+    fn connection(&mut self, stmt: &Statement, from: &Expression, to: &Expression) -> Vec<Statement> {
+        let target = root_name(to).map(|n| Target::reference(self.path.clone(), n.clone()));
+        let finding = |severity, message: String| Finding::new(
+            if severity == Severity::Error { ILLEGAL_PARTIAL_CONNECT } else { DROPPED_BY_PARTIAL_CONNECT },
+            severity,
+            message,
+        ).with_target(target.clone());
+
+        let types = to.r#type().ok().zip(from.r#type().ok());
+        let (to_type, from_type) = if let Some(types) = types {
+            types
+        } else {
+            self.report.push(finding(Severity::Error, format!("cannot type `{} <- {}`", to, from)));
+            return vec![stmt.clone()]
+        };
+
+        let mut expansion = Expansion::default();
+        match expansion.expand(to, from, &to_type, &from_type, Orientation::Normal) {
+            Ok(()) => {
+                self.changed = true;
+                self.report.extend(expansion.dropped.into_iter().map(|d| finding(
+                    Severity::Info,
+                    format!("`{}` is not connected by `{} <- {}`", d, to, from),
+                )));
+                expansion.connections.into_iter().map(|k| stmt.with_kind(k)).collect()
+            },
+            Err(msg) => {
+                self.report.push(finding(Severity::Error, format!("illegal partial connection `{} <- {}`: {}", to, from, msg)));
+                vec![stmt.clone()]
+            },
+        }
+    }
+}
+
+
+/// Full connections and dropped parts resulting from a partial connection
+#[derive(Default)]
+struct Expansion {
+    connections: Vec<stmt::Kind>,
+    dropped: Vec<Expression>,
+}
+
+impl Expansion {
+    /// Expand the partial connection of `from` to `to`
+    ///
+    /// The orientation denotes whether the elements are connected in reverse
+    /// direction.
+    fn expand(
+        &mut self,
+        to: &Expression,
+        from: &Expression,
+        to_type: &Type,
+        from_type: &Type,
+        orientation: Orientation,
+    ) -> Result<(), String> {
+        match (to_type.resolved(), from_type.resolved()) {
+            (Type::GroundType(t), Type::GroundType(f)) => {
+                if matches!(t, GroundType::Analog(_)) || !(TypeExt::eq(t, f) || t == f) {
+                    return Err(format!("cannot connect `{}` of type {} to `{}` of type {}", from, f, to, t))
+                }
+                let connection = if orientation == Orientation::Normal {
+                    stmt::Kind::Connection{from: truncated(from, f, t), to: to.clone()}
+                } else {
+                    stmt::Kind::Connection{from: truncated(to, t, f), to: from.clone()}
+                };
+                self.connections.push(connection);
+                Ok(())
+            },
+            (Type::Vector(t, tw), Type::Vector(f, fw)) => {
+                (0..*tw.min(fw)).try_for_each(|i| self.expand(
+                    &Expression::SubIndex{base: Arc::new(to.clone()), index: i},
+                    &Expression::SubIndex{base: Arc::new(from.clone()), index: i},
+                    t,
+                    f,
+                    orientation,
+                ))?;
+                let (longer, min, max) = if tw > fw { (to, *fw, *tw) } else { (from, *tw, *fw) };
+                self.dropped.extend((min..max).map(|i| Expression::SubIndex{base: Arc::new(longer.clone()), index: i}));
+                Ok(())
+            },
+            (Type::Bundle(t), Type::Bundle(f)) => {
+                t.iter().try_for_each(|tf| match f.iter().find(|ff| ff.name() == tf.name()) {
+                    Some(ff) if ff.orientation() != tf.orientation() =>
+                        Err(format!("field `{}` has different orientations", tf.name())),
+                    Some(ff) => self.expand(
+                        &Expression::SubField{base: Arc::new(to.clone()), index: tf.name().clone()},
+                        &Expression::SubField{base: Arc::new(from.clone()), index: ff.name().clone()},
+                        tf.r#type(),
+                        ff.r#type(),
+                        orientation + tf.orientation(),
+                    ),
+                    None => {
+                        self.dropped.push(Expression::SubField{base: Arc::new(to.clone()), index: tf.name().clone()});
+                        Ok(())
+                    },
+                })?;
+                self.dropped.extend(f
+                    .iter()
+                    .filter(|ff| !t.iter().any(|tf| tf.name() == ff.name()))
+                    .map(|ff| Expression::SubField{base: Arc::new(from.clone()), index: ff.name().clone()}));
+                Ok(())
+            },
+            _ => Err(format!("types of `{}` and `{}` don't match", to, from)),
+        }
+    }
+}
+
+
+/// Truncate an expression of the given ground type to the width of a sink
+///
+/// Only `UInt` and `SInt` expressions with known widths are truncated.
+fn truncated(expr: &Expression, r#type: &GroundType, sink: &GroundType) -> Expression {
+    let tail = |w: u16, s: u16| Arc::new(Expression::PrimitiveOp(Operation::Bits(Arc::new(expr.clone()), Some(w - s), None)));
+    match (r#type, sink) {
+        (GroundType::UInt(Some(w)), GroundType::UInt(Some(s))) if w > s => tail(*w, *s).as_ref().clone(),
+        (GroundType::SInt(Some(w)), GroundType::SInt(Some(s))) if w > s =>
+            Expression::PrimitiveOp(Operation::Cast(tail(*w, *s), GroundType::SInt(None))),
+        _ => expr.clone(),
+    }
+}
+
+
+/// Retrieve the name of the entity at the root of an expression
+fn root_name(expr: &Expression) -> Option<&Arc<str>> {
+    match expr {
+        expr::Expression::Reference(r)          => Some(r.name()),
+        expr::Expression::SubField{base, ..}    => root_name(base),
+        expr::Expression::SubIndex{base, ..}    => root_name(base),
+        expr::Expression::SubAccess{base, ..}   => root_name(base),
+        _ => None,
+    }
+}
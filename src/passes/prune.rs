@@ -0,0 +1,70 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Pruning of conditional statements
+
+use std::sync::Arc;
+
+use num_traits::Zero;
+
+use crate::circuit::Circuit;
+use crate::expr;
+use crate::module::Kind;
+use crate::stmt::{self, Entity, Statement};
+
+
+/// Prune conditional statements with constant conditions
+///
+/// This function replaces every conditional statement whose condition is a
+/// `UInt` literal, e.g. `when UInt<1>(1):`, with the statements of the branch
+/// taken. The statements are spliced into the enclosing statement list. This
+/// is done recursively, i.e. for nested conditional statements, too.
+///
+/// Only literal conditions are considered. Use [specialize](super::specialize)
+/// for also folding conditions depending on constant values.
+pub fn prune_constant_whens(circuit: &Circuit) -> Circuit {
+    circuit.substitute_modules(|module| match module.kind() {
+        Kind::Regular{stmts} if stmts.iter().any(has_constant_cond) => {
+            let mut module = module.as_ref().clone();
+            *module.kind_mut() = Kind::Regular{stmts: prune(stmts)};
+            Some(Arc::new(module))
+        },
+        _ => None,
+    })
+}
+
+
+/// Prune conditional statements with constant conditions in a statement list
+fn prune(stmts: &[Statement]) -> Vec<Statement> {
+    stmts.iter().flat_map(|s| match s.kind() {
+        stmt::Kind::Conditional{cond, when, r#else} => match constant(cond) {
+            Some(true)  => prune(when),
+            Some(false) => prune(r#else),
+            None        => vec![s.with_kind(stmt::Kind::Conditional{
+                cond: cond.clone(),
+                when: prune(when).into(),
+                r#else: prune(r#else).into(),
+            })],
+        },
+        _ => vec![s.clone()],
+    }).collect()
+}
+
+
+/// Determine the value of a constant condition
+///
+/// This function returns `None` if the expression is not a `UInt` literal.
+pub(crate) fn constant(expr: &expr::Expression<Arc<Entity>>) -> Option<bool> {
+    if let expr::Expression::UIntLiteral{value, ..} = expr {
+        Some(!value.is_zero())
+    } else {
+        None
+    }
+}
+
+
+/// Check whether a statement contains a conditional with a constant condition
+fn has_constant_cond(stmt: &Statement) -> bool {
+    use transiter::AutoTransIter;
+
+    stmt.trans_iter().any(|s| matches!(s.kind(), stmt::Kind::Conditional{cond, ..} if constant(cond).is_some()))
+}
@@ -0,0 +1,503 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Specialization of configurable designs
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+
+use crate::circuit::Circuit;
+use crate::expr::{self, primitive::Operation};
+use crate::memory::{Register, simple};
+use crate::module::{Direction, Kind, ParamValue};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement};
+use crate::types::{GroundType, Type, Typed};
+
+use super::Error;
+use super::prune::constant;
+
+
+/// Expression type used in statements
+type Expression = expr::Expression<Arc<Entity>>;
+
+
+/// Specialize a circuit for the given parameter values
+///
+/// This function implements a lightweight form of conditional compilation.
+/// Parameters are represented by input ports of type `UInt` or `SInt` of the
+/// top module: all references to an input port named after a parameter are
+/// replaced by the parameter's value. Constants are passed down the hierarchy
+/// through instance connections: an input port of a module is replaced by a
+/// literal if every instance of the module connects the same literal to it,
+/// unconditionally. Subsequently,
+///
+///  * expressions are folded where their operands are constant, including
+///    `mux`es with a constant selector,
+///  * conditional statements with constant conditions are replaced by the
+///    statements of the branch taken and
+///  * nodes which became unused are removed.
+///
+/// The ports themselves are retained. This function fails if a parameter
+/// value is not an integer or the corresponding port is of another type.
+pub fn specialize(circuit: &Circuit, params: &HashMap<Arc<str>, ParamValue>) -> Result<Circuit, Error> {
+    let top = circuit.top_module();
+    let mut top_consts = HashMap::new();
+    for port in top.ports().filter(|p| p.direction() == Direction::Input) {
+        if let Some(value) = params.get(port.name()) {
+            let lit = literal(value, port.r#type()).ok_or_else(|| Error::Parameter(port.name().clone()))?;
+            top_consts.insert(port.name().clone(), lit);
+        }
+    }
+
+    // Modules are visited top-down, i.e. after all modules instantiating them.
+    let mut inputs: HashMap<Arc<str>, HashMap<Arc<str>, Option<Expression>>> = Default::default();
+    let mut consts: HashMap<Arc<str>, HashMap<Arc<str>, Expression>> = Default::default();
+    for module in circuit.modules_sorted().into_iter().rev() {
+        let module_consts = if module.name() == top.name() {
+            top_consts.clone()
+        } else {
+            let driven = inputs.remove(module.name()).unwrap_or_default();
+            module
+                .ports()
+                .filter_map(|p| driven
+                    .get(p.name())
+                    .and_then(|v| v.as_ref())
+                    .and_then(|v| retyped(v, p.r#type()))
+                    .map(|v| (p.name().clone(), v)))
+                .collect()
+        };
+
+        if let Kind::Regular{stmts} = module.kind() {
+            let stmts = Folder{consts: module_consts.clone(), ..Default::default()}.stmts(stmts);
+            for (name, driven) in instance_inputs(&stmts) {
+                let entry = inputs.entry(name).or_default();
+                driven.into_iter().for_each(|(port, value)| merge(entry, port, value));
+            }
+        }
+        consts.insert(module.name().clone(), module_consts);
+    }
+
+    Ok(circuit.substitute_modules(|module| match module.kind() {
+        Kind::Regular{stmts} => {
+            let mut folder = Folder{
+                consts: consts.get(module.name()).cloned().unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let referenced = referenced_names(stmts);
+            let stmts = folder.stmts(stmts);
+            let stmts = remove_unused_nodes(stmts, |n| folder.consts.contains_key(n) || referenced.contains(n));
+
+            let mut module = module.as_ref().clone();
+            *module.kind_mut() = Kind::Regular{stmts};
+            Some(Arc::new(module))
+        },
+        _ => None,
+    }))
+}
+
+
+/// Determine the values driven into the input ports of instantiated modules
+///
+/// This function yields, for every module instantiated in the given
+/// statements, the input ports of that module alongside the literal all
+/// instances connect to it unconditionally, if any.
+fn instance_inputs(stmts: &[Statement]) -> HashMap<Arc<str>, HashMap<Arc<str>, Option<Expression>>> {
+    /// Values driven into instances, by instance and port
+    type Drivers = HashMap<(Arc<str>, Option<Arc<str>>), Option<Expression>>;
+
+    fn drivers(stmts: &[Statement], nested: bool, res: &mut Drivers) {
+        for stmt in stmts {
+            match stmt.kind() {
+                stmt::Kind::Connection{from, to} | stmt::Kind::PartialConnection{from, to} => {
+                    if let Some((inst, port, exact)) = target(to) {
+                        let value = Some(from.clone())
+                            .filter(|f| !nested && exact && port.is_some() && constant_value(f));
+                        res.entry((inst, port)).and_modify(|v| *v = None).or_insert(value);
+                    }
+                },
+                stmt::Kind::Invalidate(expr) => if let Some((inst, port, _)) = target(expr) {
+                    res.insert((inst, port), None);
+                },
+                stmt::Kind::Conditional{when, r#else, ..} => {
+                    drivers(when, true, res);
+                    drivers(r#else, true, res);
+                },
+                stmt::Kind::Match{arms, ..} => arms.iter().for_each(|a| drivers(a.stmts(), true, res)),
+                stmt::Kind::LayerBlock{stmts, ..} => drivers(stmts, true, res),
+                _ => (),
+            }
+        }
+    }
+
+    let mut driven = Default::default();
+    drivers(stmts, false, &mut driven);
+
+    let mut res: HashMap<_, HashMap<_, _>> = HashMap::new();
+    for stmt in stmts {
+        for inst in stmt.instantiations() {
+            let module = inst.module();
+            let entry = res.entry(module.name().clone()).or_default();
+            for port in module.ports().filter(|p| p.direction() == Direction::Input) {
+                let value = if driven.contains_key(&(inst.name().clone(), None)) {
+                    None
+                } else {
+                    driven.get(&(inst.name().clone(), Some(port.name().clone()))).cloned().flatten()
+                };
+                merge(entry, port.name().clone(), value);
+            }
+        }
+        for module in stmt.instance_choices().flat_map(|i| i.modules()) {
+            let entry = res.entry(module.name().clone()).or_default();
+            for port in module.ports().filter(|p| p.direction() == Direction::Input) {
+                merge(entry, port.name().clone(), None);
+            }
+        }
+    }
+    res
+}
+
+
+/// Determine the instance and port targeted by the given expression
+///
+/// This function yields the name of the instance the expression refers to, the
+/// port if any and whether the expression denotes exactly that port.
+fn target(expr: &Expression) -> Option<(Arc<str>, Option<Arc<str>>, bool)> {
+    use expr::Expression as E;
+
+    match expr {
+        E::Reference(r) if matches!(r.as_ref(), Entity::Instance(_)) => Some((r.name().clone(), None, true)),
+        E::SubField{base, index} => match target(base)? {
+            (inst, None, true) => Some((inst, Some(index.clone()), true)),
+            (inst, port, _) => Some((inst, port, false)),
+        },
+        E::SubIndex{base, ..} | E::SubAccess{base, ..} => target(base).map(|(i, p, _)| (i, p, false)),
+        _ => None,
+    }
+}
+
+
+/// Record a value driven into a port, retaining only values common to all instances
+fn merge(driven: &mut HashMap<Arc<str>, Option<Expression>>, port: Arc<str>, value: Option<Expression>) {
+    driven.entry(port).and_modify(|v| if *v != value { *v = None }).or_insert(value);
+}
+
+
+/// Adapt a literal to the given port type
+fn retyped(value: &Expression, r#type: &Type) -> Option<Expression> {
+    match (value, r#type.resolved()) {
+        (expr::Expression::UIntLiteral{value, width}, Type::GroundType(GroundType::UInt(w))) =>
+            Expression::uint_w(value.clone(), w.unwrap_or(*width)).ok(),
+        (expr::Expression::SIntLiteral{value, width}, Type::GroundType(GroundType::SInt(w))) =>
+            Expression::sint_w(value.clone(), w.unwrap_or(*width)).ok(),
+        _ => None,
+    }
+}
+
+
+/// Create a literal for the given parameter value and port type
+fn literal(value: &ParamValue, r#type: &Type) -> Option<Expression> {
+    let value = value.as_int().ok()?;
+    match r#type.resolved() {
+        Type::GroundType(GroundType::UInt(w)) => {
+            let value = BigUint::try_from(value).ok()?;
+            let width = w.or_else(|| u16::try_from(expr::uint_width(&value).max(1)).ok())?;
+            Expression::uint_w(value, width).ok()
+        },
+        Type::GroundType(GroundType::SInt(w)) => {
+            let value = BigInt::from(value);
+            let width = w.or_else(|| u16::try_from(expr::sint_width(&value)).ok())?;
+            Expression::sint_w(value, width).ok()
+        },
+        _ => None,
+    }
+}
+
+
+/// Helper for folding statements and expressions
+#[derive(Default)]
+pub(crate) struct Folder {
+    /// Entities with constant values
+    consts: HashMap<Arc<str>, Expression>,
+    /// Rebuilt entities
+    entities: HashMap<Arc<str>, Arc<Entity>>,
+}
+
+impl Folder {
+    /// Fold the given statements
+    pub fn stmts(&mut self, stmts: &[Statement]) -> Vec<Statement> {
+        stmts.iter().flat_map(|s| self.stmt(s)).collect()
+    }
+
+    /// Fold the given statement
+    ///
+    /// Conditional statements with constant conditions are replaced by the
+    /// statements of the branch taken. Thus, this function may yield any
+    /// number of statements.
+    fn stmt(&mut self, stmt: &Statement) -> Vec<Statement> {
+        let kind = match stmt.kind() {
+            stmt::Kind::Connection{from, to}            =>
+                stmt::Kind::Connection{from: self.expr(from), to: self.expr(to)},
+            stmt::Kind::PartialConnection{from, to}     =>
+                stmt::Kind::PartialConnection{from: self.expr(from), to: self.expr(to)},
+            stmt::Kind::Define{from, to}                =>
+                stmt::Kind::Define{from: self.expr(from), to: self.expr(to)},
+            stmt::Kind::Declaration(entity)             => stmt::Kind::Declaration(self.entity(entity)),
+            stmt::Kind::Invalidate(expr)                => stmt::Kind::Invalidate(self.expr(expr)),
+            stmt::Kind::Attach(exprs)                   =>
+                stmt::Kind::Attach(exprs.iter().map(|e| self.expr(e)).collect()),
+            stmt::Kind::Conditional{cond, when, r#else} => {
+                let cond = self.expr(cond);
+                match constant(&cond) {
+                    Some(true)  => return self.stmts(when),
+                    Some(false) => return self.stmts(r#else),
+                    None        => stmt::Kind::Conditional{
+                        cond,
+                        when: self.stmts(when).into(),
+                        r#else: self.stmts(r#else).into(),
+                    },
+                }
+            },
+            stmt::Kind::Match{value, arms}              => stmt::Kind::Match{
+                value: self.expr(value),
+                arms: arms.iter().map(|a| a.with_contents(a.binder().cloned(), self.stmts(a.stmts()))).collect(),
+            },
+            stmt::Kind::LayerBlock{layer, stmts}        =>
+                stmt::Kind::LayerBlock{layer: layer.clone(), stmts: self.stmts(stmts).into()},
+            stmt::Kind::Stop{name, clock, cond, code}   => stmt::Kind::Stop{
+                name: name.clone(),
+                clock: self.expr(clock),
+                cond: self.expr(cond),
+                code: *code,
+            },
+            stmt::Kind::Print{name, clock, cond, msg}   => stmt::Kind::Print{
+                name: name.clone(),
+                clock: self.expr(clock),
+                cond: self.expr(cond),
+                msg: msg.iter().map(|p| match p {
+                    stmt::print::PrintElement::Value(e, f)  => stmt::print::PrintElement::Value(self.expr(e), *f),
+                    p                                       => p.clone(),
+                }).collect(),
+            },
+            stmt::Kind::Verification{name, op, clock, pred, en, msg} => stmt::Kind::Verification{
+                name: name.clone(),
+                op: *op,
+                clock: self.expr(clock),
+                pred: self.expr(pred),
+                en: self.expr(en),
+                msg: msg.clone(),
+            },
+            stmt::Kind::Extension(custom)               =>
+                stmt::Kind::Extension(custom.map_expressions(&mut |e| self.expr(e))),
+            kind                                        => kind.clone(),
+        };
+        vec![stmt.with_kind(kind)]
+    }
+
+    /// Fold the given entity
+    ///
+    /// Nodes with constant values will be recorded as constants.
+    fn entity(&mut self, entity: &Arc<Entity>) -> Arc<Entity> {
+        let rebuilt = match entity.as_ref() {
+            Entity::Node{name, value} => {
+                let value = self.expr(value);
+                if constant_value(&value) {
+                    self.consts.insert(name.clone(), value.clone());
+                }
+                Entity::Node{name: name.clone(), value}
+            },
+            Entity::Register(reg) => {
+                let r#type = reg.r#type().map_err(|_| ()).expect("Registers are always typed");
+                let reset = reg.reset_signal().zip(reg.reset_value()).map(|(s, v)| (self.expr(s), self.expr(v)));
+                Entity::Register(Register::new(reg.name().clone(), r#type, self.expr(reg.clock()))
+                    .with_optional_reset(reset))
+            },
+            Entity::SimpleMemPort(port) => Entity::SimpleMemPort(simple::Port::new(
+                port.name().clone(),
+                port.memory().clone(),
+                port.direction(),
+                self.expr(port.address()),
+                self.expr(port.clock()),
+            )),
+            _ => return entity.clone(),
+        };
+
+        let res = if &rebuilt == entity.as_ref() { entity.clone() } else { Arc::new(rebuilt) };
+        self.entities.insert(res.name().clone(), res.clone());
+        res
+    }
+
+    /// Re-point a reference to a rebuilt entity
+    fn reference(&self, entity: &Arc<Entity>) -> Arc<Entity> {
+        self.entities.get(entity.name()).cloned().unwrap_or_else(|| entity.clone())
+    }
+
+    /// Fold the given expression
+    pub fn expr(&mut self, expr: &Expression) -> Expression {
+        use expr::Expression as E;
+
+        let mut sub = |e: &Arc<Expression>| Arc::new(self.expr(e));
+        let res = match expr {
+            E::Reference(r)             => return self
+                .consts
+                .get(r.name())
+                .cloned()
+                .unwrap_or_else(|| E::Reference(self.reference(r))),
+            E::SubField{base, index}    => E::SubField{base: sub(base), index: index.clone()},
+            E::SubIndex{base, index}    => E::SubIndex{base: sub(base), index: *index},
+            E::SubAccess{base, index}   => E::SubAccess{base: sub(base), index: sub(index)},
+            E::Mux{sel, a, b}           => E::Mux{sel: sub(sel), a: sub(a), b: sub(b)},
+            E::ValidIf{sel, value}      => E::ValidIf{sel: sub(sel), value: sub(value)},
+            E::PrimitiveOp(op)          => E::PrimitiveOp(op.map_exprs(sub)),
+            // Probes need to refer to the original entities rather than
+            // to their constant values.
+            E::Probe{target, kind}      => return E::Probe{
+                target: Arc::new(target.map_references(&mut |r| self.reference(r))),
+                kind: *kind,
+            },
+            E::Read(probe)              => E::Read(sub(probe)),
+            lit                         => return lit.clone(),
+        };
+        fold(res)
+    }
+}
+
+
+/// Fold an expression with already folded sub-expressions
+fn fold(expr: Expression) -> Expression {
+    use expr::Expression as E;
+
+    let uint = |e: &Expression| if let E::UIntLiteral{value, width} = e {
+        Some((value.clone(), *width))
+    } else {
+        None
+    };
+    let bool_lit = |b: bool| E::UIntLiteral{value: if b { One::one() } else { Zero::zero() }, width: 1};
+    let mask = |width: u16| (BigUint::one() << width) - BigUint::one();
+
+    let res = match &expr {
+        E::Mux{sel, a, b} => match constant(sel) {
+            Some(true)  => extended(a, &expr),
+            Some(false) => extended(b, &expr),
+            None        => None,
+        },
+        E::ValidIf{sel, value} if constant(sel) == Some(true) => Some(value.as_ref().clone()),
+        E::PrimitiveOp(op) => {
+            let operands: Option<Vec<_>> = op.sub_exprs().into_iter().map(|e| uint(e)).collect();
+            match (op, operands.as_deref()) {
+                (Operation::Eq(..), Some([(a, _), (b, _)]))     => Some(bool_lit(a == b)),
+                (Operation::NEq(..), Some([(a, _), (b, _)]))    => Some(bool_lit(a != b)),
+                (Operation::Lt(..), Some([(a, _), (b, _)]))     => Some(bool_lit(a < b)),
+                (Operation::LEq(..), Some([(a, _), (b, _)]))    => Some(bool_lit(a <= b)),
+                (Operation::Gt(..), Some([(a, _), (b, _)]))     => Some(bool_lit(a > b)),
+                (Operation::GEq(..), Some([(a, _), (b, _)]))    => Some(bool_lit(a >= b)),
+                (Operation::And(..), Some([(a, wa), (b, wb)]))  =>
+                    Some(E::UIntLiteral{value: a & b, width: *wa.max(wb)}),
+                (Operation::Or(..), Some([(a, wa), (b, wb)]))   =>
+                    Some(E::UIntLiteral{value: a | b, width: *wa.max(wb)}),
+                (Operation::Xor(..), Some([(a, wa), (b, wb)]))  =>
+                    Some(E::UIntLiteral{value: a ^ b, width: *wa.max(wb)}),
+                (Operation::Not(..), Some([(a, w)]))            =>
+                    Some(E::UIntLiteral{value: a ^ mask(*w), width: *w}),
+                (Operation::AndReduce(..), Some([(a, w)]))      => Some(bool_lit(*a == mask(*w))),
+                (Operation::OrReduce(..), Some([(a, _)]))       => Some(bool_lit(!a.is_zero())),
+                (Operation::XorReduce(..), Some([(a, _)]))      => Some(bool_lit(a.count_ones() % 2 == 1)),
+                _ => None,
+            }
+        },
+        _ => None,
+    };
+    res.unwrap_or(expr)
+}
+
+
+/// Extend a branch chosen from a `mux` to the type of the `mux`
+///
+/// Literals are widened, other expressions are padded. This function yields
+/// `None` if the branch cannot be extended, e.g. because a width is unknown.
+fn extended(value: &Expression, mux: &Expression) -> Option<Expression> {
+    use expr::Expression as E;
+
+    let r#type = mux.r#type().ok()?;
+    if value.r#type().ok()? == r#type {
+        return Some(value.clone())
+    }
+
+    let width = match r#type.resolved() {
+        Type::GroundType(g @ (GroundType::UInt(_) | GroundType::SInt(_))) => g.width()?,
+        _ => return None,
+    };
+    match value {
+        E::UIntLiteral{value, ..}   => Some(E::UIntLiteral{value: value.clone(), width}),
+        E::SIntLiteral{value, ..}   => Some(E::SIntLiteral{value: value.clone(), width}),
+        value                       => Some(E::PrimitiveOp(Operation::Pad(Arc::new(value.clone()), width))),
+    }
+}
+
+
+/// Check whether the given expression is a literal
+fn constant_value(expr: &Expression) -> bool {
+    matches!(expr, expr::Expression::UIntLiteral{..} | expr::Expression::SIntLiteral{..})
+}
+
+
+/// Retrieve the names of all entities referenced in the given statements
+fn referenced_names(stmts: &[Statement]) -> HashSet<Arc<str>> {
+    use transiter::AutoTransIter;
+
+    stmts
+        .iter()
+        .flat_map(AutoTransIter::trans_iter)
+        .flat_map(Statement::expressions)
+        .flat_map(|e| e.references())
+        .map(|r| r.name().clone())
+        .collect()
+}
+
+
+/// Remove unused nodes from the given statements
+///
+/// This function removes nodes which are not referenced anywhere in the
+/// statements and for which `removable` returns `true`.
+fn remove_unused_nodes(stmts: Vec<Statement>, removable: impl Fn(&Arc<str>) -> bool) -> Vec<Statement> {
+    fn remove(stmts: &[Statement], unused: &HashSet<Arc<str>>) -> Vec<Statement> {
+        stmts.iter().filter_map(|s| match s.kind() {
+            stmt::Kind::Declaration(e) if unused.contains(e.name()) => None,
+            stmt::Kind::Conditional{cond, when, r#else} => Some(s.with_kind(stmt::Kind::Conditional{
+                cond: cond.clone(),
+                when: remove(when, unused).into(),
+                r#else: remove(r#else, unused).into(),
+            })),
+            stmt::Kind::Match{value, arms} => Some(s.with_kind(stmt::Kind::Match{
+                value: value.clone(),
+                arms: arms.iter().map(|a| a.with_contents(a.binder().cloned(), remove(a.stmts(), unused))).collect(),
+            })),
+            stmt::Kind::LayerBlock{layer, stmts} => Some(s.with_kind(stmt::Kind::LayerBlock{
+                layer: layer.clone(),
+                stmts: remove(stmts, unused).into(),
+            })),
+            _ => Some(s.clone()),
+        }).collect()
+    }
+
+    let mut stmts = stmts;
+    loop {
+        let referenced = referenced_names(&stmts);
+        let unused: HashSet<_> = stmts
+            .iter()
+            .flat_map(Statement::declarations)
+            .filter(|e| matches!(e.as_ref(), Entity::Node{..}))
+            .map(|e| e.name())
+            .filter(|n| !referenced.contains(*n) && removable(n))
+            .cloned()
+            .collect();
+        if unused.is_empty() {
+            return stmts
+        }
+        stmts = remove(&stmts, &unused);
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Removal of simulation constructs
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::error::PortError;
+use crate::expr::{Expression, primitive::Operation};
+use crate::module::{Direction, Kind, Module, Port};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement};
+use crate::types::GroundType;
+
+use super::Error;
+
+
+/// Options for [strip_simulation_constructs]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StripOptions {
+    prints: bool,
+    stops: bool,
+    gate: Option<Arc<str>>,
+}
+
+impl StripOptions {
+    /// Set whether `printf` statements are affected
+    pub fn with_prints(self, prints: bool) -> Self {
+        Self {prints, ..self}
+    }
+
+    /// Set whether `stop` statements are affected
+    pub fn with_stops(self, stops: bool) -> Self {
+        Self {stops, ..self}
+    }
+
+    /// Gate affected statements behind a port instead of removing them
+    ///
+    /// Instead of removing statements, their conditions will be combined
+    /// with an input port of the given name. That port will be added to every
+    /// module containing affected statements as well as to every module
+    /// instantiating such a module, driving the instance's port.
+    pub fn with_gate(self, port: impl Into<Arc<str>>) -> Self {
+        Self {gate: Some(port.into()), ..self}
+    }
+
+    /// Check whether the given statement is affected
+    fn affects(&self, stmt: &Statement) -> bool {
+        match stmt.kind() {
+            stmt::Kind::Print{..}   => self.prints,
+            stmt::Kind::Stop{..}    => self.stops,
+            _                       => false,
+        }
+    }
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        Self {prints: true, stops: true, gate: None}
+    }
+}
+
+
+/// Number of statements affected by [strip_simulation_constructs]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Stripped {
+    pub prints: usize,
+    pub stops: usize,
+}
+
+
+/// Remove or gate simulation constructs
+///
+/// This function removes `printf` and `stop` statements, which are only
+/// meaningful for simulation, from all modules of the circuit. If a gate is
+/// configured in the `options`, the statements are retained but only enabled
+/// if the gate port is set.
+///
+/// Statements are counted once per module definition, regardless of the
+/// number of instances. The function fails if a gate port cannot be added to
+/// a module, e.g. because of a name collision.
+pub fn strip_simulation_constructs(
+    circuit: &Circuit,
+    options: &StripOptions,
+) -> Result<(Circuit, Stripped), Error> {
+    let mut stripped: Stripped = Default::default();
+    let mut gated: HashSet<Arc<str>> = Default::default();
+    let mut res = Ok(());
+
+    let circuit = circuit.substitute_modules(|module| {
+        let stmts = match module.kind() {
+            Kind::Regular{stmts} if res.is_ok() => stmts,
+            _ => return None,
+        };
+
+        let mut count: Stripped = Default::default();
+        let stmts = strip(stmts, options, &mut count);
+        stripped.prints += count.prints;
+        stripped.stops += count.stops;
+
+        let gate = options.gate.as_ref().filter(|_| count != Default::default() ||
+            module.referenced_modules().any(|m| gated.contains(m.name())));
+        let mut module = module.as_ref().clone();
+        if let Some(gate) = gate {
+            if let Err(err) = add_gate(&mut module, stmts, gate, &gated) {
+                res = Err(err);
+                return None
+            }
+            gated.insert(module.name().clone());
+        } else {
+            *module.kind_mut() = Kind::Regular{stmts};
+        }
+        Some(Arc::new(module))
+    });
+
+    res.map(|_| (circuit, stripped))
+}
+
+
+/// Strip affected statements from a list of statements
+///
+/// If the options specify a gate, statements are retained. The statements
+/// affected are counted in `count`.
+fn strip(stmts: &[Statement], options: &StripOptions, count: &mut Stripped) -> Vec<Statement> {
+    stmts.iter().filter_map(|s| match s.kind() {
+        stmt::Kind::Conditional{cond, when, r#else} => Some(s.with_kind(stmt::Kind::Conditional{
+            cond: cond.clone(),
+            when: strip(when, options, count).into(),
+            r#else: strip(r#else, options, count).into(),
+        })),
+        stmt::Kind::Print{..} if options.affects(s) => {
+            count.prints += 1;
+            options.gate.as_ref().map(|_| s.clone())
+        },
+        stmt::Kind::Stop{..} if options.affects(s) => {
+            count.stops += 1;
+            options.gate.as_ref().map(|_| s.clone())
+        },
+        _ => Some(s.clone()),
+    }).collect()
+}
+
+
+/// Add a gate port to the module and gate affected statements
+///
+/// The statements will become the module's statements. Instances of modules
+/// in `gated` will be connected to the gate port.
+fn add_gate(
+    module: &mut Module,
+    stmts: Vec<Statement>,
+    port: &Arc<str>,
+    gated: &HashSet<Arc<str>>,
+) -> Result<(), Error> {
+    fn gate_stmts(
+        stmts: &[Statement],
+        gate: &Expression<Arc<Entity>>,
+        port: &Arc<str>,
+        gated: &HashSet<Arc<str>>,
+    ) -> Vec<Statement> {
+        let and = |cond: &Expression<Arc<Entity>>| Expression::PrimitiveOp(
+            Operation::And(Arc::new(cond.clone()), Arc::new(gate.clone()))
+        );
+
+        stmts.iter().flat_map(|s| {
+            let stmt = match s.kind() {
+                stmt::Kind::Conditional{cond, when, r#else} => s.with_kind(stmt::Kind::Conditional{
+                    cond: cond.clone(),
+                    when: gate_stmts(when, gate, port, gated).into(),
+                    r#else: gate_stmts(r#else, gate, port, gated).into(),
+                }),
+                stmt::Kind::Print{name, clock, cond, msg} => s.with_kind(stmt::Kind::Print{
+                    name: name.clone(),
+                    clock: clock.clone(),
+                    cond: and(cond),
+                    msg: msg.clone(),
+                }),
+                stmt::Kind::Stop{name, clock, cond, code} => s.with_kind(stmt::Kind::Stop{
+                    name: name.clone(),
+                    clock: clock.clone(),
+                    cond: and(cond),
+                    code: *code,
+                }),
+                _ => s.clone(),
+            };
+
+            // Instances of gated modules need their gate driven
+            let connection = match s.kind() {
+                stmt::Kind::Declaration(e) => match e.as_ref() {
+                    Entity::Instance(i) if gated.contains(i.module().name()) => Some(stmt::Kind::Connection{
+                        from: gate.clone(),
+                        to: Expression::SubField{base: Arc::new(e.clone().into()), index: port.clone()},
+                    }.into()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            std::iter::once(stmt).chain(connection)
+        }).collect()
+    }
+
+    if stmts.iter().flat_map(Statement::declarations).any(|e| e.name() == port) {
+        return Err(PortError::Duplicate(port.clone()).into())
+    }
+
+    let gate = Arc::new(Port::new(port.clone(), GroundType::UInt(Some(1)).into(), Direction::Input));
+    module.add_port(gate.clone())?;
+    let gate = Expression::Reference(Arc::new(Entity::Port(gate)));
+    *module.kind_mut() = Kind::Regular{stmts: gate_stmts(&stmts, &gate, port, gated)};
+    Ok(())
+}
@@ -0,0 +1,185 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Generation of debug taps exposing internal signals
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::display::JsonString;
+use crate::expr::Expression;
+use crate::info::WithInfo;
+use crate::module::{Direction, Instance, Kind, Module, Port};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement};
+use crate::target::{Subscript, Target};
+use crate::types::{BundleField, GroundType, Type};
+
+use super::{Error, NamePolicy, punch_port};
+
+
+/// A signal exposed by [insert_debug_tap]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tap {
+    /// The signal exposed
+    pub target: Target,
+    /// Name of the field of the wrapper's debug port exposing the signal
+    pub field: Arc<str>,
+    /// Name of the port created in the modules along the signal's path
+    pub port: Arc<str>,
+    /// Type of the signal
+    pub r#type: Type,
+}
+
+
+/// Mapping of the signals exposed by [insert_debug_tap]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapMap {
+    /// Name of the generated wrapper module
+    pub wrapper: Arc<str>,
+    /// Name of the instance of the original top module within the wrapper
+    pub instance: Arc<str>,
+    /// Name of the wrapper's output port exposing all signals
+    pub port: Arc<str>,
+    /// The signals exposed, in the order they were requested
+    pub taps: Vec<Tap>,
+}
+
+impl TapMap {
+    /// Serialize the mapping as JSON
+    ///
+    /// The mapping is serialized as an object with the keys `wrapper`,
+    /// `instance`, `port` and `taps`. Each tap is an object with the keys
+    /// `target`, `field`, `port`, `type` and `width`. Unknown widths are
+    /// serialized as `null`.
+    pub fn json(&self) -> String {
+        let taps: Vec<_> = self.taps.iter().map(|t| format!(
+            "{{\"target\":{},\"field\":{},\"port\":{},\"type\":{},\"width\":{}}}",
+            JsonString(&t.target.to_string()),
+            JsonString(&t.field),
+            JsonString(&t.port),
+            JsonString(&t.r#type.to_string()),
+            t.r#type.bit_width().map(|w| w.to_string()).unwrap_or_else(|| "null".into()),
+        )).collect();
+        format!(
+            "{{\"wrapper\":{},\"instance\":{},\"port\":{},\"taps\":[{}]}}",
+            JsonString(&self.wrapper),
+            JsonString(&self.instance),
+            JsonString(&self.port),
+            taps.join(","),
+        )
+    }
+}
+
+
+/// Expose internal signals via a debug wrapper around the top module
+///
+/// For every signal, this function [punches](punch_port) a port through the
+/// instance hierarchy up to the circuit's top module. The ports are named
+/// after the `prefix` and the signal's path. It then generates a wrapper
+/// module, which becomes the new top module. The wrapper instantiates the
+/// original top module, passes through all of its original ports and exposes
+/// the signals via a single output port named `prefix`, e.g. for attaching an
+/// integrated logic analyzer.
+///
+/// All `signals` need to be relative to the top module. Since the wrapper
+/// becomes the new top module, the name of the circuit changes accordingly.
+/// Analog ports are passed through via `attach`. The function returns
+/// the resulting circuit alongside a [TapMap] describing where each signal
+/// ended up.
+pub fn insert_debug_tap(
+    circuit: &Circuit,
+    signals: &[Target],
+    prefix: impl Into<Arc<str>>,
+) -> Result<(Circuit, TapMap), Error> {
+    let prefix = prefix.into();
+    let top = circuit.top_module().name().clone();
+
+    let mut res = circuit.clone();
+    let mut fields: HashSet<Arc<str>> = Default::default();
+    let mut taps = Vec::with_capacity(signals.len());
+    for target in signals {
+        if target.path().root() != &top {
+            return Err(Error::Unresolved(target.clone()))
+        }
+
+        let (_, r#type) = super::wiring::signal(&res, target)?;
+        let field = unique(label(target), &fields);
+        fields.insert(field.clone());
+        let port: Arc<str> = format!("{}_{}", prefix, field).into();
+        res = punch_port(&res, target, port.clone())?;
+        taps.push(Tap {target: target.clone(), field, port, r#type});
+    }
+
+    let dut = res.top_module().clone();
+    let modules: HashSet<_> = res.modules().map(|m| m.name().clone()).collect();
+    let wrapper = unique(format!("{}_debug", top).into(), &modules);
+
+    // Pass through the original ports
+    let tapped: HashSet<_> = taps.iter().map(|t| t.port.clone()).collect();
+    let ports: Vec<_> = dut
+        .ports()
+        .filter(|p| !tapped.contains(p.name()))
+        .map(|p| Arc::new(Port::new(p.name().clone(), p.r#type().clone(), p.direction())))
+        .collect();
+    let debug_type: Type = taps
+        .iter()
+        .map(|t| BundleField::new(t.field.clone(), t.r#type.clone()))
+        .collect::<Vec<_>>()
+        .into();
+    let debug = Arc::new(Port::new(prefix.clone(), debug_type, Direction::Output));
+
+    let mut module = Module::new(wrapper.clone(), ports.iter().cloned(), Kind::empty_regular());
+    module.add_port(debug.clone())?;
+    let instance = NamePolicy::default().namespace(&module).derive("dut");
+
+    let inst = Arc::new(Entity::Instance(Instance::new(instance.clone(), dut)));
+    let sub = |base: &Arc<Entity>, index: &Arc<str>| Expression::SubField{
+        base: Arc::new(base.clone().into()),
+        index: index.clone(),
+    };
+    let mut stmts: Vec<Statement> = vec![stmt::Kind::Declaration(inst.clone()).into()];
+    stmts.extend(ports.iter().map(|p| {
+        let outer: Expression<_> = Arc::new(Entity::Port(p.clone())).into();
+        let inner = sub(&inst, p.name());
+        match (p.r#type().resolved(), p.direction()) {
+            (Type::GroundType(GroundType::Analog(_)), _)    => stmt::Kind::Attach(vec![outer, inner]),
+            (_, Direction::Input)                           => stmt::Kind::Connection{from: outer, to: inner},
+            (_, Direction::Output)                          => stmt::Kind::Connection{from: inner, to: outer},
+        }.into()
+    }));
+    let debug = Arc::new(Entity::Port(debug));
+    stmts.extend(taps.iter().map(|t| stmt::Kind::Connection{
+        from: sub(&inst, &t.port),
+        to: sub(&debug, &t.field),
+    }.into()));
+    *module.kind_mut() = Kind::Regular{stmts};
+
+    let map = TapMap {wrapper, instance, port: prefix, taps};
+    let res = Circuit::new(Arc::new(module)).with_info(circuit.info().map(ToString::to_string));
+    Ok((res, map))
+}
+
+
+/// Derive a field name from a target
+///
+/// The name consists of the instances along the target's path, the name of
+/// the referenced signal and its subscripts, separated by underscores.
+fn label(target: &Target) -> Arc<str> {
+    let path = target.path().elements().iter().map(|e| e.instance.to_string());
+    let reference = target.reference_name().map(ToString::to_string);
+    let subscripts = target.subscripts().iter().map(|s| match s {
+        Subscript::Field(f) => f.to_string(),
+        Subscript::Index(i) => i.to_string(),
+    });
+    path.chain(reference).chain(subscripts).collect::<Vec<_>>().join("_").into()
+}
+
+
+/// Derive a name not contained in `taken` from the given one
+fn unique(name: Arc<str>, taken: &HashSet<Arc<str>>) -> Arc<str> {
+    if !taken.contains(&name) {
+        return name
+    }
+    (0..).map(|i| Arc::from(format!("{}_{}", name, i))).find(|n| !taken.contains(n)).unwrap_or(name)
+}
@@ -0,0 +1,530 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to passes
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+use crate::error::PortError;
+use crate::manifest::{Manifest, tests::leaf_count};
+use crate::module::{Direction, Module, ParamValue};
+use crate::report::Severity;
+use crate::named::Named;
+use crate::expr::{Expression, primitive::Operation};
+use crate::stmt::{self, Statement};
+use crate::target::{Referenced, Target};
+use crate::types::{GroundType, Type, Typed};
+
+use super::{
+    AnalysisCache,
+    Error,
+    NamePolicy,
+    PassManager,
+    StripOptions,
+    expand_partial_connects,
+    insert_debug_tap,
+    insert_watchpoints,
+    miter,
+    prune_constant_whens,
+    punch_port,
+    simplify_whens,
+    specialize,
+    strip_simulation_constructs,
+};
+
+
+#[quickcheck]
+fn punch_port_exposes_signal(circuit: Circuit) -> TestResult {
+    const NAME: &str = "punched";
+
+    let tree = circuit.elaborate();
+    let signal = tree.iter().find_map(|n| n
+        .module()
+        .statements()
+        .iter()
+        .flat_map(Statement::declarations)
+        .find_map(|e| e.r#type().ok().map(|t| (n.path().clone(), e.name().clone(), t))));
+    let (path, signal, r#type) = if let Some(s) = signal {
+        s
+    } else {
+        return TestResult::discard()
+    };
+
+    let res = match punch_port(&circuit, &Target::reference(path.clone(), signal), NAME) {
+        Ok(c) => c,
+        Err(Error::Port(PortError::Duplicate(_))) => return TestResult::discard(),
+        Err(Error::Nested(_)) => return TestResult::discard(),
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    let mut prefix = path;
+    loop {
+        match res.resolve_target(&Target::reference(prefix.clone(), NAME)) {
+            Some(Referenced::Port(p)) if p.r#type() == &r#type => (),
+            _ => return TestResult::error(format!("Port missing in {}", prefix)),
+        }
+        if prefix.pop().is_none() {
+            break
+        }
+    }
+    TestResult::passed()
+}
+
+
+#[test]
+fn punch_port_rejects_nested_signal() {
+    use crate::target::InstancePath;
+
+    let circuit = crate::circuit::parse(concat!(
+        "circuit Top :\n",
+        "  module Top :\n",
+        "    input a : UInt<1>\n",
+        "\n",
+        "    when a :\n",
+        "      wire w : UInt<1>\n",
+        "      w <= a\n",
+    )).expect("Could not parse circuit");
+    let target = Target::reference(InstancePath::new("Top"), "w");
+    assert_eq!(punch_port(&circuit, &target, "punched"), Err(Error::Nested(target)));
+}
+
+
+/// Count `printf` and `stop` statements in all module definitions
+fn sim_constructs(circuit: &Circuit) -> (usize, usize) {
+    use transiter::AutoTransIter;
+
+    circuit
+        .modules()
+        .flat_map(|m| m.statements())
+        .flat_map(AutoTransIter::trans_iter)
+        .fold((0, 0), |(p, s), stmt| match stmt.kind() {
+            stmt::Kind::Print{..}   => (p + 1, s),
+            stmt::Kind::Stop{..}    => (p, s + 1),
+            _                       => (p, s),
+        })
+}
+
+
+#[quickcheck]
+fn debug_tap_exposes_signal(circuit: Circuit) -> TestResult {
+    const PREFIX: &str = "debug";
+
+    let tree = circuit.elaborate();
+    let signal = tree.iter().find_map(|n| n
+        .module()
+        .statements()
+        .iter()
+        .flat_map(Statement::declarations)
+        .find_map(|e| e.r#type().ok().map(|t| (Target::reference(n.path().clone(), e.name().clone()), t))));
+    let (target, r#type) = if let Some(s) = signal {
+        s
+    } else {
+        return TestResult::discard()
+    };
+
+    let (res, map) = match insert_debug_tap(&circuit, std::slice::from_ref(&target), PREFIX) {
+        Ok(r) => r,
+        Err(Error::Port(PortError::Duplicate(_))) => return TestResult::discard(),
+        Err(Error::Nested(_)) => return TestResult::discard(),
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    let top = res.top_module();
+    let debug = top.port_by_name(&PREFIX).map(|p| p.r#type().clone());
+    let expected = Type::from(vec![crate::types::BundleField::new(map.taps[0].field.clone(), r#type)]);
+    let dut = top.referenced_modules().next().map(|m| m.name() == circuit.top_module().name());
+    TestResult::from_bool(
+        top.name() == &map.wrapper && debug == Some(expected) && dut == Some(true) &&
+        top.ports().count() == circuit.top_module().ports().count() + 1 &&
+        map.json().contains(&format!("\"target\":{}", crate::display::JsonString(&target.to_string())))
+    )
+}
+
+
+#[quickcheck]
+fn miter_asserts_outputs(circuit: Circuit) -> TestResult {
+    let module = circuit.top_module();
+    if module.ports().map(|p| leaf_count(p.r#type())).fold(0, u64::saturating_add) > 256 {
+        return TestResult::discard()
+    }
+
+    let res = match miter(module, module) {
+        Ok(res) => res,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    let outputs = Manifest::of(module)
+        .ports()
+        .iter()
+        .filter(|p| p.direction() == Direction::Output && !matches!(p.r#type(), GroundType::Analog(_)))
+        .count();
+    let top = res.top_module();
+    let asserts = top
+        .statements()
+        .iter()
+        .filter(|s| matches!(s.kind(), stmt::Kind::Verification{op: stmt::verification::Op::Assert, ..}))
+        .count();
+    let clock = module.ports().any(|p| p.direction() == Direction::Input && p.r#type() == &GroundType::Clock);
+    let instances = top.referenced_modules().filter(|m| m.name() == module.name()).count();
+    TestResult::from_bool(
+        asserts == outputs && instances == 2 &&
+        top.ports().count() == module.ports().count() + if clock { 0 } else { 1 }
+    )
+}
+
+
+#[quickcheck]
+fn strip_removes_constructs(circuit: Circuit) -> TestResult {
+    let (prints, stops) = sim_constructs(&circuit);
+    let (res, stripped) = match strip_simulation_constructs(&circuit, &Default::default()) {
+        Ok(res) => res,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+    TestResult::from_bool(stripped.prints == prints && stripped.stops == stops && sim_constructs(&res) == (0, 0))
+}
+
+
+#[quickcheck]
+fn strip_gates_constructs(circuit: Circuit) -> TestResult {
+    use transiter::AutoTransIter;
+
+    const NAME: &str = "sim_enable";
+
+    let (res, stripped) = match strip_simulation_constructs(&circuit, &StripOptions::default().with_gate(NAME)) {
+        Ok(res) => res,
+        Err(Error::Port(PortError::Duplicate(_))) => return TestResult::discard(),
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    let gated = |c: &Expression<Arc<stmt::Entity>>| match c {
+        Expression::PrimitiveOp(Operation::And(_, g)) =>
+            matches!(g.as_ref(), Expression::Reference(p) if p.name_ref() == NAME),
+        _ => false,
+    };
+    let all_gated = res
+        .modules()
+        .flat_map(|m| m.statements())
+        .flat_map(AutoTransIter::trans_iter)
+        .all(|s| match s.kind() {
+            stmt::Kind::Print{cond, ..} | stmt::Kind::Stop{cond, ..} => gated(cond),
+            _ => true,
+        });
+    let top_gated = stripped.prints + stripped.stops == 0 || res.top_module().port_by_name(&NAME).is_some();
+    TestResult::from_bool(sim_constructs(&res) == sim_constructs(&circuit) && all_gated && top_gated)
+}
+
+
+#[quickcheck]
+fn watchpoints_print_signal(circuit: Circuit) -> TestResult {
+    use crate::target::InstancePath;
+
+    // We need a printable signal in a module with a clock port
+    let top = circuit.top_module();
+    let has_clock = top.ports().any(|p| p.r#type() == &GroundType::Clock);
+    let signal = top.ports().find(|p| matches!(p.r#type(), Type::GroundType(GroundType::UInt(_) | GroundType::SInt(_))));
+    let signal = match signal {
+        Some(p) if has_clock && top.defname().is_none() && top.port_by_name(p.name()) == Some(p) => p.name().clone(),
+        _ => return TestResult::discard(),
+    };
+
+    let watchpoint = Target::reference(InstancePath::new(top.name().clone()), signal.clone()).into();
+    let res = match insert_watchpoints(&circuit, &[watchpoint]) {
+        Ok(res) => res,
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    let prints = |m: &Module| m
+        .statements()
+        .iter()
+        .filter_map(|s| if let stmt::Kind::Print{msg, ..} = s.kind() { Some(msg.len()) } else { None })
+        .collect::<Vec<_>>();
+    let mut expected = prints(top);
+    expected.push(3);
+    TestResult::from_bool(prints(res.top_module()) == expected)
+}
+
+
+#[quickcheck]
+fn specialize_folds_params(circuit: Circuit) -> TestResult {
+    let params: HashMap<Arc<str>, ParamValue> = circuit
+        .top_module()
+        .ports()
+        .filter(|p| p.direction() == Direction::Input)
+        .filter(|p| matches!(p.r#type(), Type::GroundType(GroundType::UInt(_))))
+        .map(|p| (p.name().clone(), ParamValue::Int(0)))
+        .collect();
+
+    let res = match specialize(&circuit, &params) {
+        Ok(c) => c,
+        Err(Error::Parameter(_)) => return TestResult::discard(),
+        Err(e) => return TestResult::error(e.to_string()),
+    };
+
+    for module in res.modules() {
+        let stmts: Vec<_> = module.statements().iter().flat_map(transiter::AutoTransIter::trans_iter).collect();
+        let literal_cond = stmts.iter().any(|s| matches!(
+            s.kind(),
+            stmt::Kind::Conditional{cond: Expression::UIntLiteral{..}, ..}
+        ));
+        if literal_cond {
+            return TestResult::error(format!("Constant condition retained in {}", module.name()))
+        }
+
+        if module.name() != res.top_module().name() {
+            continue
+        }
+
+        let param = stmts
+            .iter()
+            .flat_map(|s| s.expressions())
+            .flat_map(|e| e.references())
+            .find(|r| matches!(r.as_ref(), stmt::Entity::Port(p)
+                if p.direction() == Direction::Input && params.contains_key(p.name())));
+        if let Some(param) = param {
+            return TestResult::error(format!("Parameter {} retained in {}", param.name(), module.name()))
+        }
+    }
+    TestResult::passed()
+}
+
+
+#[test]
+fn specialize_propagates_through_instances() {
+    let circuit = crate::circuit::parse(concat!(
+        "circuit Top :\n",
+        "  module Sub :\n",
+        "    input en : UInt<1>\n",
+        "    input a : UInt<4>\n",
+        "    output y : UInt<4>\n",
+        "\n",
+        "    y <= UInt<4>(0)\n",
+        "    when en :\n",
+        "      y <= a\n",
+        "\n",
+        "  module Other :\n",
+        "    input en : UInt<1>\n",
+        "    output y : UInt<1>\n",
+        "\n",
+        "    y <= UInt<1>(0)\n",
+        "    when en :\n",
+        "      y <= UInt<1>(1)\n",
+        "\n",
+        "  module Top :\n",
+        "    input en : UInt<1>\n",
+        "    input a : UInt<4>\n",
+        "    output y : UInt<4>\n",
+        "    output z : UInt<1>\n",
+        "\n",
+        "    inst s of Sub\n",
+        "    s.en <= en\n",
+        "    s.a <= a\n",
+        "    inst o1 of Other\n",
+        "    o1.en <= UInt<1>(1)\n",
+        "    inst o2 of Other\n",
+        "    o2.en <= UInt<1>(0)\n",
+        "    y <= s.y\n",
+        "    z <= and(o1.y, o2.y)\n",
+    )).expect("Could not parse circuit");
+
+    let params = vec![("en".into(), ParamValue::Int(1))].into_iter().collect();
+    let res = specialize(&circuit, &params).expect("Could not specialize circuit");
+
+    let conditionals = |name: &str| res
+        .module_by_name(name)
+        .expect("Module not found")
+        .statements()
+        .iter()
+        .filter(|s| matches!(s.kind(), stmt::Kind::Conditional{..}))
+        .count();
+    assert_eq!(conditionals("Sub"), 0);
+    assert_eq!(conditionals("Other"), 1);
+}
+
+
+#[test]
+fn specialize_extends_mux_branches() {
+    let circuit = crate::circuit::parse(concat!(
+        "circuit Top :\n",
+        "  module Top :\n",
+        "    input p : UInt<1>\n",
+        "    input a : UInt<2>\n",
+        "    input b : UInt<4>\n",
+        "    output x : UInt<4>\n",
+        "    output y : UInt<4>\n",
+        "\n",
+        "    x <= mux(p, a, b)\n",
+        "    y <= mux(p, UInt<2>(1), b)\n",
+    )).expect("Could not parse circuit");
+
+    let params = vec![("p".into(), ParamValue::Int(1))].into_iter().collect();
+    let res = specialize(&circuit, &params).expect("Could not specialize circuit");
+
+    let values: Vec<_> = res
+        .top_module()
+        .statements()
+        .iter()
+        .filter_map(|s| if let stmt::Kind::Connection{from, ..} = s.kind() { Some(from.clone()) } else { None })
+        .collect();
+    assert_eq!(values.len(), 2);
+    assert!(matches!(&values[0], Expression::PrimitiveOp(Operation::Pad(_, 4))));
+    assert_eq!(values[1], Expression::UIntLiteral{value: 1u8.into(), width: 4});
+}
+
+
+#[quickcheck]
+fn prune_removes_constant_whens(circuit: Circuit) -> bool {
+    let res = prune_constant_whens(&circuit);
+    res.modules().flat_map(|m| m.statements().to_vec()).all(|s| {
+        use transiter::AutoTransIter;
+
+        !s.trans_iter().any(|s| matches!(s.kind(), stmt::Kind::Conditional{cond: Expression::UIntLiteral{..}, ..}))
+    }) && prune_constant_whens(&res) == res
+}
+
+
+#[quickcheck]
+fn simplify_whens_removes_redundant_conds(circuit: Circuit) -> bool {
+    fn redundant(stmts: &[Statement], enclosing: &mut Vec<Expression<Arc<stmt::Entity>>>) -> bool {
+        let mut last = None;
+        stmts.iter().any(|s| if let stmt::Kind::Conditional{cond, when, r#else} = s.kind() {
+            let res = enclosing.contains(cond) || last == Some(cond) || {
+                enclosing.push(cond.clone());
+                let res = redundant(when, enclosing) || redundant(r#else, enclosing);
+                enclosing.pop();
+                res
+            };
+            last = Some(cond);
+            res
+        } else {
+            last = None;
+            false
+        })
+    }
+
+    simplify_whens(&circuit).modules().all(|m| !redundant(m.statements(), &mut Default::default()))
+}
+
+
+#[test]
+fn simplify_whens_flattens_and_prunes() {
+    let circuit = crate::circuit::parse(concat!(
+        "circuit Top :\n",
+        "  module Top :\n",
+        "    input a : UInt<1>\n",
+        "    input b : UInt<1>\n",
+        "    output x : UInt<2>\n",
+        "    output y : UInt<2>\n",
+        "\n",
+        "    x <= UInt<2>(0)\n",
+        "    y <= UInt<2>(0)\n",
+        "    when a :\n",
+        "      when b :\n",
+        "        x <= UInt<2>(1)\n",
+        "    when a :\n",
+        "      when a :\n",
+        "        y <= UInt<2>(1)\n",
+        "      else :\n",
+        "        y <= UInt<2>(2)\n",
+        "    else :\n",
+        "      when not(a) :\n",
+        "        y <= UInt<2>(3)\n",
+    )).expect("Could not parse circuit");
+    let expected = crate::circuit::parse(concat!(
+        "circuit Top :\n",
+        "  module Top :\n",
+        "    input a : UInt<1>\n",
+        "    input b : UInt<1>\n",
+        "    output x : UInt<2>\n",
+        "    output y : UInt<2>\n",
+        "\n",
+        "    x <= UInt<2>(0)\n",
+        "    y <= UInt<2>(0)\n",
+        "    when and(a, b) :\n",
+        "      x <= UInt<2>(1)\n",
+        "    when a :\n",
+        "      y <= UInt<2>(1)\n",
+        "    else :\n",
+        "      y <= UInt<2>(3)\n",
+    )).expect("Could not parse circuit");
+    assert_eq!(simplify_whens(&circuit), expected);
+}
+
+
+#[quickcheck]
+fn pass_manager_shares_artifacts(circuit: Circuit) -> bool {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let computed = Arc::new(AtomicUsize::new(0));
+    let analysis = {
+        let computed = computed.clone();
+        move |c: &Circuit, cache: &mut AnalysisCache| -> Result<Option<Circuit>, Error> {
+            cache.get_or_insert_with("modules", || {
+                computed.fetch_add(1, Ordering::SeqCst);
+                c.modules().count()
+            });
+            Ok(None)
+        }
+    };
+    let transform = |c: &Circuit, _: &mut AnalysisCache| -> Result<Option<Circuit>, Error> {
+        Ok(Some(simplify_whens(c)))
+    };
+
+    let mut manager = PassManager::new(circuit.clone())
+        .with_pass(analysis.clone())
+        .with_pass(analysis.clone())
+        .with_pass(transform)
+        .with_pass(analysis);
+    let saved = match manager.step() {
+        Ok(true) => manager.save(),
+        _ => return false,
+    };
+    let res = match manager.run() {
+        Ok(c) => c.clone(),
+        Err(_) => return false,
+    };
+    let run = computed.load(Ordering::SeqCst) == 2 &&
+        manager.completed() == 4 &&
+        res == simplify_whens(&circuit) &&
+        manager.cache().get::<usize>("modules") == Some(&res.modules().count());
+
+    manager.restore(saved);
+    run && manager.completed() == 1 &&
+        manager.circuit() == &circuit &&
+        manager.cache().get::<usize>("modules") == Some(&circuit.modules().count())
+}
+
+
+#[quickcheck]
+fn partial_connects_expanded(circuit: Circuit) -> TestResult {
+    use transiter::AutoTransIter;
+
+    let (res, report) = expand_partial_connects(&circuit);
+    let retained = res
+        .modules()
+        .flat_map(|m| m.statements().to_vec())
+        .map(|s| s.trans_iter().filter(|s| matches!(s.kind(), stmt::Kind::PartialConnection{..})).count())
+        .sum::<usize>();
+    TestResult::from_bool(retained == report.count(Severity::Error))
+}
+
+
+#[quickcheck]
+fn gensym_fresh_names(module: Module, tags: Vec<bool>) -> bool {
+    let policy = NamePolicy::default();
+    let generate = || {
+        let mut namespace = policy.namespace(&module);
+        tags.iter().map(|t| namespace.fresh(if *t { "a" } else { "b" })).collect::<Vec<_>>()
+    };
+
+    let names = generate();
+    let existing: std::collections::HashSet<_> = module
+        .ports()
+        .map(|p| p.name().clone())
+        .chain(module.statements().iter().flat_map(Statement::declarations).map(|e| e.name().clone()))
+        .collect();
+    let unique: std::collections::HashSet<_> = names.iter().collect();
+    names == generate() && unique.len() == names.len() && names.iter().all(|n| !existing.contains(n))
+}
@@ -0,0 +1,199 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Insertion of watchpoints for debugging
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::expr::Expression;
+use crate::module::{Kind, Module};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement, print::{Format, PrintElement}};
+use crate::target::Target;
+use crate::types::{GroundType, Type, Typed};
+
+use super::Error;
+
+
+/// A signal to be printed by [insert_watchpoints]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watchpoint {
+    signal: Target,
+    clock: Option<Arc<str>>,
+    cond: Option<Arc<str>>,
+    format: Format,
+}
+
+impl Watchpoint {
+    /// Create a new watchpoint for the signal the given target refers to
+    ///
+    /// By default, the signal is printed in hexadecimal format every cycle of
+    /// the first clock port of the module containing the signal.
+    pub fn new(signal: Target) -> Self {
+        Self {signal, clock: None, cond: None, format: Format::Hexadecimal}
+    }
+
+    /// Set the clock triggering the printing
+    ///
+    /// The clock is given as the name of a signal in the module containing
+    /// the watched signal.
+    pub fn with_clock(self, clock: impl Into<Arc<str>>) -> Self {
+        Self {clock: Some(clock.into()), ..self}
+    }
+
+    /// Set the condition enabling the printing
+    ///
+    /// The condition is given as the name of a single bit signal in the
+    /// module containing the watched signal. Without a condition, the signal
+    /// is printed every cycle.
+    pub fn with_condition(self, cond: impl Into<Arc<str>>) -> Self {
+        Self {cond: Some(cond.into()), ..self}
+    }
+
+    /// Set the format in which the signal's value is printed
+    pub fn with_format(self, format: Format) -> Self {
+        Self {format, ..self}
+    }
+
+    /// Retrieve the target referring to the watched signal
+    pub fn signal(&self) -> &Target {
+        &self.signal
+    }
+
+    /// Create the `printf` statement for this watchpoint
+    fn statement(&self, circuit: &Circuit, module: &Module) -> Result<Statement, Error> {
+        let (signal, r#type) = super::wiring::signal(circuit, &self.signal)?;
+
+        let clock = match self.clock.as_ref() {
+            Some(name)  => self.entity(module, name, |t| t == &GroundType::Clock)?,
+            None        => module
+                .ports()
+                .find(|p| p.r#type() == &GroundType::Clock)
+                .map(|p| Arc::new(Entity::Port(p.clone())))
+                .ok_or_else(|| Error::Unresolved(self.related(Arc::from("clock"))))?,
+        };
+        let cond = match self.cond.as_ref() {
+            Some(name)  => Expression::Reference(self.entity(module, name, |t| {
+                t == &GroundType::UInt(Some(1)) || t == &GroundType::UInt(None)
+            })?),
+            None        => Expression::UIntLiteral{value: 1u8.into(), width: 1},
+        };
+
+        let label = self.signal.reference_name().map(ToString::to_string).unwrap_or_default() + &self
+            .signal
+            .subscripts()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>();
+        let mut msg = Vec::new();
+        printable(signal, &r#type, label, self.format, &mut msg);
+        if msg.is_empty() {
+            return Err(Error::NotASignal(self.signal.clone()))
+        }
+        msg.push(PrintElement::Literal("\n".into()));
+
+        Ok(stmt::Kind::Print{name: None, clock: Expression::Reference(clock), cond, msg}.into())
+    }
+
+    /// Look up a signal with the given name and a type satisfying a predicate
+    fn entity(
+        &self,
+        module: &Module,
+        name: &Arc<str>,
+        predicate: impl Fn(&Type) -> bool,
+    ) -> Result<Arc<Entity>, Error> {
+        let target = self.related(name.clone());
+        let entity = module
+            .port_by_name(name)
+            .map(|p| Arc::new(Entity::Port(p.clone())))
+            .or_else(|| module.statements().iter().flat_map(Statement::declarations).find(|e| e.name() == name).cloned())
+            .ok_or_else(|| Error::Unresolved(target.clone()))?;
+        match entity.r#type() {
+            Ok(t) if predicate(&t)  => Ok(entity),
+            _                       => Err(Error::NotASignal(target)),
+        }
+    }
+
+    /// Create a target referring to a signal in the module of the watched one
+    fn related(&self, name: Arc<str>) -> Target {
+        Target::reference(self.signal.path().clone(), name).with_circuit(self.signal.circuit().cloned())
+    }
+}
+
+impl From<Target> for Watchpoint {
+    fn from(signal: Target) -> Self {
+        Self::new(signal)
+    }
+}
+
+
+/// Insert `printf` statements printing the values of signals
+///
+/// For each of the `watchpoints`, this function appends a `printf` statement
+/// to the module containing the watched signal. The statement prints the
+/// name and value of the signal on every cycle of the selected clock in
+/// which the selected condition holds. Aggregate signals are printed element
+/// by element. Elements which can't be printed, e.g. clocks, are omitted.
+///
+/// Since modules are shared between their instances, the statements will be
+/// executed for every instance of the affected modules. The function fails if
+/// any watchpoint can't be resolved or refers to a signal in an external
+/// module.
+pub fn insert_watchpoints(circuit: &Circuit, watchpoints: &[Watchpoint]) -> Result<Circuit, Error> {
+    let mut modules: HashMap<Arc<str>, Module> = Default::default();
+
+    for watchpoint in watchpoints {
+        let target = watchpoint.signal();
+        let name = target.path().module();
+        let original = circuit.module_by_name(name).ok_or_else(|| Error::Unresolved(target.clone()))?;
+        let stmt = watchpoint.statement(circuit, original)?;
+
+        let module = modules.entry(name.clone()).or_insert_with(|| original.as_ref().clone());
+        if let Kind::Regular{stmts} = module.kind_mut() {
+            stmts.push(stmt);
+        } else {
+            return Err(Error::ExternalModule(name.clone()))
+        }
+    }
+
+    let modules: HashMap<_, _> = modules.into_iter().map(|(k, v)| (k, Arc::new(v))).collect();
+    Ok(circuit.substitute_modules(|m| modules.get(m.name()).cloned()))
+}
+
+
+/// Append elements printing the given expression to `msg`
+///
+/// Aggregates are printed element by element, each prefixed by its label.
+fn printable(
+    expr: Expression<Arc<Entity>>,
+    r#type: &Type,
+    label: String,
+    format: Format,
+    msg: &mut Vec<PrintElement>,
+) {
+    match r#type {
+        Type::GroundType(GroundType::UInt(_) | GroundType::SInt(_)) => {
+            let sep = if msg.is_empty() { "" } else { " " };
+            msg.push(PrintElement::Literal(format!("{}{}=", sep, label)));
+            msg.push(PrintElement::Value(expr, format));
+        },
+        Type::GroundType(_) => (),
+        Type::Vector(t, w) => (0..*w).for_each(|i| printable(
+            Expression::SubIndex{base: Arc::new(expr.clone()), index: i},
+            t,
+            format!("{}[{}]", label, i),
+            format,
+            msg,
+        )),
+        Type::Bundle(v) => v.iter().for_each(|f| printable(
+            Expression::SubField{base: Arc::new(expr.clone()), index: f.name().clone()},
+            f.r#type(),
+            format!("{}.{}", label, f.name()),
+            format,
+            msg,
+        )),
+        Type::Probe(..) | Type::Enum(..) => (),
+        Type::Alias(_, t) => printable(expr, t, label, format, msg),
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Wiring of signals through the instance hierarchy
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::error::PortError;
+use crate::expr::Expression;
+use crate::module::{Direction, Kind, Module, Port};
+use crate::named::Named;
+use crate::stmt::{self, Entity, Statement};
+use crate::target::{Referenced, Subscript, Target};
+use crate::types::Type;
+
+use super::Error;
+
+
+/// Expose an internal signal as an output port of the circuit's top module
+///
+/// This function creates an output port with the given `name` in every module
+/// along the instance path of the `target`, starting at the module containing
+/// the signal. Each of those ports is connected to the signal or the port
+/// created in the instantiated module, respectively. The resulting circuit
+/// thus exposes the signal via the new port of its top module.
+///
+/// Since modules are shared between their instances, all instances of the
+/// affected modules will feature the new port. Connections are added at the
+/// top level of each module. Hence, the signal and all instances along the
+/// path must be declared at the top level rather than in nested statements.
+pub fn punch_port(circuit: &Circuit, target: &Target, name: impl Into<Arc<str>>) -> Result<Circuit, Error> {
+    let name = name.into();
+
+    let (signal, r#type) = signal(circuit, target)?;
+
+    // Collect the modules along the path, starting at the root
+    let path = target.path();
+    let root = circuit.module_by_name(path.root()).ok_or_else(|| Error::Unresolved(target.clone()))?;
+    let mut modules = vec![root.clone()];
+    for element in path.elements() {
+        let next = modules
+            .last()
+            .and_then(|m| instance(m, &element.instance, &element.module))
+            .map(|i| i.module().clone())
+            .ok_or_else(|| Error::Nested(target.clone()))?;
+        modules.push(next);
+    }
+
+    let port = Arc::new(Port::new(name.clone(), r#type, Direction::Output));
+
+    // Modify the modules bottom-up
+    let mut modules = modules.into_iter().rev();
+    let leaf = modules.next().ok_or_else(|| Error::Unresolved(target.clone()))?;
+    let mut replaced: HashMap<Arc<str>, Arc<Module>> = Default::default();
+    let mut child = Arc::new(with_driven_port(&leaf, port.clone(), signal)?);
+    replaced.insert(leaf.name().clone(), child.clone());
+
+    for (module, element) in modules.zip(path.elements().iter().rev()) {
+        let module = module.with_substituted_instances(|m| replaced.get(m.name()).cloned());
+        let inst = top_level_declarations(&module)
+            .find(|e| e.name() == &element.instance)
+            .cloned()
+            .ok_or_else(|| Error::Nested(target.clone()))?;
+        let signal = Expression::SubField{base: Arc::new(inst.into()), index: name.clone()};
+
+        child = Arc::new(with_driven_port(&module, port.clone(), signal)?);
+        replaced.insert(module.name().clone(), child.clone());
+    }
+
+    Ok(circuit.substitute_modules(|m| replaced.get(m.name()).cloned()))
+}
+
+
+/// Resolve a target referring to a signal
+///
+/// This function returns an expression referring to the signal within the
+/// module containing it as well as the signal's type. Signals declared in
+/// nested statements or reached via such instances are rejected.
+pub(super) fn signal(circuit: &Circuit, target: &Target) -> Result<(Expression<Arc<Entity>>, Type), Error> {
+    let referenced = circuit.resolve_target(target).ok_or_else(|| Error::Unresolved(target.clone()))?;
+
+    let module = target.path().elements().iter().try_fold(
+        circuit.module_by_name(target.path().root()).ok_or_else(|| Error::Unresolved(target.clone()))?,
+        |m, e| instance(m, &e.instance, &e.module).map(|i| i.module()).ok_or_else(|| Error::Nested(target.clone())),
+    )?;
+    let entity = match referenced {
+        Referenced::Port(p)     => Arc::new(Entity::Port(p.clone())),
+        Referenced::Entity(e) if top_level_declarations(module).any(|d| Arc::ptr_eq(d, e)) => e.clone(),
+        Referenced::Entity(_)   => return Err(Error::Nested(target.clone())),
+        _ => return Err(Error::NotASignal(target.clone())),
+    };
+    let r#type = referenced.r#type().ok_or_else(|| Error::NotASignal(target.clone()))?;
+    let r#type = target
+        .subscripts()
+        .iter()
+        .try_fold(r#type, |t, s| s.apply(&t).cloned())
+        .ok_or_else(|| Error::NotASignal(target.clone()))?;
+    let res = target.subscripts().iter().fold(Expression::Reference(entity), |e, s| match s {
+        Subscript::Field(f) => Expression::SubField{base: Arc::new(e), index: f.clone()},
+        Subscript::Index(i) => Expression::SubIndex{base: Arc::new(e), index: *i},
+    });
+    Ok((res, r#type))
+}
+
+
+/// Create a copy of the given module with a port driven by the given signal
+fn with_driven_port(
+    module: &Module,
+    port: Arc<Port>,
+    signal: Expression<Arc<Entity>>,
+) -> Result<Module, Error> {
+    let name = port.name().clone();
+    if module.statements().iter().flat_map(Statement::declarations).any(|e| e.name() == &name) {
+        return Err(PortError::Duplicate(name).into())
+    }
+
+    let mut res = module.clone();
+    res.add_port(port.clone())?;
+    if let Kind::Regular{stmts} = res.kind_mut() {
+        stmts.push(stmt::Kind::Connection{from: signal, to: Arc::new(Entity::Port(port)).into()}.into());
+        Ok(res)
+    } else {
+        Err(Error::ExternalModule(module.name().clone()))
+    }
+}
+
+
+/// Retrieve the entities declared at the top level of the given module
+///
+/// Declarations in nested statements, e.g. inside conditional branches, are
+/// not included since they are not visible at the module's top level.
+fn top_level_declarations(module: &Module) -> impl Iterator<Item = &Arc<Entity>> {
+    module.statements().iter().filter_map(|s| if let stmt::Kind::Declaration(e) = s.kind() {
+        Some(e)
+    } else {
+        None
+    })
+}
+
+
+/// Find an instance declared at the top level of the given module
+fn instance<'a>(module: &'a Module, name: &str, defname: &str) -> Option<&'a crate::module::Instance> {
+    top_level_declarations(module).find_map(|e| match e.as_ref() {
+        Entity::Instance(i) if i.name_ref() == name && i.module().name_ref() == defname => Some(i),
+        _ => None,
+    })
+}
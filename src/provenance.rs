@@ -0,0 +1,190 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Provenance tracking for statements
+//!
+//! Transformations of a [Circuit] may replace, split or merge statements. For
+//! tracing a statement in a transformed circuit back to the statements in the
+//! original circuit, statements may carry an [Id]. Initially, ids are assigned
+//! to all statements via [Provenance::assign]. Passes preserve the ids of
+//! statements they don't alter. For new statements, they report the ids of
+//! the statements they were derived from in a [RenameMap]. Such maps are
+//! recorded in the [Provenance] via [Provenance::apply].
+//!
+//! Entities don't carry an id of their own. They are tracked via the
+//! statement declaring them.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::circuit::Circuit;
+use crate::info::WithInfo;
+use crate::module::{Kind, Module};
+use crate::stmt::{Kind as StmtKind, Statement};
+
+
+/// Provenance id of a [Statement]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(u64);
+
+impl Id {
+    /// Create an id from its raw representation
+    pub(crate) fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Retrieve the raw representation of this id
+    pub(crate) fn raw(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+
+/// Map from statement ids to the ids of the statements they derive from
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenameMap {
+    map: HashMap<Id, Vec<Id>>,
+}
+
+impl RenameMap {
+    /// Record that the statement `id` was derived from the given `sources`
+    pub fn insert(&mut self, id: Id, sources: impl IntoIterator<Item = Id>) {
+        self.map.entry(id).or_default().extend(sources)
+    }
+
+    /// Retrieve the ids of the statements the given one was derived from
+    pub fn sources(&self, id: Id) -> &[Id] {
+        self.map.get(&id).map(AsRef::as_ref).unwrap_or(&[])
+    }
+
+    /// Retrieve an iterator over all entries
+    pub fn iter(&self) -> impl Iterator<Item = (Id, &[Id])> {
+        self.map.iter().map(|(k, v)| (*k, v.as_ref()))
+    }
+
+    /// Check whether the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+
+/// Provenance information for a circuit
+///
+/// A `Provenance` keeps track of the statements present in an original
+/// circuit, identified by their [Id], and derivations of statements
+/// introduced by transformations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    origins: HashMap<Id, Option<String>>,
+    derivations: RenameMap,
+    next: u64,
+}
+
+impl Provenance {
+    /// Assign ids to all statements in a circuit
+    ///
+    /// This function returns a copy of the given circuit in which every
+    /// statement carries a unique [Id] as well as the provenance information
+    /// recording those statements as origins.
+    pub fn assign(circuit: &Circuit) -> (Circuit, Self) {
+        let mut res: Self = Default::default();
+        let circuit = circuit.substitute_modules(|m| if let Kind::Regular{stmts} = m.kind() {
+            let stmts = res.assign_stmts(stmts);
+            let mut module = Module::clone(m);
+            *module.kind_mut() = Kind::Regular{stmts};
+            Some(Arc::new(module))
+        } else {
+            None
+        });
+        (circuit, res)
+    }
+
+    /// Assign ids to the given statements, recording them as origins
+    fn assign_stmts(&mut self, stmts: &[Statement]) -> Vec<Statement> {
+        stmts.iter().map(|s| {
+            let id = self.fresh_id();
+            self.origins.insert(id, s.info().map(ToOwned::to_owned));
+
+            let stmt = if let StmtKind::Conditional{cond, when, r#else} = s.kind() {
+                let kind = StmtKind::Conditional{
+                    cond: cond.clone(),
+                    when: self.assign_stmts(when).into(),
+                    r#else: self.assign_stmts(r#else).into(),
+                };
+                Statement::from(kind).with_info(s.info().map(ToOwned::to_owned))
+            } else {
+                s.clone()
+            };
+            stmt.with_id(id)
+        }).collect()
+    }
+
+    /// Create a new, unique id
+    ///
+    /// Passes use this function for creating ids for new statements. The
+    /// derivation of the new statement should be reported via a [RenameMap].
+    pub fn fresh_id(&mut self) -> Id {
+        let res = Id(self.next);
+        self.next += 1;
+        res
+    }
+
+    /// Create a new, unique id for a statement derived from the given ones
+    pub fn derived_id(&mut self, sources: impl IntoIterator<Item = Id>) -> Id {
+        let res = self.fresh_id();
+        self.derivations.insert(res, sources);
+        res
+    }
+
+    /// Record the derivations reported by a pass
+    pub fn apply(&mut self, renames: &RenameMap) {
+        renames.iter().for_each(|(id, sources)| self.derivations.insert(id, sources.iter().cloned()))
+    }
+
+    /// Retrieve the ids of the original statements the given one derives from
+    ///
+    /// If the statement with the given id is an original statement, this
+    /// function yields only that id. The ids are returned in ascending order.
+    pub fn origins(&self, id: Id) -> Vec<Id> {
+        let mut res = Vec::new();
+        let mut stack = vec![id];
+        let mut seen = std::collections::HashSet::new();
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue
+            }
+            if self.origins.contains_key(&id) {
+                res.push(id)
+            }
+            stack.extend(self.derivations.sources(id))
+        }
+        res.sort_unstable();
+        res
+    }
+
+    /// Retrieve the info attributes of the original statements
+    ///
+    /// This function returns the info attributes of all original statements
+    /// the given statement derives from, omitting those without one.
+    pub fn origin_info(&self, id: Id) -> Vec<&str> {
+        self.origins(id)
+            .into_iter()
+            .filter_map(|i| self.origins.get(&i).and_then(Option::as_deref))
+            .collect()
+    }
+
+    /// Check whether the statement with the given id is an original statement
+    pub fn is_original(&self, id: Id) -> bool {
+        self.origins.contains_key(&id)
+    }
+}
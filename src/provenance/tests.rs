@@ -0,0 +1,48 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to provenance tracking
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+use crate::info::WithInfo;
+
+use super::{Provenance, RenameMap};
+
+
+#[quickcheck]
+fn assign_unique_ids(original: Circuit) -> TestResult {
+    use transiter::AutoTransIter;
+
+    let (circuit, provenance) = Provenance::assign(&original);
+    let stmts: Vec<_> = circuit
+        .modules()
+        .flat_map(|m| m.statements())
+        .flat_map(AutoTransIter::trans_iter)
+        .collect();
+
+    let mut ids: Vec<_> = stmts.iter().filter_map(|s| s.id()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let res = ids.len() == stmts.len() && stmts.iter().all(|s| s.id().map(|i| {
+        provenance.is_original(i) && provenance.origin_info(i) == s.info().into_iter().collect::<Vec<_>>()
+    }).unwrap_or(false));
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn derived_origins(num: u8) -> bool {
+    let mut provenance: Provenance = Default::default();
+    let originals: Vec<_> = (0..num).map(|_| provenance.fresh_id()).collect();
+    originals.iter().for_each(|i| { provenance.origins.insert(*i, None); });
+
+    let mut renames: RenameMap = Default::default();
+    let merged = provenance.fresh_id();
+    renames.insert(merged, originals.iter().cloned());
+    provenance.apply(&renames);
+    let split = provenance.derived_id(std::iter::once(merged));
+
+    provenance.origins(split) == originals && !provenance.is_original(split)
+}
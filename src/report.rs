@@ -0,0 +1,235 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Diagnostic reports
+//!
+//! Checks such as [lint](crate::lint) rules report issues as [Finding]s. A
+//! finding carries a [Severity], the id of the rule reporting it, a message
+//! and optionally a [Target] and a location in the source. Findings may be
+//! collected in a [Report], which may be printed in a human readable format
+//! via its `Display` impl or serialized as JSON via [Report::json] for
+//! consumption by other tools, e.g. CI systems.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::display::JsonString;
+use crate::target::Target;
+
+
+/// Severity of a [Finding]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {Error, Warning, Info}
+
+impl Severity {
+    /// Retrieve the keyword associated with the severity
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Error     => "error",
+            Self::Warning   => "warning",
+            Self::Info      => "info",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+
+/// A single issue
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    /// Id of the rule which reported the finding
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// The module or entity the finding refers to
+    pub target: Option<Target>,
+    /// Byte range in the source the finding refers to
+    pub span: Option<Range<usize>>,
+    /// Suggested fix, if any
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    /// Create a new finding without target, span and fix
+    pub fn new(rule: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {rule, severity, message: message.into(), target: None, span: None, fix: None}
+    }
+
+    /// Set the target the finding refers to
+    pub fn with_target(self, target: impl Into<Option<Target>>) -> Self {
+        Self {target: target.into(), ..self}
+    }
+
+    /// Set the location in the source the finding refers to
+    pub fn with_span(self, span: impl Into<Option<Range<usize>>>) -> Self {
+        Self {span: span.into(), ..self}
+    }
+
+    /// Set the suggested fix
+    pub fn with_fix(self, fix: impl Into<Option<Fix>>) -> Self {
+        Self {fix: fix.into(), ..self}
+    }
+
+    /// Retrieve a value serializing the finding as JSON
+    pub fn json(&self) -> Json<'_, Self> {
+        Json(self)
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.severity, self.rule)?;
+        if let Some(target) = self.target.as_ref() {
+            write!(f, ": {}", target)?;
+        }
+        if let Some(span) = self.span.as_ref() {
+            write!(f, " ({}..{})", span.start, span.end)?;
+        }
+        write!(f, ": {}", self.message)?;
+        match &self.fix {
+            Some(Fix::Rename(name)) => write!(f, " (rename to {})", name),
+            None                    => Ok(()),
+        }
+    }
+}
+
+
+/// Fix suggested along with a [Finding]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fix {
+    /// Rename the entity targeted by the finding
+    Rename(Arc<str>),
+}
+
+
+/// A collection of [Finding]s
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a finding to the report
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding)
+    }
+
+    /// Retrieve all findings in the order they were added
+    pub fn findings(&self) -> &[Finding] {
+        self.findings.as_ref()
+    }
+
+    /// Retrieve the number of findings with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+
+    /// Check whether the report contains any finding with [Severity::Error]
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|f| f.severity == Severity::Error)
+    }
+
+    /// Check whether the report does not contain any findings
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Retrieve a value serializing the report as JSON
+    ///
+    /// The report is serialized as an object holding the list of findings
+    /// under the key `findings` and the number of findings per severity under
+    /// the keywords of the severities.
+    pub fn json(&self) -> Json<'_, Self> {
+        Json(self)
+    }
+}
+
+impl Extend<Finding> for Report {
+    fn extend<I: IntoIterator<Item = Finding>>(&mut self, iter: I) {
+        self.findings.extend(iter)
+    }
+}
+
+impl std::iter::FromIterator<Finding> for Report {
+    fn from_iter<I: IntoIterator<Item = Finding>>(iter: I) -> Self {
+        Self {findings: iter.into_iter().collect()}
+    }
+}
+
+impl From<Vec<Finding>> for Report {
+    fn from(findings: Vec<Finding>) -> Self {
+        Self {findings}
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.findings.iter().try_for_each(|finding| writeln!(f, "{}", finding))?;
+        writeln!(
+            f,
+            "{} errors, {} warnings, {} infos",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info),
+        )
+    }
+}
+
+
+/// JSON serialization of a [Finding] or [Report]
+///
+/// The `Display` impl of this type emits a single line of JSON.
+#[derive(Copy, Clone, Debug)]
+pub struct Json<'a, T>(&'a T);
+
+impl fmt::Display for Json<'_, Finding> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let finding = self.0;
+        write!(f, "{{\"severity\":\"{}\",\"rule\":{}", finding.severity, JsonString(finding.rule))?;
+        write!(f, ",\"message\":{}", JsonString(&finding.message))?;
+        write!(f, ",\"target\":")?;
+        match finding.target.as_ref() {
+            Some(target)    => write!(f, "{}", JsonString(&target.to_string()))?,
+            None            => write!(f, "null")?,
+        }
+        match finding.span.as_ref() {
+            Some(span)      => write!(f, ",\"span\":{{\"start\":{},\"end\":{}}}", span.start, span.end)?,
+            None            => write!(f, ",\"span\":null")?,
+        }
+        write!(f, ",\"fix\":")?;
+        match finding.fix.as_ref() {
+            Some(Fix::Rename(name)) => write!(f, "{{\"rename\":{}}}}}", JsonString(name)),
+            None                    => write!(f, "null}}"),
+        }
+    }
+}
+
+impl fmt::Display for Json<'_, Report> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.0;
+        write!(f, "{{\"findings\":[")?;
+        report.findings.iter().enumerate().try_for_each(|(i, finding)| if i > 0 {
+            write!(f, ",{}", finding.json())
+        } else {
+            write!(f, "{}", finding.json())
+        })?;
+        write!(f, "]")?;
+        [Severity::Error, Severity::Warning, Severity::Info]
+            .iter()
+            .try_for_each(|s| write!(f, ",\"{}\":{}", s, report.count(*s)))?;
+        write!(f, "}}")
+    }
+}
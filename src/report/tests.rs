@@ -0,0 +1,85 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to diagnostic reports
+
+use quickcheck::{Arbitrary, Gen};
+
+use super::*;
+
+
+impl Arbitrary for Severity {
+    fn arbitrary(g: &mut Gen) -> Self {
+        *g.choose(&[Self::Error, Self::Warning, Self::Info]).unwrap()
+    }
+}
+
+
+impl Arbitrary for Finding {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let span = Option::<(u16, u16)>::arbitrary(g).map(|(a, b)| a.min(b).into()..a.max(b).into());
+        Self::new("arbitrary", Arbitrary::arbitrary(g), String::arbitrary(g))
+            .with_target(Option::<Target>::arbitrary(g))
+            .with_span(span)
+            .with_fix(Option::<String>::arbitrary(g).map(|n| Fix::Rename(n.into())))
+    }
+}
+
+
+#[quickcheck]
+fn report_counts(findings: Vec<Finding>) -> bool {
+    let report: Report = findings.iter().cloned().collect();
+    let display = report.to_string();
+    report.count(Severity::Error) + report.count(Severity::Warning) + report.count(Severity::Info) ==
+        findings.len() &&
+        report.has_errors() == findings.iter().any(|f| f.severity == Severity::Error) &&
+        display.ends_with(&format!("{} infos\n", report.count(Severity::Info)))
+}
+
+
+#[quickcheck]
+fn json_string_escapes(s: String) -> bool {
+    let json = Finding::new("rule", Severity::Info, s.clone()).json().to_string();
+    let start = "{\"severity\":\"info\",\"rule\":\"rule\",\"message\":\"";
+    let end = "\",\"target\":null,\"span\":null,\"fix\":null}";
+    let escaped = &json[start.len()..json.len() - end.len()];
+
+    // Unescaping must yield the original message
+    let mut res = String::new();
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            if c == '"' || c < ' ' {
+                return false
+            }
+            res.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&code, 16).ok().and_then(std::char::from_u32) {
+                    Some(c) => res.push(c),
+                    None => return false,
+                }
+            },
+            Some(c) => res.push(c),
+            None => return false,
+        }
+    }
+    json.starts_with(start) && json.ends_with(end) && res == s
+}
+
+
+#[test]
+fn json_escapes_message() {
+    let finding = Finding::new("rule", Severity::Warning, "name \"a\\b\"\n");
+    assert_eq!(
+        finding.json().to_string(),
+        r#"{"severity":"warning","rule":"rule","message":"name \"a\\b\"\n","target":null,"span":null,"fix":null}"#,
+    );
+    let report: Report = vec![finding].into();
+    assert!(report.json().to_string().contains(r#""message":"name \"a\\b\"\n""#));
+}
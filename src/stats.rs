@@ -0,0 +1,135 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Circuit statistics
+//!
+//! This module provides [CircuitStats], a summary of the contents of a circuit
+//! suitable for reporting. Statistics are gathered over module definitions,
+//! i.e. a module instantiated multiple times only contributes once.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::circuit::Circuit;
+use crate::expr::{self, Expression};
+use crate::module::Module;
+use crate::stmt::{Entity, Kind, Statement};
+
+
+/// Statistics of a circuit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CircuitStats {
+    /// Number of distinct modules, including external modules
+    pub modules: usize,
+    /// Number of instantiations
+    pub instances: usize,
+    /// Number of registers
+    pub registers: usize,
+    /// Number of memories, including simple memories
+    pub memories: usize,
+    /// Total number of bits held by all memories with known widths
+    pub memory_bits: u64,
+    /// Number of wires
+    pub wires: usize,
+    /// Number of nodes
+    pub nodes: usize,
+    /// Number of primitive operations per operation keyword
+    pub primitive_ops: BTreeMap<&'static str, usize>,
+    /// Maximum depth of any expression
+    ///
+    /// References and literals have a depth of one.
+    pub max_expr_depth: usize,
+    /// Maximum nesting depth of conditional statements
+    pub max_cond_depth: usize,
+}
+
+impl CircuitStats {
+    /// Gather statistics for the given circuit
+    pub fn of(circuit: &Circuit) -> Self {
+        let mut res: Self = Default::default();
+        circuit.modules().for_each(|m| res.add_module(m));
+        res
+    }
+
+    /// Add the contents of a single module to the statistics
+    fn add_module(&mut self, module: &Module) {
+        self.modules += 1;
+        self.add_stmts(module.statements(), 0)
+    }
+
+    /// Add the given statements nested in `depth` conditionals
+    fn add_stmts(&mut self, stmts: &[Statement], depth: usize) {
+        self.max_cond_depth = self.max_cond_depth.max(depth);
+
+        stmts.iter().for_each(|stmt| {
+            stmt.expressions().into_iter().for_each(|e| self.add_expr(e));
+
+            match stmt.as_ref() {
+                Kind::Declaration(entity)           => match entity.as_ref() {
+                    Entity::Wire{..}        => self.wires += 1,
+                    Entity::Register(..)    => self.registers += 1,
+                    Entity::Node{..}        => self.nodes += 1,
+                    Entity::Memory(mem)     => {
+                        self.memories += 1;
+                        self.memory_bits = self.memory_bits.saturating_add(mem.bits().unwrap_or(0));
+                    },
+                    Entity::Instance(..) | Entity::InstanceChoice(..) => self.instances += 1,
+                    _                       => (),
+                },
+                Kind::SimpleMemDecl(mem)            => {
+                    self.memories += 1;
+                    self.memory_bits = self.memory_bits.saturating_add(mem.bits().unwrap_or(0));
+                },
+                Kind::Conditional{when, r#else, ..} => {
+                    self.add_stmts(when, depth + 1);
+                    self.add_stmts(r#else, depth + 1);
+                },
+                _                                   => (),
+            }
+        })
+    }
+
+    /// Add the given expression and its sub-expressions
+    fn add_expr<R: expr::Reference>(&mut self, expr: &Expression<R>) {
+        use transiter::AutoTransIter;
+
+        expr.trans_iter().for_each(|e| if let Expression::PrimitiveOp(op) = e {
+            *self.primitive_ops.entry(op.keyword()).or_default() += 1
+        });
+        self.max_expr_depth = self.max_expr_depth.max(expr_depth(expr));
+    }
+
+    /// Retrieve the total number of primitive operations
+    pub fn total_primitive_ops(&self) -> usize {
+        self.primitive_ops.values().sum()
+    }
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("modules", self.modules as u64),
+            ("instances", self.instances as u64),
+            ("registers", self.registers as u64),
+            ("memories", self.memories as u64),
+            ("memory bits", self.memory_bits),
+            ("wires", self.wires as u64),
+            ("nodes", self.nodes as u64),
+            ("primitive ops", self.total_primitive_ops() as u64),
+            ("max expression depth", self.max_expr_depth as u64),
+            ("max conditional depth", self.max_cond_depth as u64),
+        ];
+        rows.iter().try_for_each(|(k, v)| writeln!(f, "{:<24}{:>10}", k, v))?;
+        self.primitive_ops.iter().try_for_each(|(k, v)| writeln!(f, "  {:<22}{:>10}", k, v))
+    }
+}
+
+
+/// Compute the depth of an expression
+fn expr_depth<R: expr::Reference>(expr: &Expression<R>) -> usize {
+    use transiter::AutoTransIter;
+
+    <&Expression<R> as AutoTransIter<_>>::recurse(&expr).into_iter().map(expr_depth).max().unwrap_or(0) + 1
+}
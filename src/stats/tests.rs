@@ -0,0 +1,36 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to circuit statistics
+
+use quickcheck::TestResult;
+
+use crate::circuit::Circuit;
+use crate::stmt::Entity;
+use crate::types::Type;
+
+use super::*;
+
+
+#[quickcheck]
+fn stats_declaration_counts(circuit: Circuit) -> TestResult {
+    let count = |f: fn(&Entity) -> bool| circuit
+        .modules()
+        .flat_map(|m| m.statements())
+        .flat_map(Statement::declarations)
+        .filter(|e| f(e))
+        .count();
+
+    let stats = CircuitStats::of(&circuit);
+    let res = stats.modules == circuit.modules().count() &&
+        stats.instances == count(|e| matches!(e, Entity::Instance(..))) &&
+        stats.registers == count(|e| matches!(e, Entity::Register(..))) &&
+        stats.wires == count(|e| matches!(e, Entity::Wire{..})) &&
+        stats.nodes == count(|e| matches!(e, Entity::Node{..}));
+    TestResult::from_bool(res)
+}
+
+
+#[quickcheck]
+fn bit_width_vector(base: Type, width: u16) -> bool {
+    Type::Vector(base.clone().into(), width).bit_width() == base.bit_width().and_then(|b| b.checked_mul(width.into()))
+}
@@ -6,24 +6,34 @@ pub(crate) mod display;
 pub(crate) mod parsers;
 
 pub mod context;
+pub mod decl;
 pub mod entity;
+pub mod extension;
+pub mod grammar;
 pub mod print;
+pub mod r#match;
+pub mod rewrite;
+pub mod verification;
 
-#[cfg(test)]
-pub mod tests;
+#[cfg(any(test, feature = "test-util"))]
+#[cfg_attr(not(test), allow(dead_code, unused_imports))]
+pub(crate) mod tests;
 
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
+use crate::circuit::Version;
 use crate::expr;
 use crate::indentation::{DisplayIndented, Indentation};
 use crate::info;
 use crate::memory::simple::Memory as SimpleMem;
 use crate::module;
+use crate::provenance::Id;
 
+pub use decl::DeclInfo;
 pub use entity::Entity;
 
 
@@ -32,6 +42,7 @@ pub use entity::Entity;
 pub struct Statement {
     kind: Kind,
     info: Option<String>,
+    id: Option<Id>,
 }
 
 impl Statement {
@@ -64,15 +75,116 @@ impl Statement {
         })
     }
 
+    /// Retrieve all instance choices appearing in this statement
+    ///
+    /// This function retrieves all [module::InstanceChoice]s (declarations) in
+    /// a given statement, including those in nested statements.
+    pub fn instance_choices(&self) -> impl Iterator<Item = &module::InstanceChoice> {
+        self.declarations().filter_map(|e| if let Entity::InstanceChoice(i) = e.as_ref() {
+            Some(i)
+        } else {
+            None
+        })
+    }
+
+    /// Retrieve all expressions used directly in this statement
+    ///
+    /// This function retrieves the expressions appearing in the statement
+    /// itself, including those in declarations such as node values and
+    /// register clocks. For conditional statements, only the condition is
+    /// included, i.e. expressions in nested statements are not.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self.as_ref() {
+            Kind::Connection{from, to}          => vec![to, from],
+            Kind::PartialConnection{from, to}   => vec![to, from],
+            Kind::Declaration(entity)           => match entity.as_ref() {
+                Entity::Register(reg)       => std::iter::once(reg.clock())
+                    .chain(reg.reset_signal())
+                    .chain(reg.reset_value())
+                    .collect(),
+                Entity::Node{value, ..}     => vec![value],
+                Entity::SimpleMemPort(port) => vec![port.address(), port.clock()],
+                _                           => Default::default(),
+            },
+            Kind::Invalidate(expr)              => vec![expr],
+            Kind::Attach(exprs)                 => exprs.iter().collect(),
+            Kind::Conditional{cond, ..}         => vec![cond],
+            Kind::Stop{clock, cond, ..}         => vec![clock, cond],
+            Kind::Print{clock, cond, msg, ..}   => vec![clock, cond]
+                .into_iter()
+                .chain(msg.iter().filter_map(|p| if let print::PrintElement::Value(e, _) = p {
+                    Some(e)
+                } else {
+                    None
+                }))
+                .collect(),
+            Kind::Verification{clock, pred, en, ..} => vec![clock, pred, en],
+            Kind::Define{from, to}              => vec![to, from],
+            Kind::Match{value, ..}              => vec![value],
+            Kind::Extension(stmt)               => stmt.expressions(),
+            _                                   => Default::default(),
+        }
+    }
+
     /// Retrieve the statement [Kind]
     pub fn kind(&self) -> &Kind {
         &self.kind
     }
+
+    /// Create a statement of the given kind with this statement's attributes
+    ///
+    /// The resulting statement retains the info and provenance id of this
+    /// statement.
+    pub fn with_kind(&self, kind: Kind) -> Self {
+        Self {kind, info: self.info.clone(), id: self.id}
+    }
+
+    /// Create a copy of this statement with its direct children mapped
+    ///
+    /// This function applies `f` to every statement nested directly in this
+    /// statement, i.e. the statements in both branches of a conditional
+    /// statement, in the arms of a match statement or in a layer block, and
+    /// replaces them with the result. Statements nested deeper are not visited
+    /// unless `f` calls `map_children` itself. For statements without
+    /// children, this function returns a plain copy.
+    pub fn map_children(&self, mut f: impl FnMut(&Self) -> Self) -> Self {
+        match self.kind() {
+            Kind::Conditional{cond, when, r#else} => self.with_kind(Kind::Conditional{
+                cond: cond.clone(),
+                when: when.iter().map(&mut f).collect(),
+                r#else: r#else.iter().map(&mut f).collect(),
+            }),
+            Kind::Match{value, arms} => self.with_kind(Kind::Match{
+                value: value.clone(),
+                arms: arms.iter()
+                    .map(|a| a.with_contents(a.binder().cloned(), a.stmts().iter().map(&mut f).collect::<Vec<_>>()))
+                    .collect(),
+            }),
+            Kind::LayerBlock{layer, stmts} => self.with_kind(Kind::LayerBlock{
+                layer: layer.clone(),
+                stmts: stmts.iter().map(&mut f).collect(),
+            }),
+            _ => self.clone(),
+        }
+    }
+
+    /// Retrieve the provenance id of this statement
+    ///
+    /// Statements created by parsing don't carry an id. Ids may be assigned
+    /// via [Provenance::assign](crate::provenance::Provenance::assign).
+    pub fn id(&self) -> Option<Id> {
+        self.id
+    }
+
+    /// Set the provenance id of this statement
+    pub fn with_id(self, id: impl Into<Option<Id>>) -> Self {
+        Self {id: id.into(), ..self}
+    }
 }
 
 impl From<Kind> for Statement {
     fn from(kind: Kind) -> Self {
-        Self {kind, info: Default::default()}
+        Self {kind, info: Default::default(), id: Default::default()}
     }
 }
 
@@ -96,18 +208,31 @@ impl<'a> transiter::AutoTransIter<&'a Statement> for &'a Statement {
     type RecIter = Vec<Self>;
 
     fn recurse(item: &Self) -> Self::RecIter {
-        if let Kind::Conditional{when, r#else, ..} = item.kind() {
-            when.iter().chain(r#else.iter()).collect()
-        } else {
-            Default::default()
+        match item.kind() {
+            Kind::Conditional{when, r#else, ..} => when.iter().chain(r#else.iter()).collect(),
+            Kind::Match{arms, ..}               => arms.iter().flat_map(|a| a.stmts().iter()).collect(),
+            Kind::LayerBlock{stmts, ..}         => stmts.iter().collect(),
+            _                                   => Default::default(),
         }
     }
 }
 
-impl DisplayIndented for Statement {
-    fn fmt<W: fmt::Write>(&self, indent: &mut Indentation, f: &mut W) -> fmt::Result {
+impl Statement {
+    /// Format this statement in the given `syntax`
+    ///
+    /// For FIRRTL versions `3.0.0` and later, connections and invalidations
+    /// are formatted using the `connect` and `invalidate` keywords,
+    /// respectively. Otherwise, `<=` and `is invalid` are used. Parameter
+    /// assignments of instances are only included if enabled in `syntax`.
+    pub(crate) fn fmt_versioned<W: fmt::Write>(
+        &self,
+        syntax: display::Syntax,
+        indent: &mut Indentation,
+        f: &mut W,
+    ) -> fmt::Result {
         use crate::display::CommaSeparated;
         use crate::info::Info;
+        use crate::named::Named;
         use display::OptionalName;
 
         fn into_expr(elem: &print::PrintElement) -> Option<&Expression> {
@@ -123,43 +248,52 @@ impl DisplayIndented for Statement {
             when: &Arc<[Statement]>,
             r#else: &Arc<[Statement]>,
             indent: &mut Indentation,
+            syntax: display::Syntax,
             info: Info,
             f: &mut impl fmt::Write,
         ) -> fmt::Result {
             writeln!(f, "when {}:{}", cond, info)?;
-            display::StatementList(when.as_ref()).fmt(&mut indent.sub(), f)?;
+            display::StatementList(when.as_ref(), syntax).fmt(&mut indent.sub(), f)?;
 
             if let [stmt] = r#else.as_ref() {
                 if let Kind::Conditional{cond, when, r#else} = stmt.as_ref() {
                     write!(f, "{}else ", indent.lock())?;
-                    return fmt_indendet_cond(cond, when, r#else, indent, Info::of(stmt), f);
+                    return fmt_indendet_cond(cond, when, r#else, indent, syntax, Info::of(stmt), f);
                 }
             }
 
             if r#else.len() > 0 {
                 writeln!(f, "{}else:", indent.lock())?;
-                display::StatementList(r#else.as_ref()).fmt(&mut indent.sub(), f)
+                display::StatementList(r#else.as_ref(), syntax).fmt(&mut indent.sub(), f)
             } else {
                 Ok(())
             }
         }
 
         let info = Info::of(self);
+        let keywords = syntax.version.map(|v| v >= Version::CONNECT_KEYWORDS).unwrap_or(false);
 
         match self.as_ref() {
+            Kind::Connection{from, to} if keywords  =>
+                writeln!(f, "{}connect {}, {}{}", indent.lock(), to, from, info),
             Kind::Connection{from, to}              =>
                 writeln!(f, "{}{} <= {}{}", indent.lock(), to, from, info),
             Kind::PartialConnection{from, to}       =>
                 writeln!(f, "{}{} <- {}{}", indent.lock(), to, from, info),
             Kind::Empty                             => writeln!(f, "{}skip{}", indent.lock(), info),
-            Kind::Declaration(entity)               => display::EntityDecl(entity, info).fmt(indent, f),
+            Kind::Declaration(entity)               => match entity.as_ref() {
+                Entity::Instance(inst) if syntax.instance_parameters =>
+                    writeln!(f, "{}{:#}{}", indent.lock(), inst, info),
+                entity => display::EntityDecl(entity, info).fmt(indent, f),
+            },
             Kind::SimpleMemDecl(mem)                => writeln!(f, "{}{}{}", indent.lock(), mem, info),
+            Kind::Invalidate(expr) if keywords      => writeln!(f, "{}invalidate {}{}", indent.lock(), expr, info),
             Kind::Invalidate(expr)                  => writeln!(f, "{}{} is invalid", indent.lock(), expr),
             Kind::Attach(exprs)                     =>
                 writeln!(f, "{}attach({}){}", indent.lock(), CommaSeparated::from(exprs), info),
             Kind::Conditional{cond, when, r#else}   => {
                 write!(f, "{}", indent.lock())?;
-                fmt_indendet_cond(cond, when, r#else, indent, info, f)
+                fmt_indendet_cond(cond, when, r#else, indent, syntax, info, f)
             },
             Kind::Stop{name, clock, cond, code}     => writeln!(f,
                 "{}stop({}, {}, {}){}{}",
@@ -180,11 +314,48 @@ impl DisplayIndented for Statement {
                 OptionalName::from(name.as_ref().map(AsRef::as_ref)),
                 info,
             ),
+            Kind::Verification{name, op, clock, pred, en, msg} => writeln!(f,
+                "{}{}({}, {}, {}, {}){}{}",
+                indent.lock(),
+                op,
+                clock,
+                pred,
+                en,
+                display::StringLiteral(msg),
+                OptionalName::from(name.as_ref().map(AsRef::as_ref)),
+                info,
+            ),
+            Kind::Define{from, to}                  =>
+                writeln!(f, "{}define {} = {}{}", indent.lock(), to, from, info),
+            Kind::Match{value, arms}                => {
+                writeln!(f, "{}match {}:{}", indent.lock(), value, info)?;
+                let mut arm_indent = indent.sub();
+                arms.iter().try_for_each(|arm| {
+                    write!(f, "{}{}", arm_indent.lock(), arm.variant())?;
+                    if let Some(binder) = arm.binder() {
+                        write!(f, "({})", binder.name())?;
+                    }
+                    writeln!(f, ":")?;
+                    display::StatementList(arm.stmts().as_ref(), syntax).fmt(&mut arm_indent.sub(), f)
+                })
+            },
+            Kind::LayerBlock{layer, stmts}          => {
+                writeln!(f, "{}layerblock {}:{}", indent.lock(), layer, info)?;
+                display::StatementList(stmts.as_ref(), syntax).fmt(&mut indent.sub(), f)
+            },
+            Kind::Extension(stmt)                   =>
+                writeln!(f, "{}{}{}", indent.lock(), stmt, info),
         }
     }
 }
 
-#[cfg(test)]
+impl DisplayIndented for Statement {
+    fn fmt<W: fmt::Write>(&self, indent: &mut Indentation, f: &mut W) -> fmt::Result {
+        self.fmt_versioned(Default::default(), indent, f)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Statement {
     fn arbitrary(g: &mut Gen) -> Self {
         use std::iter::from_fn as fn_iter;
@@ -197,7 +368,7 @@ impl Arbitrary for Statement {
             return Kind::Empty.into()
         }
 
-        let opts: [&dyn Fn(&mut Gen) -> Kind; 10] = [
+        let opts: [&dyn Fn(&mut Gen) -> Kind; 11] = [
             &|g| {
                 let t = Type::arbitrary(g);
                 Kind::Connection{
@@ -245,6 +416,14 @@ impl Arbitrary for Statement {
                 cond: expr_with_type(GT::UInt(Some(1)), source_flow(g), g),
                 msg: tests::FormatString::arbitrary(g).into(),
             },
+            &|g| Kind::Verification {
+                name: Option::<Identifier>::arbitrary(g).map(Into::into),
+                op: Arbitrary::arbitrary(g),
+                clock: expr_with_type(GT::Clock, source_flow(g), g),
+                pred: expr_with_type(GT::UInt(Some(1)), source_flow(g), g),
+                en: expr_with_type(GT::UInt(Some(1)), source_flow(g), g),
+                msg: crate::tests::ASCII::arbitrary(g).to_string(),
+            },
         ];
 
         // We want to reduce the effective generation size in order to keep
@@ -338,6 +517,19 @@ pub enum Kind {
     Conditional{cond: Expression, when: Arc<[Statement]>, r#else: Arc<[Statement]>},
     Stop{name: Option<Arc<str>>, clock: Expression, cond: Expression, code: i64},
     Print{name: Option<Arc<str>>, clock: Expression, cond: Expression, msg: Vec<print::PrintElement>},
+    Verification{
+        name: Option<Arc<str>>,
+        op: verification::Op,
+        clock: Expression,
+        pred: Expression,
+        en: Expression,
+        msg: String,
+    },
+    Define{from: Expression, to: Expression},
+    Match{value: Expression, arms: Vec<r#match::Arm>},
+    LayerBlock{layer: Arc<str>, stmts: Arc<[Statement]>},
+    /// A custom statement provided by a [Dialect](extension::Dialect)
+    Extension(Arc<dyn extension::CustomStatement>),
 }
 
 
@@ -5,10 +5,13 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::circuit::Version;
 use crate::memory::simple::Memory as SimpleMem;
 use crate::module::{Module, Port as ModPort};
 use crate::named::Named;
+use crate::types::TypeAlias;
 use super::entity::Entity;
+use super::extension::Dialect;
 
 
 /// Context for named things
@@ -30,6 +33,44 @@ pub trait Context {
     /// Retrieve the module with the given name
     fn module(&self, name: &str) -> Option<Arc<Module>>;
 
+    /// Retrieve the FIRRTL version governing the grammar, if any
+    fn version(&self) -> Option<Version> {
+        None
+    }
+
+    /// Retrieve the [Dialect]s providing custom statements
+    fn dialects(&self) -> &[Arc<dyn Dialect>] {
+        &[]
+    }
+
+    /// Retrieve the [TypeAlias]es which may be used in types
+    fn type_aliases(&self) -> &[TypeAlias] {
+        &[]
+    }
+
+    /// Check whether multiple statements on a single line, separated by `;`, are
+    /// recognized
+    ///
+    /// If `false`, a `;` always starts a comment.
+    fn separated_statements(&self) -> bool {
+        false
+    }
+
+    /// Check whether parameter assignments of instances are recognized
+    ///
+    /// See [Instance](crate::module::Instance) for the syntax of such
+    /// assignments, which is an extension.
+    fn instance_parameters(&self) -> bool {
+        false
+    }
+
+    /// Record a parsed statement
+    ///
+    /// Returns `false` if the number of statements exceeds the limit.
+    fn count_statement(&mut self) -> bool {
+        true
+    }
+
     /// Create a [SubContext] for this Context
     fn sub(&mut self) -> SubContext
     where Self: Sized
@@ -45,12 +86,30 @@ pub struct TopContext<M> {
     entities: HashMap<Arc<str>, Arc<Entity>>,
     memories: HashMap<Arc<str>, Arc<SimpleMem>>,
     module: M,
+    version: Option<Version>,
+    dialects: Vec<Arc<dyn Dialect>>,
+    aliases: Arc<[TypeAlias]>,
+    statements: usize,
+    max_statements: Option<usize>,
+    separated: bool,
+    parameters: bool,
 }
 
 impl<M> TopContext<M> {
     /// Create a new toplevel Context
     pub fn new(module: M) -> Self {
-        Self {entities: Default::default(), memories: Default::default(), module}
+        Self {
+            entities: Default::default(),
+            memories: Default::default(),
+            module,
+            version: Default::default(),
+            dialects: Default::default(),
+            aliases: Arc::new([]),
+            statements: 0,
+            max_statements: None,
+            separated: false,
+            parameters: false,
+        }
     }
 
     /// Create a new toplevel Context
@@ -62,6 +121,37 @@ impl<M> TopContext<M> {
     pub fn with_ports(self, ports: impl IntoIterator<Item = Arc<ModPort>>) -> Self {
         self.with_entities(ports.into_iter().map(Into::into).map(Arc::new))
     }
+
+    /// Set the FIRRTL version governing the grammar
+    pub fn with_version(self, version: impl Into<Option<Version>>) -> Self {
+        Self {version: version.into(), ..self}
+    }
+
+    /// Set the [Dialect]s providing custom statements
+    pub fn with_dialects(self, dialects: impl IntoIterator<Item = Arc<dyn Dialect>>) -> Self {
+        Self {dialects: dialects.into_iter().collect(), ..self}
+    }
+
+    /// Set the [TypeAlias]es which may be used in types
+    pub fn with_type_aliases(self, aliases: impl Into<Arc<[TypeAlias]>>) -> Self {
+        Self {aliases: aliases.into(), ..self}
+    }
+
+    /// Set the maximum number of statements, including nested ones
+    pub fn with_max_statements(self, max: impl Into<Option<usize>>) -> Self {
+        Self {max_statements: max.into(), ..self}
+    }
+
+    /// Set whether multiple statements on a single line, separated by `;`,
+    /// are recognized
+    pub fn with_separated_statements(self, separated: bool) -> Self {
+        Self {separated, ..self}
+    }
+
+    /// Set whether parameter assignments of instances are recognized
+    pub fn with_instance_parameters(self, parameters: bool) -> Self {
+        Self {parameters, ..self}
+    }
 }
 
 impl<M> From<M> for TopContext<M> {
@@ -90,6 +180,31 @@ impl<M: Fn(&str) -> Option<Arc<Module>>> Context for TopContext<M> {
     fn module(&self, name: &str) -> Option<Arc<Module>> {
         (self.module)(name)
     }
+
+    fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    fn dialects(&self) -> &[Arc<dyn Dialect>] {
+        self.dialects.as_ref()
+    }
+
+    fn type_aliases(&self) -> &[TypeAlias] {
+        self.aliases.as_ref()
+    }
+
+    fn separated_statements(&self) -> bool {
+        self.separated
+    }
+
+    fn instance_parameters(&self) -> bool {
+        self.parameters
+    }
+
+    fn count_statement(&mut self) -> bool {
+        self.statements += 1;
+        self.max_statements.map(|m| self.statements <= m).unwrap_or(true)
+    }
 }
 
 
@@ -133,6 +248,30 @@ impl<'p> Context for SubContext<'p> {
     fn module(&self, name: &str) -> Option<Arc<Module>> {
         self.parent.module(name)
     }
+
+    fn version(&self) -> Option<Version> {
+        self.parent.version()
+    }
+
+    fn dialects(&self) -> &[Arc<dyn Dialect>] {
+        self.parent.dialects()
+    }
+
+    fn type_aliases(&self) -> &[TypeAlias] {
+        self.parent.type_aliases()
+    }
+
+    fn separated_statements(&self) -> bool {
+        self.parent.separated_statements()
+    }
+
+    fn instance_parameters(&self) -> bool {
+        self.parent.instance_parameters()
+    }
+
+    fn count_statement(&mut self) -> bool {
+        self.parent.count_statement()
+    }
 }
 
 impl Drop for SubContext<'_> {
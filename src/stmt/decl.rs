@@ -0,0 +1,98 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Info attributes of declarations
+//!
+//! Entities do not carry an info attribute themselves. Instead, the info of a
+//! declaration is attached to the [Statement] declaring the entity. Transforms
+//! moving entities to new statements, e.g. for hoisting declarations out of
+//! conditional statements, thus need to propagate the info explicitly. A
+//! [DeclInfo] records the info attributes of declarations and re-attaches
+//! them to new declarations of the same entities.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::info::WithInfo;
+//! use firrtl_ast::stmt::{DeclInfo, Kind};
+//!
+//! let circuit = firrtl_ast::circuit::parse(concat!(
+//!     "circuit Foo:\n",
+//!     "  module Foo:\n",
+//!     "    input a: UInt<1>\n",
+//!     "    when a:\n",
+//!     "      wire w: UInt<1> @[foo.scala 4:5]\n",
+//!     "      w <= a\n",
+//! )).unwrap();
+//! let stmts = circuit.top_module().statements();
+//! let infos = DeclInfo::collect(stmts);
+//!
+//! let entity = stmts.iter().flat_map(|s| s.declarations()).next().unwrap();
+//! let hoisted = infos.declare(entity.clone());
+//! assert_eq!(hoisted.info(), Some("foo.scala 4:5"));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::info::WithInfo;
+use crate::named::Named;
+
+use super::{Entity, Kind, Statement};
+
+
+/// Info attributes of declarations, identified by the name of the entity
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeclInfo {
+    infos: HashMap<Arc<str>, String>,
+}
+
+impl DeclInfo {
+    /// Record the info of all declarations in the given statements
+    ///
+    /// Declarations nested in conditional statements are included.
+    /// Declarations without an info attribute are not recorded.
+    pub fn collect<'a>(stmts: impl IntoIterator<Item = &'a Statement>) -> Self {
+        use transiter::AutoTransIter;
+
+        let infos = stmts
+            .into_iter()
+            .flat_map(AutoTransIter::trans_iter)
+            .filter_map(|s| match (s.kind(), s.info()) {
+                (Kind::Declaration(e), Some(info)) => Some((e.name().clone(), info.to_string())),
+                _ => None,
+            })
+            .collect();
+        Self {infos}
+    }
+
+    /// Retrieve the info recorded for the entity with the given name
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.infos.get(name.as_ref()).map(AsRef::as_ref)
+    }
+
+    /// Record an info for the entity with the given name
+    ///
+    /// The info replaces any info previously recorded for that entity.
+    pub fn insert(&mut self, name: impl Into<Arc<str>>, info: impl Into<String>) {
+        self.infos.insert(name.into(), info.into());
+    }
+
+    /// Create a declaration of the given entity with the recorded info
+    pub fn declare(&self, entity: Arc<Entity>) -> Statement {
+        let info = self.get(entity.name_ref()).map(Into::into);
+        Statement::from(Kind::Declaration(entity)).with_info(info)
+    }
+
+    /// Re-attach recorded info to declarations lacking an info
+    ///
+    /// This function yields a copy of the given statement in which every
+    /// declaration without an info attribute, including nested ones, carries
+    /// the info recorded for the entity declared, if any.
+    pub fn restore(&self, stmt: &Statement) -> Statement {
+        match stmt.kind() {
+            Kind::Declaration(e) if stmt.info().is_none() =>
+                stmt.clone().with_info(self.get(e.name_ref()).map(Into::into)),
+            _ => stmt.map_children(|s| self.restore(s)),
+        }
+    }
+}
@@ -4,12 +4,29 @@
 
 use std::fmt;
 
+use crate::circuit::Version;
 use crate::indentation::{DisplayIndented, Indentation};
 use crate::info::Info;
 
 use super::print;
 
 
+/// Syntax in which statements are displayed
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Syntax {
+    /// FIRRTL version governing the grammar
+    pub version: Option<Version>,
+    /// Whether to include parameter assignments of instances
+    pub instance_parameters: bool,
+}
+
+impl From<Option<Version>> for Syntax {
+    fn from(version: Option<Version>) -> Self {
+        Self {version, ..Default::default()}
+    }
+}
+
+
 /// Utility for displaying an entity declaration
 pub(crate) struct EntityDecl<'a>(pub &'a super::Entity, pub Info<'a>);
 
@@ -29,6 +46,22 @@ impl DisplayIndented for EntityDecl<'_> {
             E::Memory(mem)          => MemoryDecl(mem, self.1.clone()).fmt(indentation, f),
             E::SimpleMemPort(port)  => writeln!(f, "{}{}{}", indentation.lock(), port, self.1),
             E::Instance(inst)       => writeln!(f, "{}{}{}", indentation.lock(), inst, self.1),
+            E::InstanceChoice(inst) => {
+                use crate::named::Named;
+
+                writeln!(
+                    f,
+                    "{}instchoice {} of {}, {}:{}",
+                    indentation.lock(),
+                    inst.name(),
+                    inst.default_module().name(),
+                    inst.option(),
+                    self.1,
+                )?;
+                let mut indentation = indentation.sub();
+                inst.cases().iter().try_for_each(|(c, m)| writeln!(f, "{}{} => {}", indentation.lock(), c, m.name()))
+            },
+            E::Binder{..}           => Err(Default::default()),
         }
     }
 }
@@ -45,15 +78,7 @@ impl fmt::Display for FormatString<'_> {
         write!(f, "\"")?;
         for element in self.0 {
             match element {
-                P::Literal(s)               => s.chars().try_for_each(|c| match c {
-                    '%'  => write!(f, "%%"),
-                    '\n' => write!(f, "\\n"),
-                    '\t' => write!(f, "\\t"),
-                    '\\' => write!(f, "\\\\"),
-                    '"'  => write!(f, "\\\""),
-                    '\'' => write!(f, "\\'"),
-                    c    => fmt::Display::fmt(&c, f),
-                }),
+                P::Literal(s)               => escape(s, f),
                 P::Value(_, F::Binary)      => write!(f, "%b"),
                 P::Value(_, F::Decimal)     => write!(f, "%d"),
                 P::Value(_, F::Hexadecimal) => write!(f, "%x"),
@@ -65,6 +90,40 @@ impl fmt::Display for FormatString<'_> {
 }
 
 
+/// Utility for rendering a string literal
+///
+/// The string is escaped in the same way as literal parts of a format string.
+pub struct StringLiteral<'a>(pub &'a str);
+
+impl fmt::Display for StringLiteral<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        escape(self.0, f)?;
+        write!(f, "\"")
+    }
+}
+
+
+/// Write a string with characters special to string literals escaped
+///
+/// Control characters other than newline, tab and carriage return are written
+/// as `\xNN` escape sequences. All other characters, including non-ASCII
+/// ones, are written as they are.
+fn escape(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    s.chars().try_for_each(|c| match c {
+        '%'  => write!(f, "%%"),
+        '\n' => write!(f, "\\n"),
+        '\t' => write!(f, "\\t"),
+        '\r' => write!(f, "\\r"),
+        '\\' => write!(f, "\\\\"),
+        '"'  => write!(f, "\\\""),
+        '\'' => write!(f, "\\'"),
+        c if c.is_control() => write!(f, "\\x{:02x}", c as u32),
+        c    => fmt::Display::fmt(&c, f),
+    })
+}
+
+
 /// Utility for formatting an optional name for a "special" statement
 pub struct OptionalName<'a>(pub Option<&'a str>);
 
@@ -82,12 +141,14 @@ impl fmt::Display for OptionalName<'_> {
 
 
 /// Utility for displaying a list of statements
-pub struct StatementList<'a>(pub &'a [super::Statement]);
+///
+/// The statements are displayed in the given [Syntax].
+pub(crate) struct StatementList<'a>(pub &'a [super::Statement], pub Syntax);
 
 impl DisplayIndented for StatementList<'_> {
     fn fmt<W: fmt::Write>(&self, indent: &mut Indentation, f: &mut W) -> fmt::Result {
         if self.0.len() > 0 {
-            self.0.iter().try_for_each(|s| s.fmt(indent, f))
+            self.0.iter().try_for_each(|s| s.fmt_versioned(self.1, indent, f))
         } else {
             super::Statement::from(super::Kind::Empty).fmt(indent, f)
         }
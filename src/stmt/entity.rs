@@ -4,7 +4,7 @@
 
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use crate::expr;
@@ -18,6 +18,10 @@ use crate::types;
 ///
 /// FIRRTL defines several entities which may be referenced inside an
 /// expression.
+///
+/// Entities do not carry an info attribute. The info of a declaration is
+/// attached to the declaring [Statement](super::Statement) instead. See
+/// [DeclInfo](super::DeclInfo) for propagating it to new declarations.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Entity {
     Port(Arc<module::Port>),
@@ -27,17 +31,42 @@ pub enum Entity {
     Memory(Memory),
     SimpleMemPort(simple_mem::Port<Arc<Self>>),
     Instance(module::Instance),
+    /// Instance of one of several modules, chosen via an option group
+    InstanceChoice(module::InstanceChoice),
+    /// Data bound in an arm of a [match](super::Kind::Match) statement
+    Binder{name: Arc<str>, r#type: types::Type},
 }
 
 impl Entity {
     /// Checks whether this entity can be declared via a [super::Statement]
     ///
     /// Returns true if the entity can be declared, which will be the case for
-    /// most entities. Note that [module::Port]s cannot be declared.
+    /// most entities. Note that neither [module::Port]s nor binders can be
+    /// declared.
     pub fn is_declarable(&self) -> bool {
         match self {
-            Self::Port(..)  => false,
-            _ => true,
+            Self::Port(..)      => false,
+            Self::Binder{..}    => false,
+            _                   => true,
+        }
+    }
+
+    /// Create a copy of this entity with the given name
+    ///
+    /// This function returns `None` for [module::Port]s, which can only be
+    /// renamed as part of their module's interface.
+    pub fn with_name(&self, name: impl Into<Arc<str>>) -> Option<Self> {
+        let name = name.into();
+        match self {
+            Self::Port(..)              => None,
+            Self::Wire{r#type, ..}      => Some(Self::Wire{name, r#type: r#type.clone()}),
+            Self::Register(reg)         => Some(Self::Register(reg.clone().with_name(name))),
+            Self::Node{value, ..}       => Some(Self::Node{name, value: value.clone()}),
+            Self::Memory(mem)           => Some(Self::Memory(mem.clone().with_name(name))),
+            Self::SimpleMemPort(port)   => Some(Self::SimpleMemPort(port.clone().with_name(name))),
+            Self::Instance(inst)        => Some(Self::Instance(inst.clone().with_name(name))),
+            Self::InstanceChoice(inst)  => Some(Self::InstanceChoice(inst.clone().with_name(name))),
+            Self::Binder{r#type, ..}    => Some(Self::Binder{name, r#type: r#type.clone()}),
         }
     }
 }
@@ -72,6 +101,26 @@ impl From<module::Instance> for Entity {
     }
 }
 
+impl From<module::InstanceChoice> for Entity {
+    fn from(inst: module::InstanceChoice) -> Self {
+        Self::InstanceChoice(inst)
+    }
+}
+
+// Entities don't contain any floating point values outside of instantiated
+// modules' parameters, which we don't expect to be NaN.
+impl Eq for Entity {}
+
+/// Entities are hashed by name
+///
+/// Within a module, entities are identified by their name. Hence, hashing the
+/// name only is both sufficient and cheap.
+impl std::hash::Hash for Entity {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name().hash(state)
+    }
+}
+
 impl expr::Reference for Arc<Entity> {
     fn flow(&self) -> Option<expr::Flow> {
         match self.as_ref() {
@@ -82,6 +131,8 @@ impl expr::Reference for Arc<Entity> {
             Entity::Memory(mem)         => mem.flow(),
             Entity::SimpleMemPort(port) => port.flow(),
             Entity::Instance(inst)      => inst.flow(),
+            Entity::InstanceChoice(i)   => i.flow(),
+            Entity::Binder{..}          => Some(expr::Flow::Source),
         }
     }
 }
@@ -98,6 +149,8 @@ impl Named for Entity {
             Entity::Memory(mem)         => mem.name(),
             Entity::SimpleMemPort(port) => port.name(),
             Entity::Instance(inst)      => inst.name(),
+            Entity::InstanceChoice(i)   => i.name(),
+            Entity::Binder{name, ..}    => name,
         }
     }
 }
@@ -116,11 +169,13 @@ impl types::Typed for Arc<Entity> {
             Entity::Memory(mem)         => mem.r#type().map_err(|_| self.clone()),
             Entity::SimpleMemPort(port) => port.r#type().map_err(|_| self.clone()),
             Entity::Instance(inst)      => inst.r#type().map_err(|_| self.clone()),
+            Entity::InstanceChoice(i)   => i.r#type().map_err(|_| self.clone()),
+            Entity::Binder{r#type, ..}  => Ok(r#type.clone()),
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl expr::tests::TypedRef for Arc<Entity> {
     fn with_type(r#type: types::Type, flow: expr::Flow, g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -165,7 +220,7 @@ impl expr::tests::TypedRef for Arc<Entity> {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Entity {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -210,6 +265,13 @@ impl Arbitrary for Entity {
             Self::Memory(mem)           => Box::new(mem.shrink().map(Into::into)),
             Self::SimpleMemPort(port)   => Box::new(port.shrink().map(Into::into)),
             Self::Instance(inst)        => Box::new(inst.shrink().map(Into::into)),
+            Self::InstanceChoice(..)    => Box::new(std::iter::empty()),
+            Self::Binder{name, r#type}  => {
+                let res = (Identifier::from(name.as_ref()), r#type.clone())
+                    .shrink()
+                    .map(|(n, r#type)| Self::Binder{name: n.into(), r#type});
+                Box::new(res)
+            },
         }
     }
 }
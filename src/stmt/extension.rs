@@ -0,0 +1,91 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Dialect extensions
+//!
+//! Downstream projects may need statements not covered by FIRRTL, e.g. for
+//! proprietary annotations of a design. This module allows adding such
+//! statements without forking this crate: a [CustomStatement] is carried in a
+//! [Kind::Extension](super::Kind::Extension) statement and a [Dialect] allows
+//! parsers to recognize it.
+//!
+//! Passes provided by this crate treat custom statements opaquely. They are
+//! only aware of the [Expression](crate::expr::Expression)s a custom statement exposes, which are
+//! rewritten alongside the rest of a module, e.g. when entities are renamed.
+//! Custom statements cannot declare entities.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use super::Expression;
+
+
+/// A statement provided by a [Dialect]
+///
+/// A custom statement occupies a single line, starting with its keyword.
+pub trait CustomStatement: fmt::Debug + Send + Sync {
+    /// Retrieve the keyword introducing the statement
+    fn keyword(&self) -> &str;
+
+    /// Format the statement, excluding the keyword, indentation and info
+    ///
+    /// The output is placed right after the keyword. It must not contain line
+    /// breaks and needs to be understood by the [Dialect] providing the
+    /// statement.
+    fn fmt_args(&self, f: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Retrieve all expressions used in the statement
+    fn expressions(&self) -> Vec<&Expression>;
+
+    /// Create a copy of this statement with every expression mapped
+    ///
+    /// The function is expected to replace each expression returned by
+    /// [Self::expressions] with the result of `f` applied to it.
+    fn map_expressions(&self, f: &mut dyn FnMut(&Expression) -> Expression) -> Arc<dyn CustomStatement>;
+
+    /// Retrieve this statement as [Any], e.g. for downcasting
+    fn as_any(&self) -> &dyn Any;
+
+    /// Check whether this statement equals another custom statement
+    ///
+    /// Implementations will usually attempt to downcast `other` to their own
+    /// type via [Self::as_any] and compare the result.
+    fn eq_dyn(&self, other: &dyn CustomStatement) -> bool;
+}
+
+impl PartialEq for dyn CustomStatement {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_dyn(other)
+    }
+}
+
+impl fmt::Display for dyn CustomStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.keyword())?;
+        self.fmt_args(f)
+    }
+}
+
+
+/// A set of [CustomStatement]s recognized by parsers
+///
+/// Dialects are made available to parsers via the statement
+/// [Context](super::context::Context).
+pub trait Dialect: fmt::Debug + Send + Sync {
+    /// Retrieve the keywords of all statements provided by this dialect
+    fn keywords(&self) -> &[&str];
+
+    /// Parse the statement introduced by the given keyword
+    ///
+    /// The `input` starts right after the keyword. Expressions may be parsed
+    /// via `expr`, which yields the remaining input along with the expression,
+    /// resolving references in the current scope. On success, this function
+    /// yields the statement and the input remaining after it, which may only
+    /// consist of an info and the end of the line.
+    fn parse<'i>(
+        &self,
+        keyword: &str,
+        input: &'i str,
+        expr: &dyn Fn(&'i str) -> Option<(&'i str, Expression)>,
+    ) -> Option<(&'i str, Arc<dyn CustomStatement>)>;
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Parsers for embedding statements in other grammars
+//!
+//! This module exposes the parsers used internally for statements as [nom]
+//! parsers. They allow implementing custom statement dialects or embedding
+//! FIRRTL fragments in other grammars. All parsers operate on `&str` and
+//! yield an [Error] on failure.
+//!
+//! Names are resolved through a [Context]. Contrary to the parsers used for
+//! entire circuits, indentation is given explicitly as a number of spaces.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::stmt::context::TopContext;
+//! use firrtl_ast::stmt::grammar::statements;
+//!
+//! let ctx = TopContext::new(|_: &str| None);
+//! let (rest, stmts) = statements(ctx, 2, "  wire w: UInt<1>\n  w is invalid\nend\n").unwrap();
+//! assert_eq!(stmts.len(), 2);
+//! assert_eq!(rest, "end\n");
+//! ```
+
+use crate::indentation::Indentation;
+use crate::parsers::le;
+
+use super::{Entity, Statement, context::Context, parsers};
+
+pub use crate::parsers::{Error, IResult};
+
+
+/// Parse a single statement
+///
+/// The statement is expected to be indented by exactly `indentation` spaces
+/// and to end with a line break. Entities declared by the statement are not
+/// added to the `ctx`.
+pub fn statement<'i>(ctx: &mut impl Context, indentation: usize, input: &'i str) -> IResult<'i, Statement> {
+    parsers::stmt(ctx, input, &mut Indentation::exact(indentation))
+}
+
+
+/// Parse a sequence of statements
+///
+/// All statements are expected to be indented by exactly `indentation`
+/// spaces. The parser stops at the first line with a different indentation.
+/// Statements may refer to entities declared by preceding statements.
+pub fn statements<'i>(ctx: impl Context, indentation: usize, input: &'i str) -> IResult<'i, Vec<Statement>> {
+    parsers::stmts(ctx, input, &mut Indentation::exact(indentation))
+}
+
+
+/// Parse an entity declaration
+///
+/// This parser accepts declarations of wires, registers, nodes, memories,
+/// simple memory ports and instances indented by exactly `indentation` spaces
+/// and ending with a line break. It yields the [Entity] along with its info,
+/// if any.
+pub fn entity_decl<'i>(
+    ctx: &impl Context,
+    indentation: usize,
+    input: &'i str,
+) -> IResult<'i, (Entity, Option<String>)> {
+    parsers::entity_decl(ctx, input, &mut Indentation::exact(indentation), le)
+}
+
+
+/// Parse a conditional statement
+///
+/// The input is expected to start with the `when` keyword, i.e. any preceding
+/// indentation must have been consumed already. The `indentation` is the one
+/// of the line containing the `when`: any `else` is expected at the same
+/// indentation and the statements in either branch need to be indented
+/// further.
+pub fn indented_condition<'i>(
+    ctx: &mut impl Context,
+    indentation: usize,
+    input: &'i str,
+) -> IResult<'i, Statement> {
+    parsers::indented_condition(ctx, input, &mut Indentation::exact(indentation))
+}
+
+
+/// Parse a match statement
+///
+/// The input is expected to start with the `match` keyword, i.e. any preceding
+/// indentation must have been consumed already. The `indentation` is the one
+/// of the line containing the `match`: the arms need to be indented further
+/// and the statements of each arm further still.
+pub fn indented_match<'i>(
+    ctx: &mut impl Context,
+    indentation: usize,
+    input: &'i str,
+) -> IResult<'i, Statement> {
+    parsers::indented_match(ctx, input, &mut Indentation::exact(indentation))
+}
+
+
+/// Parse a layer block
+///
+/// The input is expected to start with the `layerblock` keyword, i.e. any
+/// preceding indentation must have been consumed already. The `indentation` is
+/// the one of the line containing the `layerblock`: the statements of the
+/// block need to be indented further.
+pub fn indented_layerblock<'i>(
+    ctx: &mut impl Context,
+    indentation: usize,
+    input: &'i str,
+) -> IResult<'i, Statement> {
+    parsers::indented_layerblock(ctx, input, &mut Indentation::exact(indentation))
+}
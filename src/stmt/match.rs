@@ -0,0 +1,51 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Utilities related to match statements
+
+use std::sync::Arc;
+
+use super::{Entity, Statement};
+
+
+/// Arm of a [super::Kind::Match] statement
+///
+/// An arm selects one variant of the matched enumeration. If the variant
+/// carries data, that data may be bound to a [Entity::Binder] which is visible
+/// to the arm's statements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arm {
+    variant: Arc<str>,
+    binder: Option<Arc<Entity>>,
+    stmts: Arc<[Statement]>,
+}
+
+impl Arm {
+    /// Create a new arm for the given variant
+    pub fn new(
+        variant: impl Into<Arc<str>>,
+        binder: Option<Arc<Entity>>,
+        stmts: impl Into<Arc<[Statement]>>,
+    ) -> Self {
+        Self {variant: variant.into(), binder, stmts: stmts.into()}
+    }
+
+    /// Retrieve the name of the variant selected by this arm
+    pub fn variant(&self) -> &Arc<str> {
+        &self.variant
+    }
+
+    /// Retrieve the binder for the variant's data, if any
+    pub fn binder(&self) -> Option<&Arc<Entity>> {
+        self.binder.as_ref()
+    }
+
+    /// Retrieve the statements executed if this arm is selected
+    pub fn stmts(&self) -> &Arc<[Statement]> {
+        &self.stmts
+    }
+
+    /// Create a copy of this arm with the given binder and statements
+    pub fn with_contents(&self, binder: Option<Arc<Entity>>, stmts: impl Into<Arc<[Statement]>>) -> Self {
+        Self {variant: self.variant.clone(), binder, stmts: stmts.into()}
+    }
+}
@@ -8,83 +8,403 @@ use std::sync::Arc;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::{anychar, char as chr};
-use nom::combinator::{iterator, map, opt, value, verify};
-use nom::multi::{many1, separated_list1};
+use nom::combinator::{cond, iterator, map, map_opt, opt, value, verify};
+use nom::multi::{many0, many1, separated_list1};
 use nom::sequence::{preceded, tuple};
 
+use crate::circuit::{Version, parsers::layer_path};
 use crate::expr::parsers::expr;
 use crate::indentation::Indentation;
 use crate::info::{WithInfo, parse as info};
 use crate::memory::parsers::{memory, register, simple_mem, simple_mem_port};
-use crate::module::parsers::instance;
-use crate::parsers::{IResult, comma, decimal, identifier, kw, le, lp, op, rp, spaced};
-use crate::types::parsers::r#type;
+use crate::module::parsers::{instance, instance_choice, port};
+use crate::parsers::{IResult, comma, decimal, escape_sequence, identifier, kw, le, lp, op, rp, skip_block, spaced};
+use crate::types::parsers::{field_name, r#type};
 
-use super::{context::Context, print};
+use super::{context::Context, print, verification};
 
 
 /// Parser for sequences of statements
+///
+/// If the `ctx` [enables separated statements](Context::separated_statements),
+/// multiple statements may be given on a single line, separated by `;`, as
+/// long as the statements following a separator are not conditional
+/// statements. Since `;` also starts a comment, the remainder of a line which
+/// doesn't form a statement is treated as a comment. Otherwise, everything
+/// following a `;` is a comment.
 pub fn stmts<'i>(
-    mut ctx: impl Context,
+    ctx: impl Context,
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+) -> IResult<'i, Vec<super::Statement>> {
+    stmt_seq(ctx, input, indentation, None)
+}
+
+
+/// Parser for sequences of statements, recovering from malformed statements
+///
+/// Contrary to [stmts], this parser does not fail if a statement is malformed.
+/// Instead, the error is recorded in `diagnostics` and the statement is
+/// skipped, including any lines indented further. Parsing continues with the
+/// next line at the same indentation. Malformed statements within conditional,
+/// match and layerblock statements are skipped in the same way, retaining the
+/// enclosing statement.
+pub fn stmts_recovering<'i>(
+    ctx: impl Context,
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    diagnostics: &'_ mut Vec<crate::parsers::Error<'i>>,
+) -> IResult<'i, Vec<super::Statement>> {
+    stmt_seq(ctx, input, indentation, Some(diagnostics))
+}
+
+
+/// Parser for sequences of statements, optionally recovering from errors
+fn stmt_seq<'i>(
+    ctx: impl Context,
     mut input: &'i str,
     indentation: &'_ mut Indentation,
+    mut diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
 ) -> IResult<'i, Vec<super::Statement>> {
-    let mut res: Vec<super::Statement> = Default::default();
+    use nom::Parser;
 
-    while let Ok((i, stmt)) = stmt(&mut ctx, input, indentation) {
+    fn add<'i>(
+        ctx: &mut impl Context,
+        input: &'i str,
+        stmt: super::Statement,
+        res: &mut Vec<super::Statement>,
+    ) -> Result<(), nom::Err<crate::parsers::Error<'i>>> {
+        if !ctx.count_statement() {
+            return Err(nom::Err::Failure(statement_limit(input)))
+        }
         match stmt.as_ref() {
             super::Kind::Declaration(e)     => ctx.add_entity(e.clone()),
             super::Kind::SimpleMemDecl(m)   => ctx.add_memory(m.clone()),
             _ => (),
         }
         res.push(stmt);
-        input = i;
+        Ok(())
+    }
+
+    // A line which is indented like the statements but fails to parse is
+    // reported as such, rather than ending the sequence silently.
+    fn misindented<'i>(input: &'i str, err: &crate::parsers::Error<'i>) -> bool {
+        input.trim().is_empty() || has_context(err, input, crate::error::INDENTATION_CONTEXT)
+    }
+
+    let separated = ctx.separated_statements();
+    let mut ctx = Tracking::from(ctx);
+    let mut res: Vec<super::Statement> = Default::default();
+
+    loop {
+        ctx.unresolved.get_mut().clear();
+        let parsed = if separated {
+            stmt_with_end(&mut ctx, input, indentation, separated_end, diagnostics.as_deref_mut())
+        } else {
+            stmt_with_end(&mut ctx, input, indentation, le, diagnostics.as_deref_mut())
+        };
+        let err = match parsed {
+            Ok((i, stmt))                                       => {
+                add(&mut ctx, input, stmt, &mut res)?;
+                input = i;
+                None
+            },
+            Err(nom::Err::Error(e)) if misindented(input, &e)   => break,
+            Err(nom::Err::Failure(e)) if exceeds_statement_limit(&e) => return Err(nom::Err::Failure(e)),
+            Err(nom::Err::Error(e))                             => {
+                let e = misplaced_port(input, ctx.type_aliases()).or_else(|| ctx.unresolved(input)).unwrap_or(e);
+                Some(nom::error::ContextError::add_context(input, "invalid statement", e))
+            },
+            Err(nom::Err::Failure(e))                           => Some(e),
+            Err(e)                                              => return Err(e),
+        };
+
+        if let Some(err) = err {
+            let diagnostics = if let Some(d) = diagnostics.as_mut() {
+                d
+            } else {
+                return Err(nom::Err::Failure(err))
+            };
+            diagnostics.push(err);
+
+            // Skip the statement, including any `else` branches
+            input = skip_block(input);
+            while tuple((indentation.clone().parser(), kw("else")))(input).is_ok() {
+                input = skip_block(input);
+            }
+            continue
+        }
+
+        // Further statements on the same line
+        while let (true, Ok((i, _))) = (separated, spaced(chr(';')).parse(input)) {
+            let separated = verify(
+                spaced(|i| stmt_with_end(&mut ctx, i, &mut Indentation::root(), separated_end, None)),
+                |s: &super::Statement| !matches!(
+                    s.kind(),
+                    super::Kind::Conditional{..} | super::Kind::Match{..} | super::Kind::LayerBlock{..},
+                ),
+            )(i);
+            match separated {
+                Ok((i, stmt))   => {
+                    add(&mut ctx, input, stmt, &mut res)?;
+                    input = i;
+                },
+                Err(_)          => match le(input) {
+                    Ok((i, _))  => input = i,
+                    Err(_)      => return Ok((input, res)),
+                },
+            }
+        }
     }
 
     Ok((input, res))
 }
 
 
+/// [Context] recording names which could not be resolved
+///
+/// Statements are parsed speculatively. Hence, failed lookups are not errors
+/// per se. However, if a statement fails to parse, an unresolved name usually
+/// is the culprit.
+struct Tracking<C: Context> {
+    inner: C,
+    unresolved: std::cell::RefCell<Vec<usize>>,
+}
+
+impl<C: Context> Tracking<C> {
+    /// Record the given name if the lookup `res` failed
+    fn track<T>(&self, name: &str, res: Option<T>) -> Option<T> {
+        if res.is_none() {
+            self.unresolved.borrow_mut().push(name.as_ptr() as usize)
+        }
+        res
+    }
+
+    /// Create an error for the last name in `input` which could not be resolved
+    ///
+    /// Only names in positions where a reference is expected are considered.
+    /// Names preceding another identifier are usually keywords, e.g. `wire` in
+    /// `wire w: UInt`, with the exception of the `is` in `x is invalid`. Names
+    /// preceding a `(` are reported if they are not primitive operations.
+    fn unresolved<'i>(&self, input: &'i str) -> Option<crate::parsers::Error<'i>> {
+        use nom::Parser;
+
+        let start = input.as_ptr() as usize;
+        let mut offsets: Vec<_> = self.unresolved
+            .borrow()
+            .iter()
+            .filter_map(|p| p.checked_sub(start).filter(|o| *o < input.len() && input.is_char_boundary(*o)))
+            .collect();
+        offsets.sort_unstable();
+
+        offsets.into_iter().rev().find_map(|o| {
+            let name = &input[o..];
+            let rest = identifier(name).ok()?.0.trim_start_matches(' ');
+            if rest.starts_with('(') {
+                match crate::expr::parsers::primitive_op(|_| None::<Arc<super::Entity>>, name) {
+                    Err(nom::Err::Error(e)) if has_context(&e, name, crate::error::UNKNOWN_OPERATION_CONTEXT) =>
+                        Some(e),
+                    _ => None,
+                }
+            } else if !rest.starts_with(crate::parsers::is_identifier_char) || kw("is").parse(rest).is_ok() {
+                Some(unknown_reference(name))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<C: Context> From<C> for Tracking<C> {
+    fn from(inner: C) -> Self {
+        Self {inner, unresolved: Default::default()}
+    }
+}
+
+impl<C: Context> Context for Tracking<C> {
+    fn entity(&self, name: &str) -> Option<Arc<super::Entity>> {
+        self.track(name, self.inner.entity(name))
+    }
+
+    fn add_entity(&mut self, entity: Arc<super::Entity>) {
+        self.inner.add_entity(entity)
+    }
+
+    fn memory(&self, name: &str) -> Option<Arc<crate::memory::simple::Memory>> {
+        self.track(name, self.inner.memory(name))
+    }
+
+    fn add_memory(&mut self, memory: Arc<crate::memory::simple::Memory>) {
+        self.inner.add_memory(memory)
+    }
+
+    fn module(&self, name: &str) -> Option<Arc<crate::module::Module>> {
+        self.track(name, self.inner.module(name))
+    }
+
+    fn version(&self) -> Option<crate::circuit::Version> {
+        self.inner.version()
+    }
+
+    fn dialects(&self) -> &[Arc<dyn super::extension::Dialect>] {
+        self.inner.dialects()
+    }
+
+    fn type_aliases(&self) -> &[crate::types::TypeAlias] {
+        self.inner.type_aliases()
+    }
+
+    fn separated_statements(&self) -> bool {
+        self.inner.separated_statements()
+    }
+
+    fn instance_parameters(&self) -> bool {
+        self.inner.instance_parameters()
+    }
+
+    fn count_statement(&mut self) -> bool {
+        self.inner.count_statement()
+    }
+}
+
+
+/// Create an error if `input` starts with a port declaration
+///
+/// Ports need to be declared before any statements. Such a misplaced port
+/// would otherwise be reported as a malformed statement.
+fn misplaced_port<'i>(input: &'i str, aliases: &[crate::types::TypeAlias]) -> Option<crate::parsers::Error<'i>> {
+    use nom::error::{ContextError, ErrorKind, ParseError};
+
+    let input = input.trim_start_matches([' ', '\t']);
+    tuple((|i| port(i, aliases), le))(input).ok().map(|_| crate::parsers::Error::add_context(
+        input,
+        crate::error::MISPLACED_PORT_CONTEXT,
+        crate::parsers::Error::from_error_kind(input, ErrorKind::Verify),
+    ))
+}
+
+
+/// Check whether the error carries the given context at the start of `input`
+fn has_context(err: &crate::parsers::Error<'_>, input: &str, context: &'static str) -> bool {
+    err.trace().iter().any(|(i, k)| i.as_ptr() == input.as_ptr() && *k == nom::error::VerboseErrorKind::Context(context))
+}
+
+
+/// Create an error for exceeding the limit of statements at `input`
+fn statement_limit(input: &str) -> crate::parsers::Error<'_> {
+    use nom::error::{ContextError, ErrorKind, ParseError};
+
+    crate::parsers::Error::add_context(
+        input,
+        crate::error::STATEMENT_LIMIT_CONTEXT,
+        crate::parsers::Error::from_error_kind(input, ErrorKind::TooLarge),
+    )
+}
+
+
+/// Check whether parsing failed because the limit of statements was exceeded
+pub(crate) fn exceeds_statement_limit(err: &crate::parsers::Error<'_>) -> bool {
+    err.trace().iter().any(|(_, k)| *k == nom::error::VerboseErrorKind::Context(crate::error::STATEMENT_LIMIT_CONTEXT))
+}
+
+
+/// Create an error for an unknown reference at the start of `input`
+fn unknown_reference(input: &str) -> crate::parsers::Error<'_> {
+    use nom::error::{ContextError, ErrorKind, ParseError};
+
+    crate::parsers::Error::add_context(
+        input,
+        crate::error::UNKNOWN_REFERENCE_CONTEXT,
+        crate::parsers::Error::from_error_kind(input, ErrorKind::Verify),
+    )
+}
+
+
+/// Parser for the end of a statement which may be followed by another one
+///
+/// This parser accepts the same input as [le], except for a `;` followed by
+/// further text on the same line. Such a `;` is not consumed, since it may
+/// separate two statements.
+fn separated_end<'i>(input: &'i str) -> IResult<'i, ()> {
+    use nom::character::complete::{satisfy, space0};
+    use nom::combinator::peek;
+
+    alt((
+        value((), peek(tuple((space0, chr(';'), space0, satisfy(|c| c != '\r' && c != '\n'))))),
+        le,
+    ))(input)
+}
+
+
 /// Parser for individual statements
 pub fn stmt<'i>(
     ctx: &'_ mut impl Context,
     input: &'i str,
     indentation: &'_ mut Indentation,
+) -> IResult<'i, super::Statement> {
+    stmt_with_end(ctx, input, indentation, le, None)
+}
+
+
+/// Parser for individual statements, ending in the given parser
+///
+/// The `end` parser is applied in place of [le] at the end of a statement. If
+/// `diagnostics` are given, malformed statements nested in the statement are
+/// skipped and recorded.
+fn stmt_with_end<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &'_ mut Indentation,
+    end: impl Fn(&'i str) -> IResult<'i, ()> + Copy,
+    diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
 ) -> IResult<'i, super::Statement> {
     use super::{Kind, Statement as S};
     use print::PrintElement as P;
 
     let indent = indentation.clone().into_parser();
 
+    let connect_keywords = ctx.version().map(|v| v >= Version::CONNECT_KEYWORDS).unwrap_or(false);
     let expr = |i| expr(|n| ctx.entity(n), i);
 
     let res = alt((
-        map(
-            tuple((indent.clone(), &expr, spaced(op("<=")), spaced(&expr), info, le)),
-            |(i, to, _, from, info, _)| (i, S::from(Kind::Connection{from, to}).with_info(info)),
+        map_opt(
+            cond(!connect_keywords, tuple((indent.clone(), &expr, spaced(op("<=")), spaced(&expr), info, end))),
+            |r| r.map(|(i, to, _, from, info, _)| (i, S::from(Kind::Connection{from, to}).with_info(info))),
         ),
         map(
-            tuple((indent.clone(), &expr, spaced(op("<-")), spaced(&expr), info, le)),
+            tuple((indent.clone(), &expr, spaced(op("<-")), spaced(&expr), info, end)),
             |(i, to, _, from, info, _)| (i, S::from(Kind::PartialConnection{from, to}).with_info(info)),
         ),
+        map_opt(
+            cond(connect_keywords, tuple((indent.clone(), kw("connect"), spaced(&expr), comma, spaced(&expr), info, end))),
+            |r| r.map(|(i, _, to, _, from, info, _)| (i, S::from(Kind::Connection{from, to}).with_info(info))),
+        ),
+        map(
+            tuple((indent.clone(), kw("define"), spaced(&expr), spaced(op("=")), spaced(&expr), info, end)),
+            |(i, _, to, _, from, info, _)| (i, S::from(Kind::Define{from, to}).with_info(info)),
+        ),
         map(
-            tuple((indent.clone(), kw("skip"), info, le)),
+            tuple((indent.clone(), kw("skip"), info, end)),
             |(i, _, info, ..)| (i, S::from(Kind::Empty).with_info(info))),
         |i| {
             let mut indent = indent.clone().into();
-            entity_decl(ctx, i, &mut indent)
+            entity_decl(ctx, i, &mut indent, end)
                 .map(|(i, (e, info))| (i, (indent, S::from(Kind::Declaration(Arc::new(e))).with_info(info))))
         },
         map(
-            tuple((indent.clone(), simple_mem, info, le)),
+            tuple((indent.clone(), |i| simple_mem(i, ctx.type_aliases()), info, end)),
             |(i, mem, info, _)| (i, S::from(Kind::SimpleMemDecl(Arc::new(mem))).with_info(info)),
         ),
-        map(
-            tuple((indent.clone(), &expr, spaced(kw("is")), spaced(kw("invalid")), info, le)),
-            |(i, e, .., info, _)| (i, S::from(Kind::Invalidate(e)).with_info(info)),
+        map_opt(
+            cond(!connect_keywords, tuple((indent.clone(), &expr, spaced(kw("is")), spaced(kw("invalid")), info, end))),
+            |r| r.map(|(i, e, .., info, _)| (i, S::from(Kind::Invalidate(e)).with_info(info))),
+        ),
+        map_opt(
+            cond(connect_keywords, tuple((indent.clone(), kw("invalidate"), spaced(&expr), info, end))),
+            |r| r.map(|(i, _, e, info, _)| (i, S::from(Kind::Invalidate(e)).with_info(info))),
         ),
         map(
-            tuple((indent.clone(), kw("attach"), lp, separated_list1(comma, spaced(&expr)), rp, info, le)),
+            tuple((indent.clone(), kw("attach"), lp, separated_list1(comma, spaced(&expr)), rp, info, end)),
             |(i, _, _, e, _, info, _)| (i, S::from(Kind::Attach(e)).with_info(info)),
         ),
         map(
@@ -100,7 +420,7 @@ pub fn stmt<'i>(
                 rp,
                 optional_name,
                 info,
-                le,
+                end,
             )),
             |(i, _, _, clock, _, cond, _, code, _, name, info, ..)|
                 (i, S::from(Kind::Stop{name, clock, cond, code}).with_info(info)),
@@ -120,27 +440,72 @@ pub fn stmt<'i>(
                         |(_, p, ..)| p
                     )(i)?;
                     let mut exprs = iterator(i, preceded(spaced(comma), spaced(&expr)));
+                    // The iterator must not be advanced after it was exhausted
+                    let mut values = (&mut exprs).fuse();
                     let ps: Vec<_> = fmt_str.into_iter().filter_map(|e| match e {
                         FmtStrPart::Literal(s) => Some(P::Literal(s)),
-                        FmtStrPart::FormatSpec(f) => (&mut exprs).next().map(|e| P::Value(e, f)),
+                        FmtStrPart::FormatSpec(f) => values.next().map(|e| P::Value(e, f)),
                     }).collect();
                     exprs.finish().map(|(i, _)| (i, ps))
                 }),
                 rp,
                 optional_name,
                 info,
-                le,
+                end,
             )),
             |(i, _, _, clock, _, cond, _, msg, _, name, info, ..)|
                 (i, S::from(Kind::Print{name, clock, cond, msg}).with_info(info)),
         ),
+        map(
+            tuple((
+                indent.clone(),
+                alt((
+                    value(verification::Op::Assert, kw("assert")),
+                    value(verification::Op::Assume, kw("assume")),
+                    value(verification::Op::Cover, kw("cover")),
+                )),
+                lp,
+                spaced(&expr),
+                comma,
+                spaced(&expr),
+                comma,
+                spaced(&expr),
+                comma,
+                spaced(string_literal),
+                rp,
+                optional_name,
+                info,
+                end,
+            )),
+            |(i, op, _, clock, _, pred, _, en, _, msg, _, name, info, ..)|
+                (i, S::from(Kind::Verification{name, op, clock, pred, en, msg}).with_info(info)),
+        ),
+        map(
+            tuple((indent.clone(), |i| custom_stmt(ctx, i), info, end)),
+            |(i, kind, info, _)| (i, S::from(kind).with_info(info)),
+        ),
     ))(input);
 
     let (input, (indent, stmt)) = res.or_else(|_| {
         use nom::Parser;
 
         let (i, mut indent) = indent.clone().parse(input)?;
-        indented_condition(ctx, i, &mut indent).map(|(i, stmt)| (i, (indent, stmt)))
+
+        // Diagnostics are only retained if the statement is parsed in the end
+        let mut nested = diagnostics.as_ref().map(|_| Vec::new());
+        let res = match condition(ctx, i, &mut indent, nested.as_mut()) {
+            Err(nom::Err::Error(_)) => r#match(ctx, i, &mut indent, nested.as_mut()),
+            res                     => res,
+        };
+        let (i, stmt) = match res {
+            Err(nom::Err::Error(_)) => layerblock(ctx, i, &mut indent, nested.as_mut()),
+            res                     => res,
+        }?;
+
+        if let (Some(diagnostics), Some(nested)) = (diagnostics, nested) {
+            diagnostics.extend(nested)
+        }
+        Ok((i, (indent, stmt)))
     })?;
 
     *indentation = indent;
@@ -149,28 +514,68 @@ pub fn stmt<'i>(
 }
 
 
+/// Parser for custom statements provided by the [Context]'s dialects
+///
+/// The statement is parsed by the first [Dialect](super::extension::Dialect)
+/// providing the keyword found at the beginning of the input.
+fn custom_stmt<'i>(ctx: &'_ impl Context, input: &'i str) -> IResult<'i, super::Kind> {
+    use nom::error::{ErrorKind, ParseError};
+
+    let (rest, keyword) = identifier(input)?;
+    ctx.dialects()
+        .iter()
+        .find(|d| d.keywords().contains(&keyword))
+        .and_then(|d| d.parse(keyword, rest, &|i: &'i str| expr(|n| ctx.entity(n), i).ok()))
+        .map(|(i, s)| (i, super::Kind::Extension(s)))
+        .ok_or_else(|| nom::Err::Error(crate::parsers::Error::from_error_kind(input, ErrorKind::Verify)))
+}
+
+
 /// Parser for conditionals, assuming that the initial indendation was parsed
 ///
 /// This parser will parse a conditional statement. It expects the initial
 /// `when` right at the beginning of the input and aussumes that is matches the
-/// given indentation.
-fn indented_condition<'i>(
+/// given indentation. Branches consisting of a single statement may be given
+/// on the same line as the `when` or `else`, e.g. `when c : x <= y`.
+pub fn indented_condition<'i>(
     ctx: &'_ mut impl Context,
     input: &'i str,
     indentation: &mut Indentation,
 ) -> IResult<'i, super::Statement> {
-    let (input, (cond, when_info)) = map(
-        tuple((kw("when"), spaced(|i| expr(|n| ctx.entity(n), i)), spaced(op(":")), info, le)),
-        |(_, e, _, info, ..)| (e, info),
+    condition(ctx, input, indentation, None)
+}
+
+
+/// Parser for conditionals, optionally recovering from malformed statements
+///
+/// See [indented_condition] and [stmts_recovering] for details.
+fn condition<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &mut Indentation,
+    mut diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
+) -> IResult<'i, super::Statement> {
+    use nom::Parser;
+
+    let (input, cond) = map(
+        tuple((kw("when"), spaced(|i| expr(|n| ctx.entity(n), i)), spaced(op(":")))),
+        |(_, e, _)| e,
     )(input)?;
 
-    let (input, when) = stmts(ctx.sub(), input, &mut indentation.sub())?;
+    let (input, (when, when_info)) = if let Ok((i, (info, _))) = tuple((info, le))(input) {
+        stmt_seq(ctx.sub(), i, &mut indentation.sub(), diagnostics.as_deref_mut()).map(|(i, s)| (i, (s, info)))
+    } else {
+        map(spaced(|i| inline_stmt(ctx, i)), |s| (vec![s], None))(input)
+    }?;
 
     let (input, r#else) = if let Ok((i, _)) = tuple((indentation.clone().parser(), kw("else")))(input) {
         if let Ok((i, _)) = tuple((spaced(op(":")), info, le))(i) {
-            stmts(ctx.sub(), i, &mut indentation.sub())
+            stmt_seq(ctx.sub(), i, &mut indentation.sub(), diagnostics)
+        } else if let Ok((i, _)) = spaced(op(":")).parse(i) {
+            map(spaced(|i| inline_stmt(ctx, i)), |s| vec![s])(i)
         } else {
-            map(spaced(|i| indented_condition(&mut ctx.sub(), i, indentation)), |s| vec![s],)(i)
+            let (i, _) = nom::character::complete::space0::<_, crate::parsers::Error>(i)?;
+            condition(&mut ctx.sub(), i, indentation, diagnostics).map(|(i, s)| (i, vec![s]))
         }?
     } else {
         (input, Default::default())
@@ -181,11 +586,150 @@ fn indented_condition<'i>(
 }
 
 
+/// Parser for match statements, assuming that the initial indendation was parsed
+///
+/// This parser will parse a match statement. It expects the initial `match`
+/// right at the beginning of the input and aussumes that is matches the given
+/// indentation. The matched expression must be of an enumeration type. Each
+/// arm is given on its own, further indented line, naming a variant and
+/// optionally a binder for the variant's data, followed by the arm's
+/// statements.
+pub fn indented_match<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &mut Indentation,
+) -> IResult<'i, super::Statement> {
+    r#match(ctx, input, indentation, None)
+}
+
+
+/// Parser for match statements, optionally recovering from malformed statements
+///
+/// See [indented_match] and [stmts_recovering] for details.
+fn r#match<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &mut Indentation,
+    mut diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
+) -> IResult<'i, super::Statement> {
+    use crate::named::Named;
+    use crate::types::{Type, Typed};
+
+    let (mut input, ((value, variants), info)) = map(
+        tuple((
+            kw("match"),
+            spaced(map_opt(|i| expr(|n| ctx.entity(n), i), |e| match e.r#type().as_ref().map(Type::resolved) {
+                Ok(Type::Enum(v))   => Some((e.clone(), v.clone())),
+                _                   => None,
+            })),
+            spaced(op(":")),
+            info,
+            le,
+        )),
+        |(_, v, _, info, _)| (v, info),
+    )(input)?;
+
+    let mut arm_indent = indentation.sub();
+    let mut arms = Vec::new();
+    loop {
+        let arm = map_opt(
+            tuple((
+                arm_indent.parser(),
+                field_name,
+                opt(tuple((lp, spaced(identifier), rp))),
+                spaced(op(":")),
+                le,
+            )),
+            |(_, variant, binder, ..)| {
+                let variant = variants.iter().find(|v| v.name().as_ref() == variant)?;
+                let binder = match binder {
+                    Some((_, n, _)) => Some(Arc::new(super::Entity::Binder{
+                        name: n.into(),
+                        r#type: variant.data()?.clone(),
+                    })),
+                    None            => None,
+                };
+                Some((variant.name().clone(), binder))
+            },
+        )(input);
+        let (i, (variant, binder)) = match arm {
+            Ok(res)                                     => res,
+            Err(nom::Err::Error(_)) if !arms.is_empty() => break,
+            Err(e)                                      => return Err(e),
+        };
+
+        let mut sub = ctx.sub();
+        if let Some(binder) = binder.as_ref() {
+            sub.add_entity(binder.clone())
+        }
+        let (i, stmts) = stmt_seq(sub, i, &mut arm_indent.sub(), diagnostics.as_deref_mut())?;
+        arms.push(super::r#match::Arm::new(variant, binder, stmts));
+        input = i;
+    }
+
+    Ok((input, super::Statement::from(super::Kind::Match{value, arms}).with_info(info)))
+}
+
+
+/// Parser for layer blocks, assuming that the initial indendation was parsed
+///
+/// This parser will parse a `layerblock` statement. It expects the initial
+/// `layerblock` right at the beginning of the input and aussumes that is
+/// matches the given indentation. The statements of the block are given on
+/// the following, further indented lines.
+pub fn indented_layerblock<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &mut Indentation,
+) -> IResult<'i, super::Statement> {
+    layerblock(ctx, input, indentation, None)
+}
+
+
+/// Parser for layer blocks, optionally recovering from malformed statements
+///
+/// See [indented_layerblock] and [stmts_recovering] for details.
+fn layerblock<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+    indentation: &mut Indentation,
+    diagnostics: Option<&'_ mut Vec<crate::parsers::Error<'i>>>,
+) -> IResult<'i, super::Statement> {
+    let (input, (_, layer, _, info, _)) =
+        tuple((kw("layerblock"), spaced(layer_path), spaced(op(":")), info, le))(input)?;
+    let (input, stmts) = stmt_seq(ctx.sub(), input, &mut indentation.sub(), diagnostics)?;
+
+    let block = super::Kind::LayerBlock{layer, stmts: stmts.into()};
+    Ok((input, super::Statement::from(block).with_info(info)))
+}
+
+
+/// Parser for a statement following a `when` or `else` on the same line
+///
+/// Conditional, match and layerblock statements are not accepted, since their
+/// contents would lack a reference for their indentation.
+fn inline_stmt<'i>(
+    ctx: &'_ mut impl Context,
+    input: &'i str,
+) -> IResult<'i, super::Statement> {
+    verify(
+        |i| stmt(&mut ctx.sub(), i, &mut Indentation::root()),
+        |s: &super::Statement| !matches!(
+            s.kind(),
+            super::Kind::Conditional{..} | super::Kind::Match{..} | super::Kind::LayerBlock{..},
+        ),
+    )(input)
+}
+
+
 /// Parser for entity declarations
+///
+/// The `end` parser is applied at the end of a declaration, usually [le].
 pub fn entity_decl<'i>(
     ctx: &'_ impl Context,
     input: &'i str,
     indentation: &'_ mut Indentation,
+    end: impl Fn(&'i str) -> IResult<'i, ()> + Copy,
 ) -> IResult<'i, (super::Entity, Option<String>)> {
     use nom::Parser;
 
@@ -194,11 +738,19 @@ pub fn entity_decl<'i>(
 
     let (input, (indent, entity, info)) = alt((
         map(
-            tuple((indent.clone(), kw("wire"), &ident, spaced(op(":")), spaced(r#type), info, le)),
+            tuple((
+                indent.clone(),
+                kw("wire"),
+                &ident,
+                spaced(op(":")),
+                spaced(|i| r#type(i, ctx.type_aliases())),
+                info,
+                end,
+            )),
             |(i, _, n, _, r#type, info, _)| (i, super::Entity::Wire{name: n.into(), r#type}, info)
         ),
         map(
-            tuple((indent.clone(), |i| register(|n| ctx.entity(n), i), info, le)),
+            tuple((indent.clone(), |i| register(|n| ctx.entity(n), i, ctx.type_aliases()), info, end)),
             |(i, r, info, _)| (i, r.into(), info)
         ),
         map(
@@ -209,22 +761,26 @@ pub fn entity_decl<'i>(
                 spaced(op("=")),
                 spaced(|i| expr(|n| ctx.entity(n), i)),
                 info,
-                le
+                end
             )),
             |(i, _, n, _, value, info, _)| (i, super::Entity::Node{name: n.into(), value}, info)
         ),
         |i| {
             let mut indent = Into::into(indent.clone());
-            memory(i, &mut indent).map(|(i, (m, info))| (i, (indent, m.into(), info)))
+            memory(i, &mut indent, ctx.type_aliases()).map(|(i, (m, info))| (i, (indent, m.into(), info)))
         },
         map(
-            tuple((indent.clone(), |i| simple_mem_port(|n| ctx.memory(n), |n| ctx.entity(n), i), info, le)),
+            tuple((indent.clone(), |i| simple_mem_port(|n| ctx.memory(n), |n| ctx.entity(n), i), info, end)),
             |(i, r, info, _)| (i, r.into(), info)
         ),
         map(
-            tuple((indent.clone(), |i| instance(|n| ctx.module(n), i), info, le)),
+            tuple((indent.clone(), |i| instance(|n| ctx.module(n), i, ctx.instance_parameters()), info, end)),
             |(i, inst, info, _)| (i, inst.into(), info)
         ),
+        |i| {
+            let mut indent = Into::into(indent.clone());
+            instance_choice(|n| ctx.module(n), i, &mut indent).map(|(i, (c, info))| (i, (indent, c.into(), info)))
+        },
     ))(input)?;
 
     *indentation = indent;
@@ -247,10 +803,8 @@ pub fn fmt_string_part<'i>(
         map(
             many1(alt((
                 value('%', tag("%%")),
-                value('\n', tag("\\n")),
-                value('\t', tag("\\t")),
-                preceded(chr('\\'), anychar),
-                verify(anychar, |c| !"%\n\t\"".contains(*c)),
+                escape_sequence,
+                verify(anychar, |c| !"%\\\n\t\"".contains(*c)),
             ))),
             |v| FmtStrPart::Literal(v.into_iter().collect()),
         )
@@ -258,6 +812,23 @@ pub fn fmt_string_part<'i>(
 }
 
 
+/// Parser for a string literal
+///
+/// String literals are escaped in the same way as format strings but must not
+/// contain any format specifiers.
+pub fn string_literal<'i>(
+    input: &'i str,
+) -> IResult<'i, String> {
+    map(
+        verify(
+            tuple((chr('"'), many0(fmt_string_part), chr('"'))),
+            |(_, p, _): &(_, Vec<_>, _)| p.iter().all(|p| matches!(p, FmtStrPart::Literal(_))),
+        ),
+        |(_, p, _)| p.into_iter().filter_map(|p| if let FmtStrPart::Literal(s) = p { Some(s) } else { None }).collect(),
+    )(input)
+}
+
+
 /// Format string part
 ///
 /// Instances of this type serves as prototypes for `PrintElement`s.
@@ -4,7 +4,7 @@
 
 use super::Expression;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 
@@ -15,7 +15,7 @@ pub enum PrintElement {
     Value(Expression, Format),
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for PrintElement {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::expr::tests::{expr_with_type, source_flow};
@@ -50,7 +50,7 @@ impl Arbitrary for PrintElement {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Format {Binary, Decimal, Hexadecimal, Character}
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Format {
     fn arbitrary(g: &mut Gen) -> Self {
         g.choose(&[Self::Binary, Self::Decimal, Self::Hexadecimal, Self::Character]).unwrap().clone()
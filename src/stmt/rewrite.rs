@@ -0,0 +1,130 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Rewriting of statements
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{Entity, Expression, Kind, Statement};
+use super::print::PrintElement;
+
+
+/// Rewriter for entities in statements
+///
+/// Entities are referenced by [Statement]s and [Expression](crate::expr::Expression)s
+/// via `Arc`s. Replacing an entity thus requires rebuilding every item which
+/// (transitively) refers to it. A rewriter performs that task: it applies a
+/// user supplied function to every entity it encounters and re-points all
+/// references to replaced entities. Entities depending on replaced entities,
+/// e.g. nodes or registers, are rebuilt automatically.
+///
+/// The rewriter remembers all replacements. Thus, the same instance should be
+/// used for rewriting all statements of a module.
+pub struct EntityRewriter<F: FnMut(&Arc<Entity>) -> Option<Arc<Entity>>> {
+    f: F,
+    done: HashMap<*const Entity, Arc<Entity>>,
+}
+
+impl<F: FnMut(&Arc<Entity>) -> Option<Arc<Entity>>> EntityRewriter<F> {
+    /// Create a new rewriter
+    ///
+    /// The function `f` will be applied to every entity encountered. If it
+    /// returns a new entity, that entity will replace the original one.
+    pub fn new(f: F) -> Self {
+        Self {f, done: Default::default()}
+    }
+
+    /// Register a replacement for the given entity
+    ///
+    /// Future references to `original` will be re-pointed to `replacement`
+    /// without consulting the rewriter's function.
+    pub fn with_replacement(mut self, original: &Arc<Entity>, replacement: Arc<Entity>) -> Self {
+        self.done.insert(Arc::as_ptr(original), replacement);
+        self
+    }
+
+    /// Rewrite the given entity
+    pub fn entity(&mut self, entity: &Arc<Entity>) -> Arc<Entity> {
+        if let Some(res) = self.done.get(&Arc::as_ptr(entity)) {
+            return res.clone()
+        }
+
+        let rebuilt = match entity.as_ref() {
+            Entity::Register(reg)       => Entity::Register(reg.map_references(&mut |r| self.entity(r))),
+            Entity::Node{name, value}   => Entity::Node{name: name.clone(), value: self.expr(value)},
+            Entity::SimpleMemPort(port) => Entity::SimpleMemPort(port.map_references(&mut |r| self.entity(r))),
+            _                           => entity.as_ref().clone(),
+        };
+        let rebuilt = if &rebuilt == entity.as_ref() {
+            entity.clone()
+        } else {
+            Arc::new(rebuilt)
+        };
+
+        let res = (self.f)(&rebuilt).unwrap_or(rebuilt);
+        self.done.insert(Arc::as_ptr(entity), res.clone());
+        res
+    }
+
+    /// Rewrite the given expression
+    pub fn expr(&mut self, expr: &Expression) -> Expression {
+        expr.map_references(&mut |r| self.entity(r))
+    }
+
+    /// Rewrite the given statement
+    pub fn stmt(&mut self, stmt: &Statement) -> Statement {
+        let kind = match stmt.kind() {
+            Kind::Connection{from, to}          => Kind::Connection{from: self.expr(from), to: self.expr(to)},
+            Kind::PartialConnection{from, to}   => Kind::PartialConnection{from: self.expr(from), to: self.expr(to)},
+            Kind::Empty                         => Kind::Empty,
+            Kind::Declaration(entity)           => Kind::Declaration(self.entity(entity)),
+            Kind::SimpleMemDecl(mem)            => Kind::SimpleMemDecl(mem.clone()),
+            Kind::Invalidate(expr)              => Kind::Invalidate(self.expr(expr)),
+            Kind::Attach(exprs)                 => Kind::Attach(exprs.iter().map(|e| self.expr(e)).collect()),
+            Kind::Conditional{cond, when, r#else} => Kind::Conditional{
+                cond: self.expr(cond),
+                when: self.stmts(when).into(),
+                r#else: self.stmts(r#else).into(),
+            },
+            Kind::Stop{name, clock, cond, code} => Kind::Stop{
+                name: name.clone(),
+                clock: self.expr(clock),
+                cond: self.expr(cond),
+                code: *code,
+            },
+            Kind::Print{name, clock, cond, msg} => Kind::Print{
+                name: name.clone(),
+                clock: self.expr(clock),
+                cond: self.expr(cond),
+                msg: msg.iter().map(|p| match p {
+                    PrintElement::Literal(s)    => PrintElement::Literal(s.clone()),
+                    PrintElement::Value(e, f)   => PrintElement::Value(self.expr(e), *f),
+                }).collect(),
+            },
+            Kind::Verification{name, op, clock, pred, en, msg} => Kind::Verification{
+                name: name.clone(),
+                op: *op,
+                clock: self.expr(clock),
+                pred: self.expr(pred),
+                en: self.expr(en),
+                msg: msg.clone(),
+            },
+            Kind::Define{from, to}              => Kind::Define{from: self.expr(from), to: self.expr(to)},
+            Kind::Match{value, arms}            => Kind::Match{
+                value: self.expr(value),
+                arms: arms.iter().map(|a| {
+                    let binder = a.binder().map(|b| self.entity(b));
+                    a.with_contents(binder, self.stmts(a.stmts()))
+                }).collect(),
+            },
+            Kind::LayerBlock{layer, stmts}      => Kind::LayerBlock{layer: layer.clone(), stmts: self.stmts(stmts).into()},
+            Kind::Extension(custom)             => Kind::Extension(custom.map_expressions(&mut |e| self.expr(e))),
+        };
+        Statement {kind, info: stmt.info.clone(), id: stmt.id}
+    }
+
+    /// Rewrite the given statements
+    pub fn stmts(&mut self, stmts: &[Statement]) -> Vec<Statement> {
+        stmts.iter().map(|s| self.stmt(s)).collect()
+    }
+}
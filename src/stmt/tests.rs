@@ -59,7 +59,7 @@ fn parse_stmt(mut base: Indentation, original: Statement) -> Result<TestResult,
     let mut s: String = Default::default();
     original.fmt(&mut base, &mut s).map_err(|e| e.to_string())?;
 
-    let mut ctx = BinSearchCtx {refs, mems, mods};
+    let mut ctx = BinSearchCtx {refs, mems, mods, separated: false};
     let parser = move |i| super::parsers::stmt(&mut ctx, i, &mut base);
 
     let res = all_consuming(parser)(&s)
@@ -71,7 +71,7 @@ fn parse_stmt(mut base: Indentation, original: Statement) -> Result<TestResult,
 
 
 #[quickcheck]
-fn parse_stmts(mut base: Indentation, original: Statement) -> Result<TestResult, String> {
+fn parse_stmts(mut base: Indentation, original: Statement, separated: bool) -> Result<TestResult, String> {
     let original = if let Some(stmts) = stmt_with_decls(
         original,
         &mut Default::default(),
@@ -108,12 +108,26 @@ fn parse_stmts(mut base: Indentation, original: Statement) -> Result<TestResult,
     }
 
     let mut buf: String = Default::default();
-    original.iter().try_for_each(|s| s.fmt(&mut base, &mut buf)).map_err(|e| e.to_string())?;
+    let mut joinable = false;
+    for stmt in &original {
+        let mut line: String = Default::default();
+        stmt.fmt(&mut base, &mut line).map_err(|e| e.to_string())?;
+
+        // Put single line statements on the same line if requested
+        let single = line.trim_end_matches('\n').lines().count() == 1 && !matches!(stmt.kind(), Kind::Conditional{..});
+        if separated && joinable && single {
+            buf = format!("{} ; {}", buf.trim_end_matches('\n'), line.trim_start());
+        } else {
+            buf.push_str(&line);
+        }
+        joinable = single;
+    }
 
     let mut ctx = BinSearchCtx {
         refs: ports.into_iter().map(Into::into).map(Arc::new).collect(),
         mems: Default::default(),
-        mods
+        mods,
+        separated,
     };
     let parser = move |i| super::parsers::stmts(ctx.sub(), i, &mut base);
 
@@ -159,8 +173,8 @@ fn parse_entity(mut base: Indentation, original: Entity) -> Result<TestResult, S
         .fmt(&mut base, &mut s)
         .map_err(|e| e.to_string())?;
 
-    let ctx = BinSearchCtx {refs, mems, mods};
-    let parser = move |i| super::parsers::entity_decl(&ctx, i, &mut base);
+    let ctx = BinSearchCtx {refs, mems, mods, separated: false};
+    let parser = move |i| super::parsers::entity_decl(&ctx, i, &mut base, crate::parsers::le);
 
     let res = all_consuming(parser)(&s)
         .finish()
@@ -199,6 +213,17 @@ fn parse_fmt_string(original: FormatString) -> Result<TestResult, String> {
 }
 
 
+#[quickcheck]
+fn parse_string_literal(original: String) -> Result<Equivalence<String>, String> {
+    let s = super::display::StringLiteral(original.as_ref()).to_string();
+    let res = all_consuming(super::parsers::string_literal)(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
 #[quickcheck]
 fn parse_optional_name(original: Option<Identifier>) -> Result<Equivalence<Option<Arc<str>>>, String> {
     let s = super::display::OptionalName(original.as_ref().map(AsRef::as_ref)).to_string();
@@ -337,6 +362,11 @@ pub fn stmt_exprs(stmt: &Statement) -> Vec<&Expression<Arc<Entity>>> {
                 None
             }))
             .collect(),
+        Kind::Verification{clock, pred, en, ..} => vec![clock, pred, en],
+        Kind::Define{from, to}              => vec![from, to],
+        Kind::Match{value, ..}              => vec![value],
+        Kind::LayerBlock{..}                => Default::default(),
+        Kind::Extension(custom)             => custom.expressions(),
     }
 }
 
@@ -427,6 +457,7 @@ struct BinSearchCtx {
     pub refs: Vec<Arc<Entity>>,
     pub mems: Vec<Arc<SimpleMem>>,
     pub mods: Vec<Arc<Module>>,
+    pub separated: bool,
 }
 
 impl Context for BinSearchCtx {
@@ -445,5 +476,222 @@ impl Context for BinSearchCtx {
     fn module(&self, name: &str) -> Option<Arc<Module>> {
         self.mods.binary_search_by_key(&name, |r| r.name()).ok().map(|i| self.mods[i].clone())
     }
+
+    fn separated_statements(&self) -> bool {
+        self.separated
+    }
+}
+
+
+
+#[quickcheck]
+fn decl_info_restore(original: Statement) -> bool {
+    use crate::info::WithInfo;
+    use super::DeclInfo;
+
+    fn annotate(stmt: &Statement) -> Statement {
+        match stmt.kind() {
+            Kind::Declaration(e) => stmt.clone().with_info(Some(format!("decl.fir {}", e.name()))),
+            _ => stmt.map_children(annotate),
+        }
+    }
+
+    fn strip(stmt: &Statement) -> Statement {
+        match stmt.kind() {
+            Kind::Declaration(_) => stmt.clone().with_info(None),
+            _ => stmt.map_children(strip),
+        }
+    }
+
+    let annotated = annotate(&original);
+    let infos = DeclInfo::collect(std::iter::once(&annotated));
+    infos.restore(&strip(&annotated)) == annotated &&
+        annotated.declarations().all(|e| infos.declare(e.clone()).info() == infos.get(e.name()))
+}
+
+
+#[test]
+fn grammar_fragments() {
+    use nom::bytes::complete::tag;
+    use nom::sequence::preceded;
+
+    use super::context::TopContext;
+    use super::grammar;
+
+    let mut ctx = TopContext::new(|_: &str| None);
+    let (rest, (wire, _)) = grammar::entity_decl(&ctx, 2, "  wire w: UInt<1>\n  w is invalid\n")
+        .expect("Could not parse declaration");
+    assert_eq!(wire.name_ref(), "w");
+    assert_eq!(rest, "  w is invalid\n");
+
+    ctx.add_entity(Arc::new(wire));
+    let (rest, cond) = grammar::indented_condition(&mut ctx, 2, "when w:\n    w is invalid\n  else:\n    skip\nend\n")
+        .expect("Could not parse conditional");
+    assert!(matches!(cond.kind(), Kind::Conditional{when, r#else, ..} if when.len() == 1 && r#else.len() == 1));
+    assert_eq!(rest, "end\n");
+
+    let (rest, stmts) = preceded(tag("begin\n"), |i| grammar::statements(TopContext::new(|_: &str| None), 1, i))(
+        "begin\n wire a: UInt<1>\n a is invalid\nend\n"
+    ).expect("Could not parse embedded statements");
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(rest, "end\n");
+}
+
+
+#[test]
+fn parse_define() {
+    use super::context::TopContext;
+    use super::grammar;
+    use crate::module::{Direction, Port};
+    use crate::types::{GroundType as GT, ProbeKind, Type};
+
+    let uint: Type = GT::UInt(Some(8)).into();
+    let out = Arc::new(Entity::Port(Arc::new(
+        Port::new("out", Type::Probe(Arc::new(uint.clone()), ProbeKind::Read), Direction::Output)
+    )));
+    let x = Arc::new(Entity::Wire{name: "x".into(), r#type: uint});
+
+    let mut ctx = TopContext::new(|_: &str| None);
+    ctx.add_entity(out.clone());
+    ctx.add_entity(x.clone());
+
+    let text = "  define out = probe(x) @[Foo.scala 1:2]\n";
+    let (rest, stmt) = grammar::statement(&mut ctx, 2, text).expect("Could not parse define");
+    assert_eq!(rest, "");
+    assert_eq!(stmt.kind(), &Kind::Define{
+        from: Expression::Probe{target: Arc::new(x.into()), kind: ProbeKind::Read},
+        to: out.into(),
+    });
+    assert_eq!(stmt.expressions().len(), 2);
+
+    let mut printed = String::new();
+    stmt.fmt(&mut Indentation::exact(2), &mut printed).expect("Could not print define");
+    assert_eq!(printed, text);
 }
 
+
+#[test]
+fn parse_match() {
+    use super::context::TopContext;
+    use super::grammar;
+    use crate::types::{EnumVariant, GroundType as GT, Type};
+
+    let uint: Type = GT::UInt(Some(2)).into();
+    let r#enum = Type::Enum(vec![EnumVariant::new("A"), EnumVariant::new("B").with_data(uint.clone())].into());
+    let e = Arc::new(Entity::Wire{name: "e".into(), r#type: r#enum});
+    let y = Arc::new(Entity::Wire{name: "y".into(), r#type: uint.clone()});
+
+    let mut ctx = TopContext::new(|_: &str| None);
+    ctx.add_entity(e.clone());
+    ctx.add_entity(y.clone());
+
+    let text = "  match e: @[Foo.scala 1:2]\n    A:\n      skip\n    B(b):\n      y <= b\n";
+    let (rest, stmt) = grammar::statement(&mut ctx, 2, text).expect("Could not parse match");
+    assert_eq!(rest, "");
+
+    let arms = if let Kind::Match{value, arms} = stmt.kind() {
+        assert_eq!(value, &Expression::from(e));
+        arms
+    } else {
+        panic!("Expected match statement, got {:?}", stmt)
+    };
+    assert_eq!(arms.iter().map(|a| a.variant().as_ref()).collect::<Vec<_>>(), vec!["A", "B"]);
+    assert_eq!(arms[0].binder(), None);
+    let binder = arms[1].binder().expect("Missing binder");
+    assert_eq!(binder.as_ref(), &Entity::Binder{name: "b".into(), r#type: uint});
+    assert_eq!(arms[1].stmts().as_ref(), &[
+        Statement::from(Kind::Connection{from: binder.clone().into(), to: y.into()})
+    ]);
+
+    let mut printed = String::new();
+    stmt.fmt(&mut Indentation::exact(2), &mut printed).expect("Could not print match");
+    assert_eq!(printed, text);
+
+    assert!(grammar::statement(&mut ctx, 2, "  match e:\n    C:\n      skip\n").is_err());
+    assert!(grammar::statement(&mut ctx, 2, "  match e:\n    A(a):\n      skip\n").is_err());
+    assert!(grammar::statement(&mut ctx, 2, "  match y:\n    A:\n      skip\n").is_err());
+}
+
+
+#[test]
+fn custom_stmt() {
+    use super::context::TopContext;
+    use super::extension::{CustomStatement, Dialect};
+    use super::grammar;
+    use super::rewrite::EntityRewriter;
+    use crate::types::{GroundType as GT, Type};
+
+    #[derive(Debug, PartialEq)]
+    struct Trace(Expression<Arc<Entity>>);
+
+    impl CustomStatement for Trace {
+        fn keyword(&self) -> &str {
+            "trace"
+        }
+
+        fn fmt_args(&self, f: &mut dyn std::fmt::Write) -> std::fmt::Result {
+            write!(f, " {}", self.0)
+        }
+
+        fn expressions(&self) -> Vec<&Expression<Arc<Entity>>> {
+            vec![&self.0]
+        }
+
+        fn map_expressions(
+            &self,
+            f: &mut dyn FnMut(&Expression<Arc<Entity>>) -> Expression<Arc<Entity>>,
+        ) -> Arc<dyn CustomStatement> {
+            Arc::new(Self(f(&self.0)))
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn eq_dyn(&self, other: &dyn CustomStatement) -> bool {
+            other.as_any().downcast_ref::<Self>() == Some(self)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Tracing;
+
+    impl Dialect for Tracing {
+        fn keywords(&self) -> &[&str] {
+            &["trace"]
+        }
+
+        fn parse<'i>(
+            &self,
+            _keyword: &str,
+            input: &'i str,
+            expr: &dyn Fn(&'i str) -> Option<(&'i str, Expression<Arc<Entity>>)>,
+        ) -> Option<(&'i str, Arc<dyn CustomStatement>)> {
+            expr(input.strip_prefix(' ')?).map(|(i, e)| (i, Arc::new(Trace(e)) as Arc<dyn CustomStatement>))
+        }
+    }
+
+    let uint: Type = GT::UInt(Some(8)).into();
+    let x = Arc::new(Entity::Wire{name: "x".into(), r#type: uint.clone()});
+    let y = Arc::new(Entity::Wire{name: "y".into(), r#type: uint});
+
+    let text = "  trace x @[Foo.scala 1:2]\n";
+
+    let mut ctx = TopContext::new(|_: &str| None);
+    ctx.add_entity(x.clone());
+    assert!(grammar::statement(&mut ctx, 2, text).is_err());
+
+    let mut ctx = TopContext::new(|_: &str| None).with_dialects(vec![Arc::new(Tracing) as Arc<dyn Dialect>]);
+    ctx.add_entity(x.clone());
+    let (rest, stmt) = grammar::statement(&mut ctx, 2, text).expect("Could not parse custom statement");
+    assert_eq!(rest, "");
+    assert_eq!(stmt.kind(), &Kind::Extension(Arc::new(Trace(x.clone().into()))));
+    assert_eq!(stmt.expressions(), vec![&Expression::from(x.clone())]);
+
+    let mut printed = String::new();
+    stmt.fmt(&mut Indentation::exact(2), &mut printed).expect("Could not print custom statement");
+    assert_eq!(printed, text);
+
+    let rewritten = EntityRewriter::new(|_| None).with_replacement(&x, y.clone()).stmt(&stmt);
+    assert_eq!(rewritten.kind(), &Kind::Extension(Arc::new(Trace(y.into()))));
+}
@@ -0,0 +1,44 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Utilities related to verification statements
+
+use std::fmt;
+
+#[cfg(any(test, feature = "test-util"))]
+use quickcheck::{Arbitrary, Gen};
+
+
+/// Operation of a [super::Kind::Verification] statement
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// The predicate is required to hold whenever the statement is enabled
+    Assert,
+    /// The predicate is assumed to hold whenever the statement is enabled
+    Assume,
+    /// The predicate is expected to hold at least once while enabled
+    Cover,
+}
+
+impl Op {
+    /// Retrieve the keyword associated with the operation
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Assert    => "assert",
+            Self::Assume    => "assume",
+            Self::Cover     => "cover",
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.keyword(), f)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Op {
+    fn arbitrary(g: &mut Gen) -> Self {
+        *g.choose(&[Self::Assert, Self::Assume, Self::Cover]).unwrap()
+    }
+}
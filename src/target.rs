@@ -0,0 +1,412 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Targets addressing elements of a circuit
+//!
+//! FIRRTL tooling, e.g. annotations, refers to modules, instances and entities
+//! via "targets". A [Target] consists of an optional circuit name, an
+//! [InstancePath] and an optional reference to an entity inside the module
+//! the path leads to, e.g. `~Top|Top/a:ModA/b:ModB>wire.field[0]`.
+//!
+//! Targets may be resolved to the item they refer to via
+//! [Circuit::resolve_target](crate::circuit::Circuit::resolve_target).
+
+pub(crate) mod parsers;
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(any(test, feature = "test-util"))]
+use quickcheck::{Arbitrary, Gen};
+
+use crate::error::ParseError;
+use crate::memory::simple::Memory as SimpleMem;
+use crate::module::{Module, Port};
+use crate::named::Named;
+use crate::stmt::{Entity, Kind as StmtKind, Statement};
+use crate::types::{self, Type, VecWidth};
+
+
+/// Path through the instance hierarchy of a circuit
+///
+/// An `InstancePath` starts at a "root" module. Each [PathElement] then
+/// selects an instance within the module reached so far, alongside the name
+/// of the instantiated module. The textual form of an `InstancePath` is the
+/// root module's name followed by `/instance:Module` for each element.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstancePath {
+    root: Arc<str>,
+    elements: Vec<PathElement>,
+}
+
+impl InstancePath {
+    /// Create a new path consisting only of the given root module
+    pub fn new(root: impl Into<Arc<str>>) -> Self {
+        Self {root: root.into(), elements: Default::default()}
+    }
+
+    /// Create a copy of this path extended by the given instance
+    pub fn with_instance(mut self, instance: impl Into<Arc<str>>, module: impl Into<Arc<str>>) -> Self {
+        self.push(instance, module);
+        self
+    }
+
+    /// Extend this path by the given instance
+    pub fn push(&mut self, instance: impl Into<Arc<str>>, module: impl Into<Arc<str>>) {
+        self.elements.push(PathElement {instance: instance.into(), module: module.into()})
+    }
+
+    /// Remove the last element of the path
+    ///
+    /// If the path only consists of the root module, this function returns
+    /// `None`.
+    pub fn pop(&mut self) -> Option<PathElement> {
+        self.elements.pop()
+    }
+
+    /// Retrieve the name of the root module
+    pub fn root(&self) -> &Arc<str> {
+        &self.root
+    }
+
+    /// Retrieve the instances along the path
+    pub fn elements(&self) -> &[PathElement] {
+        self.elements.as_ref()
+    }
+
+    /// Retrieve the name of the module this path leads to
+    ///
+    /// For a path consisting only of the root module, this will be the name of
+    /// the root module.
+    pub fn module(&self) -> &Arc<str> {
+        self.elements.last().map(|e| &e.module).unwrap_or(&self.root)
+    }
+
+    /// Determine whether this path is a prefix of another one
+    pub fn is_prefix_of(&self, other: &Self) -> bool {
+        self.root == other.root && other.elements.starts_with(self.elements.as_ref())
+    }
+
+    /// Resolve this path, starting at the given module
+    ///
+    /// This function returns the module the path leads to. The root module's
+    /// name is not checked.
+    pub fn resolve_from<'a>(&self, root: &'a Arc<Module>) -> Option<&'a Arc<Module>> {
+        self.elements.iter().try_fold(root, |m, e| m
+            .statements()
+            .iter()
+            .flat_map(Statement::instantiations)
+            .find(|i| i.name_ref() == e.instance.as_ref() && i.module().name_ref() == e.module.as_ref())
+            .map(|i| i.module()))
+    }
+}
+
+impl std::str::FromStr for InstancePath {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use nom::Finish;
+
+        nom::combinator::all_consuming(parsers::instance_path)(s)
+            .finish()
+            .map(|(_, p)| p)
+            .map_err(|e| crate::error::convert_error(s, nom::Err::Error(e)))
+    }
+}
+
+impl fmt::Display for InstancePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.root, f)?;
+        self.elements.iter().try_for_each(|e| write!(f, "/{}", e))
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for InstancePath {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        let len = u8::arbitrary(g) % 8;
+        let mut res = Self::new(Identifier::arbitrary(g));
+        (0..len).for_each(|_| res.push(Identifier::arbitrary(g), Identifier::arbitrary(g)));
+        res
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let root = self.root.clone();
+        let res = self.elements.shrink().map(move |elements| Self {root: root.clone(), elements});
+        Box::new(res)
+    }
+}
+
+
+/// Element of an [InstancePath]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathElement {
+    pub instance: Arc<str>,
+    pub module: Arc<str>,
+}
+
+impl fmt::Display for PathElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.instance, self.module)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for PathElement {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        Self {instance: Identifier::arbitrary(g).into(), module: Identifier::arbitrary(g).into()}
+    }
+}
+
+
+/// Target addressing a module or an entity within a circuit
+///
+/// A `Target` either refers to a module, via an [InstancePath], or to a named
+/// item within the module the path leads to, optionally followed by a number
+/// of [Subscript]s selecting only a part of that item.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Target {
+    circuit: Option<Arc<str>>,
+    path: InstancePath,
+    reference: Option<Arc<str>>,
+    subscripts: Vec<Subscript>,
+}
+
+impl Target {
+    /// Create a new target referring to the module the given path leads to
+    pub fn module(path: InstancePath) -> Self {
+        Self {circuit: None, path, reference: None, subscripts: Default::default()}
+    }
+
+    /// Create a new target referring to a named item
+    ///
+    /// The target will refer to the item with the given name within the module
+    /// the given `path` leads to.
+    pub fn reference(path: InstancePath, name: impl Into<Arc<str>>) -> Self {
+        Self {reference: Some(name.into()), ..Self::module(path)}
+    }
+
+    /// Set the name of the circuit this target is associated with
+    pub fn with_circuit(self, circuit: impl Into<Option<Arc<str>>>) -> Self {
+        Self {circuit: circuit.into(), ..self}
+    }
+
+    /// Append a subscript
+    ///
+    /// # Note
+    ///
+    /// Subscripts are only meaningful for targets referring to a named item.
+    /// Subscripts added to a target without a reference will be ignored.
+    pub fn with_subscript(mut self, subscript: Subscript) -> Self {
+        if self.reference.is_some() {
+            self.subscripts.push(subscript);
+        }
+        self
+    }
+
+    /// Retrieve the name of the circuit, if any
+    pub fn circuit(&self) -> Option<&Arc<str>> {
+        self.circuit.as_ref()
+    }
+
+    /// Retrieve the instance path
+    pub fn path(&self) -> &InstancePath {
+        &self.path
+    }
+
+    /// Retrieve the name of the referenced item, if any
+    pub fn reference_name(&self) -> Option<&Arc<str>> {
+        self.reference.as_ref()
+    }
+
+    /// Retrieve the subscripts applied to the referenced item
+    pub fn subscripts(&self) -> &[Subscript] {
+        self.subscripts.as_ref()
+    }
+
+    /// Resolve this target, starting at the given module
+    ///
+    /// The `root` is assumed to be the module the target's [InstancePath]
+    /// starts at. This function returns the item the target refers to or
+    /// `None` if the target does not refer to any item. Subscripts are checked
+    /// against the referenced item's type if it can be determined.
+    pub fn resolve_from<'a>(&self, root: &'a Arc<Module>) -> Option<Referenced<'a>> {
+        let module = self.path.resolve_from(root)?;
+        let name = if let Some(name) = self.reference.as_ref() {
+            name.as_ref()
+        } else {
+            return Some(Referenced::Module(module))
+        };
+
+        let res = module
+            .port_by_name(&name)
+            .map(Referenced::Port)
+            .or_else(|| module
+                .statements()
+                .iter()
+                .flat_map(Statement::declarations)
+                .find(|e| e.name_ref() == name)
+                .map(Referenced::Entity))
+            .or_else(|| module
+                .statements()
+                .iter()
+                .flat_map(transiter::AutoTransIter::trans_iter)
+                .find_map(|s| match s.kind() {
+                    StmtKind::SimpleMemDecl(m) if m.name_ref() == name => Some(Referenced::SimpleMemory(m)),
+                    _ => None,
+                }))?;
+
+        if let Some(r#type) = res.r#type() {
+            self.subscripts
+                .iter()
+                .try_fold(r#type, |t, s| s.apply(&t).cloned())
+                .map(|_| res)
+        } else {
+            Some(res)
+        }
+    }
+}
+
+impl std::str::FromStr for Target {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use nom::Finish;
+
+        nom::combinator::all_consuming(parsers::target)(s)
+            .finish()
+            .map(|(_, t)| t)
+            .map_err(|e| crate::error::convert_error(s, nom::Err::Error(e)))
+    }
+}
+
+impl From<InstancePath> for Target {
+    fn from(path: InstancePath) -> Self {
+        Self::module(path)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(circuit) = self.circuit.as_ref() {
+            write!(f, "~{}|", circuit)?;
+        }
+        fmt::Display::fmt(&self.path, f)?;
+        if let Some(reference) = self.reference.as_ref() {
+            write!(f, ">{}", reference)?;
+            self.subscripts.iter().try_for_each(|s| fmt::Display::fmt(s, f))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Target {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        let res = Self::module(Arbitrary::arbitrary(g))
+            .with_circuit(Option::<Identifier>::arbitrary(g).map(Into::into));
+        if bool::arbitrary(g) {
+            let len = u8::arbitrary(g) % 4;
+            (0..len).fold(
+                Self {reference: Some(Identifier::arbitrary(g).into()), ..res},
+                |t, _| t.with_subscript(Arbitrary::arbitrary(g)),
+            )
+        } else {
+            res
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let res = self.path.shrink().map({
+            let t = self.clone();
+            move |path| Self {path, ..t.clone()}
+        }).chain(self.subscripts.shrink().map({
+            let t = self.clone();
+            move |subscripts| Self {subscripts, ..t.clone()}
+        }));
+        Box::new(res)
+    }
+}
+
+
+/// Subscript selecting a part of a referenced item
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Subscript {
+    /// Field of a bundle
+    Field(Arc<str>),
+    /// Element of a vector
+    Index(VecWidth),
+}
+
+impl Subscript {
+    /// Compute the type of the selected part of an item with the given type
+    ///
+    /// If the subscript is not applicable to the given type, e.g. because the
+    /// field does not exist or the index is out of bounds, this function
+    /// returns `None`.
+    pub fn apply<'a>(&self, r#type: &'a Type) -> Option<&'a Type> {
+        match self {
+            Self::Field(name)   => r#type.field(name.as_ref()).map(types::BundleField::r#type),
+            Self::Index(index)  => r#type
+                .vector()
+                .and_then(|(t, w)| if index < &w { Some(t.as_ref()) } else { None }),
+        }
+    }
+}
+
+impl fmt::Display for Subscript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(name)   => write!(f, ".{}", name),
+            Self::Index(index)  => write!(f, "[{}]", index),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for Subscript {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        if bool::arbitrary(g) {
+            Self::Field(Identifier::arbitrary(g).into())
+        } else {
+            Self::Index(Arbitrary::arbitrary(g))
+        }
+    }
+}
+
+
+/// Item referred to by a [Target]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Referenced<'a> {
+    Module(&'a Arc<Module>),
+    Port(&'a Arc<Port>),
+    Entity(&'a Arc<Entity>),
+    SimpleMemory(&'a Arc<SimpleMem>),
+}
+
+impl Referenced<'_> {
+    /// Retrieve the type of the referenced item, if it can be determined
+    ///
+    /// Modules don't have a type. For all other items, this function returns
+    /// the type if it can be determined.
+    pub fn r#type(&self) -> Option<Type> {
+        use types::Typed;
+
+        match self {
+            Self::Module(_)         => None,
+            Self::Port(p)           => Some(p.r#type().clone()),
+            Self::Entity(e)         => e.r#type().ok(),
+            Self::SimpleMemory(m)   => m.r#type().ok(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Parsers for targets
+
+use nom::branch::alt;
+use nom::combinator::{map, opt};
+use nom::multi::many0;
+use nom::sequence::{delimited, preceded, tuple};
+
+use crate::parsers::{IResult, decimal, identifier, op};
+use crate::types::parsers::field_name;
+
+
+/// Parse a target
+pub fn target(input: &str) -> IResult<'_, super::Target> {
+    let circuit = delimited(op("~"), identifier, op("|"));
+    let reference = tuple((preceded(op(">"), identifier), many0(subscript)));
+
+    map(
+        tuple((opt(circuit), instance_path, opt(reference))),
+        |(c, p, r)| match r {
+            Some((n, s))    => s.into_iter().fold(super::Target::reference(p, n), super::Target::with_subscript),
+            None            => super::Target::module(p),
+        }.with_circuit(c.map(Into::into))
+    )(input)
+}
+
+
+/// Parse an instance path
+pub fn instance_path(input: &str) -> IResult<'_, super::InstancePath> {
+    let element = tuple((preceded(op("/"), identifier), preceded(op(":"), identifier)));
+
+    let (input, root) = identifier(input)?;
+    let (input, elements) = many0(element)(input)?;
+    let res = elements
+        .into_iter()
+        .fold(super::InstancePath::new(root), |p, (i, m)| p.with_instance(i, m));
+    Ok((input, res))
+}
+
+
+/// Parse a subscript
+pub fn subscript(input: &str) -> IResult<'_, super::Subscript> {
+    use super::Subscript as S;
+
+    alt((
+        map(preceded(op("."), field_name), |n| S::Field(n.into())),
+        map(delimited(op("["), decimal, op("]")), S::Index),
+    ))(input)
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Tests related to targets
+
+use nom::combinator::all_consuming;
+
+use crate::tests::Equivalence;
+
+use super::{InstancePath, Target, parsers};
+
+
+#[quickcheck]
+fn parse_instance_path(original: InstancePath) -> Result<Equivalence<InstancePath>, String> {
+    use nom::Finish;
+
+    let s = original.to_string();
+    let res = all_consuming(parsers::instance_path)(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn parse_target(original: Target) -> Result<Equivalence<Target>, String> {
+    use nom::Finish;
+
+    let s = original.to_string();
+    let res = all_consuming(parsers::target)(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn instance_path_prefix(path: InstancePath) -> bool {
+    let mut prefix = path.clone();
+    prefix.pop();
+    prefix.is_prefix_of(&path) && path.is_prefix_of(&path)
+}
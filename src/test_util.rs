@@ -0,0 +1,42 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Utilities for property-based testing
+//!
+//! This module is only available with the `test-util` feature enabled. It
+//! exposes the utilities this library uses for testing its own parsers and
+//! formatters, allowing crates which extend the AST, e.g. with custom passes
+//! or dialect supersets, to test their printers and parsers using the same
+//! methodology.
+//!
+//! With the feature enabled, all AST types implement
+//! [quickcheck::Arbitrary], generating well-formed values. In addition, this
+//! module provides the [Equivalence] type for expressing round-trip properties
+//! as well as a number of generators for values with specific constraints.
+//!
+//! # Example
+//!
+//! ```
+//! use firrtl_ast::Circuit;
+//! use firrtl_ast::test_util::Equivalence;
+//!
+//! fn round_trip(original: Circuit) -> Result<Equivalence<Circuit>, String> {
+//!     let parsed = firrtl_ast::circuit::parse(&original.to_string()).map_err(|e| e.to_string())?;
+//!     Ok(Equivalence(original, parsed))
+//! }
+//!
+//! quickcheck::QuickCheck::new()
+//!     .tests(4)
+//!     .quickcheck(round_trip as fn(Circuit) -> Result<Equivalence<Circuit>, String>);
+//! ```
+
+pub use crate::expr::tests::{
+    TypedExpr,
+    TypedRef,
+    expr_with_type,
+    primitive_op_with_type,
+    sink_flow,
+    source_flow,
+};
+pub use crate::stmt::tests::{FormatString, stmt_list, stmts_with_decls};
+pub use crate::tests::{ASCII, Equivalence, Identifier};
+pub use crate::types::bundle_fields;
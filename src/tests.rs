@@ -52,7 +52,7 @@ where
 
 
 /// Utility type for generating identifiers for tests
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Identifier {
     data: String
 }
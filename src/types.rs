@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 //! FIRRTL types
 
+pub mod alias;
 pub mod combinator;
 pub mod ground;
+pub mod interval;
 pub mod orientation;
 pub mod oriented;
 pub mod r#type;
@@ -15,13 +17,14 @@ mod display;
 mod tests;
 
 
+pub use alias::TypeAlias;
 pub use combinator::Combinator;
 pub use ground::{GroundType, MaxWidth, ResetKind, combine_fixed_max};
 pub use orientation::Orientation;
 pub use oriented::OrientedType;
-pub use r#type::{BundleField, Type};
+pub use r#type::{Access, BundleField, EnumVariant, ProbeKind, Type};
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 pub use r#type::bundle_fields;
 
 
@@ -0,0 +1,66 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Type aliases
+//!
+//! A [TypeAlias] declared in a circuit introduces a name for a [Type]. Where
+//! an alias is used, e.g. in the type of a port, the type is represented as a
+//! [Type::Alias] carrying both the alias' name and the type aliased. Hence,
+//! types are formatted as they were declared.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::info::{self, WithInfo};
+use crate::named::Named;
+
+use super::Type;
+
+
+/// Declaration of a name for a [Type]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeAlias {
+    name: Arc<str>,
+    r#type: Arc<Type>,
+    info: Option<String>,
+}
+
+impl TypeAlias {
+    /// Create a new alias for the given type
+    pub fn new(name: impl Into<Arc<str>>, r#type: Type) -> Self {
+        Self {name: name.into(), r#type: Arc::new(r#type), info: Default::default()}
+    }
+
+    /// Retrieve the type aliased
+    pub fn r#type(&self) -> &Type {
+        &self.r#type
+    }
+
+    /// Create a [Type] referring to this alias
+    pub fn to_type(&self) -> Type {
+        Type::Alias(self.name.clone(), self.r#type.clone())
+    }
+}
+
+impl Named for TypeAlias {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl WithInfo for TypeAlias {
+    fn info(&self) -> Option<&str> {
+        self.info.as_ref().map(AsRef::as_ref)
+    }
+
+    fn set_info(&mut self, info: Option<String>) {
+        self.info = info
+    }
+}
+
+impl fmt::Display for TypeAlias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "type {} = {}{}", self.name, self.r#type, info::Info::of(self))
+    }
+}
@@ -4,14 +4,15 @@
 
 use std::fmt;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use super::{BitWidth, Combinator, SBits, UBits};
+use super::interval::{self, Bound};
 
 
 /// FIRRTL ground type
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum GroundType {
     /// Unsigned integer type with width
     UInt(BitWidth),
@@ -25,6 +26,8 @@ pub enum GroundType {
     Reset(ResetKind),
     /// Analog signal with number of wires
     Analog(BitWidth),
+    /// Interval type, with lower bound, upper bound and point
+    Interval(Option<Bound>, Option<Bound>, Option<SBits>),
 }
 
 impl GroundType {
@@ -40,6 +43,7 @@ impl GroundType {
             Self::Clock       => Some(1),
             Self::Reset(_)    => Some(1),
             Self::Analog(w)   => *w,
+            Self::Interval(l, u, p) => interval::width(*l, *u, *p),
         }
     }
 
@@ -47,8 +51,9 @@ impl GroundType {
     ///
     /// This function returns a copy of the type, with the width replaced by the
     /// given one. In the case of [GroundType::Fixed], the point will be
-    /// preserved; in the case of [GroundType::Clock], this function will return
-    /// a simple copy.
+    /// preserved; in the case of [GroundType::Clock] and
+    /// [GroundType::Interval], whose widths are implied, this function will
+    /// return a simple copy.
     pub fn with_width(&self, width: BitWidth) -> Self {
         match self {
             Self::UInt(_)     => Self::UInt(width),
@@ -57,6 +62,7 @@ impl GroundType {
             Self::Clock       => Self::Clock,
             Self::Reset(k)    => Self::Reset(*k),
             Self::Analog(_)   => Self::Analog(width),
+            Self::Interval(..) => *self,
         }
     }
 }
@@ -69,6 +75,7 @@ impl super::TypeExt for GroundType {
             (Self::Fixed(_, _), Self::Fixed(_, _)) => true,
             (Self::Clock,       Self::Clock)       => true,
             (Self::Analog(_),   Self::Analog(_))   => true,
+            (Self::Interval(..), Self::Interval(..)) => true,
             _ => false
         }
     }
@@ -120,11 +127,25 @@ impl fmt::Display for GroundType {
             Self::Reset(R::Regular) => write!(f, "Reset"),
             Self::Reset(R::Async)   => write!(f, "AsyncReset"),
             Self::Analog(w)         => write!(f, "Analog{}", Width::from(w)),
+            Self::Interval(None, None, None) => write!(f, "Interval"),
+            Self::Interval(l, u, p) => {
+                match l {
+                    Some(Bound::Closed(v))  => write!(f, "Interval[{}, ", v)?,
+                    Some(Bound::Open(v))    => write!(f, "Interval({}, ", v)?,
+                    None                    => write!(f, "Interval[?, ")?,
+                }
+                match u {
+                    Some(Bound::Closed(v))  => write!(f, "{}]", v)?,
+                    Some(Bound::Open(v))    => write!(f, "{})", v)?,
+                    None                    => write!(f, "?]")?,
+                }
+                p.map(|p| write!(f, ".{}", p)).unwrap_or(Ok(()))
+            },
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for GroundType {
     fn arbitrary(g: &mut Gen) -> Self {
         let opts: [&dyn Fn(&mut Gen) -> Self; 5] = [
@@ -150,7 +171,7 @@ impl Arbitrary for GroundType {
 
 
 /// Kind of reset signal
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ResetKind {Regular, Async}
 
 
@@ -161,9 +182,11 @@ pub enum ResetKind {Regular, Async}
 /// [GroundType]s.  However, it will yield an error for fixed types.
 ///
 /// This [Combinator] extends the [FnWidth][super::combinator::FnWidth] for
-/// fixed types. For two [GroundType::Fixed], the [Combinator] computes a fixed
-/// type, taking into account both point offsets. All other combinations are
-/// forwarded to an [FnWidth][super::combinator::FnWidth].
+/// fixed and interval types. For two [GroundType::Fixed], the [Combinator]
+/// computes a fixed type, taking into account both point offsets. For two
+/// [GroundType::Interval]s, it computes an interval spanning both intervals
+/// with the greater point. All other combinations are forwarded to an
+/// [FnWidth][super::combinator::FnWidth].
 pub struct MaxWidth {}
 
 impl MaxWidth {
@@ -196,6 +219,11 @@ impl Combinator<GroundType> for MaxWidth {
                 GT::Fixed(combine_fixed_max((*lw, *lp), (*rw, *rp)), Some(max(*lp, *rp)))
             ),
             (GT::Fixed(..), GT::Fixed(..)) => Ok(GT::Fixed(None, None)),
+            (GT::Interval(ll, lu, lp), GT::Interval(rl, ru, rp)) => Ok(GT::Interval(
+                interval::lower_union(*ll, *rl),
+                interval::upper_union(*lu, *ru),
+                lp.and_then(|l| rp.map(|r| max(l, r))),
+            )),
             (l, r) => super::combinator::FnWidth::from(|l, r| Some(max(l, r))).combine(l, r),
         }
     }
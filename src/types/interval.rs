@@ -0,0 +1,272 @@
+// Copyright (c) 2021 FZI Forschungszentrum Informatik
+// SPDX-License-Identifier: Apache-2.0
+//! Interval bounds
+//!
+//! This module provides the building blocks of [GroundType::Interval], which
+//! is bounded by (possibly fractional) decimal numbers rather than a width.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::{BitWidth, GroundType, SBits};
+
+
+/// A decimal number as used for interval bounds
+///
+/// The number is represented by an integer mantissa and a decimal scale, i.e.
+/// its value is `mantissa * 10^-scale`. The representation is normalized, i.e.
+/// the mantissa never carries trailing zeros as long as the scale is non-zero.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Decimal {
+    mantissa: i64,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Create a new decimal number with value `mantissa * 10^-scale`
+    pub fn new(mut mantissa: i64, mut scale: u32) -> Self {
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        Self {mantissa, scale}
+    }
+
+    /// Retrieve the mantissa
+    pub fn mantissa(&self) -> i64 {
+        self.mantissa
+    }
+
+    /// Retrieve the decimal scale
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Create a decimal number with the value `raw * 2^-point`
+    ///
+    /// If the value cannot be represented, this function returns `None`.
+    pub fn from_fixed(raw: i64, point: SBits) -> Option<Self> {
+        let exp = point.unsigned_abs().into();
+        if point < 0 {
+            raw.checked_mul(2i64.checked_pow(exp)?).map(Self::from)
+        } else {
+            raw.checked_mul(5i64.checked_pow(exp)?).map(|m| Self::new(m, exp))
+        }
+    }
+
+    /// Compute the fraction `num/den` of this number multiplied by `2^point`
+    ///
+    /// The denominator is always positive. If either part of the fraction does
+    /// not fit into an `i128`, this function returns `None`.
+    fn fixed_fraction(&self, point: SBits) -> Option<(i128, i128)> {
+        let den = 10i128.checked_pow(self.scale)?;
+        let exp = 2i128.checked_pow(point.unsigned_abs().into())?;
+        if point < 0 {
+            Some((self.mantissa.into(), den.checked_mul(exp)?))
+        } else {
+            Some((i128::from(self.mantissa).checked_mul(exp)?, den))
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let pow = |s: u32| 10i128.checked_pow(s);
+        let scale = std::cmp::max(self.scale, other.scale);
+        let lhs = pow(scale - self.scale).and_then(|p| p.checked_mul(self.mantissa.into()));
+        let rhs = pow(scale - other.scale).and_then(|p| p.checked_mul(other.mantissa.into()));
+        match (lhs, rhs) {
+            (Some(l), Some(r)) => l.cmp(&r),
+            _ => {
+                let value = |d: &Self| d.mantissa as f64 * 10f64.powi(-(d.scale as i32));
+                value(self).partial_cmp(&value(other)).unwrap_or(Ordering::Equal)
+            },
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::str::FromStr for Decimal {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let frac = frac.trim_end_matches('0');
+        let mantissa = [int, frac].concat().parse()?;
+        Ok(Self::new(mantissa, frac.len() as u32))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scale == 0 {
+            return write!(f, "{}", self.mantissa)
+        }
+
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let digits = if digits.len() <= scale {
+            format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits)
+        } else {
+            digits
+        };
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+
+/// Bound of an interval
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Bound {
+    /// The bound itself is part of the interval
+    Closed(Decimal),
+    /// The bound itself is not part of the interval
+    Open(Decimal),
+}
+
+impl Bound {
+    /// Retrieve the value of the bound
+    pub fn value(&self) -> Decimal {
+        match self {
+            Self::Closed(v) => *v,
+            Self::Open(v)   => *v,
+        }
+    }
+
+    /// Check whether the bound is closed
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Closed(_))
+    }
+
+    /// Compute the lowest fixed point value within this lower bound
+    ///
+    /// The value is expressed as a multiple of `2^-point`.
+    pub fn lowest_raw(&self, point: SBits) -> Option<i128> {
+        let (num, den) = self.value().fixed_fraction(point)?;
+        match self {
+            Self::Closed(_) => Some(-(-num).div_euclid(den)),
+            Self::Open(_)   => num.div_euclid(den).checked_add(1),
+        }
+    }
+
+    /// Compute the highest fixed point value within this upper bound
+    ///
+    /// The value is expressed as a multiple of `2^-point`.
+    pub fn highest_raw(&self, point: SBits) -> Option<i128> {
+        let (num, den) = self.value().fixed_fraction(point)?;
+        match self {
+            Self::Closed(_) => Some(num.div_euclid(den)),
+            Self::Open(_)   => (-(-num).div_euclid(den)).checked_sub(1),
+        }
+    }
+}
+
+
+/// Compute the width of an interval type
+///
+/// The width is the number of bits required for representing every value in
+/// the interval as a signed fixed point number with the given point. If any
+/// of the parameters is unknown, this function returns `None`.
+pub fn width(lower: Option<Bound>, upper: Option<Bound>, point: Option<SBits>) -> BitWidth {
+    use std::convert::TryInto;
+
+    let point = point?;
+    let bits = |v: i128| 129 - (if v < 0 { !v } else { v }).leading_zeros();
+    let lowest = bits(lower?.lowest_raw(point)?);
+    let highest = bits(upper?.highest_raw(point)?);
+    std::cmp::max(lowest, highest).try_into().ok()
+}
+
+
+/// Compute the union of two lower bounds
+///
+/// The result is the lower bound of the smallest interval containing both
+/// intervals bounded by the given bounds.
+pub fn lower_union(lhs: Option<Bound>, rhs: Option<Bound>) -> Option<Bound> {
+    Some(select(lhs?, rhs?, Ordering::Less))
+}
+
+/// Compute the union of two upper bounds
+///
+/// The result is the upper bound of the smallest interval containing both
+/// intervals bounded by the given bounds.
+pub fn upper_union(lhs: Option<Bound>, rhs: Option<Bound>) -> Option<Bound> {
+    Some(select(lhs?, rhs?, Ordering::Greater))
+}
+
+/// Compute the intersection of two lower bounds
+///
+/// An unknown bound does not restrict the intersection.
+pub fn lower_intersection(lhs: Option<Bound>, rhs: Option<Bound>) -> Option<Bound> {
+    match (lhs, rhs) {
+        (Some(l), Some(r)) => Some(select_open(l, r, Ordering::Greater)),
+        (l, r) => l.or(r),
+    }
+}
+
+/// Compute the intersection of two upper bounds
+///
+/// An unknown bound does not restrict the intersection.
+pub fn upper_intersection(lhs: Option<Bound>, rhs: Option<Bound>) -> Option<Bound> {
+    match (lhs, rhs) {
+        (Some(l), Some(r)) => Some(select_open(l, r, Ordering::Less)),
+        (l, r) => l.or(r),
+    }
+}
+
+/// Select the bound which compares as `preferred`, preferring closed bounds
+fn select(lhs: Bound, rhs: Bound, preferred: Ordering) -> Bound {
+    match lhs.value().cmp(&rhs.value()) {
+        Ordering::Equal if rhs.is_closed()  => rhs,
+        Ordering::Equal                     => lhs,
+        o if o == preferred                 => lhs,
+        _                                   => rhs,
+    }
+}
+
+/// Select the bound which compares as `preferred`, preferring open bounds
+fn select_open(lhs: Bound, rhs: Bound, preferred: Ordering) -> Bound {
+    match lhs.value().cmp(&rhs.value()) {
+        Ordering::Equal if rhs.is_closed()  => lhs,
+        Ordering::Equal                     => rhs,
+        o if o == preferred                 => lhs,
+        _                                   => rhs,
+    }
+}
+
+
+/// Retrieve the bounds of the values representable by a ground type
+///
+/// For [GroundType::Interval]s, this function returns the bounds of the
+/// interval. For [GroundType::UInt]s and [GroundType::SInt]s, this function
+/// returns the closed bounds of the values representable with the given width
+/// or unknown bounds if the width is not known. For other types, this function
+/// returns `None`.
+pub fn bounds(r#type: &GroundType) -> Option<(Option<Bound>, Option<Bound>)> {
+    let pow = |w: u16| 2i64.checked_pow(w.into());
+    match r#type {
+        GroundType::Interval(l, u, _) => Some((*l, *u)),
+        GroundType::UInt(w) => Some((
+            w.map(|_| Bound::Closed(0.into())),
+            w.and_then(pow).map(|p| Bound::Closed((p - 1).into())),
+        )),
+        GroundType::SInt(w) => {
+            let half = w.and_then(|w| w.checked_sub(1)).and_then(pow);
+            Some((half.map(|h| Bound::Closed((-h).into())), half.map(|h| Bound::Closed((h - 1).into()))))
+        },
+        _ => None,
+    }
+}
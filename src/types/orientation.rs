@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //! Orientation
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 
@@ -32,7 +32,7 @@ impl std::ops::Add for Orientation {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Orientation {
     fn arbitrary(g: &mut Gen) -> Self {
         *g.choose(&[Self::Normal, Self::Flipped]).unwrap()
@@ -4,7 +4,7 @@
 
 use std::sync::Arc;
 
-use super::{Orientation, TypeExt};
+use super::{Orientation, ProbeKind, TypeExt};
 
 
 /// Oriented type
@@ -16,6 +16,8 @@ pub enum OrientedType {
     GroundType(super::GroundType, Orientation),
     Vector(Arc<Self>, super::VecWidth),
     Bundle(Arc<[(Arc<str>, Self)]>),
+    Probe(Arc<Self>, ProbeKind),
+    Enum(Arc<[super::EnumVariant]>, Orientation),
 }
 
 impl OrientedType {
@@ -25,6 +27,8 @@ impl OrientedType {
             Self::GroundType(g, o) => Self::GroundType(*g, *o + Orientation::Flipped),
             Self::Vector(t, w)     => Self::Vector(Arc::new(t.flipped()), *w),
             Self::Bundle(v)        => Self::Bundle(v.iter().map(|(n, t)| (n.clone(), t.flipped())).collect()),
+            Self::Probe(t, k)      => Self::Probe(Arc::new(t.flipped()), *k),
+            Self::Enum(v, o)       => Self::Enum(v.clone(), *o + Orientation::Flipped),
         }
     }
 }
@@ -39,6 +43,9 @@ impl TypeExt for OrientedType {
             } else {
                 false
             },
+            (Self::Probe(t1, k1), Self::Probe(t2, k2)) => k1 == k2 && TypeExt::eq(t1.as_ref(), t2.as_ref()),
+            (Self::Enum(v1, o1), Self::Enum(v2, o2)) =>
+                TypeExt::eq(&super::Type::Enum(v1.clone()), &super::Type::Enum(v2.clone())) && o1 == o2,
             _ => false
         }
     }
@@ -48,6 +55,8 @@ impl TypeExt for OrientedType {
             Self::GroundType(t, o) => t.is_passive() && *o == Orientation::Normal,
             Self::Vector(t, _) => t.is_passive(),
             Self::Bundle(v) => v.iter().all(|(_, t)| t.is_passive()),
+            Self::Probe(t, _) => t.is_passive(),
+            Self::Enum(v, o) => super::Type::Enum(v.clone()).is_passive() && *o == Orientation::Normal,
         }
     }
 
@@ -5,13 +5,18 @@
 use std::sync::Arc;
 
 use nom::branch::alt;
-use nom::bytes::complete::take_while;
-use nom::combinator::{map, opt, value};
+use nom::bytes::complete::take_while1;
+use nom::character::complete::{char as chr, digit1};
+use nom::combinator::{map, map_opt, map_res, opt, peek, recognize, value};
 use nom::error::context;
 use nom::multi::{fold_many0, separated_list0};
-use nom::sequence::{preceded, tuple};
+use nom::sequence::{preceded, terminated, tuple};
 
-use crate::parsers::{IResult, decimal, is_identifier_char, kw, op, spaced};
+use crate::named::Named;
+use crate::parsers::{IResult, decimal, identifier, is_identifier_char, kw, op, spaced};
+
+use super::TypeAlias;
+use super::interval::{Bound, Decimal};
 
 
 /// Parse a ground type
@@ -28,10 +33,42 @@ pub fn ground_type(input: &str) -> IResult<super::GroundType> {
         value(G::Reset(K::Regular), kw("Reset")),
         value(G::Reset(K::Async), kw("AsyncReset")),
         map(preceded(kw("Analog"), bitwidth), G::Analog),
+        map(preceded(kw("Interval"), interval), |(l, u, p)| G::Interval(l, u, p)),
     ))(input)
 }
 
 
+/// Parse the bounds and point of an interval type
+///
+/// This function parses optional bounds enclosed in brackets or parentheses,
+/// depending on whether they are closed or open, followed by an optional point
+/// preceded by a `.`. Unknown bounds are denoted by a `?`.
+fn interval(input: &str) -> IResult<'_, (Option<Bound>, Option<Bound>, Option<super::SBits>)> {
+    let bound = |i| alt((value(None, op("?")), map(interval_decimal, Some)))(i);
+
+    let lower = map(
+        tuple((alt((value(true, op("[")), value(false, op("(")))), spaced(bound))),
+        |(c, v)| v.map(|v| if c { Bound::Closed(v) } else { Bound::Open(v) })
+    );
+    let upper = map(
+        tuple((spaced(bound), spaced(alt((value(true, op("]")), value(false, op(")"))))))),
+        |(v, c)| v.map(|v| if c { Bound::Closed(v) } else { Bound::Open(v) })
+    );
+
+    let (input, bounds) = opt(spaced(tuple((lower, spaced(op(",")), upper))))(input)?;
+    let (input, point) = opt(preceded(spaced(op(".")), spaced(decimal)))(input)?;
+    let (lower, upper) = bounds.map(|(l, _, u)| (l, u)).unwrap_or((None, None));
+    Ok((input, (lower, upper, point)))
+}
+
+
+/// Parse a decimal number, i.e. a number with an optional fractional part
+fn interval_decimal(input: &str) -> IResult<'_, Decimal> {
+    let number = tuple((opt(alt((chr('-'), chr('+')))), digit1, opt(tuple((chr('.'), digit1)))));
+    context("expected decimal number", map_res(recognize(number), str::parse))(input)
+}
+
+
 /// Parse a BitWidth
 ///
 /// This function parses an optional bit-width encapsulated in `<` and `>`.
@@ -41,21 +78,53 @@ pub fn bitwidth(input: &str) -> IResult<super::BitWidth> {
 
 
 /// Parse a type
-pub fn r#type(input: &str) -> IResult<super::Type> {
-    use super::Type as T;
+///
+/// Names of the given `aliases` are parsed as [Type::Alias](super::Type::Alias)es.
+pub fn r#type<'i>(input: &'i str, aliases: &[TypeAlias]) -> IResult<'i, super::Type> {
+    use super::{ProbeKind as K, Type as T};
+
+    let r#type = |i| r#type(i, aliases);
 
+    // A field may be named `flip`. Hence, we only consider `flip` to be the
+    // orientation if it is followed by a field name.
     let field = map(
-        tuple((opt(kw("flip")), spaced(field_name), spaced(op(":")), spaced(r#type))),
+        tuple((
+            opt(terminated(kw("flip"), peek(spaced(field_name)))),
+            spaced(field_name),
+            spaced(op(":")),
+            spaced(r#type),
+        )),
         |(o, n, _, t)| super::BundleField::new(n, t)
             .with_orientation(o.map(|_| super::Orientation::Flipped).unwrap_or_default())
     );
 
+    let variant = map(
+        tuple((spaced(field_name), opt(preceded(spaced(op(":")), spaced(r#type))))),
+        |(n, t)| match t {
+            Some(t) => super::EnumVariant::new(n).with_data(t),
+            None    => super::EnumVariant::new(n),
+        }
+    );
+
     let (input, res) = alt((
+        map(
+            tuple((op("{|"), separated_list0(spaced(op(",")), variant), spaced(op("|}")))),
+            |(_, v, _)| T::Enum(v.into())
+        ),
         map(
             tuple((op("{"), separated_list0(spaced(op(",")), spaced(field)), spaced(op("}")))),
             |(_, v, _)| T::Bundle(v.into())
         ),
+        map(
+            tuple((kw("Probe"), spaced(op("<")), spaced(r#type), spaced(op(">")))),
+            |(_, _, t, _)| T::Probe(Arc::new(t), K::Read)
+        ),
+        map(
+            tuple((kw("RWProbe"), spaced(op("<")), spaced(r#type), spaced(op(">")))),
+            |(_, _, t, _)| T::Probe(Arc::new(t), K::ReadWrite)
+        ),
         map(ground_type, T::GroundType),
+        map_opt(identifier, |n| aliases.iter().find(|a| a.name().as_ref() == n).map(TypeAlias::to_type)),
     ))(input)?;
 
     fold_many0(
@@ -69,7 +138,8 @@ pub fn r#type(input: &str) -> IResult<super::Type> {
 /// Parser for field names
 ///
 /// The rules for field names are somehow more relaxed than for identifiers.
+/// However, field names must not be empty.
 pub fn field_name(input: &str) -> IResult<&str> {
-    context("expected field name", take_while(is_identifier_char))(input)
+    context("expected field name", take_while1(is_identifier_char))(input)
 }
 
@@ -6,7 +6,7 @@ use nom::combinator::all_consuming;
 
 use crate::tests::Equivalence;
 
-use super::{BitWidth, GroundType, Type, combinator, parsers};
+use super::{BitWidth, GroundType, ProbeKind, Type, combinator, parsers};
 use combinator::Combinator;
 
 
@@ -28,7 +28,7 @@ fn parse_type(original: Type) -> Result<Equivalence<Type>, String> {
     use nom::Finish;
 
     let s = original.to_string();
-    let res = all_consuming(parsers::r#type)(&s)
+    let res = all_consuming(|i| parsers::r#type(i, &[]))(&s)
         .finish()
         .map(|(_, parsed)| Equivalence::of(original, parsed))
         .map_err(|e| e.to_string());
@@ -36,6 +36,38 @@ fn parse_type(original: Type) -> Result<Equivalence<Type>, String> {
 }
 
 
+#[quickcheck]
+fn parse_probe_type(inner: Type, kind: ProbeKind, width: u8) -> Result<Equivalence<Type>, String> {
+    use std::sync::Arc;
+
+    use nom::Finish;
+
+    let original = Type::Vector(Arc::new(Type::Probe(Arc::new(inner), kind)), width.into());
+    let s = original.to_string();
+    let res = all_consuming(|i| parsers::r#type(i, &[]))(&s)
+        .finish()
+        .map(|(_, parsed)| Equivalence::of(original, parsed))
+        .map_err(|e| e.to_string());
+    res
+}
+
+
+#[quickcheck]
+fn probe_type_ext(inner: Type, kind: ProbeKind) -> bool {
+    use std::sync::Arc;
+
+    use super::{ProbeKind as K, TypeExt};
+
+    let probe = Type::Probe(Arc::new(inner.clone()), kind);
+    let other = if kind == K::Read { K::ReadWrite } else { K::Read };
+    TypeExt::eq(&probe, &probe) &&
+        !TypeExt::eq(&probe, &Type::Probe(Arc::new(inner.clone()), other)) &&
+        !TypeExt::eq(&probe, &inner) &&
+        probe.is_passive() == inner.is_passive() &&
+        probe.ground_type().is_none()
+}
+
+
 #[quickcheck]
 fn type_partial_eq(lhs: Type, rhs: GroundType) -> Equivalence<bool> {
     Equivalence::of(lhs == rhs, lhs == Type::from(rhs))
@@ -80,3 +112,140 @@ impl Combinator<GroundType> for DummyCombinator {
         Ok(lhs.clone())
     }
 }
+
+
+#[test]
+fn bundle_edge_cases() {
+    use std::sync::Arc;
+
+    use nom::Finish;
+
+    use super::{BundleField, GroundType as GT, Orientation, TypeExt};
+
+    let parse = |s: &str| all_consuming(|i| parsers::r#type(i, &[]))(s).finish().map(|(_, t)| t).map_err(|e| e.to_string());
+
+    let empty = Type::from(Vec::<BundleField>::new());
+    assert_eq!(parse("{}"), Ok(empty.clone()));
+    assert_eq!(parse("{ }"), Ok(empty.clone()));
+    assert_eq!(empty.to_string(), "{}");
+    assert_eq!(empty.bit_width(), Some(0));
+    assert!(empty.is_passive());
+    assert!(TypeExt::eq(&empty, &empty));
+    assert!(!TypeExt::eq(&empty, &Type::from(vec![BundleField::new("a", GT::UInt(None))])));
+
+    let nested = Type::Vector(Arc::new(Type::from(vec![BundleField::new("a", empty.clone())])), 3);
+    assert_eq!(parse("{a: {}}[3]"), Ok(nested.clone()));
+    assert_eq!(parse(&nested.to_string()), Ok(nested));
+
+    let flip = |n: &str| BundleField::new(n, GT::Clock).with_orientation(Orientation::Flipped);
+    assert_eq!(parse("{flip: Clock}"), Ok(Type::from(vec![BundleField::new("flip", GT::Clock)])));
+    assert_eq!(parse("{flip flip: Clock}"), Ok(Type::from(vec![flip("flip")])));
+    assert_eq!(parse("{flip a : Clock}"), Ok(Type::from(vec![flip("a")])));
+
+    assert!(parse("{: Clock}").is_err());
+    assert!(parse("{flip : Clock, }").is_err());
+}
+
+
+#[test]
+fn access_path_types() {
+    use nom::Finish;
+
+    use super::{Access as A, GroundType as GT};
+
+    let (_, t) = all_consuming(|i| parsers::r#type(i, &[]))("{a: UInt<1>[2]}[3][4]").finish().expect("Could not parse type");
+    assert_eq!(t.to_string(), "{a: UInt<1>[2]}[3][4]");
+
+    let bundle = t.at_access_path(&[A::Index(3), A::Dynamic]).expect("Could not access bundle");
+    assert_eq!(bundle.to_string(), "{a: UInt<1>[2]}");
+
+    let path = [A::Dynamic, A::Index(2), A::Field("a".into()), A::Index(1)];
+    assert_eq!(t.at_access_path(&path), Some(&Type::from(GT::UInt(Some(1)))));
+    assert_eq!(path.iter().map(ToString::to_string).collect::<String>(), "[_][2].a[1]");
+
+    assert_eq!(t.at_access_path(&[]), Some(&t));
+    assert_eq!(t.at_access_path(&[A::Index(4)]), None);
+    assert_eq!(t.at_access_path(&[A::Dynamic, A::Index(3)]), None);
+    assert_eq!(t.at_access_path(&[A::Dynamic, A::Dynamic, A::Field("b".into())]), None);
+    assert_eq!(t.at_access_path(&[A::Field("a".into())]), None);
+}
+
+
+#[test]
+fn enum_types() {
+    use nom::Finish;
+
+    use super::{EnumVariant, GroundType as GT, TypeExt};
+
+    let parse = |s: &str| all_consuming(|i| parsers::r#type(i, &[]))(s).finish().map(|(_, t)| t).map_err(|e| e.to_string());
+
+    let t = parse("{|A, B: UInt<2>, C : {a: UInt<3>}|}").expect("Could not parse enum");
+    assert_eq!(t.to_string(), "{|A, B: UInt<2>, C: {a: UInt<3>}|}");
+    assert_eq!(parse(&t.to_string()), Ok(t.clone()));
+
+    assert_eq!(t.variant("B"), Some(&EnumVariant::new("B").with_data(GT::UInt(Some(2)))));
+    assert_eq!(t.variant("A").and_then(EnumVariant::data), None);
+    assert_eq!(t.variant("D"), None);
+    assert_eq!(t.tag("A"), Some(0));
+    assert_eq!(t.tag("C"), Some(2));
+    assert_eq!(t.tag_width(), Some(2));
+    assert_eq!(t.bit_width(), Some(5));
+    assert!(t.is_passive());
+    assert!(TypeExt::eq(&t, &t));
+
+    let single = parse("{|A|}").expect("Could not parse enum");
+    assert_eq!(single.tag_width(), Some(0));
+    assert_eq!(single.bit_width(), Some(0));
+    assert!(!TypeExt::eq(&t, &single));
+    assert_eq!(Type::from(GT::Clock).tag_width(), None);
+}
+
+
+#[test]
+fn unique_fields() {
+    use nom::Finish;
+
+    let parse = |s: &str| all_consuming(|i| parsers::r#type(i, &[]))(s).finish().map(|(_, t)| t).expect("Could not parse type");
+
+    assert!(parse("UInt<1>").fields_unique());
+    assert!(parse("{a: UInt<1>, b: {a: UInt<1>}}").fields_unique());
+    assert!(!parse("{a: UInt<1>, a: SInt<1>}").fields_unique());
+    assert!(!parse("{a: {b: Clock, b: Clock}[2]}").fields_unique());
+    assert!(!parse("{|A: {b: Clock, b: Clock}|}").fields_unique());
+
+    let duplicate = parse("{a: UInt<1>, a: SInt<1>}");
+    assert_eq!(duplicate.field("a").map(|f| f.r#type()), Some(&Type::from(GroundType::UInt(Some(1)))));
+}
+
+
+#[test]
+fn interval_types() {
+    use nom::Finish;
+
+    use super::{Combinator, GroundType as GT, MaxWidth, TypeExt};
+
+    let parse = |s: &str| all_consuming(parsers::ground_type)(s).finish().map(|(_, t)| t).map_err(|e| e.to_string());
+
+    let a = parse("Interval[0, 4].2").expect("Could not parse interval");
+    assert_eq!(a.to_string(), "Interval[0, 4].2");
+    assert_eq!(a.width(), Some(6));
+
+    let b = parse("Interval( -1.50, 2.25 ].1").expect("Could not parse interval");
+    assert_eq!(b.to_string(), "Interval(-1.5, 2.25].1");
+    assert_eq!(b.width(), Some(4));
+    assert_eq!(parse(&b.to_string()), Ok(b));
+
+    let combined = MaxWidth::new().combine(&a, &b).expect("Could not combine intervals");
+    assert_eq!(combined.to_string(), "Interval(-1.5, 4].2");
+    assert_eq!(combined.width(), Some(6));
+
+    let unknown = parse("Interval[?, 3)").expect("Could not parse interval");
+    assert_eq!(unknown.to_string(), "Interval[?, 3)");
+    assert_eq!(unknown.width(), None);
+    assert_eq!(parse("Interval").map(|t| t.to_string()), Ok("Interval".to_string()));
+    assert_eq!(parse("Interval.3").map(|t| t.width()), Ok(None));
+
+    assert!(TypeExt::eq(&a, &unknown));
+    assert!(!TypeExt::eq(&a, &GT::SInt(Some(6))));
+    assert_eq!(a.with_width(Some(2)), a);
+}
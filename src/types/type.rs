@@ -5,7 +5,7 @@
 use std::fmt;
 use std::sync::Arc;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 use quickcheck::{Arbitrary, Gen};
 
 use crate::named::Named;
@@ -18,6 +18,18 @@ pub enum Type {
     GroundType(GroundType),
     Vector(Arc<Self>, super::VecWidth),
     Bundle(Arc<[BundleField]>),
+    /// A probe, i.e. a reference to a value of the given type
+    ///
+    /// Probes are used for expressing cross-module references. They do not
+    /// correspond to any hardware themselves.
+    Probe(Arc<Self>, ProbeKind),
+    /// An enumeration, i.e. a tagged union of the given variants
+    Enum(Arc<[EnumVariant]>),
+    /// A use of a [TypeAlias](super::TypeAlias) with the given name
+    ///
+    /// The aliased type is retained alongside the name. Apart from formatting,
+    /// an alias behaves like the type aliased.
+    Alias(Arc<str>, Arc<Self>),
 }
 
 impl Type {
@@ -31,6 +43,41 @@ impl Type {
                     .map(|f| (f.name().clone(), f.r#type().with_orientation(f.orientation() + orientation)))
                     .collect()
             ),
+            Self::Probe(t, k)   => OrientedType::Probe(Arc::new(t.with_orientation(orientation)), *k),
+            Self::Enum(v)       => OrientedType::Enum(v.clone(), orientation),
+            Self::Alias(_, t)   => t.with_orientation(orientation),
+        }
+    }
+
+    /// Retrieve the type aliased, if this type is an alias
+    ///
+    /// This function resolves aliases of aliases. For any other type, the type
+    /// itself is returned.
+    pub fn resolved(&self) -> &Self {
+        match self {
+            Self::Alias(_, t)   => t.resolved(),
+            t                   => t,
+        }
+    }
+
+    /// Create a copy of this type with all aliases expanded
+    ///
+    /// The resulting type does not contain any aliases, at any level of
+    /// nesting.
+    pub fn expanded(&self) -> Self {
+        match self {
+            Self::GroundType(_) => self.clone(),
+            Self::Vector(t, w)  => Self::Vector(Arc::new(t.expanded()), *w),
+            Self::Bundle(v)     => v.iter().map(|f| f.clone().with_type(f.r#type().expanded())).collect(),
+            Self::Probe(t, k)   => Self::Probe(Arc::new(t.expanded()), *k),
+            Self::Enum(v)       => Self::Enum(v
+                .iter()
+                .map(|v| match v.data() {
+                    Some(d) => EnumVariant::new(v.name().clone()).with_data(d.expanded()),
+                    None    => v.clone(),
+                })
+                .collect()),
+            Self::Alias(_, t)   => t.expanded(),
         }
     }
 
@@ -55,13 +102,84 @@ impl Type {
     /// This function returns the type of a vector element and the width of the
     /// vector, or `None` if called on a type not a vector type.
     pub fn vector(&self) -> Option<(&Arc<Self>, super::VecWidth)> {
-        if let Self::Vector(t, w) = self {
+        if let Self::Vector(t, w) = self.resolved() {
             Some((t, *w))
         } else {
             None
         }
     }
 
+    /// If this type is a probe type, return the referenced type and kind
+    pub fn probe(&self) -> Option<(&Arc<Self>, ProbeKind)> {
+        if let Self::Probe(t, k) = self.resolved() {
+            Some((t, *k))
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve an iterator over the variants in this type
+    ///
+    /// If the type is not an enumeration type, this function returns `None`.
+    pub fn variants(&self) -> Option<impl Iterator<Item = &EnumVariant>> {
+        if let Self::Enum(v) = self.resolved() {
+            Some(v.iter())
+        } else {
+            None
+        }
+    }
+
+    /// Return the enumeration variant with the given name
+    ///
+    /// If the type is not an enumeration type or the enumeration does not
+    /// contain a variant with the given name, this function returns `None`.
+    pub fn variant(&self, variant: &str) -> Option<&EnumVariant> {
+        self.variants().and_then(|mut v| v.find(|v| v.name().as_ref() == variant))
+    }
+
+    /// Return the tag of the enumeration variant with the given name
+    ///
+    /// Variants are tagged in the order of their declaration, starting with
+    /// `0`. If the type is not an enumeration type or the enumeration does not
+    /// contain a variant with the given name, this function returns `None`.
+    pub fn tag(&self, variant: &str) -> Option<usize> {
+        self.variants().and_then(|mut v| v.position(|v| v.name().as_ref() == variant))
+    }
+
+    /// Retrieve the number of bits required for the tag of an enumeration
+    ///
+    /// If the type is not an enumeration type, this function returns `None`.
+    pub fn tag_width(&self) -> Option<u64> {
+        self.variants().map(|v| {
+            let max = v.count().saturating_sub(1) as u64;
+            (u64::BITS - max.leading_zeros()).into()
+        })
+    }
+
+    /// Retrieve the total number of bits of a value of this type
+    ///
+    /// This function returns the number of physical wires corresponding to the
+    /// type, i.e. the sum of the widths of all ground type elements. If the
+    /// width of any element is not known or the number of bits exceeds the
+    /// range of a `u64`, this function returns `None`. Probes don't correspond
+    /// to any physical wires. Enumerations require the bits for the tag and the
+    /// bits of the variant with the widest data.
+    pub fn bit_width(&self) -> Option<u64> {
+        match self {
+            Self::GroundType(g) => g.width().map(Into::into),
+            Self::Vector(t, w)  => t.bit_width().and_then(|b| b.checked_mul((*w).into())),
+            Self::Bundle(v)     => v
+                .iter()
+                .try_fold(0u64, |acc, f| f.r#type().bit_width().and_then(|b| acc.checked_add(b))),
+            Self::Probe(..)     => Some(0),
+            Self::Enum(v)       => v
+                .iter()
+                .try_fold(0u64, |acc, v| v.data().map(Type::bit_width).unwrap_or(Some(0)).map(|b| acc.max(b)))
+                .and_then(|b| self.tag_width().and_then(|t| b.checked_add(t))),
+            Self::Alias(_, t)   => t.bit_width(),
+        }
+    }
+
     /// Return the bundle field with the given name
     ///
     /// If the type is not a bundle type or the bundle does not contain a field
@@ -74,17 +192,50 @@ impl Type {
     ///
     /// If the type is not a bundle type, this function returns `None`.
     pub fn fields(&self) -> Option<impl Iterator<Item = &BundleField>> {
-        if let Self::Bundle(v) = self {
+        if let Self::Bundle(v) = self.resolved() {
             Some(v.iter())
         } else {
             None
         }
     }
+
+    /// Check whether the field names of all bundles in this type are unique
+    ///
+    /// Bundles at any level of nesting are considered, e.g. bundles inside
+    /// vectors or other bundles. Types without any bundle trivially have
+    /// unique field names.
+    pub fn fields_unique(&self) -> bool {
+        match self {
+            Self::GroundType(_) => true,
+            Self::Vector(t, _)  => t.fields_unique(),
+            Self::Bundle(v)     => {
+                let mut names = std::collections::HashSet::new();
+                v.iter().all(|f| names.insert(f.name()) && f.r#type().fields_unique())
+            },
+            Self::Probe(t, _)   => t.fields_unique(),
+            Self::Enum(v)       => v.iter().filter_map(EnumVariant::data).all(Self::fields_unique),
+            Self::Alias(_, t)   => t.fields_unique(),
+        }
+    }
+
+    /// Retrieve the type of the part of a value selected by an access path
+    ///
+    /// This function applies the [Access]es in the given path in order,
+    /// starting with this type. If any of them is not applicable, e.g. because
+    /// a field does not exist or an index is out of bounds, this function
+    /// returns `None`. An empty path selects the entire value.
+    pub fn at_access_path(&self, path: &[Access]) -> Option<&Self> {
+        path.iter().try_fold(self, |t, a| match a {
+            Access::Field(name) => t.field(name.as_ref()).map(BundleField::r#type),
+            Access::Index(i)    => t.vector().filter(|(_, w)| i < w).map(|(t, _)| t.as_ref()),
+            Access::Dynamic     => t.vector_base().map(AsRef::as_ref),
+        })
+    }
 }
 
 impl TypeExt for Type {
     fn eq(&self, rhs: &Self) -> bool {
-        match (self, rhs) {
+        match (self.resolved(), rhs.resolved()) {
             (Self::GroundType(t1), Self::GroundType(t2)) => TypeExt::eq(t1, t2),
             (Self::Vector(t1, w1), Self::Vector(t2, w2)) => TypeExt::eq(t1.as_ref(), t2.as_ref()) && w1 == w2,
             (Self::Bundle(v1), Self::Bundle(v2)) => if v1.len() == v2.len() {
@@ -96,6 +247,15 @@ impl TypeExt for Type {
             } else {
                 false
             },
+            (Self::Probe(t1, k1), Self::Probe(t2, k2)) => k1 == k2 && TypeExt::eq(t1.as_ref(), t2.as_ref()),
+            (Self::Enum(v1), Self::Enum(v2)) => v1.len() == v2.len() && v1
+                .iter()
+                .zip(v2.iter())
+                .all(|(l, r)| l.name() == r.name() && match (l.data(), r.data()) {
+                    (Some(l), Some(r))  => TypeExt::eq(l, r),
+                    (None, None)        => true,
+                    _                   => false,
+                }),
             _ => false
         }
     }
@@ -107,11 +267,14 @@ impl TypeExt for Type {
             Self::Bundle(v) => v
                 .iter()
                 .all(|f| f.orientation() == Orientation::Normal && f.r#type().is_passive()),
+            Self::Probe(t, _) => t.is_passive(),
+            Self::Enum(v) => v.iter().filter_map(EnumVariant::data).all(TypeExt::is_passive),
+            Self::Alias(_, t) => t.is_passive(),
         }
     }
 
     fn ground_type(&self) -> Option<GroundType> {
-        if let Self::GroundType(g) = self {
+        if let Self::GroundType(g) = self.resolved() {
             Some(*g)
         } else {
             None
@@ -121,7 +284,7 @@ impl TypeExt for Type {
 
 impl<C: Combinator<GroundType>> Combinator<Type> for C {
     fn combine<'a>(&self, lhs: &'a Type, rhs: &'a Type) -> Result<Type, (&'a Type, &'a Type)> {
-        match (lhs, rhs) {
+        match (lhs.resolved(), rhs.resolved()) {
             (Type::GroundType(t1), Type::GroundType(t2)) => self.combine(t1, t2)
                 .map_err(|_| (lhs, rhs))
                 .map(Into::into),
@@ -135,10 +298,28 @@ impl<C: Combinator<GroundType>> Combinator<Type> for C {
                 ).map_err(|_| (lhs, rhs))?;
                 Ok(res.into())
             },
+            (Type::Probe(t1, k1), Type::Probe(t2, k2)) if k1 == k2 =>
+                <Self as Combinator<Type>>::combine(self, t1.as_ref(), t2.as_ref())
+                    .map(|t| Type::Probe(Arc::new(t), *k1)),
+            (Type::Enum(v1), Type::Enum(v2)) if v1.len() == v2.len() => {
+                let mut res: Vec<_> = Default::default();
+                v1.iter().zip(v2.iter()).try_for_each(|(l, r)| match (l.data(), r.data()) {
+                    _ if l.name() != r.name()   => Err(()),
+                    (Some(d1), Some(d2))        => <Self as Combinator<Type>>::combine(self, d1, d2)
+                        .map(|d| res.push(EnumVariant::new(l.name().clone()).with_data(d)))
+                        .map_err(|_| ()),
+                    (None, None)                => {
+                        res.push(l.clone());
+                        Ok(())
+                    },
+                    _                           => Err(()),
+                }).map_err(|_| (lhs, rhs))?;
+                Ok(Type::Enum(res.into()))
+            },
             _ => Err((lhs, rhs))
-        }.map(|res| if res == *lhs {
+        }.map(|res| if res == *lhs.resolved() {
             lhs.clone()
-        } else if res == *rhs {
+        } else if res == *rhs.resolved() {
             rhs.clone()
         } else {
             res
@@ -166,7 +347,7 @@ impl std::iter::FromIterator<BundleField> for Type {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl From<std::collections::HashMap<Arc<str>, BundleField>> for Type {
     fn from(v: std::collections::HashMap<Arc<str>, BundleField>) -> Self {
         Self::Bundle(v.into_iter().map(|(_, f)| f).collect())
@@ -175,7 +356,7 @@ impl From<std::collections::HashMap<Arc<str>, BundleField>> for Type {
 
 impl PartialEq<GroundType> for Type {
     fn eq(&self, other: &GroundType) -> bool {
-        match self {
+        match self.resolved() {
             Self::GroundType(g) => g == other,
             _ => false,
         }
@@ -190,12 +371,17 @@ impl fmt::Display for Type {
             Self::GroundType(g) => fmt::Display::fmt(g, f),
             Self::Vector(t, w)  => write!(f, "{}[{}]", t, w),
             Self::Bundle(v)     => write!(f, "{{{}}}", CommaSeparated::from(v)),
+            Self::Probe(t, k)   => write!(f, "{}<{}>", k.keyword(), t),
+            Self::Enum(v)       => write!(f, "{{|{}|}}", CommaSeparated::from(v)),
+            Self::Alias(n, _)   => fmt::Display::fmt(n, f),
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for Type {
+    // Probes and enumerations are not generated since there are no expressions
+    // of these types which could be generated for them, yet.
     fn arbitrary(g: &mut Gen) -> Self {
         let opts: [&dyn Fn(&mut Gen) -> Self; 3] = [
             &|g| Self::GroundType(Arbitrary::arbitrary(g)),
@@ -224,12 +410,79 @@ impl Arbitrary for Type {
                     .map(Self::Bundle)
                     .chain(v.to_vec().into_iter().map(|f| f.r#type().clone()));
                 Box::new(res)
-            }
+            },
+            Self::Probe(t, k) => {
+                let k = *k;
+                let res = t.shrink().map(move |t| Self::Probe(t, k)).chain(std::iter::once(t.as_ref().clone()));
+                Box::new(res)
+            },
+            Self::Enum(v) => Box::new(v.to_vec().shrink().filter(|v| !v.is_empty()).map(|v| Self::Enum(v.into()))),
+            Self::Alias(_, t) => Box::new(std::iter::once(t.as_ref().clone())),
         }
     }
 }
 
 
+/// Step in an access path into a value
+///
+/// A sequence of accesses corresponds to a chain of sub-field, sub-index and
+/// sub-access expressions. See [Type::at_access_path].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    /// Field of a bundle, i.e. a sub-field
+    Field(Arc<str>),
+    /// Element of a vector at a constant index, i.e. a sub-index
+    Index(super::VecWidth),
+    /// Element of a vector at a dynamic index, i.e. a sub-access
+    Dynamic,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(name)   => write!(f, ".{}", name),
+            Self::Index(index)  => write!(f, "[{}]", index),
+            Self::Dynamic       => write!(f, "[_]"),
+        }
+    }
+}
+
+
+/// Kind of a [Type::Probe]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProbeKind {
+    /// A read-only probe, i.e. `Probe`
+    Read,
+    /// A probe which may also be used for forcing values, i.e. `RWProbe`
+    ReadWrite,
+}
+
+impl ProbeKind {
+    /// Retrieve the keyword associated with the probe kind
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Read      => "Probe",
+            Self::ReadWrite => "RWProbe",
+        }
+    }
+
+    /// Retrieve the operator creating a probe of this kind
+    pub fn operator(&self) -> &'static str {
+        match self {
+            Self::Read      => "probe",
+            Self::ReadWrite => "rwprobe",
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for ProbeKind {
+    fn arbitrary(g: &mut Gen) -> Self {
+        *g.choose(&[Self::Read, Self::ReadWrite]).unwrap()
+    }
+}
+
+
 /// A field in a bundle
 #[derive(Clone, PartialEq, Debug)]
 pub struct BundleField {
@@ -320,7 +573,7 @@ impl fmt::Display for BundleField {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 impl Arbitrary for BundleField {
     fn arbitrary(g: &mut Gen) -> Self {
         use crate::tests::Identifier;
@@ -336,10 +589,76 @@ impl Arbitrary for BundleField {
 }
 
 
+/// A variant of an enumeration type
+///
+/// A variant may carry data of some passive type. The data of variants
+/// without an explicit type is of type `UInt<0>`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnumVariant {
+    name: Arc<str>,
+    data: Option<Type>,
+}
+
+impl EnumVariant {
+    /// Create a new variant without data
+    pub fn new(name: impl Into<Arc<str>>) -> Self {
+        Self {name: name.into(), data: None}
+    }
+
+    /// Set the type of the data carried by the variant
+    pub fn with_data(self, data: impl Into<Type>) -> Self {
+        Self {data: Some(data.into()), ..self}
+    }
+
+    /// Retrieve the type of the data carried by the variant, if any
+    pub fn data(&self) -> Option<&Type> {
+        self.data.as_ref()
+    }
+}
+
+impl Named for EnumVariant {
+    type Name = Arc<str>;
+
+    fn name(&self) -> &Self::Name {
+        &self.name
+    }
+}
+
+impl fmt::Display for EnumVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        self.data.as_ref().map(|t| write!(f, ": {}", t)).transpose().map(|_| ())
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Arbitrary for EnumVariant {
+    fn arbitrary(g: &mut Gen) -> Self {
+        use crate::tests::Identifier;
+
+        let res = Self::new(Identifier::arbitrary(g));
+        if bool::arbitrary(g) {
+            res.with_data(Type::arbitrary(g))
+        } else {
+            res
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let n = self.name.clone();
+        let res = self
+            .data
+            .shrink()
+            .map(move |d| Self {name: n.clone(), data: d});
+        Box::new(res)
+    }
+}
+
+
 /// Generate a hashmap containing [BundleField]s, mapped to by their name
 ///
 /// Naturally, the [BundleField]s are guranteed to have unique names.
-#[cfg(test)]
+#[cfg(any(test, feature = "test-util"))]
 pub fn bundle_fields(max_size: usize, g: &mut Gen) -> std::collections::HashMap<Arc<str>, BundleField> {
     if max_size == 0 {
         Default::default()